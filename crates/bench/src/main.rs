@@ -0,0 +1,283 @@
+//! Load-test harness for Isola templates.
+//!
+//! Builds a template once, then runs `--concurrency` workers that each
+//! instantiate `--iterations` sandboxes, optionally evaluating a script and
+//! calling a guest function in every sandbox. Latency percentiles and memory
+//! usage are written as a JSON report to stdout or `--output`.
+
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use isola::{
+    host::{Host, OutputTarget},
+    sandbox::{Arg, DirPerms, FilePerms, SandboxOptions, SandboxTemplate},
+    value::Value,
+};
+
+use crate::report::{Environment, MemoryReport, PhaseSamples, Phases, Report, peak_rss_bytes};
+
+mod report;
+
+const USAGE: &str = "\
+Usage: isola-bench --wasm <PATH> [OPTIONS]
+
+Options:
+  --wasm <PATH>          Runtime component to benchmark (required)
+  --mount <HOST:GUEST>   Read-only mount, repeatable (e.g. the Python stdlib at /lib)
+  --cache <DIR>          Compiled artifact cache directory
+  --prelude <CODE>       Prelude evaluated at template build time
+  --max-memory <BYTES>   Per-sandbox memory limit
+  --concurrency <N>      Concurrent workers [default: 1]
+  --iterations <N>       Sandboxes instantiated per worker [default: 10]
+  --script <CODE>        Script evaluated in every sandbox
+  --call <FUNCTION>      Guest function called in every sandbox after --script
+  --arg <JSON>           Positional argument for --call, repeatable
+  --output <PATH>        Write the JSON report to a file instead of stdout
+";
+
+#[derive(Debug)]
+struct Config {
+    wasm: PathBuf,
+    mounts: Vec<(PathBuf, String)>,
+    cache: Option<PathBuf>,
+    prelude: Option<String>,
+    max_memory: Option<usize>,
+    concurrency: usize,
+    iterations: usize,
+    script: Option<String>,
+    call: Option<String>,
+    args: Vec<String>,
+    output: Option<PathBuf>,
+}
+
+impl Config {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let mut wasm = None;
+        let mut config = Self {
+            wasm: PathBuf::new(),
+            mounts: Vec::new(),
+            cache: None,
+            prelude: None,
+            max_memory: None,
+            concurrency: 1,
+            iterations: 10,
+            script: None,
+            call: None,
+            args: Vec::new(),
+            output: None,
+        };
+        while let Some(flag) = args.next() {
+            if flag == "-h" || flag == "--help" {
+                return Ok(None);
+            }
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {flag}"))?;
+            match flag.as_str() {
+                "--wasm" => wasm = Some(PathBuf::from(value)),
+                "--mount" => {
+                    let (host, guest) = value
+                        .split_once(':')
+                        .with_context(|| format!("expected HOST:GUEST, got '{value}'"))?;
+                    config.mounts.push((PathBuf::from(host), guest.to_string()));
+                }
+                "--cache" => config.cache = Some(PathBuf::from(value)),
+                "--prelude" => config.prelude = Some(value),
+                "--max-memory" => config.max_memory = Some(value.parse()?),
+                "--concurrency" => config.concurrency = value.parse()?,
+                "--iterations" => config.iterations = value.parse()?,
+                "--script" => config.script = Some(value),
+                "--call" => config.call = Some(value),
+                "--arg" => config.args.push(value),
+                "--output" => config.output = Some(PathBuf::from(value)),
+                _ => bail!("unknown option {flag}"),
+            }
+        }
+        config.wasm = wasm.context("--wasm is required")?;
+        if config.concurrency == 0 || config.iterations == 0 {
+            bail!("--concurrency and --iterations must be at least 1");
+        }
+        Ok(Some(config))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BenchHost;
+
+impl Host for BenchHost {}
+
+#[derive(Default)]
+struct WorkerSamples {
+    instantiate: PhaseSamples,
+    eval: PhaseSamples,
+    call: PhaseSamples,
+    memory: Vec<usize>,
+}
+
+async fn run_worker(template: Arc<SandboxTemplate>, config: Arc<Config>) -> Result<WorkerSamples> {
+    let mut samples = WorkerSamples::default();
+    for _ in 0..config.iterations {
+        let start = Instant::now();
+        let sandbox = template
+            .instantiate(BenchHost, SandboxOptions::default())
+            .await;
+        samples.instantiate.record(start.elapsed(), &sandbox);
+        let Ok(mut sandbox) = sandbox else {
+            continue;
+        };
+
+        if let Some(script) = &config.script {
+            let start = Instant::now();
+            let result = sandbox.eval_script(script, OutputTarget::discard()).await;
+            samples.eval.record(start.elapsed(), &result);
+        }
+        if let Some(function) = &config.call {
+            let args = config
+                .args
+                .iter()
+                .map(|json| Value::from_json(json).map(Arg::Positional))
+                .collect::<Result<Vec<_>, _>>()?;
+            let start = Instant::now();
+            let result = sandbox
+                .call_with_sink(function, args, OutputTarget::discard())
+                .await;
+            samples.call.record(start.elapsed(), &result);
+        }
+        samples.memory.push(sandbox.memory_usage());
+    }
+    Ok(samples)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e3
+}
+
+async fn run(config: Config) -> Result<Report> {
+    let mut builder = SandboxTemplate::builder()
+        .cache(config.cache.clone())
+        .prelude(config.prelude.clone());
+    for (host, guest) in &config.mounts {
+        builder = builder.mount(host, guest, DirPerms::READ, FilePerms::READ);
+    }
+    if let Some(max_memory) = config.max_memory {
+        builder = builder.max_memory(max_memory);
+    }
+
+    let start = Instant::now();
+    let template = Arc::new(
+        builder
+            .build(&config.wasm)
+            .await
+            .with_context(|| format!("failed to build template from {}", config.wasm.display()))?,
+    );
+    let build_ms = millis(start.elapsed());
+
+    let config = Arc::new(config);
+    let start = Instant::now();
+    let workers: Vec<_> = (0..config.concurrency)
+        .map(|_| tokio::spawn(run_worker(Arc::clone(&template), Arc::clone(&config))))
+        .collect();
+    let mut merged = WorkerSamples::default();
+    for worker in workers {
+        let samples = worker.await??;
+        merged.instantiate.merge(samples.instantiate);
+        merged.eval.merge(samples.eval);
+        merged.call.merge(samples.call);
+        merged.memory.extend(samples.memory);
+    }
+    let wall = start.elapsed();
+
+    merged.memory.sort_unstable();
+    let sandboxes = config.concurrency * config.iterations;
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "sandbox counts are far below f64 precision limits"
+    )]
+    let throughput_per_sec = sandboxes as f64 / wall.as_secs_f64();
+    Ok(Report {
+        runtime: config.wasm.display().to_string(),
+        concurrency: config.concurrency,
+        iterations: config.iterations,
+        build_ms,
+        wall_ms: millis(wall),
+        throughput_per_sec,
+        phases: Phases {
+            instantiate: merged.instantiate.into_stats(),
+            eval: config.script.as_ref().map(|_| merged.eval.into_stats()),
+            call: config.call.as_ref().map(|_| merged.call.into_stats()),
+        },
+        memory: MemoryReport {
+            peak_rss: peak_rss_bytes(),
+            max_sandbox: merged.memory.last().copied().unwrap_or(0),
+            p50_sandbox: merged
+                .memory
+                .get(merged.memory.len().saturating_sub(1) / 2)
+                .copied()
+                .unwrap_or(0),
+        },
+        environment: Environment::current(),
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let Some(config) = Config::parse(std::env::args().skip(1))? else {
+        print!("{USAGE}");
+        return Ok(());
+    };
+    let output = config.output.clone();
+    let report = serde_json::to_string_pretty(&run(config).await?)?;
+    match output {
+        Some(path) => tokio::fs::write(&path, report + "\n")
+            .await
+            .with_context(|| format!("failed to write report to {}", path.display()))?,
+        None => println!("{report}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Config>> {
+        Config::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn parses_repeatable_options() {
+        let config = parse(&[
+            "--wasm",
+            "python.wasm",
+            "--mount",
+            "/usr/lib/python:/lib",
+            "--concurrency",
+            "4",
+            "--call",
+            "main",
+            "--arg",
+            "1",
+            "--arg",
+            "\"x\"",
+        ])
+        .expect("valid options")
+        .expect("not help");
+        assert_eq!(config.wasm, PathBuf::from("python.wasm"));
+        assert_eq!(
+            config.mounts,
+            [(PathBuf::from("/usr/lib/python"), "/lib".to_string())]
+        );
+        assert_eq!(config.concurrency, 4);
+        assert_eq!(config.iterations, 10);
+        assert_eq!(config.args, ["1", "\"x\""]);
+
+        assert!(parse(&["--help"]).expect("help").is_none());
+        assert!(parse(&["--concurrency", "2"]).is_err());
+        assert!(parse(&["--wasm", "a.wasm", "--iterations", "0"]).is_err());
+        assert!(parse(&["--wasm"]).is_err());
+    }
+}
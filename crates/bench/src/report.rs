@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Latency distribution of one measured phase, in microseconds.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub errors: usize,
+    pub mean_us: f64,
+    pub min_us: u64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(mut samples: Vec<Duration>, errors: usize) -> Self {
+        if samples.is_empty() {
+            return Self {
+                errors,
+                ..Self::default()
+            };
+        }
+        samples.sort_unstable();
+        let micros = |d: Duration| u64::try_from(d.as_micros()).unwrap_or(u64::MAX);
+        let percentile = |p: usize| micros(samples[(samples.len() - 1) * p / 100]);
+        let total: Duration = samples.iter().sum();
+        #[expect(
+            clippy::cast_precision_loss,
+            reason = "sample counts are far below f64 precision limits"
+        )]
+        let mean_us = total.as_secs_f64() * 1e6 / samples.len() as f64;
+        Self {
+            count: samples.len(),
+            errors,
+            mean_us,
+            min_us: micros(samples[0]),
+            p50_us: percentile(50),
+            p90_us: percentile(90),
+            p99_us: percentile(99),
+            max_us: micros(samples[samples.len() - 1]),
+        }
+    }
+}
+
+/// Samples collected for one phase across all workers.
+#[derive(Debug, Default)]
+pub struct PhaseSamples {
+    pub samples: Vec<Duration>,
+    pub errors: usize,
+}
+
+impl PhaseSamples {
+    pub fn record<T, E>(&mut self, elapsed: Duration, result: &Result<T, E>) {
+        if result.is_ok() {
+            self.samples.push(elapsed);
+        } else {
+            self.errors += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        self.samples.extend(other.samples);
+        self.errors += other.errors;
+    }
+
+    pub fn into_stats(self) -> LatencyStats {
+        LatencyStats::from_samples(self.samples, self.errors)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Phases {
+    pub instantiate: LatencyStats,
+    pub eval: Option<LatencyStats>,
+    pub call: Option<LatencyStats>,
+}
+
+/// Memory measurements, in bytes.
+#[derive(Debug, Serialize)]
+pub struct MemoryReport {
+    /// Peak resident set size of the benchmark process, when the platform
+    /// exposes it.
+    pub peak_rss: Option<u64>,
+    /// Largest guest linear memory observed in a sandbox.
+    pub max_sandbox: usize,
+    /// Median guest linear memory across sandboxes.
+    pub p50_sandbox: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Environment {
+    pub isola_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub cpus: usize,
+}
+
+impl Environment {
+    pub fn current() -> Self {
+        Self {
+            isola_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            cpus: std::thread::available_parallelism().map_or(1, std::num::NonZero::get),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub runtime: String,
+    pub concurrency: usize,
+    pub iterations: usize,
+    pub build_ms: f64,
+    pub wall_ms: f64,
+    pub throughput_per_sec: f64,
+    pub phases: Phases,
+    pub memory: MemoryReport,
+    pub environment: Environment,
+}
+
+/// Read the peak resident set size from `/proc/self/status`.
+pub fn peak_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: u64 = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_report_percentiles_and_errors() {
+        let samples = (1..=100).map(Duration::from_micros).collect();
+        let stats = LatencyStats::from_samples(samples, 3);
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.errors, 3);
+        assert_eq!(stats.min_us, 1);
+        assert_eq!(stats.p50_us, 50);
+        assert_eq!(stats.p90_us, 90);
+        assert_eq!(stats.p99_us, 99);
+        assert_eq!(stats.max_us, 100);
+        assert!((stats.mean_us - 50.5).abs() < 1e-9);
+
+        assert_eq!(
+            LatencyStats::from_samples(Vec::new(), 2),
+            LatencyStats {
+                errors: 2,
+                ..LatencyStats::default()
+            }
+        );
+    }
+}
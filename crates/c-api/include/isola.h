@@ -15,6 +15,7 @@ typedef enum isola_error_code {
   ISOLA_ERROR_CODE_INTERNAL = 2,
   ISOLA_ERROR_CODE_STREAM_FULL = 3,
   ISOLA_ERROR_CODE_STREAM_CLOSED = 4,
+  ISOLA_ERROR_CODE_INVALID_HANDLE = 5,
 } isola_error_code;
 
 typedef enum isola_callback_event {
@@ -26,32 +27,15 @@ typedef enum isola_callback_event {
   ISOLA_CALLBACK_EVENT_LOG = 5,
 } isola_callback_event;
 
-typedef struct isola_context_handle isola_context_handle;
-
 /**
- * Opaque handle for an in-flight hostcall response.
- *
- * The C side delivers the result by calling exactly one of:
- * - `isola_hostcall_response_resolve` — on success
- * - `isola_hostcall_response_reject` — on failure
- *
- * Either call consumes and frees the handle.
+ * Opaque handle for an isola context. Zero is never a valid handle.
  */
-typedef struct isola_hostcall_response isola_hostcall_response;
+typedef uint64_t isola_context_handle;
 
 /**
- * Opaque handle for an in-flight HTTP response.
- *
- * The C side drives the response through three phases:
- * 1. `isola_http_response_body_start` — deliver status and headers
- * 2. `isola_http_response_body_push` — deliver body chunks (zero or more)
- * 3. `isola_http_response_body_close` — signal EOF and free the handle
+ * Opaque handle for a sandbox. Zero is never a valid handle.
  */
-typedef struct isola_http_response_body isola_http_response_body;
-
-typedef struct isola_sandbox_handle isola_sandbox_handle;
-
-typedef struct isola_stream_handle isola_stream_handle;
+typedef uint64_t isola_sandbox_handle;
 
 /**
  * C-compatible HTTP header.
@@ -78,6 +62,28 @@ typedef struct isola_http_request {
   size_t body_len;
 } isola_http_request;
 
+/**
+ * Opaque handle for an in-flight HTTP response.
+ *
+ * The C side drives the response through three phases:
+ * 1. `isola_http_response_body_start` — deliver status and headers
+ * 2. `isola_http_response_body_push` — deliver body chunks (zero or more)
+ * 3. `isola_http_response_body_close` — signal EOF and release the handle
+ */
+typedef uint64_t isola_http_response_body;
+
+/**
+ * Opaque handle for an in-flight hostcall response.
+ *
+ * The C side delivers the result by calling exactly one of:
+ * - `isola_hostcall_response_resolve` — on success
+ * - `isola_hostcall_response_reject` — on failure
+ * - `isola_hostcall_response_cancel` — when the work is abandoned
+ *
+ * Each call releases the handle.
+ */
+typedef uint64_t isola_hostcall_response;
+
 /**
  * Unified vtable for sandbox event handling and optional HTTP support.
  *
@@ -107,10 +113,10 @@ typedef struct isola_sandbox_handler_vtable {
    * 2. `isola_http_response_body_push(response_body, data, len)` — for each
    *    body chunk.
    * 3. `isola_http_response_body_close(response_body)` — to signal EOF and
-   *    free the handle.
+   *    release the handle.
    */
   void (*http_request)(const struct isola_http_request *request,
-                       struct isola_http_response_body *response_body,
+                       isola_http_response_body response_body,
                        void *user_data);
   /**
    * Called to handle a hostcall from guest code.
@@ -121,13 +127,13 @@ typedef struct isola_sandbox_handler_vtable {
    * - `isola_hostcall_response_resolve(response, data, len)` — on success
    * - `isola_hostcall_response_reject(response, error_message)` — on failure
    *
-   * Exactly one of resolve/reject/cancel must be called. The call frees the
-   * handle.
+   * Exactly one of resolve/reject/cancel must be called. The call releases
+   * the handle.
    */
   void (*hostcall)(const char *call_type,
                    const uint8_t *payload,
                    size_t payload_len,
-                   struct isola_hostcall_response *response,
+                   isola_hostcall_response response,
                    void *user_data);
 } isola_sandbox_handler_vtable;
 
@@ -157,9 +163,14 @@ typedef struct isola_encoded_value {
   struct isola_blob data;
 } isola_encoded_value;
 
+/**
+ * Opaque handle for a streaming argument. Zero is never a valid handle.
+ */
+typedef uint64_t isola_stream_handle;
+
 typedef union isola_argument_value {
   struct isola_encoded_value value;
-  const struct isola_stream_handle *stream;
+  isola_stream_handle stream;
 } isola_argument_value;
 
 typedef struct isola_argument {
@@ -185,21 +196,19 @@ extern "C" {
  *
  * # Safety
  *
- * `out_context` must point to writable handle storage. It is set to `NULL`
- * before context creation, so it remains `NULL` if creation fails.
+ * `out_context` must point to writable handle storage. It is set to `0`
+ * before context creation, so it remains `0` if creation fails.
  */
-enum isola_error_code isola_context_create(int nr_thread,
-                                           struct isola_context_handle **out_context);
+enum isola_error_code isola_context_create(int nr_thread, isola_context_handle *out_context);
 
 /**
  * Initializes the isola context with the specified path.
  *
  * # Safety
  *
- * `ctx` must be a live context handle and `path` must be a valid,
- * null-terminated C string. `NULL` arguments are rejected.
+ * `path` must be a valid, null-terminated C string. `NULL` is rejected.
  */
-enum isola_error_code isola_context_initialize(struct isola_context_handle *ctx, const char *path);
+enum isola_error_code isola_context_initialize(isola_context_handle ctx, const char *path);
 
 /**
  * Sets a configuration value for the isola context.
@@ -226,38 +235,69 @@ enum isola_error_code isola_context_initialize(struct isola_context_handle *ctx,
  * The caller must ensure that both `key` and `value` are valid,
  * null-terminated C strings.
  */
-enum isola_error_code isola_context_config_set(struct isola_context_handle *ctx,
+enum isola_error_code isola_context_config_set(isola_context_handle ctx,
                                                const char *key,
                                                const char *value);
 
 /**
- * Destroy a context handle. Passing `NULL` is allowed and has no effect.
+ * Applies a JSON config patch to the isola context.
+ *
+ * Must be called **before** `isola_context_initialize`. The patch is a JSON
+ * object using the same schema as the python-sdk `configure` call; omitted
+ * keys are left unchanged and unknown keys are rejected with
+ * `ISOLA_ERROR_CODE_INVALID_ARGUMENT`.
+ *
+ * # Supported keys
+ *
+ * | Key               | Value                                                       |
+ * |-------------------|-------------------------------------------------------------|
+ * | `cache_dir`       | Cache directory path, or `null` to disable caching.         |
+ * | `max_memory`      | Byte count, or `null` for the default.                      |
+ * | `prelude`         | Guest prelude source, or `null` for the default.            |
+ * | `runtime_lib_dir` | Host directory mounted at `/lib`, or `null` for default. |
+ * | `mounts`          | Array of `{"host","guest","dir_perms","file_perms"}`.       |
+ * | `env`             | Object mapping variable names to values.                    |
+ * | `timeout_ms`      | Default call deadline in milliseconds, or `null` for none.  |
+ *
+ * `mounts` and `env` replace every entry configured so far. Mount
+ * permissions are `"read"` (the default), `"write"`, or `"read-write"`.
+ * `timeout_ms` applies to `isola_sandbox_load_script` and
+ * `isola_sandbox_run` calls that pass a timeout of `0`, and is inherited by
+ * sandboxes created afterwards.
  *
  * # Safety
- * A non-`NULL` handle must have been returned by `isola_context_create` and
- * must not have been destroyed previously.
+ *
+ * `patch` must be a valid, null-terminated C string.
+ */
+enum isola_error_code isola_context_configure_json(isola_context_handle ctx, const char *patch);
+
+/**
+ * Destroy a context handle. Passing `0` is allowed and has no effect.
+ *
+ * Sandboxes created from the context keep it alive until they are
+ * destroyed. Destroying a handle twice returns
+ * `ISOLA_ERROR_CODE_INVALID_HANDLE`.
  */
-void isola_context_destroy(struct isola_context_handle *ctx);
+enum isola_error_code isola_context_destroy(isola_context_handle ctx);
 
 /**
  * Creates a new sandbox instance from the context.
  *
  * # Safety
  *
- * `ctx` must be a live context handle and `out_sandbox` must point to writable
- * handle storage. The output is set to `NULL` before creation.
+ * `out_sandbox` must point to writable handle storage. The output is set to
+ * `0` before creation.
  */
-enum isola_error_code isola_sandbox_create(const struct isola_context_handle *ctx,
-                                           struct isola_sandbox_handle **out_sandbox);
+enum isola_error_code isola_sandbox_create(isola_context_handle ctx,
+                                           isola_sandbox_handle *out_sandbox);
 
 /**
- * Destroy a sandbox handle. Passing `NULL` is allowed and has no effect.
+ * Destroy a sandbox handle. Passing `0` is allowed and has no effect.
  *
- * # Safety
- * A non-`NULL` handle must have been returned by `isola_sandbox_create` and
- * must not have been destroyed previously.
+ * A call still running on the sandbox keeps it alive until it returns.
+ * Destroying a handle twice returns `ISOLA_ERROR_CODE_INVALID_HANDLE`.
  */
-void isola_sandbox_destroy(struct isola_sandbox_handle *sandbox);
+enum isola_error_code isola_sandbox_destroy(isola_sandbox_handle sandbox);
 
 /**
  * Sets a per-sandbox configuration value, overriding context-level defaults.
@@ -283,10 +323,24 @@ void isola_sandbox_destroy(struct isola_sandbox_handle *sandbox);
  * The caller must ensure that both `key` and `value` are valid,
  * null-terminated C strings.
  */
-enum isola_error_code isola_sandbox_set_config(struct isola_sandbox_handle *sandbox,
+enum isola_error_code isola_sandbox_set_config(isola_sandbox_handle sandbox,
                                                const char *key,
                                                const char *value);
 
+/**
+ * Applies a JSON config patch to a sandbox, overriding context-level
+ * defaults.
+ *
+ * Must be called **before** `isola_sandbox_start`. Accepts the `max_memory`,
+ * `mounts`, `env`, and `timeout_ms` keys of the `isola_context_configure_json`
+ * schema, with the same replacement semantics.
+ *
+ * # Safety
+ *
+ * `patch` must be a valid, null-terminated C string.
+ */
+enum isola_error_code isola_sandbox_configure_json(isola_sandbox_handle sandbox, const char *patch);
+
 /**
  * Sets the handler vtable on a sandbox.
  *
@@ -297,33 +351,35 @@ enum isola_error_code isola_sandbox_set_config(struct isola_sandbox_handle *sand
  *
  * `vtable` must point to a valid `SandboxHandlerVtable`.
  */
-enum isola_error_code isola_sandbox_set_handler(struct isola_sandbox_handle *sandbox,
+enum isola_error_code isola_sandbox_set_handler(isola_sandbox_handle sandbox,
                                                 const struct isola_sandbox_handler_vtable *vtable,
                                                 void *user_data);
 
 /**
  * Start a configured sandbox.
- *
- * # Safety
- * `sandbox` must be a live handle returned by `isola_sandbox_create`. `NULL`
- * is rejected.
  */
-enum isola_error_code isola_sandbox_start(struct isola_sandbox_handle *sandbox);
+enum isola_error_code isola_sandbox_start(isola_sandbox_handle sandbox);
 
 /**
  * Loads a script into the sandbox.
  *
+ * A `timeout_in_ms` of `0` uses the configured `timeout_ms`; without one
+ * the script runs without a deadline.
+ *
  * # Safety
  *
  * The caller must ensure that `input` is a valid, null-terminated C string.
  */
-enum isola_error_code isola_sandbox_load_script(struct isola_sandbox_handle *sandbox,
+enum isola_error_code isola_sandbox_load_script(isola_sandbox_handle sandbox,
                                                 const char *input,
                                                 uint64_t timeout_in_ms);
 
 /**
  * Runs a function in the sandbox with the specified arguments.
  *
+ * A `timeout_in_ms` of `0` uses the configured `timeout_ms`; without one
+ * the call runs without a deadline.
+ *
  * # Safety
  *
  * The caller must ensure that:
@@ -332,7 +388,7 @@ enum isola_error_code isola_sandbox_load_script(struct isola_sandbox_handle *san
  *   `args_len`
  * - Each `Argument` in the array has valid pointers and data
  */
-enum isola_error_code isola_sandbox_run(struct isola_sandbox_handle *sandbox,
+enum isola_error_code isola_sandbox_run(isola_sandbox_handle sandbox,
                                         const char *func,
                                         const struct isola_argument *args,
                                         size_t args_len,
@@ -344,10 +400,10 @@ enum isola_error_code isola_sandbox_run(struct isola_sandbox_handle *sandbox,
  * # Safety
  *
  * `out_stream` must point to writable handle storage. The output is set to
- * `NULL` before creation. Unknown `format` values are rejected.
+ * `0` before creation. Unknown `format` values are rejected.
  */
 enum isola_error_code isola_stream_create(isola_argument_type format,
-                                          struct isola_stream_handle **out_stream);
+                                          isola_stream_handle *out_stream);
 
 /**
  * Pushes data to a stream.
@@ -361,22 +417,20 @@ enum isola_error_code isola_stream_create(isola_argument_type format,
  * * `blocking` - If non-zero, blocks until space is available in the channel.
  *   If zero, returns immediately with an error if the channel is full.
  */
-enum isola_error_code isola_stream_push(const struct isola_stream_handle *stream,
+enum isola_error_code isola_stream_push(isola_stream_handle stream,
                                         const uint8_t *data,
                                         size_t len,
                                         int blocking);
 
 /**
- * Signals the end of a stream and frees the handle.
- *
- * After calling this function, no more data can be pushed to the stream
- * and the handle is invalid.
+ * Signals the end of a stream.
  *
- * # Safety
- * `stream` must be a live handle returned by `isola_stream_create` that has
- * not already been ended. `NULL` is rejected.
+ * After calling this function, no more data can be pushed to the stream.
+ * A stream ended before any `isola_sandbox_run` took it can still be passed
+ * to one; the handle is released once it has been both ended and taken.
+ * Ending a stream twice returns `ISOLA_ERROR_CODE_INVALID_HANDLE`.
  */
-enum isola_error_code isola_stream_end(struct isola_stream_handle *stream);
+enum isola_error_code isola_stream_end(isola_stream_handle stream);
 
 /**
  * Delivers the HTTP status code and response headers.
@@ -389,11 +443,10 @@ enum isola_error_code isola_stream_end(struct isola_stream_handle *stream);
  *
  * # Safety
  *
- * - `body` must be a live handle obtained from an `http_request` callback.
- * - `headers` must point to a valid array of `headers_len` elements (may be
- *   NULL if `headers_len` is 0).
+ * `headers` must point to a valid array of `headers_len` elements (may be
+ * NULL if `headers_len` is 0).
  */
-enum isola_error_code isola_http_response_body_start(const struct isola_http_response_body *body,
+enum isola_error_code isola_http_response_body_start(isola_http_response_body body,
                                                      uint16_t status,
                                                      const struct isola_http_header *headers,
                                                      size_t headers_len);
@@ -405,29 +458,25 @@ enum isola_error_code isola_http_response_body_start(const struct isola_http_res
  *
  * # Safety
  *
- * - `body` must be a live handle obtained from an `http_request` callback.
- * - `data` must point to a valid buffer of `len` bytes.
+ * `data` must point to a valid buffer of `len` bytes.
  */
-enum isola_error_code isola_http_response_body_push(const struct isola_http_response_body *body,
+enum isola_error_code isola_http_response_body_push(isola_http_response_body body,
                                                     const uint8_t *data,
                                                     size_t len);
 
 /**
- * Signals EOF and frees the response body handle.
+ * Signals EOF and releases the response body handle.
  *
  * Must be called exactly once per handle, even if no data was pushed.
- *
- * # Safety
- *
- * `body` must be `NULL` or a live handle obtained from an `http_request`
- * callback. After this call a non-`NULL` pointer is invalid.
+ * Passing `0` has no effect; closing a handle twice returns
+ * `ISOLA_ERROR_CODE_INVALID_HANDLE`.
  */
-void isola_http_response_body_close(struct isola_http_response_body *body);
+enum isola_error_code isola_http_response_body_close(isola_http_response_body body);
 
 /**
  * Resolves a hostcall with a JSON result value, consuming the handle.
  *
- * Must be called exactly once per handle. After this call the pointer is
+ * Must be called exactly once per handle. After this call the handle is
  * invalid. Use `isola_hostcall_response_reject` to deliver an error instead.
  *
  * If the data is not valid JSON, the handle is **not** consumed and the
@@ -436,39 +485,32 @@ void isola_http_response_body_close(struct isola_http_response_body *body);
  *
  * # Safety
  *
- * - `response` must be a live handle obtained from a `hostcall` callback.
- * - `data` must point to a valid JSON buffer of `len` bytes.
+ * `data` must point to a valid JSON buffer of `len` bytes.
  */
-enum isola_error_code isola_hostcall_response_resolve(struct isola_hostcall_response *response,
+enum isola_error_code isola_hostcall_response_resolve(isola_hostcall_response response,
                                                       const uint8_t *data,
                                                       size_t len);
 
 /**
  * Rejects a hostcall with an error message, consuming the handle.
  *
- * Must be called exactly once per handle. After this call the pointer is
+ * Must be called exactly once per handle. After this call the handle is
  * invalid. Use `isola_hostcall_response_resolve` to deliver a result instead.
  *
  * # Safety
  *
- * - `response` must be a live handle obtained from a `hostcall` callback.
- * - `error_message` must be a valid, null-terminated C string.
+ * `error_message` must be a valid, null-terminated C string.
  */
-enum isola_error_code isola_hostcall_response_reject(struct isola_hostcall_response *response,
+enum isola_error_code isola_hostcall_response_reject(isola_hostcall_response response,
                                                      const char *error_message);
 
 /**
  * Cancels a hostcall without a result, consuming the handle.
  *
  * Use this when external work is abandoned. The waiting guest call fails and
- * the response allocation is released.
- *
- * # Safety
- *
- * `response` must be `NULL` or a live handle obtained from a `hostcall`
- * callback.
+ * the handle is released. Passing `0` has no effect.
  */
-void isola_hostcall_response_cancel(struct isola_hostcall_response *response);
+enum isola_error_code isola_hostcall_response_cancel(isola_hostcall_response response);
 
 /**
  * Return the most recent error message produced on the calling thread.
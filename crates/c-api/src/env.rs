@@ -11,6 +11,30 @@ use isola::{
 };
 use tokio_stream::wrappers::ReceiverStream;
 
+use crate::handle::{HandleKind, Registry};
+
+/// Opaque handle for an in-flight HTTP response.
+///
+/// The C side drives the response through three phases:
+/// 1. `isola_http_response_body_start` — deliver status and headers
+/// 2. `isola_http_response_body_push` — deliver body chunks (zero or more)
+/// 3. `isola_http_response_body_close` — signal EOF and release the handle
+pub type HttpResponseHandle = u64;
+
+/// Opaque handle for an in-flight hostcall response.
+///
+/// The C side delivers the result by calling exactly one of:
+/// - `isola_hostcall_response_resolve` — on success
+/// - `isola_hostcall_response_reject` — on failure
+/// - `isola_hostcall_response_cancel` — when the work is abandoned
+///
+/// Each call releases the handle.
+pub type HostcallResponseHandle = u64;
+
+pub static HTTP_RESPONSES: Registry<HttpResponseBody> = Registry::new(HandleKind::HttpResponse);
+pub static HOSTCALL_RESPONSES: Registry<HostcallResponse> =
+    Registry::new(HandleKind::HostcallResponse);
+
 /// C-compatible HTTP header.
 #[repr(C)]
 pub struct HttpHeader {
@@ -40,12 +64,7 @@ pub struct HttpResponseHead {
     pub headers: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
-/// Opaque handle for an in-flight HTTP response.
-///
-/// The C side drives the response through three phases:
-/// 1. `isola_http_response_body_start` — deliver status and headers
-/// 2. `isola_http_response_body_push` — deliver body chunks (zero or more)
-/// 3. `isola_http_response_body_close` — signal EOF and free the handle
+/// Channels behind an [`HttpResponseHandle`].
 pub struct HttpResponseBody {
     head: Mutex<Option<tokio::sync::oneshot::Sender<HttpResponseHead>>>,
     body: tokio::sync::mpsc::Sender<Result<Frame<Bytes>, BoxError>>,
@@ -76,33 +95,35 @@ impl HttpResponseBody {
     }
 }
 
-/// Opaque handle for an in-flight hostcall response.
-///
-/// The C side delivers the result by calling exactly one of:
-/// - `isola_hostcall_response_resolve` — on success
-/// - `isola_hostcall_response_reject` — on failure
-///
-/// Either call consumes and frees the handle.
+/// Result channel behind a [`HostcallResponseHandle`].
 pub struct HostcallResponse {
     sender: Mutex<Option<tokio::sync::oneshot::Sender<Result<Value, BoxError>>>>,
 }
 
 impl HostcallResponse {
+    pub fn new(sender: tokio::sync::oneshot::Sender<Result<Value, BoxError>>) -> Self {
+        Self {
+            sender: Mutex::new(Some(sender)),
+        }
+    }
+
     /// Resolve the hostcall with a successful value.
-    pub fn resolve(self, value: Value) -> Result<(), ()> {
+    pub fn resolve(&self, value: Value) -> Result<(), ()> {
         self.sender
-            .into_inner()
+            .lock()
             .map_err(|_| ())?
+            .take()
             .ok_or(())?
             .send(Ok(value))
             .map_err(|_| ())
     }
 
     /// Reject the hostcall with an error message.
-    pub fn reject(self, error: String) -> Result<(), ()> {
+    pub fn reject(&self, error: String) -> Result<(), ()> {
         self.sender
-            .into_inner()
+            .lock()
             .map_err(|_| ())?
+            .take()
             .ok_or(())?
             .send(Err(Box::new(std::io::Error::other(error))))
             .map_err(|_| ())
@@ -151,9 +172,7 @@ impl Host for Env {
                 Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
             })?;
 
-            let response = Box::into_raw(Box::new(HostcallResponse {
-                sender: Mutex::new(Some(tx)),
-            }));
+            let response = HOSTCALL_RESPONSES.insert(HostcallResponse::new(tx));
 
             hostcall_fn(
                 call_type_c.as_ptr(),
@@ -238,10 +257,10 @@ impl Host for Env {
                 body_len,
             };
 
-            let response_body = Box::into_raw(Box::new(HttpResponseBody {
+            let response_body = HTTP_RESPONSES.insert(HttpResponseBody {
                 head: Mutex::new(Some(head_tx)),
                 body: body_tx,
-            }));
+            });
 
             http_request_fn(&raw const c_request, response_body, handler.user_data);
         }
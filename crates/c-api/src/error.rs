@@ -36,6 +36,12 @@ pub enum Error {
     #[error("Stream is closed")]
     StreamClosed,
 
+    #[error("Invalid {0} handle")]
+    InvalidHandle(&'static str),
+
+    #[error("The {0} handle was already destroyed")]
+    ClosedHandle(&'static str),
+
     #[error("C Error")]
     C(ErrorCode, Cow<'static, CStr>),
 }
@@ -56,6 +62,7 @@ pub enum ErrorCode {
     Internal = 2,
     StreamFull = 3,
     StreamClosed = 4,
+    InvalidHandle = 5,
 }
 
 trait IntoCStr {
@@ -87,6 +94,9 @@ impl Error {
                 Self::Internal(msg) => Self::C(ErrorCode::Internal, msg.clone().into_cstr()),
                 Self::StreamFull => Self::C(ErrorCode::StreamFull, c"Stream is full".into()),
                 Self::StreamClosed => Self::C(ErrorCode::StreamClosed, c"Stream is closed".into()),
+                Self::InvalidHandle(_) | Self::ClosedHandle(_) => {
+                    Self::C(ErrorCode::InvalidHandle, self.to_string().into_cstr())
+                }
                 Self::C(..) => unreachable!(),
             };
         }
@@ -110,6 +120,7 @@ impl From<&Error> for ErrorCode {
             Error::Internal(_) => Self::Internal,
             Error::StreamFull => Self::StreamFull,
             Error::StreamClosed => Self::StreamClosed,
+            Error::InvalidHandle(_) | Error::ClosedHandle(_) => Self::InvalidHandle,
             Error::C(code, _) => *code,
         }
     }
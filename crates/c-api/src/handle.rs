@@ -0,0 +1,136 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::error::{Error, Result};
+
+/// Number of low bits of a handle holding its sequence number. The bits
+/// above them hold the [`HandleKind`], so a handle passed to a function
+/// expecting another kind of object is rejected.
+const SEQUENCE_BITS: u32 = 56;
+const SEQUENCE_MASK: u64 = (1 << SEQUENCE_BITS) - 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandleKind {
+    Context = 1,
+    Sandbox = 2,
+    Stream = 3,
+    HttpResponse = 4,
+    HostcallResponse = 5,
+}
+
+impl HandleKind {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Context => "context",
+            Self::Sandbox => "sandbox",
+            Self::Stream => "stream",
+            Self::HttpResponse => "HTTP response",
+            Self::HostcallResponse => "hostcall response",
+        }
+    }
+}
+
+/// Objects handed to C callers, keyed by opaque integer handles.
+///
+/// Handles are never reused, so a handle that was issued but is no longer
+/// registered has been destroyed. Lookups return a shared reference, which
+/// keeps the object alive while a call uses it even if another thread
+/// destroys its handle in the meantime.
+pub struct Registry<T> {
+    kind: HandleKind,
+    next: AtomicU64,
+    entries: Mutex<BTreeMap<u64, Arc<T>>>,
+}
+
+impl<T> Registry<T> {
+    pub const fn new(kind: HandleKind) -> Self {
+        Self {
+            kind,
+            next: AtomicU64::new(1),
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Register `value` and return its new handle, which is never zero.
+    pub fn insert(&self, value: T) -> u64 {
+        let sequence = self.next.fetch_add(1, Ordering::Relaxed);
+        assert!(sequence <= SEQUENCE_MASK, "handle space exhausted");
+        let handle = ((self.kind as u64) << SEQUENCE_BITS) | sequence;
+        self.entries().insert(handle, Arc::new(value));
+        handle
+    }
+
+    pub fn get(&self, handle: u64) -> Result<Arc<T>> {
+        self.entries()
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| self.missing(handle))
+    }
+
+    /// Unregister `handle`. Later uses of it fail, but the object lives on
+    /// until calls still holding it return.
+    pub fn remove(&self, handle: u64) -> Result<Arc<T>> {
+        self.entries()
+            .remove(&handle)
+            .ok_or_else(|| self.missing(handle))
+    }
+
+    fn entries(&self) -> MutexGuard<'_, BTreeMap<u64, Arc<T>>> {
+        // The map is never left half-updated, so a poisoned lock is still
+        // consistent.
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn missing(&self, handle: u64) -> Error {
+        let sequence = handle & SEQUENCE_MASK;
+        if handle >> SEQUENCE_BITS == self.kind as u64
+            && sequence != 0
+            && sequence < self.next.load(Ordering::Relaxed)
+        {
+            Error::ClosedHandle(self.kind.name())
+        } else {
+            Error::InvalidHandle(self.kind.name())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_stale_and_foreign_handles() {
+        let streams = Registry::new(HandleKind::Stream);
+        let sandboxes = Registry::<()>::new(HandleKind::Sandbox);
+
+        let handle = streams.insert("stream");
+        assert_ne!(handle, 0);
+        assert_eq!(*streams.get(handle).expect("live handle"), "stream");
+        assert!(matches!(
+            sandboxes.get(handle),
+            Err(Error::InvalidHandle("sandbox"))
+        ));
+
+        let held = streams.get(handle).expect("live handle");
+        streams.remove(handle).expect("first removal");
+        assert_eq!(*held, "stream");
+        assert!(matches!(
+            streams.remove(handle),
+            Err(Error::ClosedHandle("stream"))
+        ));
+        assert!(matches!(
+            streams.get(handle),
+            Err(Error::ClosedHandle("stream"))
+        ));
+        assert!(matches!(streams.get(0), Err(Error::InvalidHandle(_))));
+        assert!(matches!(
+            streams.get(handle + 1),
+            Err(Error::InvalidHandle(_))
+        ));
+    }
+}
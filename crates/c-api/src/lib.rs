@@ -1,25 +1,30 @@
 use std::{
+    collections::BTreeMap,
     ffi::{CStr, c_char, c_int, c_void},
     path::PathBuf,
-    sync::{Arc, OnceLock},
-    time::Duration,
+    sync::{Arc, Mutex, OnceLock, TryLockError},
+    time::{Duration, Instant},
 };
 
 use isola::{
     host::{BoxError, LogLevel, OutputEvent, OutputTarget},
-    sandbox::{Arg, DirPerms, FilePerms, Sandbox, SandboxOptions, SandboxTemplate},
+    sandbox::{
+        Arg, CallOptions, DirPerms, ErrorKind, FilePerms, Sandbox, SandboxOptions, SandboxTemplate,
+    },
     value::Value,
 };
 use serde::Deserialize;
 use tokio::runtime::{Builder, Runtime};
 
 use crate::{
-    env::Env,
+    env::{Env, HOSTCALL_RESPONSES, HTTP_RESPONSES, HostcallResponseHandle, HttpResponseHandle},
     error::{Error, ErrorCode, Result},
+    handle::{HandleKind, Registry},
 };
 
 mod env;
 mod error;
+mod handle;
 
 macro_rules! c_try {
     ($expr:expr) => {
@@ -85,11 +90,11 @@ pub struct SandboxHandlerVtable {
     /// 2. `isola_http_response_body_push(response_body, data, len)` — for each
     ///    body chunk.
     /// 3. `isola_http_response_body_close(response_body)` — to signal EOF and
-    ///    free the handle.
+    ///    release the handle.
     pub http_request: Option<
         extern "C" fn(
             request: *const crate::env::HttpRequestInfo,
-            response_body: *mut crate::env::HttpResponseBody,
+            response_body: HttpResponseHandle,
             user_data: *mut c_void,
         ),
     >,
@@ -102,14 +107,14 @@ pub struct SandboxHandlerVtable {
     /// - `isola_hostcall_response_resolve(response, data, len)` — on success
     /// - `isola_hostcall_response_reject(response, error_message)` — on failure
     ///
-    /// Exactly one of resolve/reject/cancel must be called. The call frees the
-    /// handle.
+    /// Exactly one of resolve/reject/cancel must be called. The call releases
+    /// the handle.
     pub hostcall: Option<
         extern "C" fn(
             call_type: *const c_char,
             payload: *const u8,
             payload_len: usize,
-            response: *mut crate::env::HostcallResponse,
+            response: HostcallResponseHandle,
             user_data: *mut c_void,
         ),
     >,
@@ -196,24 +201,177 @@ struct MountConfig {
     writable: bool,
 }
 
-impl MountConfig {
-    fn dir_perms(&self) -> DirPerms {
-        if self.writable {
-            DirPerms::READ | DirPerms::MUTATE
+impl From<MountConfig> for ConfiguredMount {
+    fn from(mount: MountConfig) -> Self {
+        let (dir_perms, file_perms) = if mount.writable {
+            (
+                DirPerms::READ | DirPerms::MUTATE,
+                FilePerms::READ | FilePerms::WRITE,
+            )
         } else {
-            DirPerms::READ
+            (DirPerms::READ, FilePerms::READ)
+        };
+        Self {
+            host: PathBuf::from(mount.host),
+            guest: mount.guest,
+            dir_perms,
+            file_perms,
         }
     }
+}
 
-    fn file_perms(&self) -> FilePerms {
-        if self.writable {
-            FilePerms::READ | FilePerms::WRITE
-        } else {
-            FilePerms::READ
+#[derive(Clone, Copy, Debug, Deserialize)]
+enum PermissionConfig {
+    #[serde(rename = "read")]
+    Read,
+    #[serde(rename = "write")]
+    Write,
+    #[serde(rename = "read-write", alias = "read_write", alias = "rw")]
+    ReadWrite,
+}
+
+/// Mount entry of a JSON config patch, matching the python-sdk schema:
+/// `{"host": "/h", "guest": "/g", "dir_perms": "read", "file_perms": "rw"}`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MountPatch {
+    host: String,
+    guest: String,
+    #[serde(default)]
+    dir_perms: Option<PermissionConfig>,
+    #[serde(default)]
+    file_perms: Option<PermissionConfig>,
+}
+
+impl TryFrom<MountPatch> for ConfiguredMount {
+    type Error = Error;
+
+    fn try_from(mount: MountPatch) -> Result<Self> {
+        if mount.host.is_empty() {
+            return Err(Error::InvalidArgument("mount host path must not be empty"));
         }
+        if mount.guest.is_empty() {
+            return Err(Error::InvalidArgument("mount guest path must not be empty"));
+        }
+        let dir_perms = match mount.dir_perms.unwrap_or(PermissionConfig::Read) {
+            PermissionConfig::Read => DirPerms::READ,
+            PermissionConfig::Write => DirPerms::MUTATE,
+            PermissionConfig::ReadWrite => DirPerms::READ | DirPerms::MUTATE,
+        };
+        let file_perms = match mount.file_perms.unwrap_or(PermissionConfig::Read) {
+            PermissionConfig::Read => FilePerms::READ,
+            PermissionConfig::Write => FilePerms::WRITE,
+            PermissionConfig::ReadWrite => FilePerms::READ | FilePerms::WRITE,
+        };
+        Ok(Self {
+            host: PathBuf::from(mount.host),
+            guest: mount.guest,
+            dir_perms,
+            file_perms,
+        })
     }
 }
 
+fn parse_mounts(mounts: Vec<MountPatch>) -> Result<Vec<ConfiguredMount>> {
+    mounts.into_iter().map(ConfiguredMount::try_from).collect()
+}
+
+fn parse_max_memory(max_memory: Option<u64>) -> Result<Option<usize>> {
+    max_memory
+        .map(|value| {
+            value
+                .try_into()
+                .map_err(|_| Error::InvalidArgument("max_memory exceeds usize"))
+        })
+        .transpose()
+}
+
+fn parse_timeout(timeout_ms: Option<u64>) -> Result<Option<Duration>> {
+    match timeout_ms {
+        Some(0) => Err(Error::InvalidArgument("timeout_ms must be positive")),
+        timeout_ms => Ok(timeout_ms.map(Duration::from_millis)),
+    }
+}
+
+/// Deadline for a call given `timeout_in_ms`, where `0` falls back to the
+/// configured `timeout_ms` and, without one, to no deadline.
+const fn call_timeout(timeout_in_ms: u64, default: Option<Duration>) -> Option<Duration> {
+    match timeout_in_ms {
+        0 => default,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Deserialize a present field, keeping an explicit `null` as `Some(None)` so
+/// it can be told apart from an omitted one.
+#[expect(clippy::option_option, reason = "JSON patch needs tri-state fields")]
+fn nullable<'de, T, D>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+fn parse_json_patch<T: serde::de::DeserializeOwned>(patch: &CStr) -> Result<T> {
+    let patch = patch
+        .to_str()
+        .map_err(|_| Error::InvalidArgument("Invalid UTF-8 in config patch"))?;
+    serde_json::from_str(patch).map_err(|_| Error::InvalidArgument("Invalid JSON config patch"))
+}
+
+/// JSON config patch accepted by `isola_context_configure_json`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[expect(clippy::option_option, reason = "JSON patch needs tri-state fields")]
+struct ContextConfigPatch {
+    #[serde(default, deserialize_with = "nullable")]
+    cache_dir: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    max_memory: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    prelude: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    runtime_lib_dir: Option<Option<String>>,
+    #[serde(default)]
+    mounts: Option<Vec<MountPatch>>,
+    #[serde(default)]
+    env: Option<BTreeMap<String, String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    timeout_ms: Option<Option<u64>>,
+}
+
+/// JSON config patch accepted by `isola_sandbox_configure_json`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[expect(clippy::option_option, reason = "JSON patch needs tri-state fields")]
+struct SandboxConfigPatch {
+    #[serde(default, deserialize_with = "nullable")]
+    max_memory: Option<Option<u64>>,
+    #[serde(default)]
+    mounts: Option<Vec<MountPatch>>,
+    #[serde(default)]
+    env: Option<BTreeMap<String, String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    timeout_ms: Option<Option<u64>>,
+}
+
+#[derive(Clone, Debug)]
+struct ConfiguredMount {
+    host: PathBuf,
+    guest: String,
+    dir_perms: DirPerms,
+    file_perms: FilePerms,
+}
+
+#[derive(Default)]
+enum CacheDirConfig {
+    #[default]
+    Auto,
+    Disabled,
+    Custom(PathBuf),
+}
+
 const DEFAULT_MAX_MEMORY: usize = 64 * 1024 * 1024;
 const DEFAULT_PRELUDE: &str = "import sandbox.asyncio";
 
@@ -221,9 +379,82 @@ const DEFAULT_PRELUDE: &str = "import sandbox.asyncio";
 struct ContextConfig {
     max_memory: Option<usize>,
     prelude: Option<String>,
-    cache: Option<String>,
+    cache: CacheDirConfig,
+    runtime_lib_dir: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    mounts: Vec<ConfiguredMount>,
+    timeout: Option<Duration>,
+}
+
+impl ContextConfig {
+    fn apply_patch(&mut self, patch: ContextConfigPatch) -> Result<()> {
+        if let Some(cache_dir) = patch.cache_dir {
+            self.cache = cache_dir.map_or(CacheDirConfig::Disabled, |path| {
+                CacheDirConfig::Custom(PathBuf::from(path))
+            });
+        }
+        if let Some(max_memory) = patch.max_memory {
+            self.max_memory = parse_max_memory(max_memory)?;
+        }
+        if let Some(prelude) = patch.prelude {
+            // `null` restores the default prelude, an empty string disables it.
+            self.prelude = prelude;
+        }
+        if let Some(runtime_lib_dir) = patch.runtime_lib_dir {
+            self.runtime_lib_dir = runtime_lib_dir.map(PathBuf::from);
+        }
+        if let Some(mounts) = patch.mounts {
+            self.mounts = parse_mounts(mounts)?;
+        }
+        if let Some(env) = patch.env {
+            self.env = env.into_iter().collect();
+        }
+        if let Some(timeout_ms) = patch.timeout_ms {
+            self.timeout = parse_timeout(timeout_ms)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct PendingSandboxConfig {
+    max_memory: Option<usize>,
     env: Vec<(String, String)>,
-    mounts: Vec<MountConfig>,
+    mounts: Vec<ConfiguredMount>,
+    /// Deadline for calls that pass no timeout, inherited from the context.
+    timeout: Option<Duration>,
+}
+
+impl PendingSandboxConfig {
+    fn to_options(&self) -> SandboxOptions {
+        let mut options = SandboxOptions::default();
+        if let Some(max_memory) = self.max_memory {
+            options = options.max_memory(max_memory);
+        }
+        for mount in &self.mounts {
+            options = options.mount(&mount.host, &mount.guest, mount.dir_perms, mount.file_perms);
+        }
+        for (k, v) in &self.env {
+            options = options.env(k, v);
+        }
+        options
+    }
+
+    fn apply_patch(&mut self, patch: SandboxConfigPatch) -> Result<()> {
+        if let Some(max_memory) = patch.max_memory {
+            self.max_memory = parse_max_memory(max_memory)?;
+        }
+        if let Some(mounts) = patch.mounts {
+            self.mounts = parse_mounts(mounts)?;
+        }
+        if let Some(env) = patch.env {
+            self.env = env.into_iter().collect();
+        }
+        if let Some(timeout_ms) = patch.timeout_ms {
+            self.timeout = parse_timeout(timeout_ms)?;
+        }
+        Ok(())
+    }
 }
 
 struct ContextCore {
@@ -233,7 +464,7 @@ struct ContextCore {
 }
 
 impl ContextCore {
-    fn create_handle(nr_thread: i32) -> Result<Box<ContextHandle>> {
+    fn new(nr_thread: i32) -> Result<Self> {
         let rt = match nr_thread {
             0 => Builder::new_current_thread()
                 .enable_all()
@@ -256,11 +487,11 @@ impl ContextCore {
                 .build()
                 .map_err(|e| Error::Internal(format!("failed to build runtime: {e}")))?,
         };
-        Ok(Box::new(ContextHandle(Arc::new(Self {
+        Ok(Self {
             rt,
             module: None,
             config: ContextConfig::default(),
-        }))))
+        })
     }
 
     fn set_config(&mut self, key: &CStr, value: &CStr) -> Result<()> {
@@ -287,7 +518,7 @@ impl ContextCore {
                 self.config.prelude = Some(value.to_string());
             }
             "cache" => {
-                self.config.cache = Some(value.to_string());
+                self.config.cache = CacheDirConfig::Custom(PathBuf::from(value));
             }
             "env" => {
                 let env: EnvConfig = serde_json::from_str(value)
@@ -297,13 +528,22 @@ impl ContextCore {
             "mount" => {
                 let mount: MountConfig = serde_json::from_str(value)
                     .map_err(|_| Error::InvalidArgument("Invalid JSON for mount"))?;
-                self.config.mounts.push(mount);
+                self.config.mounts.push(mount.into());
             }
             _ => return Err(Error::InvalidArgument("Unknown config key")),
         }
         Ok(())
     }
 
+    fn configure_json(&mut self, patch: &CStr) -> Result<()> {
+        if self.module.is_some() {
+            return Err(Error::InvalidArgument(
+                "Cannot set config after initialization",
+            ));
+        }
+        self.config.apply_patch(parse_json_patch(patch)?)
+    }
+
     fn load(&mut self, path: &str) -> Result<()> {
         if self.module.is_some() {
             return Err(Error::InvalidArgument("Runtime already loaded"));
@@ -313,18 +553,21 @@ impl ContextCore {
         let parent = path
             .parent()
             .ok_or_else(|| Error::Internal("Wasm path has no parent directory".to_string()))?;
-        let mut lib_dir = std::env::var("WASI_PYTHON_RUNTIME").map_or_else(
-            |_| {
-                let mut lib_dir = parent.to_owned();
-                lib_dir.push("wasm32-wasip1");
-                lib_dir.push("wasi-deps");
-                lib_dir.push("usr");
-                lib_dir.push("local");
-                lib_dir
-            },
-            PathBuf::from,
-        );
-        lib_dir.push("lib");
+        let lib_dir = self.config.runtime_lib_dir.take().unwrap_or_else(|| {
+            let mut lib_dir = std::env::var("WASI_PYTHON_RUNTIME").map_or_else(
+                |_| {
+                    let mut lib_dir = parent.to_owned();
+                    lib_dir.push("wasm32-wasip1");
+                    lib_dir.push("wasi-deps");
+                    lib_dir.push("usr");
+                    lib_dir.push("local");
+                    lib_dir
+                },
+                PathBuf::from,
+            );
+            lib_dir.push("lib");
+            lib_dir
+        });
 
         let max_memory = self.config.max_memory.unwrap_or(DEFAULT_MAX_MEMORY);
         let prelude = match self.config.prelude.take() {
@@ -332,26 +575,22 @@ impl ContextCore {
             Some(s) => Some(s),
             None => Some(DEFAULT_PRELUDE.to_string()),
         };
-        let cache = self
-            .config
-            .cache
-            .take()
-            .map_or_else(|| parent.join("cache"), PathBuf::from);
+        let cache = match std::mem::take(&mut self.config.cache) {
+            CacheDirConfig::Auto => Some(parent.join("cache")),
+            CacheDirConfig::Disabled => None,
+            CacheDirConfig::Custom(path) => Some(path),
+        };
 
         self.rt.block_on(async {
             let mut builder = SandboxTemplate::builder()
                 .prelude(prelude)
-                .cache(Some(cache))
+                .cache(cache)
                 .max_memory(max_memory)
                 .mount(&lib_dir, "/lib", DirPerms::READ, FilePerms::READ);
 
             for mount in &self.config.mounts {
-                builder = builder.mount(
-                    &mount.host,
-                    &mount.guest,
-                    mount.dir_perms(),
-                    mount.file_perms(),
-                );
+                builder =
+                    builder.mount(&mount.host, &mount.guest, mount.dir_perms, mount.file_perms);
             }
 
             for (k, v) in &self.config.env {
@@ -367,37 +606,65 @@ impl ContextCore {
         })
     }
 
-    fn new_sandbox(self: &Arc<Self>) -> Result<SandboxHandle> {
+    fn new_sandbox(self: &Arc<Self>) -> Result<SandboxCore> {
         if self.module.is_none() {
             return Err(Error::InvalidArgument("Runtime not loaded"));
         }
-        Ok(SandboxHandle {
+        Ok(SandboxCore {
             ctx: self.clone(),
             handler_slot: Arc::new(OnceLock::new()),
             inner: SandboxInner::Pending {
-                options: SandboxOptions::default(),
+                config: self.pending_config(),
             },
         })
     }
+
+    /// Configuration a new sandbox starts from before its own overrides.
+    fn pending_config(&self) -> PendingSandboxConfig {
+        PendingSandboxConfig {
+            timeout: self.config.timeout,
+            ..PendingSandboxConfig::default()
+        }
+    }
 }
 
-pub struct ContextHandle(Arc<ContextCore>);
+/// Opaque handle for an isola context. Zero is never a valid handle.
+pub type ContextHandle = u64;
+
+static CONTEXTS: Registry<Mutex<Arc<ContextCore>>> = Registry::new(HandleKind::Context);
+
+/// Run `f` on the context behind `ctx`, which must not be shared with any
+/// sandbox yet.
+fn with_context_mut<R>(
+    ctx: ContextHandle,
+    f: impl FnOnce(&mut ContextCore) -> Result<R>,
+) -> Result<R> {
+    let ctx = CONTEXTS.get(ctx)?;
+    let mut core = ctx
+        .lock()
+        .map_err(|_| Error::Internal("Context mutex poisoned".to_string()))?;
+    Arc::get_mut(&mut core)
+        .ok_or(Error::InvalidArgument(
+            "context is shared by one or more sandboxes",
+        ))
+        .and_then(f)
+}
 
 /// Creates a new isola context with the specified number of threads.
 ///
 /// # Safety
 ///
-/// `out_context` must point to writable handle storage. It is set to `NULL`
-/// before context creation, so it remains `NULL` if creation fails.
+/// `out_context` must point to writable handle storage. It is set to `0`
+/// before context creation, so it remains `0` if creation fails.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_context_create(
     nr_thread: c_int,
-    out_context: *mut *mut ContextHandle,
+    out_context: *mut ContextHandle,
 ) -> ErrorCode {
     let out_context = c_try!(unsafe { require_mut(out_context, "out_context must not be NULL") });
-    *out_context = std::ptr::null_mut();
-    let ctx = c_try!(ContextCore::create_handle(nr_thread));
-    *out_context = Box::into_raw(ctx);
+    *out_context = 0;
+    let ctx = c_try!(ContextCore::new(nr_thread));
+    *out_context = CONTEXTS.insert(Mutex::new(Arc::new(ctx)));
     ErrorCode::Ok
 }
 
@@ -405,25 +672,18 @@ pub unsafe extern "C" fn isola_context_create(
 ///
 /// # Safety
 ///
-/// `ctx` must be a live context handle and `path` must be a valid,
-/// null-terminated C string. `NULL` arguments are rejected.
+/// `path` must be a valid, null-terminated C string. `NULL` is rejected.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_context_initialize(
-    ctx: *mut ContextHandle,
+    ctx: ContextHandle,
     path: *const c_char,
 ) -> ErrorCode {
-    let ctx = c_try!(unsafe { require_mut(ctx, "ctx must not be NULL") });
-    let Some(ctx) = Arc::get_mut(&mut ctx.0) else {
-        return fail(Error::InvalidArgument(
-            "context is shared by one or more sandboxes",
-        ));
-    };
     let path = c_try!(unsafe { require_cstr(path, "path must not be NULL") });
     let path = c_try!(
         path.to_str()
             .map_or_else(|_| Err(Error::InvalidArgument("Invalid path string")), Ok)
     );
-    c_try!(ctx.load(path));
+    c_try!(with_context_mut(ctx, |ctx| ctx.load(path)));
     ErrorCode::Ok
 }
 
@@ -452,32 +712,65 @@ pub unsafe extern "C" fn isola_context_initialize(
 /// null-terminated C strings.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_context_config_set(
-    ctx: *mut ContextHandle,
+    ctx: ContextHandle,
     key: *const c_char,
     value: *const c_char,
 ) -> ErrorCode {
-    let ctx = c_try!(unsafe { require_mut(ctx, "ctx must not be NULL") });
-    let Some(ctx) = Arc::get_mut(&mut ctx.0) else {
-        return fail(Error::InvalidArgument(
-            "context is shared by one or more sandboxes",
-        ));
-    };
     let key = c_try!(unsafe { require_cstr(key, "key must not be NULL") });
     let value = c_try!(unsafe { require_cstr(value, "value must not be NULL") });
-    c_try!(ctx.set_config(key, value));
+    c_try!(with_context_mut(ctx, |ctx| ctx.set_config(key, value)));
     ErrorCode::Ok
 }
 
-/// Destroy a context handle. Passing `NULL` is allowed and has no effect.
+/// Applies a JSON config patch to the isola context.
+///
+/// Must be called **before** `isola_context_initialize`. The patch is a JSON
+/// object using the same schema as the python-sdk `configure` call; omitted
+/// keys are left unchanged and unknown keys are rejected with
+/// `ISOLA_ERROR_CODE_INVALID_ARGUMENT`.
+///
+/// # Supported keys
+///
+/// | Key               | Value                                                       |
+/// |-------------------|-------------------------------------------------------------|
+/// | `cache_dir`       | Cache directory path, or `null` to disable caching.         |
+/// | `max_memory`      | Byte count, or `null` for the default.                      |
+/// | `prelude`         | Guest prelude source, or `null` for the default.            |
+/// | `runtime_lib_dir` | Host directory mounted at `/lib`, or `null` for default. |
+/// | `mounts`          | Array of `{"host","guest","dir_perms","file_perms"}`.       |
+/// | `env`             | Object mapping variable names to values.                    |
+/// | `timeout_ms`      | Default call deadline in milliseconds, or `null` for none.  |
+///
+/// `mounts` and `env` replace every entry configured so far. Mount
+/// permissions are `"read"` (the default), `"write"`, or `"read-write"`.
+/// `timeout_ms` applies to `isola_sandbox_load_script` and
+/// `isola_sandbox_run` calls that pass a timeout of `0`, and is inherited by
+/// sandboxes created afterwards.
 ///
 /// # Safety
-/// A non-`NULL` handle must have been returned by `isola_context_create` and
-/// must not have been destroyed previously.
+///
+/// `patch` must be a valid, null-terminated C string.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn isola_context_destroy(ctx: *mut ContextHandle) {
-    if !ctx.is_null() {
-        drop(unsafe { Box::from_raw(ctx) });
+pub unsafe extern "C" fn isola_context_configure_json(
+    ctx: ContextHandle,
+    patch: *const c_char,
+) -> ErrorCode {
+    let patch = c_try!(unsafe { require_cstr(patch, "patch must not be NULL") });
+    c_try!(with_context_mut(ctx, |ctx| ctx.configure_json(patch)));
+    ErrorCode::Ok
+}
+
+/// Destroy a context handle. Passing `0` is allowed and has no effect.
+///
+/// Sandboxes created from the context keep it alive until they are
+/// destroyed. Destroying a handle twice returns
+/// `ISOLA_ERROR_CODE_INVALID_HANDLE`.
+#[unsafe(no_mangle)]
+pub extern "C" fn isola_context_destroy(ctx: ContextHandle) -> ErrorCode {
+    if ctx != 0 {
+        c_try!(CONTEXTS.remove(ctx));
     }
+    ErrorCode::Ok
 }
 
 // ---------------------------------------------------------------------------
@@ -487,23 +780,24 @@ pub unsafe extern "C" fn isola_context_destroy(ctx: *mut ContextHandle) {
 enum SandboxInner {
     Uninitialized,
     Pending {
-        options: SandboxOptions,
+        config: PendingSandboxConfig,
     },
     Running {
-        sandbox: Sandbox<Env>,
+        sandbox: Box<Sandbox<Env>>,
         handler: Arc<SandboxHandler>,
+        timeout: Option<Duration>,
     },
 }
 
-pub struct SandboxHandle {
+struct SandboxCore {
     ctx: Arc<ContextCore>,
     handler_slot: Arc<OnceLock<Arc<SandboxHandler>>>,
     inner: SandboxInner,
 }
 
-impl SandboxHandle {
+impl SandboxCore {
     fn set_config(&mut self, key: &CStr, value: &CStr) -> Result<()> {
-        let SandboxInner::Pending { options } = &mut self.inner else {
+        let SandboxInner::Pending { config } = &mut self.inner else {
             return Err(Error::InvalidArgument("Cannot set config after start"));
         };
         let key = key
@@ -518,28 +812,31 @@ impl SandboxHandle {
                 let bytes: usize = value
                     .parse()
                     .map_err(|_| Error::InvalidArgument("Invalid max_memory value"))?;
-                *options = std::mem::take(options).max_memory(bytes);
+                config.max_memory = Some(bytes);
             }
             "env" => {
                 let env: EnvConfig = serde_json::from_str(value)
                     .map_err(|_| Error::InvalidArgument("Invalid JSON for env"))?;
-                *options = std::mem::take(options).env(&env.name, &env.value);
+                config.env.retain(|(k, _)| *k != env.name);
+                config.env.push((env.name, env.value));
             }
             "mount" => {
                 let mount: MountConfig = serde_json::from_str(value)
                     .map_err(|_| Error::InvalidArgument("Invalid JSON for mount"))?;
-                *options = std::mem::take(options).mount(
-                    &mount.host,
-                    &mount.guest,
-                    mount.dir_perms(),
-                    mount.file_perms(),
-                );
+                config.mounts.push(mount.into());
             }
             _ => return Err(Error::InvalidArgument("Unknown config key")),
         }
         Ok(())
     }
 
+    fn configure_json(&mut self, patch: &CStr) -> Result<()> {
+        let SandboxInner::Pending { config } = &mut self.inner else {
+            return Err(Error::InvalidArgument("Cannot set config after start"));
+        };
+        config.apply_patch(parse_json_patch(patch)?)
+    }
+
     fn set_handler(&self, handler: Arc<SandboxHandler>) -> Result<()> {
         self.handler_slot
             .set(handler)
@@ -548,7 +845,7 @@ impl SandboxHandle {
 
     fn start(&mut self) -> Result<()> {
         match std::mem::replace(&mut self.inner, SandboxInner::Uninitialized) {
-            SandboxInner::Pending { options } => {
+            SandboxInner::Pending { config } => {
                 let handler = self
                     .handler_slot
                     .get()
@@ -564,18 +861,22 @@ impl SandboxHandle {
                 let sandbox = self
                     .ctx
                     .rt
-                    .block_on(async { module.instantiate(env, options.clone()).await });
+                    .block_on(async { module.instantiate(env, config.to_options()).await });
                 let sandbox = match sandbox {
                     Ok(sandbox) => sandbox,
                     Err(error) => {
-                        self.inner = SandboxInner::Pending { options };
+                        self.inner = SandboxInner::Pending { config };
                         return Err(Error::Internal(format!(
                             "Failed to create instance: {error}"
                         )));
                     }
                 };
 
-                self.inner = SandboxInner::Running { sandbox, handler };
+                self.inner = SandboxInner::Running {
+                    sandbox: Box::new(sandbox),
+                    handler,
+                    timeout: config.timeout,
+                };
                 Ok(())
             }
             _ => Err(Error::InvalidArgument("Instance not in pending state")),
@@ -584,18 +885,26 @@ impl SandboxHandle {
 
     fn load_script(&mut self, input: &str, timeout_in_ms: u64) -> Result<()> {
         match &mut self.inner {
-            SandboxInner::Running { sandbox, handler } => {
+            SandboxInner::Running {
+                sandbox,
+                handler,
+                timeout,
+            } => {
+                let options = call_options(call_timeout(timeout_in_ms, *timeout));
                 self.ctx
                     .rt
-                    .block_on(async {
-                        tokio::time::timeout(
-                            Duration::from_millis(timeout_in_ms),
-                            sandbox.eval_script(input, handler.output_target()),
-                        )
-                        .await
-                    })
-                    .map_err(|_| Error::Internal("Script execution timeout".to_string()))?
-                    .map_err(|e| Error::Internal(format!("Script loading failed: {e}")))?;
+                    .block_on(sandbox.eval_script_with_options(
+                        input,
+                        handler.output_target(),
+                        options,
+                    ))
+                    .map_err(|e| {
+                        if e.kind() == ErrorKind::Timeout {
+                            Error::Internal("Script execution timeout".to_string())
+                        } else {
+                            Error::Internal(format!("Script loading failed: {e}"))
+                        }
+                    })?;
 
                 Ok(())
             }
@@ -625,30 +934,33 @@ impl SandboxHandle {
             SandboxInner::Running {
                 mut sandbox,
                 handler,
+                timeout,
             } => {
-                let timeout = Duration::from_millis(timeout_in_ms);
-                let result = self.ctx.rt.block_on(async {
-                    tokio::time::timeout(
-                        timeout,
-                        sandbox.call_with_sink(func, isola_args, handler.output_target()),
-                    )
-                    .await
-                });
+                let call_timeout = call_timeout(timeout_in_ms, timeout);
+                let result = self.ctx.rt.block_on(sandbox.call_with_options(
+                    func,
+                    isola_args,
+                    handler.output_target(),
+                    call_options(call_timeout),
+                ));
 
                 // Restore the sandbox state.
-                self.inner = SandboxInner::Running { sandbox, handler };
+                self.inner = SandboxInner::Running {
+                    sandbox,
+                    handler,
+                    timeout,
+                };
 
-                result.map_or_else(
-                    |_| {
-                        Err(Error::Internal(format!(
+                result.map_err(|e| {
+                    if e.kind() == ErrorKind::Timeout {
+                        Error::Internal(format!(
                             "Sandbox execution timed out after {}ms",
-                            timeout.as_millis()
-                        )))
-                    },
-                    |inner| {
-                        inner.map_err(|e| Error::Internal(format!("Sandbox execution failed: {e}")))
-                    },
-                )?;
+                            call_timeout.unwrap_or_default().as_millis()
+                        ))
+                    } else {
+                        Error::Internal(format!("Sandbox execution failed: {e}"))
+                    }
+                })?;
 
                 Ok(())
             }
@@ -657,35 +969,71 @@ impl SandboxHandle {
     }
 }
 
+fn call_options(timeout: Option<Duration>) -> CallOptions {
+    let options = CallOptions::default();
+    match timeout {
+        Some(timeout) => options.deadline(Instant::now() + timeout),
+        None => options,
+    }
+}
+
+/// Opaque handle for a sandbox. Zero is never a valid handle.
+pub type SandboxHandle = u64;
+
+static SANDBOXES: Registry<Mutex<SandboxCore>> = Registry::new(HandleKind::Sandbox);
+
+/// Run `f` on the sandbox behind `sandbox`. A sandbox serves one call at a
+/// time; a concurrent or reentrant call is rejected instead of blocking.
+fn with_sandbox<R>(
+    sandbox: SandboxHandle,
+    f: impl FnOnce(&mut SandboxCore) -> Result<R>,
+) -> Result<R> {
+    let sandbox = SANDBOXES.get(sandbox)?;
+    let mut core = match sandbox.try_lock() {
+        Ok(core) => core,
+        Err(TryLockError::WouldBlock) => {
+            return Err(Error::InvalidArgument("sandbox is busy with another call"));
+        }
+        Err(TryLockError::Poisoned(_)) => {
+            return Err(Error::Internal("Sandbox mutex poisoned".to_string()));
+        }
+    };
+    f(&mut core)
+}
+
 /// Creates a new sandbox instance from the context.
 ///
 /// # Safety
 ///
-/// `ctx` must be a live context handle and `out_sandbox` must point to writable
-/// handle storage. The output is set to `NULL` before creation.
+/// `out_sandbox` must point to writable handle storage. The output is set to
+/// `0` before creation.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_sandbox_create(
-    ctx: *const ContextHandle,
-    out_sandbox: *mut *mut SandboxHandle,
+    ctx: ContextHandle,
+    out_sandbox: *mut SandboxHandle,
 ) -> ErrorCode {
-    let ctx = c_try!(unsafe { require_ref(ctx, "ctx must not be NULL") });
     let out_sandbox = c_try!(unsafe { require_mut(out_sandbox, "out_sandbox must not be NULL") });
-    *out_sandbox = std::ptr::null_mut();
-    let sandbox = c_try!(ctx.0.new_sandbox());
-    *out_sandbox = Box::into_raw(Box::new(sandbox));
+    *out_sandbox = 0;
+    let ctx = c_try!(CONTEXTS.get(ctx));
+    let core = c_try!(
+        ctx.lock()
+            .map_err(|_| Error::Internal("Context mutex poisoned".to_string()))
+            .and_then(|core| core.new_sandbox())
+    );
+    *out_sandbox = SANDBOXES.insert(Mutex::new(core));
     ErrorCode::Ok
 }
 
-/// Destroy a sandbox handle. Passing `NULL` is allowed and has no effect.
+/// Destroy a sandbox handle. Passing `0` is allowed and has no effect.
 ///
-/// # Safety
-/// A non-`NULL` handle must have been returned by `isola_sandbox_create` and
-/// must not have been destroyed previously.
+/// A call still running on the sandbox keeps it alive until it returns.
+/// Destroying a handle twice returns `ISOLA_ERROR_CODE_INVALID_HANDLE`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn isola_sandbox_destroy(sandbox: *mut SandboxHandle) {
-    if !sandbox.is_null() {
-        drop(unsafe { Box::from_raw(sandbox) });
+pub extern "C" fn isola_sandbox_destroy(sandbox: SandboxHandle) -> ErrorCode {
+    if sandbox != 0 {
+        c_try!(SANDBOXES.remove(sandbox));
     }
+    ErrorCode::Ok
 }
 
 /// Sets a per-sandbox configuration value, overriding context-level defaults.
@@ -712,14 +1060,33 @@ pub unsafe extern "C" fn isola_sandbox_destroy(sandbox: *mut SandboxHandle) {
 /// null-terminated C strings.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_sandbox_set_config(
-    sandbox: *mut SandboxHandle,
+    sandbox: SandboxHandle,
     key: *const c_char,
     value: *const c_char,
 ) -> ErrorCode {
-    let sandbox = c_try!(unsafe { require_mut(sandbox, "sandbox must not be NULL") });
     let key = c_try!(unsafe { require_cstr(key, "key must not be NULL") });
     let value = c_try!(unsafe { require_cstr(value, "value must not be NULL") });
-    c_try!(sandbox.set_config(key, value));
+    c_try!(with_sandbox(sandbox, |sandbox| sandbox.set_config(key, value)));
+    ErrorCode::Ok
+}
+
+/// Applies a JSON config patch to a sandbox, overriding context-level
+/// defaults.
+///
+/// Must be called **before** `isola_sandbox_start`. Accepts the `max_memory`,
+/// `mounts`, `env`, and `timeout_ms` keys of the `isola_context_configure_json`
+/// schema, with the same replacement semantics.
+///
+/// # Safety
+///
+/// `patch` must be a valid, null-terminated C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn isola_sandbox_configure_json(
+    sandbox: SandboxHandle,
+    patch: *const c_char,
+) -> ErrorCode {
+    let patch = c_try!(unsafe { require_cstr(patch, "patch must not be NULL") });
+    c_try!(with_sandbox(sandbox, |sandbox| sandbox.configure_json(patch)));
     ErrorCode::Ok
 }
 
@@ -743,11 +1110,10 @@ pub enum CallbackEvent {
 /// `vtable` must point to a valid `SandboxHandlerVtable`.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_sandbox_set_handler(
-    sandbox: *mut SandboxHandle,
+    sandbox: SandboxHandle,
     vtable: *const SandboxHandlerVtable,
     user_data: *mut c_void,
 ) -> ErrorCode {
-    let sandbox = c_try!(unsafe { require_mut(sandbox, "sandbox must not be NULL") });
     let vtable = c_try!(unsafe { require_ref(vtable, "vtable must not be NULL") });
     let Some(on_event) = vtable.on_event else {
         return fail(Error::InvalidArgument("vtable.on_event must not be NULL"));
@@ -762,46 +1128,46 @@ pub unsafe extern "C" fn isola_sandbox_set_handler(
         on_event,
         user_data,
     });
-    c_try!(sandbox.set_handler(handler));
+    c_try!(with_sandbox(sandbox, |sandbox| sandbox.set_handler(handler)));
     ErrorCode::Ok
 }
 
 /// Start a configured sandbox.
-///
-/// # Safety
-/// `sandbox` must be a live handle returned by `isola_sandbox_create`. `NULL`
-/// is rejected.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn isola_sandbox_start(sandbox: *mut SandboxHandle) -> ErrorCode {
-    let sandbox = c_try!(unsafe { require_mut(sandbox, "sandbox must not be NULL") });
-    c_try!(sandbox.start());
+pub extern "C" fn isola_sandbox_start(sandbox: SandboxHandle) -> ErrorCode {
+    c_try!(with_sandbox(sandbox, SandboxCore::start));
     ErrorCode::Ok
 }
 
 /// Loads a script into the sandbox.
 ///
+/// A `timeout_in_ms` of `0` uses the configured `timeout_ms`; without one
+/// the script runs without a deadline.
+///
 /// # Safety
 ///
 /// The caller must ensure that `input` is a valid, null-terminated C string.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_sandbox_load_script(
-    sandbox: *mut SandboxHandle,
+    sandbox: SandboxHandle,
     input: *const c_char,
     timeout_in_ms: u64,
 ) -> ErrorCode {
-    let sandbox = c_try!(unsafe { require_mut(sandbox, "sandbox must not be NULL") });
     let input = c_try!(unsafe { require_cstr(input, "input must not be NULL") });
     let input = c_try!(
         input
             .to_str()
             .map_or_else(|_| Err(Error::InvalidArgument("Invalid input string")), Ok)
     );
-    c_try!(sandbox.load_script(input, timeout_in_ms));
+    c_try!(with_sandbox(sandbox, |sandbox| sandbox.load_script(input, timeout_in_ms)));
     ErrorCode::Ok
 }
 
 /// Runs a function in the sandbox with the specified arguments.
 ///
+/// A `timeout_in_ms` of `0` uses the configured `timeout_ms`; without one
+/// the call runs without a deadline.
+///
 /// # Safety
 ///
 /// The caller must ensure that:
@@ -811,13 +1177,12 @@ pub unsafe extern "C" fn isola_sandbox_load_script(
 /// - Each `Argument` in the array has valid pointers and data
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_sandbox_run(
-    sandbox: *mut SandboxHandle,
+    sandbox: SandboxHandle,
     func: *const c_char,
     args: *const Argument,
     args_len: usize,
     timeout_in_ms: u64,
 ) -> ErrorCode {
-    let sandbox = c_try!(unsafe { require_mut(sandbox, "sandbox must not be NULL") });
     let func = c_try!(unsafe { require_cstr(func, "func must not be NULL") });
     let func = c_try!(
         func.to_str()
@@ -825,8 +1190,8 @@ pub unsafe extern "C" fn isola_sandbox_run(
     );
 
     let mut validated_args = Vec::with_capacity(args_len);
-    let mut unique_stream_ptrs = std::collections::HashSet::new();
-    let mut stream_ptrs = Vec::new();
+    let mut unique_streams = std::collections::HashSet::new();
+    let mut streams = Vec::new();
     if args_len != 0 {
         if args.is_null() {
             return fail(Error::InvalidArgument(
@@ -873,19 +1238,14 @@ pub unsafe extern "C" fn isola_sandbox_run(
                     }
                 }
                 ISOLA_ARGUMENT_KIND_STREAM => {
-                    let stream_ptr = unsafe { arg.value.stream };
-                    if stream_ptr.is_null() {
-                        return fail(Error::InvalidArgument(
-                            "Stream argument handle must not be NULL",
-                        ));
-                    }
-                    if !unique_stream_ptrs.insert(stream_ptr) {
+                    let stream = unsafe { arg.value.stream };
+                    if !unique_streams.insert(stream) {
                         return fail(Error::InvalidArgument(
                             "Stream argument handle must not be reused",
                         ));
                     }
-                    let stream_index = stream_ptrs.len();
-                    stream_ptrs.push(stream_ptr);
+                    let stream_index = streams.len();
+                    streams.push(stream);
                     ValidatedArgument::Stream(name, stream_index)
                 }
                 _ => return fail(Error::InvalidArgument("Unknown argument kind")),
@@ -894,7 +1254,7 @@ pub unsafe extern "C" fn isola_sandbox_run(
         }
     }
 
-    let mut receivers = c_try!(take_stream_receivers(&stream_ptrs));
+    let mut receivers = c_try!(take_stream_receivers(&streams));
 
     let mut parsed_args = Vec::with_capacity(validated_args.len());
     for arg in validated_args {
@@ -913,7 +1273,11 @@ pub unsafe extern "C" fn isola_sandbox_run(
         parsed_args.push(parsed);
     }
 
-    c_try!(sandbox.run(func, parsed_args, timeout_in_ms));
+    c_try!(with_sandbox(sandbox, |sandbox| sandbox.run(
+        func,
+        parsed_args,
+        timeout_in_ms
+    )));
     ErrorCode::Ok
 }
 
@@ -969,7 +1333,7 @@ pub struct EncodedValue {
 #[derive(Copy, Clone)]
 pub union ArgumentValue {
     pub value: EncodedValue,
-    pub stream: *const StreamHandle,
+    pub stream: StreamHandle,
 }
 
 #[repr(C)]
@@ -983,13 +1347,18 @@ pub struct Argument {
 // Stream
 // ---------------------------------------------------------------------------
 
-pub struct StreamHandle {
+/// Opaque handle for a streaming argument. Zero is never a valid handle.
+pub type StreamHandle = u64;
+
+static STREAMS: Registry<Stream> = Registry::new(HandleKind::Stream);
+
+struct Stream {
     format: ValueFormat,
     sender: std::sync::Mutex<Option<tokio::sync::mpsc::Sender<Value>>>,
     receiver: std::sync::Mutex<Option<tokio::sync::mpsc::Receiver<Value>>>,
 }
 
-impl StreamHandle {
+impl Stream {
     fn take_receiver(&self) -> Result<tokio::sync::mpsc::Receiver<Value>> {
         self.receiver
             .lock()
@@ -1005,6 +1374,26 @@ impl StreamHandle {
         debug_assert!(slot.is_none());
         *slot = Some(receiver);
     }
+
+    /// Close the sending side. Fails if the stream was already ended.
+    fn end(&self) -> Result<()> {
+        self.sender
+            .lock()
+            .map_err(|_| Error::Internal("Stream mutex poisoned".to_string()))?
+            .take()
+            .map(drop)
+            .ok_or(Error::ClosedHandle("stream"))
+    }
+
+    /// Whether both ends are gone, so the handle has no further use.
+    fn is_released(&self) -> bool {
+        let ended = self.sender.lock().is_ok_and(|sender| sender.is_none());
+        ended
+            && self
+                .receiver
+                .lock()
+                .is_ok_and(|receiver| receiver.is_none())
+    }
 }
 
 enum RawArgument {
@@ -1025,17 +1414,20 @@ unsafe fn blob_as_slice<'a>(blob: Blob) -> Option<&'a [u8]> {
 }
 
 fn take_stream_receivers(
-    stream_ptrs: &[*const StreamHandle],
+    handles: &[StreamHandle],
 ) -> Result<Vec<Option<tokio::sync::mpsc::Receiver<Value>>>> {
-    let mut receivers = Vec::with_capacity(stream_ptrs.len());
-    for stream_ptr in stream_ptrs {
-        let stream = unsafe { &**stream_ptr };
+    let streams = handles
+        .iter()
+        .map(|&handle| STREAMS.get(handle))
+        .collect::<Result<Vec<_>>>()?;
+    let mut receivers = Vec::with_capacity(streams.len());
+    for stream in &streams {
         let receiver = match stream.take_receiver() {
             Ok(receiver) => receiver,
             Err(error) => {
-                for (acquired_ptr, receiver) in stream_ptrs.iter().zip(receivers).rev() {
+                for (acquired, receiver) in streams.iter().zip(receivers).rev() {
                     if let Some(receiver) = receiver {
-                        unsafe { &**acquired_ptr }.restore_receiver(receiver);
+                        acquired.restore_receiver(receiver);
                     }
                 }
                 return Err(error);
@@ -1043,6 +1435,11 @@ fn take_stream_receivers(
         };
         receivers.push(Some(receiver));
     }
+    for (&handle, stream) in handles.iter().zip(&streams) {
+        if stream.is_released() {
+            let _ = STREAMS.remove(handle);
+        }
+    }
     Ok(receivers)
 }
 
@@ -1051,22 +1448,21 @@ fn take_stream_receivers(
 /// # Safety
 ///
 /// `out_stream` must point to writable handle storage. The output is set to
-/// `NULL` before creation. Unknown `format` values are rejected.
+/// `0` before creation. Unknown `format` values are rejected.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_stream_create(
     format: ArgumentType,
-    out_stream: *mut *mut StreamHandle,
+    out_stream: *mut StreamHandle,
 ) -> ErrorCode {
     let out_stream = c_try!(unsafe { require_mut(out_stream, "out_stream must not be NULL") });
-    *out_stream = std::ptr::null_mut();
+    *out_stream = 0;
     let format = c_try!(parse_argument_type(format));
     let (sender, receiver) = tokio::sync::mpsc::channel(1024);
-    let stream = Box::new(StreamHandle {
+    *out_stream = STREAMS.insert(Stream {
         format,
         sender: std::sync::Mutex::new(Some(sender)),
         receiver: std::sync::Mutex::new(Some(receiver)),
     });
-    *out_stream = Box::into_raw(stream);
     ErrorCode::Ok
 }
 
@@ -1082,12 +1478,12 @@ pub unsafe extern "C" fn isola_stream_create(
 ///   If zero, returns immediately with an error if the channel is full.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_stream_push(
-    stream: *const StreamHandle,
+    stream: StreamHandle,
     data: *const u8,
     len: usize,
     blocking: c_int,
 ) -> ErrorCode {
-    let stream = c_try!(unsafe { require_ref(stream, "stream must not be NULL") });
+    let stream = c_try!(STREAMS.get(stream));
     let Some(data) = (unsafe { blob_as_slice(Blob { data, len }) }) else {
         let err = Error::InvalidArgument("Invalid stream buffer");
         crate::error::set_last_error(err);
@@ -1110,10 +1506,10 @@ pub unsafe extern "C" fn isola_stream_push(
         ValueFormat::Cbor => Value::from_cbor(data.to_vec()),
     };
 
-    send_stream_value(stream, value, blocking)
+    send_stream_value(&stream, value, blocking)
 }
 
-fn send_stream_value(stream: &StreamHandle, value: Value, blocking: c_int) -> ErrorCode {
+fn send_stream_value(stream: &Stream, value: Value, blocking: c_int) -> ErrorCode {
     if blocking != 0 {
         // Clone the sender so the mutex is not held while blocking.
         let sender = match stream.sender.lock() {
@@ -1145,19 +1541,20 @@ fn send_stream_value(stream: &StreamHandle, value: Value, blocking: c_int) -> Er
     }
 }
 
-/// Signals the end of a stream and frees the handle.
-///
-/// After calling this function, no more data can be pushed to the stream
-/// and the handle is invalid.
+/// Signals the end of a stream.
 ///
-/// # Safety
-/// `stream` must be a live handle returned by `isola_stream_create` that has
-/// not already been ended. `NULL` is rejected.
+/// After calling this function, no more data can be pushed to the stream.
+/// A stream ended before any `isola_sandbox_run` took it can still be passed
+/// to one; the handle is released once it has been both ended and taken.
+/// Ending a stream twice returns `ISOLA_ERROR_CODE_INVALID_HANDLE`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn isola_stream_end(stream: *mut StreamHandle) -> ErrorCode {
-    let stream = c_try!(unsafe { require_mut(stream, "stream must not be NULL") });
-    // Dropping the StreamHandle closes the sender and frees the handle.
-    drop(unsafe { Box::from_raw(std::ptr::from_mut(stream)) });
+pub extern "C" fn isola_stream_end(stream: StreamHandle) -> ErrorCode {
+    let handle = stream;
+    let stream = c_try!(STREAMS.get(handle));
+    c_try!(stream.end());
+    if stream.is_released() {
+        let _ = STREAMS.remove(handle);
+    }
     ErrorCode::Ok
 }
 
@@ -1175,17 +1572,16 @@ pub unsafe extern "C" fn isola_stream_end(stream: *mut StreamHandle) -> ErrorCod
 ///
 /// # Safety
 ///
-/// - `body` must be a live handle obtained from an `http_request` callback.
-/// - `headers` must point to a valid array of `headers_len` elements (may be
-///   NULL if `headers_len` is 0).
+/// `headers` must point to a valid array of `headers_len` elements (may be
+/// NULL if `headers_len` is 0).
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_http_response_body_start(
-    body: *const crate::env::HttpResponseBody,
+    body: HttpResponseHandle,
     status: u16,
     headers: *const crate::env::HttpHeader,
     headers_len: usize,
 ) -> ErrorCode {
-    let body = c_try!(unsafe { require_ref(body, "body must not be NULL") });
+    let body = c_try!(HTTP_RESPONSES.get(body));
     if http::StatusCode::from_u16(status).is_err() {
         return fail(Error::InvalidArgument("Invalid HTTP response status"));
     }
@@ -1240,15 +1636,14 @@ pub unsafe extern "C" fn isola_http_response_body_start(
 ///
 /// # Safety
 ///
-/// - `body` must be a live handle obtained from an `http_request` callback.
-/// - `data` must point to a valid buffer of `len` bytes.
+/// `data` must point to a valid buffer of `len` bytes.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_http_response_body_push(
-    body: *const crate::env::HttpResponseBody,
+    body: HttpResponseHandle,
     data: *const u8,
     len: usize,
 ) -> ErrorCode {
-    let body = c_try!(unsafe { require_ref(body, "body must not be NULL") });
+    let body = c_try!(HTTP_RESPONSES.get(body));
     let Some(data) = (unsafe { blob_as_slice(Blob { data, len }) }) else {
         return fail(Error::InvalidArgument("Invalid HTTP response body buffer"));
     };
@@ -1261,20 +1656,18 @@ pub unsafe extern "C" fn isola_http_response_body_push(
     ErrorCode::Ok
 }
 
-/// Signals EOF and frees the response body handle.
+/// Signals EOF and releases the response body handle.
 ///
 /// Must be called exactly once per handle, even if no data was pushed.
-///
-/// # Safety
-///
-/// `body` must be `NULL` or a live handle obtained from an `http_request`
-/// callback. After this call a non-`NULL` pointer is invalid.
+/// Passing `0` has no effect; closing a handle twice returns
+/// `ISOLA_ERROR_CODE_INVALID_HANDLE`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn isola_http_response_body_close(body: *mut crate::env::HttpResponseBody) {
+pub extern "C" fn isola_http_response_body_close(body: HttpResponseHandle) -> ErrorCode {
     // Dropping the sender signals EOF to the receiver stream.
-    if !body.is_null() {
-        drop(unsafe { Box::from_raw(body) });
+    if body != 0 {
+        c_try!(HTTP_RESPONSES.remove(body));
     }
+    ErrorCode::Ok
 }
 
 // ---------------------------------------------------------------------------
@@ -1283,7 +1676,7 @@ pub unsafe extern "C" fn isola_http_response_body_close(body: *mut crate::env::H
 
 /// Resolves a hostcall with a JSON result value, consuming the handle.
 ///
-/// Must be called exactly once per handle. After this call the pointer is
+/// Must be called exactly once per handle. After this call the handle is
 /// invalid. Use `isola_hostcall_response_reject` to deliver an error instead.
 ///
 /// If the data is not valid JSON, the handle is **not** consumed and the
@@ -1292,17 +1685,15 @@ pub unsafe extern "C" fn isola_http_response_body_close(body: *mut crate::env::H
 ///
 /// # Safety
 ///
-/// - `response` must be a live handle obtained from a `hostcall` callback.
-/// - `data` must point to a valid JSON buffer of `len` bytes.
+/// `data` must point to a valid JSON buffer of `len` bytes.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_hostcall_response_resolve(
-    response: *mut crate::env::HostcallResponse,
+    response: HostcallResponseHandle,
     data: *const u8,
     len: usize,
 ) -> ErrorCode {
-    if response.is_null() {
-        return fail(Error::InvalidArgument("response must not be NULL"));
-    }
+    let handle = response;
+    c_try!(HOSTCALL_RESPONSES.get(handle));
     // Validate before consuming so the handle survives parse errors.
     let Some(json) = (unsafe { blob_as_slice(Blob { data, len }) }) else {
         return fail(Error::InvalidArgument("Invalid hostcall response buffer"));
@@ -1316,7 +1707,7 @@ pub unsafe extern "C" fn isola_hostcall_response_resolve(
         return ErrorCode::InvalidArgument;
     };
     // Now consume the handle.
-    let response = unsafe { Box::from_raw(response) };
+    let response = c_try!(HOSTCALL_RESPONSES.remove(handle));
     if response.resolve(value).is_err() {
         crate::error::set_last_error(Error::Internal(
             "hostcall response already completed or receiver dropped".to_string(),
@@ -1328,27 +1719,25 @@ pub unsafe extern "C" fn isola_hostcall_response_resolve(
 
 /// Rejects a hostcall with an error message, consuming the handle.
 ///
-/// Must be called exactly once per handle. After this call the pointer is
+/// Must be called exactly once per handle. After this call the handle is
 /// invalid. Use `isola_hostcall_response_resolve` to deliver a result instead.
 ///
 /// # Safety
 ///
-/// - `response` must be a live handle obtained from a `hostcall` callback.
-/// - `error_message` must be a valid, null-terminated C string.
+/// `error_message` must be a valid, null-terminated C string.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn isola_hostcall_response_reject(
-    response: *mut crate::env::HostcallResponse,
+    response: HostcallResponseHandle,
     error_message: *const c_char,
 ) -> ErrorCode {
-    if response.is_null() {
-        return fail(Error::InvalidArgument("response must not be NULL"));
-    }
+    let handle = response;
+    c_try!(HOSTCALL_RESPONSES.get(handle));
     let msg = c_try!(unsafe { require_cstr(error_message, "error_message must not be NULL") });
     let Ok(msg_str) = msg.to_str() else {
         crate::error::set_last_error(Error::InvalidArgument("Invalid UTF-8 in error message"));
         return ErrorCode::InvalidArgument;
     };
-    let response = unsafe { Box::from_raw(response) };
+    let response = c_try!(HOSTCALL_RESPONSES.remove(handle));
     if response.reject(msg_str.to_string()).is_err() {
         crate::error::set_last_error(Error::Internal(
             "hostcall response already completed or receiver dropped".to_string(),
@@ -1361,27 +1750,41 @@ pub unsafe extern "C" fn isola_hostcall_response_reject(
 /// Cancels a hostcall without a result, consuming the handle.
 ///
 /// Use this when external work is abandoned. The waiting guest call fails and
-/// the response allocation is released.
-///
-/// # Safety
-///
-/// `response` must be `NULL` or a live handle obtained from a `hostcall`
-/// callback.
+/// the handle is released. Passing `0` has no effect.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn isola_hostcall_response_cancel(
-    response: *mut crate::env::HostcallResponse,
-) {
-    if !response.is_null() {
-        drop(unsafe { Box::from_raw(response) });
+pub extern "C" fn isola_hostcall_response_cancel(response: HostcallResponseHandle) -> ErrorCode {
+    if response != 0 {
+        c_try!(HOSTCALL_RESPONSES.remove(response));
     }
+    ErrorCode::Ok
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn pending_sandbox(ctx: ContextHandle) -> SandboxHandle {
+        let ctx = CONTEXTS.get(ctx).expect("live context");
+        let ctx = Arc::clone(&ctx.lock().expect("context lock"));
+        let config = ctx.pending_config();
+        SANDBOXES.insert(Mutex::new(SandboxCore {
+            ctx,
+            handler_slot: Arc::new(OnceLock::new()),
+            inner: SandboxInner::Pending { config },
+        }))
+    }
+
+    fn stream() -> StreamHandle {
+        let mut stream = 0;
+        assert_eq!(
+            unsafe { isola_stream_create(ISOLA_ARGUMENT_TYPE_JSON, &raw mut stream) },
+            ErrorCode::Ok
+        );
+        stream
+    }
+
     #[test]
-    fn ffi_rejects_null_handles_and_output_slots() {
+    fn ffi_rejects_invalid_handles_and_output_slots() {
         assert_eq!(
             unsafe { isola_context_create(0, std::ptr::null_mut()) },
             ErrorCode::InvalidArgument
@@ -1389,68 +1792,220 @@ mod tests {
         assert!(!crate::error::isola_last_error().is_null());
 
         assert_eq!(
-            unsafe { isola_context_initialize(std::ptr::null_mut(), std::ptr::null()) },
-            ErrorCode::InvalidArgument
+            unsafe { isola_context_initialize(0, c"/x".as_ptr()) },
+            ErrorCode::InvalidHandle
         );
+        let mut sandbox = 0;
         assert_eq!(
-            unsafe { isola_sandbox_create(std::ptr::null(), std::ptr::null_mut()) },
-            ErrorCode::InvalidArgument
+            unsafe { isola_sandbox_create(0, &raw mut sandbox) },
+            ErrorCode::InvalidHandle
         );
+        assert_eq!(isola_sandbox_start(0), ErrorCode::InvalidHandle);
         assert_eq!(
-            unsafe { isola_sandbox_start(std::ptr::null_mut()) },
-            ErrorCode::InvalidArgument
+            unsafe { isola_stream_push(0, std::ptr::null(), 0, 0) },
+            ErrorCode::InvalidHandle
         );
+        assert_eq!(isola_stream_end(0), ErrorCode::InvalidHandle);
+
+        assert_eq!(isola_context_destroy(0), ErrorCode::Ok);
+        assert_eq!(isola_sandbox_destroy(0), ErrorCode::Ok);
+        assert_eq!(isola_http_response_body_close(0), ErrorCode::Ok);
+        assert_eq!(isola_hostcall_response_cancel(0), ErrorCode::Ok);
+    }
+
+    #[test]
+    fn ffi_detects_double_free_and_use_after_close() {
+        let mut ctx = 0;
         assert_eq!(
-            unsafe { isola_stream_push(std::ptr::null(), std::ptr::null(), 0, 0) },
-            ErrorCode::InvalidArgument
+            unsafe { isola_context_create(0, &raw mut ctx) },
+            ErrorCode::Ok
+        );
+        let sandbox = pending_sandbox(ctx);
+
+        assert_eq!(isola_context_destroy(ctx), ErrorCode::Ok);
+        assert_eq!(isola_context_destroy(ctx), ErrorCode::InvalidHandle);
+        // The sandbox keeps its context alive.
+        assert_eq!(
+            unsafe { isola_sandbox_configure_json(sandbox, c"{}".as_ptr()) },
+            ErrorCode::Ok
+        );
+        // A handle of one kind is not accepted as another.
+        assert_eq!(
+            unsafe { isola_context_configure_json(sandbox, c"{}".as_ptr()) },
+            ErrorCode::InvalidHandle
         );
+        assert_eq!(isola_sandbox_destroy(sandbox), ErrorCode::Ok);
+        assert_eq!(isola_sandbox_start(sandbox), ErrorCode::InvalidHandle);
+        let message = unsafe { CStr::from_ptr(crate::error::isola_last_error()) };
         assert_eq!(
-            unsafe { isola_stream_end(std::ptr::null_mut()) },
+            message.to_str().expect("utf-8"),
+            "The sandbox handle was already destroyed"
+        );
+
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        let response = HOSTCALL_RESPONSES.insert(crate::env::HostcallResponse::new(tx));
+        assert_eq!(
+            unsafe { isola_hostcall_response_resolve(response, b"{".as_ptr(), 1) },
             ErrorCode::InvalidArgument
         );
+        assert_eq!(
+            unsafe { isola_hostcall_response_resolve(response, b"1".as_ptr(), 1) },
+            ErrorCode::Ok
+        );
+        assert_eq!(
+            isola_hostcall_response_cancel(response),
+            ErrorCode::InvalidHandle
+        );
+    }
 
-        unsafe {
-            isola_context_destroy(std::ptr::null_mut());
-            isola_sandbox_destroy(std::ptr::null_mut());
-            isola_http_response_body_close(std::ptr::null_mut());
-            isola_hostcall_response_cancel(std::ptr::null_mut());
+    #[test]
+    fn ended_streams_stay_usable_until_taken() {
+        let stream = stream();
+        assert_eq!(
+            unsafe { isola_stream_push(stream, b"1".as_ptr(), 1, 0) },
+            ErrorCode::Ok
+        );
+        assert_eq!(isola_stream_end(stream), ErrorCode::Ok);
+        assert_eq!(isola_stream_end(stream), ErrorCode::InvalidHandle);
+
+        let mut receivers = take_stream_receivers(&[stream]).expect("receiver");
+        let mut receiver = receivers[0].take().expect("receiver");
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_err());
+        assert!(matches!(
+            STREAMS.get(stream),
+            Err(Error::ClosedHandle("stream"))
+        ));
+    }
+
+    #[test]
+    fn json_config_patches_replace_configured_entries() {
+        let mut context = 0;
+        assert_eq!(
+            unsafe { isola_context_create(0, &raw mut context) },
+            ErrorCode::Ok
+        );
+        assert_eq!(
+            unsafe {
+                isola_context_config_set(
+                    context,
+                    c"mount".as_ptr(),
+                    c"{\"host\":\"/a\",\"guest\":\"/a\"}".as_ptr(),
+                )
+            },
+            ErrorCode::Ok
+        );
+        assert_eq!(
+            unsafe {
+                isola_context_configure_json(
+                    context,
+                    cr#"{"cache_dir":null,"max_memory":1024,"mounts":[{"host":"/h","guest":"/g","file_perms":"rw"}],"env":{"A":"1"},"timeout_ms":5000}"#.as_ptr(),
+                )
+            },
+            ErrorCode::Ok
+        );
+        let core = CONTEXTS.get(context).expect("live context");
+        let core = core.lock().expect("context lock");
+        {
+            let config = &core.config;
+            assert!(matches!(config.cache, CacheDirConfig::Disabled));
+            assert_eq!(config.max_memory, Some(1024));
+            assert_eq!(config.mounts.len(), 1);
+            assert_eq!(config.mounts[0].guest, "/g");
+            assert_eq!(
+                config.mounts[0].file_perms,
+                FilePerms::READ | FilePerms::WRITE
+            );
+            assert_eq!(config.env, [("A".to_string(), "1".to_string())]);
+            assert_eq!(config.timeout, Some(Duration::from_secs(5)));
+        }
+        drop(core);
+
+        for invalid in [
+            c"{",
+            cr#"{"timeout_ms":0}"#,
+            cr#"{"mounts":[{"host":"","guest":"/g"}]}"#,
+        ] {
+            assert_eq!(
+                unsafe { isola_context_configure_json(context, invalid.as_ptr()) },
+                ErrorCode::InvalidArgument
+            );
         }
+
+        let sandbox = pending_sandbox(context);
+        assert_eq!(
+            unsafe {
+                isola_sandbox_configure_json(
+                    sandbox,
+                    cr#"{"max_memory":null,"env":{"B":"2"}}"#.as_ptr(),
+                )
+            },
+            ErrorCode::Ok
+        );
+        let core = SANDBOXES.get(sandbox).expect("live sandbox");
+        let core = core.lock().expect("sandbox lock");
+        let SandboxInner::Pending { config } = &core.inner else {
+            panic!("sandbox should still be pending");
+        };
+        assert_eq!(config.max_memory, None);
+        assert_eq!(config.env, [("B".to_string(), "2".to_string())]);
+        assert_eq!(config.timeout, Some(Duration::from_secs(5)));
+        drop(core);
+        assert_eq!(
+            unsafe { isola_sandbox_configure_json(sandbox, cr#"{"timeout_ms":null}"#.as_ptr()) },
+            ErrorCode::Ok
+        );
+        let core = SANDBOXES.get(sandbox).expect("live sandbox");
+        let core = core.lock().expect("sandbox lock");
+        let SandboxInner::Pending { config } = &core.inner else {
+            panic!("sandbox should still be pending");
+        };
+        assert_eq!(config.timeout, None);
+        drop(core);
+        assert_eq!(
+            unsafe { isola_sandbox_configure_json(sandbox, cr#"{"prelude":""}"#.as_ptr()) },
+            ErrorCode::InvalidArgument
+        );
+        assert_eq!(isola_sandbox_destroy(sandbox), ErrorCode::Ok);
+        assert_eq!(isola_context_destroy(context), ErrorCode::Ok);
+    }
+
+    #[test]
+    fn configured_timeout_applies_to_calls_without_one() {
+        let configured = Some(Duration::from_secs(5));
+        assert_eq!(call_timeout(0, configured), configured);
+        assert_eq!(
+            call_timeout(20, configured),
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(call_timeout(0, None), None);
     }
 
     #[test]
     fn ffi_rejects_unknown_stream_format_without_allocating() {
-        let mut stream = std::ptr::dangling_mut();
+        let mut stream = u64::MAX;
         assert_eq!(
             unsafe { isola_stream_create(99, &raw mut stream) },
             ErrorCode::InvalidArgument
         );
-        assert!(stream.is_null());
+        assert_eq!(stream, 0);
     }
 
     #[test]
     fn invalid_value_does_not_consume_stream_receiver() {
-        let context = ContextCore::create_handle(0).expect("create context");
-        let mut sandbox = SandboxHandle {
-            ctx: Arc::clone(&context.0),
-            handler_slot: Arc::new(OnceLock::new()),
-            inner: SandboxInner::Pending {
-                options: SandboxOptions::default(),
-            },
-        };
-        let (sender, receiver) = tokio::sync::mpsc::channel(1);
-        let stream = StreamHandle {
-            format: ValueFormat::Json,
-            sender: std::sync::Mutex::new(Some(sender)),
-            receiver: std::sync::Mutex::new(Some(receiver)),
-        };
+        let mut context = 0;
+        assert_eq!(
+            unsafe { isola_context_create(0, &raw mut context) },
+            ErrorCode::Ok
+        );
+        let sandbox = pending_sandbox(context);
+        let stream = stream();
         let invalid_json = b"{";
         let args = [
             Argument {
                 kind: ISOLA_ARGUMENT_KIND_STREAM,
                 name: std::ptr::null(),
-                value: ArgumentValue {
-                    stream: std::ptr::from_ref(&stream),
-                },
+                value: ArgumentValue { stream },
             },
             Argument {
                 kind: ISOLA_ARGUMENT_KIND_VALUE,
@@ -1469,38 +2024,41 @@ mod tests {
 
         assert_eq!(
             unsafe {
-                isola_sandbox_run(
-                    &raw mut sandbox,
-                    c"main".as_ptr(),
-                    args.as_ptr(),
-                    args.len(),
-                    1000,
-                )
+                isola_sandbox_run(sandbox, c"main".as_ptr(), args.as_ptr(), args.len(), 1000)
             },
             ErrorCode::InvalidArgument
         );
-        assert!(stream.take_receiver().is_ok());
+        assert!(
+            STREAMS
+                .get(stream)
+                .expect("live stream")
+                .take_receiver()
+                .is_ok()
+        );
+        assert_eq!(isola_stream_end(stream), ErrorCode::Ok);
+        assert_eq!(isola_sandbox_destroy(sandbox), ErrorCode::Ok);
+        assert_eq!(isola_context_destroy(context), ErrorCode::Ok);
     }
 
     #[test]
     fn failed_stream_acquisition_restores_other_receivers() {
-        let (first_sender, first_receiver) = tokio::sync::mpsc::channel(1);
-        let first = StreamHandle {
-            format: ValueFormat::Json,
-            sender: std::sync::Mutex::new(Some(first_sender)),
-            receiver: std::sync::Mutex::new(Some(first_receiver)),
-        };
-        let (second_sender, second_receiver) = tokio::sync::mpsc::channel(1);
-        let second = StreamHandle {
-            format: ValueFormat::Json,
-            sender: std::sync::Mutex::new(Some(second_sender)),
-            receiver: std::sync::Mutex::new(Some(second_receiver)),
-        };
-        let held_receiver = second.take_receiver().expect("take second receiver");
-        let streams = [std::ptr::from_ref(&first), std::ptr::from_ref(&second)];
-
-        assert!(take_stream_receivers(&streams).is_err());
-        assert!(first.take_receiver().is_ok());
+        let first = stream();
+        let second = stream();
+        let held_receiver = STREAMS
+            .get(second)
+            .expect("live stream")
+            .take_receiver()
+            .expect("take second receiver");
+
+        assert!(take_stream_receivers(&[first, second]).is_err());
+        assert!(
+            STREAMS
+                .get(first)
+                .expect("live stream")
+                .take_receiver()
+                .is_ok()
+        );
         drop(held_receiver);
+        assert!(take_stream_receivers(&[first, 0]).is_err());
     }
 }
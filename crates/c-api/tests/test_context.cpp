@@ -36,11 +36,11 @@ static std::string runtime_wasm_path() {
 }
 
 TEST_CASE("Context") {
-  isola_context_handle *ctx;
+  isola_context_handle ctx;
   REQUIRE(isola_context_create(0, &ctx) == 0);
   auto path = runtime_wasm_path();
   REQUIRE(isola_context_initialize(ctx, path.c_str()) == 0);
-  isola_sandbox_handle *sandbox;
+  isola_sandbox_handle sandbox;
   REQUIRE(isola_sandbox_create(ctx, &sandbox) == 0);
   callback_outputs outputs;
   isola_sandbox_handler_vtable vtable = {};
@@ -81,7 +81,7 @@ TEST_CASE("Context") {
                                     "        total += value\n"
                                     "    return total\n",
                                     1000) == 0);
-  isola_stream_handle *stream;
+  isola_stream_handle stream;
   REQUIRE(isola_stream_create(ISOLA_ARGUMENT_TYPE_CBOR, &stream) == 0);
   args[0].kind = ISOLA_ARGUMENT_KIND_STREAM;
   args[0].value.stream = stream;
@@ -112,8 +112,11 @@ TEST_CASE("Context") {
   REQUIRE(!outputs.logs.empty());
   REQUIRE(outputs.logs[0].find("hello-log") != std::string::npos);
 
-  isola_sandbox_destroy(sandbox);
-  isola_context_destroy(ctx);
+  REQUIRE(isola_sandbox_destroy(sandbox) == 0);
+  REQUIRE(isola_context_destroy(ctx) == 0);
+  REQUIRE(isola_sandbox_run(sandbox, "main", nullptr, 0, 1000) ==
+          ISOLA_ERROR_CODE_INVALID_HANDLE);
+  REQUIRE(isola_context_destroy(ctx) == ISOLA_ERROR_CODE_INVALID_HANDLE);
 }
 
 // ---------------------------------------------------------------------------
@@ -127,7 +130,7 @@ struct http_test_context {
 };
 
 static void mock_http_handler(const isola_http_request *request,
-                              isola_http_response_body *body, void *user_data) {
+                              isola_http_response_body body, void *user_data) {
   auto *tc = reinterpret_cast<http_test_context *>(user_data);
 
   // Capture the request details for later assertions.
@@ -166,12 +169,12 @@ static void mock_on_event(isola_callback_event event, const uint8_t *data,
 }
 
 TEST_CASE("HTTP mock handler") {
-  isola_context_handle *ctx;
+  isola_context_handle ctx;
   REQUIRE(isola_context_create(0, &ctx) == 0);
   auto path = runtime_wasm_path();
   REQUIRE(isola_context_initialize(ctx, path.c_str()) == 0);
 
-  isola_sandbox_handle *sandbox;
+  isola_sandbox_handle sandbox;
   REQUIRE(isola_sandbox_create(ctx, &sandbox) == 0);
 
   http_test_context tc;
@@ -201,8 +204,8 @@ TEST_CASE("HTTP mock handler") {
   REQUIRE(result.find("hello from mock") != std::string::npos);
   REQUIRE(result.find("200") != std::string::npos);
 
-  isola_sandbox_destroy(sandbox);
-  isola_context_destroy(ctx);
+  REQUIRE(isola_sandbox_destroy(sandbox) == 0);
+  REQUIRE(isola_context_destroy(ctx) == 0);
 }
 
 // ---------------------------------------------------------------------------
@@ -217,7 +220,7 @@ struct hostcall_test_context {
 
 static void mock_hostcall_handler(const char *type, const uint8_t *payload,
                                   size_t payload_len,
-                                  isola_hostcall_response *response,
+                                  isola_hostcall_response response,
                                   void *user_data) {
   auto *tc = reinterpret_cast<hostcall_test_context *>(user_data);
 
@@ -241,12 +244,12 @@ static void hostcall_on_event(isola_callback_event event, const uint8_t *data,
 }
 
 TEST_CASE("Hostcall mock handler") {
-  isola_context_handle *ctx;
+  isola_context_handle ctx;
   REQUIRE(isola_context_create(0, &ctx) == 0);
   auto path = runtime_wasm_path();
   REQUIRE(isola_context_initialize(ctx, path.c_str()) == 0);
 
-  isola_sandbox_handle *sandbox;
+  isola_sandbox_handle sandbox;
   REQUIRE(isola_sandbox_create(ctx, &sandbox) == 0);
 
   hostcall_test_context tc;
@@ -277,6 +280,6 @@ TEST_CASE("Hostcall mock handler") {
   REQUIRE(result.find("key") != std::string::npos);
   REQUIRE(result.find("value") != std::string::npos);
 
-  isola_sandbox_destroy(sandbox);
-  isola_context_destroy(ctx);
+  REQUIRE(isola_sandbox_destroy(sandbox) == 0);
+  REQUIRE(isola_context_destroy(ctx) == 0);
 }
@@ -0,0 +1,128 @@
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// Source of time for a sandbox's WASI clocks.
+///
+/// Set one with [`SandboxOptions::clock`](crate::sandbox::SandboxOptions::clock)
+/// to control what time guest code observes and how long its sleeps take,
+/// for example to make time-dependent scripts deterministic in tests. Without
+/// one, sandboxes use the host's real clocks.
+///
+/// [`ManualClock`] covers the common case of a frozen clock that only moves
+/// when the host advances it or the guest sleeps.
+pub trait ClockProvider: Send + Sync + 'static {
+    /// Current wall-clock time as the duration since the Unix epoch.
+    fn wall_now(&self) -> Duration;
+
+    /// Current monotonic time in nanoseconds since an arbitrary origin.
+    ///
+    /// Must never decrease.
+    fn monotonic_now(&self) -> u64;
+
+    /// Complete once [`monotonic_now`](Self::monotonic_now) has reached
+    /// `deadline`.
+    ///
+    /// Guest sleeps and timeouts wait on this future. An implementation may
+    /// move its clock forward to `deadline` and complete at once.
+    fn wait_until(&self, deadline: u64) -> impl Future<Output = ()> + Send;
+}
+
+impl<T: ClockProvider> ClockProvider for Arc<T> {
+    fn wall_now(&self) -> Duration {
+        (**self).wall_now()
+    }
+
+    fn monotonic_now(&self) -> u64 {
+        (**self).monotonic_now()
+    }
+
+    async fn wait_until(&self, deadline: u64) {
+        (**self).wait_until(deadline).await;
+    }
+}
+
+/// [`ClockProvider`] whose time only moves when the host calls
+/// [`advance`](Self::advance) or the guest sleeps.
+///
+/// Guest sleeps return immediately after moving the clock to the end of the
+/// sleep, so code that waits for an hour finishes at once yet observes the
+/// hour passing. Share the clock through an [`Arc`] to keep a handle for
+/// advancing it.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    wall_start: Duration,
+    elapsed_nanos: AtomicU64,
+}
+
+impl ManualClock {
+    /// Create a clock whose wall time starts at `wall_start` after the Unix
+    /// epoch and whose monotonic time starts at zero.
+    #[must_use]
+    pub const fn new(wall_start: Duration) -> Self {
+        Self {
+            wall_start,
+            elapsed_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Move both clocks forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let _ = self
+            .elapsed_nanos
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |elapsed| {
+                Some(elapsed.saturating_add(nanos))
+            });
+    }
+
+    /// Time the clock has moved since it was created.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_nanos.load(Ordering::Acquire))
+    }
+}
+
+impl ClockProvider for ManualClock {
+    fn wall_now(&self) -> Duration {
+        self.wall_start.saturating_add(self.elapsed())
+    }
+
+    fn monotonic_now(&self) -> u64 {
+        self.elapsed_nanos.load(Ordering::Acquire)
+    }
+
+    async fn wait_until(&self, deadline: u64) {
+        self.elapsed_nanos.fetch_max(deadline, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn manual_clock_moves_only_when_advanced_or_waited_on() {
+        let clock = ManualClock::new(Duration::from_secs(100));
+        assert_eq!(clock.wall_now(), Duration::from_secs(100));
+        assert_eq!(clock.monotonic_now(), 0);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.wall_now(), Duration::from_secs(105));
+        assert_eq!(clock.monotonic_now(), 5_000_000_000);
+
+        clock.wait_until(3_000_000_000).await;
+        assert_eq!(
+            clock.elapsed(),
+            Duration::from_secs(5),
+            "past deadlines keep the time"
+        );
+        clock.wait_until(3_600_000_000_000).await;
+        assert_eq!(clock.elapsed(), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+/// Host policy consulted before guest code touches a file in a mount.
+///
+/// Set one with
+/// [`SandboxOptions::file_policy`](crate::sandbox::SandboxOptions::file_policy)
+/// to log filesystem access or to deny paths dynamically, on top of the
+/// static [`DirPerms`](crate::sandbox::DirPerms) and
+/// [`FilePerms`](crate::sandbox::FilePerms) of each mount. Denied operations
+/// fail in the guest with `EACCES`.
+///
+/// The policy only sees operations the mount permissions already allow.
+/// Renaming or linking an entry checks [`Open`](FileOperation::Open) on its
+/// old path and [`Create`](FileOperation::Create) on its new one, and a
+/// symlink also checks `Open` on its target, so none of them can expose a
+/// denied file under an allowed name.
+pub trait FilePolicy: Send + Sync + 'static {
+    /// Return whether the guest may perform `access`.
+    ///
+    /// This runs synchronously on the sandbox executor and must not block
+    /// for long.
+    fn allows(&self, access: &FileAccess<'_>) -> bool;
+}
+
+impl<T: FilePolicy> FilePolicy for Arc<T> {
+    fn allows(&self, access: &FileAccess<'_>) -> bool {
+        (**self).allows(access)
+    }
+}
+
+/// Filesystem operation checked by a [`FilePolicy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileOperation {
+    /// Open an existing file or directory.
+    Open,
+    /// Create a file, directory, or link, move an entry to a new path, or
+    /// open a file with the create flag.
+    Create,
+    /// List the entries of a directory.
+    ReadDirectory,
+}
+
+/// Guest filesystem access checked by a [`FilePolicy`].
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct FileAccess<'a> {
+    /// What the guest is about to do.
+    pub operation: FileOperation,
+    /// Guest path of the mount the access happens in, such as `/data`.
+    pub mount: &'a str,
+    /// `/`-separated path relative to the mount point, empty for the mount
+    /// point itself.
+    pub path: &'a str,
+}
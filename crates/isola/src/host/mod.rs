@@ -1,9 +1,36 @@
-use std::{future::Future, pin::Pin, sync::Arc};
+mod clock;
+mod entropy;
+mod file_policy;
+#[cfg(feature = "otel")]
+mod otel;
+mod sinks;
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
 use bytes::Bytes;
 use http_body::Frame;
 use parking_lot::Mutex;
 
+#[cfg(feature = "otel")]
+pub use self::otel::OtelOutputSink;
+#[cfg(feature = "serde")]
+pub use self::sinks::JsonLinesWriterSink;
+pub use self::{
+    clock::{ClockProvider, ManualClock},
+    entropy::{EntropySource, SeededEntropy},
+    file_policy::{FileAccess, FileOperation, FilePolicy},
+    sinks::{
+        BoundedCollectSink, ChannelSink, FilterSink, MapSink, OutputEventRef, TeeSink, TracingSink,
+    },
+};
 use crate::{sandbox::CallOutput, value::Value};
 
 /// Thread-safe error returned by host callbacks and output sinks.
@@ -137,11 +164,101 @@ pub enum OutputEvent {
     },
 }
 
+tokio::task_local! {
+    static CALL_ID: u64;
+}
+
+/// Return the call id of the guest operation the current task serves.
+///
+/// Set while a sandbox operation runs, including inside [`Host`] methods,
+/// [`OutputTarget::synchronous`] callbacks, and channel sends made on its
+/// behalf, so hostcalls, HTTP requests, and log records of concurrent
+/// sandboxes sharing one host or sink can be told apart. The id matches
+/// [`EventMeta::call_id`] and
+/// [`Sandbox::last_call_id`](crate::sandbox::Sandbox::last_call_id).
+#[must_use]
+pub fn current_call_id() -> Option<u64> {
+    CALL_ID.try_with(|call_id| *call_id).ok()
+}
+
+/// Run `future` with [`current_call_id`] returning `call_id`.
+pub(crate) async fn with_call_id<F: Future>(call_id: Option<u64>, future: F) -> F::Output {
+    match call_id {
+        Some(call_id) => CALL_ID.scope(call_id, future).await,
+        None => future.await,
+    }
+}
+
+/// Position of one [`OutputSink`] callback within a guest operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct EventMeta {
+    /// Identifier of the guest operation, unique within the process. See
+    /// [`current_call_id`].
+    pub call_id: u64,
+    /// Position of the event within the operation, starting at 0.
+    pub sequence: u64,
+    /// Resources the operation has used up to the moment the guest returned
+    /// its final value. Set only for [`OutputSink::on_complete`].
+    pub exec_stats: Option<ExecStats>,
+}
+
+/// Resources used by one guest operation.
+///
+/// Guest time covers WebAssembly execution, including time the guest spends
+/// waiting to be rescheduled after yielding to the executor. Everything else,
+/// such as [`Host`] methods, HTTP requests, and output delivery, is host time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ExecStats {
+    /// Time since the operation started.
+    pub wall_time: Duration,
+    /// Part of [`wall_time`](Self::wall_time) spent running guest code.
+    pub guest_time: Duration,
+    /// Epoch ticks that elapsed while guest code was running. Ticks advance
+    /// only when the engine's epoch is incremented, so this stays 0 for
+    /// operations too short to be preempted.
+    pub guest_ticks: u64,
+    /// Largest linear memory size in bytes reached during the operation.
+    pub peak_memory: usize,
+    /// Hostcalls the guest made, including denied ones and ones routed to
+    /// plugins.
+    pub hostcalls: u64,
+    /// Outgoing HTTP requests the guest made, not counting denied ones.
+    pub http_requests: u64,
+    /// Bytes of encoded output the guest emitted, including its return value.
+    pub output_bytes: u64,
+}
+
+impl ExecStats {
+    /// Return the part of [`wall_time`](Self::wall_time) spent outside guest
+    /// code.
+    #[must_use]
+    pub const fn host_time(&self) -> Duration {
+        self.wall_time.saturating_sub(self.guest_time)
+    }
+}
+
 /// Receives values and logs produced by one guest operation.
 ///
 /// The runtime awaits each callback. Returning an error aborts the current
 /// operation and surfaces the error from the corresponding
 /// [`Sandbox`](crate::sandbox::Sandbox) method.
+///
+/// # Ordering
+///
+/// Every callback receives an [`EventMeta`]. Its `call_id` is shared by all
+/// events of one `eval_*` or `call*` operation, and `sequence` numbers the
+/// events of that operation without gaps in the order the guest produced
+/// them. [`OutputSink::on_complete`] always carries the highest sequence
+/// number.
+///
+/// Items are delivered one at a time. Log records for stdout and stderr are
+/// delivered from the guest's write path and may still be in flight when
+/// the next item callback starts, so a sink that forwards events to other
+/// tasks should order them by `sequence` rather than by arrival. Text a guest
+/// prints before yielding a value is flushed first and therefore has a lower
+/// sequence number than the value.
 pub trait OutputSink: Send + Sync + 'static {
     /// Receive one value yielded or explicitly emitted by guest code.
     ///
@@ -150,6 +267,7 @@ pub trait OutputSink: Send + Sync + 'static {
     /// Returning an error aborts the current guest operation.
     fn on_item(
         &self,
+        meta: EventMeta,
         value: Value,
     ) -> impl Future<Output = core::result::Result<(), BoxError>> + Send;
 
@@ -164,6 +282,7 @@ pub trait OutputSink: Send + Sync + 'static {
     /// Returning an error makes the current guest operation fail.
     fn on_complete(
         &self,
+        meta: EventMeta,
         value: Option<Value>,
     ) -> impl Future<Output = core::result::Result<(), BoxError>> + Send;
 
@@ -177,6 +296,7 @@ pub trait OutputSink: Send + Sync + 'static {
     /// fails.
     fn on_log(
         &self,
+        _meta: EventMeta,
         _level: LogLevel,
         _log_context: LogContext<'_>,
         _message: &str,
@@ -189,12 +309,13 @@ type BoxSinkFuture<'a> =
     Pin<Box<dyn Future<Output = core::result::Result<(), BoxError>> + Send + 'a>>;
 
 trait ErasedOutputSink: Send + Sync + 'static {
-    fn on_item(&self, value: Value) -> BoxSinkFuture<'_>;
+    fn on_item(&self, meta: EventMeta, value: Value) -> BoxSinkFuture<'_>;
 
-    fn on_complete(&self, value: Option<Value>) -> BoxSinkFuture<'_>;
+    fn on_complete(&self, meta: EventMeta, value: Option<Value>) -> BoxSinkFuture<'_>;
 
     fn on_log<'a>(
         &'a self,
+        meta: EventMeta,
         level: LogLevel,
         log_context: LogContext<'a>,
         message: &'a str,
@@ -202,21 +323,22 @@ trait ErasedOutputSink: Send + Sync + 'static {
 }
 
 impl<T: OutputSink> ErasedOutputSink for T {
-    fn on_item(&self, value: Value) -> BoxSinkFuture<'_> {
-        Box::pin(OutputSink::on_item(self, value))
+    fn on_item(&self, meta: EventMeta, value: Value) -> BoxSinkFuture<'_> {
+        Box::pin(OutputSink::on_item(self, meta, value))
     }
 
-    fn on_complete(&self, value: Option<Value>) -> BoxSinkFuture<'_> {
-        Box::pin(OutputSink::on_complete(self, value))
+    fn on_complete(&self, meta: EventMeta, value: Option<Value>) -> BoxSinkFuture<'_> {
+        Box::pin(OutputSink::on_complete(self, meta, value))
     }
 
     fn on_log<'a>(
         &'a self,
+        meta: EventMeta,
         level: LogLevel,
         log_context: LogContext<'a>,
         message: &'a str,
     ) -> BoxSinkFuture<'a> {
-        Box::pin(OutputSink::on_log(self, level, log_context, message))
+        Box::pin(OutputSink::on_log(self, meta, level, log_context, message))
     }
 }
 
@@ -227,6 +349,9 @@ type SyncOutputCallback =
 enum OutputTargetKind {
     Discard,
     Capture(Arc<Mutex<CallOutput>>),
+    /// One output per completed call, followed by the one being collected,
+    /// and the target log records are forwarded to.
+    CaptureBatch(Arc<Mutex<Vec<CallOutput>>>, Box<OutputTarget>),
     Bounded(tokio::sync::mpsc::Sender<OutputEvent>),
     Unbounded(tokio::sync::mpsc::UnboundedSender<OutputEvent>),
     Sync(Arc<SyncOutputCallback>),
@@ -241,14 +366,24 @@ enum OutputTargetKind {
 #[derive(Clone)]
 pub struct OutputTarget {
     kind: OutputTargetKind,
+    scope: Option<Arc<CallScope>>,
+}
+
+/// Event numbering shared by every clone of a target bound to one operation.
+struct CallScope {
+    call_id: u64,
+    next_sequence: AtomicU64,
 }
 
+static NEXT_CALL_ID: AtomicU64 = AtomicU64::new(1);
+
 impl OutputTarget {
     /// Construct a target that discards all output.
     #[must_use]
     pub const fn discard() -> Self {
         Self {
             kind: OutputTargetKind::Discard,
+            scope: None,
         }
     }
 
@@ -260,6 +395,7 @@ impl OutputTarget {
     pub const fn bounded(sender: tokio::sync::mpsc::Sender<OutputEvent>) -> Self {
         Self {
             kind: OutputTargetKind::Bounded(sender),
+            scope: None,
         }
     }
 
@@ -271,6 +407,7 @@ impl OutputTarget {
     pub const fn unbounded(sender: tokio::sync::mpsc::UnboundedSender<OutputEvent>) -> Self {
         Self {
             kind: OutputTargetKind::Unbounded(sender),
+            scope: None,
         }
     }
 
@@ -283,6 +420,7 @@ impl OutputTarget {
     ) -> Self {
         Self {
             kind: OutputTargetKind::Sync(Arc::new(callback)),
+            scope: None,
         }
     }
 
@@ -291,15 +429,62 @@ impl OutputTarget {
     pub fn asynchronous<T: OutputSink>(sink: Arc<T>) -> Self {
         Self {
             kind: OutputTargetKind::Async(sink),
+            scope: None,
         }
     }
 
     pub(crate) const fn capture(output: Arc<Mutex<CallOutput>>) -> Self {
         Self {
             kind: OutputTargetKind::Capture(output),
+            scope: None,
+        }
+    }
+
+    /// Construct a target collecting the output of a batch of calls, each
+    /// ended by a completion, into `outputs`, and forwarding log records to
+    /// `logs`.
+    ///
+    /// `outputs` must start with one empty entry; the last entry is always
+    /// the call being collected.
+    pub(crate) fn capture_batch(outputs: Arc<Mutex<Vec<CallOutput>>>, logs: Self) -> Self {
+        Self {
+            kind: OutputTargetKind::CaptureBatch(outputs, Box::new(logs)),
+            scope: None,
         }
     }
 
+    /// Bind a clone of this target to a new guest operation with its own
+    /// call id and event sequence.
+    pub(crate) fn for_call(self) -> Self {
+        Self {
+            kind: self.kind,
+            scope: Some(Arc::new(CallScope {
+                call_id: NEXT_CALL_ID.fetch_add(1, Ordering::Relaxed),
+                next_sequence: AtomicU64::new(0),
+            })),
+        }
+    }
+
+    /// Return the call id of the operation this target is bound to.
+    pub(crate) fn call_id(&self) -> Option<u64> {
+        self.scope.as_ref().map(|scope| scope.call_id)
+    }
+
+    fn next_meta(&self) -> EventMeta {
+        self.scope.as_ref().map_or(
+            EventMeta {
+                call_id: 0,
+                sequence: 0,
+                exec_stats: None,
+            },
+            |scope| EventMeta {
+                call_id: scope.call_id,
+                sequence: scope.next_sequence.fetch_add(1, Ordering::Relaxed),
+                exec_stats: None,
+            },
+        )
+    }
+
     pub(crate) async fn on_item(&self, value: Value) -> core::result::Result<(), BoxError> {
         match &self.kind {
             OutputTargetKind::Discard => Ok(()),
@@ -307,6 +492,12 @@ impl OutputTarget {
                 output.lock().items.push(value);
                 Ok(())
             }
+            OutputTargetKind::CaptureBatch(outputs, _) => {
+                if let Some(output) = outputs.lock().last_mut() {
+                    output.items.push(value);
+                }
+                Ok(())
+            }
             OutputTargetKind::Bounded(sender) => sender
                 .send(OutputEvent::Item(value))
                 .await
@@ -315,13 +506,14 @@ impl OutputTarget {
                 .send(OutputEvent::Item(value))
                 .map_err(|_| output_channel_closed()),
             OutputTargetKind::Sync(callback) => callback(OutputEvent::Item(value)),
-            OutputTargetKind::Async(sink) => sink.on_item(value).await,
+            OutputTargetKind::Async(sink) => sink.on_item(self.next_meta(), value).await,
         }
     }
 
     pub(crate) async fn on_complete(
         &self,
         value: Option<Value>,
+        exec_stats: ExecStats,
     ) -> core::result::Result<(), BoxError> {
         match &self.kind {
             OutputTargetKind::Discard => Ok(()),
@@ -329,6 +521,16 @@ impl OutputTarget {
                 output.lock().result = value;
                 Ok(())
             }
+            OutputTargetKind::CaptureBatch(outputs, _) => {
+                let mut outputs = outputs.lock();
+                if let Some(output) = outputs.last_mut() {
+                    output.result = value;
+                    output.exec_stats = exec_stats;
+                }
+                outputs.push(CallOutput::default());
+                drop(outputs);
+                Ok(())
+            }
             OutputTargetKind::Bounded(sender) => sender
                 .send(OutputEvent::Complete(value))
                 .await
@@ -337,7 +539,13 @@ impl OutputTarget {
                 .send(OutputEvent::Complete(value))
                 .map_err(|_| output_channel_closed()),
             OutputTargetKind::Sync(callback) => callback(OutputEvent::Complete(value)),
-            OutputTargetKind::Async(sink) => sink.on_complete(value).await,
+            OutputTargetKind::Async(sink) => {
+                let meta = EventMeta {
+                    exec_stats: Some(exec_stats),
+                    ..self.next_meta()
+                };
+                sink.on_complete(meta, value).await
+            }
         }
     }
 
@@ -349,6 +557,14 @@ impl OutputTarget {
     ) -> core::result::Result<(), BoxError> {
         match &self.kind {
             OutputTargetKind::Discard | OutputTargetKind::Capture(_) => Ok(()),
+            OutputTargetKind::CaptureBatch(_, logs) => {
+                // Log events share the batch's call id and numbering.
+                let logs = Self {
+                    kind: logs.kind.clone(),
+                    scope: self.scope.clone(),
+                };
+                Box::pin(logs.on_log(level, context, message)).await
+            }
             OutputTargetKind::Bounded(sender) => sender
                 .send(output_log_event(level, context, message))
                 .await
@@ -357,7 +573,9 @@ impl OutputTarget {
                 .send(output_log_event(level, context, message))
                 .map_err(|_| output_channel_closed()),
             OutputTargetKind::Sync(callback) => callback(output_log_event(level, context, message)),
-            OutputTargetKind::Async(sink) => sink.on_log(level, context, message).await,
+            OutputTargetKind::Async(sink) => {
+                sink.on_log(self.next_meta(), level, context, message).await
+            }
         }
     }
 }
@@ -476,6 +694,33 @@ pub trait Host: Send + Sync + 'static {
             )
         }
     }
+
+    /// Observe a guest linear memory growth request.
+    ///
+    /// Called from the store's resource limiter with the current size, the
+    /// requested size, and the sandbox memory limit, all in bytes, including
+    /// the initial allocation at instantiation. Return `false` to deny the
+    /// growth; the guest then sees an allocation failure and the resulting
+    /// error is classified as
+    /// [`ErrorKind::MemoryLimit`](crate::sandbox::ErrorKind::MemoryLimit).
+    /// Growth beyond `limit` is denied regardless of the return value.
+    ///
+    /// This runs synchronously on the sandbox executor and must not block.
+    /// The default implementation allows all growth within the limit.
+    fn on_memory_growth(&self, current: usize, requested: usize, limit: usize) -> bool {
+        let _ = (current, requested, limit);
+        true
+    }
+
+    /// Observe a [`SharedSandbox`](crate::sandbox::SharedSandbox) dropping
+    /// its sandbox after it sat unused for `idle`, its
+    /// [`idle_timeout`](crate::sandbox::SandboxOptions::idle_timeout).
+    ///
+    /// Called once the sandbox has been dropped, from a background task. The
+    /// default implementation does nothing.
+    fn on_idle_dispose(&self, idle: Duration) {
+        let _ = idle;
+    }
 }
 
 impl<T: Host + ?Sized> Host for Arc<T> {
@@ -490,11 +735,19 @@ impl<T: Host + ?Sized> Host for Arc<T> {
     async fn http_request(&self, req: HttpRequest) -> core::result::Result<HttpResponse, BoxError> {
         (**self).http_request(req).await
     }
+
+    fn on_memory_growth(&self, current: usize, requested: usize, limit: usize) -> bool {
+        (**self).on_memory_growth(current, requested, limit)
+    }
+
+    fn on_idle_dispose(&self, idle: Duration) {
+        (**self).on_idle_dispose(idle);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::atomic::AtomicUsize;
 
     use super::*;
 
@@ -508,7 +761,10 @@ mod tests {
         let target = OutputTarget::bounded(sender);
 
         target.on_item(value()).await.unwrap();
-        target.on_complete(None).await.unwrap();
+        target
+            .on_complete(None, ExecStats::default())
+            .await
+            .unwrap();
         target
             .on_log(LogLevel::Info, LogContext::Other("runtime"), "message")
             .await
@@ -550,47 +806,142 @@ mod tests {
             Ok(())
         });
         target.on_item(value()).await.unwrap();
-        target.on_complete(None).await.unwrap();
+        target
+            .on_complete(None, ExecStats::default())
+            .await
+            .unwrap();
         assert_eq!(event_count.load(Ordering::Relaxed), 2);
 
         let output = Arc::new(Mutex::new(CallOutput::default()));
         let target = OutputTarget::capture(Arc::clone(&output));
         target.on_item(value()).await.unwrap();
-        target.on_complete(Some(value())).await.unwrap();
+        target
+            .on_complete(Some(value()), ExecStats::default())
+            .await
+            .unwrap();
         let output = output.lock();
         assert_eq!(output.items.len(), 1);
         assert!(output.result.is_some());
         drop(output);
     }
 
-    struct CountingAsyncSink(AtomicUsize);
+    #[tokio::test]
+    async fn batch_capture_forwards_logs() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let outputs = Arc::new(Mutex::new(vec![CallOutput::default()]));
+        let target =
+            OutputTarget::capture_batch(Arc::clone(&outputs), OutputTarget::unbounded(sender))
+                .for_call();
 
-    impl OutputSink for CountingAsyncSink {
+        target.on_item(value()).await.unwrap();
+        target
+            .on_log(LogLevel::Info, LogContext::Other("runtime"), "first")
+            .await
+            .unwrap();
+        target
+            .on_complete(Some(value()), ExecStats::default())
+            .await
+            .unwrap();
+
+        assert_eq!(outputs.lock().len(), 2);
+        assert_eq!(outputs.lock()[0].items.len(), 1);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(OutputEvent::Log { message, .. }) if message == "first"
+        ));
+        assert!(receiver.try_recv().is_err(), "only logs are forwarded");
+    }
+
+    struct RecordingAsyncSink(Mutex<Vec<EventMeta>>);
+
+    impl OutputSink for RecordingAsyncSink {
         fn on_item(
             &self,
+            meta: EventMeta,
             _value: Value,
         ) -> impl Future<Output = core::result::Result<(), BoxError>> + Send {
-            self.0.fetch_add(1, Ordering::Relaxed);
+            self.0.lock().push(meta);
             std::future::ready(Ok(()))
         }
 
         fn on_complete(
             &self,
+            meta: EventMeta,
             _value: Option<Value>,
         ) -> impl Future<Output = core::result::Result<(), BoxError>> + Send {
-            self.0.fetch_add(1, Ordering::Relaxed);
+            self.0.lock().push(meta);
+            std::future::ready(Ok(()))
+        }
+
+        fn on_log(
+            &self,
+            meta: EventMeta,
+            _level: LogLevel,
+            _log_context: LogContext<'_>,
+            _message: &str,
+        ) -> impl Future<Output = core::result::Result<(), BoxError>> + Send {
+            self.0.lock().push(meta);
             std::future::ready(Ok(()))
         }
     }
 
     #[tokio::test]
     async fn asynchronous_target_uses_private_adapter() {
-        let sink = Arc::new(CountingAsyncSink(AtomicUsize::new(0)));
+        let sink = Arc::new(RecordingAsyncSink(Mutex::new(Vec::new())));
         let target = OutputTarget::from(Arc::clone(&sink));
 
         target.on_item(value()).await.unwrap();
-        target.on_complete(None).await.unwrap();
+        target
+            .on_complete(None, ExecStats::default())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.0.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn call_id_is_scoped_to_the_operation() {
+        assert_eq!(current_call_id(), None);
+        let seen = with_call_id(Some(7), async { current_call_id() }).await;
+        assert_eq!(seen, Some(7));
+        assert_eq!(with_call_id(None, async { current_call_id() }).await, None);
+    }
+
+    #[tokio::test]
+    async fn call_scoped_targets_number_events_in_order() {
+        let sink = Arc::new(RecordingAsyncSink(Mutex::new(Vec::new())));
+        let target = OutputTarget::from(Arc::clone(&sink));
+        let first = target.clone().for_call();
+        let second = target.for_call();
 
-        assert_eq!(sink.0.load(Ordering::Relaxed), 2);
+        first.on_item(value()).await.unwrap();
+        first
+            .clone()
+            .on_log(LogLevel::Stdout, LogContext::Stdout, "line")
+            .await
+            .unwrap();
+        second.on_item(value()).await.unwrap();
+        let stats = ExecStats {
+            wall_time: Duration::from_millis(3),
+            guest_time: Duration::from_millis(2),
+            hostcalls: 1,
+            output_bytes: 8,
+            ..ExecStats::default()
+        };
+        first.on_complete(None, stats).await.unwrap();
+
+        let metas = sink.0.lock().clone();
+        let call_id = metas[0].call_id;
+        assert_ne!(metas[2].call_id, call_id);
+        assert_eq!(metas[2].sequence, 0);
+        let sequences: Vec<_> = metas
+            .iter()
+            .filter(|meta| meta.call_id == call_id)
+            .map(|meta| meta.sequence)
+            .collect();
+        assert_eq!(sequences, [0, 1, 2]);
+        assert_eq!(metas[0].exec_stats, None);
+        assert_eq!(metas[3].exec_stats, Some(stats));
+        assert_eq!(stats.host_time(), Duration::from_millis(1));
     }
 }
@@ -0,0 +1,269 @@
+use std::{future::Future, time::SystemTime};
+
+use opentelemetry::{
+    Context, KeyValue,
+    logs::{AnyValue, LogRecord as _, Logger, Severity},
+    trace::TraceContextExt as _,
+};
+
+use super::{BoxError, EventMeta, LogContext, LogLevel, OutputSink};
+use crate::value::Value;
+
+const ITEM_EVENT: &str = "isola.item";
+const COMPLETE_EVENT: &str = "isola.complete";
+const LOG_CONTEXT_ATTRIBUTE: &str = "isola.log.context";
+const VALUE_SIZE_ATTRIBUTE: &str = "isola.value.size";
+
+/// [`OutputSink`] that bridges guest output into OpenTelemetry.
+///
+/// Guest log records are emitted through the wrapped [`Logger`] and carry the
+/// trace context of the span that was active when the sink was created. Items
+/// and the completion value are recorded as events on that same span, so they
+/// appear alongside the host's own request instrumentation.
+///
+/// Available with the `otel` feature.
+pub struct OtelOutputSink<L> {
+    logger: L,
+    context: Context,
+}
+
+impl<L: Logger> OtelOutputSink<L> {
+    /// Create a sink bound to the currently active OpenTelemetry context.
+    #[must_use]
+    pub fn new(logger: L) -> Self {
+        Self::with_context(logger, Context::current())
+    }
+
+    /// Create a sink bound to an explicit OpenTelemetry context.
+    ///
+    /// Use this when the sink is constructed outside the span that should own
+    /// the guest output.
+    #[must_use]
+    pub const fn with_context(logger: L, context: Context) -> Self {
+        Self { logger, context }
+    }
+
+    fn emit_log(&self, level: LogLevel, log_context: LogContext<'_>, message: &str) {
+        let severity = severity(level);
+        if !self.logger.event_enabled(severity, "isola", None) {
+            return;
+        }
+
+        let mut record = self.logger.create_log_record();
+        let now = SystemTime::now();
+        record.set_timestamp(now);
+        record.set_observed_timestamp(now);
+        record.set_target("isola");
+        record.set_severity_number(severity);
+        record.set_severity_text(level.as_str());
+        record.set_body(AnyValue::from(message.to_owned()));
+        record.add_attribute(
+            LOG_CONTEXT_ATTRIBUTE,
+            match log_context {
+                LogContext::Stdout => LogLevel::Stdout.as_str().to_owned(),
+                LogContext::Stderr => LogLevel::Stderr.as_str().to_owned(),
+                LogContext::Other(context) => context.to_owned(),
+            },
+        );
+
+        let span = self.context.span();
+        let span_context = span.span_context();
+        if span_context.is_valid() {
+            record.set_trace_context(
+                span_context.trace_id(),
+                span_context.span_id(),
+                Some(span_context.trace_flags()),
+            );
+        }
+        self.logger.emit(record);
+    }
+
+    fn add_event(&self, name: &'static str, value: Option<&Value>) {
+        let attributes = value.map_or_else(Vec::new, |value| {
+            vec![KeyValue::new(
+                VALUE_SIZE_ATTRIBUTE,
+                i64::try_from(value.as_cbor().len()).unwrap_or(i64::MAX),
+            )]
+        });
+        self.context.span().add_event(name, attributes);
+    }
+}
+
+impl<L> OutputSink for OtelOutputSink<L>
+where
+    L: Logger + Send + Sync + 'static,
+{
+    fn on_item(
+        &self,
+        _meta: EventMeta,
+        value: Value,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        self.add_event(ITEM_EVENT, Some(&value));
+        std::future::ready(Ok(()))
+    }
+
+    fn on_complete(
+        &self,
+        _meta: EventMeta,
+        value: Option<Value>,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        self.add_event(COMPLETE_EVENT, value.as_ref());
+        std::future::ready(Ok(()))
+    }
+
+    fn on_log(
+        &self,
+        _meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        self.emit_log(level, log_context, message);
+        std::future::ready(Ok(()))
+    }
+}
+
+const fn severity(level: LogLevel) -> Severity {
+    match level {
+        LogLevel::Trace => Severity::Trace,
+        LogLevel::Debug => Severity::Debug,
+        LogLevel::Info | LogLevel::Stdout => Severity::Info,
+        LogLevel::Warn => Severity::Warn,
+        LogLevel::Error | LogLevel::Stderr => Severity::Error,
+        LogLevel::Critical => Severity::Fatal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, sync::Arc};
+
+    use opentelemetry::{
+        Key,
+        trace::{SpanContext, SpanId, TraceFlags, TraceId, TraceState},
+    };
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordedLog {
+        severity: Option<Severity>,
+        body: Option<AnyValue>,
+        context: Option<AnyValue>,
+        trace: Option<(TraceId, SpanId)>,
+    }
+
+    impl opentelemetry::logs::LogRecord for RecordedLog {
+        fn set_event_name(&mut self, _name: &'static str) {}
+
+        fn set_target<T>(&mut self, _target: T)
+        where
+            T: Into<Cow<'static, str>>,
+        {
+        }
+
+        fn set_timestamp(&mut self, _timestamp: SystemTime) {}
+
+        fn set_observed_timestamp(&mut self, _timestamp: SystemTime) {}
+
+        fn set_severity_text(&mut self, _text: &'static str) {}
+
+        fn set_severity_number(&mut self, number: Severity) {
+            self.severity = Some(number);
+        }
+
+        fn set_body(&mut self, body: AnyValue) {
+            self.body = Some(body);
+        }
+
+        fn add_attributes<I, K, V>(&mut self, attributes: I)
+        where
+            I: IntoIterator<Item = (K, V)>,
+            K: Into<Key>,
+            V: Into<AnyValue>,
+        {
+            for (key, value) in attributes {
+                self.add_attribute(key, value);
+            }
+        }
+
+        fn add_attribute<K, V>(&mut self, key: K, value: V)
+        where
+            K: Into<Key>,
+            V: Into<AnyValue>,
+        {
+            if key.into().as_str() == LOG_CONTEXT_ATTRIBUTE {
+                self.context = Some(value.into());
+            }
+        }
+
+        fn set_trace_context(
+            &mut self,
+            trace_id: TraceId,
+            span_id: SpanId,
+            _trace_flags: Option<TraceFlags>,
+        ) {
+            self.trace = Some((trace_id, span_id));
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingLogger(Arc<Mutex<Vec<RecordedLog>>>);
+
+    impl Logger for RecordingLogger {
+        type LogRecord = RecordedLog;
+
+        fn create_log_record(&self) -> Self::LogRecord {
+            RecordedLog::default()
+        }
+
+        fn emit(&self, record: Self::LogRecord) {
+            self.0.lock().push(record);
+        }
+
+        fn event_enabled(&self, _level: Severity, _target: &str, _name: Option<&str>) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn guest_logs_become_log_records_with_span_context() {
+        let span_context = SpanContext::new(
+            TraceId::from(7),
+            SpanId::from(9),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        );
+        let logger = RecordingLogger::default();
+        let sink = OtelOutputSink::with_context(
+            logger.clone(),
+            Context::new().with_remote_span_context(span_context),
+        );
+
+        let meta = |sequence| EventMeta {
+            call_id: 1,
+            sequence,
+            exec_stats: None,
+        };
+        sink.on_log(meta(0), LogLevel::Stderr, LogContext::Stderr, "boom")
+            .await
+            .unwrap();
+        sink.on_item(meta(1), Value::from_cbor(vec![0x01]))
+            .await
+            .unwrap();
+        sink.on_complete(meta(2), None).await.unwrap();
+
+        let records = logger.0.lock();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].severity, Some(Severity::Error));
+        assert_eq!(records[0].body, Some(AnyValue::from("boom".to_owned())));
+        assert_eq!(
+            records[0].context,
+            Some(AnyValue::from("stderr".to_owned()))
+        );
+        assert_eq!(records[0].trace, Some((TraceId::from(7), SpanId::from(9))));
+        drop(records);
+    }
+}
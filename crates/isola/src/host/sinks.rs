@@ -0,0 +1,741 @@
+use std::{future::Future, sync::Arc};
+
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+
+use super::{BoxError, EventMeta, LogContext, LogLevel, OutputEvent, OutputSink};
+use crate::value::Value;
+
+impl<T: OutputSink> OutputSink for Arc<T> {
+    fn on_item(
+        &self,
+        meta: EventMeta,
+        value: Value,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        (**self).on_item(meta, value)
+    }
+
+    fn on_complete(
+        &self,
+        meta: EventMeta,
+        value: Option<Value>,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        (**self).on_complete(meta, value)
+    }
+
+    fn on_log(
+        &self,
+        meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        (**self).on_log(meta, level, log_context, message)
+    }
+}
+
+/// Borrowed view of one sink callback, passed to [`FilterSink`] predicates.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum OutputEventRef<'a> {
+    /// A value yielded or explicitly emitted by guest code.
+    Item(&'a Value),
+    /// The final return value after guest execution completes.
+    Complete(Option<&'a Value>),
+    /// One guest log record.
+    Log {
+        /// Severity or output channel.
+        level: LogLevel,
+        /// Source context supplied by the guest runtime.
+        context: LogContext<'a>,
+        /// Log message text.
+        message: &'a str,
+    },
+}
+
+/// [`OutputSink`] that delivers every event to two sinks in turn.
+///
+/// `first` receives each event before `second`; an error from either aborts
+/// the guest operation. Nest tees to fan out to more sinks, and wrap a sink
+/// in an [`Arc`] to keep a handle to it, for example to read collected
+/// output after the call.
+#[derive(Debug, Clone)]
+pub struct TeeSink<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: OutputSink, B: OutputSink> TeeSink<A, B> {
+    /// Create a sink that forwards to `first`, then `second`.
+    #[must_use]
+    pub const fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: OutputSink, B: OutputSink> OutputSink for TeeSink<A, B> {
+    async fn on_item(&self, meta: EventMeta, value: Value) -> Result<(), BoxError> {
+        self.first.on_item(meta, value.clone()).await?;
+        self.second.on_item(meta, value).await
+    }
+
+    async fn on_complete(&self, meta: EventMeta, value: Option<Value>) -> Result<(), BoxError> {
+        self.first.on_complete(meta, value.clone()).await?;
+        self.second.on_complete(meta, value).await
+    }
+
+    async fn on_log(
+        &self,
+        meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> Result<(), BoxError> {
+        self.first.on_log(meta, level, log_context, message).await?;
+        self.second.on_log(meta, level, log_context, message).await
+    }
+}
+
+/// [`OutputSink`] that forwards only the events accepted by a predicate.
+///
+/// Dropped events keep their sequence numbers, so the inner sink sees gaps
+/// in [`EventMeta::sequence`]. Rejecting [`OutputEventRef::Complete`] means
+/// the inner sink is never told that the operation finished.
+#[derive(Debug, Clone)]
+pub struct FilterSink<S, F> {
+    inner: S,
+    predicate: F,
+}
+
+impl<S, F> FilterSink<S, F>
+where
+    S: OutputSink,
+    F: Fn(OutputEventRef<'_>) -> bool + Send + Sync + 'static,
+{
+    /// Create a sink that forwards events to `inner` when `predicate`
+    /// returns `true`.
+    #[must_use]
+    pub const fn new(inner: S, predicate: F) -> Self {
+        Self { inner, predicate }
+    }
+}
+
+impl<S, F> OutputSink for FilterSink<S, F>
+where
+    S: OutputSink,
+    F: Fn(OutputEventRef<'_>) -> bool + Send + Sync + 'static,
+{
+    async fn on_item(&self, meta: EventMeta, value: Value) -> Result<(), BoxError> {
+        if (self.predicate)(OutputEventRef::Item(&value)) {
+            self.inner.on_item(meta, value).await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn on_complete(&self, meta: EventMeta, value: Option<Value>) -> Result<(), BoxError> {
+        if (self.predicate)(OutputEventRef::Complete(value.as_ref())) {
+            self.inner.on_complete(meta, value).await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn on_log(
+        &self,
+        meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> Result<(), BoxError> {
+        if (self.predicate)(OutputEventRef::Log {
+            level,
+            context: log_context,
+            message,
+        }) {
+            self.inner.on_log(meta, level, log_context, message).await
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// [`OutputSink`] that transforms item and completion values before
+/// forwarding them.
+///
+/// Log records pass through unchanged. An error returned by the mapping
+/// function aborts the guest operation like an error from the inner sink.
+#[derive(Debug, Clone)]
+pub struct MapSink<S, F> {
+    inner: S,
+    map: F,
+}
+
+impl<S, F> MapSink<S, F>
+where
+    S: OutputSink,
+    F: Fn(Value) -> Result<Value, BoxError> + Send + Sync + 'static,
+{
+    /// Create a sink that applies `map` to every item and to the final value
+    /// before forwarding them to `inner`.
+    #[must_use]
+    pub const fn new(inner: S, map: F) -> Self {
+        Self { inner, map }
+    }
+}
+
+impl<S, F> OutputSink for MapSink<S, F>
+where
+    S: OutputSink,
+    F: Fn(Value) -> Result<Value, BoxError> + Send + Sync + 'static,
+{
+    async fn on_item(&self, meta: EventMeta, value: Value) -> Result<(), BoxError> {
+        let value = (self.map)(value)?;
+        self.inner.on_item(meta, value).await
+    }
+
+    async fn on_complete(&self, meta: EventMeta, value: Option<Value>) -> Result<(), BoxError> {
+        let value = value.map(&self.map).transpose()?;
+        self.inner.on_complete(meta, value).await
+    }
+
+    fn on_log(
+        &self,
+        meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        self.inner.on_log(meta, level, log_context, message)
+    }
+}
+
+/// [`OutputSink`] that sends every event with its [`EventMeta`] into a
+/// bounded Tokio channel.
+///
+/// Unlike [`OutputTarget::bounded`](super::OutputTarget::bounded), receivers
+/// see call ids, sequence numbers, and the [`ExecStats`](super::ExecStats)
+/// of the completion. Delivery waits for channel capacity, and dropping the
+/// receiver aborts the guest operation.
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    sender: mpsc::Sender<(EventMeta, OutputEvent)>,
+}
+
+impl ChannelSink {
+    /// Create a sink sending into `sender`.
+    #[must_use]
+    pub const fn new(sender: mpsc::Sender<(EventMeta, OutputEvent)>) -> Self {
+        Self { sender }
+    }
+
+    /// Create a sink and the receiving end of a channel holding up to
+    /// `capacity` events.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    #[must_use]
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<(EventMeta, OutputEvent)>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (Self::new(sender), receiver)
+    }
+
+    async fn send(&self, meta: EventMeta, event: OutputEvent) -> Result<(), BoxError> {
+        self.sender
+            .send((meta, event))
+            .await
+            .map_err(|_| super::output_channel_closed())
+    }
+}
+
+impl OutputSink for ChannelSink {
+    async fn on_item(&self, meta: EventMeta, value: Value) -> Result<(), BoxError> {
+        self.send(meta, OutputEvent::Item(value)).await
+    }
+
+    async fn on_complete(&self, meta: EventMeta, value: Option<Value>) -> Result<(), BoxError> {
+        self.send(meta, OutputEvent::Complete(value)).await
+    }
+
+    async fn on_log(
+        &self,
+        meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> Result<(), BoxError> {
+        let event = OutputEvent::Log {
+            level,
+            context: log_context.into(),
+            message: message.to_owned(),
+        };
+        self.send(meta, event).await
+    }
+}
+
+/// [`OutputSink`] that keeps events in memory up to a byte budget.
+///
+/// Item and completion values count their encoded size and log records their
+/// message length. An event that would take the total past the budget is
+/// not kept and aborts the guest operation instead. Wrap the sink in an
+/// [`Arc`] to [`take`](Self::take) the events after the call.
+#[derive(Debug)]
+pub struct BoundedCollectSink {
+    max_bytes: usize,
+    collected: Mutex<Collected>,
+}
+
+#[derive(Debug, Default)]
+struct Collected {
+    events: Vec<OutputEvent>,
+    bytes: usize,
+}
+
+impl BoundedCollectSink {
+    /// Create a sink keeping at most `max_bytes` of output.
+    #[must_use]
+    pub const fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            collected: Mutex::new(Collected {
+                events: Vec::new(),
+                bytes: 0,
+            }),
+        }
+    }
+
+    /// Bytes of output currently kept.
+    #[must_use]
+    pub fn bytes(&self) -> usize {
+        self.collected.lock().bytes
+    }
+
+    /// Remove and return the kept events in delivery order, resetting the
+    /// budget.
+    #[must_use]
+    pub fn take(&self) -> Vec<OutputEvent> {
+        std::mem::take(&mut *self.collected.lock()).events
+    }
+
+    fn push(&self, len: usize, event: OutputEvent) -> Result<(), BoxError> {
+        let mut collected = self.collected.lock();
+        let bytes = collected.bytes.saturating_add(len);
+        if bytes > self.max_bytes {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::OutOfMemory,
+                format!("collected output exceeds {} bytes", self.max_bytes),
+            )
+            .into());
+        }
+        collected.bytes = bytes;
+        collected.events.push(event);
+        drop(collected);
+        Ok(())
+    }
+}
+
+impl OutputSink for BoundedCollectSink {
+    fn on_item(
+        &self,
+        _meta: EventMeta,
+        value: Value,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        std::future::ready(self.push(value.as_cbor().len(), OutputEvent::Item(value)))
+    }
+
+    fn on_complete(
+        &self,
+        _meta: EventMeta,
+        value: Option<Value>,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        let len = value.as_ref().map_or(0, |value| value.as_cbor().len());
+        std::future::ready(self.push(len, OutputEvent::Complete(value)))
+    }
+
+    fn on_log(
+        &self,
+        _meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        let event = OutputEvent::Log {
+            level,
+            context: log_context.into(),
+            message: message.to_owned(),
+        };
+        std::future::ready(self.push(message.len(), event))
+    }
+}
+
+/// [`OutputSink`] that writes every event as one line of JSON.
+///
+/// Each line is an object with `call_id`, `sequence`, and an `event` of
+/// `"item"`, `"complete"`, or `"log"`. Items carry their `value`, and
+/// completions carry `value` when the guest returned one along with
+/// `exec_stats`. Log lines carry `level`, `context`, and `message`. The
+/// writer is flushed after every line; a write error or a value that cannot
+/// be converted to JSON aborts the guest operation.
+///
+/// Available with the `serde` feature.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct JsonLinesWriterSink<W> {
+    writer: tokio::sync::Mutex<W>,
+}
+
+#[cfg(feature = "serde")]
+impl<W> JsonLinesWriterSink<W>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    /// Create a sink writing to `writer`.
+    #[must_use]
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: tokio::sync::Mutex::new(writer),
+        }
+    }
+
+    /// Return the wrapped writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    async fn write_line(
+        &self,
+        meta: EventMeta,
+        event: &str,
+        fields: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), BoxError> {
+        use tokio::io::AsyncWriteExt as _;
+
+        let mut line = serde_json::Map::with_capacity(fields.len() + 3);
+        line.insert("call_id".to_owned(), meta.call_id.into());
+        line.insert("sequence".to_owned(), meta.sequence.into());
+        line.insert("event".to_owned(), event.into());
+        line.extend(fields);
+        let mut line = serde_json::to_vec(&line)?;
+        line.push(b'\n');
+
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&line).await?;
+        writer.flush().await?;
+        drop(writer);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W> OutputSink for JsonLinesWriterSink<W>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    async fn on_item(&self, meta: EventMeta, value: Value) -> Result<(), BoxError> {
+        let mut fields = serde_json::Map::new();
+        fields.insert("value".to_owned(), value.to_json_value()?);
+        self.write_line(meta, "item", fields).await
+    }
+
+    async fn on_complete(&self, meta: EventMeta, value: Option<Value>) -> Result<(), BoxError> {
+        let mut fields = serde_json::Map::new();
+        if let Some(value) = value {
+            fields.insert("value".to_owned(), value.to_json_value()?);
+        }
+        if let Some(stats) = meta.exec_stats {
+            fields.insert("exec_stats".to_owned(), exec_stats_json(stats));
+        }
+        self.write_line(meta, "complete", fields).await
+    }
+
+    async fn on_log(
+        &self,
+        meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> Result<(), BoxError> {
+        let mut fields = serde_json::Map::new();
+        fields.insert("level".to_owned(), level.as_str().into());
+        fields.insert("context".to_owned(), context_name(log_context).into());
+        fields.insert("message".to_owned(), message.into());
+        self.write_line(meta, "log", fields).await
+    }
+}
+
+#[cfg(feature = "serde")]
+fn exec_stats_json(stats: super::ExecStats) -> serde_json::Value {
+    let micros = |time: std::time::Duration| u64::try_from(time.as_micros()).unwrap_or(u64::MAX);
+    serde_json::json!({
+        "wall_time_us": micros(stats.wall_time),
+        "guest_time_us": micros(stats.guest_time),
+        "guest_ticks": stats.guest_ticks,
+        "peak_memory": stats.peak_memory,
+        "hostcalls": stats.hostcalls,
+        "http_requests": stats.http_requests,
+        "output_bytes": stats.output_bytes,
+    })
+}
+
+/// [`OutputSink`] that reports guest output as `tracing` events.
+///
+/// Log records become events at the matching level, with standard output at
+/// `INFO` and standard error at `ERROR`. Items and the completion are
+/// recorded at `DEBUG` with their encoded size rather than their contents.
+/// Every event carries the `call_id` and `sequence` of its [`EventMeta`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+impl TracingSink {
+    /// Create a tracing sink.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl OutputSink for TracingSink {
+    fn on_item(
+        &self,
+        meta: EventMeta,
+        value: Value,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        tracing::debug!(
+            call_id = meta.call_id,
+            sequence = meta.sequence,
+            size = value.as_cbor().len(),
+            "guest item"
+        );
+        std::future::ready(Ok(()))
+    }
+
+    fn on_complete(
+        &self,
+        meta: EventMeta,
+        value: Option<Value>,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        tracing::debug!(
+            call_id = meta.call_id,
+            sequence = meta.sequence,
+            size = value.as_ref().map(|value| value.as_cbor().len()),
+            exec_stats = ?meta.exec_stats,
+            "guest complete"
+        );
+        std::future::ready(Ok(()))
+    }
+
+    fn on_log(
+        &self,
+        meta: EventMeta,
+        level: LogLevel,
+        log_context: LogContext<'_>,
+        message: &str,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send {
+        macro_rules! log {
+            ($level:ident) => {
+                tracing::$level!(
+                    call_id = meta.call_id,
+                    sequence = meta.sequence,
+                    level = level.as_str(),
+                    context = context_name(log_context),
+                    "{message}"
+                )
+            };
+        }
+        match level {
+            LogLevel::Trace => log!(trace),
+            LogLevel::Debug => log!(debug),
+            LogLevel::Info | LogLevel::Stdout => log!(info),
+            LogLevel::Warn => log!(warn),
+            LogLevel::Error | LogLevel::Critical | LogLevel::Stderr => log!(error),
+        }
+        std::future::ready(Ok(()))
+    }
+}
+
+const fn context_name(log_context: LogContext<'_>) -> &str {
+    match log_context {
+        LogContext::Stdout => LogLevel::Stdout.as_str(),
+        LogContext::Stderr => LogLevel::Stderr.as_str(),
+        LogContext::Other(context) => context,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{ExecStats, OutputEvent, OutputTarget};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<OutputEvent>>);
+
+    impl OutputSink for Recorder {
+        async fn on_item(&self, _meta: EventMeta, value: Value) -> Result<(), BoxError> {
+            self.0.lock().push(OutputEvent::Item(value));
+            Ok(())
+        }
+
+        async fn on_complete(
+            &self,
+            _meta: EventMeta,
+            value: Option<Value>,
+        ) -> Result<(), BoxError> {
+            self.0.lock().push(OutputEvent::Complete(value));
+            Ok(())
+        }
+
+        async fn on_log(
+            &self,
+            _meta: EventMeta,
+            level: LogLevel,
+            log_context: LogContext<'_>,
+            message: &str,
+        ) -> Result<(), BoxError> {
+            self.0.lock().push(OutputEvent::Log {
+                level,
+                context: log_context.into(),
+                message: message.to_owned(),
+            });
+            Ok(())
+        }
+    }
+
+    /// Encode a small unsigned integer as a single-byte CBOR value.
+    fn int(value: u8) -> Value {
+        Value::from_cbor(vec![value])
+    }
+
+    #[tokio::test]
+    async fn combinators_compose_into_one_pipeline() {
+        let collected = Arc::new(Recorder::default());
+        let logs = Arc::new(Recorder::default());
+        let sink = TeeSink::new(
+            MapSink::new(Arc::clone(&collected), |value: Value| {
+                Ok(int(value.as_cbor()[0] * 10))
+            }),
+            FilterSink::new(
+                Arc::clone(&logs),
+                |event| matches!(event, OutputEventRef::Log { level, .. } if level != LogLevel::Debug),
+            ),
+        );
+        let target = OutputTarget::from(Arc::new(sink)).for_call();
+
+        target.on_item(int(1)).await.unwrap();
+        target
+            .on_log(LogLevel::Debug, LogContext::Other("app"), "hidden")
+            .await
+            .unwrap();
+        target
+            .on_log(LogLevel::Stdout, LogContext::Stdout, "shown")
+            .await
+            .unwrap();
+        target
+            .on_complete(Some(int(2)), ExecStats::default())
+            .await
+            .unwrap();
+
+        let collected = collected.0.lock();
+        assert!(matches!(
+            collected.as_slice(),
+            [
+                OutputEvent::Item(item),
+                OutputEvent::Log { .. },
+                OutputEvent::Log { .. },
+                OutputEvent::Complete(Some(result)),
+            ] if *item == int(10) && *result == int(20)
+        ));
+        drop(collected);
+        let logs = logs.0.lock();
+        assert!(matches!(
+            logs.as_slice(),
+            [OutputEvent::Log { message, .. }] if message == "shown"
+        ));
+        drop(logs);
+    }
+
+    #[tokio::test]
+    async fn map_errors_abort_before_delivery() {
+        let collected = Arc::new(Recorder::default());
+        let sink = MapSink::new(Arc::clone(&collected), |_value: Value| {
+            Err::<Value, BoxError>("rejected".into())
+        });
+        let target = OutputTarget::from(Arc::new(sink));
+
+        let error = target.on_item(int(1)).await.unwrap_err();
+        assert_eq!(error.to_string(), "rejected");
+        assert!(collected.0.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn adapters_deliver_events_with_meta() {
+        let (channel, mut receiver) = ChannelSink::channel(8);
+        let collect = Arc::new(BoundedCollectSink::new(3));
+        let sink = TeeSink::new(
+            TeeSink::new(channel, Arc::clone(&collect)),
+            TracingSink::new(),
+        );
+        let target = OutputTarget::from(Arc::new(sink)).for_call();
+
+        target.on_item(int(1)).await.unwrap();
+        target
+            .on_log(LogLevel::Stdout, LogContext::Stdout, "hi")
+            .await
+            .unwrap();
+        let stats = ExecStats {
+            hostcalls: 2,
+            ..ExecStats::default()
+        };
+        target.on_complete(None, stats).await.unwrap();
+
+        let (meta, event) = receiver.recv().await.unwrap();
+        assert_eq!(meta.sequence, 0);
+        assert!(matches!(event, OutputEvent::Item(item) if item == int(1)));
+        receiver.recv().await.unwrap();
+        let (meta, event) = receiver.recv().await.unwrap();
+        assert_eq!(meta.exec_stats, Some(stats));
+        assert!(matches!(event, OutputEvent::Complete(None)));
+
+        assert_eq!(collect.bytes(), 3);
+        let error = target.on_item(int(2)).await.unwrap_err();
+        assert_eq!(error.to_string(), "collected output exceeds 3 bytes");
+        assert_eq!(collect.take().len(), 3);
+        assert_eq!(collect.bytes(), 0);
+
+        drop(receiver);
+        assert!(target.on_item(int(3)).await.is_err(), "receiver dropped");
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn json_lines_writer_writes_one_object_per_event() {
+        let sink = Arc::new(JsonLinesWriterSink::new(Vec::new()));
+        let target = OutputTarget::from(Arc::clone(&sink)).for_call();
+        target.on_item(int(1)).await.unwrap();
+        target
+            .on_log(LogLevel::Warn, LogContext::Other("app"), "careful")
+            .await
+            .unwrap();
+        target
+            .on_complete(Some(int(2)), ExecStats::default())
+            .await
+            .unwrap();
+        drop(target);
+
+        let sink = Arc::into_inner(sink).expect("target dropped");
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["event"], "item");
+        assert_eq!(lines[0]["value"], 1);
+        assert_eq!(lines[1]["context"], "app");
+        assert_eq!(lines[1]["message"], "careful");
+        assert_eq!(lines[2]["event"], "complete");
+        assert_eq!(lines[2]["sequence"], 2);
+        assert_eq!(lines[2]["exec_stats"]["hostcalls"], 0);
+    }
+}
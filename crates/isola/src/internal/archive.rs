@@ -0,0 +1,674 @@
+use std::{
+    fmt,
+    fmt::Write as _,
+    io,
+    io::{Cursor, Read as _},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use cap_fs_ext::DirExt as _;
+use cap_std::{ambient_authority, fs::Dir as CapDir};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use tokio::sync::OnceCell;
+
+use crate::{
+    internal::{
+        filesystem::{MountUsage, WriteBudget},
+        guest_files::{self, HostWrite, ResolvedPath},
+    },
+    sandbox::{DirectoryMapping, FsQuota},
+};
+
+/// Where the contents of an archive mount come from.
+#[derive(Clone)]
+pub enum ArchiveSource {
+    Path(PathBuf),
+    Bytes(Bytes),
+}
+
+/// Limits for archive mounts when
+/// [`archive_limits`](crate::sandbox::SandboxOptions::archive_limits) is not
+/// set: 1 GiB of file contents and 100 000 entries.
+pub const DEFAULT_ARCHIVE_MOUNT_LIMITS: FsQuota = FsQuota {
+    max_bytes: Some(1 << 30),
+    max_inodes: Some(100_000),
+};
+
+/// A tar or zip archive exposed to the guest as a read-only mount.
+///
+/// The archive is unpacked into a private temporary directory the first time
+/// it is needed. Every mapping sharing this value reuses that directory, which
+/// is removed once the last of them is dropped.
+pub struct ArchiveMount {
+    source: ArchiveSource,
+    unpacked: OnceCell<Unpacked>,
+}
+
+struct Unpacked {
+    dir: TempDir,
+    digest: String,
+    bytes: u64,
+    entries: u64,
+}
+
+impl fmt::Debug for ArchiveMount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ArchiveMount");
+        match &self.source {
+            ArchiveSource::Path(path) => debug.field("path", path),
+            ArchiveSource::Bytes(bytes) => debug.field("len", &bytes.len()),
+        };
+        debug
+            .field("unpacked", &self.unpacked.get().map(|u| u.dir.path()))
+            .finish()
+    }
+}
+
+impl ArchiveMount {
+    pub const fn new(source: ArchiveSource) -> Self {
+        Self {
+            source,
+            unpacked: OnceCell::const_new(),
+        }
+    }
+
+    /// Unpack the archive if needed and return the directory holding its
+    /// contents.
+    ///
+    /// Fails if the archive exceeds `limits`. An archive unpacked earlier
+    /// under looser limits is checked against `limits` again.
+    pub async fn unpack(&self, limits: FsQuota) -> io::Result<&Path> {
+        let unpacked = self
+            .unpacked
+            .get_or_try_init(|| {
+                let source = self.source.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || unpack(&source, limits))
+                        .await
+                        .map_err(io::Error::other)?
+                }
+            })
+            .await?;
+        UnpackTotals {
+            limits,
+            bytes: unpacked.bytes,
+            entries: unpacked.entries,
+        }
+        .check()?;
+        Ok(unpacked.dir.path())
+    }
+
+    /// SHA-256 of the archive bytes, available once it has been unpacked.
+    pub fn digest(&self) -> Option<&str> {
+        self.unpacked.get().map(|u| u.digest.as_str())
+    }
+}
+
+/// Point every archive-backed mapping at its contents, unpacked within
+/// `limits`.
+pub async fn unpack_archives(mappings: &mut [DirectoryMapping], limits: FsQuota) -> io::Result<()> {
+    for mapping in mappings {
+        if let Some(archive) = &mapping.archive {
+            mapping.host = archive.unpack(limits).await?.to_path_buf();
+        }
+    }
+    Ok(())
+}
+
+fn unpack(source: &ArchiveSource, limits: FsQuota) -> io::Result<Unpacked> {
+    let data = match source {
+        ArchiveSource::Path(path) => Bytes::from(std::fs::read(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot read archive '{}': {e}", path.display()),
+            )
+        })?),
+        ArchiveSource::Bytes(bytes) => bytes.clone(),
+    };
+    let mut digest = String::with_capacity(64);
+    for b in Sha256::digest(&data) {
+        let _ = write!(&mut digest, "{b:02x}");
+    }
+    let dir = tempfile::Builder::new()
+        .prefix("isola-archive-")
+        .tempdir()?;
+    let totals = unpack_into(dir.path(), &data, limits)?;
+    Ok(Unpacked {
+        dir,
+        digest,
+        bytes: totals.bytes,
+        entries: totals.entries,
+    })
+}
+
+/// Unpack the tar or zip `data` into `dir`, failing once its files exceed
+/// `limits.max_bytes` or it has more than `limits.max_inodes` entries.
+///
+/// Entries that would land outside `dir`, such as absolute paths or paths
+/// containing `..`, are skipped, as are symbolic links in zip archives.
+fn unpack_into(dir: &Path, data: &[u8], limits: FsQuota) -> io::Result<UnpackTotals> {
+    let mut totals = UnpackTotals {
+        limits,
+        bytes: 0,
+        entries: 0,
+    };
+    if is_zip(data) {
+        unpack_zip(dir, data, &mut totals)?;
+    } else {
+        unpack_tar(dir, data, &mut totals)?;
+    }
+    Ok(totals)
+}
+
+fn unpack_tar(dir: &Path, data: &[u8], totals: &mut UnpackTotals) -> io::Result<()> {
+    let mut archive = tar::Archive::new(Cursor::new(data));
+    let mut directories = Vec::new();
+    for entry in archive.entries().map_err(|e| invalid_archive("tar", &e))? {
+        let entry = entry.map_err(|e| invalid_archive("tar", &e))?;
+        totals.add_entry()?;
+        totals.add_bytes(entry.size())?;
+        // Like `Archive::unpack`, create directories last so their
+        // permissions cannot block unpacking their contents.
+        if entry.header().entry_type().is_dir() {
+            directories.push(entry);
+        } else {
+            unpack_tar_entry(dir, entry)?;
+        }
+    }
+    for entry in directories {
+        unpack_tar_entry(dir, entry)?;
+    }
+    Ok(())
+}
+
+fn unpack_tar_entry(dir: &Path, mut entry: tar::Entry<'_, impl io::Read>) -> io::Result<()> {
+    entry
+        .unpack_in(dir)
+        .map(|_| ())
+        .map_err(|e| invalid_archive("tar", &e))
+}
+
+fn unpack_zip(dir: &Path, data: &[u8], totals: &mut UnpackTotals) -> io::Result<()> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(data)).map_err(|e| invalid_archive("zip", &e))?;
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| invalid_archive("zip", &e))?;
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        totals.add_entry()?;
+        let path = dir.join(path);
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else if file.is_file() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out = std::fs::File::create(&path)?;
+            // Sizes in the archive can lie, so bound what is decompressed.
+            let limit = totals.remaining_bytes().saturating_add(1);
+            let copied = io::copy(&mut (&mut file).take(limit), &mut out)?;
+            totals.add_bytes(copied)?;
+        }
+    }
+    Ok(())
+}
+
+/// What one archive has unpacked so far, checked against its limits.
+struct UnpackTotals {
+    limits: FsQuota,
+    bytes: u64,
+    entries: u64,
+}
+
+impl UnpackTotals {
+    fn add_entry(&mut self) -> io::Result<()> {
+        self.entries = self.entries.saturating_add(1);
+        self.check()
+    }
+
+    fn add_bytes(&mut self, bytes: u64) -> io::Result<()> {
+        self.bytes = self.bytes.saturating_add(bytes);
+        self.check()
+    }
+
+    fn check(&self) -> io::Result<()> {
+        match (self.limits.max_bytes, self.limits.max_inodes) {
+            (Some(max), _) if self.bytes > max => Err(too_large(&format!("{max} bytes"))),
+            (_, Some(max)) if self.entries > max => Err(too_large(&format!("{max} entries"))),
+            _ => Ok(()),
+        }
+    }
+
+    fn remaining_bytes(&self) -> u64 {
+        self.limits
+            .max_bytes
+            .map_or(u64::MAX, |max| max.saturating_sub(self.bytes))
+    }
+}
+
+fn too_large(limit: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::FileTooLarge,
+        format!("archive exceeds its limit of {limit}"),
+    )
+}
+
+/// Unpack the tar or zip `data` into the guest directory `resolved`,
+/// returning the sorted guest paths of the files written.
+///
+/// The archive is unpacked into a private staging directory in the mount
+/// first, bounded by `limits` and by what the mount's quota and the write
+/// `budget` have left. Its regular files and directories are then charged
+/// and renamed into place, so a failed extraction leaves the target
+/// untouched. Entries can neither escape the mount nor follow links the
+/// guest left in the target directory.
+pub async fn extract_into(
+    resolved: &ResolvedPath,
+    data: Bytes,
+    limits: FsQuota,
+    budget: Option<Arc<WriteBudget>>,
+) -> io::Result<Vec<String>> {
+    resolved.ensure_writable()?;
+    let resolved = resolved.clone();
+    tokio::task::spawn_blocking(move || {
+        extract_blocking(&resolved, &data, limits, budget.as_deref())
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+fn extract_blocking(
+    resolved: &ResolvedPath,
+    data: &[u8],
+    limits: FsQuota,
+    budget: Option<&WriteBudget>,
+) -> io::Result<Vec<String>> {
+    let usage = resolved.usage.as_deref();
+    let staging = tempfile::Builder::new()
+        .prefix(".isola-extract-")
+        .tempdir_in(&resolved.root)?;
+    unpack_into(
+        staging.path(),
+        data,
+        tightest(limits, usage.map(MountUsage::remaining), budget),
+    )?;
+    let staged = CapDir::open_ambient_dir(staging.path(), ambient_authority())?;
+    let mut entries = Vec::new();
+    list_staged(&staged, Path::new(""), &mut entries)?;
+
+    let root = CapDir::open_ambient_dir(&resolved.root, ambient_authority())?;
+    let (dir, missing) =
+        guest_files::open_existing_dirs(root, &resolved.relative, &resolved.guest)?;
+    let mut charge = HostWrite {
+        inodes: u64::try_from(missing.len()).unwrap_or(u64::MAX),
+        bytes: 0,
+    };
+    let mut written = 0u64;
+    for entry in &entries {
+        let existing = if missing.is_empty() {
+            existing_entry(&dir, entry, &resolved.guest)?
+        } else {
+            None
+        };
+        if existing.is_none() {
+            charge.inodes = charge.inodes.saturating_add(1);
+        }
+        charge.bytes = charge
+            .bytes
+            .saturating_add(entry.len.saturating_sub(existing.unwrap_or(0)));
+        written = written.saturating_add(entry.len);
+    }
+
+    if let Some(budget) = budget
+        && !budget.try_charge(written)
+    {
+        return Err(too_large("the sandbox's write budget"));
+    }
+    if let Err(e) = charge.reserve(usage, &resolved.guest) {
+        if let Some(budget) = budget {
+            budget.refund(written);
+        }
+        return Err(e);
+    }
+    let moved = guest_files::create_dirs(dir, &missing).and_then(|dir| {
+        for entry in &entries {
+            if entry.is_dir {
+                if dir.symlink_metadata(&entry.path).is_err() {
+                    dir.create_dir(&entry.path)?;
+                }
+            } else {
+                staged.rename(&entry.path, &dir, &entry.path)?;
+            }
+        }
+        Ok(())
+    });
+    if let Err(e) = moved {
+        charge.release(usage);
+        if let Some(budget) = budget {
+            budget.refund(written);
+        }
+        return Err(e);
+    }
+
+    let prefix = resolved.guest.trim_end_matches('/');
+    let mut files: Vec<String> = entries
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| {
+            let relative: Vec<_> = entry
+                .path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            format!("{prefix}/{}", relative.join("/"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Regular file or directory unpacked into the staging directory.
+struct StagedEntry {
+    path: PathBuf,
+    is_dir: bool,
+    len: u64,
+}
+
+/// List the regular files and directories below `dir`, parents first.
+fn list_staged(dir: &CapDir, path: &Path, entries: &mut Vec<StagedEntry>) -> io::Result<()> {
+    let current = if path.as_os_str().is_empty() {
+        dir.try_clone()?
+    } else {
+        dir.open_dir_nofollow(path)?
+    };
+    for entry in current.entries()? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        let path = path.join(entry.file_name());
+        if meta.is_dir() {
+            entries.push(StagedEntry {
+                path: path.clone(),
+                is_dir: true,
+                len: 0,
+            });
+            list_staged(dir, &path, entries)?;
+        } else if meta.is_file() {
+            entries.push(StagedEntry {
+                path,
+                is_dir: false,
+                len: meta.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Size of what `entry` replaces in `dir`, or `None` if it is new. Fails if
+/// the target holds something the entry cannot replace; since parents are
+/// checked first, this catches links the guest left in their place.
+fn existing_entry(dir: &CapDir, entry: &StagedEntry, guest: &str) -> io::Result<Option<u64>> {
+    let meta = match dir.symlink_metadata(&entry.path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match (entry.is_dir, meta.is_dir(), meta.is_file()) {
+        (true, true, _) => Ok(Some(0)),
+        (false, _, true) => Ok(Some(meta.len())),
+        _ => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "cannot replace '{}' in guest directory '{guest}'",
+                entry.path.display()
+            ),
+        )),
+    }
+}
+
+/// `limits` tightened to what the mount's quota and the write budget allow.
+fn tightest(limits: FsQuota, quota: Option<FsQuota>, budget: Option<&WriteBudget>) -> FsQuota {
+    let min = |a: Option<u64>, b: Option<u64>| match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    let quota = quota.unwrap_or_default();
+    FsQuota {
+        max_bytes: min(
+            min(limits.max_bytes, quota.max_bytes),
+            budget.map(WriteBudget::remaining),
+        ),
+        max_inodes: min(limits.max_inodes, quota.max_inodes),
+    }
+}
+
+fn invalid_archive(format: &str, error: &dyn fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid {format} archive: {error}"),
+    )
+}
+
+fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn tar_bytes() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o400);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "lib/util.py", &b"x=1\n"[..])
+            .expect("append");
+        builder.into_inner().expect("tar")
+    }
+
+    fn zip_bytes() -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("lib/util.py", zip::write::SimpleFileOptions::default())
+            .expect("start");
+        writer.write_all(b"x=1\n").expect("write");
+        writer.finish().expect("zip").into_inner()
+    }
+
+    #[tokio::test]
+    async fn unpacks_tar_and_zip_archives() {
+        for data in [tar_bytes(), zip_bytes()] {
+            let archive = ArchiveMount::new(ArchiveSource::Bytes(data.into()));
+            assert!(archive.digest().is_none());
+            let dir = archive
+                .unpack(DEFAULT_ARCHIVE_MOUNT_LIMITS)
+                .await
+                .expect("unpacked")
+                .to_path_buf();
+            assert_eq!(
+                std::fs::read(dir.join("lib/util.py")).expect("read"),
+                b"x=1\n"
+            );
+            assert_eq!(
+                archive
+                    .unpack(DEFAULT_ARCHIVE_MOUNT_LIMITS)
+                    .await
+                    .expect("unpacked"),
+                dir
+            );
+            let err = archive
+                .unpack(FsQuota::default().max_bytes(Some(3)))
+                .await
+                .expect_err("checked against the new limits");
+            assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+            assert_eq!(archive.digest().map(str::len), Some(64));
+
+            drop(archive);
+            assert!(!dir.exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn extracts_into_writable_guest_directories() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let writable = [DirectoryMapping::new(dir.path(), "/work").with_permissions(
+            wasmtime_wasi::DirPerms::all(),
+            wasmtime_wasi::FilePerms::all(),
+        )];
+
+        for data in [tar_bytes(), zip_bytes()] {
+            let resolved = guest_files::resolve(&writable, "/work/project").expect("resolved");
+            let files = extract_into(&resolved, data.into(), FsQuota::default(), None)
+                .await
+                .expect("extracted");
+            assert_eq!(files, ["/work/project/lib/util.py"]);
+            assert_eq!(
+                std::fs::read(dir.path().join("project/lib/util.py")).expect("read"),
+                b"x=1\n"
+            );
+        }
+
+        let read_only = [DirectoryMapping::new(dir.path(), "/work")];
+        let resolved = guest_files::resolve(&read_only, "/work/other").expect("resolved");
+        let err = extract_into(&resolved, tar_bytes().into(), FsQuota::default(), None)
+            .await
+            .expect_err("read-only mount");
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!dir.path().join("other").exists());
+
+        #[cfg(unix)]
+        {
+            let outside = tempfile::tempdir().expect("tempdir");
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).expect("symlink");
+            let resolved = guest_files::resolve(&writable, "/work").expect("resolved");
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, "escape/x", &b"x"[..])
+                .expect("append");
+            let data = builder.into_inner().expect("tar");
+            assert!(
+                extract_into(&resolved, data.into(), FsQuota::default(), None)
+                    .await
+                    .is_err()
+            );
+            assert!(!outside.path().join("x").exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn extraction_is_bounded_charged_and_all_or_nothing() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let writable = [DirectoryMapping::new(dir.path(), "/work").with_permissions(
+            wasmtime_wasi::DirPerms::all(),
+            wasmtime_wasi::FilePerms::all(),
+        )];
+        let usage = Arc::new(MountUsage::new(
+            FsQuota::default().max_bytes(Some(8)).max_inodes(Some(4)),
+        ));
+        let budget = Arc::new(WriteBudget::new(10));
+        let at = |guest_path| {
+            let mut resolved = guest_files::resolve(&writable, guest_path).expect("resolved");
+            resolved.usage = Some(Arc::clone(&usage));
+            resolved
+        };
+        let extract = |guest_path, data: Vec<u8>, limits| {
+            let resolved = at(guest_path);
+            let budget = Some(Arc::clone(&budget));
+            async move { extract_into(&resolved, data.into(), limits, budget).await }
+        };
+
+        for limits in [
+            FsQuota::default().max_bytes(Some(3)),
+            FsQuota::default().max_inodes(Some(0)),
+        ] {
+            for data in [tar_bytes(), zip_bytes()] {
+                let err = extract("/work/a", data, limits).await.expect_err("limited");
+                assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+            }
+        }
+        assert_eq!(
+            usage.remaining(),
+            FsQuota::default().max_bytes(Some(8)).max_inodes(Some(4))
+        );
+        assert_eq!(budget.remaining(), 10);
+
+        // `a`, `a/lib` and `a/lib/util.py` are charged to both.
+        extract("/work/a", tar_bytes(), FsQuota::default())
+            .await
+            .expect("extracted");
+        assert_eq!(
+            usage.remaining(),
+            FsQuota::default().max_bytes(Some(4)).max_inodes(Some(1))
+        );
+        assert_eq!(budget.remaining(), 6);
+
+        // The second file does not fit the quota, so neither is extracted.
+        let mut builder = tar::Builder::new(Vec::new());
+        for name in ["first.py", "second.py"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(3);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, &b"x=1"[..])
+                .expect("append");
+        }
+        let data = builder.into_inner().expect("tar");
+        let err = extract("/work/b", data, FsQuota::default())
+            .await
+            .expect_err("over quota");
+        assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+        assert!(!dir.path().join("b").exists());
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .expect("read_dir")
+            .map(|entry| entry.expect("entry").file_name())
+            .collect();
+        assert_eq!(names, ["a"]);
+        assert_eq!(budget.remaining(), 6);
+    }
+
+    #[tokio::test]
+    async fn mappings_point_at_unpacked_archives() {
+        let file = tempfile::NamedTempFile::new().expect("tempfile");
+        std::fs::write(file.path(), tar_bytes()).expect("write");
+        let mut mappings = [DirectoryMapping::new(PathBuf::new(), "/lib")
+            .with_archive(ArchiveSource::Path(file.path().to_path_buf()))];
+        unpack_archives(&mut mappings, DEFAULT_ARCHIVE_MOUNT_LIMITS)
+            .await
+            .expect("unpacked");
+        assert!(mappings[0].host.join("lib/util.py").is_file());
+
+        let mut missing = [DirectoryMapping::new(PathBuf::new(), "/lib")
+            .with_archive(ArchiveSource::Path("/nonexistent.tar".into()))];
+        let err = unpack_archives(&mut missing, DEFAULT_ARCHIVE_MOUNT_LIMITS)
+            .await
+            .expect_err("missing");
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let mut limited = [DirectoryMapping::new(PathBuf::new(), "/lib")
+            .with_archive(ArchiveSource::Bytes(zip_bytes().into()))];
+        let err = unpack_archives(&mut limited, FsQuota::default().max_inodes(Some(0)))
+            .await
+            .expect_err("limited");
+        assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+        assert!(
+            limited[0]
+                .archive
+                .as_ref()
+                .and_then(|a| a.digest())
+                .is_none()
+        );
+    }
+}
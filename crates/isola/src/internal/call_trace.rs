@@ -0,0 +1,106 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+
+use crate::sandbox::{TraceEntry, TraceKind, TraceOutcome};
+
+/// Number of payload bytes kept in each trace entry.
+const PAYLOAD_LIMIT: usize = 256;
+
+/// Ring buffer of the most recent hostcalls and HTTP requests of one sandbox.
+pub struct CallTrace {
+    capacity: usize,
+    entries: Mutex<VecDeque<TraceEntry>>,
+}
+
+/// A hostcall or HTTP request that has been dispatched but not yet recorded.
+pub struct PendingTrace {
+    entry: TraceEntry,
+    started: Instant,
+}
+
+impl PendingTrace {
+    pub fn new(kind: TraceKind, call_id: Option<u64>, target: String, payload: &[u8]) -> Self {
+        let mut pending = Self {
+            entry: TraceEntry {
+                kind,
+                call_id,
+                target,
+                payload: Bytes::new(),
+                payload_len: 0,
+                status: None,
+                duration: Duration::ZERO,
+                outcome: TraceOutcome::Ok,
+            },
+            started: Instant::now(),
+        };
+        pending.set_payload(payload);
+        pending
+    }
+
+    /// Replace the recorded payload, for requests whose body is only known
+    /// after dispatch starts.
+    pub fn set_payload(&mut self, payload: &[u8]) {
+        self.entry.payload = Bytes::copy_from_slice(&payload[..payload.len().min(PAYLOAD_LIMIT)]);
+        self.entry.payload_len = payload.len();
+    }
+}
+
+impl CallTrace {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+        }
+    }
+
+    /// Record a finished request, evicting the oldest entry when full.
+    pub fn finish(&self, pending: PendingTrace, status: Option<u16>, outcome: TraceOutcome) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entry = pending.entry;
+        entry.status = status;
+        entry.duration = pending.started.elapsed();
+        entry.outcome = outcome;
+        let mut entries = self.entries.lock();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Return the recorded entries, oldest first.
+    pub fn snapshot(&self) -> Vec<TraceEntry> {
+        self.entries.lock().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recent_entries_with_truncated_payloads() {
+        let trace = CallTrace::new(2);
+        for (call_id, target) in [(1, "a"), (2, "b"), (3, "c")] {
+            let pending = PendingTrace::new(
+                TraceKind::Hostcall,
+                Some(call_id),
+                target.to_string(),
+                &[0; PAYLOAD_LIMIT + 1],
+            );
+            trace.finish(pending, None, TraceOutcome::Ok);
+        }
+        let entries = trace.snapshot();
+        let targets: Vec<_> = entries.iter().map(|entry| entry.target.as_str()).collect();
+        assert_eq!(targets, ["b", "c"]);
+        assert_eq!(entries[0].call_id, Some(2));
+        assert_eq!(entries[0].payload.len(), PAYLOAD_LIMIT);
+        assert_eq!(entries[0].payload_len, PAYLOAD_LIMIT + 1);
+    }
+}
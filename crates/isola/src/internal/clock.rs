@@ -0,0 +1,216 @@
+use std::{fmt, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use wasmtime::component::{Accessor, HasData, Linker, Resource};
+use wasmtime_wasi::{
+    HostMonotonicClock, HostWallClock,
+    clocks::WasiClocksCtxView,
+    p2::{self, DynPollable, Pollable},
+    p3,
+};
+
+use crate::host::ClockProvider;
+
+type BoxWait = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+trait ErasedClock: Send + Sync + 'static {
+    fn wall_now(&self) -> Duration;
+
+    fn monotonic_now(&self) -> u64;
+
+    fn wait_until(self: Arc<Self>, deadline: u64) -> BoxWait;
+}
+
+impl<T: ClockProvider> ErasedClock for T {
+    fn wall_now(&self) -> Duration {
+        ClockProvider::wall_now(self)
+    }
+
+    fn monotonic_now(&self) -> u64 {
+        ClockProvider::monotonic_now(self)
+    }
+
+    fn wait_until(self: Arc<Self>, deadline: u64) -> BoxWait {
+        Box::pin(async move { ClockProvider::wait_until(&*self, deadline).await })
+    }
+}
+
+/// [`ClockProvider`] backing the WASI clocks of a sandbox, shared by every
+/// clone of the options that configured it.
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn ErasedClock>);
+
+impl SharedClock {
+    pub fn new(provider: impl ClockProvider) -> Self {
+        Self(Arc::new(provider))
+    }
+
+    /// Wait for the monotonic clock to reach `deadline`, or forever if it is
+    /// `None`.
+    fn wait(&self, deadline: Option<u64>) -> BoxWait {
+        match deadline {
+            Some(deadline) => Arc::clone(&self.0).wait_until(deadline),
+            None => Box::pin(std::future::pending()),
+        }
+    }
+
+    fn deadline_after(&self, nanos: u64) -> Option<u64> {
+        self.0.monotonic_now().checked_add(nanos)
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedClock").finish_non_exhaustive()
+    }
+}
+
+impl HostWallClock for SharedClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        self.0.wall_now()
+    }
+}
+
+impl HostMonotonicClock for SharedClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.0.monotonic_now()
+    }
+}
+
+/// WASI monotonic clock view that waits on the sandbox's [`ClockProvider`]
+/// instead of sleeping in real time when one is configured.
+pub struct ProviderClocks<'a> {
+    pub inner: WasiClocksCtxView<'a>,
+    pub clock: Option<&'a SharedClock>,
+}
+
+struct ProviderClocksData;
+
+impl HasData for ProviderClocksData {
+    type Data<'a> = ProviderClocks<'a>;
+}
+
+/// Replace the monotonic clock bindings installed by `wasmtime_wasi` with
+/// ones that honor a configured [`ClockProvider`].
+pub fn add_to_linker<T: Send + 'static>(
+    linker: &mut Linker<T>,
+    getter: fn(&mut T) -> ProviderClocks<'_>,
+) -> wasmtime::Result<()> {
+    linker.allow_shadowing(true);
+    p2::bindings::clocks::monotonic_clock::add_to_linker::<T, ProviderClocksData>(linker, getter)?;
+    p3::bindings::clocks::monotonic_clock::add_to_linker::<T, ProviderClocksData>(linker, getter)?;
+    linker.allow_shadowing(false);
+    Ok(())
+}
+
+/// Pollable that becomes ready when a [`ClockProvider`] deadline passes.
+struct ProviderDeadline(Option<BoxWait>);
+
+#[async_trait::async_trait]
+impl Pollable for ProviderDeadline {
+    async fn ready(&mut self) {
+        if let Some(wait) = &mut self.0 {
+            wait.await;
+            self.0 = None;
+        }
+    }
+}
+
+impl ProviderClocks<'_> {
+    fn subscribe_deadline(
+        &mut self,
+        clock: &SharedClock,
+        deadline: Option<u64>,
+    ) -> wasmtime::Result<Resource<DynPollable>> {
+        let deadline = self
+            .inner
+            .table
+            .push(ProviderDeadline(Some(clock.wait(deadline))))?;
+        p2::subscribe(self.inner.table, deadline)
+    }
+}
+
+impl p2::bindings::clocks::monotonic_clock::Host for ProviderClocks<'_> {
+    fn now(&mut self) -> wasmtime::Result<u64> {
+        p2::bindings::clocks::monotonic_clock::Host::now(&mut self.inner)
+    }
+
+    fn resolution(&mut self) -> wasmtime::Result<u64> {
+        p2::bindings::clocks::monotonic_clock::Host::resolution(&mut self.inner)
+    }
+
+    fn subscribe_instant(&mut self, when: u64) -> wasmtime::Result<Resource<DynPollable>> {
+        // Past deadlines keep the standard pollable, which yields to the
+        // runtime so guests polling in a loop do not starve it.
+        match self.clock.cloned() {
+            Some(clock) if when > clock.0.monotonic_now() => {
+                self.subscribe_deadline(&clock, Some(when))
+            }
+            _ => p2::bindings::clocks::monotonic_clock::Host::subscribe_instant(
+                &mut self.inner,
+                when,
+            ),
+        }
+    }
+
+    fn subscribe_duration(&mut self, duration: u64) -> wasmtime::Result<Resource<DynPollable>> {
+        match self.clock.cloned() {
+            Some(clock) if duration > 0 => {
+                let deadline = clock.deadline_after(duration);
+                self.subscribe_deadline(&clock, deadline)
+            }
+            _ => p2::bindings::clocks::monotonic_clock::Host::subscribe_duration(
+                &mut self.inner,
+                duration,
+            ),
+        }
+    }
+}
+
+impl p3::bindings::clocks::monotonic_clock::Host for ProviderClocks<'_> {
+    fn now(&mut self) -> wasmtime::Result<u64> {
+        p3::bindings::clocks::monotonic_clock::Host::now(&mut self.inner)
+    }
+
+    fn get_resolution(&mut self) -> wasmtime::Result<u64> {
+        p3::bindings::clocks::monotonic_clock::Host::get_resolution(&mut self.inner)
+    }
+}
+
+impl<U> p3::bindings::clocks::monotonic_clock::HostWithStore<U> for ProviderClocksData {
+    async fn wait_until(store: &Accessor<U, Self>, when: u64) -> wasmtime::Result<()> {
+        let wait = store.with(|mut view| -> wasmtime::Result<BoxWait> {
+            let mut view = view.get();
+            if let Some(clock) = view.clock {
+                return Ok(clock.wait(Some(when)));
+            }
+            let now = p3::bindings::clocks::monotonic_clock::Host::now(&mut view.inner)?;
+            Ok(Box::pin(tokio::time::sleep(Duration::from_nanos(
+                when.saturating_sub(now),
+            ))))
+        })?;
+        wait.await;
+        Ok(())
+    }
+
+    async fn wait_for(store: &Accessor<U, Self>, duration: u64) -> wasmtime::Result<()> {
+        if duration == 0 {
+            return Ok(());
+        }
+        let wait = store.with(|mut view| -> BoxWait {
+            match view.get().clock {
+                Some(clock) => clock.wait(clock.deadline_after(duration)),
+                None => Box::pin(tokio::time::sleep(Duration::from_nanos(duration))),
+            }
+        });
+        wait.await;
+        Ok(())
+    }
+}
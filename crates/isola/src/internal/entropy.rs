@@ -0,0 +1,50 @@
+use std::{convert::Infallible, fmt, sync::Arc};
+
+use rand_core::TryRng;
+
+use crate::host::EntropySource;
+
+/// [`EntropySource`] backing the WASI random interfaces of a sandbox, shared
+/// by every clone of the options that configured it.
+#[derive(Clone)]
+pub struct SharedEntropy(Arc<dyn EntropySource>);
+
+impl SharedEntropy {
+    pub fn new(source: impl EntropySource) -> Self {
+        Self(Arc::new(source))
+    }
+
+    /// Draw a seed for `wasi:random/insecure-seed`.
+    pub fn seed(&self) -> u128 {
+        let mut bytes = [0; 16];
+        self.0.fill(&mut bytes);
+        u128::from_le_bytes(bytes)
+    }
+}
+
+impl fmt::Debug for SharedEntropy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedEntropy").finish_non_exhaustive()
+    }
+}
+
+impl TryRng for SharedEntropy {
+    type Error = Infallible;
+
+    fn try_next_u32(&mut self) -> Result<u32, Infallible> {
+        let mut bytes = [0; 4];
+        self.0.fill(&mut bytes);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn try_next_u64(&mut self) -> Result<u64, Infallible> {
+        let mut bytes = [0; 8];
+        self.0.fill(&mut bytes);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn try_fill_bytes(&mut self, dst: &mut [u8]) -> Result<(), Infallible> {
+        self.0.fill(dst);
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use wasmtime::component::Resource;
+use wasmtime_wasi::filesystem::Descriptor;
+
+use crate::{
+    host::{FileAccess, FileOperation, FilePolicy},
+    internal::mount_filter::join_relative,
+};
+
+/// [`FilePolicy`] of a sandbox, shared by every clone of the options that
+/// configured it.
+#[derive(Clone)]
+pub struct SharedFilePolicy(Arc<dyn FilePolicy>);
+
+impl SharedFilePolicy {
+    pub fn new(policy: impl FilePolicy) -> Self {
+        Self(Arc::new(policy))
+    }
+}
+
+impl fmt::Debug for SharedFilePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedFilePolicy").finish_non_exhaustive()
+    }
+}
+
+/// Location of a directory descriptor, or of a path looked up in one.
+#[derive(Clone)]
+pub struct PolicyPath {
+    mount: Arc<str>,
+    /// Path relative to the mount point, empty for the mount point itself.
+    relative: String,
+}
+
+/// File policy of one sandbox, and the directory descriptors whose location
+/// it needs to resolve guest paths.
+///
+/// Nothing is tracked without a policy.
+#[derive(Default)]
+pub struct FilePolicyState {
+    policy: Option<SharedFilePolicy>,
+    by_descriptor: HashMap<u32, PolicyPath>,
+}
+
+impl FilePolicyState {
+    pub fn new(policy: Option<SharedFilePolicy>) -> Self {
+        Self {
+            policy,
+            by_descriptor: HashMap::new(),
+        }
+    }
+
+    pub const fn is_active(&self) -> bool {
+        self.policy.is_some()
+    }
+
+    /// Record that `fd` is the root of the mount at `guest_path`.
+    pub fn track_mount(&mut self, fd: &Resource<Descriptor>, guest_path: &str) {
+        if self.policy.is_some() {
+            self.track(
+                fd,
+                Some(PolicyPath {
+                    mount: guest_path.into(),
+                    relative: String::new(),
+                }),
+            );
+        }
+    }
+
+    /// Location of `path` looked up in `fd`, or `None` when there is no
+    /// policy or the path leaves the mount.
+    pub fn resolve(&self, fd: &Resource<Descriptor>, path: &str) -> Option<PolicyPath> {
+        let dir = self.by_descriptor.get(&fd.rep())?;
+        Some(PolicyPath {
+            mount: Arc::clone(&dir.mount),
+            relative: join_relative(&dir.relative, path)?,
+        })
+    }
+
+    /// Location of the directory `fd` itself.
+    pub fn location(&self, fd: &Resource<Descriptor>) -> Option<PolicyPath> {
+        self.by_descriptor.get(&fd.rep()).cloned()
+    }
+
+    /// Ask the policy whether `operation` may touch `target`. Accesses
+    /// without a resolved location are left to the filesystem.
+    pub fn allows(&self, target: Option<&PolicyPath>, operation: FileOperation) -> bool {
+        match (&self.policy, target) {
+            (Some(policy), Some(target)) => policy.0.allows(&FileAccess {
+                operation,
+                mount: &target.mount,
+                path: &target.relative,
+            }),
+            _ => true,
+        }
+    }
+
+    pub fn track(&mut self, fd: &Resource<Descriptor>, dir: Option<PolicyPath>) {
+        // Resource indices are reused, so always overwrite or clear the entry
+        // for a newly created descriptor.
+        match dir {
+            Some(dir) => self.by_descriptor.insert(fd.rep(), dir),
+            None => self.by_descriptor.remove(&fd.rep()),
+        };
+    }
+
+    pub fn untrack(&mut self, fd: &Resource<Descriptor>) {
+        self.by_descriptor.remove(&fd.rep());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<(FileOperation, String, String)>>,
+    }
+
+    impl FilePolicy for Recorder {
+        fn allows(&self, access: &FileAccess<'_>) -> bool {
+            self.seen.lock().push((
+                access.operation,
+                access.mount.to_string(),
+                access.path.to_string(),
+            ));
+            !access.path.starts_with("secret")
+        }
+    }
+
+    #[test]
+    fn resolves_paths_against_tracked_directories() {
+        let recorder = Arc::new(Recorder::default());
+        let mut state = FilePolicyState::new(Some(SharedFilePolicy::new(Arc::clone(&recorder))));
+        let (root, sub) = (Resource::new_own(1), Resource::new_own(2));
+
+        state.track_mount(&root, "/data");
+        state.track(&sub, state.resolve(&root, "docs/./guide"));
+        assert!(state.allows(
+            state.resolve(&sub, "../intro.md").as_ref(),
+            FileOperation::Open
+        ));
+        assert!(!state.allows(
+            state.resolve(&root, "secret.txt").as_ref(),
+            FileOperation::Create
+        ));
+        assert!(state.resolve(&root, "../etc").is_none());
+        assert!(state.allows(state.location(&sub).as_ref(), FileOperation::ReadDirectory));
+
+        state.untrack(&sub);
+        assert!(state.location(&sub).is_none());
+        assert_eq!(
+            *recorder.seen.lock(),
+            [
+                (FileOperation::Open, "/data".into(), "docs/intro.md".into()),
+                (FileOperation::Create, "/data".into(), "secret.txt".into()),
+                (
+                    FileOperation::ReadDirectory,
+                    "/data".into(),
+                    "docs/guide".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn tracks_nothing_without_a_policy() {
+        let mut state = FilePolicyState::default();
+        let root = Resource::new_own(1);
+
+        state.track_mount(&root, "/data");
+        assert!(state.resolve(&root, "a.txt").is_none());
+        assert!(state.allows(None, FileOperation::Create));
+    }
+}
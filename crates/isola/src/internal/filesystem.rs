@@ -0,0 +1,1662 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use bytes::Bytes;
+use cap_std::{ambient_authority, fs::Dir as CapDir};
+use wasmtime::component::{HasData, Linker, Resource};
+use wasmtime_wasi::{
+    DirPerms, FilePerms,
+    filesystem::{Descriptor, Dir, OpenMode, WasiFilesystemCtxView},
+    p2::{
+        DynInputStream, DynOutputStream, FsError, FsResult, OutputStream, Pollable, StreamError,
+        StreamResult,
+        bindings::filesystem::{
+            preopens,
+            types::{self, ErrorCode, HostDescriptor, HostDirectoryEntryStream},
+        },
+    },
+    p3::bindings::filesystem::preopens as p3_preopens,
+};
+
+use crate::{
+    host::FileOperation,
+    internal::{
+        file_policy::FilePolicyState,
+        mount_filter::{FilteredDir, MountFilters},
+        overlay::{MountOverlays, copy_up, entries_to_copy_up, merge_listings, open_subdirs},
+    },
+    sandbox::{DirectoryMapping, FsQuota},
+};
+
+/// Space consumed by one sandbox in a quota-limited mount.
+///
+/// Usage starts at zero when the sandbox is created and tracks growth the
+/// guest causes: bytes written past the end of a file, files and directories
+/// created, and the matching releases when the guest truncates or removes
+/// them. Content already present in the host directory is not counted.
+pub struct MountUsage {
+    quota: FsQuota,
+    bytes: AtomicU64,
+    inodes: AtomicU64,
+}
+
+impl MountUsage {
+    pub const fn new(quota: FsQuota) -> Self {
+        Self {
+            quota,
+            bytes: AtomicU64::new(0),
+            inodes: AtomicU64::new(0),
+        }
+    }
+
+    /// What the mount may still grow by before reaching its quota.
+    #[cfg(feature = "archive")]
+    pub fn remaining(&self) -> FsQuota {
+        let remaining = |limit: Option<u64>, used: &AtomicU64| {
+            limit.map(|limit| limit.saturating_sub(used.load(Ordering::Relaxed)))
+        };
+        FsQuota {
+            max_bytes: remaining(self.quota.max_bytes, &self.bytes),
+            max_inodes: remaining(self.quota.max_inodes, &self.inodes),
+        }
+    }
+
+    pub fn try_grow(&self, bytes: u64) -> bool {
+        try_add(&self.bytes, bytes, self.quota.max_bytes)
+    }
+
+    pub fn shrink(&self, bytes: u64) {
+        saturating_sub(&self.bytes, bytes);
+    }
+
+    fn try_add_inode(&self) -> bool {
+        self.try_add_inodes(1)
+    }
+
+    pub fn try_add_inodes(&self, count: u64) -> bool {
+        try_add(&self.inodes, count, self.quota.max_inodes)
+    }
+
+    fn remove_inode(&self) {
+        self.remove_inodes(1);
+    }
+
+    pub fn remove_inodes(&self, count: u64) {
+        saturating_sub(&self.inodes, count);
+    }
+}
+
+fn try_add(counter: &AtomicU64, amount: u64, limit: Option<u64>) -> bool {
+    counter
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+            let next = current.checked_add(amount)?;
+            limit.is_none_or(|limit| next <= limit).then_some(next)
+        })
+        .is_ok()
+}
+
+fn saturating_sub(counter: &AtomicU64, amount: u64) {
+    let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        Some(current.saturating_sub(amount))
+    });
+}
+
+/// Bytes one sandbox wrote to files, counted against
+/// [`SandboxOptions::max_write_bytes`](crate::sandbox::SandboxOptions::max_write_bytes).
+///
+/// Unlike [`MountUsage`], every byte written counts, including overwrites of
+/// existing content, and nothing is released when files shrink or are
+/// removed.
+pub struct WriteBudget {
+    limit: u64,
+    written: AtomicU64,
+}
+
+impl WriteBudget {
+    pub const fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            written: AtomicU64::new(0),
+        }
+    }
+
+    /// Bytes that may still be written.
+    #[cfg(feature = "archive")]
+    pub fn remaining(&self) -> u64 {
+        self.limit
+            .saturating_sub(self.written.load(Ordering::Relaxed))
+    }
+
+    pub fn try_charge(&self, bytes: u64) -> bool {
+        try_add(&self.written, bytes, Some(self.limit))
+    }
+
+    pub fn refund(&self, bytes: u64) {
+        saturating_sub(&self.written, bytes);
+    }
+}
+
+/// Quota bookkeeping for the mounts of one sandbox.
+///
+/// Descriptors are tracked by resource index: preopened directories are
+/// matched to their mount by guest path, and every descriptor opened beneath
+/// them inherits the same usage counter.
+#[derive(Default)]
+pub struct MountQuotas {
+    by_guest_path: HashMap<String, Arc<MountUsage>>,
+    by_descriptor: HashMap<u32, Arc<MountUsage>>,
+}
+
+impl MountQuotas {
+    /// Usage of the quota-limited mount at `guest_path`.
+    pub fn for_guest_path(&self, guest_path: &str) -> Option<Arc<MountUsage>> {
+        self.by_guest_path.get(guest_path).cloned()
+    }
+
+    pub fn insert(&mut self, guest_path: &str, quota: FsQuota) {
+        self.by_guest_path
+            .insert(guest_path.to_string(), Arc::new(MountUsage::new(quota)));
+    }
+
+    fn get(&self, fd: &Resource<Descriptor>) -> Option<Arc<MountUsage>> {
+        self.by_descriptor.get(&fd.rep()).cloned()
+    }
+
+    fn track(&mut self, fd: &Resource<Descriptor>, usage: Option<Arc<MountUsage>>) {
+        // Resource indices are reused, so always overwrite or clear the entry
+        // for a newly created descriptor.
+        match usage {
+            Some(usage) => self.by_descriptor.insert(fd.rep(), usage),
+            None => self.by_descriptor.remove(&fd.rep()),
+        };
+    }
+}
+
+/// Mounts added or removed after a sandbox was instantiated.
+///
+/// Creation-time mounts are preopened in the WASI context, which cannot
+/// change afterwards, so they are hidden rather than removed.
+#[derive(Default)]
+pub struct LiveMounts {
+    hidden: HashSet<String>,
+    added: Vec<(DirectoryMapping, Dir)>,
+}
+
+impl LiveMounts {
+    /// Mount `mapping`, replacing whatever is mounted at its guest path.
+    pub fn mount(&mut self, mapping: DirectoryMapping, read_only: bool) -> io::Result<()> {
+        let (dir_perms, file_perms) = if read_only {
+            (
+                mapping.dir_perms & DirPerms::READ,
+                mapping.file_perms & FilePerms::READ,
+            )
+        } else {
+            (mapping.dir_perms, mapping.file_perms)
+        };
+        let mut open_mode = OpenMode::empty();
+        if dir_perms.contains(DirPerms::READ) {
+            open_mode |= OpenMode::READ;
+        }
+        if dir_perms.contains(DirPerms::MUTATE) {
+            open_mode |= OpenMode::WRITE;
+        }
+        let dir = CapDir::open_ambient_dir(&mapping.host, ambient_authority())?;
+        let dir = Dir::new(dir, dir_perms, file_perms, open_mode, false);
+
+        self.added.retain(|(added, _)| added.guest != mapping.guest);
+        self.hidden.insert(mapping.guest.clone());
+        self.added.push((mapping, dir));
+        Ok(())
+    }
+
+    /// Remove the mount at `guest_path`, returning whether there was one.
+    ///
+    /// `initial` tells whether a creation-time mount uses the path.
+    pub fn unmount(&mut self, guest_path: &str, initial: bool) -> bool {
+        let before = self.added.len();
+        self.added.retain(|(added, _)| added.guest != guest_path);
+        let removed = self.added.len() != before;
+        let hidden = initial && self.hidden.insert(guest_path.to_string());
+        removed || hidden
+    }
+
+    /// Apply these changes to the creation-time `mappings`.
+    pub fn apply(&self, mappings: &[DirectoryMapping]) -> Vec<DirectoryMapping> {
+        mappings
+            .iter()
+            .filter(|mapping| !self.hidden.contains(&mapping.guest))
+            .chain(self.added.iter().map(|(mapping, _)| mapping))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Files and directories the guest opened, counted against
+/// [`SandboxOptions::max_open_files`](crate::sandbox::SandboxOptions::max_open_files).
+///
+/// Preopened mounts are not counted; only descriptors returned by `open-at`
+/// are. Opening past the limit fails inside the guest with a quota error
+/// rather than trapping, so the guest sees an ordinary `OSError`.
+#[derive(Default)]
+pub struct OpenFiles {
+    limit: Option<usize>,
+    open: HashSet<u32>,
+}
+
+impl OpenFiles {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            open: HashSet::new(),
+        }
+    }
+
+    fn ensure_available(&self) -> FsResult<()> {
+        match self.limit {
+            Some(limit) if self.open.len() >= limit => Err(ErrorCode::Quota.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn track(&mut self, fd: &Resource<Descriptor>) {
+        self.open.insert(fd.rep());
+    }
+
+    fn untrack(&mut self, fd: &Resource<Descriptor>) {
+        self.open.remove(&fd.rep());
+    }
+}
+
+/// WASI filesystem view that enforces [`FsQuota`] limits, read-only mode and
+/// the include patterns of filtered mounts, and resolves overlay mounts
+/// before delegating to the standard wasmtime implementation.
+pub struct QuotaFilesystem<'a> {
+    pub inner: WasiFilesystemCtxView<'a>,
+    pub quotas: &'a mut MountQuotas,
+    pub overlays: &'a mut MountOverlays,
+    pub filters: &'a mut MountFilters,
+    pub live: &'a LiveMounts,
+    pub open_files: &'a mut OpenFiles,
+    pub write_budget: Option<&'a Arc<WriteBudget>>,
+    pub policy: &'a mut FilePolicyState,
+    /// Reject every operation that could create, modify, or remove an entry.
+    pub read_only: bool,
+}
+
+struct QuotaFilesystemData;
+
+impl HasData for QuotaFilesystemData {
+    type Data<'a> = QuotaFilesystem<'a>;
+}
+
+/// Replace the `wasi:filesystem` bindings installed by `wasmtime_wasi` with
+/// quota-enforcing ones.
+pub fn add_to_linker<T: Send + 'static>(
+    linker: &mut Linker<T>,
+    getter: fn(&mut T) -> QuotaFilesystem<'_>,
+) -> wasmtime::Result<()> {
+    linker.allow_shadowing(true);
+    types::add_to_linker::<T, QuotaFilesystemData>(linker, getter)?;
+    preopens::add_to_linker::<T, QuotaFilesystemData>(linker, getter)?;
+    linker.allow_shadowing(false);
+    Ok(())
+}
+
+/// `wasi:filesystem@0.3` preopens that list no directories.
+struct NoPreopens;
+
+impl HasData for NoPreopens {
+    type Data<'a> = Self;
+}
+
+impl p3_preopens::Host for NoPreopens {
+    fn get_directories(&mut self) -> wasmtime::Result<Vec<(Resource<Descriptor>, String)>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Replace the `wasi:filesystem@0.3` preopens installed by `wasmtime_wasi`
+/// with ones that list no directories.
+///
+/// Only the 0.2 bindings enforce quotas, filters, overlays, the open file
+/// and write limits, the file policy and read-only access, so components
+/// importing 0.3 can still be instantiated but cannot reach a mount.
+pub fn hide_p3_preopens<T: Send + 'static>(linker: &mut Linker<T>) -> wasmtime::Result<()> {
+    linker.allow_shadowing(true);
+    p3_preopens::add_to_linker::<T, NoPreopens>(linker, |_| NoPreopens)?;
+    linker.allow_shadowing(false);
+    Ok(())
+}
+
+fn no_space() -> FsError {
+    ErrorCode::InsufficientSpace.into()
+}
+
+fn is_no_entry(error: &FsError) -> bool {
+    matches!(error.downcast_ref(), Some(ErrorCode::NoEntry))
+}
+
+fn wants_write(oflags: types::OpenFlags, flags: types::DescriptorFlags) -> bool {
+    oflags.intersects(types::OpenFlags::CREATE | types::OpenFlags::TRUNCATE)
+        || flags
+            .intersects(types::DescriptorFlags::WRITE | types::DescriptorFlags::MUTATE_DIRECTORY)
+}
+
+/// Layer of an overlay mount holding an entry.
+#[derive(Clone, Copy)]
+enum Layer {
+    /// The preopened top layer.
+    Top,
+    /// The lower layer at this index, highest first.
+    Lower(usize),
+}
+
+impl QuotaFilesystem<'_> {
+    fn ensure_writable(&self) -> FsResult<()> {
+        if self.read_only {
+            return Err(ErrorCode::ReadOnly.into());
+        }
+        Ok(())
+    }
+
+    /// The filtered mount directory `fd` is in and the path of `path`
+    /// relative to the mount point, unless that is the mount point itself or
+    /// lies outside the mount.
+    fn filtered(&self, fd: &Resource<Descriptor>, path: &str) -> Option<(FilteredDir, String)> {
+        let dir = self.filters.get(fd)?;
+        let relative = dir.join(path).filter(|relative| !relative.is_empty())?;
+        Some((dir, relative))
+    }
+
+    /// Fail with `NoEntry` when `path` names a file or symlink hidden by the
+    /// include patterns of a filtered mount. Directories are never hidden.
+    async fn ensure_visible(
+        &mut self,
+        fd: &Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: &str,
+    ) -> FsResult<()> {
+        let Some((dir, relative)) = self.filtered(fd, path) else {
+            return Ok(());
+        };
+        if dir.filter.allows(&relative) {
+            return Ok(());
+        }
+        let stat = self.stat_merged(fd, path_flags, path).await?;
+        if stat.type_ == types::DescriptorType::Directory {
+            Ok(())
+        } else {
+            Err(ErrorCode::NoEntry.into())
+        }
+    }
+
+    /// Fail with `Access` when the file policy denies `operation` on `path`
+    /// looked up in `fd`.
+    fn ensure_policy_allows(
+        &self,
+        fd: &Resource<Descriptor>,
+        path: &str,
+        operation: FileOperation,
+    ) -> FsResult<()> {
+        if self
+            .policy
+            .allows(self.policy.resolve(fd, path).as_ref(), operation)
+        {
+            Ok(())
+        } else {
+            Err(ErrorCode::Access.into())
+        }
+    }
+
+    /// Fail with `Access` unless the file policy lets a symlink created at
+    /// `dest_path` in `fd` point to `target`.
+    ///
+    /// The target is resolved like the mount filter does, relative to the
+    /// symlink's directory. Targets the policy cannot see, such as absolute
+    /// paths or ones leaving the mount, are denied.
+    fn ensure_policy_allows_target(
+        &self,
+        fd: &Resource<Descriptor>,
+        dest_path: &str,
+        target: &str,
+    ) -> FsResult<()> {
+        if !self.policy.is_active() {
+            return Ok(());
+        }
+        let parent = dest_path.rsplit_once('/').map_or("", |(parent, _)| parent);
+        let resolved = if target.starts_with('/') {
+            None
+        } else {
+            self.policy.resolve(fd, &format!("{parent}/{target}"))
+        };
+        match resolved {
+            Some(resolved) if self.policy.allows(Some(&resolved), FileOperation::Open) => Ok(()),
+            _ => Err(ErrorCode::Access.into()),
+        }
+    }
+
+    /// Reject creating a file or symlink at `path` that the include patterns
+    /// of a filtered mount would hide.
+    fn ensure_creatable(&self, fd: &Resource<Descriptor>, path: &str) -> FsResult<()> {
+        match self.filtered(fd, path) {
+            Some((dir, relative)) if !dir.filter.allows(&relative) => Err(ErrorCode::Access.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn file_size(&self, fd: &Resource<Descriptor>) -> FsResult<u64> {
+        match self.inner.table.get(fd)? {
+            Descriptor::File(file) => Ok(file.file.metadata().map_err(ErrorCode::from)?.len()),
+            Descriptor::Dir(_) => Err(ErrorCode::IsDirectory.into()),
+        }
+    }
+
+    async fn existing_size(
+        &mut self,
+        fd: &Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: &str,
+    ) -> FsResult<Option<u64>> {
+        let stat = HostDescriptor::stat_at(
+            &mut self.inner,
+            Resource::new_borrow(fd.rep()),
+            path_flags,
+            path.to_string(),
+        )
+        .await;
+        match stat {
+            Ok(stat) => Ok(Some(stat.size)),
+            Err(e) if is_no_entry(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Run `stat_at` against the top layer or one of the lower layers of an
+    /// overlay.
+    async fn stat_in(
+        &mut self,
+        fd: &Resource<Descriptor>,
+        layer: Option<&Dir>,
+        path_flags: types::PathFlags,
+        path: &str,
+    ) -> FsResult<types::DescriptorStat> {
+        let Some(layer) = layer else {
+            return HostDescriptor::stat_at(
+                &mut self.inner,
+                Resource::new_borrow(fd.rep()),
+                path_flags,
+                path.to_string(),
+            )
+            .await;
+        };
+        let tmp = self.push_layer(layer)?;
+        let result = HostDescriptor::stat_at(
+            &mut self.inner,
+            Resource::new_borrow(tmp.rep()),
+            path_flags,
+            path.to_string(),
+        )
+        .await;
+        self.inner.table.delete(tmp)?;
+        result
+    }
+
+    /// Run `stat_at` on `path`, falling through the layers of an overlay.
+    async fn stat_merged(
+        &mut self,
+        fd: &Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: &str,
+    ) -> FsResult<types::DescriptorStat> {
+        let lower = self.overlays.get(fd).unwrap_or_else(|| Arc::new([]));
+        match self.locate(fd, &lower, path_flags, path).await? {
+            Some((_, stat)) => Ok(stat),
+            None => Err(ErrorCode::NoEntry.into()),
+        }
+    }
+
+    /// Read the entries of directory `fd`, merging the layers of an overlay.
+    async fn read_merged_directory(
+        &mut self,
+        fd: Resource<Descriptor>,
+    ) -> FsResult<Resource<types::DirectoryEntryStream>> {
+        let Some(lower) = self.overlays.get(&fd) else {
+            return self.inner.read_directory(fd).await;
+        };
+        let stream = self.inner.read_directory(fd).await?;
+        let mut listings = vec![self.read_entries(&stream).await?];
+        for layer in lower.iter() {
+            let tmp = self.push_layer(layer)?;
+            let result = self
+                .inner
+                .read_directory(Resource::new_borrow(tmp.rep()))
+                .await;
+            self.inner.table.delete(tmp)?;
+            let layer_stream = result?;
+            let entries = self.read_entries(&layer_stream).await;
+            self.inner.table.delete(layer_stream)?;
+            listings.push(entries?);
+        }
+        self.overlays.set_listing(&stream, merge_listings(listings));
+        Ok(stream)
+    }
+
+    /// Find the highest overlay layer holding `path`.
+    async fn locate(
+        &mut self,
+        fd: &Resource<Descriptor>,
+        lower: &[Dir],
+        path_flags: types::PathFlags,
+        path: &str,
+    ) -> FsResult<Option<(Layer, types::DescriptorStat)>> {
+        let layers = std::iter::once(Layer::Top).chain((0..lower.len()).map(Layer::Lower));
+        for layer in layers {
+            let dir = match layer {
+                Layer::Top => None,
+                Layer::Lower(i) => Some(&lower[i]),
+            };
+            match self.stat_in(fd, dir, path_flags, path).await {
+                Ok(stat) => return Ok(Some((layer, stat))),
+                Err(e) if is_no_entry(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Return the lower layer holding `path` when the top layer of an overlay
+    /// does not have it.
+    async fn lower_layer_of(
+        &mut self,
+        fd: &Resource<Descriptor>,
+        path: &str,
+    ) -> FsResult<Option<Dir>> {
+        let Some(lower) = self.overlays.get(fd) else {
+            return Ok(None);
+        };
+        match self
+            .locate(fd, &lower, types::PathFlags::empty(), path)
+            .await?
+        {
+            Some((Layer::Lower(i), _)) => Ok(Some(lower[i].clone())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Entries that only exist in a lower layer cannot be removed or renamed.
+    async fn ensure_in_top_layer(&mut self, fd: &Resource<Descriptor>, path: &str) -> FsResult<()> {
+        if self.lower_layer_of(fd, path).await?.is_some() {
+            return Err(ErrorCode::ReadOnly.into());
+        }
+        Ok(())
+    }
+
+    fn push_layer(&mut self, layer: &Dir) -> FsResult<Resource<Descriptor>> {
+        Ok(self.inner.table.push(Descriptor::Dir(layer.clone()))?)
+    }
+
+    fn writable_dir(&self, fd: &Resource<Descriptor>) -> FsResult<Option<Dir>> {
+        match self.inner.table.get(fd)? {
+            Descriptor::Dir(dir) if !self.read_only && dir.perms.contains(DirPerms::MUTATE) => {
+                Ok(Some(dir.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn read_entries(
+        &mut self,
+        stream: &Resource<types::DirectoryEntryStream>,
+    ) -> FsResult<Vec<types::DirectoryEntry>> {
+        let mut entries = Vec::new();
+        while let Some(entry) = self
+            .inner
+            .read_directory_entry(Resource::new_borrow(stream.rep()))
+            .await?
+        {
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    async fn open_top_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+        oflags: types::OpenFlags,
+        flags: types::DescriptorFlags,
+    ) -> FsResult<Resource<Descriptor>> {
+        let usage = self.quotas.get(&fd);
+        let mut created = false;
+        let mut truncated = 0;
+        if let Some(usage) = &usage
+            && (oflags.contains(types::OpenFlags::CREATE)
+                || oflags.contains(types::OpenFlags::TRUNCATE))
+        {
+            match self.existing_size(&fd, path_flags, &path).await? {
+                None if oflags.contains(types::OpenFlags::CREATE) => {
+                    if !usage.try_add_inode() {
+                        return Err(no_space());
+                    }
+                    created = true;
+                }
+                Some(size) if oflags.contains(types::OpenFlags::TRUNCATE) => truncated = size,
+                _ => {}
+            }
+        }
+
+        match self
+            .inner
+            .open_at(fd, path_flags, path, oflags, flags)
+            .await
+        {
+            Ok(opened) => {
+                if let Some(usage) = &usage {
+                    usage.shrink(truncated);
+                }
+                self.quotas.track(&opened, usage);
+                Ok(opened)
+            }
+            Err(e) => {
+                if created && let Some(usage) = &usage {
+                    usage.remove_inode();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Charge copying `bytes` and `inodes` new entries up into the top layer
+    /// of an overlay.
+    fn reserve_copy_up(&self, usage: Option<&MountUsage>, inodes: u64, bytes: u64) -> FsResult<()> {
+        if let Some(budget) = self.write_budget
+            && !budget.try_charge(bytes)
+        {
+            return Err(no_space());
+        }
+        if let Some(usage) = usage {
+            if !usage.try_add_inodes(inodes) {
+                self.release_copy_up(None, 0, bytes);
+                return Err(no_space());
+            }
+            if !usage.try_grow(bytes) {
+                usage.remove_inodes(inodes);
+                self.release_copy_up(None, 0, bytes);
+                return Err(no_space());
+            }
+        }
+        Ok(())
+    }
+
+    fn release_copy_up(&self, usage: Option<&MountUsage>, inodes: u64, bytes: u64) {
+        if let Some(budget) = self.write_budget {
+            budget.refund(bytes);
+        }
+        if let Some(usage) = usage {
+            usage.shrink(bytes);
+            usage.remove_inodes(inodes);
+        }
+    }
+
+    /// Open `path` in the highest overlay layer holding it.
+    ///
+    /// When the top layer is writable, entries opened for writing or, for
+    /// directories, for mutation are first copied up into it so that changes
+    /// land there. The copy counts against the mount quota and the write
+    /// budget like any other write.
+    async fn open_overlay_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        lower: &[Dir],
+        path_flags: types::PathFlags,
+        path: String,
+        oflags: types::OpenFlags,
+        flags: types::DescriptorFlags,
+    ) -> FsResult<Resource<Descriptor>> {
+        let found = self.locate(&fd, lower, path_flags, &path).await?;
+        let (opened, below) = if let Some((Layer::Lower(i), stat)) = found {
+            let is_dir = stat.type_ == types::DescriptorType::Directory;
+            match self.writable_dir(&fd)? {
+                Some(top) if wants_write(oflags, flags) => {
+                    let bytes = if is_dir { 0 } else { stat.size };
+                    let inodes = entries_to_copy_up(&top, &path)
+                        .await
+                        .map_err(ErrorCode::from)?;
+                    let usage = self.quotas.get(&fd);
+                    self.reserve_copy_up(usage.as_deref(), inodes, bytes)?;
+                    if let Err(e) = copy_up(&lower[i], &top, &path, is_dir).await {
+                        self.release_copy_up(usage.as_deref(), inodes, bytes);
+                        return Err(ErrorCode::from(e).into());
+                    }
+                    let opened = self
+                        .open_top_at(fd, path_flags, path.clone(), oflags, flags)
+                        .await?;
+                    (opened, lower)
+                }
+                _ => {
+                    let tmp = self.push_layer(&lower[i])?;
+                    let result = self
+                        .inner
+                        .open_at(
+                            Resource::new_borrow(tmp.rep()),
+                            path_flags,
+                            path.clone(),
+                            oflags,
+                            flags,
+                        )
+                        .await;
+                    self.inner.table.delete(tmp)?;
+                    let opened = result?;
+                    self.quotas.track(&opened, None);
+                    (opened, &lower[i + 1..])
+                }
+            }
+        } else {
+            let opened = self
+                .open_top_at(fd, path_flags, path.clone(), oflags, flags)
+                .await?;
+            (opened, lower)
+        };
+        let below = match self.inner.table.get(&opened)? {
+            Descriptor::Dir(_) => Some(open_subdirs(below, &path).await),
+            Descriptor::File(_) => None,
+        };
+        self.overlays.track(&opened, below);
+        Ok(opened)
+    }
+
+    fn wrap_stream(
+        &mut self,
+        usage: Option<Arc<MountUsage>>,
+        stream: Resource<DynOutputStream>,
+        position: Option<u64>,
+        size: u64,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        let inner = self.inner.table.delete(stream)?;
+        let wrapped: DynOutputStream = Box::new(QuotaOutputStream {
+            inner,
+            usage,
+            budget: self.write_budget.cloned(),
+            position,
+            size,
+            pending: None,
+        });
+        Ok(self.inner.table.push(wrapped)?)
+    }
+
+    async fn write_within_quota(
+        &mut self,
+        fd: Resource<Descriptor>,
+        buf: Vec<u8>,
+        offset: types::Filesize,
+    ) -> FsResult<types::Filesize> {
+        let Some(usage) = self.quotas.get(&fd) else {
+            return self.inner.write(fd, buf, offset).await;
+        };
+        let current = self.file_size(&fd)?;
+        let end = offset.saturating_add(buf.len() as u64);
+        let growth = end.saturating_sub(current);
+        if !usage.try_grow(growth) {
+            return Err(no_space());
+        }
+        match self.inner.write(fd, buf, offset).await {
+            Ok(written) => {
+                // Release the reservation for any bytes the write did not reach.
+                let actual = offset.saturating_add(written).saturating_sub(current);
+                usage.shrink(growth.saturating_sub(actual));
+                Ok(written)
+            }
+            Err(e) => {
+                usage.shrink(growth);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl preopens::Host for QuotaFilesystem<'_> {
+    fn get_directories(&mut self) -> wasmtime::Result<Vec<(Resource<Descriptor>, String)>> {
+        let preopened = preopens::Host::get_directories(&mut self.inner)?;
+        let mut directories = Vec::with_capacity(preopened.len() + self.live.added.len());
+        for (fd, guest_path) in preopened {
+            if self.live.hidden.contains(&guest_path) {
+                self.inner.table.delete(fd)?;
+                continue;
+            }
+            let usage = self.quotas.by_guest_path.get(&guest_path).cloned();
+            self.quotas.track(&fd, usage);
+            let lower = self.overlays.for_guest_path(&guest_path);
+            self.overlays.track(&fd, lower);
+            let filter = self.filters.for_guest_path(&guest_path);
+            self.filters.track(&fd, filter);
+            self.policy.track_mount(&fd, &guest_path);
+            directories.push((fd, guest_path));
+        }
+        for (mapping, dir) in &self.live.added {
+            let fd = self.inner.table.push(Descriptor::Dir(dir.clone()))?;
+            self.quotas.track(&fd, None);
+            self.overlays.track(&fd, None);
+            self.filters.track(&fd, None);
+            self.policy.track_mount(&fd, &mapping.guest);
+            directories.push((fd, mapping.guest.clone()));
+        }
+        Ok(directories)
+    }
+}
+
+impl types::Host for QuotaFilesystem<'_> {
+    fn convert_error_code(&mut self, err: FsError) -> wasmtime::Result<ErrorCode> {
+        types::Host::convert_error_code(&mut self.inner, err)
+    }
+
+    fn filesystem_error_code(
+        &mut self,
+        err: Resource<wasmtime::Error>,
+    ) -> wasmtime::Result<Option<ErrorCode>> {
+        types::Host::filesystem_error_code(&mut self.inner, err)
+    }
+}
+
+impl HostDescriptor for QuotaFilesystem<'_> {
+    async fn advise(
+        &mut self,
+        fd: Resource<Descriptor>,
+        offset: types::Filesize,
+        len: types::Filesize,
+        advice: types::Advice,
+    ) -> FsResult<()> {
+        self.inner.advise(fd, offset, len, advice).await
+    }
+
+    async fn sync_data(&mut self, fd: Resource<Descriptor>) -> FsResult<()> {
+        self.inner.sync_data(fd).await
+    }
+
+    async fn get_flags(&mut self, fd: Resource<Descriptor>) -> FsResult<types::DescriptorFlags> {
+        self.inner.get_flags(fd).await
+    }
+
+    async fn get_type(&mut self, fd: Resource<Descriptor>) -> FsResult<types::DescriptorType> {
+        self.inner.get_type(fd).await
+    }
+
+    async fn set_size(&mut self, fd: Resource<Descriptor>, size: types::Filesize) -> FsResult<()> {
+        self.ensure_writable()?;
+        let Some(usage) = self.quotas.get(&fd) else {
+            return self.inner.set_size(fd, size).await;
+        };
+        let current = self.file_size(&fd)?;
+        let growth = size.saturating_sub(current);
+        if !usage.try_grow(growth) {
+            return Err(no_space());
+        }
+        match self.inner.set_size(fd, size).await {
+            Ok(()) => {
+                usage.shrink(current.saturating_sub(size));
+                Ok(())
+            }
+            Err(e) => {
+                usage.shrink(growth);
+                Err(e)
+            }
+        }
+    }
+
+    async fn set_times(
+        &mut self,
+        fd: Resource<Descriptor>,
+        atim: types::NewTimestamp,
+        mtim: types::NewTimestamp,
+    ) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.inner.set_times(fd, atim, mtim).await
+    }
+
+    async fn read(
+        &mut self,
+        fd: Resource<Descriptor>,
+        len: types::Filesize,
+        offset: types::Filesize,
+    ) -> FsResult<(Vec<u8>, bool)> {
+        self.inner.read(fd, len, offset).await
+    }
+
+    async fn write(
+        &mut self,
+        fd: Resource<Descriptor>,
+        buf: Vec<u8>,
+        offset: types::Filesize,
+    ) -> FsResult<types::Filesize> {
+        self.ensure_writable()?;
+        let len = buf.len() as u64;
+        let budget = self.write_budget.cloned();
+        if let Some(budget) = &budget
+            && !budget.try_charge(len)
+        {
+            return Err(no_space());
+        }
+        let result = self.write_within_quota(fd, buf, offset).await;
+        if let Some(budget) = budget {
+            budget.refund(
+                result
+                    .as_ref()
+                    .map_or(len, |written| len.saturating_sub(*written)),
+            );
+        }
+        result
+    }
+
+    async fn read_directory(
+        &mut self,
+        fd: Resource<Descriptor>,
+    ) -> FsResult<Resource<types::DirectoryEntryStream>> {
+        if !self.policy.allows(
+            self.policy.location(&fd).as_ref(),
+            FileOperation::ReadDirectory,
+        ) {
+            return Err(ErrorCode::Access.into());
+        }
+        let filter = self.filters.get(&fd);
+        let stream = self.read_merged_directory(fd).await?;
+        let Some(dir) = filter else {
+            return Ok(stream);
+        };
+        let mut entries = match self.overlays.listing(&stream) {
+            Some(listing) => std::mem::take(listing),
+            None => self.read_entries(&stream).await?.into(),
+        };
+        entries.retain(|entry| {
+            entry.type_ == types::DescriptorType::Directory
+                || dir
+                    .join(&entry.name)
+                    .is_some_and(|relative| dir.filter.allows(&relative))
+        });
+        self.overlays.set_listing(&stream, entries);
+        Ok(stream)
+    }
+
+    async fn sync(&mut self, fd: Resource<Descriptor>) -> FsResult<()> {
+        self.inner.sync(fd).await
+    }
+
+    async fn create_directory_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path: String,
+    ) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.ensure_policy_allows(&fd, &path, FileOperation::Create)?;
+        let Some(usage) = self.quotas.get(&fd) else {
+            return self.inner.create_directory_at(fd, path).await;
+        };
+        if !usage.try_add_inode() {
+            return Err(no_space());
+        }
+        let result = self.inner.create_directory_at(fd, path).await;
+        if result.is_err() {
+            usage.remove_inode();
+        }
+        result
+    }
+
+    async fn stat(&mut self, fd: Resource<Descriptor>) -> FsResult<types::DescriptorStat> {
+        self.inner.stat(fd).await
+    }
+
+    async fn stat_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+    ) -> FsResult<types::DescriptorStat> {
+        let stat = self.stat_merged(&fd, path_flags, &path).await?;
+        if stat.type_ != types::DescriptorType::Directory
+            && let Some((dir, relative)) = self.filtered(&fd, &path)
+            && !dir.filter.allows(&relative)
+        {
+            return Err(ErrorCode::NoEntry.into());
+        }
+        Ok(stat)
+    }
+
+    async fn set_times_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+        atim: types::NewTimestamp,
+        mtim: types::NewTimestamp,
+    ) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.ensure_visible(&fd, path_flags, &path).await?;
+        self.inner
+            .set_times_at(fd, path_flags, path, atim, mtim)
+            .await
+    }
+
+    async fn link_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        old_path_flags: types::PathFlags,
+        old_path: String,
+        new_descriptor: Resource<Descriptor>,
+        new_path: String,
+    ) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.ensure_visible(&fd, old_path_flags, &old_path).await?;
+        self.ensure_creatable(&new_descriptor, &new_path)?;
+        self.ensure_policy_allows(&fd, &old_path, FileOperation::Open)?;
+        self.ensure_policy_allows(&new_descriptor, &new_path, FileOperation::Create)?;
+        let Some(usage) = self.quotas.get(&new_descriptor) else {
+            return self
+                .inner
+                .link_at(fd, old_path_flags, old_path, new_descriptor, new_path)
+                .await;
+        };
+        if !usage.try_add_inode() {
+            return Err(no_space());
+        }
+        let result = self
+            .inner
+            .link_at(fd, old_path_flags, old_path, new_descriptor, new_path)
+            .await;
+        if result.is_err() {
+            usage.remove_inode();
+        }
+        result
+    }
+
+    async fn open_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+        oflags: types::OpenFlags,
+        flags: types::DescriptorFlags,
+    ) -> FsResult<Resource<Descriptor>> {
+        if wants_write(oflags, flags) {
+            self.ensure_writable()?;
+        }
+        self.open_files.ensure_available()?;
+        let target = self.policy.resolve(&fd, &path);
+        let operation = if oflags.contains(types::OpenFlags::CREATE) {
+            FileOperation::Create
+        } else {
+            FileOperation::Open
+        };
+        if !self.policy.allows(target.as_ref(), operation) {
+            return Err(ErrorCode::Access.into());
+        }
+        if let Some((dir, relative)) = self.filtered(&fd, &path)
+            && !dir.filter.allows(&relative)
+        {
+            let creating = oflags.contains(types::OpenFlags::CREATE)
+                && !oflags.contains(types::OpenFlags::DIRECTORY);
+            match self.stat_merged(&fd, path_flags, &path).await {
+                Ok(stat) if stat.type_ == types::DescriptorType::Directory => {}
+                Ok(_) => return Err(ErrorCode::NoEntry.into()),
+                Err(e) if creating && is_no_entry(&e) => return Err(ErrorCode::Access.into()),
+                Err(e) => return Err(e),
+            }
+        }
+        let filter = self.filters.get(&fd);
+        let opened = match self.overlays.get(&fd) {
+            Some(lower) => {
+                self.open_overlay_at(fd, &lower, path_flags, path.clone(), oflags, flags)
+                    .await?
+            }
+            None => {
+                self.open_top_at(fd, path_flags, path.clone(), oflags, flags)
+                    .await?
+            }
+        };
+        let dir = match (filter, self.inner.table.get(&opened)?) {
+            (Some(dir), Descriptor::Dir(_)) => dir.join(&path).map(|relative| FilteredDir {
+                filter: dir.filter,
+                relative,
+            }),
+            _ => None,
+        };
+        self.filters.track(&opened, dir);
+        let is_dir = matches!(self.inner.table.get(&opened)?, Descriptor::Dir(_));
+        self.policy.track(&opened, target.filter(|_| is_dir));
+        self.open_files.track(&opened);
+        Ok(opened)
+    }
+
+    fn drop(&mut self, fd: Resource<Descriptor>) -> wasmtime::Result<()> {
+        self.open_files.untrack(&fd);
+        self.policy.untrack(&fd);
+        self.quotas.by_descriptor.remove(&fd.rep());
+        self.overlays.untrack(&fd);
+        self.filters.untrack(&fd);
+        HostDescriptor::drop(&mut self.inner, fd)
+    }
+
+    async fn readlink_at(&mut self, fd: Resource<Descriptor>, path: String) -> FsResult<String> {
+        self.ensure_visible(&fd, types::PathFlags::empty(), &path)
+            .await?;
+        if let Some(layer) = self.lower_layer_of(&fd, &path).await? {
+            let tmp = self.push_layer(&layer)?;
+            let result = self
+                .inner
+                .readlink_at(Resource::new_borrow(tmp.rep()), path)
+                .await;
+            self.inner.table.delete(tmp)?;
+            return result;
+        }
+        self.inner.readlink_at(fd, path).await
+    }
+
+    async fn remove_directory_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path: String,
+    ) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.ensure_in_top_layer(&fd, &path).await?;
+        let usage = self.quotas.get(&fd);
+        self.inner.remove_directory_at(fd, path).await?;
+        if let Some(usage) = usage {
+            usage.remove_inode();
+        }
+        Ok(())
+    }
+
+    async fn rename_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        old_path: String,
+        new_fd: Resource<Descriptor>,
+        new_path: String,
+    ) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.ensure_in_top_layer(&fd, &old_path).await?;
+        self.ensure_policy_allows(&fd, &old_path, FileOperation::Open)?;
+        self.ensure_policy_allows(&new_fd, &new_path, FileOperation::Create)?;
+        if self.filters.get(&fd).is_some() || self.filters.get(&new_fd).is_some() {
+            // Moving a directory would change which of its entries the
+            // patterns hide.
+            let stat = self
+                .stat_merged(&fd, types::PathFlags::empty(), &old_path)
+                .await?;
+            if stat.type_ == types::DescriptorType::Directory {
+                return Err(ErrorCode::Access.into());
+            }
+            self.ensure_visible(&fd, types::PathFlags::empty(), &old_path)
+                .await?;
+            self.ensure_creatable(&new_fd, &new_path)?;
+        }
+        let from = self.quotas.get(&fd);
+        let to = self.quotas.get(&new_fd);
+        let crosses_mounts = match (&from, &to) {
+            (Some(from), Some(to)) => !Arc::ptr_eq(from, to),
+            (None, None) => false,
+            _ => true,
+        };
+        if !crosses_mounts {
+            return self.inner.rename_at(fd, old_path, new_fd, new_path).await;
+        }
+
+        // Moving an entry between mounts transfers its size to the
+        // destination mount's quota.
+        let size = self
+            .existing_size(&fd, types::PathFlags::empty(), &old_path)
+            .await?
+            .unwrap_or(0);
+        if let Some(to) = &to {
+            if !to.try_add_inode() {
+                return Err(no_space());
+            }
+            if !to.try_grow(size) {
+                to.remove_inode();
+                return Err(no_space());
+            }
+        }
+        match self.inner.rename_at(fd, old_path, new_fd, new_path).await {
+            Ok(()) => {
+                if let Some(from) = from {
+                    from.shrink(size);
+                    from.remove_inode();
+                }
+                Ok(())
+            }
+            Err(e) => {
+                if let Some(to) = to {
+                    to.shrink(size);
+                    to.remove_inode();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn symlink_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        src_path: String,
+        dest_path: String,
+    ) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.ensure_policy_allows(&fd, &dest_path, FileOperation::Create)?;
+        self.ensure_policy_allows_target(&fd, &dest_path, &src_path)?;
+        if let Some(dir) = self.filters.get(&fd) {
+            // A symlink must not reveal a hidden file under a visible name.
+            let parent = dest_path.rsplit_once('/').map_or("", |(parent, _)| parent);
+            let target = if src_path.starts_with('/') {
+                None
+            } else {
+                dir.join(&format!("{parent}/{src_path}"))
+            };
+            if !target.is_some_and(|target| dir.filter.allows(&target)) {
+                return Err(ErrorCode::Access.into());
+            }
+            self.ensure_creatable(&fd, &dest_path)?;
+        }
+        let Some(usage) = self.quotas.get(&fd) else {
+            return self.inner.symlink_at(fd, src_path, dest_path).await;
+        };
+        if !usage.try_add_inode() {
+            return Err(no_space());
+        }
+        let result = self.inner.symlink_at(fd, src_path, dest_path).await;
+        if result.is_err() {
+            usage.remove_inode();
+        }
+        result
+    }
+
+    async fn unlink_file_at(&mut self, fd: Resource<Descriptor>, path: String) -> FsResult<()> {
+        self.ensure_writable()?;
+        self.ensure_in_top_layer(&fd, &path).await?;
+        self.ensure_visible(&fd, types::PathFlags::empty(), &path)
+            .await?;
+        let Some(usage) = self.quotas.get(&fd) else {
+            return self.inner.unlink_file_at(fd, path).await;
+        };
+        let size = self
+            .existing_size(&fd, types::PathFlags::empty(), &path)
+            .await?
+            .unwrap_or(0);
+        self.inner.unlink_file_at(fd, path).await?;
+        usage.shrink(size);
+        usage.remove_inode();
+        Ok(())
+    }
+
+    fn read_via_stream(
+        &mut self,
+        fd: Resource<Descriptor>,
+        offset: types::Filesize,
+    ) -> FsResult<Resource<DynInputStream>> {
+        self.inner.read_via_stream(fd, offset)
+    }
+
+    fn write_via_stream(
+        &mut self,
+        fd: Resource<Descriptor>,
+        offset: types::Filesize,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        self.ensure_writable()?;
+        let usage = self.quotas.get(&fd);
+        if usage.is_none() && self.write_budget.is_none() {
+            return self.inner.write_via_stream(fd, offset);
+        }
+        let size = self.file_size(&fd)?;
+        let stream = self.inner.write_via_stream(fd, offset)?;
+        self.wrap_stream(usage, stream, Some(offset), size)
+    }
+
+    fn append_via_stream(
+        &mut self,
+        fd: Resource<Descriptor>,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        self.ensure_writable()?;
+        let usage = self.quotas.get(&fd);
+        if usage.is_none() && self.write_budget.is_none() {
+            return self.inner.append_via_stream(fd);
+        }
+        let size = self.file_size(&fd)?;
+        let stream = self.inner.append_via_stream(fd)?;
+        self.wrap_stream(usage, stream, None, size)
+    }
+
+    async fn is_same_object(
+        &mut self,
+        a: Resource<Descriptor>,
+        b: Resource<Descriptor>,
+    ) -> wasmtime::Result<bool> {
+        self.inner.is_same_object(a, b).await
+    }
+
+    async fn metadata_hash(
+        &mut self,
+        fd: Resource<Descriptor>,
+    ) -> FsResult<types::MetadataHashValue> {
+        self.inner.metadata_hash(fd).await
+    }
+
+    async fn metadata_hash_at(
+        &mut self,
+        fd: Resource<Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+    ) -> FsResult<types::MetadataHashValue> {
+        self.ensure_visible(&fd, path_flags, &path).await?;
+        if let Some(layer) = self.lower_layer_of(&fd, &path).await? {
+            let tmp = self.push_layer(&layer)?;
+            let result = self
+                .inner
+                .metadata_hash_at(Resource::new_borrow(tmp.rep()), path_flags, path)
+                .await;
+            self.inner.table.delete(tmp)?;
+            return result;
+        }
+        self.inner.metadata_hash_at(fd, path_flags, path).await
+    }
+}
+
+impl HostDirectoryEntryStream for QuotaFilesystem<'_> {
+    async fn read_directory_entry(
+        &mut self,
+        stream: Resource<types::DirectoryEntryStream>,
+    ) -> FsResult<Option<types::DirectoryEntry>> {
+        if let Some(listing) = self.overlays.listing(&stream) {
+            return Ok(listing.pop_front());
+        }
+        self.inner.read_directory_entry(stream).await
+    }
+
+    fn drop(&mut self, stream: Resource<types::DirectoryEntryStream>) -> wasmtime::Result<()> {
+        self.overlays.drop_stream(&stream);
+        HostDirectoryEntryStream::drop(&mut self.inner, stream)
+    }
+}
+
+/// File output stream that charges file growth against a mount quota and
+/// written bytes against the sandbox's write budget.
+///
+/// File streams write in the background, so a write is only reserved when it
+/// is accepted and is settled once the next readiness check or flush reports
+/// whether it completed.
+struct QuotaOutputStream {
+    inner: DynOutputStream,
+    usage: Option<Arc<MountUsage>>,
+    budget: Option<Arc<WriteBudget>>,
+    /// Write offset for positioned streams, or `None` when appending.
+    position: Option<u64>,
+    /// Known file size, used to distinguish overwrites from growth.
+    size: u64,
+    /// Write accepted by the inner stream but not yet known to be complete.
+    pending: Option<PendingWrite>,
+}
+
+#[derive(Clone, Copy)]
+struct PendingWrite {
+    len: u64,
+    end: u64,
+    growth: u64,
+}
+
+impl QuotaOutputStream {
+    fn reserve(&self, len: u64) -> StreamResult<(u64, u64)> {
+        let start = self.position.unwrap_or(self.size);
+        let end = start.saturating_add(len);
+        let growth = end.saturating_sub(self.size);
+        if let Some(budget) = &self.budget
+            && !budget.try_charge(len)
+        {
+            return Err(StreamError::LastOperationFailed(no_space_io_error()));
+        }
+        if let Some(usage) = &self.usage
+            && !usage.try_grow(growth)
+        {
+            if let Some(budget) = &self.budget {
+                budget.refund(len);
+            }
+            return Err(StreamError::LastOperationFailed(no_space_io_error()));
+        }
+        Ok((end, growth))
+    }
+
+    fn refund(&self, write: PendingWrite) {
+        if let Some(usage) = &self.usage {
+            usage.shrink(write.growth);
+        }
+        if let Some(budget) = &self.budget {
+            budget.refund(write.len);
+        }
+    }
+
+    /// Keep the reservation for the write `result` started, or refund it if
+    /// the inner stream rejected it outright.
+    fn start(&mut self, result: StreamResult<()>, write: PendingWrite) -> StreamResult<()> {
+        match &result {
+            Ok(()) => self.pending = Some(write),
+            Err(_) => self.refund(write),
+        }
+        result
+    }
+
+    /// Settle the pending write once the inner stream reports on it: keep
+    /// the charge if it completed and refund it if it failed.
+    fn settle<T>(&mut self, result: StreamResult<T>) -> StreamResult<T> {
+        let Some(write) = self.pending.take() else {
+            return result;
+        };
+        if result.is_ok() {
+            self.size = self.size.max(write.end);
+            if let Some(position) = &mut self.position {
+                *position = write.end;
+            }
+        } else {
+            self.refund(write);
+        }
+        result
+    }
+}
+
+#[cfg(unix)]
+fn no_space_io_error() -> wasmtime::Error {
+    const ENOSPC: i32 = 28;
+    wasmtime::Error::new(std::io::Error::from_raw_os_error(ENOSPC))
+}
+
+#[cfg(not(unix))]
+fn no_space_io_error() -> wasmtime::Error {
+    wasmtime::Error::new(std::io::Error::from(std::io::ErrorKind::StorageFull))
+}
+
+#[async_trait::async_trait]
+impl Pollable for QuotaOutputStream {
+    async fn ready(&mut self) {
+        self.inner.ready().await;
+    }
+}
+
+#[async_trait::async_trait]
+impl OutputStream for QuotaOutputStream {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        let len = bytes.len() as u64;
+        let (end, growth) = self.reserve(len)?;
+        let result = self.inner.write(bytes);
+        self.start(result, PendingWrite { len, end, growth })
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        let result = self.inner.flush();
+        // Flushing succeeds while a write is in flight, so only a failure
+        // settles it.
+        if result.is_ok() {
+            return result;
+        }
+        self.settle(result)
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        let result = self.inner.check_write();
+        // Zero means the pending write is still in flight.
+        if matches!(result, Ok(0)) {
+            return result;
+        }
+        self.settle(result)
+    }
+
+    fn write_zeroes(&mut self, nelem: usize) -> StreamResult<()> {
+        let len = nelem as u64;
+        let (end, growth) = self.reserve(len)?;
+        let result = self.inner.write_zeroes(nelem);
+        self.start(result, PendingWrite { len, end, growth })
+    }
+
+    async fn cancel(&mut self) {
+        self.inner.cancel().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_enforces_byte_and_inode_limits() {
+        let usage = MountUsage::new(FsQuota::default().max_bytes(Some(10)).max_inodes(Some(1)));
+
+        assert!(usage.try_grow(6));
+        assert!(!usage.try_grow(5));
+        usage.shrink(6);
+        assert!(usage.try_grow(10));
+
+        assert!(usage.try_add_inode());
+        assert!(!usage.try_add_inode());
+        usage.remove_inode();
+        assert!(usage.try_add_inode());
+    }
+
+    #[test]
+    fn open_files_enforce_the_limit() {
+        let mut files = OpenFiles::new(Some(2));
+        let (a, b) = (Resource::new_own(1), Resource::new_own(2));
+
+        files.track(&a);
+        files.track(&b);
+        let err = files.ensure_available().unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(ErrorCode::Quota)));
+        files.untrack(&a);
+        files.untrack(&a);
+        assert!(files.ensure_available().is_ok());
+        assert!(OpenFiles::default().ensure_available().is_ok());
+    }
+
+    #[test]
+    fn live_mounts_replace_and_hide_initial_mounts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let initial = [
+            DirectoryMapping::new("/host/lib", "/lib"),
+            DirectoryMapping::new("/host/data", "/data"),
+        ];
+        let guests = |mounts: &LiveMounts| -> Vec<String> {
+            mounts
+                .apply(&initial)
+                .into_iter()
+                .map(|mapping| mapping.guest)
+                .collect()
+        };
+        let mut mounts = LiveMounts::default();
+
+        mounts
+            .mount(DirectoryMapping::new(dir.path(), "/data"), false)
+            .expect("mount");
+        mounts
+            .mount(DirectoryMapping::new(dir.path(), "/request"), true)
+            .expect("mount");
+        assert_eq!(guests(&mounts), ["/lib", "/data", "/request"]);
+        assert_eq!(mounts.apply(&initial)[1].host, dir.path());
+
+        assert!(mounts.unmount("/lib", true));
+        assert!(!mounts.unmount("/lib", true), "already unmounted");
+        assert!(mounts.unmount("/data", true));
+        assert!(!mounts.unmount("/missing", false));
+        assert_eq!(guests(&mounts), ["/request"]);
+
+        assert!(
+            mounts
+                .mount(
+                    DirectoryMapping::new(dir.path().join("missing"), "/x"),
+                    false
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn write_budget_counts_every_byte() {
+        let budget = WriteBudget::new(10);
+
+        assert!(budget.try_charge(6));
+        assert!(budget.try_charge(4));
+        assert!(!budget.try_charge(1));
+        budget.refund(3);
+        assert!(budget.try_charge(3));
+        assert!(!budget.try_charge(1));
+    }
+
+    /// Stream whose writes are accepted and then fail in the background.
+    struct FailingStream;
+
+    #[async_trait::async_trait]
+    impl Pollable for FailingStream {
+        async fn ready(&mut self) {}
+    }
+
+    #[async_trait::async_trait]
+    impl OutputStream for FailingStream {
+        fn write(&mut self, _bytes: Bytes) -> StreamResult<()> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> StreamResult<()> {
+            Ok(())
+        }
+
+        fn check_write(&mut self) -> StreamResult<usize> {
+            Err(StreamError::LastOperationFailed(no_space_io_error()))
+        }
+    }
+
+    #[test]
+    fn stream_writes_are_refunded_when_they_fail_later() {
+        let usage = Arc::new(MountUsage::new(FsQuota::default().max_bytes(Some(8))));
+        let budget = Arc::new(WriteBudget::new(8));
+        let mut stream = QuotaOutputStream {
+            inner: Box::new(FailingStream),
+            usage: Some(Arc::clone(&usage)),
+            budget: Some(Arc::clone(&budget)),
+            position: Some(0),
+            size: 0,
+            pending: None,
+        };
+
+        stream.write(Bytes::from_static(b"abcdef")).unwrap();
+        stream.flush().unwrap();
+        assert!(!budget.try_charge(8));
+        assert!(stream.check_write().is_err());
+        assert_eq!(stream.size, 0);
+        assert!(budget.try_charge(8));
+        assert!(usage.try_grow(8));
+    }
+
+    #[test]
+    fn unlimited_usage_never_rejects() {
+        let usage = MountUsage::new(FsQuota::default());
+        assert!(usage.try_grow(u64::MAX / 2));
+        assert!(usage.try_add_inode());
+    }
+}
@@ -0,0 +1,725 @@
+use std::{
+    collections::{BTreeMap, btree_map},
+    fs::Metadata,
+    io,
+    io::Write as _,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use cap_fs_ext::{DirExt as _, FollowSymlinks, OpenOptionsFollowExt as _};
+use cap_std::{
+    ambient_authority,
+    fs::{Dir as CapDir, OpenOptions},
+};
+use wasmtime_wasi::DirPerms;
+
+use crate::{
+    internal::filesystem::MountUsage,
+    sandbox::{DirectoryMapping, GuestFileInfo, GuestFileKind},
+};
+
+/// Host location of a guest path inside a mount.
+#[derive(Clone)]
+pub struct ResolvedPath {
+    /// Host directory backing the mount.
+    pub root: PathBuf,
+    /// Lower overlay layers below `root`, highest first.
+    pub lower: Vec<PathBuf>,
+    /// Guest path normalized to `/`-separated components.
+    pub guest: String,
+    /// Path relative to the mount's host directory.
+    pub relative: PathBuf,
+    /// Whether the guest may create entries in the mount.
+    pub writable: bool,
+    /// Guest path of the mount.
+    pub mount: String,
+    /// Quota usage of the mount that host writes are charged to.
+    pub usage: Option<Arc<MountUsage>>,
+}
+
+impl ResolvedPath {
+    /// Fail unless the guest may create entries at this path.
+    pub fn ensure_writable(&self) -> io::Result<()> {
+        if self.writable {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("guest path '{}' is not in a writable mount", self.guest),
+            ))
+        }
+    }
+
+    /// Host directories of the mount, highest overlay layer first.
+    fn layers(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.root.as_path()).chain(self.lower.iter().map(PathBuf::as_path))
+    }
+}
+
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+}
+
+/// Find the innermost mount containing `guest_path`.
+///
+/// `..` components are rejected rather than resolved so a path can never
+/// name a file outside the mount it matched.
+pub fn resolve(mappings: &[DirectoryMapping], guest_path: &str) -> io::Result<ResolvedPath> {
+    if components(guest_path).any(|component| component == "..") {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("guest path '{guest_path}' must not contain '..'"),
+        ));
+    }
+    let path: Vec<&str> = components(guest_path).collect();
+    mappings
+        .iter()
+        .rev()
+        .filter_map(|mapping| {
+            let mount: Vec<&str> = components(&mapping.guest).collect();
+            path.starts_with(&mount).then_some((mount.len(), mapping))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(depth, mapping)| ResolvedPath {
+            root: mapping.host.clone(),
+            lower: mapping.lower.clone(),
+            guest: format!("/{}", path.join("/")),
+            relative: path[depth..].iter().collect(),
+            writable: mapping.dir_perms.contains(DirPerms::MUTATE),
+            mount: mapping.guest.clone(),
+            usage: None,
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no mount contains guest path '{guest_path}'"),
+            )
+        })
+}
+
+/// Resolve `relative` under `root`, refusing symlinks that lead outside it.
+async fn contained(root: &Path, relative: &Path) -> io::Result<PathBuf> {
+    let root = tokio::fs::canonicalize(root).await?;
+    let path = tokio::fs::canonicalize(root.join(relative)).await?;
+    if path.starts_with(&root) {
+        Ok(path)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("'{}' resolves outside its mount", relative.display()),
+        ))
+    }
+}
+
+/// List the regular files below `resolved`, returning sorted guest paths.
+///
+/// Files from every overlay layer are listed once. Symbolic links are not
+/// followed.
+pub async fn list_files(resolved: &ResolvedPath) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut found = false;
+    for root in resolved.layers() {
+        match contained(root, &resolved.relative).await {
+            Ok(dir) => {
+                found = true;
+                list_dir(dir, &resolved.guest, &mut files).await?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if !found {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("guest directory '{}' does not exist", resolved.guest),
+        ));
+    }
+    files.sort_unstable();
+    files.dedup();
+    Ok(files)
+}
+
+async fn list_dir(dir: PathBuf, guest: &str, files: &mut Vec<String>) -> io::Result<()> {
+    let mut pending = vec![(dir, guest.trim_end_matches('/').to_string())];
+    while let Some((dir, guest)) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_type = entry.file_type().await?;
+            let name = entry.file_name();
+            let guest = format!("{guest}/{}", name.to_string_lossy());
+            if file_type.is_dir() {
+                pending.push((entry.path(), guest));
+            } else if file_type.is_file() {
+                files.push(guest);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn file_info(guest: String, meta: &Metadata) -> GuestFileInfo {
+    let file_type = meta.file_type();
+    let kind = if file_type.is_dir() {
+        GuestFileKind::Directory
+    } else if file_type.is_file() {
+        GuestFileKind::File
+    } else if file_type.is_symlink() {
+        GuestFileKind::Symlink
+    } else {
+        GuestFileKind::Other
+    };
+    GuestFileInfo {
+        path: guest,
+        kind,
+        size: meta.len(),
+        modified: meta.modified().ok(),
+    }
+}
+
+/// List the entries of the directory at `resolved`, sorted by guest path.
+///
+/// Entries from every overlay layer are listed once, described by the
+/// highest layer holding them. Symbolic links are reported, not followed.
+pub async fn list_entries(resolved: &ResolvedPath) -> io::Result<Vec<GuestFileInfo>> {
+    let mut entries = BTreeMap::new();
+    let mut found = false;
+    for root in resolved.layers() {
+        let dir = match contained(root, &resolved.relative).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if !tokio::fs::metadata(&dir).await?.is_dir() {
+            return Err(not_a_directory(&resolved.guest));
+        }
+        found = true;
+        let mut reader = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = reader.next_entry().await? {
+            let guest = format!(
+                "{}/{}",
+                resolved.guest.trim_end_matches('/'),
+                entry.file_name().to_string_lossy()
+            );
+            if let btree_map::Entry::Vacant(slot) = entries.entry(guest) {
+                let info = file_info(slot.key().clone(), &entry.metadata().await?);
+                slot.insert(info);
+            }
+        }
+    }
+    if !found {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("guest directory '{}' does not exist", resolved.guest),
+        ));
+    }
+    Ok(entries.into_values().collect())
+}
+
+/// Describe the entry at `resolved` from the highest overlay layer holding
+/// it, without following a final symbolic link.
+pub async fn stat(resolved: &ResolvedPath) -> io::Result<GuestFileInfo> {
+    let parent = resolved.relative.parent().unwrap_or_else(|| Path::new(""));
+    for root in resolved.layers() {
+        let meta = match resolved.relative.file_name() {
+            Some(name) => match contained(root, parent).await {
+                Ok(dir) => tokio::fs::symlink_metadata(dir.join(name)).await,
+                Err(e) => Err(e),
+            },
+            None => tokio::fs::metadata(root).await,
+        };
+        match meta {
+            Ok(meta) => return Ok(file_info(resolved.guest.clone(), &meta)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("guest path '{}' does not exist", resolved.guest),
+    ))
+}
+
+/// Keep the guest paths in `files` whose path relative to the directory
+/// `guest` matches the glob `pattern`.
+///
+/// `*` and `?` never match `/`, while `**` matches any number of
+/// directories.
+pub fn filter_glob(files: Vec<String>, guest: &str, pattern: &str) -> io::Result<Vec<String>> {
+    let matcher = glob::Pattern::new(pattern).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid glob pattern '{pattern}': {e}"),
+        )
+    })?;
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::new()
+    };
+    let prefix = format!("{}/", guest.trim_end_matches('/'));
+    Ok(files
+        .into_iter()
+        .filter(|file| {
+            file.strip_prefix(&prefix)
+                .is_some_and(|relative| matcher.matches_with(relative, options))
+        })
+        .collect())
+}
+
+/// Make sure `resolved` names a directory, first creating any missing
+/// components in the mount's top layer when `create` is set.
+///
+/// Existing components must be real directories; symbolic links are not
+/// followed while creating.
+pub async fn ensure_dir(resolved: &ResolvedPath, create: bool) -> io::Result<()> {
+    if create {
+        let mut path = resolved.root.clone();
+        for component in resolved.relative.components() {
+            path.push(component);
+            match tokio::fs::symlink_metadata(&path).await {
+                Ok(meta) if meta.is_dir() => {}
+                Ok(_) => return Err(not_a_directory(&resolved.guest)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    tokio::fs::create_dir(&path).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    for root in resolved.layers() {
+        match contained(root, &resolved.relative).await {
+            Ok(path) if tokio::fs::metadata(&path).await?.is_dir() => return Ok(()),
+            Ok(_) => return Err(not_a_directory(&resolved.guest)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("guest directory '{}' does not exist", resolved.guest),
+    ))
+}
+
+fn not_a_directory(guest: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotADirectory,
+        format!("guest path '{guest}' is not a directory"),
+    )
+}
+
+/// Read the file at `resolved` from the highest overlay layer holding it.
+pub async fn read_file(resolved: &ResolvedPath) -> io::Result<Vec<u8>> {
+    let mut missing = None;
+    for root in resolved.layers() {
+        match contained(root, &resolved.relative).await {
+            Ok(path) => return tokio::fs::read(path).await,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => missing = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(missing.unwrap_or_else(|| io::ErrorKind::NotFound.into()))
+}
+
+/// Write `contents` to the file at `resolved`, replacing an existing file
+/// and creating missing parent directories, and charge the growth to the
+/// mount's quota.
+///
+/// Every component is opened relative to the mount's host directory without
+/// following symbolic links, so a guest swapping a link in while the host
+/// writes cannot redirect the write.
+pub async fn write_file(resolved: &ResolvedPath, contents: Vec<u8>) -> io::Result<()> {
+    resolved.ensure_writable()?;
+    let Some(name) = resolved.relative.file_name().map(PathBuf::from) else {
+        return Err(io::Error::new(
+            io::ErrorKind::IsADirectory,
+            format!("guest path '{}' is a mount point", resolved.guest),
+        ));
+    };
+    let root = resolved.root.clone();
+    let parent = resolved
+        .relative
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let guest = resolved.guest.clone();
+    let usage = resolved.usage.clone();
+    tokio::task::spawn_blocking(move || {
+        let root = CapDir::open_ambient_dir(&root, ambient_authority())?;
+        let (dir, missing) = open_existing_dirs(root, &parent, &guest)?;
+        let existing = match &missing[..] {
+            [] => existing_file_len(&dir, &name, &guest)?,
+            _ => None,
+        };
+        let len = u64::try_from(contents.len()).unwrap_or(u64::MAX);
+        let charge = HostWrite {
+            inodes: u64::try_from(missing.len())
+                .unwrap_or(u64::MAX)
+                .saturating_add(u64::from(existing.is_none())),
+            bytes: len.saturating_sub(existing.unwrap_or(0)),
+        };
+        charge.reserve(usage.as_deref(), &guest)?;
+        let result = create_dirs(dir, &missing).and_then(|dir| {
+            let mut file = dir.open_with(
+                &name,
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .follow(FollowSymlinks::No),
+            )?;
+            file.write_all(&contents)
+        });
+        match (result, usage) {
+            (Ok(()), Some(usage)) => {
+                usage.shrink(existing.unwrap_or(0).saturating_sub(len));
+                Ok(())
+            }
+            (Ok(()), None) => Ok(()),
+            (Err(e), usage) => {
+                charge.release(usage.as_deref());
+                Err(e)
+            }
+        }
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Quota a host write charges to its mount.
+pub struct HostWrite {
+    pub inodes: u64,
+    pub bytes: u64,
+}
+
+impl HostWrite {
+    pub fn reserve(&self, usage: Option<&MountUsage>, guest: &str) -> io::Result<()> {
+        let Some(usage) = usage else {
+            return Ok(());
+        };
+        if !usage.try_add_inodes(self.inodes) {
+            return Err(quota_exceeded(guest));
+        }
+        if !usage.try_grow(self.bytes) {
+            usage.remove_inodes(self.inodes);
+            return Err(quota_exceeded(guest));
+        }
+        Ok(())
+    }
+
+    pub fn release(&self, usage: Option<&MountUsage>) {
+        if let Some(usage) = usage {
+            usage.shrink(self.bytes);
+            usage.remove_inodes(self.inodes);
+        }
+    }
+}
+
+fn quota_exceeded(guest: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::StorageFull,
+        format!("writing guest path '{guest}' would exceed the mount's quota"),
+    )
+}
+
+/// Open the directories along `path` below `root` that exist, without
+/// following symbolic links, returning the deepest one and the components
+/// missing below it.
+pub fn open_existing_dirs(
+    root: CapDir,
+    path: &Path,
+    guest: &str,
+) -> io::Result<(CapDir, Vec<PathBuf>)> {
+    let mut dir = root;
+    let mut missing = Vec::new();
+    for component in path.components() {
+        if !missing.is_empty() {
+            missing.push(PathBuf::from(component.as_os_str()));
+            continue;
+        }
+        match dir.open_dir_nofollow(component) {
+            Ok(next) => dir = next,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                missing.push(PathBuf::from(component.as_os_str()));
+            }
+            Err(_) => return Err(not_a_directory(guest)),
+        }
+    }
+    Ok((dir, missing))
+}
+
+pub fn create_dirs(mut dir: CapDir, missing: &[PathBuf]) -> io::Result<CapDir> {
+    for component in missing {
+        dir.create_dir(component)?;
+        dir = dir.open_dir_nofollow(component)?;
+    }
+    Ok(dir)
+}
+
+/// Size of the regular file `name` in `dir`, or `None` if there is none.
+fn existing_file_len(dir: &CapDir, name: &Path, guest: &str) -> io::Result<Option<u64>> {
+    match dir.symlink_metadata(name) {
+        Ok(meta) if meta.is_dir() => Err(io::Error::new(
+            io::ErrorKind::IsADirectory,
+            format!("guest path '{guest}' is a directory"),
+        )),
+        Ok(meta) if meta.is_symlink() => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("guest path '{guest}' is a symbolic link"),
+        )),
+        Ok(meta) => Ok(Some(meta.len())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_to_the_innermost_mount() {
+        let mappings = [
+            DirectoryMapping::new("/host/root", "/data"),
+            DirectoryMapping::new("/host/out", "/data/out/"),
+        ];
+        let resolved = resolve(&mappings, "/data/out/./plots/a.png").expect("resolved");
+        assert_eq!(resolved.root, Path::new("/host/out"));
+        assert_eq!(resolved.guest, "/data/out/plots/a.png");
+        assert_eq!(resolved.relative, Path::new("plots/a.png"));
+
+        let resolved = resolve(&mappings, "/data/output.csv").expect("resolved");
+        assert_eq!(resolved.root, Path::new("/host/root"));
+
+        assert_eq!(
+            resolve(&mappings, "/tmp/a").err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+        assert_eq!(
+            resolve(&mappings, "/data/out/../../etc/passwd")
+                .err()
+                .map(|e| e.kind()),
+            Some(io::ErrorKind::PermissionDenied)
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_and_reads_files_below_a_mount() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir(dir.path().join("plots")).expect("mkdir");
+        std::fs::write(dir.path().join("result.csv"), "a,b\n").expect("write");
+        std::fs::write(dir.path().join("plots/a.png"), [0x89]).expect("write");
+        let mappings = [DirectoryMapping::new(dir.path(), "/tmp")];
+
+        let root = resolve(&mappings, "/tmp").expect("resolved");
+        assert_eq!(
+            list_files(&root).await.expect("listed"),
+            ["/tmp/plots/a.png", "/tmp/result.csv"]
+        );
+        let file = resolve(&mappings, "/tmp/result.csv").expect("resolved");
+        assert_eq!(read_file(&file).await.expect("read"), b"a,b\n");
+
+        #[cfg(unix)]
+        {
+            let outside = tempfile::tempdir().expect("tempdir");
+            std::fs::write(outside.path().join("secret"), "x").expect("write");
+            std::os::unix::fs::symlink(outside.path().join("secret"), dir.path().join("link"))
+                .expect("symlink");
+            let link = resolve(&mappings, "/tmp/link").expect("resolved");
+            assert_eq!(
+                read_file(&link).await.err().map(|e| e.kind()),
+                Some(io::ErrorKind::PermissionDenied)
+            );
+            assert!(
+                !list_files(&root)
+                    .await
+                    .expect("listed")
+                    .contains(&"/tmp/link".to_string())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_and_stats_entries_across_overlay_layers() {
+        let upper = tempfile::tempdir().expect("tempdir");
+        let lower = tempfile::tempdir().expect("tempdir");
+        std::fs::write(upper.path().join("plot.png"), [0x89, 0x50]).expect("write");
+        std::fs::write(lower.path().join("plot.png"), "old").expect("write");
+        std::fs::create_dir(lower.path().join("data")).expect("mkdir");
+        let mut mapping = DirectoryMapping::new(upper.path(), "/out");
+        mapping.lower = vec![lower.path().to_path_buf()];
+        let mappings = [mapping];
+
+        let root = resolve(&mappings, "/out/").expect("resolved");
+        let entries = list_entries(&root).await.expect("listed");
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|info| (info.path.as_str(), info.kind, info.size))
+            .collect();
+        assert_eq!(summary[0].0, "/out/data");
+        assert_eq!(summary[0].1, GuestFileKind::Directory);
+        assert_eq!(summary[1], ("/out/plot.png", GuestFileKind::File, 2));
+        assert_eq!(summary.len(), 2);
+
+        let plot = resolve(&mappings, "/out/plot.png").expect("resolved");
+        assert_eq!(stat(&plot).await.expect("stat"), entries[1]);
+        assert_eq!(
+            stat(&root).await.expect("stat").kind,
+            GuestFileKind::Directory
+        );
+        assert_eq!(
+            list_entries(&plot).await.err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotADirectory)
+        );
+        let missing = resolve(&mappings, "/out/missing").expect("resolved");
+        assert_eq!(
+            stat(&missing).await.err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc/passwd", upper.path().join("link")).expect("symlink");
+            let link = resolve(&mappings, "/out/link").expect("resolved");
+            assert_eq!(
+                stat(&link).await.expect("stat").kind,
+                GuestFileKind::Symlink
+            );
+        }
+    }
+
+    #[test]
+    fn filters_files_by_glob_relative_to_the_directory() {
+        let files = ["/src/a.py", "/src/b.txt", "/src/pkg/c.py", "/srcx/d.py"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            filter_glob(files.clone(), "/src", "*.py").expect("filtered"),
+            ["/src/a.py"]
+        );
+        assert_eq!(
+            filter_glob(files.clone(), "/src/", "**/*.py").expect("filtered"),
+            ["/src/a.py", "/src/pkg/c.py"]
+        );
+        assert_eq!(
+            filter_glob(files, "/src", "[").err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_files_only_into_writable_mounts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let writable = [DirectoryMapping::new(dir.path(), "/work")
+            .with_permissions(DirPerms::all(), wasmtime_wasi::FilePerms::all())];
+        let read_only = [DirectoryMapping::new(dir.path(), "/work")];
+
+        let file = resolve(&writable, "/work/in/data.csv").expect("resolved");
+        write_file(&file, b"a,b\n".to_vec()).await.expect("written");
+        write_file(&file, b"c,d\n".to_vec())
+            .await
+            .expect("replaced");
+        assert_eq!(read_file(&file).await.expect("read"), b"c,d\n");
+
+        let file = resolve(&read_only, "/work/other.csv").expect("resolved");
+        assert_eq!(
+            write_file(&file, Vec::new()).await.err().map(|e| e.kind()),
+            Some(io::ErrorKind::PermissionDenied)
+        );
+        for guest_path in ["/work", "/work/in"] {
+            let dir = resolve(&writable, guest_path).expect("resolved");
+            assert_eq!(
+                write_file(&dir, Vec::new()).await.err().map(|e| e.kind()),
+                Some(io::ErrorKind::IsADirectory)
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            let outside = tempfile::tempdir().expect("tempdir");
+            std::os::unix::fs::symlink(outside.path().join("target"), dir.path().join("link"))
+                .expect("symlink");
+            let link = resolve(&writable, "/work/link").expect("resolved");
+            assert_eq!(
+                write_file(&link, Vec::new()).await.err().map(|e| e.kind()),
+                Some(io::ErrorKind::PermissionDenied)
+            );
+            assert!(!outside.path().join("target").exists());
+
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).expect("symlink");
+            let escaped = resolve(&writable, "/work/escape/data.csv").expect("resolved");
+            assert!(write_file(&escaped, Vec::new()).await.is_err());
+            assert!(!outside.path().join("data.csv").exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn host_writes_count_against_the_mount_quota() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let writable = [DirectoryMapping::new(dir.path(), "/work")
+            .with_permissions(DirPerms::all(), wasmtime_wasi::FilePerms::all())];
+        let usage = Arc::new(MountUsage::new(
+            crate::sandbox::FsQuota::default()
+                .max_bytes(Some(8))
+                .max_inodes(Some(3)),
+        ));
+        let at = |guest_path| {
+            let mut resolved = resolve(&writable, guest_path).expect("resolved");
+            resolved.usage = Some(Arc::clone(&usage));
+            resolved
+        };
+
+        write_file(&at("/work/in/a"), vec![b'x'; 6])
+            .await
+            .expect("written");
+        write_file(&at("/work/in/a"), vec![b'x'; 2])
+            .await
+            .expect("replaced");
+        assert_eq!(
+            write_file(&at("/work/in/b"), vec![b'x'; 7])
+                .await
+                .err()
+                .map(|e| e.kind()),
+            Some(io::ErrorKind::StorageFull)
+        );
+        assert!(!dir.path().join("in/b").exists());
+        write_file(&at("/work/in/b"), vec![b'x'; 6])
+            .await
+            .expect("written");
+        assert_eq!(
+            write_file(&at("/work/c"), Vec::new())
+                .await
+                .err()
+                .map(|e| e.kind()),
+            Some(io::ErrorKind::StorageFull)
+        );
+    }
+
+    #[tokio::test]
+    async fn creates_missing_directories_only_in_writable_mounts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(dir.path().join("file"), "x").expect("write");
+        let writable = [DirectoryMapping::new(dir.path(), "/work")
+            .with_permissions(DirPerms::all(), wasmtime_wasi::FilePerms::all())];
+        let read_only = [DirectoryMapping::new(dir.path(), "/work")];
+
+        let nested = resolve(&read_only, "/work/a/b").expect("resolved");
+        assert!(!nested.writable);
+        assert_eq!(
+            ensure_dir(&nested, false).await.err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+
+        let nested = resolve(&writable, "/work/a/b").expect("resolved");
+        ensure_dir(&nested, true).await.expect("created");
+        assert!(dir.path().join("a/b").is_dir());
+        let nested = resolve(&read_only, "/work/a/b").expect("resolved");
+        ensure_dir(&nested, false).await.expect("exists");
+
+        let file = resolve(&writable, "/work/file/sub").expect("resolved");
+        assert_eq!(
+            ensure_dir(&file, true).await.err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotADirectory)
+        );
+    }
+}
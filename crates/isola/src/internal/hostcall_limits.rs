@@ -0,0 +1,138 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+/// Hostcall limits configured with
+/// [`SandboxOptions::hostcall_limits`](crate::sandbox::SandboxOptions::hostcall_limits).
+#[derive(Clone, Copy, Debug)]
+pub struct HostcallLimits {
+    pub max_calls: u64,
+    pub max_concurrent: usize,
+    pub min_interval: Duration,
+}
+
+/// Reason a hostcall was rejected before reaching the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostcallLimitExceeded {
+    Calls(u64),
+    Concurrent(usize),
+}
+
+impl fmt::Display for HostcallLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Calls(max) => write!(f, "hostcall limit exceeded: at most {max} per call"),
+            Self::Concurrent(max) => {
+                write!(f, "hostcall limit exceeded: at most {max} concurrent")
+            }
+        }
+    }
+}
+
+/// Enforces [`HostcallLimits`] for the hostcalls of one sandbox.
+pub struct HostcallLimiter {
+    limits: HostcallLimits,
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    calls: u64,
+    in_flight: usize,
+    next_start: Option<Instant>,
+}
+
+/// Slot held by a running hostcall; frees its concurrency slot when dropped.
+pub struct HostcallPermit {
+    limiter: Arc<HostcallLimiter>,
+    start_at: Instant,
+}
+
+impl HostcallLimiter {
+    pub fn new(limits: HostcallLimits) -> Self {
+        Self {
+            limits,
+            state: Mutex::new(LimiterState::default()),
+        }
+    }
+
+    /// Start counting calls for a new guest operation.
+    pub fn reset(&self) {
+        self.state.lock().calls = 0;
+    }
+
+    /// Admit one hostcall, reserving the earliest start time that keeps
+    /// calls `min_interval` apart.
+    pub fn acquire(self: &Arc<Self>) -> Result<HostcallPermit, HostcallLimitExceeded> {
+        let mut state = self.state.lock();
+        if state.calls >= self.limits.max_calls {
+            return Err(HostcallLimitExceeded::Calls(self.limits.max_calls));
+        }
+        if state.in_flight >= self.limits.max_concurrent {
+            return Err(HostcallLimitExceeded::Concurrent(
+                self.limits.max_concurrent,
+            ));
+        }
+        state.calls += 1;
+        state.in_flight += 1;
+        let now = Instant::now();
+        let start_at = state.next_start.map_or(now, |next| next.max(now));
+        state.next_start = Some(start_at + self.limits.min_interval);
+        drop(state);
+        Ok(HostcallPermit {
+            limiter: Arc::clone(self),
+            start_at,
+        })
+    }
+}
+
+impl HostcallPermit {
+    /// Wait until this call may reach the host.
+    pub async fn ready(&self) {
+        tokio::time::sleep_until(self.start_at.into()).await;
+    }
+}
+
+impl Drop for HostcallPermit {
+    fn drop(&mut self) {
+        self.limiter.state.lock().in_flight -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiter_counts_calls_and_concurrency() {
+        let limiter = Arc::new(HostcallLimiter::new(HostcallLimits {
+            max_calls: 3,
+            max_concurrent: 2,
+            min_interval: Duration::from_millis(50),
+        }));
+        let first = limiter.acquire().expect("first call");
+        let second = limiter.acquire().expect("second call");
+        assert_eq!(
+            second.start_at - first.start_at,
+            Duration::from_millis(50),
+            "calls are spaced by the minimum interval"
+        );
+        assert!(matches!(
+            limiter.acquire(),
+            Err(HostcallLimitExceeded::Concurrent(2))
+        ));
+        drop(first);
+        let _third = limiter.acquire().expect("freed slot");
+        drop(second);
+        assert!(matches!(
+            limiter.acquire(),
+            Err(HostcallLimitExceeded::Calls(3))
+        ));
+        limiter.reset();
+        assert!(limiter.acquire().is_ok(), "counts restart with each call");
+    }
+}
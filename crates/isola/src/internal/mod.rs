@@ -1,5 +1,19 @@
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod call_trace;
+pub mod clock;
+pub mod entropy;
+pub mod file_policy;
+pub mod filesystem;
+pub mod guest_files;
+pub mod hostcall_limits;
 pub mod module;
+pub mod mount_filter;
+pub mod overlay;
+pub mod plugin;
 pub mod resource;
 pub mod sandbox;
+pub mod scratch;
+pub mod stdio;
 pub mod trace_output;
 pub mod wasm;
@@ -2,15 +2,22 @@ use std::{
     collections::hash_map::DefaultHasher,
     fmt::Write as _,
     hash::{Hash, Hasher},
-    path::Path,
+    path::{Path, PathBuf},
     sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
 };
 
 use sha2::{Digest, Sha256};
 use wasmtime::Engine;
 
 use super::ModuleConfig;
-use crate::sandbox::{Error, Result};
+use crate::sandbox::{CacheGcStats, Error, Result};
+
+const CACHE_EXTENSION: &str = "cwasm";
+
+/// Age after which a temporary file is assumed to be left over from an
+/// interrupted write rather than one still in progress.
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(60 * 60);
 
 fn engine_fingerprint(engine: &Engine) -> u64 {
     let mut hasher = DefaultHasher::new();
@@ -32,9 +39,16 @@ pub fn cache_key(engine: &Engine, cfg: &ModuleConfig, wasm_bytes: &[u8]) -> Stri
     for mapping in &cfg.directory_mappings {
         h.update(mapping.guest.as_bytes());
         h.update([0]);
-        let host = mapping.host.to_string_lossy();
-        h.update(host.as_bytes());
+        h.update(mapping.source_key().as_bytes());
         h.update([0]);
+        for lower in &mapping.lower {
+            h.update(lower.to_string_lossy().as_bytes());
+            h.update([0]);
+        }
+        for pattern in &mapping.include {
+            h.update(pattern.as_bytes());
+            h.update([0]);
+        }
         h.update(mapping.dir_perms.bits().to_le_bytes());
         h.update(mapping.file_perms.bits().to_le_bytes());
     }
@@ -47,16 +61,23 @@ pub fn cache_key(engine: &Engine, cfg: &ModuleConfig, wasm_bytes: &[u8]) -> Stri
         h.update([0]);
     }
 
-    if let Some(prelude) = &cfg.prelude {
-        h.update([1]);
-        h.update(prelude.as_bytes());
-    } else {
-        h.update([0]);
+    h.update((cfg.preludes.len() as u64).to_le_bytes());
+    for prelude in &cfg.preludes {
+        h.update((prelude.code.len() as u64).to_le_bytes());
+        h.update(prelude.code.as_bytes());
     }
 
     h.update((cfg.max_memory as u64).to_le_bytes());
     // Optimization level is fixed in `configure_engine`.
     h.update([1]);
+    // Only tagged when they differ from the default, so existing entries
+    // stay valid.
+    if !cfg.copy_on_write {
+        h.update(b"no-copy-on-write");
+    }
+    if cfg.snapshots {
+        h.update(b"snapshots");
+    }
 
     let digest = h.finalize();
     let mut out = String::with_capacity(digest.len() * 2);
@@ -70,8 +91,10 @@ pub async fn write_cache_file_atomic(cache_path: &Path, bytes: &[u8]) -> Result<
     static CACHE_WRITE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
 
     let sequence = CACHE_WRITE_SEQUENCE.fetch_add(1, Ordering::Relaxed);
-    let tmp_path =
-        cache_path.with_extension(format!("cwasm.tmp-{}-{sequence}", std::process::id()));
+    let tmp_path = cache_path.with_extension(format!(
+        "{CACHE_EXTENSION}.tmp-{}-{sequence}",
+        std::process::id()
+    ));
 
     tokio::fs::write(&tmp_path, bytes)
         .await
@@ -89,3 +112,378 @@ pub async fn write_cache_file_atomic(cache_path: &Path, bytes: &[u8]) -> Result<
         }
     }
 }
+
+/// Exclusive cross-process lock on one cache entry.
+///
+/// The lock is released when the guard is dropped.
+pub struct CacheEntryLock {
+    _file: std::fs::File,
+}
+
+/// Block until this process holds the compile lock for `cache_path`.
+///
+/// Processes that miss the cache at the same time serialize here, so only the
+/// first one compiles and the rest pick up its artifact. Returns `None` when
+/// the filesystem does not support locking; callers then fall back to
+/// uncoordinated compilation, which the atomic rename in
+/// [`write_cache_file_atomic`] still keeps safe.
+pub async fn lock_cache_entry(cache_path: &Path) -> Result<Option<CacheEntryLock>> {
+    let lock_path = lock_file_path(cache_path);
+    let locked = tokio::task::spawn_blocking(move || {
+        loop {
+            let file = std::fs::File::options()
+                .create(true)
+                .write(true)
+                .truncate(false)
+                .open(&lock_path)?;
+            match file.lock() {
+                // Garbage collection may have removed the file while this
+                // process waited on it; lock the file now at the path instead.
+                Ok(()) if !is_current_lock_file(&file, &lock_path) => {}
+                Ok(()) => return Ok(Some(CacheEntryLock { _file: file })),
+                Err(e) if e.kind() == std::io::ErrorKind::Unsupported => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    })
+    .await
+    .map_err(|e| Error::Other(e.into()))?;
+    locked.map_err(Error::from)
+}
+
+fn lock_file_path(cache_path: &Path) -> PathBuf {
+    cache_path.with_extension(format!("{CACHE_EXTENSION}.lock"))
+}
+
+/// Whether `file` is still the lock file linked at `lock_path`.
+#[cfg(unix)]
+fn is_current_lock_file(file: &std::fs::File, lock_path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt as _;
+
+    match (file.metadata(), std::fs::metadata(lock_path)) {
+        (Ok(locked), Ok(current)) => locked.dev() == current.dev() && locked.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+/// Whether `file` is still the lock file linked at `lock_path`.
+///
+/// Windows refuses to remove a file another process has open, so a lock file
+/// cannot disappear from under a process waiting on it.
+#[cfg(not(unix))]
+const fn is_current_lock_file(_file: &std::fs::File, _lock_path: &Path) -> bool {
+    true
+}
+
+/// Mark a cache entry as recently used so age and size eviction keep it.
+pub fn touch_cache_file(cache_path: &Path) {
+    if let Ok(file) = std::fs::File::options().write(true).open(cache_path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Evict compiled artifacts older than `max_age`, then the least recently
+/// used ones until the directory fits in `max_size` bytes.
+///
+/// Compile lock files whose artifact is gone and that no process holds are
+/// removed as well, and so are temporary files left behind by interrupted
+/// writes.
+///
+/// `keep` is never evicted, so a freshly written entry survives a cleanup
+/// triggered by its own build.
+pub async fn gc_cache_dir(
+    cache_dir: &Path,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    keep: Option<&Path>,
+) -> std::io::Result<CacheGcStats> {
+    let mut stats = CacheGcStats::default();
+    let mut entries = match list_cache_entries(cache_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(stats),
+        Err(e) => return Err(e),
+    };
+    // Oldest first, so size eviction drops the least recently used entries.
+    entries.sort_by_key(|entry| entry.modified);
+
+    let now = SystemTime::now();
+    let mut retained = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let expired = max_age.is_some_and(|max_age| {
+            now.duration_since(entry.modified)
+                .is_ok_and(|age| age > max_age)
+        });
+        if expired && Some(entry.path.as_path()) != keep {
+            remove_cache_entry(entry, &mut stats).await?;
+        } else {
+            retained.push(entry);
+        }
+    }
+
+    let mut total: u64 = retained.iter().map(|entry| entry.len).sum();
+    if let Some(max_size) = max_size {
+        for entry in std::mem::take(&mut retained) {
+            if total > max_size && Some(entry.path.as_path()) != keep {
+                total -= entry.len;
+                remove_cache_entry(entry, &mut stats).await?;
+            } else {
+                retained.push(entry);
+            }
+        }
+    }
+
+    let dir = cache_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || remove_stale_files(&dir, now))
+        .await
+        .map_err(std::io::Error::other)??;
+
+    stats.retained_entries = retained.len();
+    stats.retained_bytes = total;
+    Ok(stats)
+}
+
+/// Remove lock files of evicted entries and stale temporary files from
+/// `cache_dir`.
+fn remove_stale_files(cache_dir: &Path, now: SystemTime) -> std::io::Result<()> {
+    let artifact_suffix = format!(".{CACHE_EXTENSION}");
+    let temp_marker = format!(".{CACHE_EXTENSION}.tmp-");
+    for entry in std::fs::read_dir(cache_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Some(artifact) = name.strip_suffix(".lock") {
+            if artifact.ends_with(&artifact_suffix) && !cache_dir.join(artifact).exists() {
+                remove_unheld_lock_file(&path);
+            }
+        } else if name.contains(&temp_marker) {
+            let stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| {
+                    now.duration_since(modified)
+                        .is_ok_and(|age| age > STALE_TEMP_FILE_AGE)
+                });
+            if stale {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove the lock file at `lock_path` unless a process holds it.
+///
+/// The file is removed while this process holds the lock. A process that
+/// opened it earlier and then gets the lock notices the file is gone and
+/// locks the new one, so two processes never compile the same entry at once.
+fn remove_unheld_lock_file(lock_path: &Path) {
+    let Ok(file) = std::fs::File::options().write(true).open(lock_path) else {
+        return;
+    };
+    if file.try_lock().is_ok() {
+        // Fails on Windows while another process has the file open.
+        let _ = std::fs::remove_file(lock_path);
+    }
+}
+
+struct CacheEntry {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
+
+async fn list_cache_entries(cache_dir: &Path) -> std::io::Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    let mut dir = tokio::fs::read_dir(cache_dir).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(CACHE_EXTENSION) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        entries.push(CacheEntry {
+            path,
+            len: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    Ok(entries)
+}
+
+async fn remove_cache_entry(entry: CacheEntry, stats: &mut CacheGcStats) -> std::io::Result<()> {
+    match tokio::fs::remove_file(&entry.path).await {
+        // The entry's compile lock file is left to `remove_stale_files`, which
+        // only removes it once no process holds it.
+        Ok(()) => {
+            stats.removed_entries += 1;
+            stats.removed_bytes += entry.len;
+            Ok(())
+        }
+        // Another process evicted the same entry first.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_entry(dir: &Path, name: &str, len: usize, age: Duration) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, vec![0_u8; len]).expect("write cache entry");
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .expect("open cache entry")
+            .set_modified(SystemTime::now() - age)
+            .expect("set mtime");
+        path
+    }
+
+    #[tokio::test]
+    async fn gc_evicts_expired_then_least_recently_used_entries() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let expired = write_entry(dir.path(), "a.cwasm", 10, Duration::from_secs(7200));
+        let oldest = write_entry(dir.path(), "b.cwasm", 10, Duration::from_secs(120));
+        let newest = write_entry(dir.path(), "c.cwasm", 10, Duration::from_secs(60));
+        let unrelated = write_entry(dir.path(), "notes.txt", 100, Duration::from_secs(7200));
+
+        let stats = gc_cache_dir(dir.path(), Some(15), Some(Duration::from_secs(3600)), None)
+            .await
+            .expect("gc");
+
+        assert_eq!(stats.removed_entries, 2);
+        assert_eq!(stats.removed_bytes, 20);
+        assert_eq!(stats.retained_entries, 1);
+        assert_eq!(stats.retained_bytes, 10);
+        assert!(!expired.exists());
+        assert!(!oldest.exists());
+        assert!(newest.exists());
+        assert!(unrelated.exists());
+    }
+
+    #[test]
+    fn cache_key_tags_non_default_builds() {
+        let engine = Engine::default();
+        let mut cfg = ModuleConfig {
+            cache: None,
+            cache_max_size: None,
+            cache_max_age: None,
+            max_memory: usize::MAX,
+            directory_mappings: Vec::new(),
+            env: Vec::new(),
+            preludes: Vec::new(),
+            compile_threads: None,
+            copy_on_write: true,
+            snapshots: false,
+            namespace: None,
+            linker_extensions: false,
+        };
+        let plain = cache_key(&engine, &cfg, b"wasm");
+        cfg.snapshots = true;
+        assert_ne!(cache_key(&engine, &cfg, b"wasm"), plain);
+        cfg.snapshots = false;
+        cfg.copy_on_write = false;
+        assert_ne!(cache_key(&engine, &cfg, b"wasm"), plain);
+    }
+
+    #[tokio::test]
+    async fn cache_entry_lock_is_exclusive() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cache_path = dir.path().join("a.cwasm");
+
+        let held = lock_cache_entry(&cache_path)
+            .await
+            .expect("lock")
+            .expect("locking supported");
+        let lock_path = dir.path().join("a.cwasm.lock");
+        let other = std::fs::File::open(&lock_path).expect("open lock file");
+        assert!(other.try_lock().is_err());
+
+        drop(held);
+        other.try_lock().expect("lock released on drop");
+    }
+
+    #[tokio::test]
+    async fn gc_keeps_the_protected_entry() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let kept = write_entry(dir.path(), "a.cwasm", 10, Duration::from_secs(7200));
+
+        let stats = gc_cache_dir(dir.path(), Some(0), Some(Duration::ZERO), Some(&kept))
+            .await
+            .expect("gc");
+
+        assert_eq!(stats.removed_entries, 0);
+        assert!(kept.exists());
+    }
+
+    #[tokio::test]
+    async fn gc_leaves_held_compile_locks_in_place() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let evicted = write_entry(dir.path(), "a.cwasm", 10, Duration::from_secs(7200));
+        let held = lock_cache_entry(&evicted)
+            .await
+            .expect("lock")
+            .expect("locking supported");
+
+        let stats = gc_cache_dir(dir.path(), None, Some(Duration::ZERO), None)
+            .await
+            .expect("gc");
+
+        assert_eq!(stats.removed_entries, 1);
+        assert!(!evicted.exists());
+        let lock_path = dir.path().join("a.cwasm.lock");
+        let other = std::fs::File::open(&lock_path).expect("lock file kept");
+        assert!(
+            other.try_lock().is_err(),
+            "the held lock must stay effective"
+        );
+        drop(held);
+    }
+
+    #[tokio::test]
+    async fn gc_removes_unheld_locks_of_evicted_entries() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let evicted = write_entry(dir.path(), "a.cwasm", 10, Duration::from_secs(7200));
+        let kept = write_entry(dir.path(), "b.cwasm", 10, Duration::ZERO);
+        for path in [&evicted, &kept] {
+            drop(lock_cache_entry(path).await.expect("lock"));
+        }
+
+        gc_cache_dir(dir.path(), None, Some(Duration::from_secs(3600)), None)
+            .await
+            .expect("gc");
+
+        assert!(!dir.path().join("a.cwasm.lock").exists());
+        assert!(dir.path().join("b.cwasm.lock").exists());
+        let relocked = lock_cache_entry(&evicted)
+            .await
+            .expect("lock")
+            .expect("locking supported");
+        assert!(dir.path().join("a.cwasm.lock").exists());
+        drop(relocked);
+    }
+
+    #[tokio::test]
+    async fn gc_removes_stale_temp_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let stale = write_entry(dir.path(), "a.cwasm.tmp-1-0", 10, Duration::from_secs(7200));
+        let in_progress = write_entry(dir.path(), "a.cwasm.tmp-1-1", 10, Duration::ZERO);
+
+        let stats = gc_cache_dir(dir.path(), None, None, None)
+            .await
+            .expect("gc");
+
+        assert_eq!(stats.retained_entries, 0);
+        assert!(!stale.exists());
+        assert!(in_progress.exists());
+    }
+}
@@ -1,30 +1,123 @@
-use wasmtime::Store;
+use std::{sync::Arc, time::Instant};
+
+use wasmtime::{
+    Store, Trap, UpdateDeadline,
+    component::{ComponentNamedList, Lift, Lower, TypedFunc},
+};
 
 use crate::{
-    host::{Host, OutputTarget},
-    internal::sandbox::InstanceState,
+    host::{Host, OutputTarget, with_call_id},
+    internal::sandbox::{InstanceState, output_limits::OutputLimits},
+    sandbox::{CapabilitySet, Error, InterruptState, Result},
 };
 
-/// RAII guard that clears the output target when dropped, even if the call
-/// panics or returns early.
+/// RAII guard that clears the output target, call capabilities, output
+/// limits, deadline and interrupt state and records the call's fuel consumption when dropped, even
+/// if the call panics or returns early.
 pub struct CallCleanup<'a, H: Host> {
     pub store: &'a mut Store<InstanceState<H>>,
+    /// Fuel the store held when the call started, if the engine meters fuel.
+    fuel_start: Option<u64>,
+    interrupt: Option<Arc<InterruptState>>,
 }
 
 impl<'a, H: Host> CallCleanup<'a, H> {
     pub const fn new(store: &'a mut Store<InstanceState<H>>) -> Self {
-        Self { store }
+        Self {
+            store,
+            fuel_start: None,
+            interrupt: None,
+        }
+    }
+
+    /// Interrupt guest code when `interrupt` is requested or at the first
+    /// epoch tick after `deadline`.
+    pub fn set_interrupts(&mut self, deadline: Option<Instant>, interrupt: Arc<InterruptState>) {
+        interrupt.start();
+        let requested = Arc::clone(&interrupt);
+        let ticks = self.store.data().epoch_yield_ticks();
+        self.store.epoch_deadline_callback(move |mut store| {
+            store.data_mut().record_guest_ticks(ticks);
+            Ok(
+                if requested.requested() || deadline.is_some_and(|d| Instant::now() >= d) {
+                    UpdateDeadline::Interrupt
+                } else {
+                    UpdateDeadline::Yield(ticks)
+                },
+            )
+        });
+        self.interrupt = Some(interrupt);
+    }
+
+    /// Classify a failed call, reporting [`Error::Cancelled`] when it was
+    /// interrupted through an [`InterruptHandle`](crate::sandbox::InterruptHandle).
+    pub fn classify_error(&self, error: wasmtime::Error) -> Error {
+        if self.interrupt.as_ref().is_some_and(|i| i.requested()) {
+            return Error::Cancelled;
+        }
+        self.store.data().classify_error(error)
+    }
+
+    /// Give the call `budget` units of fuel, or an unlimited amount with
+    /// `None`, and start counting what it consumes.
+    ///
+    /// Does nothing without a budget when the engine does not meter fuel.
+    pub fn set_fuel_budget(&mut self, budget: Option<u64>) -> Result<()> {
+        self.store.data_mut().set_last_fuel_consumed(None);
+        if self.store.get_fuel().is_err() {
+            return match budget {
+                Some(_) => Err(Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "fuel budgets require a template built with fuel metering",
+                ))),
+                None => Ok(()),
+            };
+        }
+        let fuel = budget.unwrap_or(u64::MAX);
+        self.store.set_fuel(fuel).map_err(Error::Wasm)?;
+        self.fuel_start = Some(fuel);
+        Ok(())
     }
 
     pub fn set_output_target(&mut self, target: OutputTarget) {
         self.store.data_mut().set_output_target(Some(target));
     }
+
+    pub fn set_capabilities(&mut self, capabilities: Option<CapabilitySet>) {
+        self.store.data_mut().set_capabilities(capabilities);
+    }
+
+    pub fn set_output_limits(&mut self, max_bytes: Option<usize>, max_items: Option<usize>) {
+        self.store
+            .data_mut()
+            .set_output_limits(OutputLimits::new(max_bytes, max_items));
+    }
 }
 
 impl<H: Host> Drop for CallCleanup<'_, H> {
     fn drop(&mut self) {
         // Cleanup only; explicit flush is handled by call sites.
         self.store.data_mut().set_output_target(None);
+        self.store.data_mut().set_capabilities(None);
+        self.store
+            .data_mut()
+            .set_output_limits(OutputLimits::default());
+        if let Some(start) = self.fuel_start.take() {
+            let consumed = self
+                .store
+                .get_fuel()
+                .ok()
+                .map(|left| start.saturating_sub(left));
+            self.store.data_mut().set_last_fuel_consumed(consumed);
+            // Fuel is metered here, so this cannot fail; between calls the
+            // guest runs unbudgeted.
+            let _ = self.store.set_fuel(u64::MAX);
+        }
+        if let Some(interrupt) = self.interrupt.take() {
+            interrupt.finish();
+            let ticks = self.store.data().epoch_yield_ticks();
+            self.store.epoch_deadline_async_yield_and_update(ticks);
+        }
     }
 }
 
@@ -55,3 +148,50 @@ impl<H: Host> wasmtime::AsContextMut for CallCleanup<'_, H> {
         wasmtime::AsContextMut::as_context_mut(&mut *self.store)
     }
 }
+
+/// Invoke a guest export.
+///
+/// With `native_async`, the export runs as a component-model async task inside
+/// the store's event loop, so guest subtasks and pending host futures make
+/// progress together rather than through one awaited call at a time.
+///
+/// Guest code is interrupted by the store's epoch deadline; `deadline` and
+/// `interrupt` also bound time the call spends waiting on the host, after
+/// which the call is abandoned with [`Trap::Interrupt`].
+pub async fn call_export<H, Params, Return>(
+    store: &mut Store<InstanceState<H>>,
+    func: TypedFunc<Params, Return>,
+    params: Params,
+    native_async: bool,
+    deadline: Option<Instant>,
+    interrupt: &InterruptState,
+) -> wasmtime::Result<Return>
+where
+    H: Host,
+    Params: ComponentNamedList + Lower + Send + Sync + 'static,
+    Return: ComponentNamedList + Lift + Send + Sync + 'static,
+{
+    let call_id = store.data().call_id();
+    let call = with_call_id(call_id, async move {
+        if native_async {
+            store
+                .run_concurrent(async move |accessor| func.call_concurrent(accessor, params).await)
+                .await?
+        } else {
+            func.call_async(store, params).await
+        }
+    });
+    let call = async {
+        match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), call)
+                .await
+                .unwrap_or_else(|_| Err(Trap::Interrupt.into())),
+            None => call.await,
+        }
+    };
+    tokio::select! {
+        biased;
+        result = call => result,
+        () = interrupt.cancelled() => Err(Trap::Interrupt.into()),
+    }
+}
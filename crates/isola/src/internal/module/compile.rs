@@ -1,6 +1,6 @@
-use std::path::Path;
+use std::sync::Arc;
 
-use wasmtime::{Engine, component::Component};
+use wasmtime::{Engine, Precompiled, component::Component};
 use wasmtime_wizer::{WasmtimeWizerComponent, Wizer};
 
 use crate::{
@@ -8,47 +8,131 @@ use crate::{
     internal::{
         module::{
             ModuleConfig,
-            cache::{cache_key, write_cache_file_atomic},
+            cache::{
+                cache_key, gc_cache_dir, lock_cache_entry, touch_cache_file,
+                write_cache_file_atomic,
+            },
+            prelude::initialize,
         },
-        sandbox::{InstanceState, exports::GuestIndices},
+        sandbox::{InstanceState, SandboxPre},
+    },
+    sandbox::{
+        CacheBackend, CacheStatus, DirectoryMapping, Error, Result, SandboxOptions, check_world,
     },
-    sandbox::{DirectoryMapping, Error, Result},
     value::Value as IsolaValue,
 };
 
 pub async fn load_or_compile_component(
     engine: &Engine,
-    wasm_path: &Path,
+    wasm_bytes: &[u8],
     directory_mappings: &[DirectoryMapping],
     cfg: &ModuleConfig,
-) -> Result<Component> {
-    let wasm_bytes = tokio::fs::read(wasm_path).await.map_err(Error::from)?;
-
+    backend: Option<&dyn CacheBackend>,
+) -> Result<(Component, CacheStatus)> {
     let Some(cache_dir) = &cfg.cache else {
+        if let Some(backend) = backend {
+            let key = cache_key(engine, cfg, wasm_bytes);
+            let (component, _, status) =
+                fetch_or_compile(engine, cfg, directory_mappings, wasm_bytes, &key, backend)
+                    .await?;
+            return Ok((component, status));
+        }
         let bytes =
-            compile_serialized_component(engine, cfg, directory_mappings, &wasm_bytes).await?;
+            compile_serialized_component(engine, cfg, directory_mappings, wasm_bytes).await?;
         // SAFETY: bytes are produced by wasmtime for the same version/config; if
         // incompatible, deserialization will fail and surface as an error.
         let component = unsafe { Component::deserialize(engine, &bytes) }.map_err(Error::Wasm)?;
-        return Ok(component);
+        return Ok((component, CacheStatus::Disabled));
     };
 
     tokio::fs::create_dir_all(cache_dir)
         .await
         .map_err(Error::from)?;
-    let key = cache_key(engine, cfg, &wasm_bytes);
+    let key = cache_key(engine, cfg, wasm_bytes);
     let cache_path = cache_dir.join(format!("{key}.cwasm"));
 
+    let evicting = cfg.cache_max_size.is_some() || cfg.cache_max_age.is_some();
+    if let Ok(component) = unsafe { Component::deserialize_file(engine, &cache_path) } {
+        if evicting {
+            touch_cache_file(&cache_path);
+        }
+        return Ok((component, CacheStatus::Hit));
+    }
+
+    // Another process may be compiling the same entry; wait for it and reuse
+    // its artifact instead of compiling a duplicate.
+    let lock = lock_cache_entry(&cache_path).await?;
     if let Ok(component) = unsafe { Component::deserialize_file(engine, &cache_path) } {
-        return Ok(component);
+        return Ok((component, CacheStatus::Hit));
     }
 
-    let bytes = compile_serialized_component(engine, cfg, directory_mappings, &wasm_bytes).await?;
+    let (fetched, bytes, status) = match backend {
+        Some(backend) => {
+            let (component, bytes, status) =
+                fetch_or_compile(engine, cfg, directory_mappings, wasm_bytes, &key, backend)
+                    .await?;
+            (Some(component), bytes, status)
+        }
+        None => (
+            None,
+            compile_serialized_component(engine, cfg, directory_mappings, wasm_bytes).await?,
+            CacheStatus::Miss,
+        ),
+    };
     write_cache_file_atomic(&cache_path, &bytes).await?;
+    drop(lock);
+    if evicting {
+        // Cleanup is best-effort; a failure must not fail the build that
+        // produced a valid artifact.
+        let _ = gc_cache_dir(
+            cache_dir,
+            cfg.cache_max_size,
+            cfg.cache_max_age,
+            Some(&cache_path),
+        )
+        .await;
+    }
 
-    let component =
-        unsafe { Component::deserialize_file(engine, &cache_path) }.map_err(Error::Wasm)?;
-    Ok(component)
+    let component = match fetched {
+        Some(component) => component,
+        None => unsafe { Component::deserialize_file(engine, &cache_path) }.map_err(Error::Wasm)?,
+    };
+    Ok((component, status))
+}
+
+/// Fetch a compiled artifact from `backend`, compiling and uploading it when
+/// the backend does not have a usable copy, and return it both loaded and
+/// serialized along with whether it was cached.
+async fn fetch_or_compile(
+    engine: &Engine,
+    cfg: &ModuleConfig,
+    directory_mappings: &[DirectoryMapping],
+    wasm_bytes: &[u8],
+    key: &str,
+    backend: &dyn CacheBackend,
+) -> Result<(Component, Vec<u8>, CacheStatus)> {
+    let key = cfg
+        .namespace
+        .as_ref()
+        .map_or_else(|| key.to_string(), |namespace| format!("{namespace}/{key}"));
+    // Backend failures and foreign or corrupt artifacts are treated as misses
+    // so a flaky or stale shared cache never prevents a local build; the
+    // upload below then replaces the unusable artifact.
+    if let Ok(Some(bytes)) = backend.load(&key).await
+        && Engine::detect_precompiled(&bytes) == Some(Precompiled::Component)
+        // SAFETY: whoever installed the backend with the unsafe
+        // `SandboxTemplateBuilder::cache_backend` vouches for its artifacts.
+        // Incompatible artifacts fail to deserialize.
+        && let Ok(component) = unsafe { Component::deserialize(engine, &bytes) }
+    {
+        return Ok((component, bytes, CacheStatus::Hit));
+    }
+
+    let bytes = compile_serialized_component(engine, cfg, directory_mappings, wasm_bytes).await?;
+    let _ = backend.store(&key, &bytes).await;
+    // SAFETY: the bytes were just produced by wasmtime for this engine.
+    let component = unsafe { Component::deserialize(engine, &bytes) }.map_err(Error::Wasm)?;
+    Ok((component, bytes, CacheStatus::Miss))
 }
 
 async fn compile_serialized_component(
@@ -62,25 +146,41 @@ async fn compile_serialized_component(
     let directory_mappings = directory_mappings.to_vec();
     let wasm_bytes = wasm_bytes.to_vec();
 
+    let compile_pool = compile_thread_pool(cfg.compile_threads)?;
     tokio::task::spawn_blocking(move || {
         // Run initialization on a blocking worker so the compile-time guest
         // instantiation stays off the main async scheduler.
-        tokio::runtime::Handle::current().block_on(async move {
+        let handle = tokio::runtime::Handle::current();
+        let compile = async move {
             let wizer = Wizer::new();
             let (cx, instrumented_wasm) = wizer
                 .instrument_component(&wasm_bytes)
                 .map_err(Error::Wasm)?;
 
             let component = Component::new(&engine, &instrumented_wasm).map_err(Error::Wasm)?;
-            let linker = InstanceState::<CompileHost>::new_linker(&engine).map_err(Error::Wasm)?;
-            let mut store = InstanceState::new(
-                &engine,
-                &directory_mappings,
-                &cfg.env,
-                cfg.max_memory,
-                CompileHost,
-            )
-            .map_err(Error::Wasm)?;
+            check_world(&engine, &component, cfg.linker_extensions)?;
+            if cfg.snapshots {
+                // Sandboxes initialize themselves and keep the accessors
+                // `Sandbox::snapshot` reads guest state through.
+                return component.serialize().map_err(Error::Wasm);
+            }
+            let mut linker =
+                InstanceState::<CompileHost>::new_linker(&engine).map_err(Error::Wasm)?;
+            if cfg.linker_extensions {
+                // Extensions are registered for the embedder's host type, not
+                // this one; initialization must not call into them.
+                linker
+                    .define_unknown_imports_as_traps(&component)
+                    .map_err(Error::Wasm)?;
+            }
+            let options = SandboxOptions {
+                max_memory: Some(cfg.max_memory),
+                directory_mappings,
+                env: cfg.env,
+                ..SandboxOptions::default()
+            };
+            let mut store = InstanceState::new(&engine, &options, &[], Arc::new(CompileHost))
+                .map_err(Error::Wasm)?;
             store.epoch_deadline_async_yield_and_update(1);
 
             let pre = linker.instantiate_pre(&component).map_err(Error::Wasm)?;
@@ -88,15 +188,13 @@ async fn compile_serialized_component(
                 .instantiate_async(&mut store)
                 .await
                 .map_err(Error::Wasm)?;
-            let guest = GuestIndices::new(&pre)
-                .map_err(Error::Wasm)?
-                .load(&mut store, &instance)
-                .map_err(Error::Wasm)?;
+            let guest = SandboxPre::new(pre)
+                .and_then(|pre| pre.load(&mut store, &instance))
+                .map_err(|missing| Error::IncompatibleWorld { missing })?;
 
-            guest
-                .call_initialize(&mut store, true, cfg.prelude.as_deref())
+            initialize(&guest, &mut store, true, &cfg.preludes)
                 .await
-                .map_err(Error::Wasm)?;
+                .map_err(Error::Wasm)??;
 
             let data = wizer
                 .snapshot_component(
@@ -111,12 +209,30 @@ async fn compile_serialized_component(
 
             let component = Component::new(&engine, &data).map_err(Error::Wasm)?;
             component.serialize().map_err(Error::Wasm)
-        })
+        };
+        // Cranelift fans out on the ambient rayon pool, so driving the
+        // compile from a dedicated pool bounds the compiler thread count.
+        match compile_pool {
+            Some(pool) => pool.install(|| handle.block_on(compile)),
+            None => handle.block_on(compile),
+        }
     })
     .await
     .map_err(|e| Error::Other(e.into()))?
 }
 
+fn compile_thread_pool(threads: Option<usize>) -> Result<Option<rayon::ThreadPool>> {
+    match threads {
+        None | Some(0 | 1) => Ok(None),
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("isola-compile-{index}"))
+            .build()
+            .map(Some)
+            .map_err(|e| Error::Other(e.into())),
+    }
+}
+
 struct CompileHost;
 
 #[expect(
@@ -1,14 +1,211 @@
-use wasmtime::Config;
+use wasmtime::{Config, InstanceAllocationStrategy, PoolingAllocationConfig, Strategy};
 
-pub fn configure_engine(cfg: &mut Config) {
+use crate::sandbox::{CompilerStrategy, EngineConfig, PoolingConfig};
+
+/// Upper bound on core instances linked into one runtime component.
+const CORE_INSTANCES_PER_SANDBOX: u32 = 32;
+/// Upper bound on linear memories owned by one runtime component.
+const MEMORIES_PER_SANDBOX: u32 = 4;
+/// Upper bound on tables owned by one runtime component.
+const TABLES_PER_SANDBOX: u32 = 32;
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+/// Largest initialized heap always compiled into a dense copy-on-write image.
+const MAX_DENSE_IMAGE_SIZE: u64 = 1024 * 1024 * 1024;
+/// Host stack reserved above the guest stack limit on each async fiber, for
+/// host calls and trap handling. Matches Wasmtime's default split.
+const ASYNC_STACK_HEADROOM: usize = 1536 * 1024;
+
+pub fn configure_engine(cfg: &mut Config, engine: EngineConfig) {
     cfg.epoch_interruption(true);
     cfg.table_lazy_init(false);
     cfg.generate_address_map(false);
     cfg.wasm_backtrace_max_frames(None);
     cfg.wasm_branch_hinting(true);
-    // Wasmtime rejects `native_unwind_info(false)` on Windows (ABI requires unwind
-    // info).
-    #[cfg(not(target_os = "windows"))]
-    cfg.native_unwind_info(false);
+    cfg.strategy(match engine.strategy {
+        CompilerStrategy::Cranelift => Strategy::Cranelift,
+        CompilerStrategy::Winch => Strategy::Winch,
+    });
+    // Relaxed SIMD cannot be enabled without SIMD.
+    cfg.wasm_simd(engine.simd);
+    cfg.wasm_relaxed_simd(engine.simd);
+    cfg.parallel_compilation(engine.parallel_compilation);
+    // The default keeps unwind info on Windows, where Wasmtime rejects
+    // `native_unwind_info(false)` because the ABI requires it.
+    cfg.native_unwind_info(engine.native_unwind_info);
+    cfg.debug_info(engine.debug_info);
     cfg.cranelift_opt_level(wasmtime::OptLevel::Speed);
 }
+
+/// Apply the template's compiler thread setting.
+///
+/// A single thread disables parallel compilation entirely; larger counts are
+/// enforced by the thread pool the compile step runs on.
+pub fn configure_compile_threads(cfg: &mut Config, threads: Option<usize>) {
+    if matches!(threads, Some(0 | 1)) {
+        cfg.parallel_compilation(false);
+    }
+}
+
+/// Configure how guest linear memory is initialized at instantiation.
+///
+/// With copy-on-write enabled, every sandbox maps the template's snapshot heap
+/// privately instead of copying it, so untouched pages stay shared between
+/// instances. The dense-image threshold is raised to the template's memory
+/// limit so the snapshot always qualifies, even when sparse.
+pub fn configure_memory_init(cfg: &mut Config, copy_on_write: bool, max_memory: usize) {
+    cfg.memory_init_cow(copy_on_write);
+    if copy_on_write {
+        let dense_image_size = u64::try_from(max_memory)
+            .unwrap_or(u64::MAX)
+            .min(MAX_DENSE_IMAGE_SIZE);
+        cfg.memory_guaranteed_dense_image_size(dense_image_size);
+    }
+}
+
+/// Apply the template's guest stack limit.
+///
+/// Async fibers must be larger than the guest stack limit, so the fiber size
+/// grows with it to keep the default headroom for host frames.
+pub fn configure_stack(cfg: &mut Config, max_stack: Option<usize>) {
+    if let Some(max_stack) = max_stack {
+        cfg.max_wasm_stack(max_stack);
+        cfg.async_stack_size(max_stack.saturating_add(ASYNC_STACK_HEADROOM));
+    }
+}
+
+/// Compile to Pulley bytecode for the host's pointer width and byte order.
+///
+/// Wasmtime then runs guest code in its portable interpreter, so no
+/// executable memory is ever mapped.
+#[cfg(feature = "pulley")]
+pub fn configure_interpreter(cfg: &mut Config) -> wasmtime::Result<()> {
+    let target = match (
+        cfg!(target_pointer_width = "64"),
+        cfg!(target_endian = "big"),
+    ) {
+        (true, false) => "pulley64",
+        (true, true) => "pulley64be",
+        (false, false) => "pulley32",
+        (false, true) => "pulley32be",
+    };
+    cfg.target(target)?;
+    Ok(())
+}
+
+pub fn configure_pooling(cfg: &mut Config, pooling: &PoolingConfig) {
+    let instances = pooling.max_instances;
+    let max_memory_size = pooling
+        .max_memory_pages
+        .saturating_mul(WASM_PAGE_SIZE)
+        .try_into()
+        .unwrap_or(usize::MAX);
+
+    let mut pool = PoolingAllocationConfig::new();
+    pool.total_component_instances(instances)
+        .total_core_instances(instances.saturating_mul(CORE_INSTANCES_PER_SANDBOX))
+        .total_memories(instances.saturating_mul(MEMORIES_PER_SANDBOX))
+        .total_tables(instances.saturating_mul(TABLES_PER_SANDBOX))
+        .total_stacks(instances)
+        .max_core_instances_per_component(CORE_INSTANCES_PER_SANDBOX)
+        .max_memories_per_component(MEMORIES_PER_SANDBOX)
+        .max_tables_per_component(TABLES_PER_SANDBOX)
+        .max_memory_size(max_memory_size)
+        .table_elements(pooling.table_elements);
+    cfg.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pooling_engine_can_be_created() {
+        let mut cfg = Config::default();
+        configure_engine(&mut cfg, EngineConfig::default());
+        configure_pooling(
+            &mut cfg,
+            &PoolingConfig::default()
+                .max_instances(2)
+                .max_memory_pages(16)
+                .table_elements(64),
+        );
+        wasmtime::Engine::new(&cfg).expect("pooling engine");
+    }
+
+    #[test]
+    fn tuned_engines_can_be_created() {
+        let mut cfg = Config::default();
+        configure_engine(
+            &mut cfg,
+            EngineConfig::default()
+                .simd(false)
+                .parallel_compilation(false)
+                .native_unwind_info(true)
+                .debug_info(true),
+        );
+        wasmtime::Engine::new(&cfg).expect("tuned engine");
+
+        // This build does not include Winch, so selecting it is reported
+        // when the engine is created.
+        let mut cfg = Config::default();
+        configure_engine(
+            &mut cfg,
+            EngineConfig::default().strategy(CompilerStrategy::Winch),
+        );
+        assert!(wasmtime::Engine::new(&cfg).is_err());
+    }
+
+    #[test]
+    fn single_threaded_compile_engine_can_be_created() {
+        let mut cfg = Config::default();
+        configure_engine(&mut cfg, EngineConfig::default());
+        configure_compile_threads(&mut cfg, Some(1));
+        wasmtime::Engine::new(&cfg).expect("single-threaded engine");
+    }
+
+    #[test]
+    fn stack_sized_engines_can_be_created() {
+        for max_stack in [64 * 1024, 8 * 1024 * 1024] {
+            let mut cfg = Config::default();
+            configure_engine(&mut cfg, EngineConfig::default());
+            configure_stack(&mut cfg, Some(max_stack));
+            wasmtime::Engine::new(&cfg).expect("stack sized engine");
+        }
+    }
+
+    #[test]
+    fn memory_init_engines_can_be_created() {
+        for copy_on_write in [true, false] {
+            let mut cfg = Config::default();
+            configure_engine(&mut cfg, EngineConfig::default());
+            configure_memory_init(&mut cfg, copy_on_write, usize::MAX);
+            wasmtime::Engine::new(&cfg).expect("memory init engine");
+        }
+    }
+
+    #[cfg(feature = "pulley")]
+    #[test]
+    fn interpreter_engine_runs_guest_code() {
+        // (module (func (export "f") (result i32) i32.const 7))
+        const MODULE: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x00, 0x01,
+            0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x05, 0x01, 0x01, 0x66, 0x00, 0x00, 0x0a, 0x06,
+            0x01, 0x04, 0x00, 0x41, 0x07, 0x0b,
+        ];
+
+        let mut cfg = Config::default();
+        configure_engine(&mut cfg, EngineConfig::default());
+        configure_interpreter(&mut cfg).expect("pulley target");
+        let engine = wasmtime::Engine::new(&cfg).expect("interpreter engine");
+        assert!(engine.is_pulley());
+
+        let module = wasmtime::Module::new(&engine, MODULE).expect("module");
+        let mut store = wasmtime::Store::new(&engine, ());
+        store.set_epoch_deadline(1);
+        let instance = wasmtime::Instance::new(&mut store, &module, &[]).expect("instance");
+        let f = instance
+            .get_typed_func::<(), i32>(&mut store, "f")
+            .expect("export");
+        assert_eq!(f.call(&mut store, ()).expect("call"), 7);
+    }
+}
@@ -4,22 +4,32 @@ use std::{
         Arc, OnceLock,
         atomic::{AtomicU64, Ordering},
     },
-    time::Duration,
+    thread::Thread,
+    time::{Duration, Instant},
 };
 
 use parking_lot::Mutex;
 use wasmtime::Engine;
 
-const EPOCH_TICK: Duration = Duration::from_millis(10);
+/// Epoch tick interval used unless a template configures its own.
+pub const DEFAULT_EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Engine ticked by the global ticker at its own interval.
+struct TickedEngine {
+    engine: Engine,
+    interval: Duration,
+    next: Instant,
+}
 
 /// Shared global epoch ticker state.
 struct EpochTickerShared {
-    engines: Mutex<HashMap<u64, Engine>>,
+    engines: Mutex<HashMap<u64, TickedEngine>>,
     next_id: AtomicU64,
 }
 
 pub struct GlobalEpochTicker {
     shared: Arc<EpochTickerShared>,
+    thread: Thread,
 }
 
 /// Registration that keeps epoch ticks active for a specific engine.
@@ -36,28 +46,57 @@ impl GlobalEpochTicker {
         });
 
         let shared_bg = Arc::clone(&shared);
-        std::thread::Builder::new()
+        let handle = std::thread::Builder::new()
             .name("isola-epoch-ticker".to_string())
             .spawn(move || {
                 // Keep epoch progression independent of Tokio scheduling.
                 // This avoids timeout starvation in current-thread runtimes.
                 loop {
-                    std::thread::park_timeout(EPOCH_TICK);
-                    let engines: Vec<Engine> = shared_bg.engines.lock().values().cloned().collect();
-                    for engine in engines {
+                    let now = Instant::now();
+                    let mut due = Vec::new();
+                    let mut wake = None::<Instant>;
+                    for ticked in shared_bg.engines.lock().values_mut() {
+                        if ticked.next <= now {
+                            due.push(ticked.engine.clone());
+                            ticked.next = next_tick(ticked.next, ticked.interval, now);
+                        }
+                        wake = Some(wake.map_or(ticked.next, |wake| wake.min(ticked.next)));
+                    }
+                    for engine in due {
                         engine.increment_epoch();
                     }
+                    // Registering an engine unparks the thread so it can
+                    // pick up a shorter interval.
+                    match wake {
+                        Some(wake) => std::thread::park_timeout(
+                            wake.saturating_duration_since(Instant::now()),
+                        ),
+                        None => std::thread::park(),
+                    }
                 }
             })?;
 
-        Ok(Self { shared })
+        Ok(Self {
+            shared,
+            thread: handle.thread().clone(),
+        })
     }
 
-    pub fn register(&self, engine: Engine) -> Arc<EpochTickerRegistration> {
+    /// Increment `engine`'s epoch every `interval` until the registration is
+    /// dropped.
+    pub fn register(&self, engine: Engine, interval: Duration) -> Arc<EpochTickerRegistration> {
         let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
         let mut engines = self.shared.engines.lock();
-        engines.insert(id, engine);
+        engines.insert(
+            id,
+            TickedEngine {
+                engine,
+                interval,
+                next: Instant::now() + interval,
+            },
+        );
         drop(engines);
+        self.thread.unpark();
 
         Arc::new(EpochTickerRegistration {
             id,
@@ -66,6 +105,13 @@ impl GlobalEpochTicker {
     }
 }
 
+/// Schedule the tick after one due at `scheduled`, skipping ticks missed
+/// while the ticker was descheduled rather than bursting to catch up.
+fn next_tick(scheduled: Instant, interval: Duration, now: Instant) -> Instant {
+    let next = scheduled + interval;
+    if next <= now { now + interval } else { next }
+}
+
 impl Drop for EpochTickerRegistration {
     fn drop(&mut self) {
         let mut engines = self.shared.engines.lock();
@@ -85,3 +131,20 @@ pub fn global_epoch_ticker() -> std::io::Result<&'static GlobalEpochTicker> {
         Err((kind, message)) => Err(std::io::Error::new(*kind, message.clone())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_tick_skips_missed_ticks() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(10);
+        assert_eq!(next_tick(start, tick, start), start + tick);
+        assert_eq!(
+            next_tick(start, tick, start + Duration::from_millis(35)),
+            start + Duration::from_millis(45),
+            "a late ticker does not fire the missed ticks in a burst"
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
+use self::prelude::Prelude;
 use crate::sandbox::DirectoryMapping;
 
 pub mod cache;
@@ -7,12 +8,27 @@ pub mod call;
 pub mod compile;
 pub mod configure;
 pub mod epoch;
+pub mod precompiled;
+pub mod prelude;
 
 #[derive(Clone, Debug)]
 pub struct ModuleConfig {
     pub cache: Option<PathBuf>,
+    pub cache_max_size: Option<u64>,
+    pub cache_max_age: Option<Duration>,
     pub max_memory: usize,
     pub directory_mappings: Vec<DirectoryMapping>,
     pub env: Vec<(String, String)>,
-    pub prelude: Option<String>,
+    pub preludes: Vec<Prelude>,
+    pub compile_threads: Option<usize>,
+    pub copy_on_write: bool,
+    /// Compile the instrumented, uninitialized component so sandboxes can be
+    /// snapshotted.
+    pub snapshots: bool,
+    pub namespace: Option<String>,
+    /// Definitions from [`SandboxTemplateBuilder::with_linker`] may satisfy
+    /// imports the crate does not know.
+    ///
+    /// [`SandboxTemplateBuilder::with_linker`]: crate::sandbox::SandboxTemplateBuilder::with_linker
+    pub linker_extensions: bool,
 }
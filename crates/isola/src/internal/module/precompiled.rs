@@ -0,0 +1,104 @@
+use std::hash::{Hash, Hasher};
+
+use sha2::{Digest, Sha256};
+use wasmtime::{Engine, component::Component};
+
+use crate::sandbox::{Error, Result};
+
+const MAGIC: &[u8; 16] = b"isola-template\0\x01";
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+/// Hashes into SHA-256 so the fingerprint does not depend on the Rust
+/// release, unlike `DefaultHasher`.
+struct StableHasher(Sha256);
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("digest has 32 bytes"))
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+}
+
+fn engine_fingerprint(engine: &Engine) -> u64 {
+    let mut hasher = StableHasher(Sha256::new());
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Serialize `component` with a header recording which engine configuration
+/// it was compiled for.
+pub fn encode(engine: &Engine, component: &Component) -> Result<Vec<u8>> {
+    let compiled = component.serialize().map_err(Error::Wasm)?;
+    let mut bytes = Vec::with_capacity(HEADER_LEN + compiled.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&engine_fingerprint(engine).to_le_bytes());
+    bytes.extend_from_slice(&compiled);
+    Ok(bytes)
+}
+
+/// Load a component written by [`encode`], checking that it was compiled for
+/// an engine compatible with `engine`.
+///
+/// # Safety
+///
+/// `bytes` is loaded as native code. The header is not a signature: anyone
+/// can write a matching one, so the caller must ensure the bytes come from
+/// [`encode`] on a trusted machine, for example by verifying a signature.
+pub unsafe fn decode(engine: &Engine, bytes: &[u8]) -> Result<Component> {
+    let invalid = |message: &str| {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            message.to_string(),
+        ))
+    };
+    let (header, compiled) = bytes
+        .split_at_checked(HEADER_LEN)
+        .ok_or_else(|| invalid("not a precompiled isola template"))?;
+    let (magic, fingerprint) = header.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(invalid("not a precompiled isola template"));
+    }
+    if fingerprint != engine_fingerprint(engine).to_le_bytes() {
+        return Err(invalid(
+            "precompiled template was compiled by another Wasmtime version or with different \
+             engine settings",
+        ));
+    }
+    // SAFETY: the caller guarantees the bytes were produced by `encode`; the
+    // header check only rules out artifacts for an incompatible engine.
+    unsafe { Component::deserialize(engine, compiled) }.map_err(Error::Wasm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_checks_the_header_and_engine() {
+        // (component)
+        const COMPONENT: &[u8] = &[0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+
+        let engine = Engine::default();
+        let component = Component::new(&engine, COMPONENT).expect("component");
+        let bytes = encode(&engine, &component).expect("encode");
+        // SAFETY: the bytes were just produced by `encode`.
+        unsafe { decode(&engine, &bytes) }.expect("round trip");
+
+        let err = unsafe { decode(&engine, &bytes[MAGIC.len()..]) }
+            .err()
+            .expect("missing magic");
+        assert!(err.to_string().contains("not a precompiled"));
+
+        let mut cfg = wasmtime::Config::default();
+        cfg.consume_fuel(true);
+        let fuel_engine = Engine::new(&cfg).expect("fuel engine");
+        let err = unsafe { decode(&fuel_engine, &bytes) }
+            .err()
+            .expect("incompatible engine");
+        assert!(err.to_string().contains("engine settings"));
+    }
+}
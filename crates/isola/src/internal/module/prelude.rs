@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+
+use wasmtime::AsContextMut;
+
+use crate::{
+    internal::sandbox::{Sandbox, preludes::PreludeError},
+    sandbox::{Error, Result, missing_export},
+};
+
+/// Where a template prelude's code comes from.
+#[derive(Clone, Debug)]
+pub enum PreludeSource {
+    Inline(String),
+    File(PathBuf),
+}
+
+/// Prelude code together with the name errors report it under.
+#[derive(Clone, Debug)]
+pub struct Prelude {
+    pub name: String,
+    pub code: String,
+}
+
+/// Read the code of every prelude, in order.
+pub async fn load_preludes(sources: &[PreludeSource]) -> Result<Vec<Prelude>> {
+    let mut preludes = Vec::with_capacity(sources.len());
+    for (index, source) in sources.iter().enumerate() {
+        preludes.push(match source {
+            PreludeSource::Inline(code) => Prelude {
+                name: format!("#{index}"),
+                code: code.clone(),
+            },
+            PreludeSource::File(path) => Prelude {
+                name: path.display().to_string(),
+                code: tokio::fs::read_to_string(path).await.map_err(|e| {
+                    Error::Io(std::io::Error::new(
+                        e.kind(),
+                        format!("failed to read prelude {}: {e}", path.display()),
+                    ))
+                })?,
+            },
+        });
+    }
+    Ok(preludes)
+}
+
+/// Build a prelude importing the Python `modules`, so their import cost is
+/// paid once while the template initializes.
+pub fn warmup_prelude(modules: &[String]) -> Result<Prelude> {
+    let mut code = String::new();
+    for module in modules {
+        let valid = module.split('.').all(|part| {
+            part.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+        });
+        if !valid {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid warmup import {module:?}"),
+            )));
+        }
+        code.push_str("import ");
+        code.push_str(module);
+        code.push('\n');
+    }
+    Ok(Prelude {
+        name: "warmup imports".to_string(),
+        code,
+    })
+}
+
+/// Guest code of `preludes`, as passed to the runtime's `initialize` export.
+pub fn prelude_code(preludes: &[Prelude]) -> Vec<String> {
+    preludes
+        .iter()
+        .map(|prelude| prelude.code.clone())
+        .collect()
+}
+
+/// Initialize the guest, running `preludes` in order.
+///
+/// Runtimes without the `preludes` export take at most one prelude and do
+/// not report which failed. The outer error is a trap, the inner one a
+/// failed prelude or an unsupported number of them.
+pub async fn initialize<T: Send>(
+    guest: &Sandbox,
+    mut store: impl AsContextMut<Data = T>,
+    preinit: bool,
+    preludes: &[Prelude],
+) -> wasmtime::Result<Result<()>> {
+    if let Some(exports) = &guest.preludes {
+        return Ok(exports
+            .call_initialize(&mut store, preinit, &prelude_code(preludes))
+            .await?
+            .map_err(|e| prelude_error(preludes, e)));
+    }
+    match preludes {
+        [] | [_] => {
+            let prelude = preludes.first().map(|prelude| prelude.code.as_str());
+            guest
+                .runtime
+                .call_initialize(&mut store, preinit, prelude)
+                .await?;
+            Ok(Ok(()))
+        }
+        _ => Ok(Err(missing_export("preludes"))),
+    }
+}
+
+/// Name the prelude a failed guest initialization reported.
+pub fn prelude_error(preludes: &[Prelude], error: PreludeError) -> Error {
+    let name = usize::try_from(error.index)
+        .ok()
+        .and_then(|index| preludes.get(index))
+        .map_or_else(
+            || format!("#{}", error.index),
+            |prelude| prelude.name.clone(),
+        );
+    Error::Prelude {
+        name,
+        message: error.error.message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::sandbox::exports::{Error as GuestError, ErrorCode};
+
+    #[tokio::test]
+    async fn errors_name_the_failing_prelude() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shims.py");
+        std::fs::write(&path, "x = 1").unwrap();
+        let preludes = load_preludes(&[
+            PreludeSource::Inline("import os".to_string()),
+            PreludeSource::File(path.clone()),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(prelude_code(&preludes), ["import os", "x = 1"]);
+
+        let err = prelude_error(
+            &preludes,
+            PreludeError {
+                index: 1,
+                error: GuestError {
+                    code: ErrorCode::Aborted,
+                    message: "NameError".to_string(),
+                },
+            },
+        );
+        assert_eq!(
+            err.to_string(),
+            format!("prelude {} failed: NameError", path.display())
+        );
+
+        let warmup = warmup_prelude(&["json".to_string(), "os.path".to_string()]).unwrap();
+        assert_eq!(warmup.code, "import json\nimport os.path\n");
+        for invalid in ["", "os..path", "1st", "os; print()"] {
+            assert!(
+                warmup_prelude(&[invalid.to_string()]).is_err(),
+                "{invalid:?}"
+            );
+        }
+
+        let missing = load_preludes(&[PreludeSource::File(dir.path().join("missing.py"))])
+            .await
+            .unwrap_err();
+        assert!(missing.to_string().contains("missing.py"));
+    }
+}
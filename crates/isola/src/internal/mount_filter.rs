@@ -0,0 +1,152 @@
+use std::{collections::HashMap, io, sync::Arc};
+
+use wasmtime::component::Resource;
+use wasmtime_wasi::filesystem::Descriptor;
+
+/// Glob patterns selecting the files of a mount the guest may see.
+///
+/// Patterns match paths relative to the mount point. As in
+/// [`Sandbox::eval_files`](crate::sandbox::Sandbox::eval_files), `*` and `?`
+/// never match `/` while `**` matches any number of directories.
+#[derive(Debug)]
+pub struct MountFilter {
+    patterns: Vec<glob::Pattern>,
+}
+
+impl MountFilter {
+    pub fn new(patterns: &[String]) -> io::Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid glob pattern '{pattern}': {e}"),
+                    )
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Whether the file at `relative`, a `/`-separated path inside the mount,
+    /// is visible.
+    pub fn allows(&self, relative: &str) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_with(relative, options))
+    }
+}
+
+/// Directory descriptor inside a filtered mount.
+#[derive(Clone)]
+pub struct FilteredDir {
+    pub filter: Arc<MountFilter>,
+    /// Path of the directory relative to the mount point, empty for the mount
+    /// point itself.
+    pub relative: String,
+}
+
+impl FilteredDir {
+    /// Path relative to the mount point of `path` looked up in this
+    /// directory, or `None` if it climbs out of the mount.
+    pub fn join(&self, path: &str) -> Option<String> {
+        join_relative(&self.relative, path)
+    }
+}
+
+/// Path relative to the mount point of `path` looked up in the directory at
+/// `relative`, or `None` if it climbs out of the mount.
+pub fn join_relative(relative: &str, path: &str) -> Option<String> {
+    let mut components: Vec<&str> = relative.split('/').filter(|c| !c.is_empty()).collect();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            component => components.push(component),
+        }
+    }
+    Some(components.join("/"))
+}
+
+/// Include patterns of the filtered mounts of one sandbox, and the directory
+/// descriptors opened inside them.
+#[derive(Default)]
+pub struct MountFilters {
+    by_guest_path: HashMap<String, Arc<MountFilter>>,
+    by_descriptor: HashMap<u32, FilteredDir>,
+}
+
+impl MountFilters {
+    pub fn insert(&mut self, guest_path: &str, patterns: &[String]) -> io::Result<()> {
+        let filter = MountFilter::new(patterns)?;
+        self.by_guest_path
+            .insert(guest_path.to_string(), Arc::new(filter));
+        Ok(())
+    }
+
+    pub fn for_guest_path(&self, guest_path: &str) -> Option<FilteredDir> {
+        self.by_guest_path
+            .get(guest_path)
+            .map(|filter| FilteredDir {
+                filter: Arc::clone(filter),
+                relative: String::new(),
+            })
+    }
+
+    pub fn get(&self, fd: &Resource<Descriptor>) -> Option<FilteredDir> {
+        self.by_descriptor.get(&fd.rep()).cloned()
+    }
+
+    pub fn track(&mut self, fd: &Resource<Descriptor>, dir: Option<FilteredDir>) {
+        // Resource indices are reused, so always overwrite or clear the entry
+        // for a newly created descriptor.
+        match dir {
+            Some(dir) => self.by_descriptor.insert(fd.rep(), dir),
+            None => self.by_descriptor.remove(&fd.rep()),
+        };
+    }
+
+    pub fn untrack(&mut self, fd: &Resource<Descriptor>) {
+        self.by_descriptor.remove(&fd.rep());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_match_relative_paths() {
+        let filter = Arc::new(
+            MountFilter::new(&["*.csv".to_string(), "docs/**/*.md".to_string()])
+                .expect("valid patterns"),
+        );
+        assert!(filter.allows("a.csv"));
+        assert!(
+            !filter.allows("sub/a.csv"),
+            "`*` does not cross directories"
+        );
+        assert!(filter.allows("docs/guide/intro.md"));
+        assert!(!filter.allows("secret.txt"));
+
+        let dir = FilteredDir {
+            filter,
+            relative: "docs".to_string(),
+        };
+        assert_eq!(
+            dir.join("guide/./intro.md").as_deref(),
+            Some("docs/guide/intro.md")
+        );
+        assert_eq!(dir.join("../a.csv").as_deref(), Some("a.csv"));
+        assert_eq!(dir.join("../../etc"), None);
+
+        assert!(MountFilter::new(&["[".to_string()]).is_err());
+    }
+}
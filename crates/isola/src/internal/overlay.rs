@@ -0,0 +1,246 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use cap_std::{ambient_authority, fs::Dir as CapDir};
+use wasmtime::component::Resource;
+use wasmtime_wasi::{
+    DirPerms, FilePerms, OpenMode,
+    filesystem::{Descriptor, Dir},
+    p2::bindings::filesystem::types::{DirectoryEntry, DirectoryEntryStream},
+};
+
+/// Lower layers of the overlay mounts of one sandbox.
+///
+/// The top layer of an overlay is preopened like any other mount. Every
+/// directory descriptor opened in it carries the directories at the same
+/// relative path in the layers below, highest first, which lookups fall
+/// through to when the top layer has no matching entry.
+#[derive(Default)]
+pub struct MountOverlays {
+    by_guest_path: HashMap<String, Arc<[Dir]>>,
+    by_descriptor: HashMap<u32, Arc<[Dir]>>,
+    /// Merged or filtered listings for directory streams opened on overlay
+    /// directories or in filtered mounts.
+    streams: HashMap<u32, VecDeque<DirectoryEntry>>,
+}
+
+impl MountOverlays {
+    /// Register the lower layers, highest first, of the mount at `guest_path`.
+    pub fn insert(&mut self, guest_path: &str, lower: &[PathBuf]) -> io::Result<()> {
+        let dirs = lower
+            .iter()
+            .map(|path| {
+                CapDir::open_ambient_dir(path, ambient_authority())
+                    .map(read_only_dir)
+                    .map_err(|e| {
+                        io::Error::new(
+                            e.kind(),
+                            format!("cannot open overlay layer '{}': {e}", path.display()),
+                        )
+                    })
+            })
+            .collect::<io::Result<Arc<[Dir]>>>()?;
+        self.by_guest_path.insert(guest_path.to_string(), dirs);
+        Ok(())
+    }
+
+    pub fn for_guest_path(&self, guest_path: &str) -> Option<Arc<[Dir]>> {
+        self.by_guest_path.get(guest_path).cloned()
+    }
+
+    pub fn get(&self, fd: &Resource<Descriptor>) -> Option<Arc<[Dir]>> {
+        self.by_descriptor.get(&fd.rep()).cloned()
+    }
+
+    pub fn track(&mut self, fd: &Resource<Descriptor>, lower: Option<Arc<[Dir]>>) {
+        // Resource indices are reused, so always overwrite or clear the entry
+        // for a newly created descriptor.
+        match lower.filter(|lower| !lower.is_empty()) {
+            Some(lower) => self.by_descriptor.insert(fd.rep(), lower),
+            None => self.by_descriptor.remove(&fd.rep()),
+        };
+    }
+
+    pub fn untrack(&mut self, fd: &Resource<Descriptor>) {
+        self.by_descriptor.remove(&fd.rep());
+    }
+
+    pub fn set_listing(
+        &mut self,
+        stream: &Resource<DirectoryEntryStream>,
+        entries: VecDeque<DirectoryEntry>,
+    ) {
+        self.streams.insert(stream.rep(), entries);
+    }
+
+    /// Remaining entries of a merged listing, or `None` if `stream` is not
+    /// one.
+    pub fn listing(
+        &mut self,
+        stream: &Resource<DirectoryEntryStream>,
+    ) -> Option<&mut VecDeque<DirectoryEntry>> {
+        self.streams.get_mut(&stream.rep())
+    }
+
+    pub fn drop_stream(&mut self, stream: &Resource<DirectoryEntryStream>) {
+        self.streams.remove(&stream.rep());
+    }
+}
+
+fn read_only_dir(dir: CapDir) -> Dir {
+    Dir::new(dir, DirPerms::READ, FilePerms::READ, OpenMode::READ, false)
+}
+
+/// Open the directory at `path` in each of `layers` that has one.
+pub async fn open_subdirs(layers: &[Dir], path: &str) -> Arc<[Dir]> {
+    let layers: Vec<Arc<CapDir>> = layers.iter().map(|layer| Arc::clone(&layer.dir)).collect();
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || {
+        layers
+            .iter()
+            .filter_map(|layer| layer.open_dir(&path).ok().map(read_only_dir))
+            .collect()
+    })
+    .await
+    .unwrap_or_else(|_| Arc::from([]))
+}
+
+/// Number of entries [`copy_up`] creates in `upper` for `path`: the entry
+/// itself and each parent directory it lacks.
+pub async fn entries_to_copy_up(upper: &Dir, path: &str) -> io::Result<u64> {
+    let upper = Arc::clone(&upper.dir);
+    let path = PathBuf::from(path);
+    tokio::task::spawn_blocking(move || {
+        let mut missing = 0;
+        for prefix in path.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+            match upper.symlink_metadata(prefix) {
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => missing += 1,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(missing)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Copy the entry at `path` from `lower` into the writable `upper` layer,
+/// creating its parent directories. Directories are created empty; their
+/// contents stay visible through the layers below.
+pub async fn copy_up(lower: &Dir, upper: &Dir, path: &str, is_dir: bool) -> io::Result<()> {
+    let lower = Arc::clone(&lower.dir);
+    let upper = Arc::clone(&upper.dir);
+    let path = PathBuf::from(path);
+    tokio::task::spawn_blocking(move || {
+        if is_dir {
+            return upper.create_dir_all(&path);
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            upper.create_dir_all(parent)?;
+        }
+        lower.copy(&path, &upper, &path).map(|_| ())
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Merge directory listings, highest layer first, keeping the first entry
+/// for each name.
+pub fn merge_listings(layers: Vec<Vec<DirectoryEntry>>) -> VecDeque<DirectoryEntry> {
+    let mut seen = HashSet::new();
+    layers
+        .into_iter()
+        .flatten()
+        .filter(|entry| seen.insert(entry.name.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime_wasi::p2::bindings::filesystem::types::DescriptorType;
+
+    use super::*;
+
+    fn entry(name: &str, type_: DescriptorType) -> DirectoryEntry {
+        DirectoryEntry {
+            type_,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn higher_layers_shadow_lower_listings() {
+        let merged = merge_listings(vec![
+            vec![entry("util.py", DescriptorType::RegularFile)],
+            vec![
+                entry("util.py", DescriptorType::Directory),
+                entry("base.py", DescriptorType::RegularFile),
+            ],
+        ]);
+        let merged: Vec<_> = merged
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.type_))
+            .collect();
+        assert_eq!(
+            merged,
+            [
+                ("util.py", DescriptorType::RegularFile),
+                ("base.py", DescriptorType::RegularFile)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn copies_entries_up_into_the_writable_layer() {
+        let lower = tempfile::tempdir().expect("tempdir");
+        let upper = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir_all(lower.path().join("pkg/sub")).expect("mkdir");
+        std::fs::write(lower.path().join("pkg/mod.py"), "x = 1\n").expect("write");
+
+        let mut overlays = MountOverlays::default();
+        overlays
+            .insert("/lib", &[lower.path().to_path_buf()])
+            .expect("inserted");
+        let lower_dirs = overlays.for_guest_path("/lib").expect("registered");
+        let upper_dir = read_only_dir(
+            CapDir::open_ambient_dir(upper.path(), ambient_authority()).expect("open"),
+        );
+
+        assert_eq!(
+            entries_to_copy_up(&upper_dir, "pkg/mod.py")
+                .await
+                .expect("counted"),
+            2
+        );
+        copy_up(&lower_dirs[0], &upper_dir, "pkg/mod.py", false)
+            .await
+            .expect("copied");
+        assert_eq!(
+            entries_to_copy_up(&upper_dir, "pkg/sub")
+                .await
+                .expect("counted"),
+            1
+        );
+        assert_eq!(
+            std::fs::read_to_string(upper.path().join("pkg/mod.py")).expect("read"),
+            "x = 1\n"
+        );
+        copy_up(&lower_dirs[0], &upper_dir, "pkg/sub", true)
+            .await
+            .expect("copied");
+        assert!(upper.path().join("pkg/sub").is_dir());
+
+        assert_eq!(open_subdirs(&lower_dirs, "pkg").await.len(), 1);
+        assert_eq!(open_subdirs(&lower_dirs, "missing").await.len(), 0);
+        assert!(
+            overlays
+                .insert("/x", &[PathBuf::from("/nonexistent")])
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use wasmtime::{
+    Engine, Store,
+    component::{Component, Linker, ResourceTable},
+};
+use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView};
+
+use crate::internal::resource::MemoryLimiter;
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        world: "plugin",
+        path: "wit",
+        exports: {
+            default: async,
+        },
+    });
+}
+
+use bindings::{Plugin, PluginPre};
+
+/// Store state of one plugin instance.
+///
+/// Plugins get a WASI context with no preopens, environment, or network
+/// access; everything they need must arrive through the hostcall payload.
+pub struct PluginState {
+    limiter: MemoryLimiter,
+    wasi: WasiCtx,
+    table: ResourceTable,
+}
+
+impl WasiView for PluginState {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.wasi,
+            table: &mut self.table,
+        }
+    }
+}
+
+/// Compiled plugin component shared by every sandbox of a template.
+pub struct PluginTemplate {
+    name: String,
+    pre: PluginPre<PluginState>,
+}
+
+impl PluginTemplate {
+    pub fn new(engine: &Engine, name: String, component: &Component) -> wasmtime::Result<Self> {
+        let mut linker = Linker::<PluginState>::new(engine);
+        wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+        let pre = PluginPre::new(linker.instantiate_pre(component)?)?;
+        Ok(Self { name, pre })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Per-sandbox plugin handle, instantiated on first use.
+pub struct PluginInstance {
+    template: Arc<PluginTemplate>,
+    max_memory: usize,
+    instance: tokio::sync::Mutex<Option<(Store<PluginState>, Plugin)>>,
+}
+
+impl PluginInstance {
+    pub const fn new(template: Arc<PluginTemplate>, max_memory: usize) -> Self {
+        Self {
+            template,
+            max_memory,
+            instance: tokio::sync::Mutex::const_new(None),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.template.name()
+    }
+
+    /// Invoke the plugin's `handle` export.
+    ///
+    /// Calls are serialized per sandbox; a trap discards the instance so the
+    /// next call starts from fresh plugin state.
+    pub async fn handle(&self, call_type: &str, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let mut guard = self.instance.lock().await;
+        let (store, plugin) = match &mut *guard {
+            Some(instance) => instance,
+            slot @ None => slot.insert(
+                self.instantiate()
+                    .await
+                    .map_err(|e| format!("failed to instantiate plugin '{}': {e}", self.name()))?,
+            ),
+        };
+        let result = plugin
+            .isola_script_plugin_handler()
+            .call_handle(&mut *store, call_type, payload)
+            .await;
+        if result.is_err() {
+            *guard = None;
+        }
+        drop(guard);
+        result.unwrap_or_else(|e| Err(format!("plugin '{}' trapped: {e}", self.name())))
+    }
+
+    async fn instantiate(&self) -> wasmtime::Result<(Store<PluginState>, Plugin)> {
+        let mut store = Store::new(
+            self.template.pre.engine(),
+            PluginState {
+                limiter: MemoryLimiter::new(self.max_memory),
+                wasi: WasiCtx::builder().allow_tcp(false).allow_udp(false).build(),
+                table: ResourceTable::new(),
+            },
+        );
+        store.limiter(|s| &mut s.limiter);
+        if store.get_fuel().is_ok() {
+            store.set_fuel(u64::MAX)?;
+        }
+        store.epoch_deadline_async_yield_and_update(1);
+        let plugin = self.template.pre.instantiate_async(&mut store).await?;
+        Ok((store, plugin))
+    }
+}
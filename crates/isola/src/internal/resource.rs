@@ -1,9 +1,22 @@
 use wasmtime::ResourceLimiter;
 
+use crate::sandbox::{MemoryDiagnostics, MemoryStats};
+
+/// Callback consulted on every linear memory growth request with the current
+/// size, requested size, and hard limit in bytes.
+pub type GrowthHook = Box<dyn Fn(usize, usize, usize) -> bool + Send + Sync>;
+
 pub struct MemoryLimiter {
     max_memory_hard: usize,
     max_table_elements_hard: usize,
     current: usize,
+    peak: usize,
+    call_peak: usize,
+    table_elements: usize,
+    grow_failures: u64,
+    last_growth: Option<(usize, usize)>,
+    denied_request: Option<usize>,
+    growth_hook: Option<GrowthHook>,
 }
 
 impl MemoryLimiter {
@@ -21,37 +34,107 @@ impl MemoryLimiter {
             max_memory_hard,
             max_table_elements_hard,
             current: 0,
+            peak: 0,
+            call_peak: 0,
+            table_elements: 0,
+            grow_failures: 0,
+            last_growth: None,
+            denied_request: None,
+            growth_hook: None,
         }
     }
 
+    /// Consult `hook` before every memory growth; returning `false` denies it.
+    pub fn with_growth_hook(mut self, hook: GrowthHook) -> Self {
+        self.growth_hook = Some(hook);
+        self
+    }
+
     pub const fn current(&self) -> usize {
         self.current
     }
+
+    /// Largest memory size reached since the last [`reset_call_peak`].
+    ///
+    /// [`reset_call_peak`]: Self::reset_call_peak
+    pub const fn call_peak(&self) -> usize {
+        self.call_peak
+    }
+
+    /// Start tracking the peak of a new call from the current size.
+    pub const fn reset_call_peak(&mut self) {
+        self.call_peak = self.current;
+    }
+
+    /// Return whether growth was denied since the last reset.
+    pub const fn limit_exceeded(&self) -> bool {
+        self.denied_request.is_some()
+    }
+
+    pub const fn reset_limit_exceeded(&mut self) {
+        self.denied_request = None;
+    }
+
+    /// Snapshot current usage for [`Sandbox::memory_stats`].
+    ///
+    /// [`Sandbox::memory_stats`]: crate::sandbox::Sandbox::memory_stats
+    pub const fn stats(&self) -> MemoryStats {
+        MemoryStats {
+            current: self.current,
+            peak: self.peak,
+            limit: self.max_memory_hard,
+            table_elements: self.table_elements,
+            grow_failures: self.grow_failures,
+        }
+    }
+
+    /// Snapshot usage history for an error caused by a denied growth.
+    pub const fn diagnostics(&self) -> MemoryDiagnostics {
+        MemoryDiagnostics {
+            limit: self.max_memory_hard,
+            peak: self.peak,
+            last_growth: self.last_growth,
+            denied_request: self.denied_request,
+        }
+    }
 }
 
 impl ResourceLimiter for MemoryLimiter {
     fn memory_growing(
         &mut self,
-        _current: usize,
+        current: usize,
         desired: usize,
         _maximum: Option<usize>,
     ) -> wasmtime::Result<bool> {
-        if desired > self.max_memory_hard {
+        let vetoed = self
+            .growth_hook
+            .as_ref()
+            .is_some_and(|hook| !hook(current, desired, self.max_memory_hard));
+        if vetoed || desired > self.max_memory_hard {
+            self.denied_request = Some(desired);
+            self.grow_failures += 1;
             return Ok(false);
         }
         self.current = desired;
+        self.peak = self.peak.max(desired);
+        self.call_peak = self.call_peak.max(desired);
+        self.last_growth = Some((current, desired));
         Ok(true)
     }
 
     fn table_growing(
         &mut self,
-        _current: usize,
+        current: usize,
         desired: usize,
         _maximum: Option<usize>,
     ) -> wasmtime::Result<bool> {
         if desired > self.max_table_elements_hard {
+            self.grow_failures += 1;
             return Ok(false);
         }
+        // Tables are created by growing from zero, so summing the growth
+        // counts elements across every table.
+        self.table_elements += desired - current;
         Ok(true)
     }
 }
@@ -64,11 +147,51 @@ mod tests {
     fn memory_limit_is_enforced() {
         let mut limiter = MemoryLimiter::new(1024);
         assert!(limiter.memory_growing(0, 1024, None).expect("memory grow"));
+        assert!(!limiter.limit_exceeded());
         assert!(
             !limiter
                 .memory_growing(1024, 1025, None)
                 .expect("memory grow")
         );
+        assert!(limiter.limit_exceeded());
+        assert_eq!(limiter.stats().grow_failures, 1);
+        assert_eq!(
+            limiter.diagnostics(),
+            MemoryDiagnostics {
+                limit: 1024,
+                peak: 1024,
+                last_growth: Some((0, 1024)),
+                denied_request: Some(1025),
+            }
+        );
+        limiter.reset_limit_exceeded();
+        assert!(!limiter.limit_exceeded());
+        assert_eq!(limiter.diagnostics().denied_request, None);
+        assert_eq!(limiter.call_peak(), 1024);
+        limiter.reset_call_peak();
+        assert_eq!(limiter.call_peak(), 1024);
+    }
+
+    #[test]
+    fn growth_hook_observes_and_vetoes_growth() {
+        let seen = std::sync::Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let recorded = std::sync::Arc::clone(&seen);
+        let mut limiter = MemoryLimiter::new(4096).with_growth_hook(Box::new(
+            move |current, requested, limit| {
+                recorded.lock().push((current, requested, limit));
+                requested <= 2048
+            },
+        ));
+
+        assert!(limiter.memory_growing(0, 2048, None).expect("memory grow"));
+        assert!(
+            !limiter
+                .memory_growing(2048, 3072, None)
+                .expect("memory grow")
+        );
+        assert!(limiter.limit_exceeded());
+        assert_eq!(limiter.current(), 2048);
+        assert_eq!(*seen.lock(), [(0, 2048, 4096), (2048, 3072, 4096)]);
     }
 
     #[test]
@@ -80,5 +203,30 @@ mod tests {
                 .table_growing(0, usize::MAX, None)
                 .expect("table grow")
         );
+        assert_eq!(limiter.stats().grow_failures, 1);
+    }
+
+    #[test]
+    fn stats_track_memory_and_tables() {
+        let mut limiter = MemoryLimiter::new(4096);
+        assert!(limiter.memory_growing(0, 4096, None).expect("memory grow"));
+        assert!(limiter.table_growing(0, 10, None).expect("table grow"));
+        assert!(limiter.table_growing(0, 5, None).expect("table grow"));
+        assert!(limiter.table_growing(10, 20, None).expect("table grow"));
+        assert!(
+            !limiter
+                .memory_growing(4096, 8192, None)
+                .expect("memory grow")
+        );
+        assert_eq!(
+            limiter.stats(),
+            MemoryStats {
+                current: 4096,
+                peak: 4096,
+                limit: 4096,
+                table_elements: 25,
+                grow_failures: 1,
+            }
+        );
     }
 }
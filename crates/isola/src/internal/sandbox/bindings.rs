@@ -2,7 +2,7 @@
 pub mod host_bindings;
 
 wasmtime::component::bindgen!({
-    world: "sandbox",
+    world: "sandbox-runtime",
     path: "wit",
     imports: {
         default: async | trappable,
@@ -21,10 +21,20 @@ wasmtime::component::bindgen!({
 use std::{future::Future, sync::Arc};
 
 use bytes::Bytes;
-pub use exports::isola::script::runtime;
-use wasmtime::component::{HasData, Linker};
+pub use exports::isola::script::{
+    batch, coverage, exceptions, expressions, modules, packages, preludes, preopens, runtime,
+};
+use wasmtime::{
+    AsContextMut, Store,
+    component::{HasData, Instance, InstancePre, Linker},
+};
 use wasmtime_wasi::ResourceTable;
 
+use crate::internal::{
+    call_trace::CallTrace, hostcall_limits::HostcallLimiter, plugin::PluginInstance,
+    sandbox::InstanceState,
+};
+
 pub enum EmitValue {
     Continuation(Bytes),
     PartialResult(Bytes),
@@ -32,6 +42,16 @@ pub enum EmitValue {
     Abort,
 }
 
+impl EmitValue {
+    /// Number of encoded output bytes carried by this value.
+    pub const fn byte_len(&self) -> usize {
+        match self {
+            Self::Continuation(data) | Self::PartialResult(data) | Self::End(data) => data.len(),
+            Self::Abort => 0,
+        }
+    }
+}
+
 pub trait HostView: Send {
     type Host: crate::host::Host;
 
@@ -39,6 +59,29 @@ pub trait HostView: Send {
 
     fn host(&mut self) -> &Arc<Self::Host>;
 
+    /// Return whether the active call may invoke a hostcall of `call_type`.
+    fn hostcall_allowed(&mut self, call_type: &str) -> bool;
+
+    /// Return the plugin that handles `call_type` and the call type to pass
+    /// to it, if the hostcall is routed to a plugin instead of the host.
+    fn plugin_for(&mut self, call_type: &str) -> Option<(Arc<PluginInstance>, String)>;
+
+    /// Count a hostcall in the active call's [`ExecStats`](crate::host::ExecStats).
+    fn record_hostcall(&mut self);
+
+    /// Remember the error the host or a plugin returned for a hostcall, so a
+    /// guest exception it causes can be classified as a hostcall failure.
+    fn record_hostcall_failure(&mut self, message: &str);
+
+    /// Return the call id of the active guest operation.
+    fn call_id(&self) -> Option<u64>;
+
+    /// Return the hostcall trace of the sandbox, if tracing is enabled.
+    fn call_trace(&self) -> Option<Arc<CallTrace>>;
+
+    /// Return the hostcall limiter of the sandbox, if limits are configured.
+    fn hostcall_limiter(&self) -> Option<Arc<HostcallLimiter>>;
+
     fn emit(&mut self, data: EmitValue) -> impl Future<Output = wasmtime::Result<()>> + Send;
 }
 
@@ -53,6 +96,34 @@ impl<T: ?Sized + HostView> HostView for &mut T {
         T::host(self)
     }
 
+    fn hostcall_allowed(&mut self, call_type: &str) -> bool {
+        T::hostcall_allowed(self, call_type)
+    }
+
+    fn record_hostcall(&mut self) {
+        T::record_hostcall(self);
+    }
+
+    fn record_hostcall_failure(&mut self, message: &str) {
+        T::record_hostcall_failure(self, message);
+    }
+
+    fn plugin_for(&mut self, call_type: &str) -> Option<(Arc<PluginInstance>, String)> {
+        T::plugin_for(self, call_type)
+    }
+
+    fn call_id(&self) -> Option<u64> {
+        T::call_id(self)
+    }
+
+    fn call_trace(&self) -> Option<Arc<CallTrace>> {
+        T::call_trace(self)
+    }
+
+    fn hostcall_limiter(&self) -> Option<Arc<HostcallLimiter>> {
+        T::hostcall_limiter(self)
+    }
+
     async fn emit(&mut self, data: EmitValue) -> wasmtime::Result<()> {
         T::emit(self, data).await
     }
@@ -69,3 +140,159 @@ impl<T: 'static> HasData for LinkerHost<T> {
 pub fn add_to_linker<T: HostView>(l: &mut Linker<T>) -> wasmtime::Result<()> {
     self::isola::script::host::add_to_linker::<_, LinkerHost<T>>(l, |t| HostImpl(t))
 }
+
+/// Declare [`SandboxPre`] and [`Sandbox`] over the required `runtime`
+/// interface and the optional interfaces of the `sandbox-runtime` world.
+macro_rules! sandbox_exports {
+    ($($interface:ident),* $(,)?) => {
+        /// Export indices of a runtime component, looked up once so every
+        /// instance only has to load them.
+        ///
+        /// Only `runtime` is required. Optional interfaces the component
+        /// does not export are `None`.
+        pub struct SandboxPre<T: 'static> {
+            instance_pre: InstancePre<T>,
+            pub runtime: runtime::GuestIndices,
+            $(pub $interface: Option<$interface::GuestIndices>,)*
+        }
+
+        // Derived `Clone` would require `T: Clone`.
+        impl<T: 'static> Clone for SandboxPre<T> {
+            fn clone(&self) -> Self {
+                Self {
+                    instance_pre: self.instance_pre.clone(),
+                    runtime: self.runtime.clone(),
+                    $($interface: self.$interface.clone(),)*
+                }
+            }
+        }
+
+        impl<T: 'static> SandboxPre<T> {
+            /// Look up the exports the host calls in `instance_pre`'s
+            /// component.
+            ///
+            /// # Errors
+            ///
+            /// Returns one entry per interface the component lacks or
+            /// exports without a function the host calls.
+            pub fn new(instance_pre: InstancePre<T>) -> Result<Self, Vec<String>> {
+                let mut missing = Vec::new();
+                let component = instance_pre.component();
+                let runtime = if component.get_export_index(None, RUNTIME).is_some() {
+                    runtime::GuestIndices::new(&instance_pre)
+                        .map_err(|e| missing.push(e.to_string()))
+                        .ok()
+                } else {
+                    missing.push(format!("export {RUNTIME}"));
+                    None
+                };
+                $(
+                    let $interface = component
+                        .get_export_index(None, interface_name(stringify!($interface)).as_str())
+                        .and_then(|_| {
+                            $interface::GuestIndices::new(&instance_pre)
+                                .map_err(|e| missing.push(e.to_string()))
+                                .ok()
+                        });
+                )*
+                match runtime {
+                    Some(runtime) if missing.is_empty() => Ok(Self {
+                        instance_pre,
+                        runtime,
+                        $($interface,)*
+                    }),
+                    _ => Err(missing),
+                }
+            }
+
+            pub const fn instance_pre(&self) -> &InstancePre<T> {
+                &self.instance_pre
+            }
+
+            /// Load the exports of `instance`, an instance of this
+            /// component, checking that each has the type the host calls it
+            /// with.
+            ///
+            /// # Errors
+            ///
+            /// Returns one entry per interface with a mismatched type.
+            pub fn load(
+                &self,
+                mut store: impl AsContextMut<Data = T>,
+                instance: &Instance,
+            ) -> Result<Sandbox, Vec<String>> {
+                let mut missing = Vec::new();
+                let mut incompatible = |interface: &str, e: wasmtime::Error| {
+                    missing.push(format!("export {interface} (incompatible type: {e:#})"));
+                };
+                let runtime = self
+                    .runtime
+                    .load(&mut store, instance)
+                    .map_err(|e| incompatible(RUNTIME, e))
+                    .ok();
+                $(
+                    let $interface = self.$interface.as_ref().and_then(|indices| {
+                        indices
+                            .load(&mut store, instance)
+                            .map_err(|e| {
+                                incompatible(&interface_name(stringify!($interface)), e);
+                            })
+                            .ok()
+                    });
+                )*
+                match runtime {
+                    Some(runtime) if missing.is_empty() => Ok(Sandbox {
+                        runtime,
+                        $($interface,)*
+                    }),
+                    _ => Err(missing),
+                }
+            }
+        }
+
+        /// Exports of a runtime instance.
+        pub struct Sandbox {
+            pub runtime: runtime::Guest,
+            $(pub $interface: Option<$interface::Guest>,)*
+        }
+    };
+}
+
+sandbox_exports!(
+    exceptions,
+    preludes,
+    preopens,
+    expressions,
+    modules,
+    packages,
+    coverage,
+    batch,
+);
+
+/// Interface every runtime component exports.
+pub const RUNTIME: &str = "isola:script/runtime";
+
+/// Full name of the `isola:script` interface `name`.
+pub fn interface_name(name: &str) -> String {
+    format!("isola:script/{name}")
+}
+
+impl Sandbox {
+    /// Convert `error`, returned by one of this instance's exports, with the
+    /// exception behind it when the runtime reports exceptions.
+    pub async fn guest_error<H: crate::host::Host>(
+        &self,
+        store: &mut Store<InstanceState<H>>,
+        error: runtime::Error,
+    ) -> crate::sandbox::Error {
+        let exception = match &self.exceptions {
+            Some(exports) => exports
+                .call_last_exception(&mut *store)
+                .await
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        store.data().classify_guest_error(error, exception)
+    }
+}
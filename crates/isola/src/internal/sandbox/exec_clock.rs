@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+use wasmtime::CallHook;
+
+use crate::host::ExecStats;
+
+/// Splits the wall time of one guest operation into time spent running
+/// WebAssembly and time spent in the host, and counts what the operation
+/// did along the way.
+#[derive(Default)]
+pub struct ExecClock {
+    started: Option<Instant>,
+    guest_since: Option<Instant>,
+    guest_time: Duration,
+    guest_ticks: u64,
+    hostcalls: u64,
+    output_bytes: u64,
+}
+
+impl ExecClock {
+    /// Start timing a new operation.
+    pub fn start(&mut self) {
+        *self = Self {
+            started: Some(Instant::now()),
+            ..Self::default()
+        };
+    }
+
+    /// Record a transition between guest and host code.
+    pub fn transition(&mut self, hook: CallHook) {
+        if self.started.is_none() {
+            return;
+        }
+        if hook.entering_host() {
+            if let Some(since) = self.guest_since.take() {
+                self.guest_time += since.elapsed();
+            }
+        } else if self.guest_since.is_none() {
+            self.guest_since = Some(Instant::now());
+        }
+    }
+
+    /// Account for `ticks` epoch ticks of guest execution.
+    pub const fn record_ticks(&mut self, ticks: u64) {
+        if self.started.is_some() {
+            self.guest_ticks = self.guest_ticks.saturating_add(ticks);
+        }
+    }
+
+    /// Account for one hostcall made by the guest.
+    pub const fn record_hostcall(&mut self) {
+        if self.started.is_some() {
+            self.hostcalls = self.hostcalls.saturating_add(1);
+        }
+    }
+
+    /// Account for `len` bytes of output emitted by the guest.
+    pub fn record_output(&mut self, len: usize) {
+        if self.started.is_some() {
+            let len = u64::try_from(len).unwrap_or(u64::MAX);
+            self.output_bytes = self.output_bytes.saturating_add(len);
+        }
+    }
+
+    /// Return the time and counts accounted to the running operation so far.
+    ///
+    /// Peak memory and HTTP requests are tracked elsewhere and left at 0.
+    pub fn snapshot(&self) -> ExecStats {
+        let Some(started) = self.started else {
+            return ExecStats::default();
+        };
+        let now = Instant::now();
+        let guest_time = self.guest_time + self.guest_since.map_or(Duration::ZERO, |s| now - s);
+        ExecStats {
+            wall_time: now - started,
+            guest_time,
+            guest_ticks: self.guest_ticks,
+            hostcalls: self.hostcalls,
+            output_bytes: self.output_bytes,
+            ..ExecStats::default()
+        }
+    }
+
+    /// Stop timing and return the operation's totals.
+    pub fn finish(&mut self) -> Option<ExecStats> {
+        let stats = self.started.is_some().then(|| self.snapshot());
+        *self = Self::default();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guest_time_excludes_host_calls() {
+        let mut clock = ExecClock::default();
+        clock.transition(CallHook::CallingWasm);
+        assert_eq!(clock.finish(), None, "idle transitions are ignored");
+
+        clock.start();
+        clock.transition(CallHook::CallingWasm);
+        std::thread::sleep(Duration::from_millis(5));
+        clock.record_ticks(2);
+        clock.transition(CallHook::CallingHost);
+        clock.record_hostcall();
+        clock.record_output(7);
+        std::thread::sleep(Duration::from_millis(20));
+        clock.transition(CallHook::ReturningFromHost);
+        clock.transition(CallHook::ReturningFromWasm);
+        let stats = clock.finish().expect("running operation");
+
+        assert!(stats.guest_time >= Duration::from_millis(5));
+        assert!(stats.host_time() >= Duration::from_millis(20));
+        assert_eq!(stats.guest_time + stats.host_time(), stats.wall_time);
+        assert_eq!(
+            (stats.guest_ticks, stats.hostcalls, stats.output_bytes),
+            (2, 1, 7)
+        );
+
+        clock.record_hostcall();
+        clock.start();
+        assert_eq!(clock.snapshot().hostcalls, 0, "start resets the counts");
+    }
+}
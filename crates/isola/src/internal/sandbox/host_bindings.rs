@@ -11,7 +11,12 @@ use super::{
         EmitType, Host, HostValueIterator, HostValueIteratorWithStore, HostWithStore,
     },
 };
-use crate::{host::Host as _, value::Value};
+use crate::{
+    host::{Host as _, with_call_id},
+    internal::{call_trace::PendingTrace, plugin::PluginInstance},
+    sandbox::{TraceKind, TraceOutcome},
+    value::Value,
+};
 
 pub struct ValueIterator {
     stream: Pin<Box<dyn Stream<Item = Value> + Send>>,
@@ -78,17 +83,82 @@ impl<T: HostView + 'static> HostWithStore<T> for LinkerHost<T> {
         call_type: String,
         payload: Vec<u8>,
     ) -> wasmtime::Result<Result<Vec<u8>, String>> {
-        let host = accessor.with(|mut access| Arc::clone(access.get().0.host()));
-        Ok(wasmtime_wasi::runtime::spawn(
-            async move {
-                let payload = Value::from_cbor(payload);
-                host.hostcall(&call_type, payload)
-                    .await
-                    .map(|v| v.into_cbor().into())
-                    .map_err(|e| e.to_string())
+        enum Target<H> {
+            Host(Arc<H>),
+            Plugin(Arc<PluginInstance>, String),
+        }
+
+        let (target, call_id, trace, limiter) = accessor.with(|mut access| {
+            let view = &mut *access.get().0;
+            view.record_hostcall();
+            let target =
+                view.hostcall_allowed(&call_type)
+                    .then(|| match view.plugin_for(&call_type) {
+                        Some((plugin, plugin_call_type)) => {
+                            Target::Plugin(plugin, plugin_call_type)
+                        }
+                        None => Target::Host(Arc::clone(view.host())),
+                    });
+            (
+                target,
+                view.call_id(),
+                view.call_trace(),
+                view.hostcall_limiter(),
+            )
+        });
+        let traced = trace.map(|trace| {
+            let pending =
+                PendingTrace::new(TraceKind::Hostcall, call_id, call_type.clone(), &payload);
+            (trace, pending)
+        });
+        let Some(target) = target else {
+            if let Some((trace, pending)) = traced {
+                trace.finish(pending, None, TraceOutcome::Denied);
             }
+            return Ok(Err(format!(
+                "hostcall '{call_type}' is not permitted for this call"
+            )));
+        };
+        let permit = match limiter.map(|limiter| limiter.acquire()).transpose() {
+            Ok(permit) => permit,
+            Err(exceeded) => {
+                if let Some((trace, pending)) = traced {
+                    trace.finish(pending, None, TraceOutcome::Denied);
+                }
+                return Ok(Err(exceeded.to_string()));
+            }
+        };
+        let result = wasmtime_wasi::runtime::spawn(
+            with_call_id(call_id, async move {
+                if let Some(permit) = &permit {
+                    permit.ready().await;
+                }
+                match target {
+                    Target::Host(host) => {
+                        let payload = Value::from_cbor(payload);
+                        host.hostcall(&call_type, payload)
+                            .await
+                            .map(|v| v.into_cbor().into())
+                            .map_err(|e| e.to_string())
+                    }
+                    Target::Plugin(plugin, plugin_call_type) => {
+                        plugin.handle(&plugin_call_type, &payload).await
+                    }
+                }
+            })
             .in_current_span(),
         )
-        .await)
+        .await;
+        if let Some((trace, pending)) = traced {
+            let outcome = match &result {
+                Ok(_) => TraceOutcome::Ok,
+                Err(message) => TraceOutcome::Failed(message.clone()),
+            };
+            trace.finish(pending, None, outcome);
+        }
+        if let Err(message) = &result {
+            accessor.with(|mut access| access.get().0.record_hostcall_failure(message));
+        }
+        Ok(result)
     }
 }
@@ -1,7 +1,10 @@
 pub mod bindings;
+pub mod exec_clock;
+pub mod output_limits;
 pub mod state;
 
 pub use bindings::{
-    HostView, Sandbox, SandboxPre, host_bindings::ValueIterator, runtime as exports,
+    HostView, Sandbox, SandboxPre, batch, coverage, host_bindings::ValueIterator, interface_name,
+    preludes, runtime as exports,
 };
 pub use state::InstanceState;
@@ -0,0 +1,60 @@
+use crate::sandbox::OutputLimit;
+
+/// Output budget of one guest operation, set from its
+/// [`CallOptions`](crate::sandbox::CallOptions).
+#[derive(Debug, Default)]
+pub struct OutputLimits {
+    max_bytes: Option<usize>,
+    max_items: Option<usize>,
+    bytes: usize,
+    items: usize,
+}
+
+impl OutputLimits {
+    pub const fn new(max_bytes: Option<usize>, max_items: Option<usize>) -> Self {
+        Self {
+            max_bytes,
+            max_items,
+            bytes: 0,
+            items: 0,
+        }
+    }
+
+    /// Account for `len` more bytes of emitted output.
+    pub const fn add_bytes(&mut self, len: usize) -> Result<(), OutputLimit> {
+        self.bytes = self.bytes.saturating_add(len);
+        match self.max_bytes {
+            Some(max) if self.bytes > max => Err(OutputLimit::Bytes(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Account for one more emitted value.
+    pub const fn add_item(&mut self) -> Result<(), OutputLimit> {
+        self.items = self.items.saturating_add(1);
+        match self.max_items {
+            Some(max) if self.items > max => Err(OutputLimit::Items(max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_fail_once_exceeded() {
+        let mut limits = OutputLimits::new(Some(10), Some(2));
+        assert_eq!(limits.add_bytes(6), Ok(()));
+        assert_eq!(limits.add_bytes(4), Ok(()));
+        assert_eq!(limits.add_bytes(1), Err(OutputLimit::Bytes(10)));
+        assert_eq!(limits.add_item(), Ok(()));
+        assert_eq!(limits.add_item(), Ok(()));
+        assert_eq!(limits.add_item(), Err(OutputLimit::Items(2)));
+
+        let mut unlimited = OutputLimits::default();
+        assert_eq!(unlimited.add_bytes(usize::MAX), Ok(()));
+        assert_eq!(unlimited.add_item(), Ok(()));
+    }
+}
@@ -9,7 +9,11 @@ use wasmtime::{
     Engine, Store,
     component::{Linker, ResourceTable},
 };
-use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
+use wasmtime_wasi::{
+    DirPerms, FilePerms, HostMonotonicClock, HostWallClock, WasiCtx, WasiCtxBuilder, WasiCtxView,
+    WasiView, clocks::WasiClocksCtxView, filesystem::WasiFilesystemCtxView,
+    p2::pipe::ClosedOutputStream,
+};
 use wasmtime_wasi_http::{
     WasiHttpCtx,
     p3::{
@@ -18,33 +22,94 @@ use wasmtime_wasi_http::{
     },
 };
 
-use super::bindings::{EmitValue, HostView, add_to_linker};
+use super::{
+    bindings::{EmitValue, HostView, add_to_linker},
+    exec_clock::ExecClock,
+    exports,
+    output_limits::OutputLimits,
+};
 use crate::{
-    host::{Host, HttpRequest, LogContext, LogLevel, OutputTarget},
+    host::{
+        BoxError, ExecStats, Host, HttpRequest, LogContext, LogLevel, OutputTarget, with_call_id,
+    },
     internal::{
+        call_trace::{CallTrace, PendingTrace},
+        clock::{self, ProviderClocks, SharedClock},
+        file_policy::FilePolicyState,
+        filesystem::{
+            self, LiveMounts, MountQuotas, MountUsage, OpenFiles, QuotaFilesystem, WriteBudget,
+        },
+        hostcall_limits::HostcallLimiter,
+        mount_filter::MountFilters,
+        overlay::MountOverlays,
+        plugin::PluginInstance,
         resource::MemoryLimiter,
-        trace_output::{LogTargetStore, TraceOutput, new_log_target_store, set_log_target},
+        trace_output::{
+            LogTargetStore, OutputTail, StdioPolicy, TraceOutput, new_log_target_store,
+            set_log_target,
+        },
         wasm,
     },
-    sandbox::DirectoryMapping,
+    sandbox::{
+        CapabilitySet, Classified, ErrorKind, OutputLimit, SandboxOptions, TraceKind, TraceOutcome,
+        Traceback, WasiInterface,
+    },
     value::Value,
 };
 
+/// Store data of a sandbox, exported as
+/// [`SandboxState`](crate::sandbox::SandboxState).
+///
+/// Host functions added through
+/// [`SandboxTemplateBuilder::with_linker`](crate::sandbox::SandboxTemplateBuilder::with_linker)
+/// receive it and reach the sandbox's host through
+/// [`host_handle`](Self::host_handle).
 pub struct InstanceState<H: Host> {
-    pub limiter: MemoryLimiter,
+    pub(crate) limiter: MemoryLimiter,
     wasi: WasiCtx,
     http: WasiHttpCtx,
     table: ResourceTable,
+    mount_quotas: MountQuotas,
+    mount_overlays: MountOverlays,
+    mount_filters: MountFilters,
+    live_mounts: LiveMounts,
+    open_files: OpenFiles,
+    write_budget: Option<Arc<WriteBudget>>,
+    file_policy: FilePolicyState,
+    clock: Option<SharedClock>,
+    epoch_yield_ticks: u64,
+    read_only: bool,
+    http_enabled: bool,
+    capabilities: Option<CapabilitySet>,
+    plugins: Vec<Arc<PluginInstance>>,
     host: Arc<H>,
     http_hooks: InstanceHttpHooks<H>,
+    call_trace: Option<Arc<CallTrace>>,
+    hostcall_limiter: Option<Arc<HostcallLimiter>>,
+    /// Error the host or a plugin returned for the latest failed hostcall of
+    /// the current operation.
+    last_hostcall_error: Option<String>,
 
     output_target: Option<OutputTarget>,
+    /// Guest operations started in this store.
+    operations: u64,
+    last_call_id: Option<u64>,
+    last_fuel_consumed: Option<u64>,
+    exec_clock: ExecClock,
+    last_exec_stats: Option<ExecStats>,
     log_target_store: LogTargetStore,
+    stderr_tail: OutputTail,
     output_buffer: OutputBuffer,
+    output_limits: OutputLimits,
 }
 
 struct InstanceHttpHooks<H: Host> {
     host: Arc<H>,
+    allow_http: bool,
+    call_id: Option<u64>,
+    call_trace: Option<Arc<CallTrace>>,
+    /// Requests sent to the host during the current call.
+    requests: u64,
 }
 
 type HttpSendResult = Result<
@@ -96,10 +161,13 @@ impl<H: Host> InstanceState<H> {
     /// # Errors
     ///
     /// Returns an error if any of the WASI components fail to link.
-    pub fn new_linker(engine: &Engine) -> wasmtime::Result<Linker<Self>> {
+    pub(crate) fn new_linker(engine: &Engine) -> wasmtime::Result<Linker<Self>> {
         let mut linker = Linker::<Self>::new(engine);
         wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+        filesystem::add_to_linker(&mut linker, Self::quota_filesystem)?;
         wasmtime_wasi::p3::add_to_linker(&mut linker)?;
+        filesystem::hide_p3_preopens(&mut linker)?;
+        clock::add_to_linker(&mut linker, Self::provider_clocks)?;
         wasmtime_wasi_http::p3::add_to_linker(&mut linker)?;
         wasm::logging::add_to_linker(&mut linker)?;
         add_to_linker(&mut linker)?;
@@ -112,51 +180,57 @@ impl<H: Host> InstanceState<H> {
     ///
     /// Returns an error if the preopened directories cannot be added to the
     /// WASI context.
-    pub fn new(
+    pub(crate) fn new(
         engine: &Engine,
-        directory_mappings: &[DirectoryMapping],
-        env: &[(String, String)],
-        max_memory: usize,
-        host: H,
+        options: &SandboxOptions,
+        disabled_wasi: &[WasiInterface],
+        host: Arc<H>,
     ) -> wasmtime::Result<Store<Self>> {
+        check_disabled_wasi(options, disabled_wasi)?;
         let log_target_store = new_log_target_store();
+        let stderr_tail = OutputTail::default();
         let mut builder = WasiCtxBuilder::new();
 
-        for mapping in directory_mappings {
+        let (mount_quotas, mount_overlays, mount_filters) = preopen_mounts(&mut builder, options)?;
+        for (k, v) in &options.env {
+            builder.env(k, v);
+        }
+        if let Some(workdir) = &options.workdir {
+            builder.initial_cwd(workdir);
+        }
+        builder.allow_tcp(false).allow_udp(false);
+        if disabled_wasi.contains(&WasiInterface::Stdio) {
             builder
-                .preopened_dir(
-                    &mapping.host,
-                    &mapping.guest,
-                    mapping.dir_perms,
-                    mapping.file_perms,
-                )
-                .map_err(|e| {
-                    wasmtime::Error::msg(format!(
-                        "Failed to add directory mapping '{}' -> '{}': {e}",
-                        mapping.host.display(),
-                        mapping.guest
-                    ))
-                })?;
+                .stdout(ClosedOutputStream)
+                .stderr(ClosedOutputStream);
+        } else {
+            configure_stdio(&mut builder, options, &log_target_store, &stderr_tail);
         }
-        for (k, v) in env {
-            builder.env(k, v);
+        if disabled_wasi.contains(&WasiInterface::Clocks) {
+            builder.wall_clock(FrozenClock).monotonic_clock(FrozenClock);
+        } else if let Some(clock) = &options.clock {
+            builder
+                .wall_clock(clock.clone())
+                .monotonic_clock(clock.clone());
         }
-        let wasi = builder
-            .allow_tcp(false)
-            .allow_udp(false)
-            .stdout(TraceOutput::new(
-                LogLevel::Stdout,
-                LogContext::Stdout,
-                Arc::clone(&log_target_store),
-            ))
-            .stderr(TraceOutput::new(
-                LogLevel::Stderr,
-                LogContext::Stderr,
-                Arc::clone(&log_target_store),
+        if let Some(entropy) = &options.entropy {
+            builder
+                .secure_random(entropy.clone())
+                .insecure_random(entropy.clone())
+                .insecure_random_seed(entropy.seed());
+        }
+        let wasi = builder.build();
+        let http_enabled = !disabled_wasi.contains(&WasiInterface::Http);
+        let call_trace = options
+            .trace_hostcalls
+            .filter(|capacity| *capacity > 0)
+            .map(|capacity| Arc::new(CallTrace::new(capacity)));
+        let limiter = {
+            let host = Arc::clone(&host);
+            MemoryLimiter::new(options.max_memory.unwrap_or(usize::MAX)).with_growth_hook(Box::new(
+                move |current, requested, limit| host.on_memory_growth(current, requested, limit),
             ))
-            .build();
-        let limiter = MemoryLimiter::new(max_memory);
-        let host = Arc::new(host);
+        };
 
         let mut s = Store::new(
             engine,
@@ -164,33 +238,316 @@ impl<H: Host> InstanceState<H> {
                 limiter,
                 wasi,
                 http: WasiHttpCtx::new(),
-                table: ResourceTable::new(),
+                table: resource_table(options),
+                mount_quotas,
+                mount_overlays,
+                mount_filters,
+                live_mounts: LiveMounts::default(),
+                open_files: OpenFiles::new(options.max_open_files),
+                write_budget: options.max_write_bytes.map(WriteBudget::new).map(Arc::new),
+                file_policy: FilePolicyState::new(options.file_policy.clone()),
+                clock: options.clock.clone(),
+                epoch_yield_ticks: options.epoch_yield_ticks.unwrap_or(1).max(1),
+                read_only: options.read_only,
+                http_enabled,
+                capabilities: None,
+                plugins: Vec::new(),
                 host: Arc::clone(&host),
-                http_hooks: InstanceHttpHooks { host },
+                http_hooks: InstanceHttpHooks {
+                    host,
+                    allow_http: http_enabled,
+                    call_id: None,
+                    call_trace: call_trace.clone(),
+                    requests: 0,
+                },
+                call_trace,
+                hostcall_limiter: options
+                    .hostcall_limits
+                    .map(|limits| Arc::new(HostcallLimiter::new(limits))),
+                last_hostcall_error: None,
                 output_target: None,
+                operations: 0,
+                last_call_id: None,
+                last_fuel_consumed: None,
+                exec_clock: ExecClock::default(),
+                last_exec_stats: None,
                 log_target_store,
+                stderr_tail,
                 output_buffer: OutputBuffer::new(),
+                output_limits: OutputLimits::default(),
             },
         );
         s.limiter(|s| &mut s.limiter);
+        s.call_hook(|mut s, hook| {
+            s.data_mut().exec_clock.transition(hook);
+            Ok(())
+        });
+        // Fuel-metering engines start stores with none; only per-call
+        // budgets should limit the guest.
+        if s.get_fuel().is_ok() {
+            s.set_fuel(u64::MAX)?;
+        }
         Ok(s)
     }
 
-    pub fn set_output_target(&mut self, target: Option<OutputTarget>) {
+    fn provider_clocks(&mut self) -> ProviderClocks<'_> {
+        ProviderClocks {
+            inner: WasiClocksCtxView {
+                ctx: self.wasi.clocks(),
+                table: &mut self.table,
+            },
+            clock: self.clock.as_ref(),
+        }
+    }
+
+    fn quota_filesystem(&mut self) -> QuotaFilesystem<'_> {
+        QuotaFilesystem {
+            inner: WasiFilesystemCtxView {
+                ctx: self.wasi.filesystem(),
+                table: &mut self.table,
+            },
+            quotas: &mut self.mount_quotas,
+            overlays: &mut self.mount_overlays,
+            filters: &mut self.mount_filters,
+            live: &self.live_mounts,
+            open_files: &mut self.open_files,
+            write_budget: self.write_budget.as_ref(),
+            policy: &mut self.file_policy,
+            read_only: self.read_only
+                || !self
+                    .capabilities
+                    .as_ref()
+                    .is_none_or(CapabilitySet::allows_fs_write),
+        }
+    }
+
+    /// Host the sandbox was instantiated with.
+    #[must_use]
+    pub const fn host_handle(&self) -> &Arc<H> {
+        &self.host
+    }
+
+    /// Quota usage of the mount at `guest_path`, if it has a quota.
+    pub(crate) fn mount_usage(&self, guest_path: &str) -> Option<Arc<MountUsage>> {
+        self.mount_quotas.for_guest_path(guest_path)
+    }
+
+    /// Bytes the guest may still write to files, if limited.
+    #[cfg(feature = "archive")]
+    pub(crate) fn write_budget(&self) -> Option<Arc<WriteBudget>> {
+        self.write_budget.clone()
+    }
+
+    /// Mounts changed since this store was created.
+    pub(crate) const fn live_mounts(&self) -> &LiveMounts {
+        &self.live_mounts
+    }
+
+    pub(crate) const fn live_mounts_mut(&mut self) -> &mut LiveMounts {
+        &mut self.live_mounts
+    }
+
+    pub(crate) fn set_output_target(&mut self, target: Option<OutputTarget>) {
         // Prevent cross-call output leakage and avoid retaining large buffers if
         // the call traps or is interrupted mid-output.
         self.output_buffer.reset();
+        let target = target.map(OutputTarget::for_call);
+        let call_id = target.as_ref().and_then(OutputTarget::call_id);
+        if target.is_some() {
+            self.operations += 1;
+            self.limiter.reset_limit_exceeded();
+            self.stderr_tail.lock().clear();
+            if let Some(limiter) = &self.hostcall_limiter {
+                limiter.reset();
+            }
+            self.last_hostcall_error = None;
+            self.last_call_id = call_id;
+            self.limiter.reset_call_peak();
+            self.http_hooks.requests = 0;
+            self.exec_clock.start();
+        } else if let Some(stats) = self.exec_clock.finish() {
+            self.last_exec_stats = Some(self.with_call_usage(stats));
+        }
+        self.http_hooks.call_id = call_id;
         set_log_target(&self.log_target_store, target.clone());
         self.output_target = target;
     }
 
+    /// Restrict the current call to `capabilities`, or lift the restriction
+    /// with `None`.
+    pub(crate) fn set_capabilities(&mut self, capabilities: Option<CapabilitySet>) {
+        self.http_hooks.allow_http =
+            self.http_enabled && capabilities.as_ref().is_none_or(CapabilitySet::allows_http);
+        self.capabilities = capabilities;
+    }
+
+    /// Bound the output of the current call, or lift the bound with
+    /// [`OutputLimits::default`].
+    pub(crate) const fn set_output_limits(&mut self, limits: OutputLimits) {
+        self.output_limits = limits;
+    }
+
+    /// Count `data` against the output limits of the current call.
+    fn charge_output(&mut self, data: &EmitValue) -> Result<(), OutputLimit> {
+        match data {
+            EmitValue::Continuation(data) => self.output_limits.add_bytes(data.len()),
+            EmitValue::PartialResult(data) => {
+                self.output_limits.add_bytes(data.len())?;
+                self.output_limits.add_item()
+            }
+            EmitValue::End(data) => {
+                self.output_limits.add_bytes(data.len())?;
+                if data.is_empty() && self.output_buffer.is_empty() {
+                    Ok(())
+                } else {
+                    self.output_limits.add_item()
+                }
+            }
+            EmitValue::Abort => Ok(()),
+        }
+    }
+
+    /// Fill in the usage [`ExecClock`] does not track itself.
+    const fn with_call_usage(&self, stats: ExecStats) -> ExecStats {
+        ExecStats {
+            peak_memory: self.limiter.call_peak(),
+            http_requests: self.http_hooks.requests,
+            ..stats
+        }
+    }
+
+    /// Account for `ticks` epoch ticks of guest execution in the active call.
+    pub(crate) const fn record_guest_ticks(&mut self, ticks: u64) {
+        self.exec_clock.record_ticks(ticks);
+    }
+
+    /// Return the call id of the active guest operation.
+    pub(crate) fn call_id(&self) -> Option<u64> {
+        self.output_target.as_ref().and_then(OutputTarget::call_id)
+    }
+
+    /// Return the hostcall trace, if tracing is enabled.
+    pub(crate) fn call_trace(&self) -> Option<Arc<CallTrace>> {
+        self.call_trace.clone()
+    }
+
+    /// Record hostcalls and HTTP requests in `trace` instead of this store's
+    /// own trace.
+    pub(crate) fn set_call_trace(&mut self, trace: Option<Arc<CallTrace>>) {
+        self.http_hooks.call_trace.clone_from(&trace);
+        self.call_trace = trace;
+    }
+
+    /// Return the number of guest operations started in this store, which
+    /// changes whenever guest code may have changed the guest's state.
+    pub(crate) const fn operations(&self) -> u64 {
+        self.operations
+    }
+
+    /// Report the most recent guest operation of `other`, which ran on this
+    /// sandbox's behalf, as this store's own.
+    pub(crate) const fn adopt_last_operation(&mut self, other: &Self) {
+        self.last_call_id = other.last_call_id;
+        self.last_fuel_consumed = other.last_fuel_consumed;
+        self.last_exec_stats = other.last_exec_stats;
+    }
+
+    /// Return the call id of the most recent guest operation.
+    pub(crate) const fn last_call_id(&self) -> Option<u64> {
+        self.last_call_id
+    }
+
+    /// Return the fuel consumed by the most recent guest operation, if the
+    /// engine meters fuel.
+    pub(crate) const fn last_fuel_consumed(&self) -> Option<u64> {
+        self.last_fuel_consumed
+    }
+
+    pub(crate) const fn set_last_fuel_consumed(&mut self, consumed: Option<u64>) {
+        self.last_fuel_consumed = consumed;
+    }
+
+    /// Return the number of epoch ticks guest code runs between yields.
+    pub(crate) const fn epoch_yield_ticks(&self) -> u64 {
+        self.epoch_yield_ticks
+    }
+
+    /// Return the time taken by the most recent guest operation.
+    pub(crate) const fn last_exec_stats(&self) -> Option<ExecStats> {
+        self.last_exec_stats
+    }
+
+    /// Route hostcalls named `<plugin>.<call>` to `plugins`.
+    pub(crate) fn set_plugins(&mut self, plugins: Vec<Arc<PluginInstance>>) {
+        self.plugins = plugins;
+    }
+
+    /// Convert a trap raised by the current guest operation into a sandbox
+    /// error, recording memory-limit denials that caused it and reporting
+    /// exceeded output limits.
+    pub(crate) fn classify_error(&self, error: wasmtime::Error) -> crate::sandbox::Error {
+        if let Some(limit) = error.downcast_ref::<OutputLimit>() {
+            return crate::sandbox::Error::OutputLimitExceeded(*limit);
+        }
+        if self.limiter.limit_exceeded() && error.downcast_ref::<Classified>().is_none() {
+            return crate::sandbox::Error::Wasm(
+                error
+                    .context(self.limiter.diagnostics())
+                    .context(Classified(ErrorKind::MemoryLimit)),
+            );
+        }
+        crate::sandbox::Error::Wasm(error)
+    }
+
+    /// Convert an error reported by the guest runtime, attributing it to the
+    /// memory limit when growth was denied during the current operation and
+    /// the guest failed with an out-of-memory exception.
+    ///
+    /// Guest runtimes usually turn a failed allocation into a language-level
+    /// exception (Python's `MemoryError`, JavaScript's `out of memory`), so
+    /// the denial would otherwise surface as an ordinary guest exception. Other exceptions stay user errors even after a denied
+    /// growth, since the guest may have caught the allocation failure. Guest
+    /// exceptions the runtime did not report field by field, as `exception`,
+    /// and whose message carries no traceback fall back to one printed on
+    /// stderr during the call. An exception raised from a failed hostcall,
+    /// whose message repeats the host's error, is reported as
+    /// [`ErrorKind::HostcallFailed`].
+    pub(crate) fn classify_guest_error(
+        &self,
+        error: exports::Error,
+        exception: Option<exports::GuestException>,
+    ) -> crate::sandbox::Error {
+        let mut error = crate::sandbox::Error::from(error);
+        if let crate::sandbox::Error::UserCode { message, traceback } = &mut error {
+            if let Some(exception) = exception {
+                *traceback = Some(Box::new(Traceback::from_guest(exception)));
+            } else if traceback.is_none() {
+                *traceback = Traceback::parse(self.stderr_tail.lock().as_str()).map(Box::new);
+            }
+            if self.limiter.limit_exceeded() && is_out_of_memory(message, traceback.as_deref()) {
+                return self.classify_error(wasmtime::Error::msg(std::mem::take(message)));
+            }
+            if self
+                .last_hostcall_error
+                .as_deref()
+                .is_some_and(|failure| !failure.is_empty() && message.contains(failure))
+            {
+                return crate::sandbox::Error::Wasm(
+                    wasmtime::Error::msg(Classified(ErrorKind::HostcallFailed))
+                        .context(std::mem::take(message)),
+                );
+            }
+        }
+        error
+    }
+
     #[expect(
         clippy::needless_pass_by_ref_mut,
         clippy::unused_async,
         clippy::unused_async_trait_impl,
         reason = "the async boundary is kept consistent with call cleanup hooks"
     )]
-    pub async fn flush_logs(&mut self) -> wasmtime::Result<()> {
+    pub(crate) async fn flush_logs(&mut self) -> wasmtime::Result<()> {
         Ok(())
     }
 
@@ -207,6 +564,156 @@ impl<H: Host> InstanceState<H> {
     }
 }
 
+/// Whether a guest exception reports a failed allocation: a Python
+/// `MemoryError`, or a JavaScript error whose message is `out of memory`.
+fn is_out_of_memory(message: &str, traceback: Option<&Traceback>) -> bool {
+    if let Some(traceback) = traceback {
+        return traceback.exception_type == "MemoryError";
+    }
+    let is_memory_error = |line: &str| line.split(':').next() == Some("MemoryError");
+    message.lines().next().map(str::trim) == Some("out of memory")
+        || message
+            .trim_end()
+            .lines()
+            .last()
+            .is_some_and(is_memory_error)
+}
+
+/// Reject options that need a WASI interface the template disabled.
+fn check_disabled_wasi(
+    options: &SandboxOptions,
+    disabled_wasi: &[WasiInterface],
+) -> wasmtime::Result<()> {
+    let directory_mappings = &options.directory_mappings;
+    if disabled_wasi.contains(&WasiInterface::Filesystem) && !directory_mappings.is_empty() {
+        return Err(wasmtime::Error::msg(format!(
+            "{} directory mapping(s) configured but the WASI filesystem interface is disabled",
+            directory_mappings.len()
+        )));
+    }
+    if disabled_wasi.contains(&WasiInterface::Stdio)
+        && (options.stdin.is_some()
+            || options.stdout_writer.is_some()
+            || options.stderr_writer.is_some())
+    {
+        return Err(wasmtime::Error::msg(
+            "stdio streams configured but the WASI stdio interface is disabled",
+        ));
+    }
+    if disabled_wasi.contains(&WasiInterface::Clocks) && options.clock.is_some() {
+        return Err(wasmtime::Error::msg(
+            "clock configured but the WASI clocks interface is disabled",
+        ));
+    }
+    Ok(())
+}
+
+/// Resource table for a new sandbox, capped at
+/// [`SandboxOptions::max_open_handles`].
+fn resource_table(options: &SandboxOptions) -> ResourceTable {
+    let mut table = ResourceTable::new();
+    if let Some(max_handles) = options.max_open_handles {
+        table.set_max_capacity(max_handles);
+    }
+    table
+}
+
+/// Preopen the directory mappings of `options`, returning the quotas,
+/// overlay layers and include patterns to enforce on them.
+fn preopen_mounts(
+    builder: &mut WasiCtxBuilder,
+    options: &SandboxOptions,
+) -> wasmtime::Result<(MountQuotas, MountOverlays, MountFilters)> {
+    let mut mount_quotas = MountQuotas::default();
+    let mut mount_overlays = MountOverlays::default();
+    let mut mount_filters = MountFilters::default();
+    for mapping in &options.directory_mappings {
+        let (dir_perms, file_perms) = if options.read_only {
+            (
+                mapping.dir_perms & DirPerms::READ,
+                mapping.file_perms & FilePerms::READ,
+            )
+        } else {
+            (mapping.dir_perms, mapping.file_perms)
+        };
+        if let Some(quota) = mapping.quota
+            && file_perms.contains(FilePerms::WRITE)
+        {
+            mount_quotas.insert(&mapping.guest, quota);
+        }
+        if !mapping.lower.is_empty() {
+            mount_overlays
+                .insert(&mapping.guest, &mapping.lower)
+                .map_err(|e| {
+                    wasmtime::Error::msg(format!(
+                        "Failed to add overlay mapping for '{}': {e}",
+                        mapping.guest
+                    ))
+                })?;
+        }
+        if !mapping.include.is_empty() {
+            mount_filters
+                .insert(&mapping.guest, &mapping.include)
+                .map_err(|e| {
+                    wasmtime::Error::msg(format!(
+                        "Failed to add filtered mapping for '{}': {e}",
+                        mapping.guest
+                    ))
+                })?;
+        }
+        builder
+            .preopened_dir(&mapping.host, &mapping.guest, dir_perms, file_perms)
+            .map_err(|e| {
+                wasmtime::Error::msg(format!(
+                    "Failed to add directory mapping '{}' -> '{}': {e}",
+                    mapping.host.display(),
+                    mapping.guest
+                ))
+            })?;
+    }
+    Ok((mount_quotas, mount_overlays, mount_filters))
+}
+
+/// Feed guest stdin from the configured reader and route stdout and stderr
+/// to the configured writers or, by default, the current log target.
+fn configure_stdio(
+    builder: &mut WasiCtxBuilder,
+    options: &SandboxOptions,
+    log_target_store: &LogTargetStore,
+    stderr_tail: &OutputTail,
+) {
+    if let Some(stdin) = &options.stdin {
+        builder.stdin(stdin.clone());
+    }
+    let stdio = StdioPolicy {
+        buffering: options.stdio_buffering.unwrap_or_default(),
+        max_line_length: options.max_output_line_length,
+    };
+    if let Some(stdout) = &options.stdout_writer {
+        builder.stdout(stdout.clone());
+    } else {
+        builder.stdout(TraceOutput::new(
+            LogLevel::Stdout,
+            LogContext::Stdout,
+            stdio,
+            Arc::clone(log_target_store),
+        ));
+    }
+    if let Some(stderr) = &options.stderr_writer {
+        builder.stderr(stderr.clone());
+    } else {
+        builder.stderr(
+            TraceOutput::new(
+                LogLevel::Stderr,
+                LogContext::Stderr,
+                stdio,
+                Arc::clone(log_target_store),
+            )
+            .with_tail(Arc::clone(stderr_tail)),
+        );
+    }
+}
+
 impl<H: Host> WasiView for InstanceState<H> {
     fn ctx(&mut self) -> WasiCtxView<'_> {
         WasiCtxView {
@@ -233,58 +740,95 @@ impl<H: Host> WasiHttpHooks for InstanceHttpHooks<H> {
         options: Option<RequestOptions>,
         fut: Box<dyn Future<Output = Result<(), ErrorCode>> + Send>,
     ) -> Box<dyn Future<Output = HttpSendResult> + Send> {
+        let mut traced = self.call_trace.clone().map(|trace| {
+            let target = format!("{} {}", request.method(), request.uri());
+            let pending = PendingTrace::new(TraceKind::Http, self.call_id, target, &[]);
+            (trace, pending)
+        });
+        if !self.allow_http {
+            if let Some((trace, pending)) = traced {
+                trace.finish(pending, None, TraceOutcome::Denied);
+            }
+            return Box::new(async { Err(ErrorCode::HttpRequestDenied.into()) });
+        }
+        self.requests += 1;
         let host = Arc::clone(&self.host);
+        let call_id = self.call_id;
 
         Box::new(
             async move {
-                let (parts, body) = request.into_parts();
-                let headers = parts.headers;
-
-                // Fast-path reject based on `Content-Length` if present.
-                if let Some(len) = headers.get(http::header::CONTENT_LENGTH)
-                    && let Some(len) = len.to_str().ok().and_then(|s| s.parse::<u64>().ok())
-                {
-                    let max = u64::try_from(MAX_OUTGOING_HTTP_BODY_BYTES).unwrap_or(u64::MAX);
-                    if len > max {
-                        return Err(ErrorCode::HttpRequestBodySize(Some(max)).into());
-                    }
+                let result =
+                    send_http_request(host, call_id, request, options, fut, &mut traced).await;
+                if let Some((trace, pending)) = traced {
+                    let (status, outcome) = match &result {
+                        Ok((resp, _)) => (Some(resp.status().as_u16()), TraceOutcome::Ok),
+                        Err(e) => (None, TraceOutcome::Failed(e.to_string())),
+                    };
+                    trace.finish(pending, status, outcome);
                 }
-
-                let options = options.unwrap_or_default();
-                let body_timeout = options
-                    .connect_timeout
-                    .unwrap_or(MAX_OUTGOING_HTTP_BODY_READ_TIMEOUT)
-                    .min(MAX_OUTGOING_HTTP_BODY_READ_TIMEOUT);
-                let body =
-                    collect_outgoing_http_body(body, MAX_OUTGOING_HTTP_BODY_BYTES, body_timeout)
-                        .await?;
-
-                let mut req = HttpRequest::new(body);
-                *req.method_mut() = parts.method;
-                *req.uri_mut() = parts.uri;
-                *req.headers_mut() = headers;
-                let first_byte_timeout = options
-                    .first_byte_timeout
-                    .unwrap_or(std::time::Duration::from_secs(600));
-                let resp = timeout(first_byte_timeout, host.http_request(req))
-                    .await
-                    .map_err(|_e| ErrorCode::HttpResponseTimeout)?
-                    .map_err(|e| ErrorCode::InternalError(Some(format!("request error: {e}"))))?;
-
-                let resp = resp.map(|b| {
-                    http_body_util::StreamBody::new(
-                        b.map(|e| e.map_err(|e| ErrorCode::InternalError(Some(e.to_string())))),
-                    )
-                    .boxed_unsync()
-                });
-
-                Ok((resp, fut))
+                result
             }
             .in_current_span(),
         )
     }
 }
 
+async fn send_http_request<H: Host>(
+    host: Arc<H>,
+    call_id: Option<u64>,
+    request: http::Request<http_body_util::combinators::UnsyncBoxBody<Bytes, ErrorCode>>,
+    options: Option<RequestOptions>,
+    fut: Box<dyn Future<Output = Result<(), ErrorCode>> + Send>,
+    traced: &mut Option<(Arc<CallTrace>, PendingTrace)>,
+) -> HttpSendResult {
+    let (parts, body) = request.into_parts();
+    let headers = parts.headers;
+
+    // Fast-path reject based on `Content-Length` if present.
+    if let Some(len) = headers.get(http::header::CONTENT_LENGTH)
+        && let Some(len) = len.to_str().ok().and_then(|s| s.parse::<u64>().ok())
+    {
+        let max = u64::try_from(MAX_OUTGOING_HTTP_BODY_BYTES).unwrap_or(u64::MAX);
+        if len > max {
+            return Err(ErrorCode::HttpRequestBodySize(Some(max)).into());
+        }
+    }
+
+    let options = options.unwrap_or_default();
+    let body_timeout = options
+        .connect_timeout
+        .unwrap_or(MAX_OUTGOING_HTTP_BODY_READ_TIMEOUT)
+        .min(MAX_OUTGOING_HTTP_BODY_READ_TIMEOUT);
+    let body = collect_outgoing_http_body(body, MAX_OUTGOING_HTTP_BODY_BYTES, body_timeout).await?;
+    if let (Some((_, pending)), Some(body)) = (traced.as_mut(), &body) {
+        pending.set_payload(body);
+    }
+
+    let mut req = HttpRequest::new(body);
+    *req.method_mut() = parts.method;
+    *req.uri_mut() = parts.uri;
+    *req.headers_mut() = headers;
+    let first_byte_timeout = options
+        .first_byte_timeout
+        .unwrap_or(std::time::Duration::from_secs(600));
+    let resp = timeout(
+        first_byte_timeout,
+        with_call_id(call_id, host.http_request(req)),
+    )
+    .await
+    .map_err(|_e| ErrorCode::HttpResponseTimeout)?
+    .map_err(|e| ErrorCode::InternalError(Some(format!("request error: {e}"))))?;
+
+    let resp = resp.map(|b| {
+        http_body_util::StreamBody::new(
+            b.map(|e| e.map_err(|e| ErrorCode::InternalError(Some(e.to_string())))),
+        )
+        .boxed_unsync()
+    });
+
+    Ok((resp, fut))
+}
+
 impl<H: Host> HostView for InstanceState<H> {
     type Host = H;
 
@@ -296,7 +840,45 @@ impl<H: Host> HostView for InstanceState<H> {
         &self.host
     }
 
+    fn hostcall_allowed(&mut self, call_type: &str) -> bool {
+        self.capabilities
+            .as_ref()
+            .is_none_or(|capabilities| capabilities.allows_hostcall(call_type))
+    }
+
+    fn call_id(&self) -> Option<u64> {
+        Self::call_id(self)
+    }
+
+    fn call_trace(&self) -> Option<Arc<CallTrace>> {
+        Self::call_trace(self)
+    }
+
+    fn hostcall_limiter(&self) -> Option<Arc<HostcallLimiter>> {
+        self.hostcall_limiter.clone()
+    }
+
+    fn record_hostcall(&mut self) {
+        self.exec_clock.record_hostcall();
+    }
+
+    fn record_hostcall_failure(&mut self, message: &str) {
+        self.last_hostcall_error = Some(message.to_string());
+    }
+
+    fn plugin_for(&mut self, call_type: &str) -> Option<(Arc<PluginInstance>, String)> {
+        self.plugins.iter().find_map(|plugin| {
+            let rest = call_type.strip_prefix(plugin.name())?.strip_prefix('.')?;
+            Some((Arc::clone(plugin), rest.to_string()))
+        })
+    }
+
     async fn emit(&mut self, data: EmitValue) -> wasmtime::Result<()> {
+        if let Err(limit) = self.charge_output(&data) {
+            self.output_buffer.reset();
+            return Err(wasmtime::Error::new(limit));
+        }
+        self.exec_clock.record_output(data.byte_len());
         let Some(target) = self.output_target.as_ref() else {
             return Err(wasmtime::Error::msg("output target missing"));
         };
@@ -314,16 +896,16 @@ impl<H: Host> HostView for InstanceState<H> {
                     Some(Value::from(output))
                 };
                 target
-                    .on_complete(output)
+                    .on_complete(output, self.with_call_usage(self.exec_clock.snapshot()))
                     .await
-                    .map_err(wasmtime::Error::from_boxed)
+                    .map_err(sink_error)
             }
             EmitValue::PartialResult(new_data) => {
                 let output = self.output_buffer.finish(new_data)?;
                 target
                     .on_item(Value::from(output))
                     .await
-                    .map_err(wasmtime::Error::from_boxed)
+                    .map_err(sink_error)
             }
             EmitValue::Abort => {
                 self.output_buffer.reset();
@@ -362,12 +944,41 @@ impl<H: Host> wasm::logging::HostView for InstanceState<H> {
             target
                 .on_log(output_level, output_context, message)
                 .await
-                .map_err(wasmtime::Error::from_boxed)?;
+                .map_err(sink_error)?;
         }
         Ok(())
     }
 }
 
+/// Wrap an error returned by an output sink so the call that emitted the
+/// output fails with [`ErrorKind::HostcallFailed`].
+pub fn sink_error(error: BoxError) -> wasmtime::Error {
+    wasmtime::Error::from_boxed(error).context(Classified(ErrorKind::HostcallFailed))
+}
+
+/// Clock used when the WASI clocks interface is disabled; always reads zero.
+struct FrozenClock;
+
+impl HostWallClock for FrozenClock {
+    fn resolution(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(1)
+    }
+
+    fn now(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
+
+impl HostMonotonicClock for FrozenClock {
+    fn resolution(&self) -> u64 {
+        1_000_000_000
+    }
+
+    fn now(&self) -> u64 {
+        0
+    }
+}
+
 struct OutputBuffer(BytesMut);
 
 impl OutputBuffer {
@@ -375,6 +986,11 @@ impl OutputBuffer {
         Self(BytesMut::new())
     }
 
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     #[inline]
     fn reset(&mut self) {
         let _old = std::mem::take(&mut self.0);
@@ -423,7 +1039,7 @@ mod tests {
     use parking_lot::Mutex;
 
     use super::*;
-    use crate::host::{BoxError, Host, HttpBodyStream, HttpRequest, HttpResponse};
+    use crate::host::{HttpBodyStream, HttpResponse};
 
     #[derive(Clone, Default)]
     struct ScriptedHost {
@@ -478,24 +1094,57 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn send_request_body_timeout_is_enforced() {
-        let host = ScriptedHost::default();
-        let host = Arc::new(host.clone());
-
-        let mut state = InstanceState {
+    /// Instance state for `host` with default options, as a fresh store
+    /// would hold it.
+    fn test_state(host: &Arc<ScriptedHost>) -> InstanceState<ScriptedHost> {
+        InstanceState {
             limiter: MemoryLimiter::new(1024),
             wasi: WasiCtxBuilder::new().build(),
             http: WasiHttpCtx::new(),
             table: ResourceTable::new(),
-            host: Arc::clone(&host),
+            mount_quotas: MountQuotas::default(),
+            mount_overlays: MountOverlays::default(),
+            mount_filters: MountFilters::default(),
+            live_mounts: LiveMounts::default(),
+            open_files: OpenFiles::default(),
+            write_budget: None,
+            file_policy: FilePolicyState::default(),
+            clock: None,
+            epoch_yield_ticks: 1,
+            read_only: false,
+            http_enabled: true,
+            capabilities: None,
+            plugins: Vec::new(),
+            host: Arc::clone(host),
             http_hooks: InstanceHttpHooks {
-                host: Arc::clone(&host),
+                host: Arc::clone(host),
+                allow_http: true,
+                call_id: None,
+                call_trace: None,
+                requests: 0,
             },
+            call_trace: None,
+            hostcall_limiter: None,
+            last_hostcall_error: None,
             output_target: None,
+            operations: 0,
+            last_call_id: None,
+            last_fuel_consumed: None,
+            exec_clock: ExecClock::default(),
+            last_exec_stats: None,
             log_target_store: Arc::new(Mutex::new(None)),
+            stderr_tail: OutputTail::default(),
             output_buffer: OutputBuffer::new(),
-        };
+            output_limits: OutputLimits::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_request_body_timeout_is_enforced() {
+        let host = ScriptedHost::default();
+        let host = Arc::new(host.clone());
+
+        let mut state = test_state(&host);
 
         // A body that never completes.
         let body = http_body_util::StreamBody::new(futures::stream::pending::<
@@ -549,19 +1198,7 @@ mod tests {
         let host = ScriptedHost::default();
         let host = Arc::new(host.clone());
 
-        let mut state = InstanceState {
-            limiter: MemoryLimiter::new(1024),
-            wasi: WasiCtxBuilder::new().build(),
-            http: WasiHttpCtx::new(),
-            table: ResourceTable::new(),
-            host: Arc::clone(&host),
-            http_hooks: InstanceHttpHooks {
-                host: Arc::clone(&host),
-            },
-            output_target: None,
-            log_target_store: Arc::new(Mutex::new(None)),
-            output_buffer: OutputBuffer::new(),
-        };
+        let mut state = test_state(&host);
 
         let body = http_body_util::StreamBody::new(futures::stream::empty::<
             Result<Frame<Bytes>, ErrorCode>,
@@ -656,4 +1293,112 @@ mod tests {
         assert!(buf.append(b"x").is_err());
         assert!(buf.take().is_empty());
     }
+
+    #[test]
+    fn guest_reported_exceptions_win_over_parsing() {
+        let state = test_state(&Arc::new(ScriptedHost::default()));
+        let error = state.classify_guest_error(
+            exports::Error {
+                code: exports::ErrorCode::Aborted,
+                message: "Traceback (most recent call last):\nKeyError: 'text'\n".to_string(),
+            },
+            Some(exports::GuestException {
+                exception_type: "app.Invalid".to_string(),
+                message: "bad: input".to_string(),
+                frames: vec![exports::TracebackFrame {
+                    file: "/lib/app.py".to_string(),
+                    line: Some(10),
+                    function: Some("handler".to_string()),
+                }],
+            }),
+        );
+        assert_eq!(error.exception_type(), Some("app.Invalid"));
+        let traceback = error.traceback().expect("traceback");
+        assert_eq!(traceback.message, "bad: input");
+        assert_eq!(traceback.frames[0].function.as_deref(), Some("handler"));
+
+        let parsed = state.classify_guest_error(
+            exports::Error {
+                code: exports::ErrorCode::Aborted,
+                message: "Traceback (most recent call last):\nKeyError: 'text'\n".to_string(),
+            },
+            None,
+        );
+        assert_eq!(parsed.exception_type(), Some("KeyError"));
+    }
+
+    #[tokio::test]
+    async fn failing_sinks_fail_the_call_as_hostcall_failures() {
+        let mut state = test_state(&Arc::new(ScriptedHost::default()));
+        state.set_output_target(Some(OutputTarget::synchronous(|_| {
+            Err(std::io::Error::other("sink closed").into())
+        })));
+
+        let error = state
+            .emit(EmitValue::End(Bytes::new()))
+            .await
+            .expect_err("sink must fail");
+        assert_eq!(
+            state.classify_error(error).kind(),
+            ErrorKind::HostcallFailed
+        );
+
+        let error = wasm::logging::HostView::emit_log(
+            &mut state,
+            wasm::logging::bindings::logging::Level::Info,
+            "app",
+            "message",
+        )
+        .await
+        .expect_err("sink must fail");
+        assert_eq!(
+            state.classify_error(error).kind(),
+            ErrorKind::HostcallFailed
+        );
+    }
+
+    #[test]
+    fn exceptions_from_failed_hostcalls_are_hostcall_failures() {
+        let mut state = test_state(&Arc::new(ScriptedHost::default()));
+        state.set_output_target(Some(OutputTarget::discard()));
+        let raised = || exports::Error {
+            code: exports::ErrorCode::Aborted,
+            message: "Traceback (most recent call last):\nRuntimeError: unsupported\n".to_string(),
+        };
+        assert_eq!(
+            state.classify_guest_error(raised(), None).kind(),
+            ErrorKind::GuestException
+        );
+
+        state.record_hostcall_failure("unsupported");
+        let error = state.classify_guest_error(raised(), None);
+        assert_eq!(error.kind(), ErrorKind::HostcallFailed);
+        assert!(error.to_string().contains("RuntimeError: unsupported"));
+
+        state.set_output_target(Some(OutputTarget::discard()));
+        assert_eq!(
+            state.classify_guest_error(raised(), None).kind(),
+            ErrorKind::GuestException,
+            "a new call forgets earlier hostcall failures"
+        );
+    }
+
+    #[test]
+    fn only_out_of_memory_exceptions_count_as_memory_errors() {
+        let traceback = |exception_type: &str| Traceback {
+            exception_type: exception_type.to_string(),
+            message: String::new(),
+            frames: Vec::new(),
+        };
+        assert!(is_out_of_memory("", Some(&traceback("MemoryError"))));
+        assert!(!is_out_of_memory(
+            "MemoryError",
+            Some(&traceback("ValueError"))
+        ));
+        assert!(is_out_of_memory("MemoryError", None));
+        assert!(is_out_of_memory("Traceback:\nMemoryError: big\n", None));
+        assert!(is_out_of_memory("out of memory\n\n    at main", None));
+        assert!(!is_out_of_memory("ValueError: MemoryError", None));
+        assert!(!is_out_of_memory("boom", None));
+    }
 }
@@ -0,0 +1,44 @@
+use std::{io, sync::Arc};
+
+use crate::sandbox::DirectoryMapping;
+
+/// Back every scratch mapping with a new, empty private directory.
+///
+/// Mappings cloned from a template still point at the template's directory,
+/// so each sandbox instance calls this to get its own. The directory is
+/// removed once the last mapping holding it is dropped.
+pub fn create_scratch_dirs(mappings: &mut [DirectoryMapping]) -> io::Result<()> {
+    for mapping in mappings.iter_mut().filter(|mapping| mapping.scratch) {
+        let dir = tempfile::Builder::new()
+            .prefix("isola-scratch-")
+            .tempdir()?;
+        mapping.host = dir.path().to_path_buf();
+        mapping.scratch_dir = Some(Arc::new(dir));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn scratch_dirs_are_private_and_removed_on_drop() {
+        let mut mapping = DirectoryMapping::new(PathBuf::new(), "/tmp");
+        mapping.scratch = true;
+        let mut template = [mapping, DirectoryMapping::new("/host/lib", "/lib")];
+        create_scratch_dirs(&mut template).expect("template scratch dir");
+        let mut instance = template.clone();
+        create_scratch_dirs(&mut instance).expect("instance scratch dir");
+
+        assert_ne!(template[0].host, instance[0].host);
+        assert_eq!(instance[1].host, PathBuf::from("/host/lib"));
+        let host = instance[0].host.clone();
+        assert!(host.is_dir());
+        drop(instance);
+        assert!(!host.exists());
+        assert!(template[0].host.is_dir());
+    }
+}
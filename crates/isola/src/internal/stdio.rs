@@ -0,0 +1,77 @@
+use std::{fmt, sync::Arc};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use wasmtime_wasi::{
+    cli::{AsyncStdinStream, AsyncStdoutStream, IsTerminal, StdinStream, StdoutStream},
+    p2::{InputStream, OutputStream},
+};
+
+/// Bytes a raw output stream accepts from the guest before it has to wait
+/// for the writer to catch up.
+const OUTPUT_BUDGET: usize = 64 * 1024;
+
+/// Reader feeding guest stdin, shared by every clone of the options that
+/// configured it.
+#[derive(Clone)]
+pub struct GuestStdin(Arc<AsyncStdinStream>);
+
+impl GuestStdin {
+    pub fn new(reader: impl AsyncRead + Send + Sync + 'static) -> Self {
+        Self(Arc::new(AsyncStdinStream::new(reader)))
+    }
+}
+
+impl fmt::Debug for GuestStdin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuestStdin").finish_non_exhaustive()
+    }
+}
+
+impl IsTerminal for GuestStdin {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl StdinStream for GuestStdin {
+    fn p2_stream(&self) -> Box<dyn InputStream> {
+        self.0.p2_stream()
+    }
+
+    fn async_stream(&self) -> Box<dyn AsyncRead + Send + Sync> {
+        self.0.async_stream()
+    }
+}
+
+/// Writer receiving guest stdout or stderr byte for byte, bypassing log
+/// records. Shared like [`GuestStdin`].
+#[derive(Clone)]
+pub struct GuestOutput(Arc<AsyncStdoutStream>);
+
+impl GuestOutput {
+    pub fn new(writer: impl AsyncWrite + Send + Sync + 'static) -> Self {
+        Self(Arc::new(AsyncStdoutStream::new(OUTPUT_BUDGET, writer)))
+    }
+}
+
+impl fmt::Debug for GuestOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuestOutput").finish_non_exhaustive()
+    }
+}
+
+impl IsTerminal for GuestOutput {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl StdoutStream for GuestOutput {
+    fn p2_stream(&self) -> Box<dyn OutputStream> {
+        self.0.p2_stream()
+    }
+
+    fn async_stream(&self) -> Box<dyn AsyncWrite + Send + Sync> {
+        self.0.async_stream()
+    }
+}
@@ -15,26 +15,56 @@ use wasmtime_wasi::{
     p2::{OutputStream, Pollable, StreamError, StreamResult},
 };
 
-use crate::host::{LogContext, LogLevel, OutputTarget};
+use crate::{
+    host::{LogContext, LogLevel, OutputTarget},
+    internal::sandbox::state::sink_error,
+    sandbox::StdioBuffering,
+};
+
+/// How a guest output stream groups writes into log records.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdioPolicy {
+    pub buffering: StdioBuffering,
+    pub max_line_length: Option<usize>,
+}
+
+impl StdioPolicy {
+    /// Longest unterminated line held in [`StdioBuffering::Line`] mode.
+    fn line_limit(self) -> usize {
+        self.max_line_length.unwrap_or(DEFAULT_MAX_LINE).max(1)
+    }
+}
 
 pub struct TraceOutput {
     level: LogLevel,
     context: LogContext<'static>,
+    policy: StdioPolicy,
     target_store: LogTargetStore,
+    tail: Option<OutputTail>,
 }
 
 impl TraceOutput {
     pub const fn new(
         level: LogLevel,
         context: LogContext<'static>,
+        policy: StdioPolicy,
         target_store: LogTargetStore,
     ) -> Self {
         Self {
             level,
             context,
+            policy,
             target_store,
+            tail: None,
         }
     }
+
+    /// Also keep the most recent output of this stream in `tail`.
+    #[must_use]
+    pub fn with_tail(mut self, tail: OutputTail) -> Self {
+        self.tail = Some(tail);
+        self
+    }
 }
 
 impl StdoutStream for TraceOutput {
@@ -47,7 +77,9 @@ impl StdoutStream for TraceOutput {
         Box::new(TraceOutputStream {
             level: self.level,
             context: self.context,
+            policy: self.policy,
             target_store: Arc::clone(&self.target_store),
+            tail: self.tail.clone(),
             buffer: SmallVec::new(),
             in_flight: None,
             last_error: None,
@@ -64,7 +96,9 @@ impl IsTerminal for TraceOutput {
 pub struct TraceOutputStream {
     level: LogLevel,
     context: LogContext<'static>,
+    policy: StdioPolicy,
     target_store: LogTargetStore,
+    tail: Option<OutputTail>,
     buffer: SmallVec<[u8; MAX_BUFFER + MAX_UTF8_BYTES]>,
     in_flight: Option<wasmtime_wasi::runtime::AbortOnDropJoinHandle<wasmtime::Result<()>>>,
     last_error: Option<wasmtime::Error>,
@@ -73,19 +107,29 @@ pub struct TraceOutputStream {
 const MIN_BUFFER: usize = 64;
 const MAX_BUFFER: usize = 1024;
 const MAX_UTF8_BYTES: usize = 4;
+const DEFAULT_MAX_LINE: usize = 64 * 1024;
 
 impl TraceOutputStream {
-    fn record(&mut self, message: String) -> StreamResult<()> {
+    /// Emit `messages` in order; only one emit may be in flight at a time.
+    fn record(&mut self, mut messages: Vec<String>) -> StreamResult<()> {
         if self.in_flight.is_some() {
             return Err(StreamError::Trap(wasmtime::Error::msg(
                 "write not permitted while emit pending",
             )));
         }
 
-        if message.is_empty() {
+        messages.retain(|message| !message.is_empty());
+        if messages.is_empty() {
             return Ok(());
         }
 
+        if let Some(tail) = &self.tail {
+            let mut tail = tail.lock();
+            for message in &messages {
+                tail.push(message);
+            }
+        }
+
         let Some(target) = self.target_store.lock().clone() else {
             return Ok(());
         };
@@ -93,10 +137,13 @@ impl TraceOutputStream {
         let level = self.level;
         let context = self.context;
         let mut future = Box::pin(async move {
-            target
-                .on_log(level, context, &message)
-                .await
-                .map_err(wasmtime::Error::from_boxed)
+            for message in &messages {
+                target
+                    .on_log(level, context, message)
+                    .await
+                    .map_err(sink_error)?;
+            }
+            Ok(())
         });
         let waker = noop_waker_ref();
         let mut cx = Context::from_waker(waker);
@@ -108,6 +155,63 @@ impl TraceOutputStream {
             }
         }
     }
+
+    /// Decode and emit the buffer according to the stream's policy.
+    ///
+    /// In line mode an unterminated final line stays buffered unless `force`
+    /// is set or it has reached the line limit. A trailing partial UTF-8
+    /// sequence is always retained.
+    fn emit_buffered(&mut self, force: bool) -> StreamResult<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let max = self.policy.max_line_length;
+        let (text, remainder) = decode_utf8(&self.buffer);
+        let mut records = Vec::new();
+        let held = if self.policy.buffering == StdioBuffering::Line {
+            let end = text.rfind('\n').map_or(0, |i| i + 1);
+            let (lines, partial) = text.split_at(end);
+            for line in lines.split_inclusive('\n') {
+                split_records(line, max, &mut records);
+            }
+            if force || partial.len() >= self.policy.line_limit() {
+                split_records(partial, Some(self.policy.line_limit()), &mut records);
+                ""
+            } else {
+                partial
+            }
+        } else {
+            split_records(&text, max, &mut records);
+            ""
+        };
+        let mut rest = SmallVec::from_slice(held.as_bytes());
+        rest.extend(remainder);
+        self.buffer = rest;
+        self.record(records)
+    }
+}
+
+/// Push `text` onto `out`, cut into pieces of at most `max` bytes at
+/// character boundaries.
+fn split_records(mut text: &str, max: Option<usize>, out: &mut Vec<String>) {
+    let max = max.unwrap_or(usize::MAX).max(1);
+    while text.len() > max {
+        let mut cut = max;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if cut == 0 {
+            // A single character wider than `max`; emit it whole.
+            cut = text.chars().next().map_or(text.len(), char::len_utf8);
+        }
+        let (head, tail) = text.split_at(cut);
+        out.push(head.to_string());
+        text = tail;
+    }
+    if !text.is_empty() {
+        out.push(text.to_string());
+    }
 }
 
 #[async_trait::async_trait]
@@ -159,26 +263,13 @@ impl OutputStream for TraceOutputStream {
             )));
         }
 
-        if bytes.len() + self.buffer.len() < MIN_BUFFER {
-            self.buffer.extend(bytes);
-            return Ok(());
+        let buffering = self.policy.buffering;
+        self.buffer.extend(bytes);
+        match buffering {
+            StdioBuffering::Chunked if self.buffer.len() < MIN_BUFFER => Ok(()),
+            StdioBuffering::Line => self.emit_buffered(false),
+            _ => self.emit_buffered(true),
         }
-
-        let buf: &[u8] = if self.buffer.is_empty() {
-            &bytes
-        } else {
-            self.buffer.extend(bytes);
-            &self.buffer
-        };
-        let (s, remainder) = decode_utf8(buf);
-        if !s.is_empty() {
-            self.record(s.into_owned())?;
-        }
-        self.buffer.clear();
-        if !remainder.is_empty() {
-            self.buffer.extend(remainder);
-        }
-        Ok(())
     }
 
     fn flush(&mut self) -> StreamResult<()> {
@@ -193,17 +284,7 @@ impl OutputStream for TraceOutputStream {
             return Ok(());
         }
 
-        if !self.buffer.is_empty() {
-            let (s, remainder) = decode_utf8(&self.buffer);
-            if !s.is_empty() {
-                self.record(s.into_owned())?;
-            }
-            self.buffer.clear();
-            if !remainder.is_empty() {
-                self.buffer.extend(remainder);
-            }
-        }
-        Ok(())
+        self.emit_buffered(true)
     }
 
     fn check_write(&mut self) -> StreamResult<usize> {
@@ -215,13 +296,50 @@ impl OutputStream for TraceOutputStream {
             return Ok(0);
         }
 
-        let local_capacity = MAX_BUFFER.saturating_sub(self.buffer.len());
+        // Line mode may hold an unterminated line longer than `MAX_BUFFER`;
+        // it is bounded by the line limit instead, so keep accepting writes.
+        let local_capacity = match self.policy.buffering {
+            StdioBuffering::Chunked => MAX_BUFFER.saturating_sub(self.buffer.len()),
+            _ => MAX_BUFFER,
+        };
         Ok(local_capacity)
     }
 }
 
 pub type LogTargetStore = Arc<Mutex<Option<OutputTarget>>>;
 
+/// Most recent output of a stream, kept so failures can be diagnosed from
+/// what the guest printed.
+#[derive(Debug, Default)]
+pub struct TailBuffer {
+    text: String,
+}
+
+pub type OutputTail = Arc<Mutex<TailBuffer>>;
+
+const MAX_TAIL: usize = 16 * 1024;
+
+impl TailBuffer {
+    fn push(&mut self, message: &str) {
+        self.text.push_str(message);
+        if self.text.len() > MAX_TAIL {
+            let mut cut = self.text.len() - MAX_TAIL;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+    }
+}
+
 #[must_use]
 pub fn new_log_target_store() -> LogTargetStore {
     Arc::new(Mutex::new(None))
@@ -237,10 +355,16 @@ mod tests {
     use super::*;
 
     fn new_stream() -> TraceOutputStream {
+        new_stream_with(StdioPolicy::default())
+    }
+
+    fn new_stream_with(policy: StdioPolicy) -> TraceOutputStream {
         TraceOutputStream {
             level: LogLevel::Info,
             context: LogContext::Other("test"),
+            policy,
             target_store: new_log_target_store(),
+            tail: None,
             buffer: SmallVec::new(),
             in_flight: None,
             last_error: None,
@@ -333,4 +457,54 @@ mod tests {
         // The partial byte should remain
         assert_eq!(s.buffer.as_slice(), &[0xE2]);
     }
+
+    #[test]
+    fn line_mode_holds_unterminated_line() {
+        let mut s = new_stream_with(StdioPolicy {
+            buffering: StdioBuffering::Line,
+            max_line_length: Some(8),
+        });
+        s.write(Bytes::from_static(b"one\ntw")).unwrap();
+        assert_eq!(s.buffer.as_slice(), b"tw");
+        s.write(Bytes::from_static(b"o\n")).unwrap();
+        assert!(s.buffer.is_empty());
+
+        // An unterminated line is emitted once it reaches the limit.
+        s.write(Bytes::from_static(b"abcdefgh")).unwrap();
+        assert!(s.buffer.is_empty());
+
+        s.write(Bytes::from_static(b"tail")).unwrap();
+        s.flush().unwrap();
+        assert!(s.buffer.is_empty());
+    }
+
+    #[test]
+    fn records_split_at_char_boundaries() {
+        let mut out = Vec::new();
+        split_records("aaüaa", Some(3), &mut out);
+        assert_eq!(out, ["aa", "üa", "a"]);
+
+        out.clear();
+        split_records("üü", Some(1), &mut out);
+        assert_eq!(out, ["ü", "ü"]);
+
+        out.clear();
+        split_records("line\n", None, &mut out);
+        assert_eq!(out, ["line\n"]);
+    }
+
+    #[test]
+    fn tail_keeps_recent_output() {
+        let tail = OutputTail::default();
+        let mut s = new_stream();
+        s.tail = Some(Arc::clone(&tail));
+        s.write(Bytes::from_static(b"first\n")).unwrap();
+        s.flush().unwrap();
+        assert_eq!(tail.lock().as_str(), "first\n");
+
+        tail.lock().push(&"x".repeat(MAX_TAIL));
+        let kept = tail.lock().as_str().to_string();
+        assert_eq!(kept.len(), MAX_TAIL);
+        assert!(kept.bytes().all(|b| b == b'x'));
+    }
 }
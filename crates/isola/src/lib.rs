@@ -96,6 +96,22 @@
 //!
 //! - **`serde`** (enabled by default): adds serde and JSON conversion methods
 //!   to [`value::Value`] and exports the `args!` macro.
+//! - **`otel`**: adds `host::OtelOutputSink`, which forwards guest logs to an
+//!   OpenTelemetry logger and records items as span events.
+//! - **`remote-cache`**: adds `sandbox::HttpCacheBackend`, a
+//!   [`sandbox::CacheBackend`] that shares compiled artifacts through an HTTP
+//!   object store.
+//! - **`remote-template`**: adds `TemplateSource::Url` to
+//!   [`sandbox::TemplateSource`], which downloads a runtime component pinned
+//!   by its SHA-256 digest and keeps it in the compile cache.
+//! - **`signature`**: adds `sandbox::Ed25519TrustPolicy`, a built-in
+//!   [`sandbox::TrustPolicy`] for detached Ed25519 signatures.
+//! - **`archive`**: adds `mount_archive` and `mount_archive_bytes` to
+//!   [`sandbox::SandboxOptions`] and [`sandbox::SandboxTemplateBuilder`], which
+//!   expose tar and zip archives to the guest as read-only directories.
+//! - **`pulley`**: adds `interpreter` to [`sandbox::SandboxTemplateBuilder`],
+//!   which runs guest code in Wasmtime's Pulley interpreter for platforms
+//!   that forbid JIT-compiled code, such as iOS.
 
 /// Host integration traits and transport types.
 pub mod host;
@@ -0,0 +1,154 @@
+use std::io;
+
+/// Shared store for compiled template artifacts.
+///
+/// A backend sits behind the local cache directory configured with
+/// [`SandboxTemplateBuilder::cache`](crate::sandbox::SandboxTemplateBuilder::cache):
+/// on a local miss the builder asks the backend for the artifact before
+/// compiling, and uploads freshly compiled artifacts so other nodes can reuse
+/// them. Keys already encode the runtime, engine, and template settings, so a
+/// single backend can be shared by hosts with different configurations.
+///
+/// Artifacts are native code. The builder only checks that they deserialize
+/// for the current engine, recompiling and replacing them otherwise; a
+/// [`TrustPolicy`](crate::sandbox::TrustPolicy) verifies the runtime source
+/// before the lookup but cannot vouch for an artifact a backend returns.
+/// Installing a backend is therefore `unsafe`: only point one at storage
+/// that is as trusted as the host itself, or have [`load`](Self::load)
+/// authenticate what it returns.
+#[async_trait::async_trait]
+pub trait CacheBackend: Send + Sync + 'static {
+    /// Fetch the artifact stored under `key`, or `None` if it is absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend cannot be reached. The builder treats
+    /// errors as a miss and compiles locally.
+    async fn load(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Store `artifact` under `key`, replacing any previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the upload fails. Uploads are best effort and never
+    /// fail the build.
+    async fn store(&self, key: &str, artifact: &[u8]) -> io::Result<()>;
+}
+
+/// [`CacheBackend`] that stores artifacts in an HTTP object store.
+///
+/// Artifacts are fetched with `GET {base_url}/{key}.cwasm` and uploaded with
+/// `PUT` to the same URL, which matches the object APIs of S3, GCS, and most
+/// artifact servers. Authentication headers can be attached with
+/// [`HttpCacheBackend::header`]; implement [`CacheBackend`] directly for
+/// stores that need request signing.
+///
+/// Available with the `remote-cache` feature.
+#[cfg(feature = "remote-cache")]
+#[derive(Clone, Debug)]
+pub struct HttpCacheBackend {
+    client: reqwest::Client,
+    base_url: String,
+    headers: http::HeaderMap,
+}
+
+#[cfg(feature = "remote-cache")]
+impl HttpCacheBackend {
+    /// Create a backend rooted at `base_url`.
+    #[must_use]
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            headers: http::HeaderMap::new(),
+        }
+    }
+
+    /// Send `value` as header `name` with every request.
+    #[must_use]
+    pub fn header(mut self, name: http::HeaderName, value: http::HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{key}.cwasm", self.base_url)
+    }
+}
+
+#[cfg(feature = "remote-cache")]
+#[async_trait::async_trait]
+impl CacheBackend for HttpCacheBackend {
+    async fn load(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let response = self
+            .client
+            .get(self.url(key))
+            .headers(self.headers.clone())
+            .send()
+            .await
+            .map_err(io::Error::other)?;
+        if response.status() == http::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(io::Error::other)?;
+        let body = response.bytes().await.map_err(io::Error::other)?;
+        Ok(Some(body.to_vec()))
+    }
+
+    async fn store(&self, key: &str, artifact: &[u8]) -> io::Result<()> {
+        self.client
+            .put(self.url(key))
+            .headers(self.headers.clone())
+            .body(artifact.to_vec())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "remote-cache"))]
+mod tests {
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_bytes, header, method, path},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn http_backend_round_trips_artifacts() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/cache/present.cwasm"))
+            .and(header("authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"artifact".to_vec()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/cache/absent.cwasm"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/cache/new.cwasm"))
+            .and(body_bytes(b"compiled".to_vec()))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let backend = HttpCacheBackend::new(format!("{}/cache/", server.uri())).header(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_static("Bearer token"),
+        );
+        assert_eq!(
+            backend.load("present").await.expect("load"),
+            Some(b"artifact".to_vec())
+        );
+        assert_eq!(backend.load("absent").await.expect("load"), None);
+        backend.store("new", b"compiled").await.expect("store");
+        assert!(backend.store("missing", b"x").await.is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use super::{Arg, CallOptions, CallOutput, Error, Result, Sandbox, lower_args};
+use crate::{
+    host::{Host, OutputTarget},
+    internal::{
+        module::call::{CallCleanup, call_export},
+        sandbox::batch::BatchCall,
+    },
+};
+
+impl<H: Host> Sandbox<H> {
+    /// Call several guest functions back to back in one guest invocation.
+    ///
+    /// Each `(function, args)` pair runs in order as if passed to
+    /// [`Sandbox::call`], but the batch crosses the host/guest boundary only
+    /// once, which makes it much cheaper than separate calls when each call
+    /// does little work. One result is returned per pair, in order. A failing
+    /// call does not stop the batch; its entry holds the error and the calls
+    /// after it still run.
+    ///
+    /// Guest logs are discarded; use
+    /// [`call_many_with_logs`](Self::call_many_with_logs) to receive them.
+    /// [`CallOutput::exec_stats`] of every entry covers the batch from its
+    /// start to the end of that call. Runtimes whose
+    /// [`world`](super::SandboxTemplate::world) lacks
+    /// [`batch`](super::ScriptWorld::batch) get one invocation per call
+    /// instead, and each entry's statistics cover only its own call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, instead of per-call results, if the WebAssembly
+    /// runtime traps, the sandbox is interrupted, or the guest runtime does
+    /// not report exactly one outcome per call.
+    pub async fn call_many<F, I>(
+        &mut self,
+        batch: impl IntoIterator<Item = (F, I)>,
+    ) -> Result<Vec<Result<CallOutput>>>
+    where
+        F: Into<String>,
+        I: IntoIterator<Item = Arg>,
+    {
+        self.call_many_with_logs(batch, OutputTarget::discard())
+            .await
+    }
+
+    /// Run a batch as [`call_many`](Self::call_many) does, delivering guest
+    /// log records to `logs`.
+    ///
+    /// Logs of every call arrive in order as [`OutputEvent::Log`] events;
+    /// values and results are still returned per call. A failure to deliver
+    /// a log record fails the call that emitted it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`call_many`](Self::call_many).
+    ///
+    /// [`OutputEvent::Log`]: crate::host::OutputEvent::Log
+    pub async fn call_many_with_logs<F, I>(
+        &mut self,
+        batch: impl IntoIterator<Item = (F, I)>,
+        logs: impl Into<OutputTarget>,
+    ) -> Result<Vec<Result<CallOutput>>>
+    where
+        F: Into<String>,
+        I: IntoIterator<Item = Arg>,
+    {
+        let logs = logs.into();
+        let Some(exports) = &self.bindings.batch else {
+            return self.call_each(batch, logs).await;
+        };
+        let func = exports.func_call_batch();
+
+        let mut store = CallCleanup::new(&mut self.store);
+        let calls = batch
+            .into_iter()
+            .map(|(function, args)| {
+                Ok(BatchCall {
+                    func: function.into(),
+                    args: lower_args(&mut store, args)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let expected = calls.len();
+
+        let outputs = Arc::new(Mutex::new(vec![CallOutput::default()]));
+        let target = OutputTarget::capture_batch(Arc::clone(&outputs), logs);
+
+        store.set_fuel_budget(self.origin.options.max_fuel)?;
+        store.set_interrupts(None, Arc::clone(&self.interrupt));
+        store.set_output_target(target);
+        let result = call_export(
+            &mut store,
+            func,
+            (calls,),
+            self.native_async,
+            None,
+            &self.interrupt,
+        )
+        .await;
+        let flush_result = store.data_mut().flush_logs().await.map_err(Error::Wasm);
+        let (results,) = result.map_err(|e| store.classify_error(e))?;
+        flush_result?;
+
+        let mut outputs = std::mem::take(&mut *outputs.lock());
+        // Drop the entry opened after the last completion.
+        outputs.pop();
+        if results.len() != expected || outputs.len() != expected {
+            return Err(Error::Other(
+                std::io::Error::other(format!(
+                    "guest reported {} results and {} completions for {expected} calls",
+                    results.len(),
+                    outputs.len()
+                ))
+                .into(),
+            ));
+        }
+        Ok(results
+            .into_iter()
+            .zip(outputs)
+            .map(|(result, output)| {
+                result
+                    .map(|()| output)
+                    .map_err(|e| store.data().classify_guest_error(e.error, e.exception))
+            })
+            .collect())
+    }
+
+    /// Run a batch as separate `call-func` invocations, for runtimes that do
+    /// not export `call-batch`.
+    async fn call_each<F, I>(
+        &mut self,
+        batch: impl IntoIterator<Item = (F, I)>,
+        logs: OutputTarget,
+    ) -> Result<Vec<Result<CallOutput>>>
+    where
+        F: Into<String>,
+        I: IntoIterator<Item = Arg>,
+    {
+        let mut results = Vec::new();
+        for (function, args) in batch {
+            // A batch of one, so logs reach `logs` as in a real batch.
+            let outputs = Arc::new(Mutex::new(vec![CallOutput::default()]));
+            let target = OutputTarget::capture_batch(Arc::clone(&outputs), logs.clone());
+            let coverage = self
+                .call_guest(&function.into(), args, target, CallOptions::default())
+                .await?;
+            let output = Mutex::new(outputs.lock().swap_remove(0));
+            results.push(coverage.map(|coverage| self.collected_output(&output, coverage)));
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,259 @@
+/// Privilege that a guest call may be granted through [`CallOptions`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Send outgoing HTTP requests through
+    /// [`Host::http_request`](crate::host::Host::http_request).
+    Http,
+    /// Invoke hostcalls whose call type matches this pattern.
+    ///
+    /// A pattern ending in `*` matches every call type with the preceding
+    /// prefix, so `"kv.*"` allows `kv.get` and `kv.put`. Any other pattern must
+    /// match the call type exactly.
+    Hostcall(String),
+    /// Create, modify, or remove entries on writable mounts.
+    FsWrite,
+}
+
+/// Set of [`Capability`] values granted to one guest call.
+///
+/// An empty set denies every privileged operation. Operations that are not
+/// granted are rejected by the runtime before reaching the [`Host`]; the guest
+/// observes them as failed hostcalls, denied HTTP requests, or read-only
+/// filesystem errors.
+///
+/// [`Host`]: crate::host::Host
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CapabilitySet {
+    http: bool,
+    fs_write: bool,
+    hostcalls: Vec<String>,
+}
+
+impl CapabilitySet {
+    /// Create an empty set that grants nothing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant `capability` in addition to the capabilities already present.
+    #[must_use]
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.insert(capability);
+        self
+    }
+
+    fn insert(&mut self, capability: Capability) {
+        match capability {
+            Capability::Http => self.http = true,
+            Capability::FsWrite => self.fs_write = true,
+            Capability::Hostcall(pattern) => self.hostcalls.push(pattern),
+        }
+    }
+
+    /// Return whether outgoing HTTP requests are granted.
+    #[must_use]
+    pub const fn allows_http(&self) -> bool {
+        self.http
+    }
+
+    /// Return whether filesystem writes are granted.
+    #[must_use]
+    pub const fn allows_fs_write(&self) -> bool {
+        self.fs_write
+    }
+
+    /// Return whether a hostcall with `call_type` is granted.
+    #[must_use]
+    pub fn allows_hostcall(&self, call_type: &str) -> bool {
+        self.hostcalls.iter().any(|pattern| {
+            pattern.strip_suffix('*').map_or_else(
+                || pattern == call_type,
+                |prefix| call_type.starts_with(prefix),
+            )
+        })
+    }
+}
+
+impl FromIterator<Capability> for CapabilitySet {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for capability in iter {
+            set.insert(capability);
+        }
+        set
+    }
+}
+
+use std::time::Instant;
+
+/// Per-call settings for
+/// [`Sandbox::call_with_options`](crate::sandbox::Sandbox::call_with_options).
+///
+/// Default options run the call with every privilege the sandbox itself has.
+#[derive(Clone, Debug, Default)]
+pub struct CallOptions {
+    pub(crate) capabilities: Option<CapabilitySet>,
+    pub(crate) coverage: bool,
+    pub(crate) fresh_globals: bool,
+    pub(crate) max_fuel: Option<u64>,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) max_output_bytes: Option<usize>,
+    pub(crate) max_output_items: Option<usize>,
+}
+
+impl CallOptions {
+    /// Restrict the call to the given capabilities.
+    ///
+    /// Capabilities only narrow what the sandbox is already configured to do;
+    /// granting [`Capability::FsWrite`] does not make a read-only mount
+    /// writable.
+    #[must_use]
+    pub fn capabilities(mut self, capabilities: CapabilitySet) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Record which lines of guest code the call executes.
+    ///
+    /// The report is returned in
+    /// [`CallOutput::coverage`](crate::sandbox::CallOutput::coverage) by
+    /// [`Sandbox::call_collect`](crate::sandbox::Sandbox::call_collect); calls
+    /// that deliver output to a target do not return it. Only the Python
+    /// runtime supports coverage, which it collects with `sys.monitoring`;
+    /// other runtimes fail the call, with [`Error::IncompatibleWorld`] if
+    /// they lack [`coverage`](crate::sandbox::ScriptWorld::coverage).
+    ///
+    /// [`Error::IncompatibleWorld`]: crate::sandbox::Error::IncompatibleWorld
+    #[must_use]
+    pub const fn coverage(mut self, enabled: bool) -> Self {
+        self.coverage = enabled;
+        self
+    }
+
+    /// Run the call in a copy of the guest and discard the copy afterwards.
+    ///
+    /// The copy is restored from a [snapshot](crate::sandbox::Sandbox::snapshot)
+    /// of the sandbox, so nothing the call does inside the guest reaches the
+    /// next call: rebound globals, patched modules such as a replaced
+    /// `json.dumps`, edits to `sys.modules` or JavaScript builtin prototypes,
+    /// and any other change to guest memory are all dropped with the copy.
+    /// Effects outside the guest, such as files written to mounts and
+    /// hostcalls, remain.
+    ///
+    /// Requires a template built with
+    /// [`snapshots`](crate::sandbox::SandboxTemplateBuilder::snapshots) and a
+    /// sandbox instantiated, not restored, from it; otherwise the call fails.
+    /// The first such call, and the first one after an eval or a call
+    /// without this option, takes a snapshot, which compiles a component and
+    /// takes about as long as building the template without a cache. Later
+    /// calls only instantiate the snapshot.
+    #[must_use]
+    pub const fn fresh_globals(mut self, enabled: bool) -> Self {
+        self.fresh_globals = enabled;
+        self
+    }
+
+    /// Limit the call to `max_fuel` units of fuel, overriding
+    /// [`SandboxOptions::max_fuel`](crate::sandbox::SandboxOptions::max_fuel).
+    ///
+    /// Requires a template built with
+    /// [`fuel_metering`](crate::sandbox::SandboxTemplateBuilder::fuel_metering);
+    /// otherwise the call fails before the guest runs.
+    #[must_use]
+    pub const fn max_fuel(mut self, max_fuel: u64) -> Self {
+        self.max_fuel = Some(max_fuel);
+        self
+    }
+
+    /// Abort the call if it is still running at `deadline`.
+    ///
+    /// The runtime enforces the deadline itself: guest code is interrupted
+    /// at its first yield after the deadline, within one
+    /// [epoch tick](crate::sandbox::SandboxTemplateBuilder::epoch_tick) by
+    /// default, and a call
+    /// waiting on the host is abandoned at the deadline. Either way the call
+    /// fails with [`ErrorKind::Timeout`](crate::sandbox::ErrorKind::Timeout)
+    /// and the guest stops running. A call interrupted midway may leave guest
+    /// state inconsistent; [`Sandbox::reset`](crate::sandbox::Sandbox::reset)
+    /// recovers the sandbox.
+    #[must_use]
+    pub const fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Fail the call once the values it emits exceed `max_bytes` in total.
+    ///
+    /// Every yielded item and the return value count with their encoded
+    /// size, and partially emitted values count as they arrive, so the host
+    /// never buffers more than the limit. The call fails with
+    /// [`Error::OutputLimitExceeded`](crate::sandbox::Error::OutputLimitExceeded)
+    /// and values delivered before that remain delivered. Logs and stdio do
+    /// not count.
+    #[must_use]
+    pub const fn max_output_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Fail the call once it emits more than `max_items` values.
+    ///
+    /// Every yielded item counts, as does a return value other than `None`.
+    /// The call fails like it does for
+    /// [`max_output_bytes`](Self::max_output_bytes).
+    #[must_use]
+    pub const fn max_output_items(mut self, max_items: usize) -> Self {
+        self.max_output_items = Some(max_items);
+        self
+    }
+}
+
+/// Output limit a call exceeded, reported by
+/// [`Error::OutputLimitExceeded`](crate::sandbox::Error::OutputLimitExceeded).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OutputLimit {
+    /// The byte limit set with [`CallOptions::max_output_bytes`].
+    Bytes(usize),
+    /// The item limit set with [`CallOptions::max_output_items`].
+    Items(usize),
+}
+
+impl std::fmt::Display for OutputLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bytes(max) => write!(f, "more than {max} bytes of output"),
+            Self::Items(max) => write!(f, "more than {max} output items"),
+        }
+    }
+}
+
+impl std::error::Error for OutputLimit {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hostcall_patterns_match_exact_names_and_prefixes() {
+        let set: CapabilitySet = [
+            Capability::Hostcall("kv.*".to_string()),
+            Capability::Hostcall("echo".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(set.allows_hostcall("kv.get"));
+        assert!(set.allows_hostcall("echo"));
+        assert!(!set.allows_hostcall("echo.twice"));
+        assert!(!set.allows_hostcall("kvstore"));
+        assert!(!set.allows_http());
+        assert!(!set.allows_fs_write());
+
+        let set = set.allow(Capability::Http).allow(Capability::FsWrite);
+        assert!(set.allows_http());
+        assert!(set.allows_fs_write());
+    }
+}
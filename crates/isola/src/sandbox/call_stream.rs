@@ -0,0 +1,124 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use tokio::sync::mpsc;
+
+use super::{Arg, CallOptions, CoverageReport, Result, Sandbox};
+use crate::{
+    host::{Host, OutputEvent, OutputTarget},
+    value::Value,
+};
+
+/// Items buffered between the guest and a slow consumer before the guest
+/// waits.
+const STREAM_BUFFER: usize = 16;
+
+type CallFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<CoverageReport>>> + Send + 'a>>;
+
+/// Values emitted by a guest call, returned by [`Sandbox::call_stream`].
+///
+/// Polling the stream runs the call. The stream yields every value the guest
+/// yields or emits and ends when the call finishes, whether or not it
+/// succeeded; [`result`](Self::result) then reports the outcome. Log records
+/// are not part of the stream.
+///
+/// The guest waits while the consumer falls behind, so a stream that is
+/// never polled never finishes its call.
+#[must_use = "streams do nothing unless polled"]
+pub struct CallStream<'a> {
+    call: Option<CallFuture<'a>>,
+    events: mpsc::Receiver<OutputEvent>,
+    outcome: Option<Result<()>>,
+    value: Option<Value>,
+}
+
+impl std::fmt::Debug for CallStream<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallStream")
+            .field("finished", &self.call.is_none())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CallStream<'_> {
+    /// Run the call to completion and return its final value.
+    ///
+    /// Values the stream has not yielded yet are discarded. `Ok(None)` means
+    /// the guest returned without an encoded value, as for
+    /// [`CallOutput::result`](super::CallOutput::result).
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that failed the call, as [`Sandbox::call`] would.
+    pub async fn result(mut self) -> Result<Option<Value>> {
+        while futures::StreamExt::next(&mut self).await.is_some() {}
+        // The outcome is always recorded once the stream has ended.
+        self.outcome
+            .take()
+            .unwrap_or(Ok(()))
+            .map(|()| self.value.take())
+    }
+}
+
+impl Stream for CallStream<'_> {
+    type Item = Value;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Value>> {
+        loop {
+            match self.events.poll_recv(cx) {
+                Poll::Ready(Some(OutputEvent::Item(value))) => return Poll::Ready(Some(value)),
+                Poll::Ready(Some(OutputEvent::Complete(value))) => {
+                    self.value = value;
+                    continue;
+                }
+                Poll::Ready(Some(_)) => continue,
+                // Nothing buffered; make progress on the call instead.
+                Poll::Ready(None) | Poll::Pending => {}
+            }
+            let Some(call) = self.call.as_mut() else {
+                return Poll::Ready(None);
+            };
+            match call.as_mut().poll(cx) {
+                Poll::Ready(outcome) => {
+                    self.call = None;
+                    self.outcome = Some(outcome.map(|_| ()));
+                    // Drain what the call sent before finishing.
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<H: Host> Sandbox<H> {
+    /// Call a guest function and consume its output as a [`Stream`].
+    ///
+    /// This is an alternative to [`Sandbox::call_with_sink`] that works with
+    /// standard stream combinators instead of an
+    /// [`OutputSink`](crate::host::OutputSink). The final return value and
+    /// any error are available from [`CallStream::result`] once the stream
+    /// ends.
+    pub fn call_stream<'a, I>(&'a mut self, function: &'a str, args: I) -> CallStream<'a>
+    where
+        I: IntoIterator<Item = Arg> + Send + 'a,
+        I::IntoIter: Send,
+    {
+        let (sender, events) = mpsc::channel(STREAM_BUFFER);
+        let target = OutputTarget::bounded(sender);
+        CallStream {
+            call: Some(Box::pin(self.call_impl(
+                function,
+                args,
+                target,
+                CallOptions::default(),
+            ))),
+            events,
+            outcome: None,
+            value: None,
+        }
+    }
+}
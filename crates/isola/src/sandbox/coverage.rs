@@ -0,0 +1,76 @@
+use crate::internal::sandbox::coverage;
+
+/// Lines of guest code executed during one call.
+///
+/// Collected when
+/// [`CallOptions::coverage`](crate::sandbox::CallOptions::coverage) is
+/// enabled and returned in
+/// [`CallOutput::coverage`](crate::sandbox::CallOutput::coverage). Only user
+/// code is reported; the guest language's standard library and the runtime's
+/// own modules are left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CoverageReport {
+    /// Covered files, sorted by path.
+    pub files: Vec<FileCoverage>,
+}
+
+impl CoverageReport {
+    /// Return the executed lines of the file at `path`, or `None` if no line
+    /// of it ran.
+    #[must_use]
+    pub fn lines(&self, path: &str) -> Option<&[u32]> {
+        self.files
+            .iter()
+            .find(|file| file.path == path)
+            .map(|file| file.lines.as_slice())
+    }
+}
+
+/// Executed lines of one guest source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileCoverage {
+    /// Guest path of the file, or a placeholder such as `<string>` for code
+    /// evaluated with
+    /// [`Sandbox::eval_script`](crate::sandbox::Sandbox::eval_script).
+    pub path: String,
+    /// 1-based line numbers that ran, sorted.
+    pub lines: Vec<u32>,
+}
+
+impl From<Vec<coverage::FileCoverage>> for CoverageReport {
+    fn from(files: Vec<coverage::FileCoverage>) -> Self {
+        let mut files: Vec<FileCoverage> = files
+            .into_iter()
+            .map(|file| FileCoverage {
+                path: file.path,
+                lines: file.lines,
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { files }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_lines_by_path() {
+        let report = CoverageReport::from(vec![
+            coverage::FileCoverage {
+                path: "/src/main.py".to_string(),
+                lines: vec![1, 2, 5],
+            },
+            coverage::FileCoverage {
+                path: "<string>".to_string(),
+                lines: vec![3],
+            },
+        ]);
+        assert_eq!(report.files[0].path, "/src/main.py");
+        assert_eq!(report.lines("<string>"), Some(&[3][..]));
+        assert_eq!(report.lines("/src/other.py"), None);
+    }
+}
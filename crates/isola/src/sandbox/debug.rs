@@ -0,0 +1,149 @@
+use std::{fmt, time::Duration};
+
+use bytes::Bytes;
+
+/// What a [`TraceEntry`] recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TraceKind {
+    /// A hostcall handled by the [`Host`](crate::host::Host) or a plugin.
+    Hostcall,
+    /// An outgoing HTTP request.
+    Http,
+}
+
+/// How a traced hostcall or HTTP request ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraceOutcome {
+    /// The host returned a value or an HTTP response.
+    Ok,
+    /// The request was rejected by a capability or interface restriction
+    /// before reaching the host.
+    Denied,
+    /// The host or the transport reported an error.
+    Failed(String),
+}
+
+/// One hostcall or HTTP request recorded by
+/// [`SandboxOptions::trace_hostcalls`](crate::sandbox::SandboxOptions::trace_hostcalls).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TraceEntry {
+    /// Whether this was a hostcall or an HTTP request.
+    pub kind: TraceKind,
+    /// Call id of the guest operation that made the request.
+    pub call_id: Option<u64>,
+    /// Hostcall type, or HTTP method and URI.
+    pub target: String,
+    /// Leading bytes of the hostcall payload or HTTP request body.
+    pub payload: Bytes,
+    /// Full length of the payload before truncation.
+    pub payload_len: usize,
+    /// HTTP response status, for HTTP requests that got a response.
+    pub status: Option<u16>,
+    /// Time from dispatch until the host responded.
+    pub duration: Duration,
+    /// How the request ended.
+    pub outcome: TraceOutcome,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.call_id {
+            Some(call_id) => write!(f, "call {call_id} ")?,
+            None => f.write_str("call - ")?,
+        }
+        let kind = match self.kind {
+            TraceKind::Hostcall => "hostcall",
+            TraceKind::Http => "http",
+        };
+        write!(f, "{kind} {} ({:?})", self.target, self.duration)?;
+        match &self.outcome {
+            TraceOutcome::Ok => f.write_str(" ok")?,
+            TraceOutcome::Denied => f.write_str(" denied")?,
+            TraceOutcome::Failed(message) => write!(f, " failed: {message}")?,
+        }
+        if let Some(status) = self.status {
+            write!(f, " status={status}")?;
+        }
+        write!(
+            f,
+            " payload[{}]=\"{}",
+            self.payload_len,
+            self.payload.escape_ascii()
+        )?;
+        if self.payload.len() < self.payload_len {
+            f.write_str("...")?;
+        }
+        f.write_str("\"")
+    }
+}
+
+/// Recent hostcalls and HTTP requests of a sandbox, for post-mortem
+/// debugging.
+///
+/// Returned by [`Sandbox::debug_dump`](crate::sandbox::Sandbox::debug_dump).
+/// The [`Display`](fmt::Display) form prints one entry per line, oldest
+/// first, and is meant for logs and incident reports.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct DebugDump {
+    /// Call id of the most recent `eval_*` or `call*` operation.
+    pub last_call_id: Option<u64>,
+    /// Recorded entries, oldest first. Empty when tracing is disabled.
+    pub entries: Vec<TraceEntry>,
+}
+
+impl fmt::Display for DebugDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.last_call_id {
+            Some(call_id) => writeln!(f, "last call: {call_id}")?,
+            None => writeln!(f, "last call: -")?,
+        }
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_entries_one_per_line() {
+        let dump = DebugDump {
+            last_call_id: Some(7),
+            entries: vec![
+                TraceEntry {
+                    kind: TraceKind::Hostcall,
+                    call_id: Some(7),
+                    target: "kv.get".to_string(),
+                    payload: Bytes::from_static(b"ab\x01"),
+                    payload_len: 5,
+                    status: None,
+                    duration: Duration::from_millis(2),
+                    outcome: TraceOutcome::Failed("missing".to_string()),
+                },
+                TraceEntry {
+                    kind: TraceKind::Http,
+                    call_id: None,
+                    target: "GET http://a.example/".to_string(),
+                    payload: Bytes::new(),
+                    payload_len: 0,
+                    status: Some(404),
+                    duration: Duration::ZERO,
+                    outcome: TraceOutcome::Ok,
+                },
+            ],
+        };
+        assert_eq!(
+            dump.to_string(),
+            "last call: 7\n\
+             call 7 hostcall kv.get (2ms) failed: missing payload[5]=\"ab\\x01...\"\n\
+             call - http GET http://a.example/ (0ns) ok status=404 payload[0]=\"\"\n"
+        );
+    }
+}
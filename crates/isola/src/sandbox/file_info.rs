@@ -0,0 +1,71 @@
+use std::{future::Future, time::SystemTime};
+
+use super::{Result, Sandbox};
+use crate::{host::Host, internal::guest_files};
+
+/// Kind of entry described by a [`GuestFileInfo`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GuestFileKind {
+    /// Regular file.
+    File,
+    /// Directory.
+    Directory,
+    /// Symbolic link, which is described itself rather than followed.
+    Symlink,
+    /// Any other kind of entry, such as a socket or named pipe.
+    Other,
+}
+
+/// Metadata of an entry in a sandbox's mounts, returned by
+/// [`Sandbox::list_guest_dir`] and [`Sandbox::stat_guest_path`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GuestFileInfo {
+    /// Guest path of the entry.
+    pub path: String,
+    /// What kind of entry this is.
+    pub kind: GuestFileKind,
+    /// Size in bytes as reported by the host filesystem.
+    pub size: u64,
+    /// Last modification time, if the host filesystem records one.
+    pub modified: Option<SystemTime>,
+}
+
+impl<H: Host> Sandbox<H> {
+    /// List the entries directly inside a guest directory, such as the plots
+    /// a script wrote, without reading them or running guest code.
+    ///
+    /// Entries of every overlay layer are listed once, sorted by guest path.
+    /// Use [`list_guest_files`](Self::list_guest_files) for the regular files
+    /// at any depth.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no mount contains `guest_dir`, the path contains
+    /// `..` or resolves outside its mount, it is not a directory, or the
+    /// directory cannot be read.
+    pub fn list_guest_dir(
+        &self,
+        guest_dir: &str,
+    ) -> impl Future<Output = Result<Vec<GuestFileInfo>>> + Send + 'static {
+        let resolved = guest_files::resolve(&self.directory_mappings(), guest_dir);
+        async move { Ok(guest_files::list_entries(&resolved?).await?) }
+    }
+
+    /// Describe a file or directory the guest can see.
+    ///
+    /// A symbolic link is described itself rather than followed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no mount contains `guest_path`, the path contains
+    /// `..` or resolves outside its mount, or the entry does not exist.
+    pub fn stat_guest_path(
+        &self,
+        guest_path: &str,
+    ) -> impl Future<Output = Result<GuestFileInfo>> + Send + 'static {
+        let resolved = guest_files::resolve(&self.directory_mappings(), guest_path);
+        async move { Ok(guest_files::stat(&resolved?).await?) }
+    }
+}
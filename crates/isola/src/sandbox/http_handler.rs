@@ -0,0 +1,365 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http_body::Frame;
+use minicbor::{Decoder, Encoder, data::Type};
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+
+use super::{Arg, Error, Result, Sandbox};
+use crate::{
+    host::{
+        BoxError, EventMeta, Host, HttpBodyStream, HttpRequest, HttpResponse, OutputSink,
+        OutputTarget,
+    },
+    value::Value,
+};
+
+type BodyChunk = core::result::Result<Frame<Bytes>, BoxError>;
+
+/// Number of body chunks buffered before the guest waits for the reader.
+const BODY_BUFFER: usize = 16;
+
+impl<H: Host> Sandbox<H> {
+    /// Deliver an incoming HTTP request to the guest function `function` and
+    /// stream its response back.
+    ///
+    /// The function receives one positional argument, a map with `method`,
+    /// `uri`, `headers` (a list of `[name, value]` pairs), and `body` (bytes,
+    /// or `None` when empty). It responds in one of two ways:
+    ///
+    /// - Return a map with `status`, optional `headers` (a map or a list of
+    ///   pairs), and optional `body` (bytes or text).
+    /// - Yield a map with `status` and optional `headers`, then yield each
+    ///   body chunk as bytes or text. The chunks are streamed as they are
+    ///   produced.
+    ///
+    /// `respond` is called once with the response as soon as its status and
+    /// headers are known, while the body may still be streaming; this method
+    /// returns once the guest function has finished. If the guest fails after
+    /// responding, the body stream ends with an error. A guest streaming a
+    /// body waits once a few chunks are buffered until the reader catches up.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the function is missing, guest execution fails,
+    /// the guest produces no response or a malformed one, or the WebAssembly
+    /// runtime traps.
+    pub async fn handle_http(
+        &mut self,
+        function: &str,
+        request: HttpRequest,
+        respond: impl FnOnce(HttpResponse) + Send + 'static,
+    ) -> Result<()> {
+        let sink = Arc::new(ResponseSink {
+            state: Mutex::new(ResponseState::Pending(Box::new(respond))),
+        });
+        let target = OutputTarget::asynchronous(Arc::clone(&sink));
+        let request = Arg::Positional(encode_request(&request)?);
+        let result = self.call_with_sink(function, [request], target).await;
+
+        let state = std::mem::replace(&mut *sink.state.lock(), ResponseState::Done);
+        match (result, state) {
+            (Ok(()), ResponseState::Pending(_)) => Err(Error::Other(
+                std::io::Error::other("guest function did not produce an HTTP response").into(),
+            )),
+            (Err(e), ResponseState::Streaming(body)) => {
+                let _ = body.send(Err(e.to_string().into())).await;
+                Err(e)
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+enum ResponseState {
+    Pending(Box<dyn FnOnce(HttpResponse) + Send>),
+    Streaming(mpsc::Sender<BodyChunk>),
+    Done,
+}
+
+/// Turns the guest's output into an HTTP response and its body stream.
+struct ResponseSink {
+    state: Mutex<ResponseState>,
+}
+
+impl ResponseSink {
+    async fn on_value(&self, value: &Value, complete: bool) -> core::result::Result<(), BoxError> {
+        let streaming = match &*self.state.lock() {
+            ResponseState::Streaming(sender) => Some(sender.clone()),
+            ResponseState::Pending(_) => None,
+            ResponseState::Done => return Ok(()),
+        };
+        if let Some(sender) = streaming {
+            if complete {
+                *self.state.lock() = ResponseState::Done;
+            } else {
+                let chunk = decode_chunk(value)?;
+                // The receiver is gone once the caller drops the response.
+                let _ = sender.send(Ok(Frame::data(chunk))).await;
+            }
+            return Ok(());
+        }
+
+        let (response, body) = decode_response(value, complete)?;
+        let (sender, receiver) = mpsc::channel(BODY_BUFFER);
+        if let Some(body) = body {
+            let _ = sender.send(Ok(Frame::data(body))).await;
+        }
+        let ResponseState::Pending(respond) =
+            std::mem::replace(&mut *self.state.lock(), ResponseState::Done)
+        else {
+            return Ok(());
+        };
+        let body: HttpBodyStream = Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver));
+        let response = response.body(body).map_err(|e| malformed(&e.to_string()))?;
+        respond(response);
+        if !complete {
+            *self.state.lock() = ResponseState::Streaming(sender);
+        }
+        Ok(())
+    }
+}
+
+impl OutputSink for ResponseSink {
+    async fn on_item(&self, _meta: EventMeta, value: Value) -> core::result::Result<(), BoxError> {
+        self.on_value(&value, false).await
+    }
+
+    async fn on_complete(
+        &self,
+        _meta: EventMeta,
+        value: Option<Value>,
+    ) -> core::result::Result<(), BoxError> {
+        if let Some(value) = value {
+            return self.on_value(&value, true).await;
+        }
+        let mut state = self.state.lock();
+        if matches!(*state, ResponseState::Streaming(_)) {
+            *state = ResponseState::Done;
+        }
+        drop(state);
+        Ok(())
+    }
+}
+
+fn encode_request(request: &HttpRequest) -> Result<Value> {
+    let mut encoder = Encoder::new(Vec::new());
+    (|| -> core::result::Result<(), minicbor::encode::Error<core::convert::Infallible>> {
+        encoder
+            .map(4)?
+            .str("method")?
+            .str(request.method().as_str())?
+            .str("uri")?
+            .str(&request.uri().to_string())?
+            .str("headers")?
+            .array(request.headers().len() as u64)?;
+        for (name, value) in request.headers() {
+            encoder
+                .array(2)?
+                .str(name.as_str())?
+                .str(&String::from_utf8_lossy(value.as_bytes()))?;
+        }
+        encoder.str("body")?;
+        match request.body() {
+            Some(body) => encoder.bytes(body)?,
+            None => encoder.null()?,
+        };
+        Ok(())
+    })()
+    .map_err(|e| Error::Other(e.into()))?;
+    Ok(Value::from_cbor(encoder.into_writer()))
+}
+
+fn malformed(what: &str) -> BoxError {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("malformed HTTP response from guest: {what}"),
+    )
+    .into()
+}
+
+/// Decode a response head, and its body when `with_body` is set.
+fn decode_response(
+    value: &Value,
+    with_body: bool,
+) -> core::result::Result<(http::response::Builder, Option<Bytes>), BoxError> {
+    let mut decoder = Decoder::new(value.as_cbor());
+    let len = decoder
+        .map()
+        .map_err(|_| malformed("expected a map with a status"))?
+        .ok_or_else(|| malformed("indefinite-length map"))?;
+    let mut response = http::Response::builder();
+    let mut status = None;
+    let mut body = None;
+    for _ in 0..len {
+        match decoder.str().map_err(|_| malformed("expected text keys"))? {
+            "status" => {
+                status = Some(
+                    decoder
+                        .u16()
+                        .map_err(|_| malformed("status must be an integer"))?,
+                );
+            }
+            "headers" => {
+                for (name, value) in decode_headers(&mut decoder)? {
+                    response = response.header(name, value);
+                }
+            }
+            "body" if with_body => {
+                body = decode_body(&mut decoder)?;
+            }
+            key => return Err(malformed(&format!("unexpected key '{key}'"))),
+        }
+    }
+    let status = status.ok_or_else(|| malformed("missing status"))?;
+    Ok((response.status(status), body))
+}
+
+fn decode_headers(
+    decoder: &mut Decoder<'_>,
+) -> core::result::Result<Vec<(String, String)>, BoxError> {
+    let bad = || malformed("headers must be a map or a list of [name, value] pairs");
+    let mut headers = Vec::new();
+    match decoder.datatype().map_err(|_| bad())? {
+        Type::Map => {
+            let len = decoder.map().map_err(|_| bad())?.ok_or_else(bad)?;
+            for _ in 0..len {
+                let name = decoder.str().map_err(|_| bad())?.to_string();
+                let value = decoder.str().map_err(|_| bad())?.to_string();
+                headers.push((name, value));
+            }
+        }
+        Type::Array => {
+            let len = decoder.array().map_err(|_| bad())?.ok_or_else(bad)?;
+            for _ in 0..len {
+                if decoder.array().map_err(|_| bad())? != Some(2) {
+                    return Err(bad());
+                }
+                let name = decoder.str().map_err(|_| bad())?.to_string();
+                let value = decoder.str().map_err(|_| bad())?.to_string();
+                headers.push((name, value));
+            }
+        }
+        _ => return Err(bad()),
+    }
+    Ok(headers)
+}
+
+fn decode_body(decoder: &mut Decoder<'_>) -> core::result::Result<Option<Bytes>, BoxError> {
+    let bad = || malformed("body must be bytes or text");
+    match decoder.datatype().map_err(|_| bad())? {
+        Type::Null | Type::Undefined => {
+            decoder.skip().map_err(|_| bad())?;
+            Ok(None)
+        }
+        Type::Bytes => Ok(Some(Bytes::copy_from_slice(
+            decoder.bytes().map_err(|_| bad())?,
+        ))),
+        Type::String => Ok(Some(Bytes::copy_from_slice(
+            decoder.str().map_err(|_| bad())?.as_bytes(),
+        ))),
+        _ => Err(bad()),
+    }
+}
+
+fn decode_chunk(value: &Value) -> core::result::Result<Bytes, BoxError> {
+    decode_body(&mut Decoder::new(value.as_cbor()))?.ok_or_else(|| malformed("empty body chunk"))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn cbor(f: impl FnOnce(&mut Encoder<Vec<u8>>)) -> Value {
+        let mut encoder = Encoder::new(Vec::new());
+        f(&mut encoder);
+        Value::from_cbor(encoder.into_writer())
+    }
+
+    #[test]
+    fn encodes_requests_as_maps() {
+        let request = http::Request::builder()
+            .method("POST")
+            .uri("http://a.example/hook")
+            .header("x-id", "7")
+            .body(Some(Bytes::from_static(b"hi")))
+            .expect("request");
+        let value = encode_request(&request).expect("encoded");
+        assert_eq!(
+            value.to_json().expect("json"),
+            r#"{"method":"POST","uri":"http://a.example/hook","headers":[["x-id","7"]],"body":"aGk="}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_yielded_chunks_after_the_head() {
+        let responded = Arc::new(Mutex::new(None));
+        let sink = Arc::new(ResponseSink {
+            state: Mutex::new(ResponseState::Pending(Box::new({
+                let responded = Arc::clone(&responded);
+                move |response| *responded.lock() = Some(response)
+            }))),
+        });
+        let target = OutputTarget::asynchronous(Arc::clone(&sink));
+        let head = cbor(|e| {
+            e.map(2)
+                .and_then(|e| e.str("status")?.u16(201)?.str("headers")?.map(1))
+                .and_then(|e| e.str("content-type")?.str("text/plain"))
+                .map(|_| ())
+                .expect("encode");
+        });
+        target.on_item(head).await.expect("head");
+        for chunk in ["a", "b"] {
+            let chunk = cbor(|e| {
+                e.str(chunk).expect("encode");
+            });
+            target.on_item(chunk).await.expect("chunk");
+        }
+        target
+            .on_complete(None, crate::host::ExecStats::default())
+            .await
+            .expect("complete");
+        assert!(matches!(*sink.state.lock(), ResponseState::Done));
+
+        let response = responded.lock().take().expect("responded");
+        assert_eq!(response.status(), 201);
+        assert_eq!(response.headers()["content-type"], "text/plain");
+        let body: Vec<_> = response
+            .into_body()
+            .map(|frame| frame.expect("frame").into_data().expect("data"))
+            .collect()
+            .await;
+        assert_eq!(body, [Bytes::from_static(b"a"), Bytes::from_static(b"b")]);
+    }
+
+    #[test]
+    fn returned_responses_carry_their_body_and_bad_shapes_fail() {
+        let (response, body) = decode_response(
+            &cbor(|e| {
+                e.map(2)
+                    .and_then(|e| e.str("status")?.u16(200)?.str("body")?.bytes(b"ok"))
+                    .map(|_| ())
+                    .expect("encode");
+            }),
+            true,
+        )
+        .expect("decoded");
+        assert_eq!(
+            response.body(()).expect("response").status(),
+            http::StatusCode::OK
+        );
+        assert_eq!(body.as_deref(), Some(&b"ok"[..]));
+
+        let missing_status = cbor(|e| {
+            e.map(0).expect("encode");
+        });
+        assert!(decode_response(&missing_status, true).is_err());
+        let not_a_map = cbor(|e| {
+            e.str("nope").expect("encode");
+        });
+        assert!(decode_response(&not_a_map, true).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use tokio::sync::Notify;
+use wasmtime::Engine;
+
+/// Cancellation state shared by a sandbox and its [`InterruptHandle`]s.
+#[derive(Default)]
+pub struct InterruptState {
+    running: AtomicBool,
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl InterruptState {
+    /// Mark an operation as started, forgetting interrupts aimed at earlier
+    /// ones.
+    pub fn start(&self) {
+        self.requested.store(false, Ordering::SeqCst);
+        self.running.store(true, Ordering::SeqCst);
+    }
+
+    /// Mark the running operation as finished.
+    pub fn finish(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    /// Return whether the running operation was asked to stop.
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Resolve once the running operation is asked to stop.
+    pub async fn cancelled(&self) {
+        loop {
+            let mut notified = pin!(self.notify.notified());
+            notified.as_mut().enable();
+            if self.requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Handle that cancels the operation running in a [`Sandbox`] from another
+/// task or thread.
+///
+/// Obtained from [`Sandbox::interrupt_handle`]. Handles are cheap to clone
+/// and stay valid across [`Sandbox::reset`]; they do nothing once the
+/// sandbox is dropped.
+///
+/// [`Sandbox`]: super::Sandbox
+/// [`Sandbox::interrupt_handle`]: super::Sandbox::interrupt_handle
+/// [`Sandbox::reset`]: super::Sandbox::reset
+#[derive(Clone)]
+pub struct InterruptHandle {
+    state: Arc<InterruptState>,
+    engine: Engine,
+}
+
+impl std::fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterruptHandle").finish_non_exhaustive()
+    }
+}
+
+impl InterruptHandle {
+    pub(crate) const fn new(state: Arc<InterruptState>, engine: Engine) -> Self {
+        Self { state, engine }
+    }
+
+    /// Abort the `eval_*` or `call*` operation currently running in the
+    /// sandbox, which then fails with [`Error::Cancelled`].
+    ///
+    /// Guest code is stopped at once by advancing the engine's epoch, and an
+    /// operation waiting on the host is abandoned. Interrupting an idle
+    /// sandbox does nothing, and a later operation is not affected. As with
+    /// a [deadline](super::CallOptions::deadline), guest state may be left
+    /// inconsistent; [`Sandbox::reset`](super::Sandbox::reset) recovers it.
+    ///
+    /// [`Error::Cancelled`]: super::Error::Cancelled
+    pub fn interrupt(&self) {
+        if !self.state.running.load(Ordering::SeqCst) {
+            return;
+        }
+        self.state.requested.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+        // Every store on the engine reaches its deadline; the others merely
+        // yield once.
+        self.engine.increment_epoch();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn interrupts_only_reach_the_running_operation() {
+        let state = Arc::new(InterruptState::default());
+        let handle = InterruptHandle::new(Arc::clone(&state), Engine::default());
+
+        handle.interrupt();
+        state.start();
+        assert!(!state.requested(), "idle interrupts must be ignored");
+
+        let waiter = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move { state.cancelled().await }
+        });
+        tokio::task::yield_now().await;
+        handle.interrupt();
+        waiter.await.expect("waiter finished");
+        assert!(state.requested());
+
+        state.finish();
+        state.start();
+        assert!(!state.requested(), "a new operation starts uninterrupted");
+    }
+}
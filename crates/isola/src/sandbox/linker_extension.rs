@@ -0,0 +1,98 @@
+use std::any::{Any, TypeId};
+
+use wasmtime::component::Linker;
+
+use crate::{host::Host, internal::sandbox::InstanceState};
+
+type Extend<H> = Box<dyn Fn(&mut Linker<InstanceState<H>>) -> wasmtime::Result<()> + Send + Sync>;
+
+/// Extra definitions registered with
+/// [`SandboxTemplateBuilder::with_linker`](super::SandboxTemplateBuilder::with_linker)
+/// for sandboxes of one host type.
+pub struct LinkerExtension {
+    host: TypeId,
+    /// `Extend<H>` for the host type `host`.
+    extend: Box<dyn Any + Send + Sync>,
+}
+
+impl LinkerExtension {
+    pub fn new<H: Host>(
+        extend: impl Fn(&mut Linker<InstanceState<H>>) -> wasmtime::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        let extend: Extend<H> = Box::new(extend);
+        Self {
+            host: TypeId::of::<H>(),
+            extend: Box::new(extend),
+        }
+    }
+
+    /// Add the definitions of every extension registered for `H` to
+    /// `linker`, in registration order.
+    pub fn apply<H: Host>(
+        extensions: &[Self],
+        linker: &mut Linker<InstanceState<H>>,
+    ) -> wasmtime::Result<()> {
+        extensions
+            .iter()
+            .filter(|extension| extension.host == TypeId::of::<H>())
+            .filter_map(|extension| extension.extend.downcast_ref::<Extend<H>>())
+            .try_for_each(|extend| extend(linker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use wasmtime::Engine;
+
+    use super::*;
+    use crate::{
+        host::{BoxError, HttpRequest, HttpResponse},
+        value::Value,
+    };
+
+    struct TestHost;
+
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "the test host implements the asynchronous callback contract"
+    )]
+    impl Host for TestHost {
+        async fn hostcall(&self, _call_type: &str, _payload: Value) -> Result<Value, BoxError> {
+            Err(std::io::Error::other("unsupported").into())
+        }
+
+        async fn http_request(&self, _req: HttpRequest) -> Result<HttpResponse, BoxError> {
+            Err(std::io::Error::other("unsupported").into())
+        }
+    }
+
+    fn record<H: Host>(
+        calls: &Arc<Mutex<Vec<&'static str>>>,
+        name: &'static str,
+    ) -> impl Fn(&mut Linker<InstanceState<H>>) -> wasmtime::Result<()> + Send + Sync + 'static
+    {
+        let calls = Arc::clone(calls);
+        move |_| {
+            calls.lock().push(name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn apply_runs_the_host_types_extensions_in_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let extensions = [
+            LinkerExtension::new::<TestHost>(record(&calls, "first")),
+            LinkerExtension::new::<Arc<TestHost>>(record(&calls, "other host")),
+            LinkerExtension::new::<TestHost>(record(&calls, "second")),
+        ];
+        let engine = Engine::default();
+        let mut linker = Linker::<InstanceState<TestHost>>::new(&engine);
+
+        LinkerExtension::apply(&extensions, &mut linker).unwrap();
+        assert_eq!(*calls.lock(), ["first", "second"]);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Error, Result};
+
+/// Version of the interface between the host and the guest runtime that this
+/// release of Isola implements.
+///
+/// Runtime bundles record the version they were built for in
+/// [`RuntimeMetadata::abi_version`].
+pub const RUNTIME_ABI_VERSION: u32 = 2;
+
+/// Name of the custom section runtime bundles describe themselves in.
+const SECTION_NAME: &str = "isola:runtime-metadata";
+
+/// Description of the runtime bundle a [`SandboxTemplate`] was built from.
+///
+/// Read from a custom section the bundle build writes into the component,
+/// without instantiating it. See
+/// [`SandboxTemplate::metadata`](super::SandboxTemplate::metadata).
+///
+/// [`SandboxTemplate`]: super::SandboxTemplate
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct RuntimeMetadata {
+    /// Guest language of the runtime, such as `python` or `js`.
+    pub language: String,
+    /// Version of the runtime bundle.
+    pub version: String,
+    /// Host interface version the bundle was built for.
+    pub abi_version: u32,
+    /// Free-form details of the build, such as the interpreter version.
+    pub build_info: Option<String>,
+}
+
+impl RuntimeMetadata {
+    /// Return whether the bundle was built for the host interface of this
+    /// release, [`RUNTIME_ABI_VERSION`].
+    #[must_use]
+    pub const fn is_compatible(&self) -> bool {
+        self.abi_version == RUNTIME_ABI_VERSION
+    }
+}
+
+/// Read the runtime metadata of the component `wasm`, or `None` if the
+/// bundle does not carry any.
+pub fn read_runtime_metadata(wasm: &[u8]) -> Result<Option<RuntimeMetadata>> {
+    find_custom_section(wasm, SECTION_NAME)
+        .map(parse)
+        .transpose()
+}
+
+fn parse(section: &[u8]) -> Result<RuntimeMetadata> {
+    let invalid = |message: String| {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("invalid runtime metadata: {message}"),
+        ))
+    };
+    let text = std::str::from_utf8(section).map_err(|e| invalid(e.to_string()))?;
+    let (mut language, mut version, mut abi_version, mut build_info) = (None, None, None, None);
+    for line in text.lines().filter(|line| !line.is_empty()) {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("expected key=value, got {line:?}")))?;
+        match key {
+            "language" => language = Some(value.to_string()),
+            "version" => version = Some(value.to_string()),
+            "abi_version" => {
+                abi_version = Some(value.parse().map_err(|_| {
+                    invalid(format!("abi_version must be an integer, got {value:?}"))
+                })?);
+            }
+            "build_info" => build_info = Some(value.to_string()),
+            // Newer bundles may describe more.
+            _ => {}
+        }
+    }
+    Ok(RuntimeMetadata {
+        language: language.ok_or_else(|| invalid("missing language".to_string()))?,
+        version: version.ok_or_else(|| invalid("missing version".to_string()))?,
+        abi_version: abi_version.ok_or_else(|| invalid("missing abi_version".to_string()))?,
+        build_info,
+    })
+}
+
+/// Return the contents of the top-level custom section `name` of a module or
+/// component binary.
+fn find_custom_section<'a>(wasm: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    let mut rest = wasm.strip_prefix(b"\0asm")?.get(4..)?;
+    while let Some((&id, after_id)) = rest.split_first() {
+        let (size, after_size) = read_u32(after_id)?;
+        let (contents, after) = after_size.split_at_checked(usize::try_from(size).ok()?)?;
+        if id == 0 {
+            let (len, after_len) = read_u32(contents)?;
+            let (section_name, data) = after_len.split_at_checked(usize::try_from(len).ok()?)?;
+            if section_name == name.as_bytes() {
+                return Some(data);
+            }
+        }
+        rest = after;
+    }
+    None
+}
+
+/// Decode an unsigned LEB128 `u32`, returning it and the bytes after it.
+fn read_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let mut value = 0_u32;
+    for (index, &byte) in bytes.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7f).checked_shl(7 * u32::try_from(index).ok()?)?;
+        if byte & 0x80 == 0 {
+            return Some((value, &bytes[index + 1..]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(component)` followed by a custom section `name` holding `data`.
+    fn component_with_section(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut wasm = vec![0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+        let name_len = u8::try_from(name.len()).unwrap();
+        wasm.push(0);
+        wasm.push(u8::try_from(1 + name.len() + data.len()).unwrap());
+        wasm.push(name_len);
+        wasm.extend_from_slice(name.as_bytes());
+        wasm.extend_from_slice(data);
+        wasm
+    }
+
+    #[test]
+    fn reads_metadata_from_the_custom_section() {
+        let wasm = component_with_section(
+            SECTION_NAME,
+            b"language=python\nversion=0.5.0\nabi_version=2\nbuild_info=cpython 3.14\nfuture=x\n",
+        );
+        let metadata = read_runtime_metadata(&wasm).unwrap().unwrap();
+        assert_eq!(
+            metadata,
+            RuntimeMetadata {
+                language: "python".to_string(),
+                version: "0.5.0".to_string(),
+                abi_version: 2,
+                build_info: Some("cpython 3.14".to_string()),
+            }
+        );
+        assert!(metadata.is_compatible());
+
+        let other = component_with_section("name", b"\0");
+        assert_eq!(read_runtime_metadata(&other).unwrap(), None);
+        assert_eq!(read_runtime_metadata(b"not wasm").unwrap(), None);
+
+        let missing = component_with_section(SECTION_NAME, b"language=js\n");
+        let error = read_runtime_metadata(&missing).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "io error: invalid runtime metadata: missing version"
+        );
+    }
+}
@@ -17,39 +17,126 @@
 
 #[cfg(feature = "serde")]
 mod args_macro;
+mod cache_backend;
+mod call_many;
+mod call_options;
+mod call_stream;
+mod coverage;
+mod debug;
+mod file_info;
+#[cfg(feature = "serde")]
+mod http_handler;
+mod interrupt;
+mod linker_extension;
+mod metadata;
+mod mounts;
+mod namespace;
+mod origin;
+mod pool;
+mod shared;
+mod snapshot;
+mod stats;
+mod template_manager;
+mod template_source;
+mod traceback;
+mod trust;
+mod world;
 
 use std::{
     any::{Any, TypeId},
+    borrow::Cow,
     collections::HashMap,
     path::{Path, PathBuf},
     pin::Pin,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use futures::Stream;
 use parking_lot::Mutex;
+use tokio::io::{AsyncRead, AsyncWrite};
 use wasmtime::{
     Engine, Store,
-    component::{Component, InstancePre},
+    component::{Component, Instance, InstancePre, Linker, ResourceTableError},
 };
 pub use wasmtime_wasi::{DirPerms, FilePerms};
 
+#[cfg(feature = "remote-cache")]
+pub use self::cache_backend::HttpCacheBackend;
+#[cfg(feature = "signature")]
+pub use self::trust::Ed25519TrustPolicy;
+pub use self::{
+    cache_backend::CacheBackend,
+    call_options::{CallOptions, Capability, CapabilitySet, OutputLimit},
+    call_stream::CallStream,
+    coverage::{CoverageReport, FileCoverage},
+    debug::{DebugDump, TraceEntry, TraceKind, TraceOutcome},
+    file_info::{GuestFileInfo, GuestFileKind},
+    interrupt::InterruptHandle,
+    metadata::{RUNTIME_ABI_VERSION, RuntimeMetadata},
+    namespace::Namespace,
+    pool::{PooledSandbox, SandboxPool, SandboxPoolConfig, SandboxPoolStats},
+    shared::{SharedSandbox, SharedSandboxGuard},
+    snapshot::SandboxSnapshot,
+    stats::{CacheStatus, TemplateStats},
+    template_manager::TemplateManager,
+    template_source::TemplateSource,
+    traceback::{Traceback, TracebackFrame},
+    trust::TrustPolicy,
+    world::ScriptWorld,
+};
+pub(crate) use self::{
+    interrupt::InterruptState,
+    trust::verify_artifact,
+    world::{check_world, missing_export},
+};
+use self::{
+    linker_extension::LinkerExtension,
+    metadata::read_runtime_metadata,
+    mounts::validate_mounts,
+    namespace::NamespaceSlot,
+    origin::SandboxOrigin,
+    snapshot::{FreshCopy, SnapshotSource},
+    stats::{LiveSandbox, TemplateCounters},
+};
 #[cfg(feature = "serde")]
 pub use crate::args;
+#[cfg(feature = "archive")]
+use crate::internal::archive::{
+    ArchiveMount, ArchiveSource, DEFAULT_ARCHIVE_MOUNT_LIMITS, extract_into, unpack_archives,
+};
+#[cfg(feature = "pulley")]
+use crate::internal::module::configure::configure_interpreter;
+pub use crate::internal::sandbox::InstanceState as SandboxState;
 use crate::{
-    host::{BoxError, Host, OutputTarget},
+    host::{BoxError, ClockProvider, EntropySource, ExecStats, FilePolicy, Host, OutputTarget},
     internal::{
+        call_trace::CallTrace,
+        clock::SharedClock,
+        entropy::SharedEntropy,
+        file_policy::SharedFilePolicy,
+        guest_files,
+        hostcall_limits::HostcallLimits,
         module::{
             ModuleConfig as InternalModuleConfig,
-            call::CallCleanup,
+            cache::gc_cache_dir,
+            call::{CallCleanup, call_export},
             compile::load_or_compile_component,
-            configure::configure_engine,
-            epoch::{EpochTickerRegistration, global_epoch_ticker},
+            configure::{
+                configure_compile_threads, configure_engine, configure_memory_init,
+                configure_pooling, configure_stack,
+            },
+            epoch::{DEFAULT_EPOCH_TICK, EpochTickerRegistration, global_epoch_ticker},
+            precompiled,
+            prelude::{Prelude, PreludeSource, load_preludes, warmup_prelude},
         },
+        plugin::PluginTemplate,
         sandbox::{
             HostView as _, InstanceState, Sandbox as WasmSandbox, SandboxPre, ValueIterator,
             exports::{self, Argument as RawArgument, Value as WasmValue},
         },
+        scratch::create_scratch_dirs,
+        stdio::{GuestOutput, GuestStdin},
     },
     value::Value,
 };
@@ -57,14 +144,138 @@ use crate::{
 /// Result type used by `isola::sandbox` APIs.
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
+/// Stable, machine-readable classification of an [`Error`].
+///
+/// Use [`Error::kind`] instead of matching on formatted messages. New kinds may
+/// be added in future releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Guest execution was interrupted because it ran out of time.
+    Timeout,
+    /// Guest memory growth was denied by the configured memory limit.
+    MemoryLimit,
+    /// Guest execution was cancelled by the host.
+    Cancelled,
+    /// Guest code raised an exception that it did not handle.
+    GuestException,
+    /// A host callback such as a hostcall or output sink failed.
+    HostcallFailed,
+    /// A host policy rejected the requested operation.
+    PolicyDenied,
+    /// The WebAssembly engine trapped, for example on a stack overflow.
+    EngineTrap,
+    /// Filesystem or OS-level failure.
+    Io,
+    /// Any other runtime failure.
+    Internal,
+}
+
+impl ErrorKind {
+    /// Return the stable `snake_case` name of this kind.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::MemoryLimit => "memory_limit",
+            Self::Cancelled => "cancelled",
+            Self::GuestException => "guest_exception",
+            Self::HostcallFailed => "hostcall_failed",
+            Self::PolicyDenied => "policy_denied",
+            Self::EngineTrap => "engine_trap",
+            Self::Io => "io",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Context attached to a Wasmtime error to record its [`ErrorKind`] when the
+/// kind cannot be recovered from the error itself.
+#[derive(Debug)]
+pub(crate) struct Classified(pub(crate) ErrorKind);
+
+impl core::fmt::Display for Classified {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            ErrorKind::MemoryLimit => f.write_str("guest memory limit exceeded"),
+            ErrorKind::HostcallFailed => f.write_str("host callback failed"),
+            kind => write!(f, "{kind}"),
+        }
+    }
+}
+
+/// Guest memory usage recorded when growth was denied.
+///
+/// Attached to errors of kind [`ErrorKind::MemoryLimit`] and available through
+/// [`Error::memory_diagnostics`]. All sizes are in bytes. The guest's own
+/// report, such as the top allocation sites the Python runtime lists when
+/// `tracemalloc` is tracing, is part of the error's source chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MemoryDiagnostics {
+    /// Memory limit in effect for the sandbox.
+    pub limit: usize,
+    /// Largest linear memory size the sandbox reached.
+    pub peak: usize,
+    /// Previous and new size of the last growth that succeeded.
+    pub last_growth: Option<(usize, usize)>,
+    /// Size requested by the growth that was denied.
+    pub denied_request: Option<usize>,
+}
+
+impl core::fmt::Display for MemoryDiagnostics {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "peak usage {} of {} bytes", self.peak, self.limit)?;
+        if let Some((from, to)) = self.last_growth {
+            write!(f, ", last growth {from} -> {to} bytes")?;
+        }
+        if let Some(requested) = self.denied_request {
+            write!(f, ", denied growth to {requested} bytes")?;
+        }
+        Ok(())
+    }
+}
+
+/// Snapshot of a sandbox's guest memory, returned by
+/// [`Sandbox::memory_stats`].
+///
+/// Sizes are in bytes. Compare [`current`](Self::current) and
+/// [`peak`](Self::peak) against [`limit`](Self::limit) to spot sandboxes
+/// close to running out of memory before a growth is denied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MemoryStats {
+    /// Current linear memory size.
+    pub current: usize,
+    /// Largest linear memory size the sandbox reached.
+    pub peak: usize,
+    /// Memory limit in effect for the sandbox.
+    pub limit: usize,
+    /// Total elements across the guest's tables.
+    pub table_elements: usize,
+    /// Number of memory or table growths that were denied.
+    pub grow_failures: u64,
+}
+
 /// Error produced while building or executing a sandbox.
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Guest/user-code failure.
     #[error("{message}")]
     UserCode {
         /// Error text supplied by the guest language runtime.
         message: String,
+        /// Exception type, message and stack frames reported by the Python
+        /// runtime, or parsed from `message` or the call's stderr when the
+        /// runtime only reported text.
+        traceback: Option<Box<Traceback>>,
     },
 
     /// Failure from Wasmtime APIs.
@@ -78,13 +289,210 @@ pub enum Error {
     /// Other host/runtime failures.
     #[error("runtime error: {0}")]
     Other(#[from] BoxError),
+
+    /// The operation was aborted through an [`InterruptHandle`].
+    #[error("execution cancelled")]
+    Cancelled,
+
+    /// The call emitted more output than its [`CallOptions`] allow.
+    #[error("call emitted {0}")]
+    OutputLimitExceeded(OutputLimit),
+
+    /// A mounted host path is missing, is not a directory, or cannot be
+    /// read.
+    #[error("invalid mount '{}' -> '{guest}': {cause}", host.display())]
+    InvalidMount {
+        /// Host directory behind the mount, or one of its overlay layers.
+        host: PathBuf,
+        /// Guest path the directory is mounted at.
+        guest: String,
+        /// Why the directory cannot be used.
+        #[source]
+        cause: std::io::Error,
+    },
+
+    /// A template prelude raised while the template was initializing.
+    #[error("prelude {name} failed: {message}")]
+    Prelude {
+        /// Path of the failing [`prelude_file`](SandboxTemplateBuilder::prelude_file),
+        /// or `#` followed by the prelude's position for inline code.
+        name: String,
+        /// Error text supplied by the guest language runtime.
+        message: String,
+    },
+
+    /// A [`SharedSandbox`] was in use and already had this many callers
+    /// waiting for it.
+    #[error("sandbox is busy and {0} callers are already waiting")]
+    QueueFull(usize),
+
+    /// A [`SharedSandbox`] dropped its sandbox after it sat unused for its
+    /// [`idle_timeout`](SandboxOptions::idle_timeout).
+    #[error("sandbox was disposed after being idle for {0:?}")]
+    IdleDisposed(Duration),
+
+    /// The runtime component was built for a version of the `isola:script`
+    /// world this release does not implement, or lacks the optional export
+    /// an operation needs.
+    #[error(
+        "runtime component does not match this release of isola; missing: {}",
+        missing.join(", ")
+    )]
+    IncompatibleWorld {
+        /// Imports the host does not provide, runtime exports the component
+        /// lacks, and exports whose types differ from what the host calls,
+        /// such as `import isola:script/websocket`,
+        /// `export isola:script/expressions`, or
+        /// `export isola:script/runtime (incompatible type: ...)`.
+        missing: Vec<String>,
+    },
+}
+
+impl Error {
+    /// Return the machine-readable classification of this error.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::UserCode { .. } | Self::Prelude { .. } => ErrorKind::GuestException,
+            Self::Cancelled => ErrorKind::Cancelled,
+            Self::IncompatibleWorld { .. } => ErrorKind::Internal,
+            Self::OutputLimitExceeded(_) | Self::QueueFull(_) | Self::IdleDisposed(_) => {
+                ErrorKind::PolicyDenied
+            }
+            Self::Wasm(error) => wasm_error_kind(error),
+            Self::Io(error) | Self::InvalidMount { cause: error, .. } => io_error_kind(error),
+            Self::Other(error) => {
+                error
+                    .downcast_ref::<std::io::Error>()
+                    .map_or(ErrorKind::Internal, |error| match io_error_kind(error) {
+                        ErrorKind::Io => ErrorKind::Internal,
+                        kind => kind,
+                    })
+            }
+        }
+    }
+
+    /// Return memory usage diagnostics if this error was caused by the memory
+    /// limit.
+    #[must_use]
+    pub fn memory_diagnostics(&self) -> Option<&MemoryDiagnostics> {
+        match self {
+            Self::Wasm(error) => error.downcast_ref::<MemoryDiagnostics>(),
+            _ => None,
+        }
+    }
+
+    /// Return the message reported by the guest language runtime, if this
+    /// error was raised by guest code.
+    #[must_use]
+    pub fn guest_message(&self) -> Option<&str> {
+        match self {
+            Self::UserCode { message, .. } | Self::Prelude { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Return the type name of the Python exception behind this error, such
+    /// as `ValueError` or `mod.CustomError`.
+    #[must_use]
+    pub fn exception_type(&self) -> Option<&str> {
+        self.traceback()
+            .map(|traceback| traceback.exception_type.as_str())
+    }
+
+    /// Return the Python traceback of a guest exception, as reported by the
+    /// guest runtime or parsed from the error message or the stderr of the
+    /// failed call.
+    #[must_use]
+    pub fn traceback(&self) -> Option<&Traceback> {
+        match self {
+            Self::UserCode { traceback, .. } => traceback.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Convert call arguments to their guest representation, registering
+/// streaming arguments in the store's resource table.
+fn lower_args<H: Host>(
+    store: &mut Store<InstanceState<H>>,
+    args: impl IntoIterator<Item = Arg>,
+) -> Result<Vec<RawArgument>> {
+    args.into_iter()
+        .map(|arg| match arg {
+            Arg::Positional(value) => Ok(RawArgument {
+                name: None,
+                value: WasmValue::Cbor(value.into_cbor().into()),
+            }),
+            Arg::Named(name, value) => Ok(RawArgument {
+                name: Some(name),
+                value: WasmValue::Cbor(value.into_cbor().into()),
+            }),
+            Arg::PositionalStream(stream_arg) => {
+                let iter = store
+                    .data_mut()
+                    .table()
+                    .push(ValueIterator::new(stream_arg))
+                    .map_err(|e| Error::Other(e.into()))?;
+                Ok(RawArgument {
+                    name: None,
+                    value: WasmValue::CborIterator(iter),
+                })
+            }
+            Arg::NamedStream(name, stream_arg) => {
+                let iter = store
+                    .data_mut()
+                    .table()
+                    .push(ValueIterator::new(stream_arg))
+                    .map_err(|e| Error::Other(e.into()))?;
+                Ok(RawArgument {
+                    name: Some(name),
+                    value: WasmValue::CborIterator(iter),
+                })
+            }
+        })
+        .collect()
+}
+
+fn wasm_error_kind(error: &wasmtime::Error) -> ErrorKind {
+    if let Some(Classified(kind)) = error.downcast_ref::<Classified>() {
+        return *kind;
+    }
+    if let Some(trap) = error.downcast_ref::<wasmtime::Trap>() {
+        return match trap {
+            wasmtime::Trap::Interrupt | wasmtime::Trap::OutOfFuel => ErrorKind::Timeout,
+            _ => ErrorKind::EngineTrap,
+        };
+    }
+    if matches!(
+        error.downcast_ref::<ResourceTableError>(),
+        Some(ResourceTableError::Full)
+    ) {
+        return ErrorKind::PolicyDenied;
+    }
+    error
+        .downcast_ref::<std::io::Error>()
+        .map_or(ErrorKind::Internal, io_error_kind)
+}
+
+fn io_error_kind(error: &std::io::Error) -> ErrorKind {
+    match error.kind() {
+        std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::QuotaExceeded => {
+            ErrorKind::PolicyDenied
+        }
+        _ => ErrorKind::Io,
+    }
 }
 
 impl From<exports::Error> for Error {
     fn from(value: exports::Error) -> Self {
         let exports::Error { code, message } = value;
         match code {
-            exports::ErrorCode::Aborted => Self::UserCode { message },
+            exports::ErrorCode::Aborted => Self::UserCode {
+                traceback: Traceback::parse(&message).map(Box::new),
+                message,
+            },
             exports::ErrorCode::Internal => {
                 Self::Other(std::io::Error::other(format!("[{code:?}] {message}")).into())
             }
@@ -98,6 +506,20 @@ pub(crate) struct DirectoryMapping {
     pub(crate) guest: String,
     pub(crate) dir_perms: DirPerms,
     pub(crate) file_perms: FilePerms,
+    pub(crate) quota: Option<FsQuota>,
+    /// Lower overlay layers below `host`, highest first.
+    pub(crate) lower: Vec<PathBuf>,
+    /// Glob patterns selecting the files the guest sees; empty shows all.
+    pub(crate) include: Vec<String>,
+    /// Archive whose unpacked contents back this mount; `host` is filled in
+    /// once it has been unpacked.
+    #[cfg(feature = "archive")]
+    pub(crate) archive: Option<Arc<ArchiveMount>>,
+    /// Whether `host` is a private directory created empty for each sandbox
+    /// instance.
+    pub(crate) scratch: bool,
+    /// Keeps the scratch directory behind `host` alive.
+    pub(crate) scratch_dir: Option<Arc<tempfile::TempDir>>,
 }
 
 impl DirectoryMapping {
@@ -107,7 +529,35 @@ impl DirectoryMapping {
             guest: guest.into(),
             dir_perms: DirPerms::READ,
             file_perms: FilePerms::READ,
+            quota: None,
+            lower: Vec::new(),
+            include: Vec::new(),
+            #[cfg(feature = "archive")]
+            archive: None,
+            scratch: false,
+            scratch_dir: None,
+        }
+    }
+
+    #[cfg(feature = "archive")]
+    pub fn with_archive(mut self, source: ArchiveSource) -> Self {
+        self.archive = Some(Arc::new(ArchiveMount::new(source)));
+        self
+    }
+
+    /// Identify the mount's contents for compile cache keys.
+    ///
+    /// Archive mounts are unpacked into fresh temporary directories, so they
+    /// are keyed by the archive digest rather than their host path.
+    pub(crate) fn source_key(&self) -> Cow<'_, str> {
+        #[cfg(feature = "archive")]
+        if let Some(digest) = self.archive.as_ref().and_then(|a| a.digest()) {
+            return Cow::Owned(format!("archive:{digest}"));
         }
+        if self.scratch {
+            return Cow::Borrowed("scratch");
+        }
+        self.host.to_string_lossy()
     }
 
     pub const fn with_permissions(mut self, dir_perms: DirPerms, file_perms: FilePerms) -> Self {
@@ -115,6 +565,136 @@ impl DirectoryMapping {
         self.file_perms = file_perms;
         self
     }
+
+    pub const fn with_quota(mut self, quota: Option<FsQuota>) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    fn with_include(mut self, include: Vec<String>) -> Self {
+        self.include = include;
+        self
+    }
+
+    fn overlay(overlay: OverlayMount, guest: &str) -> Self {
+        let mut layers = overlay.layers;
+        layers.reverse();
+        if overlay.capture_writes {
+            let mut mapping = Self::new(PathBuf::new(), guest)
+                .with_permissions(DirPerms::all(), FilePerms::all())
+                .with_lower(layers);
+            mapping.scratch = true;
+            mapping
+        } else if let Some(top) = overlay.writable {
+            Self::new(top, guest)
+                .with_permissions(DirPerms::all(), FilePerms::all())
+                .with_lower(layers)
+        } else {
+            let top = layers.remove(0);
+            Self::new(top, guest).with_lower(layers)
+        }
+    }
+
+    fn with_lower(mut self, lower: Vec<PathBuf>) -> Self {
+        self.lower = lower;
+        self
+    }
+}
+
+/// Host directories layered onto one guest path.
+///
+/// Layers are listed from lowest to highest. A file in a higher layer shadows
+/// the file at the same path in every layer below it, and directories present
+/// in several layers show the union of their entries. All layers are exposed
+/// read-only unless a [`writable`](Self::writable) top layer is set.
+///
+/// With a writable layer, new files land there, and a file from a lower layer
+/// is copied up into it the first time the guest opens it for writing.
+/// Directories are created in the writable layer as the guest opens them.
+/// Entries that only exist in a lower layer cannot be removed or renamed.
+#[derive(Clone, Debug)]
+pub struct OverlayMount {
+    layers: Vec<PathBuf>,
+    writable: Option<PathBuf>,
+    capture_writes: bool,
+}
+
+impl OverlayMount {
+    /// Start an overlay with `base` as its lowest layer.
+    #[must_use]
+    pub fn new(base: impl AsRef<Path>) -> Self {
+        Self {
+            layers: vec![base.as_ref().to_path_buf()],
+            writable: None,
+            capture_writes: false,
+        }
+    }
+
+    /// Add a read-only layer above the existing ones.
+    #[must_use]
+    pub fn layer(mut self, dir: impl AsRef<Path>) -> Self {
+        self.layers.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set a writable layer above every read-only layer to receive guest
+    /// writes.
+    #[must_use]
+    pub fn writable(mut self, dir: impl AsRef<Path>) -> Self {
+        self.writable = Some(dir.as_ref().to_path_buf());
+        self.capture_writes = false;
+        self
+    }
+
+    /// Capture guest writes in a private writable layer instead of a host
+    /// directory the caller provides.
+    ///
+    /// Every sandbox instance, including one recreated by [`Sandbox::reset`],
+    /// gets its own empty layer, so the guest sees a writable copy-on-write
+    /// view of the read-only layers without any host directory being
+    /// modified. The layer is a temporary directory removed with the
+    /// instance; [`Sandbox::captured_writes`] returns its contents.
+    /// Replaces any [`writable`](Self::writable) layer.
+    #[must_use]
+    pub fn capture_writes(mut self) -> Self {
+        self.writable = None;
+        self.capture_writes = true;
+        self
+    }
+}
+
+/// Space limits for a writable mount.
+///
+/// Quotas count what the sandbox adds to the mount: bytes written past the end
+/// of a file and new files, directories, and links. Truncating or removing
+/// entries releases the space again. Content already present in the host
+/// directory is not counted. Guest writes that would exceed a limit fail with
+/// `ENOSPC`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FsQuota {
+    pub(crate) max_bytes: Option<u64>,
+    pub(crate) max_inodes: Option<u64>,
+}
+
+impl FsQuota {
+    /// Set the maximum number of bytes the sandbox may add to the mount.
+    ///
+    /// `None` leaves the byte count unlimited.
+    #[must_use]
+    pub const fn max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Set the maximum number of files, directories, and links the sandbox may
+    /// create in the mount.
+    ///
+    /// `None` leaves the entry count unlimited.
+    #[must_use]
+    pub const fn max_inodes(mut self, max_inodes: Option<u64>) -> Self {
+        self.max_inodes = max_inodes;
+        self
+    }
 }
 
 /// Function argument passed to guest `call-func`.
@@ -154,116 +734,964 @@ impl core::fmt::Debug for Arg {
     }
 }
 
-/// Builder for compiling a reusable [`SandboxTemplate`].
-///
-/// `SandboxTemplateBuilder` configures template-level defaults shared by every
-/// sandbox instantiated from the resulting [`SandboxTemplate`], including base
-/// mount/env settings.
-#[derive(Default)]
-pub struct SandboxTemplateBuilder {
-    pub(crate) cache: Option<PathBuf>,
-    pub(crate) base_options: SandboxOptions,
-    pub(crate) prelude: Option<String>,
+#[cfg(feature = "serde")]
+impl Arg {
+    /// Expand a struct or map into one [`Arg::Named`] per entry.
+    ///
+    /// `value` must serialize to a map with string keys, such as a struct
+    /// with named fields or a `HashMap<String, _>`. Entries become keyword
+    /// arguments in serialization order; fields skipped by serde are left
+    /// out, so the guest function's defaults apply to them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`value::Error`](crate::value::Error) if `value` cannot be
+    /// serialized or does not serialize to a map with string keys.
+    pub fn kwargs_from<T: serde::Serialize>(value: &T) -> Result<Vec<Self>, crate::value::Error> {
+        Ok(Value::from_serde(value)?
+            .map_entries()?
+            .into_iter()
+            .map(|(name, value)| Self::Named(name, value))
+            .collect())
+    }
 }
 
-/// Compiled sandbox template that can instantiate multiple sandboxes.
+/// WASI interface family that can be withheld from sandboxes with
+/// [`SandboxTemplateBuilder::disable_wasi`].
 ///
-/// A `SandboxTemplate` is an immutable, reusable compiled artifact.
-/// Instantiate it to create one or more independent [`Sandbox`] values with
-/// isolated runtime state.
-pub struct SandboxTemplate {
-    pub(crate) base_options: SandboxOptions,
-    pub(crate) engine: Engine,
-    pub(crate) component: Component,
-    pub(crate) ticker: Arc<EpochTickerRegistration>,
-    pre_instances: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+/// The guest runtime still imports every interface, so disabled interfaces
+/// remain linked but refuse to do anything useful.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WasiInterface {
+    /// `wasi:filesystem`: no directories are preopened, so every path lookup
+    /// fails. Instantiation fails if mounts are configured.
+    Filesystem,
+    /// `wasi:clocks`: wall and monotonic clocks are frozen at zero.
+    /// Instantiation fails if [`SandboxOptions::clock`] is set.
+    Clocks,
+    /// `wasi:cli` standard input, output and error: stdin is empty and writes
+    /// fail with a closed-stream error instead of being forwarded as logs.
+    /// Instantiation fails if [`SandboxOptions::stdin`],
+    /// [`SandboxOptions::stdout_writer`] or [`SandboxOptions::stderr_writer`]
+    /// is set.
+    Stdio,
+    /// `wasi:http` outgoing requests: every request is denied before reaching
+    /// [`Host::http_request`](crate::host::Host::http_request).
+    Http,
 }
 
-/// Live guest instance with mutable execution state.
-///
-/// A `Sandbox` belongs to a single instantiation of a [`SandboxTemplate`]. It
-/// carries the guest state for eval/call operations and is isolated from other
-/// sandboxes created from the same template.
-pub struct Sandbox<H: Host> {
-    pub(crate) store: Store<InstanceState<H>>,
-    pub(crate) bindings: WasmSandbox,
-    /// Keeps the epoch ticker alive for the lifetime of this sandbox.
-    pub(crate) _ticker: Arc<EpochTickerRegistration>,
+/// Code generator used to compile a [`SandboxTemplate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CompilerStrategy {
+    /// Wasmtime's optimizing compiler. Produces the fastest guest code.
+    #[default]
+    Cranelift,
+    /// Wasmtime's single-pass baseline compiler. Compiles much faster than
+    /// Cranelift but produces slower code, and does not support
+    /// [`EngineConfig::debug_info`].
+    ///
+    /// Requires the host to enable Wasmtime's `winch` cargo feature; the
+    /// build fails otherwise.
+    Winch,
 }
 
-/// Per-instantiation policy overrides for a [`Sandbox`].
+/// Wasmtime compiler settings for a [`SandboxTemplate`].
 ///
-/// Default options inherit every setting from the [`SandboxTemplate`]. Values
-/// set here are merged with the template defaults during
-/// [`SandboxTemplate::instantiate`].
-#[derive(Clone, Debug, Default)]
-pub struct SandboxOptions {
-    pub(crate) max_memory: Option<usize>,
-    pub(crate) directory_mappings: Vec<DirectoryMapping>,
-    pub(crate) env: Vec<(String, String)>,
+/// The defaults suit long-lived servers: optimized code, parallel
+/// compilation, and no debugging metadata. Every setting shapes the compiled
+/// artifact, so templates built with different settings are cached
+/// separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "each flag is an independent compiler option"
+)]
+pub struct EngineConfig {
+    pub(crate) strategy: CompilerStrategy,
+    pub(crate) simd: bool,
+    pub(crate) parallel_compilation: bool,
+    pub(crate) native_unwind_info: bool,
+    pub(crate) debug_info: bool,
 }
 
-impl SandboxOptions {
-    /// Override the memory hard limit for this sandbox.
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            strategy: CompilerStrategy::Cranelift,
+            simd: true,
+            parallel_compilation: true,
+            native_unwind_info: cfg!(target_os = "windows"),
+            debug_info: false,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Set the code generator.
+    ///
+    /// Defaults to [`CompilerStrategy::Cranelift`].
     #[must_use]
-    pub const fn max_memory(mut self, max_memory: usize) -> Self {
-        self.max_memory = Some(max_memory);
+    pub const fn strategy(mut self, strategy: CompilerStrategy) -> Self {
+        self.strategy = strategy;
         self
     }
 
-    /// Mount a host directory into this sandbox instance.
+    /// Enable the WebAssembly SIMD and relaxed SIMD proposals.
     ///
-    /// If a guest path duplicates a module-level mount, this mount replaces it
-    /// for that instance.
+    /// Disable it to run on hosts without vector support; runtime components
+    /// built with SIMD then fail to compile. Defaults to `true`.
     #[must_use]
-    pub fn mount(
-        mut self,
-        host_path: impl AsRef<Path>,
-        guest_path: impl AsRef<str>,
-        dir_perms: DirPerms,
-        file_perms: FilePerms,
-    ) -> Self {
-        self.directory_mappings.push(
-            DirectoryMapping::new(host_path.as_ref(), guest_path.as_ref())
-                .with_permissions(dir_perms, file_perms),
-        );
+    pub const fn simd(mut self, enabled: bool) -> Self {
+        self.simd = enabled;
         self
     }
 
-    /// Add an environment variable for this sandbox instance.
+    /// Compile functions on multiple threads.
     ///
-    /// If the same key is set multiple times, the last value wins.
+    /// Disabling it compiles on a single thread, like
+    /// [`SandboxTemplateBuilder::compile_threads`] with `Some(1)`, and takes
+    /// precedence over any thread count set there. Defaults to `true`.
     #[must_use]
-    pub fn env(mut self, k: impl AsRef<str>, v: impl AsRef<str>) -> Self {
-        self.env
-            .push((k.as_ref().to_string(), v.as_ref().to_string()));
+    pub const fn parallel_compilation(mut self, enabled: bool) -> Self {
+        self.parallel_compilation = enabled;
         self
     }
 
-    /// Merge `overrides` into this options value and return the merged result.
+    /// Register native unwind information for guest code.
     ///
-    /// Merge behavior:
-    /// - `max_memory`: override wins when set.
-    /// - mounts: override entries replace on guest-path collision.
-    /// - `env`: override values replace by matching key.
+    /// Native profilers and debuggers need it to walk through guest frames.
+    /// Defaults to `false`, except on Windows, whose ABI requires it; the
+    /// build fails if it is disabled there.
     #[must_use]
-    pub fn merged_with(&self, overrides: &Self) -> Self {
-        self.merged_with_owned(overrides.clone())
+    pub const fn native_unwind_info(mut self, enabled: bool) -> Self {
+        self.native_unwind_info = enabled;
+        self
     }
 
-    fn merged_with_owned(&self, overrides: Self) -> Self {
-        let mut merged = self.clone();
+    /// Emit DWARF debug information so native debuggers can step through
+    /// guest code.
+    ///
+    /// Increases compile time and artifact size. Defaults to `false`.
+    #[must_use]
+    pub const fn debug_info(mut self, enabled: bool) -> Self {
+        self.debug_info = enabled;
+        self
+    }
+}
 
-        if let Some(max_memory) = overrides.max_memory {
-            merged.max_memory = Some(max_memory);
+/// Pooling instance allocator settings for a [`SandboxTemplate`].
+///
+/// With pooling enabled, Wasmtime reserves slots for every sandbox up front
+/// and reuses them across instantiations instead of mapping fresh memory for
+/// each one. Instantiation becomes cheaper and more predictable, at the cost
+/// of reserving virtual memory for [`PoolingConfig::max_instances`] sandboxes
+/// when the template is built.
+///
+/// Instantiation fails once every slot is in use; drop a [`Sandbox`] to return
+/// its slot to the pool.
+#[derive(Clone, Debug)]
+pub struct PoolingConfig {
+    pub(crate) max_instances: u32,
+    pub(crate) max_memory_pages: u64,
+    pub(crate) table_elements: usize,
+}
+
+impl Default for PoolingConfig {
+    fn default() -> Self {
+        Self {
+            max_instances: 1000,
+            max_memory_pages: 65536,
+            table_elements: 20_000,
         }
+    }
+}
 
-        for mapping in overrides.directory_mappings {
-            if let Some(existing) = merged
-                .directory_mappings
-                .iter_mut()
-                .find(|m| m.guest == mapping.guest)
-            {
+impl PoolingConfig {
+    /// Set the maximum number of sandboxes that may be alive at once.
+    ///
+    /// Defaults to 1000.
+    #[must_use]
+    pub const fn max_instances(mut self, max_instances: u32) -> Self {
+        self.max_instances = max_instances;
+        self
+    }
+
+    /// Set the maximum size of each guest linear memory in 64 KiB pages.
+    ///
+    /// This bounds the reserved address space per memory slot and must be at
+    /// least as large as the template's memory limit. Defaults to 65536
+    /// pages (4 GiB).
+    #[must_use]
+    pub const fn max_memory_pages(mut self, max_memory_pages: u64) -> Self {
+        self.max_memory_pages = max_memory_pages;
+        self
+    }
+
+    /// Set the maximum number of elements in each guest table.
+    ///
+    /// Defaults to 20000.
+    #[must_use]
+    pub const fn table_elements(mut self, table_elements: usize) -> Self {
+        self.table_elements = table_elements;
+        self
+    }
+}
+
+/// On-disk compile cache directory with optional eviction limits.
+///
+/// Compiled artifacts are keyed by runtime, engine, and template settings, so
+/// a long-lived cache directory accumulates one entry per runtime version and
+/// configuration ever built. [`Cache::gc`] removes entries older than
+/// [`Cache::max_age`], then the least recently used ones until the directory
+/// fits in [`Cache::max_size`]. Only compiled artifacts are touched; other
+/// files in the directory are left alone.
+///
+/// The same limits can be applied automatically after each template build
+/// with [`SandboxTemplateBuilder::cache_max_size`] and
+/// [`SandboxTemplateBuilder::cache_max_age`].
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+}
+
+impl Cache {
+    /// Open the cache rooted at `dir`.
+    ///
+    /// No limits are set by default, so [`Cache::gc`] removes nothing until
+    /// [`Cache::max_size`] or [`Cache::max_age`] is configured.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size: None,
+            max_age: None,
+        }
+    }
+
+    /// Set the maximum total size of cached artifacts in bytes.
+    #[must_use]
+    pub const fn max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Set the maximum time since a cached artifact was last written or used.
+    #[must_use]
+    pub const fn max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Return the cache directory.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Evict cached artifacts that exceed the configured limits.
+    ///
+    /// Compile lock files of evicted artifacts are removed once no process
+    /// holds them, and temporary files left by interrupted writes once they
+    /// are an hour old. A missing cache directory is treated as empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be listed or an entry cannot
+    /// be removed.
+    pub async fn gc(&self) -> Result<CacheGcStats> {
+        gc_cache_dir(&self.dir, self.max_size, self.max_age, None)
+            .await
+            .map_err(Error::from)
+    }
+}
+
+/// Outcome of a [`Cache::gc`] pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CacheGcStats {
+    /// Number of cached artifacts removed.
+    pub removed_entries: usize,
+    /// Total size of the removed artifacts in bytes.
+    pub removed_bytes: u64,
+    /// Number of cached artifacts left in the directory.
+    pub retained_entries: usize,
+    /// Total size of the remaining artifacts in bytes.
+    pub retained_bytes: u64,
+}
+
+/// Builder for compiling a reusable [`SandboxTemplate`].
+///
+/// `SandboxTemplateBuilder` configures template-level defaults shared by every
+/// sandbox instantiated from the resulting [`SandboxTemplate`], including base
+/// mount/env settings.
+#[derive(Default)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "each flag is an independent builder option"
+)]
+pub struct SandboxTemplateBuilder {
+    pub(crate) cache: Option<PathBuf>,
+    pub(crate) cache_max_size: Option<u64>,
+    pub(crate) cache_max_age: Option<Duration>,
+    pub(crate) base_options: SandboxOptions,
+    pub(crate) preludes: Vec<PreludeSource>,
+    pub(crate) warmup_imports: Vec<String>,
+    pub(crate) pooling: Option<PoolingConfig>,
+    pub(crate) engine: EngineConfig,
+    pub(crate) compile_threads: Option<usize>,
+    pub(crate) native_async: bool,
+    pub(crate) eager_memory_init: bool,
+    pub(crate) trust_policy: Option<Arc<dyn TrustPolicy>>,
+    pub(crate) disabled_wasi: Vec<WasiInterface>,
+    pub(crate) cache_backend: Option<Arc<dyn CacheBackend>>,
+    pub(crate) plugins: Vec<(String, PathBuf)>,
+    pub(crate) namespace: Option<Namespace>,
+    pub(crate) max_stack: Option<usize>,
+    #[cfg(feature = "pulley")]
+    pub(crate) interpreter: bool,
+    pub(crate) snapshots: bool,
+    pub(crate) fuel_metering: bool,
+    pub(crate) epoch_tick: Option<Duration>,
+    pub(crate) linker_extensions: Vec<LinkerExtension>,
+}
+
+/// Compiled sandbox template that can instantiate multiple sandboxes.
+///
+/// A `SandboxTemplate` is an immutable, reusable compiled artifact.
+/// Instantiate it to create one or more independent [`Sandbox`] values with
+/// isolated runtime state.
+pub struct SandboxTemplate {
+    pub(crate) base_options: SandboxOptions,
+    pub(crate) engine: Engine,
+    pub(crate) component: Component,
+    pub(crate) ticker: Arc<EpochTickerRegistration>,
+    pub(crate) native_async: bool,
+    pub(crate) disabled_wasi: Vec<WasiInterface>,
+    pub(crate) plugins: Vec<Arc<PluginTemplate>>,
+    pub(crate) namespace: Option<Namespace>,
+    pre_instances: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    counters: TemplateCounters,
+    snapshot_source: Option<Arc<SnapshotSource>>,
+    fuel_metering: bool,
+    metadata: Option<RuntimeMetadata>,
+    world: ScriptWorld,
+    linker_extensions: Arc<[LinkerExtension]>,
+}
+
+/// Live guest instance with mutable execution state.
+///
+/// A `Sandbox` belongs to a single instantiation of a [`SandboxTemplate`]. It
+/// carries the guest state for eval/call operations and is isolated from other
+/// sandboxes created from the same template.
+pub struct Sandbox<H: Host> {
+    pub(crate) store: Store<InstanceState<H>>,
+    pub(crate) instance: Instance,
+    pub(crate) bindings: WasmSandbox,
+    /// Keeps the epoch ticker alive for the lifetime of this sandbox.
+    pub(crate) _ticker: Arc<EpochTickerRegistration>,
+    pub(crate) native_async: bool,
+    /// Template and options this sandbox was instantiated from.
+    pub(crate) origin: Arc<SandboxOrigin<H>>,
+    /// Holds this sandbox's slot in the template namespace, if any.
+    pub(crate) _namespace_slot: Option<NamespaceSlot>,
+    /// Counts this sandbox in its template's live sandboxes.
+    pub(crate) _live: LiveSandbox,
+    /// Recent hostcalls and HTTP requests, when tracing is enabled.
+    pub(crate) call_trace: Option<Arc<CallTrace>>,
+    /// Cancellation state shared with this sandbox's [`InterruptHandle`]s.
+    pub(crate) interrupt: Arc<InterruptState>,
+    /// Snapshot of the guest that calls with
+    /// [`CallOptions::fresh_globals`] run in copies of.
+    pub(crate) fresh_copy: Option<FreshCopy<H>>,
+}
+
+/// How guest stdout and stderr writes are grouped into log records.
+///
+/// In every mode, concatenating the records of one stream reproduces exactly
+/// what the guest wrote, including newlines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StdioBuffering {
+    /// Batch small writes and emit chunks of up to 1 KiB without regard to
+    /// line boundaries. This has the lowest per-write overhead.
+    #[default]
+    Chunked,
+    /// Emit one record per line. An unterminated line is held until it is
+    /// completed, the guest flushes the stream (which the runtimes do before
+    /// delivering each result item and at the end of every call), or it
+    /// reaches the maximum line length.
+    Line,
+    /// Emit every write as its own record as soon as it happens.
+    Immediate,
+}
+
+/// Per-instantiation policy overrides for a [`Sandbox`].
+///
+/// Default options inherit every setting from the [`SandboxTemplate`]. Values
+/// set here are merged with the template defaults during
+/// [`SandboxTemplate::instantiate`].
+#[derive(Clone, Debug, Default)]
+pub struct SandboxOptions {
+    pub(crate) max_memory: Option<usize>,
+    pub(crate) directory_mappings: Vec<DirectoryMapping>,
+    pub(crate) env: Vec<(String, String)>,
+    pub(crate) read_only: bool,
+    pub(crate) stdio_buffering: Option<StdioBuffering>,
+    pub(crate) max_output_line_length: Option<usize>,
+    pub(crate) workdir: Option<String>,
+    pub(crate) max_open_handles: Option<usize>,
+    pub(crate) max_open_files: Option<usize>,
+    pub(crate) max_write_bytes: Option<u64>,
+    #[cfg(feature = "archive")]
+    pub(crate) archive_limits: Option<FsQuota>,
+    pub(crate) file_policy: Option<SharedFilePolicy>,
+    pub(crate) trace_hostcalls: Option<usize>,
+    pub(crate) max_fuel: Option<u64>,
+    pub(crate) stdin: Option<GuestStdin>,
+    pub(crate) stdout_writer: Option<GuestOutput>,
+    pub(crate) stderr_writer: Option<GuestOutput>,
+    pub(crate) clock: Option<SharedClock>,
+    pub(crate) entropy: Option<SharedEntropy>,
+    pub(crate) epoch_yield_ticks: Option<u64>,
+    pub(crate) hostcall_limits: Option<HostcallLimits>,
+    pub(crate) idle_timeout: Option<Duration>,
+}
+
+impl SandboxOptions {
+    /// Override the memory hard limit for this sandbox.
+    #[must_use]
+    pub const fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Limit every eval and call in this sandbox to `max_fuel` units of fuel.
+    ///
+    /// Most WebAssembly instructions consume one unit, so the budget bounds
+    /// the work a call may do independently of how fast the host is. A call
+    /// that runs out fails with [`ErrorKind::Timeout`].
+    /// [`CallOptions::max_fuel`] overrides the limit for a single call.
+    ///
+    /// Requires a template built with
+    /// [`fuel_metering`](SandboxTemplateBuilder::fuel_metering); otherwise
+    /// instantiation fails.
+    #[must_use]
+    pub const fn max_fuel(mut self, max_fuel: u64) -> Self {
+        self.max_fuel = Some(max_fuel);
+        self
+    }
+
+    /// Drop the sandbox once it has not been used for `timeout`.
+    ///
+    /// The timeout is enforced by [`SharedSandbox`], which owns the sandbox
+    /// and can drop it when no caller holds or waits for it. The sandbox
+    /// counts as idle from the moment its last
+    /// [`SharedSandboxGuard`] is dropped. Disposal then calls
+    /// [`Host::on_idle_dispose`], and later
+    /// [`lock`](SharedSandbox::lock) calls fail with
+    /// [`Error::IdleDisposed`]. A [`Sandbox`] held directly is never dropped
+    /// behind its owner's back.
+    #[must_use]
+    pub const fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Let guest code run for `ticks` epoch ticks between yields to the async
+    /// executor.
+    ///
+    /// By default guest code yields at every tick, which keeps other tasks on
+    /// the same executor responsive. Yielding less often lowers the overhead
+    /// of long computations at the cost of that fairness. Deadlines and
+    /// interrupts are only checked when the guest yields, so they may take
+    /// effect up to `ticks` ticks late. The tick length is set with
+    /// [`SandboxTemplateBuilder::epoch_tick`]. Zero is treated as one.
+    #[must_use]
+    pub const fn epoch_yield_ticks(mut self, ticks: u64) -> Self {
+        self.epoch_yield_ticks = Some(ticks);
+        self
+    }
+
+    /// Mount a host directory into this sandbox instance.
+    ///
+    /// If a guest path duplicates a module-level mount, this mount replaces it
+    /// for that instance.
+    #[must_use]
+    pub fn mount(
+        mut self,
+        host_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+    ) -> Self {
+        self.directory_mappings.push(
+            DirectoryMapping::new(host_path.as_ref(), guest_path.as_ref())
+                .with_permissions(dir_perms, file_perms),
+        );
+        self
+    }
+
+    /// Mount a host directory into this sandbox instance with a space quota.
+    ///
+    /// The quota only applies when `file_perms` includes
+    /// [`FilePerms::WRITE`]; read-only mounts cannot grow.
+    #[must_use]
+    pub fn mount_with_quota(
+        mut self,
+        host_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+        quota: FsQuota,
+    ) -> Self {
+        self.directory_mappings.push(
+            DirectoryMapping::new(host_path.as_ref(), guest_path.as_ref())
+                .with_permissions(dir_perms, file_perms)
+                .with_quota(Some(quota)),
+        );
+        self
+    }
+
+    /// Mount a host directory into this sandbox instance, showing the guest
+    /// only the files matching one of the `include` glob patterns.
+    ///
+    /// Patterns match paths relative to the mount point; `*` and `?` do not
+    /// match `/`, while `**` matches any number of directories, so `*.csv`
+    /// selects CSV files at the top of the mount and `**/*.csv` those at any
+    /// depth. Other files and symlinks are left out of directory listings
+    /// and cannot be opened; directories always stay visible. The guest
+    /// cannot create entries the patterns would hide or move directories
+    /// into or out of the mount.
+    ///
+    /// An invalid pattern fails instantiation.
+    #[must_use]
+    pub fn mount_filtered<I, S>(
+        mut self,
+        host_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+        include: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.directory_mappings.push(
+            DirectoryMapping::new(host_path.as_ref(), guest_path.as_ref())
+                .with_permissions(dir_perms, file_perms)
+                .with_include(include.into_iter().map(Into::into).collect()),
+        );
+        self
+    }
+
+    /// Mount a tar or zip archive as a read-only directory in this sandbox
+    /// instance.
+    ///
+    /// The archive is unpacked into a private temporary directory when the
+    /// sandbox is instantiated and removed once no sandbox uses it, so callers
+    /// do not have to extract it themselves. The format is detected from the
+    /// file contents. Entries that would unpack outside the mount are
+    /// skipped. Unpacking is bounded by
+    /// [`archive_limits`](Self::archive_limits), and instantiation fails
+    /// with [`std::io::ErrorKind::FileTooLarge`] if the archive exceeds them.
+    ///
+    /// Available with the `archive` feature.
+    #[cfg(feature = "archive")]
+    #[must_use]
+    pub fn mount_archive(
+        mut self,
+        archive_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+    ) -> Self {
+        self.directory_mappings.push(
+            DirectoryMapping::new(PathBuf::new(), guest_path.as_ref())
+                .with_archive(ArchiveSource::Path(archive_path.as_ref().to_path_buf())),
+        );
+        self
+    }
+
+    /// Mount an in-memory tar or zip archive as a read-only directory in this
+    /// sandbox instance.
+    ///
+    /// Behaves like [`mount_archive`](Self::mount_archive).
+    ///
+    /// Available with the `archive` feature.
+    #[cfg(feature = "archive")]
+    #[must_use]
+    pub fn mount_archive_bytes(
+        mut self,
+        archive: impl Into<bytes::Bytes>,
+        guest_path: impl AsRef<str>,
+    ) -> Self {
+        self.directory_mappings.push(
+            DirectoryMapping::new(PathBuf::new(), guest_path.as_ref())
+                .with_archive(ArchiveSource::Bytes(archive.into())),
+        );
+        self
+    }
+
+    /// Mount an empty writable directory at `guest_path` that the guest may
+    /// fill with at most `max_bytes`.
+    ///
+    /// Each sandbox instance gets its own private temporary directory, which
+    /// is removed when the sandbox is dropped, so no host directory has to be
+    /// prepared or cleaned up. Writes beyond the quota fail with `ENOSPC`, as
+    /// for [`mount_with_quota`](Self::mount_with_quota).
+    #[must_use]
+    pub fn scratch_dir(mut self, guest_path: impl AsRef<str>, max_bytes: u64) -> Self {
+        let mut mapping = DirectoryMapping::new(PathBuf::new(), guest_path.as_ref())
+            .with_permissions(DirPerms::all(), FilePerms::all())
+            .with_quota(Some(FsQuota::default().max_bytes(Some(max_bytes))));
+        mapping.scratch = true;
+        self.directory_mappings.push(mapping);
+        self
+    }
+
+    /// Mount an [`OverlayMount`] of several host directories into this
+    /// sandbox instance.
+    #[must_use]
+    pub fn mount_overlay(mut self, overlay: OverlayMount, guest_path: impl AsRef<str>) -> Self {
+        self.directory_mappings
+            .push(DirectoryMapping::overlay(overlay, guest_path.as_ref()));
+        self
+    }
+
+    /// Add an environment variable for this sandbox instance.
+    ///
+    /// If the same key is set multiple times, the last value wins.
+    #[must_use]
+    pub fn env(mut self, k: impl AsRef<str>, v: impl AsRef<str>) -> Self {
+        self.env
+            .push((k.as_ref().to_string(), v.as_ref().to_string()));
+        self
+    }
+
+    /// Make this sandbox unable to persist anything through the filesystem.
+    ///
+    /// Every mount is exposed read-only regardless of the permissions it was
+    /// configured with, and the host rejects any WASI filesystem call that
+    /// would create, modify, or remove an entry with a read-only error.
+    #[must_use]
+    pub const fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Choose how guest stdout and stderr writes are grouped into log records.
+    ///
+    /// Defaults to [`StdioBuffering::Chunked`].
+    #[must_use]
+    pub const fn stdio_buffering(mut self, buffering: StdioBuffering) -> Self {
+        self.stdio_buffering = Some(buffering);
+        self
+    }
+
+    /// Feed guest stdin from `reader`.
+    ///
+    /// Without it stdin is empty, so reads such as Python's `input()` or
+    /// `sys.stdin.read()` see end of file immediately. Reads block the
+    /// calling guest code until `reader` yields data or reaches end of file;
+    /// pair it with a timeout when the data arrives interactively, for
+    /// example from a pipe the host keeps writing to.
+    ///
+    /// The reader is consumed across calls and is not rewound by
+    /// [`Sandbox::reset`]. Clones of these options share it, so give each
+    /// sandbox its own.
+    #[must_use]
+    pub fn stdin(mut self, reader: impl AsyncRead + Send + Sync + 'static) -> Self {
+        self.stdin = Some(GuestStdin::new(reader));
+        self
+    }
+
+    /// Back the guest's wall and monotonic clocks with `clock`.
+    ///
+    /// Guest code reads the time from `clock`, and its sleeps and timeouts
+    /// wait on [`ClockProvider::wait_until`], so a
+    /// [`ManualClock`](crate::host::ManualClock) makes them return at once.
+    /// Guest sleeps in a sandbox without a clock take real time.
+    #[must_use]
+    pub fn clock(mut self, clock: impl ClockProvider) -> Self {
+        self.clock = Some(SharedClock::new(clock));
+        self
+    }
+
+    /// Draw the random bytes guest code receives from `source` instead of the
+    /// operating system.
+    ///
+    /// The source backs both `wasi:random/random`, which serves APIs such as
+    /// Python's `os.urandom` and `secrets`, and `wasi:random/insecure`. State
+    /// captured while the template was built, such as a random module seeded
+    /// during the prelude, is not drawn again.
+    #[must_use]
+    pub fn entropy(mut self, source: impl EntropySource) -> Self {
+        self.entropy = Some(SharedEntropy::new(source));
+        self
+    }
+
+    /// Consult `policy` before guest code opens, creates, or lists files in
+    /// its mounts.
+    ///
+    /// The policy sees each access with its mount and path and may deny it,
+    /// for example to log what the guest touches or to hide paths that the
+    /// static mount permissions cannot express. Denied operations fail with
+    /// `EACCES`.
+    #[must_use]
+    pub fn file_policy(mut self, policy: impl FilePolicy) -> Self {
+        self.file_policy = Some(SharedFilePolicy::new(policy));
+        self
+    }
+
+    /// Write guest stdout to `writer` unchanged instead of emitting it as log
+    /// records.
+    ///
+    /// Output is neither split into lines nor decoded as UTF-8, which suits
+    /// large or binary output such as generated images or archives. Guest
+    /// writes wait for `writer` to accept the bytes, so the output of a call
+    /// has reached it by the time the call returns.
+    /// [`stdio_buffering`](Self::stdio_buffering) and
+    /// [`max_output_line_length`](Self::max_output_line_length) do not apply.
+    ///
+    /// Clones of these options share `writer`, as for [`stdin`](Self::stdin).
+    #[must_use]
+    pub fn stdout_writer(mut self, writer: impl AsyncWrite + Send + Sync + 'static) -> Self {
+        self.stdout_writer = Some(GuestOutput::new(writer));
+        self
+    }
+
+    /// Write guest stderr to `writer` unchanged instead of emitting it as log
+    /// records.
+    ///
+    /// Behaves like [`stdout_writer`](Self::stdout_writer). Tracebacks printed
+    /// to stderr are then no longer available to attach to errors from calls
+    /// that fail without one.
+    #[must_use]
+    pub fn stderr_writer(mut self, writer: impl AsyncWrite + Send + Sync + 'static) -> Self {
+        self.stderr_writer = Some(GuestOutput::new(writer));
+        self
+    }
+
+    /// Split guest stdout and stderr records longer than `max_len` bytes.
+    ///
+    /// Longer records are delivered as several consecutive log records, cut
+    /// at UTF-8 character boundaries. With [`StdioBuffering::Line`] this also
+    /// bounds how much of an unterminated line is held before it is emitted,
+    /// which otherwise defaults to 64 KiB.
+    #[must_use]
+    pub const fn max_output_line_length(mut self, max_len: usize) -> Self {
+        self.max_output_line_length = Some(max_len);
+        self
+    }
+
+    /// Limit how many handles the guest may hold open at once.
+    ///
+    /// Every resource the guest holds counts toward the limit: open files and
+    /// directories, including the preopened mounts, as well as streams,
+    /// pollables, and HTTP requests. Opening another one once the limit is
+    /// reached aborts the running call with an error of kind
+    /// [`ErrorKind::PolicyDenied`]; closed handles free their slot again.
+    ///
+    /// Files count toward this limit whether or not
+    /// [`max_open_files`](Self::max_open_files) is set as well. Set
+    /// `max_open_files` below this limit so a guest that opens too many files
+    /// sees a failed open before it can abort the call.
+    #[must_use]
+    pub const fn max_open_handles(mut self, max_handles: usize) -> Self {
+        self.max_open_handles = Some(max_handles);
+        self
+    }
+
+    /// Limit how many files and directories the guest may hold open at once.
+    ///
+    /// Unlike [`max_open_handles`](Self::max_open_handles), only descriptors
+    /// the guest opens through the filesystem count; preopened mounts,
+    /// streams, and other resources do not. This bounds the host file
+    /// descriptors a guest can tie up. Opening another file once the limit
+    /// is reached fails inside the guest with a quota error, which Python
+    /// raises as an `OSError`, and the call carries on; closing a file frees
+    /// its slot again.
+    ///
+    /// Both limits apply when both are set: an open that stays within this
+    /// limit still aborts the call if it exceeds `max_open_handles`.
+    #[must_use]
+    pub const fn max_open_files(mut self, max_files: usize) -> Self {
+        self.max_open_files = Some(max_files);
+        self
+    }
+
+    /// Limit how many bytes the guest may write to files over the sandbox's
+    /// lifetime.
+    ///
+    /// The budget is shared by every mount and counts each byte written,
+    /// including overwrites of existing content; truncating or removing
+    /// files does not refund it. Writes that would exceed the budget fail
+    /// with `ENOSPC`. Use [`FsQuota`] to bound how much a mount may grow
+    /// instead.
+    #[must_use]
+    pub const fn max_write_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_write_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Bound what a single archive may unpack to `limits.max_bytes` of file
+    /// contents and `limits.max_inodes` files and directories.
+    ///
+    /// The limits apply to [archive mounts](Self::mount_archive), which
+    /// default to 1 GiB and 100 000 entries, and to
+    /// [`Sandbox::extract_archive`], which is additionally bounded by what
+    /// the target mount's [`FsQuota`] and
+    /// [`max_write_bytes`](Self::max_write_bytes) have left. An archive that
+    /// exceeds a limit fails with [`std::io::ErrorKind::FileTooLarge`]
+    /// before anything reaches the guest.
+    ///
+    /// Available with the `archive` feature.
+    #[cfg(feature = "archive")]
+    #[must_use]
+    pub const fn archive_limits(mut self, limits: FsQuota) -> Self {
+        self.archive_limits = Some(limits);
+        self
+    }
+
+    /// Keep a record of the last `capacity` hostcalls and HTTP requests.
+    ///
+    /// Each entry holds the hostcall type or request line, the first 256
+    /// bytes of the payload, the duration, and the outcome, and can be
+    /// retrieved with [`Sandbox::debug_dump`] after a call fails. Tracing is
+    /// off by default; a capacity of `0` turns it off again.
+    #[must_use]
+    pub const fn trace_hostcalls(mut self, capacity: usize) -> Self {
+        self.trace_hostcalls = Some(capacity);
+        self
+    }
+
+    /// Bound the hostcalls guest code makes in each eval or call.
+    ///
+    /// At most `max_calls` hostcalls are admitted per guest operation and at
+    /// most `max_concurrent` may run at once. Admitted calls start at least
+    /// `min_interval` apart; later ones wait before [`Host::hostcall`] runs,
+    /// which throttles tight guest loops. A rejected hostcall never reaches
+    /// the host: the guest receives an error whose message starts with
+    /// `hostcall limit exceeded:`, and hostcall tracing records it as
+    /// denied. Calls routed to plugins count as well.
+    ///
+    /// [`Host::hostcall`]: crate::host::Host::hostcall
+    #[must_use]
+    pub const fn hostcall_limits(
+        mut self,
+        max_calls: u64,
+        max_concurrent: usize,
+        min_interval: Duration,
+    ) -> Self {
+        self.hostcall_limits = Some(HostcallLimits {
+            max_calls,
+            max_concurrent,
+            min_interval,
+        });
+        self
+    }
+
+    /// Set the guest's working directory.
+    ///
+    /// `guest_path` must be absolute and lie inside a mount. If that mount is
+    /// writable, missing directories are created when the sandbox is
+    /// instantiated; otherwise the directory must already exist. Scripts start
+    /// in `/` when no working directory is set.
+    #[must_use]
+    pub fn workdir(mut self, guest_path: impl AsRef<str>) -> Self {
+        self.workdir = Some(guest_path.as_ref().to_string());
+        self
+    }
+
+    /// Merge `overrides` into this options value and return the merged result.
+    ///
+    /// Merge behavior:
+    /// - `max_memory`, `stdio_buffering`, `max_output_line_length`, `workdir`,
+    ///   `max_open_handles`, `max_open_files`, `max_write_bytes`,
+    ///   `archive_limits`, `trace_hostcalls`, `max_fuel`, `stdin`, `stdout_writer`,
+    ///   `stderr_writer`, `clock`, `entropy`, `file_policy`,
+    ///   `epoch_yield_ticks`, `hostcall_limits`, `idle_timeout`: override wins
+    ///   when set.
+    /// - mounts: override entries replace on guest-path collision.
+    /// - `env`: override values replace by matching key.
+    /// - `read_only`: enabled if either side enables it.
+    #[must_use]
+    pub fn merged_with(&self, overrides: &Self) -> Self {
+        self.merged_with_owned(overrides.clone())
+    }
+
+    fn merged_with_owned(&self, overrides: Self) -> Self {
+        let mut merged = self.clone();
+
+        if let Some(max_memory) = overrides.max_memory {
+            merged.max_memory = Some(max_memory);
+        }
+        if let Some(buffering) = overrides.stdio_buffering {
+            merged.stdio_buffering = Some(buffering);
+        }
+        if let Some(max_len) = overrides.max_output_line_length {
+            merged.max_output_line_length = Some(max_len);
+        }
+        if let Some(workdir) = overrides.workdir {
+            merged.workdir = Some(workdir);
+        }
+        if let Some(max_handles) = overrides.max_open_handles {
+            merged.max_open_handles = Some(max_handles);
+        }
+        if let Some(max_files) = overrides.max_open_files {
+            merged.max_open_files = Some(max_files);
+        }
+        if let Some(max_bytes) = overrides.max_write_bytes {
+            merged.max_write_bytes = Some(max_bytes);
+        }
+        #[cfg(feature = "archive")]
+        if let Some(limits) = overrides.archive_limits {
+            merged.archive_limits = Some(limits);
+        }
+        if let Some(policy) = overrides.file_policy {
+            merged.file_policy = Some(policy);
+        }
+        if let Some(capacity) = overrides.trace_hostcalls {
+            merged.trace_hostcalls = Some(capacity);
+        }
+        if let Some(max_fuel) = overrides.max_fuel {
+            merged.max_fuel = Some(max_fuel);
+        }
+        if let Some(stdin) = overrides.stdin {
+            merged.stdin = Some(stdin);
+        }
+        if let Some(stdout) = overrides.stdout_writer {
+            merged.stdout_writer = Some(stdout);
+        }
+        if let Some(stderr) = overrides.stderr_writer {
+            merged.stderr_writer = Some(stderr);
+        }
+        if let Some(clock) = overrides.clock {
+            merged.clock = Some(clock);
+        }
+        if let Some(entropy) = overrides.entropy {
+            merged.entropy = Some(entropy);
+        }
+        if let Some(ticks) = overrides.epoch_yield_ticks {
+            merged.epoch_yield_ticks = Some(ticks);
+        }
+        if let Some(limits) = overrides.hostcall_limits {
+            merged.hostcall_limits = Some(limits);
+        }
+        if let Some(timeout) = overrides.idle_timeout {
+            merged.idle_timeout = Some(timeout);
+        }
+        merged.read_only |= overrides.read_only;
+
+        for mapping in overrides.directory_mappings {
+            if let Some(existing) = merged
+                .directory_mappings
+                .iter_mut()
+                .find(|m| m.guest == mapping.guest)
+            {
                 *existing = mapping;
             } else {
                 merged.directory_mappings.push(mapping);
@@ -278,80 +1706,481 @@ impl SandboxOptions {
             }
         }
 
-        merged
+        merged
+    }
+}
+
+/// Collected output from
+/// [`Sandbox::call`](crate::sandbox::Sandbox::call).
+#[derive(Debug, Default)]
+pub struct CallOutput {
+    /// Values yielded or explicitly emitted by the guest, in emission order.
+    pub items: Vec<Value>,
+    /// Final guest return value, or `None` when no final value was encoded.
+    ///
+    /// A guest-language `None` or `null` is an encoded CBOR value and is
+    /// therefore represented as `Some(Value)`.
+    pub result: Option<Value>,
+    /// Lines executed by the call, when requested with
+    /// [`CallOptions::coverage`].
+    pub coverage: Option<CoverageReport>,
+    /// Fuel consumed by the call, when the template was built with
+    /// [`fuel_metering`](SandboxTemplateBuilder::fuel_metering).
+    pub fuel_consumed: Option<u64>,
+    /// Time, memory, hostcalls, HTTP requests and output bytes the call used.
+    pub exec_stats: ExecStats,
+}
+
+impl SandboxTemplateBuilder {
+    /// Set the optional component cache directory.
+    ///
+    /// When set, compiled artifacts are cached on disk and reused across
+    /// builds.
+    #[must_use]
+    pub fn cache(mut self, cache: Option<std::path::PathBuf>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Place this template in a tenant [`Namespace`].
+    ///
+    /// Compiled artifacts are cached in a per-namespace subdirectory of
+    /// [`cache`](Self::cache), and the namespace's sandbox count and memory
+    /// limits apply to every sandbox instantiated from this template. `None`
+    /// (the default) uses the shared cache directory without extra limits.
+    #[must_use]
+    pub fn namespace(mut self, namespace: Option<Namespace>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Require the runtime artifact to carry a detached signature accepted by
+    /// `policy`.
+    ///
+    /// The signature is read from the artifact path with `.sig` appended and
+    /// checked against the bytes that are compiled, before any guest code
+    /// runs and before a cached artifact is reused. Compiled artifacts from
+    /// the cache directory or a [`cache_backend`](Self::cache_backend) are
+    /// not signed and must be trusted on their own. `None` disables
+    /// verification.
+    #[must_use]
+    pub fn trust_policy(mut self, policy: Option<Arc<dyn TrustPolicy>>) -> Self {
+        self.trust_policy = policy;
+        self
+    }
+
+    /// Set a shared compile cache consulted before compiling locally.
+    ///
+    /// Artifacts found in the backend are reused (and copied into the local
+    /// [`cache`](Self::cache) directory when one is configured); artifacts
+    /// compiled locally are uploaded for other hosts. An artifact that fails
+    /// to deserialize is treated as a miss and overwritten with a fresh
+    /// compile. `None` disables the shared cache.
+    ///
+    /// # Safety
+    ///
+    /// Artifacts returned by the backend are loaded as native code without
+    /// being validated or checked against the
+    /// [`trust_policy`](Self::trust_policy), so a tampered artifact can run
+    /// arbitrary code in the host process. The caller must ensure that only
+    /// parties as trusted as the host itself can write to the backend, or
+    /// that the backend authenticates artifacts before returning them; see
+    /// [`CacheBackend`].
+    #[must_use]
+    pub unsafe fn cache_backend(mut self, backend: Option<Arc<dyn CacheBackend>>) -> Self {
+        self.cache_backend = backend;
+        self
+    }
+
+    /// Set the size limit applied to the cache directory after each build.
+    ///
+    /// When a build compiles a new artifact, least recently used entries are
+    /// evicted until the directory fits in `max_size` bytes. Cleanup is best
+    /// effort and never fails the build. See [`Cache::gc`].
+    #[must_use]
+    pub const fn cache_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.cache_max_size = max_size;
+        self
+    }
+
+    /// Set the age limit applied to the cache directory after each build.
+    ///
+    /// When a build compiles a new artifact, entries not written or used
+    /// within `max_age` are evicted. Cleanup is best effort and never fails
+    /// the build. See [`Cache::gc`].
+    #[must_use]
+    pub const fn cache_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.cache_max_age = max_age;
+        self
+    }
+
+    /// Set the per-sandbox memory hard limit.
+    ///
+    /// Defaults to unlimited (`usize::MAX`).
+    #[must_use]
+    pub const fn max_memory(mut self, max_memory: usize) -> Self {
+        self.base_options.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Set base directory mappings shared by all sandboxes from this template.
+    ///
+    /// These mappings can be extended or overridden per instantiation via
+    /// [`SandboxOptions::mount`](crate::sandbox::SandboxOptions::mount).
+    ///
+    /// This API matches the WASI-style preopen configuration shape.
+    #[must_use]
+    pub fn mount(
+        mut self,
+        host_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+    ) -> Self {
+        self.base_options = self
+            .base_options
+            .mount(host_path, guest_path, dir_perms, file_perms);
+        self
+    }
+
+    /// Set a base directory mapping with a space quota.
+    ///
+    /// Each sandbox gets its own usage counter for the mount; see
+    /// [`SandboxOptions::mount_with_quota`].
+    #[must_use]
+    pub fn mount_with_quota(
+        mut self,
+        host_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+        quota: FsQuota,
+    ) -> Self {
+        self.base_options = self
+            .base_options
+            .mount_with_quota(host_path, guest_path, dir_perms, file_perms, quota);
+        self
+    }
+
+    /// Set a base directory mapping that only shows files matching the
+    /// `include` glob patterns.
+    ///
+    /// See [`SandboxOptions::mount_filtered`].
+    #[must_use]
+    pub fn mount_filtered<I, S>(
+        mut self,
+        host_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+        include: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.base_options = self
+            .base_options
+            .mount_filtered(host_path, guest_path, dir_perms, file_perms, include);
+        self
+    }
+
+    /// Set a base mount backed by a tar or zip archive.
+    ///
+    /// The archive is unpacked once while building the template and shared
+    /// by every sandbox created from it; see
+    /// [`SandboxOptions::mount_archive`].
+    ///
+    /// Available with the `archive` feature.
+    #[cfg(feature = "archive")]
+    #[must_use]
+    pub fn mount_archive(
+        mut self,
+        archive_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+    ) -> Self {
+        self.base_options = self.base_options.mount_archive(archive_path, guest_path);
+        self
+    }
+
+    /// Set a base mount backed by an in-memory tar or zip archive.
+    ///
+    /// See [`SandboxOptions::mount_archive_bytes`].
+    ///
+    /// Available with the `archive` feature.
+    #[cfg(feature = "archive")]
+    #[must_use]
+    pub fn mount_archive_bytes(
+        mut self,
+        archive: impl Into<bytes::Bytes>,
+        guest_path: impl AsRef<str>,
+    ) -> Self {
+        self.base_options = self.base_options.mount_archive_bytes(archive, guest_path);
+        self
+    }
+
+    /// Set a base [`OverlayMount`] shared by all sandboxes from this template.
+    ///
+    /// The writable layer, if any, is shared as well; give each sandbox its
+    /// own with [`SandboxOptions::mount_overlay`] instead.
+    #[must_use]
+    pub fn mount_overlay(mut self, overlay: OverlayMount, guest_path: impl AsRef<str>) -> Self {
+        self.base_options = self.base_options.mount_overlay(overlay, guest_path);
+        self
+    }
+
+    /// Add an environment variable that will be present in sandbox WASI env.
+    ///
+    /// If the same key is set multiple times, the last value wins.
+    #[must_use]
+    pub fn env(mut self, k: impl AsRef<str>, v: impl AsRef<str>) -> Self {
+        self.base_options = self.base_options.env(k, v);
+        self
+    }
+
+    /// Set optional guest prelude code executed during template initialization.
+    ///
+    /// Prelude state is captured in the compiled template and is therefore
+    /// present in every sandbox instantiated from it. Replaces any preludes
+    /// added before; `None` disables the prelude.
+    #[must_use]
+    pub fn prelude(mut self, prelude: Option<String>) -> Self {
+        self.preludes = prelude.map(PreludeSource::Inline).into_iter().collect();
+        self
+    }
+
+    /// Add guest prelude code to run after the preludes added before it.
+    ///
+    /// Preludes run in the order they were added, so later ones can build
+    /// on earlier ones, for example project shims on top of a shared
+    /// library. Building fails with [`Error::Prelude`] naming the first
+    /// prelude that raises. Runtimes without
+    /// [`preludes`](ScriptWorld::preludes) take a single prelude; building
+    /// with more fails with [`Error::IncompatibleWorld`].
+    #[must_use]
+    pub fn add_prelude(mut self, code: impl Into<String>) -> Self {
+        self.preludes.push(PreludeSource::Inline(code.into()));
+        self
+    }
+
+    /// Import Python modules while the template initializes.
+    ///
+    /// The imported modules are captured in the compiled template, so the
+    /// first call in each sandbox does not pay their import cost. Imports
+    /// run at the end of the last [prelude](Self::add_prelude), so they can
+    /// load modules the preludes make importable, and do not count as a
+    /// prelude of their own. Each call adds to the list. Building fails if a
+    /// name is not a dotted module path or a module cannot be imported; a
+    /// failed import is reported as a failure of the last prelude.
+    #[must_use]
+    pub fn warmup_imports<I, S>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.warmup_imports
+            .extend(modules.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add a host file whose contents run as a prelude after the preludes
+    /// added before it.
+    ///
+    /// The file is read when the template is built. Errors from its code
+    /// name it by `path`.
+    #[must_use]
+    pub fn prelude_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.preludes.push(PreludeSource::File(path.into()));
+        self
+    }
+
+    /// Use Wasmtime's pooling instance allocator for sandboxes from this
+    /// template.
+    ///
+    /// `None` (the default) allocates instance resources on demand. See
+    /// [`PoolingConfig`] for the trade-offs.
+    #[must_use]
+    pub const fn pooling(mut self, pooling: Option<PoolingConfig>) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Set the Wasmtime compiler settings.
+    ///
+    /// See [`EngineConfig`] for the available settings and their defaults.
+    #[must_use]
+    pub const fn engine_config(mut self, engine: EngineConfig) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Set the number of threads used to compile the runtime component.
+    ///
+    /// Compilation only happens when no cached artifact is available. `None`
+    /// (the default) compiles in parallel on all available cores. `Some(1)`
+    /// compiles on a single thread; larger values cap the compiler thread
+    /// count. The count is ignored when
+    /// [`EngineConfig::parallel_compilation`] is disabled, which always
+    /// compiles on a single thread.
+    #[must_use]
+    pub const fn compile_threads(mut self, threads: Option<usize>) -> Self {
+        self.compile_threads = threads;
+        self
+    }
+
+    /// Share the template's initialized guest heap copy-on-write.
+    ///
+    /// When enabled (the default), each sandbox maps the snapshot taken at
+    /// build time instead of copying it, so pages a guest only reads stay
+    /// shared across every sandbox from this template. This lowers
+    /// per-sandbox RSS and instantiation time. Sharing is most effective when
+    /// the template is loaded from [`cache`](Self::cache), since the cached
+    /// artifact is mapped directly from disk.
+    ///
+    /// Disable it to copy the heap eagerly into each sandbox, for example on
+    /// platforms where memory mappings are expensive. Windows always copies.
+    #[must_use]
+    pub const fn copy_on_write(mut self, enabled: bool) -> Self {
+        self.eager_memory_init = !enabled;
+        self
+    }
+
+    /// Set the stack space available to guest WebAssembly frames, in bytes.
+    ///
+    /// Raise it for deeply recursive workloads that trap with a stack
+    /// overflow; lower it to reserve less host memory per running sandbox.
+    /// `None` (the default) keeps Wasmtime's 512 KiB limit. The limit is
+    /// part of the engine configuration and therefore applies to every
+    /// sandbox from this template.
+    ///
+    /// This does not change the runtime's own shadow stack in linear memory,
+    /// which is fixed when the runtime component is linked.
+    #[must_use]
+    pub const fn max_stack_size(mut self, max_stack: Option<usize>) -> Self {
+        self.max_stack = max_stack;
+        self
     }
-}
 
-/// Collected output from
-/// [`Sandbox::call`](crate::sandbox::Sandbox::call).
-#[derive(Debug, Default)]
-pub struct CallOutput {
-    /// Values yielded or explicitly emitted by the guest, in emission order.
-    pub items: Vec<Value>,
-    /// Final guest return value, or `None` when no final value was encoded.
+    /// Run guest code in Wasmtime's Pulley interpreter instead of as native
+    /// machine code.
     ///
-    /// A guest-language `None` or `null` is an encoded CBOR value and is
-    /// therefore represented as `Some(Value)`.
-    pub result: Option<Value>,
-}
+    /// The runtime component is compiled to portable bytecode, so no memory
+    /// is ever mapped executable. Use this on platforms that forbid JIT
+    /// pages, such as iOS. Guest code runs several times slower than with
+    /// native compilation. Interpreter and native builds of the same
+    /// template are cached separately.
+    ///
+    /// Defaults to `false`. Requires the `pulley` feature.
+    #[cfg(feature = "pulley")]
+    #[must_use]
+    pub const fn interpreter(mut self, enabled: bool) -> Self {
+        self.interpreter = enabled;
+        self
+    }
 
-impl SandboxTemplateBuilder {
-    /// Set the optional component cache directory.
+    /// Count the WebAssembly instructions guest code executes.
     ///
-    /// When set, compiled artifacts are cached on disk and reused across
-    /// builds.
+    /// Metering lets [`SandboxOptions::max_fuel`] and
+    /// [`CallOptions::max_fuel`] bound each call by the work it does rather
+    /// than by wall-clock time, which gives the same limit on every machine,
+    /// and reports the fuel each call consumed in
+    /// [`CallOutput::fuel_consumed`] and [`Sandbox::last_fuel_consumed`].
+    /// Epoch-based interruption keeps working alongside it. Metered code runs
+    /// somewhat slower and is cached separately.
+    ///
+    /// Defaults to `false`.
     #[must_use]
-    pub fn cache(mut self, cache: Option<std::path::PathBuf>) -> Self {
-        self.cache = cache;
+    pub const fn fuel_metering(mut self, enabled: bool) -> Self {
+        self.fuel_metering = enabled;
         self
     }
 
-    /// Set the per-sandbox memory hard limit.
+    /// Allow sandboxes from this template to be captured with
+    /// [`Sandbox::snapshot`] and cloned with [`SandboxTemplate::restore`].
     ///
-    /// Defaults to unlimited (`usize::MAX`).
+    /// Snapshots read the guest's memory and globals through accessors
+    /// compiled into the runtime component, and the template is not
+    /// preinitialized at build time. Each sandbox instantiated from it
+    /// therefore initializes the guest and runs the
+    /// [preludes](Self::add_prelude) itself, which is much slower than a
+    /// regular instantiation. The intended use is to prepare one sandbox,
+    /// snapshot it, and restore request-scoped copies from the snapshot.
+    ///
+    /// Defaults to `false`. Snapshot-enabled artifacts are cached separately.
     #[must_use]
-    pub const fn max_memory(mut self, max_memory: usize) -> Self {
-        self.base_options.max_memory = Some(max_memory);
+    pub const fn snapshots(mut self, enabled: bool) -> Self {
+        self.snapshots = enabled;
         self
     }
 
-    /// Set base directory mappings shared by all sandboxes from this template.
+    /// Run guest exports as native component-model async tasks.
     ///
-    /// These mappings can be extended or overridden per instantiation via
-    /// [`SandboxOptions::mount`](crate::sandbox::SandboxOptions::mount).
+    /// By default each eval or call is awaited as a single export invocation.
+    /// When enabled, the export is driven by wasmtime's concurrent event loop
+    /// instead, so guest `asyncio` tasks and in-flight host futures (hostcalls
+    /// and HTTP requests) are polled together. Epoch-based yielding still
+    /// applies, so timeouts and cancellation behave the same in both modes.
     ///
-    /// This API matches the WASI-style preopen configuration shape.
+    /// Defaults to `false`.
     #[must_use]
-    pub fn mount(
-        mut self,
-        host_path: impl AsRef<Path>,
-        guest_path: impl AsRef<str>,
-        dir_perms: DirPerms,
-        file_perms: FilePerms,
-    ) -> Self {
-        self.base_options = self
-            .base_options
-            .mount(host_path, guest_path, dir_perms, file_perms);
+    pub const fn native_async(mut self, native_async: bool) -> Self {
+        self.native_async = native_async;
         self
     }
 
-    /// Add an environment variable that will be present in sandbox WASI env.
+    /// Withhold a WASI interface from every sandbox instantiated from this
+    /// template.
     ///
-    /// If the same key is set multiple times, the last value wins.
+    /// Use this to shrink the attack surface of minimal sandboxes. Guest code
+    /// that touches a disabled interface gets an error (or, for clocks, a
+    /// fixed time) instead of reaching the host. Template initialization is
+    /// not affected.
     #[must_use]
-    pub fn env(mut self, k: impl AsRef<str>, v: impl AsRef<str>) -> Self {
-        self.base_options = self.base_options.env(k, v);
+    pub fn disable_wasi(mut self, interface: WasiInterface) -> Self {
+        if !self.disabled_wasi.contains(&interface) {
+            self.disabled_wasi.push(interface);
+        }
         self
     }
 
-    /// Set optional guest prelude code executed during template initialization.
+    /// Link a plugin component into every sandbox built from this template.
     ///
-    /// Prelude state is captured in the compiled template and is therefore
-    /// present in every sandbox instantiated from it. `None` disables the
-    /// prelude.
+    /// `wasm` must be a component exporting the `isola:script/plugin-handler`
+    /// interface. Guest hostcalls named `"{name}.{call}"` are handled by the
+    /// plugin's `handle` export with `call` as the call type instead of
+    /// reaching [`Host::hostcall`](crate::host::Host::hostcall). Each sandbox
+    /// gets its own plugin instance, created on first use, with no filesystem,
+    /// environment, or network access and the sandbox memory limit.
+    ///
+    /// Plugins are compiled and verified against the
+    /// [`trust_policy`](Self::trust_policy) together with the runtime.
     #[must_use]
-    pub fn prelude(mut self, prelude: Option<String>) -> Self {
-        self.prelude = prelude;
+    pub fn plugin(mut self, name: impl Into<String>, wasm: impl Into<PathBuf>) -> Self {
+        self.plugins.push((name.into(), wasm.into()));
+        self
+    }
+
+    /// Add host definitions to the linker of every sandbox instantiated with
+    /// host type `H`.
+    ///
+    /// Custom runtimes use this to import interfaces beyond the
+    /// `isola:script` world, typically by calling an `add_to_linker`
+    /// function generated by [`wasmtime::component::bindgen!`]. Host
+    /// functions reach the sandbox's host through
+    /// [`SandboxState::host_handle`]. Extensions run in registration order
+    /// after the crate's own definitions; sandboxes of other host types do
+    /// not see them.
+    ///
+    /// Imports the crate does not know are no longer reported as
+    /// [`Error::IncompatibleWorld`] once an extension is registered. While
+    /// the template is initialized they trap, and instantiation fails if no
+    /// extension defines them.
+    #[must_use]
+    pub fn with_linker<H: Host>(
+        mut self,
+        extend: impl Fn(&mut Linker<SandboxState<H>>) -> wasmtime::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.linker_extensions.push(LinkerExtension::new(extend));
         self
     }
 
@@ -364,39 +2193,370 @@ impl SandboxTemplateBuilder {
     ///
     /// # Errors
     ///
-    /// Returns an error if the path cannot be resolved or read, a mount cannot
-    /// be opened, the component is incompatible, initialization fails, or a
-    /// compiled artifact cannot be cached.
+    /// Returns an error if the path cannot be resolved or read, the component
+    /// is incompatible, initialization fails, or a compiled artifact cannot
+    /// be cached. A mounted host path that is not a readable directory fails
+    /// with [`Error::InvalidMount`].
     pub async fn build(self, wasm: impl AsRef<Path>) -> Result<SandboxTemplate> {
-        let wasm_path = std::fs::canonicalize(wasm.as_ref()).map_err(Error::from)?;
-        let base_options = self.base_options;
+        self.build_with(WasmSource::Path(wasm.as_ref())).await
+    }
+
+    /// Compile and initialize a reusable template from runtime component
+    /// bytes already in memory.
+    ///
+    /// Use this when the runtime is embedded in the host binary or fetched
+    /// from object storage, instead of writing it to a temporary file for
+    /// [`build`](Self::build). Caching behaves the same, since cache keys are
+    /// derived from the component bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`build`](Self::build). A configured
+    /// [`trust_policy`](Self::trust_policy) always rejects in-memory bytes
+    /// because there is no detached signature file to verify against.
+    pub async fn build_from_bytes(self, wasm: &[u8]) -> Result<SandboxTemplate> {
+        self.build_with(WasmSource::Bytes(wasm)).await
+    }
+
+    /// Compile and initialize a reusable template from any [`TemplateSource`].
+    ///
+    /// Behaves like [`build`](Self::build) or
+    /// [`build_from_bytes`](Self::build_from_bytes) for local sources. URL
+    /// sources are downloaded, checked against their pinned SHA-256 digest,
+    /// and kept in the [`cache`](Self::cache) directory so later builds do not
+    /// download them again.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`build`](Self::build), and an error of kind
+    /// [`ErrorKind::Io`] if a download fails or its digest does not match.
+    pub async fn build_from_source(self, source: TemplateSource) -> Result<SandboxTemplate> {
+        let wasm = match &source {
+            TemplateSource::Path(path) => WasmSource::Path(path),
+            TemplateSource::Bytes(bytes) => WasmSource::Bytes(bytes),
+            #[cfg(feature = "remote-template")]
+            TemplateSource::Url { url, sha256 } => WasmSource::Url { url, sha256 },
+        };
+        self.build_with(wasm).await
+    }
+
+    /// Load a template exported with [`SandboxTemplate::serialize`] without
+    /// compiling or initializing the runtime again.
+    ///
+    /// Use this to compile templates on a build machine and ship the artifact
+    /// to production hosts. The artifact only loads into the same Isola and
+    /// Wasmtime version on the same target, built with the same settings that
+    /// shape the engine, such as [`pooling`](Self::pooling),
+    /// [`max_memory`](Self::max_memory), [`max_stack_size`](Self::max_stack_size),
+    /// [`copy_on_write`](Self::copy_on_write),
+    /// [`engine_config`](Self::engine_config) and
+    /// [`fuel_metering`](Self::fuel_metering). Guest state prepared by the
+    /// preludes and the mounts used during initialization come from the
+    /// artifact; [preludes](Self::add_prelude) and cache settings are
+    /// ignored. Plugins are compiled from this builder's configuration as
+    /// usual.
+    ///
+    /// A configured [`trust_policy`](Self::trust_policy) verifies the
+    /// artifact's detached signature like a runtime component's.
+    ///
+    /// # Safety
+    ///
+    /// The artifact is native code that is loaded without being validated.
+    /// Its header only records the engine it was compiled for and can be
+    /// forged, so a crafted file can run arbitrary code in the host process.
+    /// The caller must ensure the file was produced by
+    /// [`SandboxTemplate::serialize`] and has not been tampered with since,
+    /// either because it comes from a trusted source or because a configured
+    /// [`trust_policy`](Self::trust_policy) verifies its signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::Io`] if the file cannot be read,
+    /// is not an exported template, or was compiled for an incompatible
+    /// engine, and an error if [`snapshots`](Self::snapshots) is enabled,
+    /// which exported templates do not support.
+    pub async unsafe fn build_precompiled(self, path: impl AsRef<Path>) -> Result<SandboxTemplate> {
+        if self.snapshots {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "precompiled templates cannot take snapshots",
+            )));
+        }
+        self.build_with(WasmSource::Precompiled(path.as_ref()))
+            .await
+    }
+
+    /// Set the per-call fuel limit for every sandbox.
+    ///
+    /// Enables [`fuel_metering`](Self::fuel_metering). See
+    /// [`SandboxOptions::max_fuel`].
+    #[must_use]
+    pub const fn max_fuel(mut self, max_fuel: u64) -> Self {
+        self.base_options.max_fuel = Some(max_fuel);
+        self.fuel_metering = true;
+        self
+    }
+
+    /// Set how often guest code is interrupted to check deadlines and yield.
+    ///
+    /// Shorter ticks make timeouts, interrupts and yields to the async
+    /// executor more precise; longer ticks lower the overhead on
+    /// compute-heavy guests. Combine with
+    /// [`SandboxOptions::epoch_yield_ticks`] to yield less often than every
+    /// tick. Defaults to 10 ms.
+    ///
+    /// Building fails if `tick` is zero.
+    #[must_use]
+    pub const fn epoch_tick(mut self, tick: Duration) -> Self {
+        self.epoch_tick = Some(tick);
+        self
+    }
+
+    /// Compiler thread count after
+    /// [`EngineConfig::parallel_compilation`] is taken into account.
+    const fn effective_compile_threads(&self) -> Option<usize> {
+        if self.engine.parallel_compilation {
+            self.compile_threads
+        } else {
+            Some(1)
+        }
+    }
+
+    /// Create the engine a template with module configuration `cfg` is
+    /// compiled for.
+    fn build_engine(&self, cfg: &InternalModuleConfig) -> Result<Engine> {
+        let mut engine_cfg = wasmtime::Config::default();
+        configure_engine(&mut engine_cfg, self.engine);
+        configure_compile_threads(&mut engine_cfg, cfg.compile_threads);
+        configure_memory_init(&mut engine_cfg, cfg.copy_on_write, cfg.max_memory);
+        configure_stack(&mut engine_cfg, self.max_stack);
+        #[cfg(feature = "pulley")]
+        if self.interpreter {
+            configure_interpreter(&mut engine_cfg).map_err(Error::Wasm)?;
+        }
+        if let Some(pooling) = &self.pooling {
+            configure_pooling(&mut engine_cfg, pooling);
+        }
+        engine_cfg.consume_fuel(self.fuel_metering);
+        Engine::new(&engine_cfg).map_err(Error::Wasm)
+    }
+
+    /// Read the preludes to run while compiling `wasm`, with warmup imports
+    /// appended to the last one. Precompiled artifacts already hold the
+    /// state their preludes built.
+    async fn load_preludes(&self, wasm: &WasmSource<'_>) -> Result<Vec<Prelude>> {
+        if matches!(wasm, WasmSource::Precompiled(_)) {
+            return Ok(Vec::new());
+        }
+        let mut preludes = load_preludes(&self.preludes).await?;
+        if !self.warmup_imports.is_empty() {
+            let warmup = warmup_prelude(&self.warmup_imports)?;
+            match preludes.last_mut() {
+                Some(last) => {
+                    last.code.push('\n');
+                    last.code.push_str(&warmup.code);
+                }
+                None => preludes.push(warmup),
+            }
+        }
+        Ok(preludes)
+    }
+
+    async fn build_with(mut self, wasm: WasmSource<'_>) -> Result<SandboxTemplate> {
+        if self.epoch_tick.is_some_and(|tick| tick.is_zero()) {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "epoch tick must be greater than zero",
+            )));
+        }
+        let wasm_bytes = wasm
+            .load(self.trust_policy.as_deref(), self.cache.as_deref())
+            .await?;
+        if let Some(namespace) = &self.namespace {
+            namespace.validate()?;
+        }
+        let preludes = self.load_preludes(&wasm).await?;
+        let mut base_options = std::mem::take(&mut self.base_options);
+        #[cfg(feature = "archive")]
+        unpack_archives(
+            &mut base_options.directory_mappings,
+            base_options
+                .archive_limits
+                .unwrap_or(DEFAULT_ARCHIVE_MOUNT_LIMITS),
+        )
+        .await?;
+        create_scratch_dirs(&mut base_options.directory_mappings)?;
+        validate_mounts(&base_options.directory_mappings).await?;
+        let max_memory = base_options.max_memory.unwrap_or(usize::MAX);
         let cfg = InternalModuleConfig {
-            cache: self.cache.clone(),
-            max_memory: base_options.max_memory.unwrap_or(usize::MAX),
+            cache: self.cache.clone().map(|cache| match &self.namespace {
+                Some(namespace) => cache.join(namespace.name()),
+                None => cache,
+            }),
+            cache_max_size: self.cache_max_size,
+            cache_max_age: self.cache_max_age,
+            max_memory: self
+                .namespace
+                .as_ref()
+                .map_or(max_memory, |namespace| namespace.clamp_memory(max_memory)),
             directory_mappings: base_options.directory_mappings.clone(),
             env: base_options.env.clone(),
-            prelude: self.prelude.clone(),
+            preludes: preludes.clone(),
+            compile_threads: self.effective_compile_threads(),
+            copy_on_write: !self.eager_memory_init,
+            snapshots: self.snapshots,
+            namespace: self.namespace.as_ref().map(|n| n.name().to_string()),
+            linker_extensions: !self.linker_extensions.is_empty(),
         };
 
-        let mut engine_cfg = wasmtime::Config::default();
-        configure_engine(&mut engine_cfg);
-        let engine = Engine::new(&engine_cfg).map_err(Error::Wasm)?;
+        let engine = self.build_engine(&cfg)?;
 
-        let component =
-            load_or_compile_component(&engine, &wasm_path, &cfg.directory_mappings, &cfg).await?;
+        let plugins = compile_plugins(&engine, self.plugins, self.trust_policy.as_deref()).await?;
+        let compile_start = Instant::now();
+        let (component, cache_status, metadata) = if matches!(wasm, WasmSource::Precompiled(_)) {
+            (
+                // SAFETY: only `build_precompiled` loads precompiled sources,
+                // and its caller vouches for the artifact.
+                unsafe { precompiled::decode(&engine, &wasm_bytes) }?,
+                CacheStatus::Precompiled,
+                None,
+            )
+        } else {
+            let metadata = read_runtime_metadata(&wasm_bytes)?;
+            let (component, cache_status) = load_or_compile_component(
+                &engine,
+                &wasm_bytes,
+                &cfg.directory_mappings,
+                &cfg,
+                self.cache_backend.as_deref(),
+            )
+            .await?;
+            (component, cache_status, metadata)
+        };
+        let world = check_world(&engine, &component, cfg.linker_extensions)?;
+        let counters = TemplateCounters::new(compile_start.elapsed(), cache_status);
         Engine::tls_eager_initialize();
-        let ticker = global_epoch_ticker()
-            .map_err(Error::from)?
-            .register(engine.clone());
+        let ticker = global_epoch_ticker().map_err(Error::from)?.register(
+            engine.clone(),
+            self.epoch_tick.unwrap_or(DEFAULT_EPOCH_TICK),
+        );
 
         Ok(SandboxTemplate {
             base_options,
             engine,
             component,
             ticker,
+            native_async: self.native_async,
+            disabled_wasi: self.disabled_wasi,
+            plugins,
+            namespace: self.namespace,
             pre_instances: Mutex::new(HashMap::new()),
+            counters,
+            snapshot_source: self.snapshots.then(|| {
+                Arc::new(SnapshotSource {
+                    wasm: wasm_bytes.into_owned(),
+                    preludes,
+                })
+            }),
+            fuel_metering: self.fuel_metering,
+            metadata,
+            world,
+            linker_extensions: self.linker_extensions.into(),
+        })
+    }
+}
+
+/// Check that `workdir` names a directory inside a mount, creating it when the
+/// mount is writable.
+async fn prepare_workdir(options: &SandboxOptions, workdir: &str) -> Result<()> {
+    if !workdir.starts_with('/') {
+        return Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("working directory '{workdir}' must be an absolute guest path"),
+        )));
+    }
+    let resolved = guest_files::resolve(&options.directory_mappings, workdir)?;
+    guest_files::ensure_dir(&resolved, resolved.writable && !options.read_only).await?;
+    Ok(())
+}
+
+/// Where a template's runtime component comes from.
+enum WasmSource<'a> {
+    Path(&'a Path),
+    Bytes(&'a [u8]),
+    /// Template exported with [`SandboxTemplate::serialize`].
+    Precompiled(&'a Path),
+    #[cfg(feature = "remote-template")]
+    Url {
+        url: &'a str,
+        sha256: &'a str,
+    },
+}
+
+impl WasmSource<'_> {
+    /// Read the component and check it against `trust_policy`.
+    async fn load(
+        &self,
+        trust_policy: Option<&dyn TrustPolicy>,
+        cache: Option<&Path>,
+    ) -> Result<Cow<'_, [u8]>> {
+        #[cfg(not(feature = "remote-template"))]
+        let _ = cache;
+        match *self {
+            Self::Path(path) | Self::Precompiled(path) => {
+                let path = std::fs::canonicalize(path).map_err(Error::from)?;
+                let bytes = tokio::fs::read(&path).await.map_err(Error::from)?;
+                if let Some(policy) = trust_policy {
+                    verify_artifact(policy, &path, &bytes).await?;
+                }
+                Ok(Cow::Owned(bytes))
+            }
+            Self::Bytes(bytes) => {
+                if trust_policy.is_some() {
+                    return Err(Error::Io(std::io::Error::new(
+                        std::io::ErrorKind::PermissionDenied,
+                        "untrusted runtime artifact: in-memory components have no signature to \
+                         verify",
+                    )));
+                }
+                Ok(Cow::Borrowed(bytes))
+            }
+            #[cfg(feature = "remote-template")]
+            Self::Url { url, sha256 } => template_source::fetch(url, sha256, cache, trust_policy)
+                .await
+                .map(Cow::Owned),
+        }
+    }
+}
+
+async fn compile_plugins(
+    engine: &Engine,
+    plugins: Vec<(String, PathBuf)>,
+    trust_policy: Option<&dyn TrustPolicy>,
+) -> Result<Vec<Arc<PluginTemplate>>> {
+    let mut compiled: Vec<Arc<PluginTemplate>> = Vec::with_capacity(plugins.len());
+    for (name, path) in plugins {
+        if name.is_empty() || compiled.iter().any(|p| p.name() == name) {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("plugin name '{name}' is empty or already registered"),
+            )));
+        }
+        let bytes = tokio::fs::read(&path).await.map_err(Error::from)?;
+        if let Some(policy) = trust_policy {
+            verify_artifact(policy, &path, &bytes).await?;
+        }
+        let engine = engine.clone();
+        let plugin = tokio::task::spawn_blocking(move || {
+            let component = Component::new(&engine, &bytes)?;
+            PluginTemplate::new(&engine, name, &component)
         })
+        .await
+        .map_err(|e| Error::Other(e.into()))?
+        .map_err(Error::Wasm)?;
+        compiled.push(Arc::new(plugin));
     }
+    Ok(compiled)
 }
 
 impl SandboxTemplate {
@@ -406,6 +2566,64 @@ impl SandboxTemplate {
         SandboxTemplateBuilder::default()
     }
 
+    /// Return the namespace this template was built in, if any.
+    #[must_use]
+    pub const fn namespace(&self) -> Option<&Namespace> {
+        self.namespace.as_ref()
+    }
+
+    /// Return the description the runtime bundle carries of itself, read
+    /// while the template was built.
+    ///
+    /// Check [`RuntimeMetadata::is_compatible`] before instantiating a
+    /// bundle of unknown origin. `None` for bundles built without metadata
+    /// and for templates loaded with
+    /// [`build_precompiled`](SandboxTemplateBuilder::build_precompiled).
+    #[must_use]
+    pub const fn metadata(&self) -> Option<&RuntimeMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Return the optional host interfaces the runtime component uses.
+    #[must_use]
+    pub const fn world(&self) -> ScriptWorld {
+        self.world
+    }
+
+    /// Write this template's compiled runtime to `path` so it can be loaded
+    /// with [`SandboxTemplateBuilder::build_precompiled`] without compiling
+    /// it again.
+    ///
+    /// The artifact records a fingerprint of the engine configuration, which
+    /// loading checks. It contains native code for this host's target and
+    /// should be treated like an executable; sign it when production hosts
+    /// use a [`trust_policy`](SandboxTemplateBuilder::trust_policy).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template was built with
+    /// [`snapshots`](SandboxTemplateBuilder::snapshots) or the file cannot be
+    /// written.
+    pub async fn serialize(&self, path: impl AsRef<Path>) -> Result<()> {
+        if self.snapshot_source.is_some() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "templates built with snapshots cannot be exported",
+            )));
+        }
+        let bytes = precompiled::encode(&self.engine, &self.component)?;
+        tokio::fs::write(path, bytes).await.map_err(Error::from)
+    }
+
+    /// Return build and instantiation metrics for this template.
+    ///
+    /// Only successful instantiations are counted; a sandbox stays live until
+    /// it is dropped.
+    #[must_use]
+    pub fn stats(&self) -> TemplateStats {
+        self.counters.snapshot()
+    }
+
     /// Create a new sandbox instance from this compiled template.
     ///
     /// Each sandbox has isolated mutable guest state. Per-sandbox
@@ -413,33 +2631,76 @@ impl SandboxTemplate {
     /// [`SandboxTemplateBuilder`].
     ///
     /// # Errors
-    /// Returns an error if instantiation fails.
+    /// Returns an error if instantiation fails, or [`Error::InvalidMount`] if
+    /// a mounted host path is not a readable directory.
     pub async fn instantiate<H: Host>(
         &self,
         host: H,
         options: SandboxOptions,
     ) -> Result<Sandbox<H>> {
-        let ticker = Arc::clone(&self.ticker);
-        let merged = self.base_options.merged_with_owned(options);
-
-        let mut store = InstanceState::new(
-            &self.engine,
-            &merged.directory_mappings,
-            &merged.env,
-            merged.max_memory.unwrap_or(usize::MAX),
+        self.instantiate_component(
+            &self.component,
+            &self.pre_instances,
+            self.snapshot_source.clone(),
             host,
+            options,
         )
-        .map_err(Error::Wasm)?;
-        store.epoch_deadline_async_yield_and_update(1);
+        .await
+    }
+
+    /// Instantiate `component`, which must be compiled for this template's
+    /// engine, linking it once per host type through `pre_instances`.
+    async fn instantiate_component<H: Host>(
+        &self,
+        component: &Component,
+        pre_instances: &Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+        source: Option<Arc<SnapshotSource>>,
+        host: H,
+        options: SandboxOptions,
+    ) -> Result<Sandbox<H>> {
+        let start = Instant::now();
+        let namespace_slot = self
+            .namespace
+            .as_ref()
+            .map(Namespace::acquire)
+            .transpose()?;
+        let ticker = Arc::clone(&self.ticker);
+        let mut merged = self.base_options.merged_with_owned(options);
+        if merged.max_fuel.is_some() && !self.fuel_metering {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "max_fuel requires a template built with fuel metering",
+            )));
+        }
+        let max_memory = merged.max_memory.unwrap_or(usize::MAX);
+        let max_memory = self
+            .namespace
+            .as_ref()
+            .map_or(max_memory, |namespace| namespace.clamp_memory(max_memory));
+        merged.max_memory = Some(max_memory);
+        #[cfg(feature = "archive")]
+        unpack_archives(
+            &mut merged.directory_mappings,
+            merged
+                .archive_limits
+                .unwrap_or(DEFAULT_ARCHIVE_MOUNT_LIMITS),
+        )
+        .await?;
+        create_scratch_dirs(&mut merged.directory_mappings)?;
+        validate_mounts(&merged.directory_mappings).await?;
+        if let Some(workdir) = &merged.workdir {
+            prepare_workdir(&merged, workdir).await?;
+        }
 
         let pre = {
-            let mut cached = self.pre_instances.lock();
+            let mut cached = pre_instances.lock();
             let host_type = TypeId::of::<H>();
             if let std::collections::hash_map::Entry::Vacant(entry) = cached.entry(host_type) {
-                let linker = InstanceState::<H>::new_linker(&self.engine).map_err(Error::Wasm)?;
-                let pre = linker
-                    .instantiate_pre(&self.component)
+                let mut linker =
+                    InstanceState::<H>::new_linker(&self.engine).map_err(Error::Wasm)?;
+                LinkerExtension::apply(&self.linker_extensions, &mut linker)
                     .map_err(Error::Wasm)?;
+                let pre = linker.instantiate_pre(component).map_err(Error::Wasm)?;
                 entry.insert(Box::new(pre));
             }
             cached
@@ -455,87 +2716,335 @@ impl SandboxTemplate {
                 })?
                 .clone()
         };
-        let bindings = SandboxPre::new(pre)
-            .map_err(Error::Wasm)?
-            .instantiate_async(&mut store)
-            .await
-            .map_err(Error::Wasm)?;
+        let origin = Arc::new(SandboxOrigin {
+            pre: SandboxPre::new(pre).map_err(|missing| Error::IncompatibleWorld { missing })?,
+            options: merged,
+            disabled_wasi: self.disabled_wasi.clone(),
+            plugins: self.plugins.clone(),
+            source,
+            linker_extensions: Arc::clone(&self.linker_extensions),
+        });
+        let (store, instance, bindings) = origin.instantiate(&self.engine, Arc::new(host)).await?;
+        let call_trace = store.data().call_trace();
 
         Ok(Sandbox {
             store,
+            instance,
             bindings,
             _ticker: ticker,
+            native_async: self.native_async,
+            origin,
+            _namespace_slot: namespace_slot,
+            _live: self.counters.record_instantiation(start.elapsed()),
+            call_trace,
+            interrupt: Arc::default(),
+            fresh_copy: None,
+        })
+    }
+}
+
+impl<H: Host> Sandbox<H> {
+    /// Evaluate source code in this sandbox's persistent guest scope.
+    ///
+    /// Guest logs and any explicitly emitted values are delivered to `target`.
+    /// Definitions created by the script remain available to later calls on
+    /// this sandbox.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the guest rejects or fails while evaluating the
+    /// script, output delivery fails, or the WebAssembly runtime traps.
+    pub async fn eval_script(
+        &mut self,
+        code: impl AsRef<str>,
+        target: impl Into<OutputTarget>,
+    ) -> Result<()> {
+        self.eval_script_impl(code.as_ref(), target.into(), CallOptions::default())
+            .await
+    }
+
+    /// Evaluate source code with per-call [`CallOptions`].
+    ///
+    /// Behaves like [`Sandbox::eval_script`] while applying the options'
+    /// capabilities, fuel limit and deadline to the evaluation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error as [`Sandbox::eval_script`] does, or if the options
+    /// request [`coverage`](CallOptions::coverage), which is only collected
+    /// for calls.
+    pub async fn eval_script_with_options(
+        &mut self,
+        code: impl AsRef<str>,
+        target: impl Into<OutputTarget>,
+        options: CallOptions,
+    ) -> Result<()> {
+        if options.coverage {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "coverage is only collected for calls",
+            )));
+        }
+        self.eval_script_impl(code.as_ref(), target.into(), options)
+            .await
+    }
+
+    async fn eval_script_impl(
+        &mut self,
+        code: &str,
+        target: OutputTarget,
+        options: CallOptions,
+    ) -> Result<()> {
+        let mut store = CallCleanup::new(&mut self.store);
+        store.set_fuel_budget(options.max_fuel.or(self.origin.options.max_fuel))?;
+        store.set_capabilities(options.capabilities);
+        store.set_output_limits(options.max_output_bytes, options.max_output_items);
+        store.set_interrupts(options.deadline, Arc::clone(&self.interrupt));
+        store.set_output_target(target);
+        let func = self.bindings.runtime.func_eval_script();
+        let result = call_export(
+            &mut store,
+            func,
+            (code.to_string(),),
+            self.native_async,
+            options.deadline,
+            &self.interrupt,
+        )
+        .await;
+        let flush_result = store.data_mut().flush_logs().await.map_err(Error::Wasm);
+        if let Err(e) = result.map_err(|e| store.classify_error(e))?.0 {
+            return Err(self.bindings.guest_error(&mut store, e).await);
+        }
+        flush_result?;
+        Ok(())
+    }
+
+    /// Evaluate an expression in the persistent guest scope and return its
+    /// value.
+    ///
+    /// The expression can use every definition made by earlier evaluations,
+    /// such as `eval_expr("1 + len(x)")` after `x` was defined with
+    /// [`eval_script`](Self::eval_script). Awaitable results are awaited.
+    /// Guest logs are discarded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expression is invalid or raises, its value
+    /// cannot be serialized, it evaluates to an iterator rather than a single
+    /// value, or the WebAssembly runtime traps, and
+    /// [`Error::IncompatibleWorld`] if the runtime lacks
+    /// [`expressions`](ScriptWorld::expressions).
+    pub async fn eval_expr(&mut self, expr: &str) -> Result<Value> {
+        let Some(exports) = &self.bindings.expressions else {
+            return Err(missing_export("expressions"));
+        };
+        let output = Arc::new(Mutex::new(CallOutput::default()));
+        let mut store = CallCleanup::new(&mut self.store);
+        store.set_fuel_budget(self.origin.options.max_fuel)?;
+        store.set_interrupts(None, Arc::clone(&self.interrupt));
+        store.set_output_target(OutputTarget::capture(Arc::clone(&output)));
+        let func = exports.func_eval_expr();
+        let result = call_export(
+            &mut store,
+            func,
+            (expr.to_string(),),
+            self.native_async,
+            None,
+            &self.interrupt,
+        )
+        .await;
+        let flush_result = store.data_mut().flush_logs().await.map_err(Error::Wasm);
+        if let Err(e) = result.map_err(|e| store.classify_error(e))?.0 {
+            return Err(self.bindings.guest_error(&mut store, e).await);
+        }
+        flush_result?;
+        drop(store);
+
+        let result = output.lock().result.take();
+        result.ok_or_else(|| {
+            Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "expression evaluated to an iterator, not a single value",
+            ))
         })
     }
-}
 
-impl<H: Host> Sandbox<H> {
-    /// Evaluate source code in this sandbox's persistent guest scope.
+    /// Evaluate a file using its exact guest-visible path string.
+    ///
+    /// The file must be visible through a mount configured on the template or
+    /// this sandbox instance. Definitions created by the file remain available
+    /// to later calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be loaded by the guest, evaluation
+    /// fails, output delivery fails, or the WebAssembly runtime traps.
+    pub async fn eval_file(
+        &mut self,
+        guest_path: &str,
+        target: impl Into<OutputTarget>,
+    ) -> Result<()> {
+        self.eval_file_impl(guest_path, target.into()).await
+    }
+
+    async fn eval_file_impl(&mut self, guest_path: &str, target: OutputTarget) -> Result<()> {
+        let mut store = CallCleanup::new(&mut self.store);
+        store.set_fuel_budget(self.origin.options.max_fuel)?;
+        store.set_interrupts(None, Arc::clone(&self.interrupt));
+        store.set_output_target(target);
+        let func = self.bindings.runtime.func_eval_file();
+        let result = call_export(
+            &mut store,
+            func,
+            (guest_path.to_string(),),
+            self.native_async,
+            None,
+            &self.interrupt,
+        )
+        .await;
+        let flush_result = store.data_mut().flush_logs().await.map_err(Error::Wasm);
+        if let Err(e) = result.map_err(|e| store.classify_error(e))?.0 {
+            return Err(self.bindings.guest_error(&mut store, e).await);
+        }
+        flush_result?;
+        Ok(())
+    }
+
+    /// Evaluate a multi-file project from a directory the guest can see.
     ///
-    /// Guest logs and any explicitly emitted values are delivered to `target`.
-    /// Definitions created by the script remain available to later calls on
-    /// this sandbox.
+    /// The Python runtime imports `guest_dir` as a regular package: its parent
+    /// directory is put on `sys.path` and its `__init__.py` runs with normal
+    /// import semantics, so modules inside it can import each other. The
+    /// package and its public names, or those listed in `__all__`, are then
+    /// bound in the persistent guest scope. A package that was already
+    /// imported is not executed again.
+    ///
+    /// The JavaScript runtime evaluates every `.js` and `.ts` file below
+    /// `guest_dir` in path order, finishing with its top-level `index.js` or
+    /// `index.ts`.
     ///
     /// # Errors
     ///
-    /// Returns an error if the guest rejects or fails while evaluating the
-    /// script, output delivery fails, or the WebAssembly runtime traps.
-    pub async fn eval_script(
+    /// Returns an error if `guest_dir` is not a package, a file fails to load
+    /// or evaluate, output delivery fails, or the WebAssembly runtime traps,
+    /// and [`Error::IncompatibleWorld`] if the runtime lacks
+    /// [`packages`](ScriptWorld::packages).
+    pub async fn eval_package(
         &mut self,
-        code: impl AsRef<str>,
+        guest_dir: &str,
         target: impl Into<OutputTarget>,
     ) -> Result<()> {
-        self.eval_script_impl(code.as_ref(), target.into()).await
+        self.eval_package_impl(guest_dir, target.into()).await
     }
 
-    async fn eval_script_impl(&mut self, code: &str, target: OutputTarget) -> Result<()> {
+    async fn eval_package_impl(&mut self, guest_dir: &str, target: OutputTarget) -> Result<()> {
+        let Some(exports) = &self.bindings.packages else {
+            return Err(missing_export("packages"));
+        };
         let mut store = CallCleanup::new(&mut self.store);
+        store.set_fuel_budget(self.origin.options.max_fuel)?;
+        store.set_interrupts(None, Arc::clone(&self.interrupt));
         store.set_output_target(target);
-        let result = self
-            .bindings
-            .isola_script_runtime()
-            .func_eval_script()
-            .call_async(&mut store, (code.to_string(),))
-            .await;
+        let func = exports.func_eval_package();
+        let result = call_export(
+            &mut store,
+            func,
+            (guest_dir.to_string(),),
+            self.native_async,
+            None,
+            &self.interrupt,
+        )
+        .await;
         let flush_result = store.data_mut().flush_logs().await.map_err(Error::Wasm);
-        result.map_err(Error::Wasm)?.0?;
+        if let Err(e) = result.map_err(|e| store.classify_error(e))?.0 {
+            return Err(self.bindings.guest_error(&mut store, e).await);
+        }
         flush_result?;
         Ok(())
     }
 
-    /// Evaluate a file using its exact guest-visible path string.
+    /// Load source code as a named module that later scripts and modules can
+    /// import.
     ///
-    /// The file must be visible through a mount configured on the template or
-    /// this sandbox instance. Definitions created by the file remain available
-    /// to later calls.
+    /// Unlike [`eval_script`](Self::eval_script), the code runs in its own
+    /// module namespace rather than the persistent guest scope; only the
+    /// module itself is bound there under `name`. Load dependencies before
+    /// the modules that import them.
+    ///
+    /// The Python runtime registers the code as the top-level module `name`,
+    /// so `import name` works from any later code. Loading `name` again
+    /// replaces the module for later imports; if the new code fails, the
+    /// previous module is kept.
+    ///
+    /// The JavaScript runtime evaluates the code as an ES module that later
+    /// modules can `import` from `name`. A name can only be loaded once.
     ///
     /// # Errors
     ///
-    /// Returns an error if the file cannot be loaded by the guest, evaluation
-    /// fails, output delivery fails, or the WebAssembly runtime traps.
-    pub async fn eval_file(
+    /// Returns an error if `name` is not a valid module name or cannot be
+    /// loaded again, evaluating the code fails, output delivery fails, or the
+    /// WebAssembly runtime traps, and [`Error::IncompatibleWorld`] if the
+    /// runtime lacks [`modules`](ScriptWorld::modules).
+    pub async fn load_module(
         &mut self,
-        guest_path: &str,
+        name: &str,
+        code: impl AsRef<str>,
         target: impl Into<OutputTarget>,
     ) -> Result<()> {
-        self.eval_file_impl(guest_path, target.into()).await
-    }
-
-    async fn eval_file_impl(&mut self, guest_path: &str, target: OutputTarget) -> Result<()> {
+        let Some(exports) = &self.bindings.modules else {
+            return Err(missing_export("modules"));
+        };
         let mut store = CallCleanup::new(&mut self.store);
-        store.set_output_target(target);
-        let result = self
-            .bindings
-            .isola_script_runtime()
-            .func_eval_file()
-            .call_async(&mut store, (guest_path.to_string(),))
-            .await;
+        store.set_fuel_budget(self.origin.options.max_fuel)?;
+        store.set_interrupts(None, Arc::clone(&self.interrupt));
+        store.set_output_target(target.into());
+        let func = exports.func_load_module();
+        let result = call_export(
+            &mut store,
+            func,
+            (name.to_string(), code.as_ref().to_string()),
+            self.native_async,
+            None,
+            &self.interrupt,
+        )
+        .await;
         let flush_result = store.data_mut().flush_logs().await.map_err(Error::Wasm);
-        result.map_err(Error::Wasm)?.0?;
+        if let Err(e) = result.map_err(|e| store.classify_error(e))?.0 {
+            return Err(self.bindings.guest_error(&mut store, e).await);
+        }
         flush_result?;
         Ok(())
     }
 
+    /// Evaluate the files below `guest_dir` whose paths relative to it match
+    /// the glob `pattern`, one after another in sorted path order.
+    ///
+    /// In `pattern`, `*` and `?` never match `/` while `**` matches any number
+    /// of directories, so `"**/*.py"` selects every Python file at any depth.
+    /// Each file is evaluated as by [`eval_file`](Self::eval_file) and
+    /// evaluation stops at the first failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `guest_dir` cannot be listed as by
+    /// [`list_guest_files`](Self::list_guest_files), `pattern` is not a valid
+    /// glob, or evaluating any matching file fails.
+    pub async fn eval_files(
+        &mut self,
+        guest_dir: &str,
+        pattern: &str,
+        target: impl Into<OutputTarget>,
+    ) -> Result<()> {
+        let target = target.into();
+        let resolved = guest_files::resolve(&self.directory_mappings(), guest_dir)?;
+        let files = guest_files::list_files(&resolved).await?;
+        let files = guest_files::filter_glob(files, &resolved.guest, pattern)?;
+        for file in files {
+            self.eval_file_impl(&file, target.clone()).await?;
+        }
+        Ok(())
+    }
+
     /// Call a guest function and deliver output incrementally to a target.
     ///
     /// Values yielded or explicitly emitted by the guest are delivered as item
@@ -554,7 +3063,37 @@ impl<H: Host> Sandbox<H> {
     where
         I: IntoIterator<Item = Arg>,
     {
-        self.call_impl(function, args, target.into()).await
+        self.call_impl(function, args, target.into(), CallOptions::default())
+            .await?;
+        Ok(())
+    }
+
+    /// Call a guest function with per-call [`CallOptions`].
+    ///
+    /// Output is delivered to `target` as with [`Sandbox::call_with_sink`].
+    /// When the options carry a [`CapabilitySet`], hostcalls, HTTP requests,
+    /// and filesystem writes outside that set are rejected for the duration of
+    /// this call.
+    /// A [`deadline`](CallOptions::deadline) stops the call, guest code
+    /// included, once it passes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the function is missing, guest execution fails,
+    /// output delivery fails, or the WebAssembly runtime traps.
+    pub async fn call_with_options<I>(
+        &mut self,
+        function: &str,
+        args: I,
+        target: impl Into<OutputTarget>,
+        options: CallOptions,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = Arg>,
+    {
+        self.call_impl(function, args, target.into(), options)
+            .await?;
+        Ok(())
     }
 
     /// Call a guest function and collect emitted items/final result.
@@ -567,69 +3106,343 @@ impl<H: Host> Sandbox<H> {
     /// Returns an error if the function is missing, guest execution fails, or
     /// the WebAssembly runtime traps.
     pub async fn call<I>(&mut self, function: &str, args: I) -> Result<CallOutput>
+    where
+        I: IntoIterator<Item = Arg>,
+    {
+        self.call_collect(function, args, CallOptions::default())
+            .await
+    }
+
+    /// Call a guest function with per-call [`CallOptions`] and collect its
+    /// output.
+    ///
+    /// This is the collecting counterpart to [`Sandbox::call_with_options`].
+    /// It is also the way to receive the report of a call made with
+    /// [`CallOptions::coverage`], which is returned in
+    /// [`CallOutput::coverage`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the function is missing, guest execution fails, a
+    /// capability check fails, coverage is not supported by the guest runtime,
+    /// or the WebAssembly runtime traps.
+    pub async fn call_collect<I>(
+        &mut self,
+        function: &str,
+        args: I,
+        options: CallOptions,
+    ) -> Result<CallOutput>
     where
         I: IntoIterator<Item = Arg>,
     {
         let output = Arc::new(Mutex::new(CallOutput::default()));
         let target = OutputTarget::capture(output.clone());
-        self.call_impl(function, args, target).await?;
+        let coverage = self.call_impl(function, args, target, options).await?;
+        Ok(self.collected_output(&output, coverage))
+    }
+
+    /// Take the output a call captured and attach its coverage and usage.
+    pub(crate) fn collected_output(
+        &self,
+        output: &Mutex<CallOutput>,
+        coverage: Option<CoverageReport>,
+    ) -> CallOutput {
+        let mut output = std::mem::take(&mut *output.lock());
+        output.coverage = coverage;
+        output.fuel_consumed = self.last_fuel_consumed();
+        output.exec_stats = self.last_exec_stats().unwrap_or_default();
+        output
+    }
 
-        let mut output = output.lock();
-        Ok(std::mem::take(&mut output))
+    async fn call_impl<I>(
+        &mut self,
+        function: &str,
+        args: I,
+        target: OutputTarget,
+        options: CallOptions,
+    ) -> Result<Option<CoverageReport>>
+    where
+        I: IntoIterator<Item = Arg>,
+    {
+        self.call_guest(function, args, target, options).await?
     }
 
-    async fn call_impl<I>(&mut self, function: &str, args: I, target: OutputTarget) -> Result<()>
+    /// Call `function` through `call-func`. The outer error means the call
+    /// could not run to completion, the inner one that the guest reported a
+    /// failure.
+    pub(crate) async fn call_guest<I>(
+        &mut self,
+        function: &str,
+        args: I,
+        target: OutputTarget,
+        options: CallOptions,
+    ) -> Result<Result<Option<CoverageReport>>>
     where
         I: IntoIterator<Item = Arg>,
     {
+        if options.fresh_globals {
+            return Box::pin(self.call_fresh(function, args, target, options)).await;
+        }
+        let coverage = if options.coverage {
+            let exports = self.bindings.coverage.as_ref();
+            Some(exports.ok_or_else(|| missing_export("coverage"))?)
+        } else {
+            None
+        };
         let mut store = CallCleanup::new(&mut self.store);
-        let internal_args = args
-            .into_iter()
-            .map(|arg| match arg {
-                Arg::Positional(value) => Ok(RawArgument {
-                    name: None,
-                    value: WasmValue::Cbor(value.into_cbor().into()),
-                }),
-                Arg::Named(name, value) => Ok(RawArgument {
-                    name: Some(name),
-                    value: WasmValue::Cbor(value.into_cbor().into()),
-                }),
-                Arg::PositionalStream(stream_arg) => {
-                    let iter = store
-                        .data_mut()
-                        .table()
-                        .push(ValueIterator::new(stream_arg))
-                        .map_err(|e| Error::Other(e.into()))?;
-                    Ok(RawArgument {
-                        name: None,
-                        value: WasmValue::CborIterator(iter),
-                    })
-                }
-                Arg::NamedStream(name, stream_arg) => {
-                    let iter = store
-                        .data_mut()
-                        .table()
-                        .push(ValueIterator::new(stream_arg))
-                        .map_err(|e| Error::Other(e.into()))?;
-                    Ok(RawArgument {
-                        name: Some(name),
-                        value: WasmValue::CborIterator(iter),
-                    })
-                }
-            })
-            .collect::<Result<Vec<RawArgument>>>()?;
+        let internal_args = lower_args(&mut store, args)?;
 
+        store.set_fuel_budget(options.max_fuel.or(self.origin.options.max_fuel))?;
+        store.set_capabilities(options.capabilities);
+        store.set_output_limits(options.max_output_bytes, options.max_output_items);
+        store.set_interrupts(options.deadline, Arc::clone(&self.interrupt));
         store.set_output_target(target);
-        let result = self
-            .bindings
-            .isola_script_runtime()
-            .func_call_func()
-            .call_async(&mut store, (function.to_string(), internal_args))
-            .await;
+        let params = (function.to_string(), internal_args);
+        let result = if let Some(exports) = &coverage {
+            call_export(
+                &mut store,
+                exports.func_call_func(),
+                params,
+                self.native_async,
+                options.deadline,
+                &self.interrupt,
+            )
+            .await
+            .map(|(result,)| result.map(|files| Some(CoverageReport::from(files))))
+        } else {
+            call_export(
+                &mut store,
+                self.bindings.runtime.func_call_func(),
+                params,
+                self.native_async,
+                options.deadline,
+                &self.interrupt,
+            )
+            .await
+            .map(|(result,)| result.map(|()| None))
+        };
         let flush_result = store.data_mut().flush_logs().await.map_err(Error::Wasm);
-        result.map_err(Error::Wasm)?.0?;
+        let result = result.map_err(|e| store.classify_error(e))?;
         flush_result?;
-        Ok(())
+        Ok(match result {
+            Ok(coverage) => Ok(coverage),
+            Err(e) => Err(self.bindings.guest_error(&mut store, e).await),
+        })
+    }
+
+    /// List the files below a guest directory, such as output a script wrote
+    /// to a writable mount.
+    ///
+    /// `guest_dir` may be a mount point or any directory inside one. Returns
+    /// the guest paths of regular files at any depth, sorted; symbolic links
+    /// are skipped. Files are read from the host directory backing the mount,
+    /// so this also works while no guest code is running.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no mount contains `guest_dir`, the path contains
+    /// `..` or resolves outside its mount, or the directory cannot be read.
+    pub fn list_guest_files(
+        &self,
+        guest_dir: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send + 'static {
+        let resolved = guest_files::resolve(&self.directory_mappings(), guest_dir);
+        async move { Ok(guest_files::list_files(&resolved?).await?) }
+    }
+
+    /// Read a file the guest can see, such as output a script wrote to a
+    /// writable mount.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no mount contains `guest_path`, the path contains
+    /// `..` or resolves outside its mount, or the file cannot be read.
+    pub fn read_guest_file(
+        &self,
+        guest_path: &str,
+    ) -> impl Future<Output = Result<Vec<u8>>> + Send + 'static {
+        let resolved = guest_files::resolve(&self.directory_mappings(), guest_path);
+        async move { Ok(guest_files::read_file(&resolved?).await?) }
+    }
+
+    /// Write a file the guest can see, such as input for a script, to a
+    /// writable mount.
+    ///
+    /// Missing parent directories are created and an existing file is
+    /// replaced. The file goes to the host directory backing the mount, or to
+    /// the top layer of an overlay mount, so hosts can hand inputs to guest
+    /// code without sharing a host directory with it. Host writes count
+    /// against the mount's [`FsQuota`], so the guest cannot reclaim their
+    /// space by deleting them, but not against
+    /// [`max_write_bytes`](SandboxOptions::max_write_bytes). The file is
+    /// opened without following symbolic links at any component.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no writable mount contains `guest_path`, the
+    /// sandbox is [read-only](SandboxOptions::read_only), the path contains
+    /// `..` or resolves outside its mount, it names a directory or symbolic
+    /// link, the mount's quota would be exceeded, or the file cannot be
+    /// written.
+    pub fn write_guest_file(
+        &self,
+        guest_path: &str,
+        contents: impl Into<Vec<u8>>,
+    ) -> impl Future<Output = Result<()>> + Send + 'static {
+        let resolved = self.resolve_for_host_write(guest_path);
+        let contents = contents.into();
+        async move { Ok(guest_files::write_file(&resolved?, contents).await?) }
+    }
+
+    /// Unpack a tar or zip archive into a writable guest directory, such as a
+    /// multi-file project uploaded by a user.
+    ///
+    /// `guest_dir` and any missing parents are created. Only regular files
+    /// are extracted, replacing existing files of the same name; symbolic
+    /// links and entries that would land outside `guest_dir` are skipped.
+    /// Returns the guest paths of the extracted files, sorted.
+    ///
+    /// The archive is unpacked into a staging directory inside the mount and
+    /// only moved into `guest_dir` once all of it unpacked, so a failed
+    /// extraction leaves `guest_dir` as it was. Unpacking is bounded by
+    /// [`archive_limits`](SandboxOptions::archive_limits) and by what the
+    /// mount's [`FsQuota`] and
+    /// [`max_write_bytes`](SandboxOptions::max_write_bytes) have left, and
+    /// the extracted files are charged to both.
+    ///
+    /// Available with the `archive` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no writable mount contains `guest_dir`, the
+    /// sandbox is [read-only](SandboxOptions::read_only), the path contains
+    /// `..` or resolves outside its mount, the archive is invalid or exceeds
+    /// a limit, or a file cannot be written, for example because the guest
+    /// left a directory or symbolic link in its place.
+    #[cfg(feature = "archive")]
+    pub fn extract_archive(
+        &self,
+        guest_dir: &str,
+        archive: impl Into<bytes::Bytes>,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send + 'static {
+        let resolved = self.resolve_for_host_write(guest_dir);
+        let archive = archive.into();
+        let limits = self.origin.options.archive_limits.unwrap_or_default();
+        let budget = self.store.data().write_budget();
+        async move { Ok(extract_into(&resolved?, archive, limits, budget).await?) }
+    }
+
+    fn resolve_for_host_write(
+        &self,
+        guest_path: &str,
+    ) -> std::io::Result<guest_files::ResolvedPath> {
+        let mut resolved = guest_files::resolve(&self.directory_mappings(), guest_path)?;
+        resolved.writable &= !self.origin.options.read_only;
+        resolved.usage = self.store.data().mount_usage(&resolved.mount);
+        Ok(resolved)
+    }
+
+    /// Return what the guest wrote below an overlay mount that
+    /// [captures writes](OverlayMount::capture_writes), as guest paths with
+    /// their contents.
+    ///
+    /// `guest_dir` may be the mount point or any directory inside it. Only
+    /// files the guest created or modified are returned, sorted by path;
+    /// files it merely read from a read-only layer are not.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no capturing mount contains `guest_dir`, the path
+    /// contains `..`, or the captured files cannot be read.
+    pub fn captured_writes(
+        &self,
+        guest_dir: &str,
+    ) -> impl Future<Output = Result<Vec<(String, Vec<u8>)>>> + Send + 'static {
+        let mappings: Vec<_> = self
+            .directory_mappings()
+            .into_iter()
+            .filter(|mapping| mapping.scratch)
+            .collect();
+        let guest_dir = guest_dir.to_string();
+        async move {
+            let resolve = |guest_path: &str| {
+                guest_files::resolve(&mappings, guest_path).map(|mut resolved| {
+                    resolved.lower.clear();
+                    resolved
+                })
+            };
+            let mut writes = Vec::new();
+            for file in guest_files::list_files(&resolve(&guest_dir)?).await? {
+                let contents = guest_files::read_file(&resolve(&file)?).await?;
+                writes.push((file, contents));
+            }
+            Ok(writes)
+        }
+    }
+
+    /// Return the hostcalls and HTTP requests recorded by
+    /// [`SandboxOptions::trace_hostcalls`], oldest first.
+    ///
+    /// Call this after an operation fails to see what the guest asked the
+    /// host to do leading up to the failure. The dump is empty when tracing
+    /// is disabled.
+    #[must_use]
+    pub fn debug_dump(&self) -> DebugDump {
+        DebugDump {
+            last_call_id: self.last_call_id(),
+            entries: self
+                .call_trace
+                .as_ref()
+                .map(|trace| trace.snapshot())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Return a handle that aborts this sandbox's running operation from
+    /// another task or thread.
+    ///
+    /// Use it to stop guest code when, for example, the user presses Ctrl-C
+    /// or a client disconnects. See [`InterruptHandle::interrupt`].
+    #[must_use]
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle::new(Arc::clone(&self.interrupt), self.store.engine().clone())
+    }
+
+    /// Return the call id of the most recent `eval_*` or `call*` operation.
+    ///
+    /// Use this to correlate an error returned by that operation with the
+    /// [`EventMeta::call_id`](crate::host::EventMeta::call_id) of its output
+    /// and with [`current_call_id`](crate::host::current_call_id) observed by
+    /// its hostcalls. `None` before the first operation.
+    #[must_use]
+    pub fn last_call_id(&self) -> Option<u64> {
+        self.store.data().last_call_id()
+    }
+
+    /// Return the fuel consumed by the most recent `eval_*` or `call*`
+    /// operation, including one that failed or ran out of fuel.
+    ///
+    /// `None` before the first operation and when the template was built
+    /// without [`fuel_metering`](SandboxTemplateBuilder::fuel_metering).
+    #[must_use]
+    pub fn last_fuel_consumed(&self) -> Option<u64> {
+        self.store.data().last_fuel_consumed()
+    }
+
+    /// Return the [`idle_timeout`](SandboxOptions::idle_timeout) this sandbox
+    /// was instantiated with.
+    #[must_use]
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.origin.options.idle_timeout
+    }
+
+    /// Return the wall, guest and host time of the most recent `eval_*` or
+    /// `call*` operation, including one that failed.
+    ///
+    /// `None` before the first operation.
+    #[must_use]
+    pub fn last_exec_stats(&self) -> Option<ExecStats> {
+        self.store.data().last_exec_stats()
     }
 
     /// Return the current guest WebAssembly linear-memory allocation in bytes.
@@ -640,26 +3453,343 @@ impl<H: Host> Sandbox<H> {
     pub fn memory_usage(&self) -> usize {
         self.store.data().limiter.current()
     }
+
+    /// Return detailed guest memory statistics.
+    ///
+    /// Counters cover the sandbox since it was instantiated or last
+    /// [`reset`](Self::reset).
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.store.data().limiter.stats()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn kwargs_from_expands_map_entries() {
+        let kwargs = Arg::kwargs_from(&serde_json::json!({
+            "limit": 10,
+            "query": {"text": "hi", "tags": ["a"]},
+        }))
+        .expect("kwargs");
+        let kwargs: Vec<_> = kwargs
+            .into_iter()
+            .map(|arg| match arg {
+                Arg::Named(name, value) => (name, value.to_json().expect("json")),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            kwargs,
+            [
+                ("limit".to_string(), "10".to_string()),
+                (
+                    "query".to_string(),
+                    r#"{"tags":["a"],"text":"hi"}"#.to_string()
+                ),
+            ]
+        );
+
+        assert!(matches!(
+            Arg::kwargs_from(&[1, 2]),
+            Err(crate::value::Error::NotAMap)
+        ));
+        assert!(matches!(
+            Arg::kwargs_from(&std::collections::HashMap::from([(1, 2)])),
+            Err(crate::value::Error::NotAMap)
+        ));
+    }
+
+    #[test]
+    fn overlay_mounts_put_the_highest_layer_on_top() {
+        let overlay = OverlayMount::new("/base").layer("/tenant");
+        let options = SandboxOptions::default()
+            .mount_overlay(overlay.clone(), "/lib")
+            .mount_overlay(overlay.writable("/scratch"), "/data");
+
+        let read_only = &options.directory_mappings[0];
+        assert_eq!(read_only.host, Path::new("/tenant"));
+        assert_eq!(read_only.lower, [PathBuf::from("/base")]);
+        assert_eq!(read_only.file_perms, FilePerms::READ);
+
+        let writable = &options.directory_mappings[1];
+        assert_eq!(writable.host, Path::new("/scratch"));
+        assert_eq!(
+            writable.lower,
+            [PathBuf::from("/tenant"), PathBuf::from("/base")]
+        );
+        assert_eq!(writable.file_perms, FilePerms::all());
+    }
+
+    #[tokio::test]
+    async fn workdir_is_created_in_writable_mounts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let options = SandboxOptions::default()
+            .workdir("/data/a")
+            .merged_with(&SandboxOptions::default().workdir("/data/a/b"));
+        assert_eq!(options.workdir.as_deref(), Some("/data/a/b"));
+
+        let read_only = options
+            .clone()
+            .mount(dir.path(), "/data", DirPerms::READ, FilePerms::READ);
+        let err = prepare_workdir(&read_only, "/data/a/b")
+            .await
+            .expect_err("missing");
+        assert!(matches!(err, Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+
+        let writable = options.mount(dir.path(), "/data", DirPerms::all(), FilePerms::all());
+        prepare_workdir(&writable, "/data/a/b")
+            .await
+            .expect("created");
+        assert!(dir.path().join("a/b").is_dir());
+        assert!(prepare_workdir(&writable, "data").await.is_err());
+        assert!(
+            prepare_workdir(&writable.read_only(), "/data/c")
+                .await
+                .is_err()
+        );
+    }
+
     #[test]
     fn sandbox_configuration_is_fluent() {
         let options = SandboxOptions::default()
             .max_memory(1024)
             .mount("/host", "/guest", DirPerms::READ, FilePerms::READ)
+            .mount_with_quota(
+                "/scratch",
+                "/tmp",
+                DirPerms::all(),
+                FilePerms::all(),
+                FsQuota::default().max_bytes(Some(4096)),
+            )
             .env("KEY", "value");
 
         assert_eq!(options.max_memory, Some(1024));
-        assert_eq!(options.directory_mappings.len(), 1);
+        assert_eq!(options.directory_mappings.len(), 2);
+        assert_eq!(options.directory_mappings[0].quota, None);
+        assert_eq!(
+            options.directory_mappings[1].quota,
+            Some(FsQuota::default().max_bytes(Some(4096)))
+        );
         assert_eq!(options.env, [("KEY".to_string(), "value".to_string())]);
+        assert!(!options.read_only);
+        assert!(
+            options
+                .merged_with(&SandboxOptions::default().read_only())
+                .read_only
+        );
+        assert!(options.clone().read_only().merged_with(&options).read_only);
+        let stdio = options.merged_with(
+            &SandboxOptions::default()
+                .stdio_buffering(StdioBuffering::Line)
+                .max_output_line_length(256),
+        );
+        assert_eq!(stdio.stdio_buffering, Some(StdioBuffering::Line));
+        assert_eq!(stdio.max_output_line_length, Some(256));
+        let fuel = options
+            .clone()
+            .max_fuel(1_000)
+            .merged_with(&SandboxOptions::default().max_fuel(10));
+        assert_eq!(fuel.max_fuel, Some(10));
+        assert_eq!(fuel.merged_with(&options).max_fuel, Some(10));
+        assert_eq!(
+            stdio
+                .merged_with(&SandboxOptions::default())
+                .stdio_buffering,
+            Some(StdioBuffering::Line)
+        );
 
-        let _builder = SandboxTemplate::builder()
+        let builder = SandboxTemplate::builder()
             .max_memory(1024)
             .mount("/host", "/guest", DirPerms::READ, FilePerms::READ)
-            .env("KEY", "value");
+            .env("KEY", "value")
+            .compile_threads(Some(4))
+            .native_async(true)
+            .max_stack_size(Some(4 << 20))
+            .disable_wasi(WasiInterface::Clocks)
+            .disable_wasi(WasiInterface::Clocks)
+            .plugin("kv", "/plugins/kv.wasm")
+            .namespace(Some(Namespace::new("tenant-a").max_sandboxes(Some(4))))
+            .copy_on_write(false)
+            .snapshots(true)
+            .max_fuel(1_000)
+            .cache_max_size(Some(1 << 30))
+            .cache_max_age(Some(Duration::from_secs(86_400)))
+            .engine_config(
+                EngineConfig::default()
+                    .strategy(CompilerStrategy::Winch)
+                    .debug_info(true),
+            )
+            .pooling(Some(
+                PoolingConfig::default()
+                    .max_instances(8)
+                    .max_memory_pages(16)
+                    .table_elements(64),
+            ));
+        assert_eq!(builder.compile_threads, Some(4));
+        assert_eq!(builder.effective_compile_threads(), Some(4));
+        assert!(builder.native_async);
+        assert_eq!(builder.engine.strategy, CompilerStrategy::Winch);
+        assert!(builder.engine.debug_info);
+        assert_eq!(builder.max_stack, Some(4 << 20));
+        assert_eq!(builder.disabled_wasi, [WasiInterface::Clocks]);
+        assert_eq!(
+            builder.namespace.as_ref().map(Namespace::name),
+            Some("tenant-a")
+        );
+        assert_eq!(
+            builder.plugins,
+            [("kv".to_string(), PathBuf::from("/plugins/kv.wasm"))]
+        );
+        assert!(builder.eager_memory_init);
+        assert!(builder.snapshots);
+        assert!(builder.fuel_metering);
+        assert_eq!(builder.base_options.max_fuel, Some(1_000));
+        assert_eq!(builder.cache_max_size, Some(1 << 30));
+        assert_eq!(builder.cache_max_age, Some(Duration::from_secs(86_400)));
+        let pooling = builder.pooling.expect("pooling configured");
+        assert_eq!(pooling.max_instances, 8);
+        assert_eq!(pooling.max_memory_pages, 16);
+        assert_eq!(pooling.table_elements, 64);
+    }
+
+    #[test]
+    fn disabled_parallel_compilation_overrides_compile_threads() {
+        let serial = EngineConfig::default().parallel_compilation(false);
+        for threads in [None, Some(4)] {
+            let builder = SandboxTemplate::builder()
+                .compile_threads(threads)
+                .engine_config(serial);
+            assert_eq!(builder.effective_compile_threads(), Some(1));
+        }
+        let builder = SandboxTemplate::builder().compile_threads(None);
+        assert_eq!(builder.effective_compile_threads(), None);
+    }
+
+    #[tokio::test]
+    async fn warmup_imports_run_at_the_end_of_the_last_prelude() {
+        use crate::internal::module::prelude::prelude_code;
+
+        let wasm = WasmSource::Bytes(b"");
+        let builder = SandboxTemplate::builder()
+            .prelude(None)
+            .warmup_imports(["json"]);
+        let preludes = builder.load_preludes(&wasm).await.expect("preludes");
+        assert_eq!(prelude_code(&preludes), ["import json\n"]);
+
+        let builder = builder.add_prelude("import sys").add_prelude("x = 1");
+        let preludes = builder.load_preludes(&wasm).await.expect("preludes");
+        assert_eq!(
+            prelude_code(&preludes),
+            ["import sys", "x = 1\nimport json\n"]
+        );
+    }
+
+    #[tokio::test]
+    async fn plugins_require_a_name_and_a_readable_component() {
+        let engine = Engine::default();
+        let unnamed = compile_plugins(&engine, vec![(String::new(), "/missing.wasm".into())], None)
+            .await
+            .err()
+            .expect("empty name rejected");
+        assert!(unnamed.to_string().contains("plugin name"));
+
+        let missing = compile_plugins(&engine, vec![("kv".into(), "/missing.wasm".into())], None)
+            .await
+            .err()
+            .expect("missing component rejected");
+        assert_eq!(missing.kind(), ErrorKind::Io);
+    }
+
+    #[tokio::test]
+    async fn in_memory_components_are_untrusted_under_a_policy() {
+        struct AcceptAll;
+
+        impl TrustPolicy for AcceptAll {
+            fn verify(
+                &self,
+                _artifact: &[u8],
+                _signature: &[u8],
+            ) -> core::result::Result<(), crate::host::BoxError> {
+                Ok(())
+            }
+        }
+
+        let wasm = b"\0asm";
+        let source = WasmSource::Bytes(wasm);
+        let loaded = source.load(None, None).await.expect("loaded");
+        assert!(matches!(loaded, Cow::Borrowed(bytes) if bytes == wasm));
+
+        let rejected = SandboxTemplate::builder()
+            .trust_policy(Some(Arc::new(AcceptAll)))
+            .build_from_bytes(wasm)
+            .await
+            .err()
+            .expect("in-memory bytes rejected");
+        assert_eq!(rejected.kind(), ErrorKind::PolicyDenied);
+        assert!(rejected.to_string().contains("untrusted runtime artifact"));
+    }
+
+    #[test]
+    fn errors_expose_stable_kinds() {
+        let guest = Error::from(exports::Error {
+            code: exports::ErrorCode::Aborted,
+            message: "boom".to_string(),
+        });
+        assert_eq!(guest.kind(), ErrorKind::GuestException);
+        assert_eq!(guest.guest_message(), Some("boom"));
+        assert_eq!(guest.exception_type(), None);
+
+        let interrupted = Error::Wasm(wasmtime::Error::from(wasmtime::Trap::Interrupt));
+        assert_eq!(interrupted.kind(), ErrorKind::Timeout);
+        assert_eq!(interrupted.guest_message(), None);
+
+        let overflow = Error::Wasm(wasmtime::Error::from(wasmtime::Trap::StackOverflow));
+        assert_eq!(overflow.kind(), ErrorKind::EngineTrap);
+
+        let handles = Error::Wasm(
+            wasmtime::Error::from(ResourceTableError::Full).context("failed to open file"),
+        );
+        assert_eq!(handles.kind(), ErrorKind::PolicyDenied);
+
+        let memory = Error::Wasm(
+            wasmtime::Error::from(wasmtime::Trap::UnreachableCodeReached)
+                .context(Classified(ErrorKind::MemoryLimit)),
+        );
+        assert_eq!(memory.kind(), ErrorKind::MemoryLimit);
+        assert!(memory.to_string().contains("memory limit"));
+        assert_eq!(memory.memory_diagnostics(), None);
+
+        let diagnostics = MemoryDiagnostics {
+            limit: 4096,
+            peak: 4096,
+            last_growth: Some((2048, 4096)),
+            denied_request: Some(8192),
+        };
+        let diagnosed = Error::Wasm(
+            wasmtime::Error::msg("MemoryError")
+                .context(diagnostics)
+                .context(Classified(ErrorKind::MemoryLimit)),
+        );
+        assert_eq!(diagnosed.kind(), ErrorKind::MemoryLimit);
+        assert_eq!(diagnosed.memory_diagnostics(), Some(&diagnostics));
+        assert_eq!(
+            diagnostics.to_string(),
+            "peak usage 4096 of 4096 bytes, last growth 2048 -> 4096 bytes, denied growth to 8192 bytes"
+        );
+
+        let denied = Error::Io(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert_eq!(denied.kind(), ErrorKind::PolicyDenied);
+        assert_eq!(ErrorKind::PolicyDenied.as_str(), "policy_denied");
+
+        assert_eq!(Error::QueueFull(4).kind(), ErrorKind::PolicyDenied);
+        assert_eq!(
+            Error::IdleDisposed(Duration::from_secs(1)).kind(),
+            ErrorKind::PolicyDenied
+        );
     }
 }
@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use wasmtime_wasi::{DirPerms, FilePerms};
+
+use super::{DirectoryMapping, Error, Result, Sandbox, WasiInterface, missing_export};
+use crate::host::Host;
+
+impl<H: Host> Sandbox<H> {
+    /// Mount a host directory into this running sandbox.
+    ///
+    /// The mount takes effect for the next `eval_*` or `call*` operation and
+    /// replaces any mount at the same guest path, including one configured
+    /// at instantiation. Unlike
+    /// [`SandboxOptions::mount`](super::SandboxOptions::mount), this does
+    /// not need a new sandbox, so a host can attach per-request data to a
+    /// long-lived one. [`read_only`](super::SandboxOptions::read_only)
+    /// sandboxes only get read access.
+    ///
+    /// Files the guest holds open are closed when the mounts change.
+    /// [`Sandbox::reset`] returns to the mounts the sandbox was instantiated
+    /// with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WASI filesystem interface is disabled, the
+    /// guest fails to reload its directories, [`Error::InvalidMount`] if
+    /// `host_path` is not a readable directory, or
+    /// [`Error::IncompatibleWorld`] if the runtime lacks
+    /// [`preopens`](super::ScriptWorld::preopens).
+    pub async fn mount(
+        &mut self,
+        host_path: impl AsRef<Path>,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+    ) -> Result<()> {
+        self.check_preopens()?;
+        if self
+            .origin
+            .disabled_wasi
+            .contains(&WasiInterface::Filesystem)
+        {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "cannot mount directories with the WASI filesystem interface disabled",
+            )));
+        }
+        let mapping = DirectoryMapping::new(host_path.as_ref(), guest_path.as_ref())
+            .with_permissions(dir_perms, file_perms);
+        validate_mounts(std::slice::from_ref(&mapping)).await?;
+        let read_only = self.origin.options.read_only;
+        self.store
+            .data_mut()
+            .live_mounts_mut()
+            .mount(mapping, read_only)
+            .map_err(Error::Io)?;
+        self.reload_preopens().await
+    }
+
+    /// Remove the mount at `guest_path` from this running sandbox.
+    ///
+    /// Both mounts configured at instantiation and those added with
+    /// [`mount`](Self::mount) can be removed. The change takes effect as for
+    /// [`mount`](Self::mount).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if nothing is mounted at `guest_path`, the guest
+    /// fails to reload its directories, or [`Error::IncompatibleWorld`] if
+    /// the runtime lacks [`preopens`](super::ScriptWorld::preopens).
+    pub async fn unmount(&mut self, guest_path: &str) -> Result<()> {
+        self.check_preopens()?;
+        let initial = self
+            .origin
+            .options
+            .directory_mappings
+            .iter()
+            .any(|mapping| mapping.guest == guest_path);
+        if !self
+            .store
+            .data_mut()
+            .live_mounts_mut()
+            .unmount(guest_path, initial)
+        {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("nothing is mounted at '{guest_path}'"),
+            )));
+        }
+        self.reload_preopens().await
+    }
+
+    /// Return the directories currently mounted in this sandbox.
+    pub(crate) fn directory_mappings(&self) -> Vec<DirectoryMapping> {
+        self.store
+            .data()
+            .live_mounts()
+            .apply(&self.origin.options.directory_mappings)
+    }
+
+    /// Fail unless the runtime can re-read its directories after a mount
+    /// change.
+    fn check_preopens(&self) -> Result<()> {
+        if self.bindings.preopens.is_none() {
+            return Err(missing_export("preopens"));
+        }
+        Ok(())
+    }
+
+    async fn reload_preopens(&mut self) -> Result<()> {
+        let Some(preopens) = &self.bindings.preopens else {
+            return Err(missing_export("preopens"));
+        };
+        preopens
+            .call_reload_preopens(&mut self.store)
+            .await
+            .map_err(|e| self.store.data().classify_error(e))
+    }
+}
+
+/// Check that every host directory behind `mappings` exists and can be
+/// listed, so a bad mount fails up front instead of at the guest's first
+/// file access.
+pub async fn validate_mounts(mappings: &[DirectoryMapping]) -> Result<()> {
+    for mapping in mappings {
+        for host in std::iter::once(&mapping.host).chain(&mapping.lower) {
+            check_mount_dir(host)
+                .await
+                .map_err(|cause| Error::InvalidMount {
+                    host: host.clone(),
+                    guest: mapping.guest.clone(),
+                    cause,
+                })?;
+        }
+    }
+    Ok(())
+}
+
+async fn check_mount_dir(path: &Path) -> std::io::Result<()> {
+    if !tokio::fs::metadata(path).await?.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotADirectory,
+            "not a directory",
+        ));
+    }
+    tokio::fs::read_dir(path).await.map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalid_mounts_name_the_host_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, "x").unwrap();
+        let missing = dir.path().join("missing");
+
+        validate_mounts(&[DirectoryMapping::new(dir.path(), "/data")])
+            .await
+            .unwrap();
+        for (host, kind) in [
+            (file, std::io::ErrorKind::NotADirectory),
+            (missing, std::io::ErrorKind::NotFound),
+        ] {
+            let err = validate_mounts(&[DirectoryMapping::new(&host, "/data")])
+                .await
+                .unwrap_err();
+            let Error::InvalidMount {
+                host: reported,
+                guest,
+                cause,
+            } = err
+            else {
+                panic!("expected an invalid mount, got {err:?}");
+            };
+            assert_eq!(reported, host);
+            assert_eq!(guest, "/data");
+            assert_eq!(cause.kind(), kind);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use crate::sandbox::{Error, Result};
+
+/// Tenant boundary shared by a group of templates.
+///
+/// Templates built with the same namespace via
+/// [`SandboxTemplateBuilder::namespace`](crate::sandbox::SandboxTemplateBuilder::namespace)
+/// keep their compiled artifacts in a `{cache}/{name}` subdirectory (and under
+/// a `{name}/` prefix in a shared [`CacheBackend`](crate::sandbox::CacheBackend)),
+/// so cache size limits and eviction apply per tenant. Each template already
+/// owns its engine and instance pool; the namespace adds limits that span
+/// every template in it.
+///
+/// Clones share the live sandbox count, so pass clones of one configured
+/// value to every template of a tenant.
+#[derive(Clone, Debug)]
+pub struct Namespace {
+    name: String,
+    max_sandboxes: Option<usize>,
+    max_memory: Option<usize>,
+    live: Arc<AtomicUsize>,
+}
+
+impl Namespace {
+    /// Create a namespace without limits.
+    ///
+    /// `name` may only contain ASCII letters, digits, `-`, `_`, and `.`, and
+    /// is validated when a template is built.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            max_sandboxes: None,
+            max_memory: None,
+            live: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Cap the number of sandboxes alive at once across all templates in
+    /// this namespace.
+    ///
+    /// Instantiation fails with [`ErrorKind::PolicyDenied`](crate::sandbox::ErrorKind::PolicyDenied)
+    /// once the cap is reached; dropping a sandbox frees its slot.
+    #[must_use]
+    pub const fn max_sandboxes(mut self, max_sandboxes: Option<usize>) -> Self {
+        self.max_sandboxes = max_sandboxes;
+        self
+    }
+
+    /// Cap the memory limit of every sandbox in this namespace.
+    ///
+    /// Template and per-sandbox `max_memory` settings are clamped to this
+    /// value.
+    #[must_use]
+    pub const fn max_memory(mut self, max_memory: Option<usize>) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    /// Return the namespace name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the number of live sandboxes in this namespace.
+    #[must_use]
+    pub fn live_sandboxes(&self) -> usize {
+        self.live.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        let valid = !self.name.is_empty()
+            && self.name != "."
+            && self.name != ".."
+            && self
+                .name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid namespace name '{}'", self.name),
+            )))
+        }
+    }
+
+    pub(crate) fn clamp_memory(&self, max_memory: usize) -> usize {
+        self.max_memory
+            .map_or(max_memory, |cap| max_memory.min(cap))
+    }
+
+    /// Reserve a sandbox slot, failing when the namespace is full.
+    pub(crate) fn acquire(&self) -> Result<NamespaceSlot> {
+        let limit = self.max_sandboxes.unwrap_or(usize::MAX);
+        self.live
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |live| {
+                (live < limit).then_some(live + 1)
+            })
+            .map_err(|live| {
+                Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::QuotaExceeded,
+                    format!(
+                        "namespace '{}' already has {live} live sandboxes",
+                        self.name
+                    ),
+                ))
+            })?;
+        Ok(NamespaceSlot {
+            live: Arc::clone(&self.live),
+        })
+    }
+}
+
+/// Live sandbox slot held by a [`Sandbox`](crate::sandbox::Sandbox) for its
+/// whole lifetime.
+#[derive(Debug)]
+pub struct NamespaceSlot {
+    live: Arc<AtomicUsize>,
+}
+
+impl Drop for NamespaceSlot {
+    fn drop(&mut self) {
+        self.live.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::ErrorKind;
+
+    #[test]
+    fn slots_are_shared_between_clones_and_released_on_drop() {
+        let namespace = Namespace::new("tenant-a")
+            .max_sandboxes(Some(2))
+            .max_memory(Some(1024));
+        let clone = namespace.clone();
+
+        let first = namespace.acquire().expect("first slot");
+        let second = clone.acquire().expect("second slot");
+        let full = namespace.acquire().expect_err("namespace is full");
+        assert_eq!(full.kind(), ErrorKind::PolicyDenied);
+        assert_eq!(clone.live_sandboxes(), 2);
+
+        drop(first);
+        let _third = clone.acquire().expect("slot released");
+        drop(second);
+        assert_eq!(namespace.live_sandboxes(), 1);
+
+        assert_eq!(namespace.clamp_memory(usize::MAX), 1024);
+        assert_eq!(namespace.clamp_memory(512), 512);
+    }
+
+    #[test]
+    fn names_must_be_safe_path_components() {
+        for name in ["tenant-a", "app_1", "v1.2"] {
+            Namespace::new(name).validate().expect("valid name");
+        }
+        for name in ["", ".", "..", "a/b", "a b", "ü"] {
+            assert!(Namespace::new(name).validate().is_err(), "{name:?}");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use wasmtime::{
+    Engine, Store,
+    component::{Component, Instance},
+};
+
+use super::{
+    Error, Result, Sandbox, SandboxOptions, WasiInterface, linker_extension::LinkerExtension,
+    snapshot::SnapshotSource,
+};
+use crate::{
+    host::Host,
+    internal::{
+        module::prelude::initialize,
+        plugin::{PluginInstance, PluginTemplate},
+        sandbox::{HostView as _, InstanceState, Sandbox as WasmSandbox, SandboxPre},
+        scratch::create_scratch_dirs,
+    },
+};
+
+/// What a sandbox was instantiated from, kept so [`Sandbox::reset`] can
+/// re-instantiate it without going back through the template.
+pub struct SandboxOrigin<H: Host> {
+    pub pre: SandboxPre<InstanceState<H>>,
+    /// Template options merged with the sandbox's own.
+    pub options: SandboxOptions,
+    pub disabled_wasi: Vec<WasiInterface>,
+    pub plugins: Vec<Arc<PluginTemplate>>,
+    /// Set when the component is instrumented for snapshots, in which case
+    /// every instance initializes the guest itself.
+    pub source: Option<Arc<SnapshotSource>>,
+    pub linker_extensions: Arc<[LinkerExtension]>,
+}
+
+impl<H: Host> SandboxOrigin<H> {
+    /// This origin with every scratch mapping, including the top layer of
+    /// capturing overlays, backed by a new empty directory.
+    ///
+    /// The previous directories are removed once the instance using them is
+    /// dropped. Without scratch mappings the origin is returned as is.
+    fn with_fresh_scratch_dirs(self: &Arc<Self>) -> Result<Arc<Self>> {
+        if !self
+            .options
+            .directory_mappings
+            .iter()
+            .any(|mapping| mapping.scratch)
+        {
+            return Ok(Arc::clone(self));
+        }
+        let mut options = self.options.clone();
+        create_scratch_dirs(&mut options.directory_mappings)?;
+        Ok(Arc::new(Self {
+            pre: self.pre.clone(),
+            options,
+            disabled_wasi: self.disabled_wasi.clone(),
+            plugins: self.plugins.clone(),
+            source: self.source.clone(),
+            linker_extensions: Arc::clone(&self.linker_extensions),
+        }))
+    }
+
+    /// This origin with `component`, a snapshot of one of its instances, in
+    /// place of the template's component.
+    pub fn for_snapshot(&self, component: &Component) -> Result<Arc<Self>> {
+        let mut linker = InstanceState::<H>::new_linker(component.engine()).map_err(Error::Wasm)?;
+        LinkerExtension::apply(&self.linker_extensions, &mut linker).map_err(Error::Wasm)?;
+        let pre = linker.instantiate_pre(component).map_err(Error::Wasm)?;
+        Ok(Arc::new(Self {
+            pre: SandboxPre::new(pre).map_err(|missing| Error::IncompatibleWorld { missing })?,
+            options: self.options.clone(),
+            disabled_wasi: self.disabled_wasi.clone(),
+            plugins: self.plugins.clone(),
+            source: None,
+            linker_extensions: Arc::clone(&self.linker_extensions),
+        }))
+    }
+
+    /// Instantiate the component in a store with fresh WASI, resource and
+    /// plugin state for `host`.
+    pub async fn instantiate(
+        &self,
+        engine: &Engine,
+        host: Arc<H>,
+    ) -> Result<(Store<InstanceState<H>>, Instance, WasmSandbox)> {
+        let mut store = InstanceState::new(engine, &self.options, &self.disabled_wasi, host)
+            .map_err(Error::Wasm)?;
+        let ticks = store.data().epoch_yield_ticks();
+        store.epoch_deadline_async_yield_and_update(ticks);
+        let max_memory = self.options.max_memory.unwrap_or(usize::MAX);
+        store.data_mut().set_plugins(
+            self.plugins
+                .iter()
+                .map(|plugin| Arc::new(PluginInstance::new(Arc::clone(plugin), max_memory)))
+                .collect(),
+        );
+
+        let instance = self
+            .pre
+            .instance_pre()
+            .instantiate_async(&mut store)
+            .await
+            .map_err(Error::Wasm)?;
+        let bindings = self
+            .pre
+            .load(&mut store, &instance)
+            .map_err(|missing| Error::IncompatibleWorld { missing })?;
+        if let Some(source) = &self.source {
+            initialize(&bindings, &mut store, false, &source.preludes)
+                .await
+                .map_err(|e| store.data().classify_error(e))??;
+        }
+        Ok((store, instance, bindings))
+    }
+}
+
+impl<H: Host> Sandbox<H> {
+    /// Return this sandbox to the state it had right after it was
+    /// instantiated or [restored](super::SandboxTemplate::restore).
+    ///
+    /// Guest globals, imported modules, open files and handles, pending
+    /// output, plugin state and the hostcall trace are all discarded: the
+    /// guest gets a new instance of the component it started from in a new
+    /// store, so nothing an earlier workload did inside the sandbox can reach
+    /// the next one. The host, options and namespace slot are kept, which skips
+    /// option merging, archive unpacking, workdir preparation and linking.
+    /// With [`copy_on_write`](super::SandboxTemplateBuilder::copy_on_write)
+    /// the guest heap is remapped rather than copied.
+    ///
+    /// Scratch directories and the captured top layer of
+    /// [`capture_writes`](super::OverlayMount::capture_writes) overlays are
+    /// replaced with new empty ones. Files the guest wrote to other mounted
+    /// host directories are not restored.
+    ///
+    /// The new instance is created before the old one is released, so a
+    /// template using [`pooling`](super::SandboxTemplateBuilder::pooling)
+    /// needs a free instance slot to reset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the new instance cannot be created. The sandbox
+    /// then keeps its previous state.
+    pub async fn reset(&mut self) -> Result<()> {
+        let host = Arc::clone(self.store.data_mut().host());
+        let origin = self.origin.with_fresh_scratch_dirs()?;
+        let (store, instance, bindings) = origin.instantiate(self.store.engine(), host).await?;
+        self.origin = origin;
+        self.call_trace = store.data().call_trace();
+        self.store = store;
+        self.instance = instance;
+        self.bindings = bindings;
+        self.fresh_copy = None;
+        Ok(())
+    }
+}
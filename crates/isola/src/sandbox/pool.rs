@@ -0,0 +1,340 @@
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use super::{Error, Result, Sandbox, SandboxOptions, SandboxTemplate};
+use crate::host::Host;
+
+/// Sizing and eviction settings for a [`SandboxPool`].
+#[derive(Clone, Debug)]
+pub struct SandboxPoolConfig {
+    pub(crate) min_idle: usize,
+    pub(crate) max_size: usize,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) options: SandboxOptions,
+}
+
+impl Default for SandboxPoolConfig {
+    fn default() -> Self {
+        Self {
+            min_idle: 1,
+            max_size: 16,
+            idle_timeout: Some(Duration::from_secs(60)),
+            options: SandboxOptions::default(),
+        }
+    }
+}
+
+impl SandboxPoolConfig {
+    /// Set how many idle sandboxes the pool keeps ready.
+    ///
+    /// They are instantiated when the pool is created and replenished in the
+    /// background as sandboxes are handed out. Defaults to 1.
+    #[must_use]
+    pub const fn min_idle(mut self, min_idle: usize) -> Self {
+        self.min_idle = min_idle;
+        self
+    }
+
+    /// Set the maximum number of sandboxes alive at once, idle or in use.
+    ///
+    /// [`SandboxPool::acquire`] waits once this many are in use. Defaults to
+    /// 16.
+    #[must_use]
+    pub const fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Drop idle sandboxes above [`min_idle`](Self::min_idle) once they have
+    /// been unused for `idle_timeout`.
+    ///
+    /// `None` keeps them until the pool is dropped. Defaults to 60 seconds.
+    #[must_use]
+    pub const fn idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Set the options every pooled sandbox is instantiated with.
+    #[must_use]
+    pub fn options(mut self, options: SandboxOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Snapshot of a [`SandboxPool`]'s occupancy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SandboxPoolStats {
+    /// Sandboxes ready to be handed out.
+    pub idle: usize,
+    /// Sandboxes handed out, being instantiated, or being reset after
+    /// their return.
+    pub in_use: usize,
+}
+
+/// Warm pool of sandboxes instantiated from one [`SandboxTemplate`].
+///
+/// [`acquire`](Self::acquire) hands out a [`PooledSandbox`] guard. Dropping
+/// the guard [resets](Sandbox::reset) the sandbox in the background and
+/// returns it to the pool, so no state from one checkout reaches the next,
+/// even if a call was cancelled midway. Sandboxes that fail to reset are
+/// discarded.
+///
+/// The pool must be created and used inside a Tokio runtime.
+pub struct SandboxPool<H: Host> {
+    inner: Arc<PoolInner<H>>,
+}
+
+struct PoolInner<H: Host> {
+    template: Arc<SandboxTemplate>,
+    make_host: Box<dyn Fn() -> H + Send + Sync>,
+    config: SandboxPoolConfig,
+    /// Idle sandboxes with their permits, most recently returned last.
+    idle: Mutex<VecDeque<IdleSandbox<H>>>,
+    /// One permit per live sandbox, idle or in use, so the pool never holds
+    /// more than `max_size`.
+    permits: Arc<Semaphore>,
+    /// Signalled when a sandbox becomes idle, since its permit then stays
+    /// with it instead of waking [`SandboxPool::acquire`] through `permits`.
+    returned: Notify,
+}
+
+struct IdleSandbox<H: Host> {
+    sandbox: Sandbox<H>,
+    permit: OwnedSemaphorePermit,
+    since: Instant,
+}
+
+impl<H: Host> SandboxPool<H> {
+    /// Create a pool of sandboxes from `template`, each with a host built by
+    /// `make_host`, and instantiate [`min_idle`](SandboxPoolConfig::min_idle)
+    /// of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `max_size` is zero or below `min_idle`, or if
+    /// instantiating the initial sandboxes fails.
+    pub async fn new(
+        template: Arc<SandboxTemplate>,
+        config: SandboxPoolConfig,
+        make_host: impl Fn() -> H + Send + Sync + 'static,
+    ) -> Result<Self> {
+        if config.max_size == 0 || config.min_idle > config.max_size {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "sandbox pool max_size ({}) must be at least 1 and at least min_idle ({})",
+                    config.max_size, config.min_idle
+                ),
+            )));
+        }
+        let inner = Arc::new(PoolInner {
+            template,
+            make_host: Box::new(make_host),
+            permits: Arc::new(Semaphore::new(config.max_size)),
+            idle: Mutex::new(VecDeque::new()),
+            returned: Notify::new(),
+            config,
+        });
+        inner.replenish().await?;
+        if let Some(idle_timeout) = inner.config.idle_timeout {
+            tokio::spawn(evict_idle(Arc::downgrade(&inner), idle_timeout));
+        }
+        Ok(Self { inner })
+    }
+
+    /// Take an idle sandbox, instantiating one if none is ready.
+    ///
+    /// Waits while [`max_size`](SandboxPoolConfig::max_size) sandboxes are
+    /// in use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a new sandbox has to be instantiated and that
+    /// fails.
+    pub async fn acquire(&self) -> Result<PooledSandbox<H>> {
+        let (sandbox, permit) = loop {
+            // Registered before checking, so a sandbox returned in between
+            // still wakes this loop.
+            let returned = self.inner.returned.notified();
+            let idle = self.inner.idle.lock().pop_back();
+            if let Some(idle) = idle {
+                break (idle.sandbox, idle.permit);
+            }
+            tokio::select! {
+                permit = Arc::clone(&self.inner.permits).acquire_owned() => {
+                    let permit = permit.map_err(|e| Error::Other(e.into()))?;
+                    break (self.inner.instantiate().await?, permit);
+                }
+                () = returned => {}
+            }
+        };
+        if self.inner.idle.lock().len() < self.inner.config.min_idle {
+            let inner = Arc::clone(&self.inner);
+            // Best-effort: a failed refill only means a later acquire
+            // instantiates on demand.
+            tokio::spawn(async move { inner.replenish().await });
+        }
+        Ok(PooledSandbox {
+            sandbox: Some(sandbox),
+            permit: Some(permit),
+            pool: Arc::clone(&self.inner),
+        })
+    }
+
+    /// Return the pool's current occupancy.
+    #[must_use]
+    pub fn stats(&self) -> SandboxPoolStats {
+        let idle = self.inner.idle.lock().len();
+        let live = self.inner.config.max_size - self.inner.permits.available_permits();
+        SandboxPoolStats {
+            idle,
+            in_use: live.saturating_sub(idle),
+        }
+    }
+}
+
+impl<H: Host> PoolInner<H> {
+    async fn instantiate(&self) -> Result<Sandbox<H>> {
+        self.template
+            .instantiate((self.make_host)(), self.config.options.clone())
+            .await
+    }
+
+    /// Instantiate sandboxes until `min_idle` are idle or the pool is full.
+    async fn replenish(&self) -> Result<()> {
+        while self.idle.lock().len() < self.config.min_idle {
+            // Counts the sandbox as in use while it is being instantiated,
+            // then stays with it while it is idle.
+            let Ok(permit) = Arc::clone(&self.permits).try_acquire_owned() else {
+                return Ok(());
+            };
+            let sandbox = self.instantiate().await?;
+            self.push_idle(sandbox, permit);
+        }
+        Ok(())
+    }
+
+    fn push_idle(&self, sandbox: Sandbox<H>, permit: OwnedSemaphorePermit) {
+        self.idle.lock().push_back(IdleSandbox {
+            sandbox,
+            permit,
+            since: Instant::now(),
+        });
+        self.returned.notify_waiters();
+    }
+
+    /// Drop the oldest idle sandboxes above `min_idle` that have been unused
+    /// for `idle_timeout`.
+    fn evict(&self, idle_timeout: Duration) {
+        let mut evicted = Vec::new();
+        let mut idle = self.idle.lock();
+        while idle.len() > self.config.min_idle
+            && idle
+                .front()
+                .is_some_and(|idle| idle.since.elapsed() >= idle_timeout)
+        {
+            evicted.extend(idle.pop_front());
+        }
+        // Tear the sandboxes down and release their permits without holding
+        // the lock.
+        drop(idle);
+        drop(evicted);
+    }
+}
+
+async fn evict_idle<H: Host>(pool: Weak<PoolInner<H>>, idle_timeout: Duration) {
+    let mut ticks = tokio::time::interval((idle_timeout / 2).max(Duration::from_millis(10)));
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let Some(pool) = pool.upgrade() else {
+            return;
+        };
+        pool.evict(idle_timeout);
+    }
+}
+
+/// Sandbox checked out of a [`SandboxPool`].
+///
+/// Dereferences to the [`Sandbox`]. Dropping it recycles the sandbox; call
+/// [`discard`](Self::discard) to drop it instead.
+pub struct PooledSandbox<H: Host> {
+    sandbox: Option<Sandbox<H>>,
+    permit: Option<OwnedSemaphorePermit>,
+    pool: Arc<PoolInner<H>>,
+}
+
+impl<H: Host> PooledSandbox<H> {
+    /// Drop the sandbox instead of returning it to the pool.
+    pub fn discard(mut self) {
+        self.sandbox = None;
+    }
+}
+
+impl<H: Host> Deref for PooledSandbox<H> {
+    type Target = Sandbox<H>;
+
+    fn deref(&self) -> &Sandbox<H> {
+        self.sandbox
+            .as_ref()
+            .expect("pooled sandbox is present until dropped")
+    }
+}
+
+impl<H: Host> DerefMut for PooledSandbox<H> {
+    fn deref_mut(&mut self) -> &mut Sandbox<H> {
+        self.sandbox
+            .as_mut()
+            .expect("pooled sandbox is present until dropped")
+    }
+}
+
+impl<H: Host> Drop for PooledSandbox<H> {
+    fn drop(&mut self) {
+        let (Some(mut sandbox), Some(permit)) = (self.sandbox.take(), self.permit.take()) else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let pool = Arc::clone(&self.pool);
+        // The permit stays with the sandbox through the reset and while it
+        // is idle, and is released only if the reset fails.
+        runtime.spawn(async move {
+            if sandbox.reset().await.is_ok() {
+                pool.push_idle(sandbox, permit);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_setters_apply() {
+        let config = SandboxPoolConfig::default()
+            .min_idle(2)
+            .max_size(4)
+            .idle_timeout(None)
+            .options(SandboxOptions::default().env("KEY", "value"));
+        assert_eq!(config.min_idle, 2);
+        assert_eq!(config.max_size, 4);
+        assert_eq!(config.idle_timeout, None);
+        assert_eq!(
+            config.options.env,
+            [("KEY".to_string(), "value".to_string())]
+        );
+    }
+}
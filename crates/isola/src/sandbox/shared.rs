@@ -0,0 +1,213 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{Mutex, OwnedMappedMutexGuard, OwnedMutexGuard};
+
+use super::{Error, Result, Sandbox};
+use crate::host::Host;
+
+/// [`Sandbox`] shared between concurrent callers.
+///
+/// A sandbox runs one call at a time. [`lock`](Self::lock) waits for the
+/// sandbox to be free instead of failing, and waiting callers get it in the
+/// order they asked for it. Clones share the same sandbox and queue.
+///
+/// When the sandbox was instantiated with an
+/// [`idle_timeout`](super::SandboxOptions::idle_timeout), the shared sandbox
+/// drops it once nobody has used it for that long.
+pub struct SharedSandbox<H: Host> {
+    /// `None` once the sandbox was disposed for being idle.
+    sandbox: Arc<Mutex<Option<Sandbox<H>>>>,
+    /// Callers waiting for the sandbox, not counting the one holding it.
+    waiting: Arc<AtomicUsize>,
+    /// When the sandbox was last released.
+    released: Arc<parking_lot::Mutex<Instant>>,
+    idle_timeout: Option<Duration>,
+    max_queue_depth: Option<usize>,
+}
+
+impl<H: Host> SharedSandbox<H> {
+    /// Share `sandbox` with no limit on how many callers may wait for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics outside a Tokio runtime if the sandbox has an idle timeout.
+    #[must_use]
+    pub fn new(sandbox: Sandbox<H>) -> Self {
+        Self::with_max_queue_depth(sandbox, None)
+    }
+
+    /// Share `sandbox`, letting at most `max_queue_depth` callers wait while
+    /// another one holds it.
+    ///
+    /// `None` lets any number of callers wait. With `Some(0)`,
+    /// [`lock`](Self::lock) fails whenever the sandbox is in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics outside a Tokio runtime if the sandbox has an idle timeout,
+    /// which is enforced by a background task.
+    #[must_use]
+    pub fn with_max_queue_depth(sandbox: Sandbox<H>, max_queue_depth: Option<usize>) -> Self {
+        let idle_timeout = sandbox.idle_timeout();
+        let shared = Self {
+            sandbox: Arc::new(Mutex::new(Some(sandbox))),
+            waiting: Arc::new(AtomicUsize::new(0)),
+            released: Arc::new(parking_lot::Mutex::new(Instant::now())),
+            idle_timeout,
+            max_queue_depth,
+        };
+        if let Some(timeout) = idle_timeout {
+            tokio::spawn(dispose_when_idle(
+                Arc::downgrade(&shared.sandbox),
+                Arc::clone(&shared.waiting),
+                Arc::clone(&shared.released),
+                timeout,
+            ));
+        }
+        shared
+    }
+
+    /// Wait for exclusive use of the sandbox.
+    ///
+    /// The sandbox is released when the returned guard is dropped. Callers
+    /// cancelled while waiting leave the queue.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::QueueFull`] if the sandbox is in use and
+    /// [`max_queue_depth`](Self::with_max_queue_depth) callers are already
+    /// waiting, and [`Error::IdleDisposed`] once the sandbox was dropped for
+    /// being idle.
+    pub async fn lock(&self) -> Result<SharedSandboxGuard<H>> {
+        if let Ok(guard) = Arc::clone(&self.sandbox).try_lock_owned() {
+            return self.guard(guard);
+        }
+        let waiting = self.waiting.fetch_add(1, Ordering::AcqRel);
+        let _queued = QueueSlot(&self.waiting);
+        if let Some(max) = self.max_queue_depth
+            && waiting >= max
+        {
+            return Err(Error::QueueFull(max));
+        }
+        let guard = Arc::clone(&self.sandbox).lock_owned().await;
+        self.guard(guard)
+    }
+
+    /// Number of callers currently waiting for the sandbox.
+    #[must_use]
+    pub fn queue_depth(&self) -> usize {
+        self.waiting.load(Ordering::Acquire)
+    }
+
+    /// Return whether the sandbox was dropped for being idle.
+    #[must_use]
+    pub fn is_disposed(&self) -> bool {
+        self.sandbox
+            .try_lock()
+            .is_ok_and(|sandbox| sandbox.is_none())
+    }
+
+    fn guard(&self, guard: OwnedMutexGuard<Option<Sandbox<H>>>) -> Result<SharedSandboxGuard<H>> {
+        let guard = OwnedMutexGuard::try_map(guard, Option::as_mut)
+            .map_err(|_| Error::IdleDisposed(self.idle_timeout.unwrap_or_default()))?;
+        Ok(SharedSandboxGuard {
+            guard,
+            released: Arc::clone(&self.released),
+        })
+    }
+}
+
+impl<H: Host> Clone for SharedSandbox<H> {
+    fn clone(&self) -> Self {
+        Self {
+            sandbox: Arc::clone(&self.sandbox),
+            waiting: Arc::clone(&self.waiting),
+            released: Arc::clone(&self.released),
+            idle_timeout: self.idle_timeout,
+            max_queue_depth: self.max_queue_depth,
+        }
+    }
+}
+
+/// Leaves the wait queue when a caller gets the sandbox, is rejected, or is
+/// cancelled.
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Drop the sandbox once it has been released and not locked again for
+/// `timeout`, then tell its host.
+async fn dispose_when_idle<H: Host>(
+    sandbox: Weak<Mutex<Option<Sandbox<H>>>>,
+    waiting: Arc<AtomicUsize>,
+    released: Arc<parking_lot::Mutex<Instant>>,
+    timeout: Duration,
+) {
+    loop {
+        // While the sandbox is held or awaited, the last release is stale;
+        // check again a full timeout later.
+        let now = Instant::now();
+        let deadline = Some(*released.lock() + timeout)
+            .filter(|deadline| *deadline > now)
+            .unwrap_or(now + timeout);
+        tokio::time::sleep_until(deadline.into()).await;
+        let Some(sandbox) = sandbox.upgrade() else {
+            return;
+        };
+        let Ok(mut guard) = sandbox.try_lock_owned() else {
+            continue;
+        };
+        if waiting.load(Ordering::Acquire) > 0 || released.lock().elapsed() < timeout {
+            continue;
+        }
+        let Some(disposed) = guard.take() else {
+            return;
+        };
+        drop(guard);
+        let host = Arc::clone(disposed.store.data().host_handle());
+        drop(disposed);
+        host.on_idle_dispose(timeout);
+        return;
+    }
+}
+
+/// Exclusive use of a [`SharedSandbox`], returned by
+/// [`SharedSandbox::lock`].
+///
+/// Dereferences to the [`Sandbox`]. Dropping it hands the sandbox to the next
+/// waiting caller.
+pub struct SharedSandboxGuard<H: Host> {
+    guard: OwnedMappedMutexGuard<Option<Sandbox<H>>, Sandbox<H>>,
+    released: Arc<parking_lot::Mutex<Instant>>,
+}
+
+impl<H: Host> Drop for SharedSandboxGuard<H> {
+    fn drop(&mut self) {
+        *self.released.lock() = Instant::now();
+    }
+}
+
+impl<H: Host> Deref for SharedSandboxGuard<H> {
+    type Target = Sandbox<H>;
+
+    fn deref(&self) -> &Sandbox<H> {
+        &self.guard
+    }
+}
+
+impl<H: Host> DerefMut for SharedSandboxGuard<H> {
+    fn deref_mut(&mut self) -> &mut Sandbox<H> {
+        &mut self.guard
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use parking_lot::Mutex;
+use wasmtime::{
+    Engine, Store,
+    component::{Component, Instance},
+};
+use wasmtime_wizer::{WasmtimeWizerComponent, Wizer};
+
+use super::{
+    Arg, CallOptions, CoverageReport, Error, Result, Sandbox, SandboxOptions, SandboxTemplate,
+    origin::SandboxOrigin,
+};
+use crate::{
+    host::{Host, OutputTarget},
+    internal::{
+        module::prelude::{Prelude, initialize},
+        sandbox::{HostView as _, InstanceState, Sandbox as WasmSandbox},
+    },
+};
+
+/// Runtime component and preludes kept by templates built with
+/// [`snapshots`](super::SandboxTemplateBuilder::snapshots).
+///
+/// Instrumenting the component again yields the accessor layout its
+/// sandboxes were compiled with, which [`Sandbox::snapshot`] reads guest
+/// state through.
+pub struct SnapshotSource {
+    pub wasm: Vec<u8>,
+    pub preludes: Vec<Prelude>,
+}
+
+/// Guest state captured from a sandbox by [`Sandbox::snapshot`].
+///
+/// Holds a runtime component whose linear memory and globals start out as
+/// they were when the snapshot was taken, compiled for the template's
+/// engine. Restore it with [`SandboxTemplate::restore`] as many times as
+/// needed; each restore only instantiates the component.
+pub struct SandboxSnapshot {
+    pub(crate) component: Component,
+    pub(crate) pre_instances: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+}
+
+impl std::fmt::Debug for SandboxSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SandboxSnapshot").finish_non_exhaustive()
+    }
+}
+
+impl<H: Host> Sandbox<H> {
+    /// Capture this sandbox's guest state so it can be cloned with
+    /// [`SandboxTemplate::restore`].
+    ///
+    /// The snapshot holds the guest's linear memory and globals, so
+    /// definitions, imported modules and anything else prepared by earlier
+    /// evals and calls are present in every restored sandbox. Tables cannot
+    /// be changed by the runtime and need no capturing. Host-side state is
+    /// not part of it: open files and handles, plugin state and the
+    /// sandbox's options are set up fresh by each restore.
+    ///
+    /// Taking a snapshot compiles a new runtime component, which takes about
+    /// as long as building the template without a cache. This sandbox stays
+    /// usable afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template was not built with
+    /// [`snapshots`](super::SandboxTemplateBuilder::snapshots), if this
+    /// sandbox was itself restored from a snapshot, or if reading the guest
+    /// state or compiling the snapshot fails.
+    pub async fn snapshot(&mut self) -> Result<SandboxSnapshot> {
+        let Some(source) = self.origin.source.clone() else {
+            return Err(Error::Other(
+                std::io::Error::other(
+                    "snapshots require a template built with `snapshots(true)` and a sandbox \
+                     instantiated from it",
+                )
+                .into(),
+            ));
+        };
+        // Release the WASI handles the guest caches, as template
+        // preinitialization does, so the snapshot refers to none of this
+        // store's resources.
+        initialize(&self.bindings, &mut self.store, true, &[])
+            .await
+            .map_err(|e| self.store.data().classify_error(e))??;
+
+        let wizer = Wizer::new();
+        let (cx, _) = wizer
+            .instrument_component(&source.wasm)
+            .map_err(Error::Wasm)?;
+        let data = wizer
+            .snapshot_component(
+                cx,
+                &mut WasmtimeWizerComponent {
+                    store: &mut self.store,
+                    instance: self.instance,
+                },
+            )
+            .await
+            .map_err(Error::Wasm)?;
+
+        let engine = self.store.engine().clone();
+        let component = tokio::task::spawn_blocking(move || Component::new(&engine, &data))
+            .await
+            .map_err(|e| Error::Other(e.into()))?
+            .map_err(Error::Wasm)?;
+        Ok(SandboxSnapshot {
+            component,
+            pre_instances: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+impl SandboxTemplate {
+    /// Create a sandbox whose guest starts from `snapshot` instead of the
+    /// template's initial state.
+    ///
+    /// Options are merged with the template defaults exactly as in
+    /// [`instantiate`](Self::instantiate), and the sandbox counts towards the
+    /// template's namespace and statistics. [`Sandbox::reset`] on the result
+    /// returns it to the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `snapshot` was taken from another template's
+    /// sandbox or if instantiation fails.
+    pub async fn restore<H: Host>(
+        &self,
+        snapshot: &SandboxSnapshot,
+        host: H,
+        options: SandboxOptions,
+    ) -> Result<Sandbox<H>> {
+        if !Engine::same(snapshot.component.engine(), &self.engine) {
+            return Err(Error::Other(
+                std::io::Error::other("snapshot was taken from another template's sandbox").into(),
+            ));
+        }
+        self.instantiate_component(
+            &snapshot.component,
+            &snapshot.pre_instances,
+            None,
+            host,
+            options,
+        )
+        .await
+    }
+}
+
+/// Snapshot of a sandbox's guest that calls with
+/// [`CallOptions::fresh_globals`] run in copies of.
+pub struct FreshCopy<H: Host> {
+    /// [`InstanceState::operations`] of the sandbox when the snapshot was
+    /// taken.
+    operations: u64,
+    origin: Arc<SandboxOrigin<H>>,
+}
+
+impl<H: Host> Sandbox<H> {
+    /// Run a call in a new instance restored from a snapshot of this
+    /// sandbox, so nothing it changes inside the guest outlives it.
+    ///
+    /// The snapshot is taken again only when an eval or call since the last
+    /// one may have changed the guest.
+    pub(crate) async fn call_fresh<I>(
+        &mut self,
+        function: &str,
+        args: I,
+        target: OutputTarget,
+        options: CallOptions,
+    ) -> Result<Result<Option<CoverageReport>>>
+    where
+        I: IntoIterator<Item = Arg>,
+    {
+        let operations = self.store.data().operations();
+        let origin = match &self.fresh_copy {
+            Some(copy) if copy.operations == operations => Arc::clone(&copy.origin),
+            _ => {
+                let snapshot = self.snapshot().await?;
+                let origin = self.origin.for_snapshot(&snapshot.component)?;
+                self.fresh_copy = Some(FreshCopy {
+                    operations,
+                    origin: Arc::clone(&origin),
+                });
+                origin
+            }
+        };
+        let host = Arc::clone(self.store.data_mut().host());
+        let (mut store, instance, bindings) = origin.instantiate(self.store.engine(), host).await?;
+        store.data_mut().set_call_trace(self.call_trace.clone());
+
+        let mut copy = SwappedIn::new(self, store, instance, bindings);
+        let options = CallOptions {
+            fresh_globals: false,
+            ..options
+        };
+        copy.call_guest(function, args, target, options).await
+    }
+}
+
+/// Sandbox running on a copy of its instance. Dropping the guard, even when
+/// the call is cancelled, puts the sandbox's own instance back and drops the
+/// copy.
+struct SwappedIn<'a, H: Host> {
+    sandbox: &'a mut Sandbox<H>,
+    parked: Option<(Store<InstanceState<H>>, Instance, WasmSandbox)>,
+}
+
+impl<'a, H: Host> SwappedIn<'a, H> {
+    const fn new(
+        sandbox: &'a mut Sandbox<H>,
+        store: Store<InstanceState<H>>,
+        instance: Instance,
+        bindings: WasmSandbox,
+    ) -> Self {
+        let parked = (
+            std::mem::replace(&mut sandbox.store, store),
+            std::mem::replace(&mut sandbox.instance, instance),
+            std::mem::replace(&mut sandbox.bindings, bindings),
+        );
+        Self {
+            sandbox,
+            parked: Some(parked),
+        }
+    }
+}
+
+impl<H: Host> Deref for SwappedIn<'_, H> {
+    type Target = Sandbox<H>;
+
+    fn deref(&self) -> &Sandbox<H> {
+        self.sandbox
+    }
+}
+
+impl<H: Host> DerefMut for SwappedIn<'_, H> {
+    fn deref_mut(&mut self) -> &mut Sandbox<H> {
+        self.sandbox
+    }
+}
+
+impl<H: Host> Drop for SwappedIn<'_, H> {
+    fn drop(&mut self) {
+        let Some((store, instance, bindings)) = self.parked.take() else {
+            return;
+        };
+        let copy = std::mem::replace(&mut self.sandbox.store, store);
+        self.sandbox.instance = instance;
+        self.sandbox.bindings = bindings;
+        self.sandbox
+            .store
+            .data_mut()
+            .adopt_last_operation(copy.data());
+    }
+}
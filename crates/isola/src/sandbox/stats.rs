@@ -0,0 +1,131 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+/// How a template's compiled component was obtained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CacheStatus {
+    /// No cache directory or cache backend was configured, so the component
+    /// was compiled.
+    Disabled,
+    /// A compiled artifact was loaded from the cache directory or backend.
+    Hit,
+    /// No usable artifact was cached, so the component was compiled and
+    /// stored.
+    Miss,
+    /// The template was loaded from an artifact exported with
+    /// [`SandboxTemplate::serialize`](crate::sandbox::SandboxTemplate::serialize).
+    Precompiled,
+}
+
+/// Snapshot of a [`SandboxTemplate`](crate::sandbox::SandboxTemplate)'s
+/// build and instantiation metrics.
+///
+/// Returned by [`SandboxTemplate::stats`](crate::sandbox::SandboxTemplate::stats).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TemplateStats {
+    /// Time spent loading or compiling the runtime component.
+    pub compile_time: Duration,
+    /// Whether the runtime component came from the compilation cache.
+    pub cache: CacheStatus,
+    /// Number of sandboxes successfully instantiated from the template.
+    pub instantiations: u64,
+    /// Mean time taken by those instantiations, or `None` before the first.
+    pub average_instantiate_time: Option<Duration>,
+    /// Number of sandboxes from the template that are still alive.
+    pub live_sandboxes: usize,
+}
+
+/// Counters shared by a template and the sandboxes created from it.
+#[derive(Debug)]
+pub struct TemplateCounters {
+    compile_time: Duration,
+    cache: CacheStatus,
+    instantiations: AtomicU64,
+    instantiate_nanos: AtomicU64,
+    live: Arc<AtomicUsize>,
+}
+
+impl TemplateCounters {
+    pub fn new(compile_time: Duration, cache: CacheStatus) -> Self {
+        Self {
+            compile_time,
+            cache,
+            instantiations: AtomicU64::new(0),
+            instantiate_nanos: AtomicU64::new(0),
+            live: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Record a successful instantiation and count the sandbox as live until
+    /// the returned guard is dropped.
+    pub fn record_instantiation(&self, elapsed: Duration) -> LiveSandbox {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.instantiate_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.instantiations.fetch_add(1, Ordering::Relaxed);
+        self.live.fetch_add(1, Ordering::AcqRel);
+        LiveSandbox {
+            live: Arc::clone(&self.live),
+        }
+    }
+
+    pub fn snapshot(&self) -> TemplateStats {
+        let instantiations = self.instantiations.load(Ordering::Relaxed);
+        let average_instantiate_time = (instantiations > 0).then(|| {
+            Duration::from_nanos(self.instantiate_nanos.load(Ordering::Relaxed) / instantiations)
+        });
+        TemplateStats {
+            compile_time: self.compile_time,
+            cache: self.cache,
+            instantiations,
+            average_instantiate_time,
+            live_sandboxes: self.live.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// Counts one sandbox as live for as long as it is held.
+#[derive(Debug)]
+pub struct LiveSandbox {
+    live: Arc<AtomicUsize>,
+}
+
+impl Drop for LiveSandbox {
+    fn drop(&mut self) {
+        self.live.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_instantiations_and_tracks_live_sandboxes() {
+        let counters = TemplateCounters::new(Duration::from_millis(5), CacheStatus::Hit);
+        let stats = counters.snapshot();
+        assert_eq!(stats.compile_time, Duration::from_millis(5));
+        assert_eq!(stats.cache, CacheStatus::Hit);
+        assert_eq!(stats.average_instantiate_time, None);
+
+        let first = counters.record_instantiation(Duration::from_millis(2));
+        let second = counters.record_instantiation(Duration::from_millis(4));
+        let stats = counters.snapshot();
+        assert_eq!(stats.instantiations, 2);
+        assert_eq!(
+            stats.average_instantiate_time,
+            Some(Duration::from_millis(3))
+        );
+        assert_eq!(stats.live_sandboxes, 2);
+
+        drop(first);
+        drop(second);
+        assert_eq!(counters.snapshot().live_sandboxes, 0);
+    }
+}
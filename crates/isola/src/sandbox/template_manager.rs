@@ -0,0 +1,176 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::{Duration, SystemTime},
+};
+
+use parking_lot::{Mutex, RwLock};
+
+use super::{Result, SandboxTemplate, SandboxTemplateBuilder};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+type BuilderFactory = Box<dyn Fn() -> SandboxTemplateBuilder + Send + Sync>;
+type Subscriber = Box<dyn Fn(&Result<Arc<SandboxTemplate>>) + Send + Sync>;
+
+/// Modification time and length of the watched component file.
+type FileVersion = Option<(Option<SystemTime>, u64)>;
+
+/// [`SandboxTemplate`] that is rebuilt whenever its runtime component file
+/// changes.
+///
+/// [`template`](Self::template) returns the most recently built template, so
+/// new sandboxes pick up a rebuilt component while sandboxes already
+/// instantiated keep running the template they came from. A background task
+/// polls the file's modification time and size and rebuilds the template
+/// with a fresh builder from the factory passed to [`watch`](Self::watch).
+/// A failed rebuild, for example of a file caught halfway through being
+/// written, keeps the previous template; the next change is picked up as
+/// usual.
+///
+/// Meant for development servers; the polling stops when the manager and all
+/// of its clones are dropped.
+#[derive(Clone)]
+pub struct TemplateManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    path: PathBuf,
+    builder: BuilderFactory,
+    current: RwLock<Arc<SandboxTemplate>>,
+    subscribers: Mutex<Vec<Subscriber>>,
+    /// File version the current template was built from. Held while a
+    /// rebuild runs so rebuilds do not overlap.
+    built: tokio::sync::Mutex<FileVersion>,
+}
+
+impl TemplateManager {
+    /// Build a template from the component at `path` with `builder()`, and
+    /// rebuild it whenever the file changes, checking once per second.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the initial build; see
+    /// [`SandboxTemplateBuilder::build`].
+    ///
+    /// # Panics
+    ///
+    /// Panics outside a Tokio runtime.
+    pub async fn watch<F>(path: impl AsRef<Path>, builder: F) -> Result<Self>
+    where
+        F: Fn() -> SandboxTemplateBuilder + Send + Sync + 'static,
+    {
+        Self::watch_every(path, DEFAULT_POLL_INTERVAL, builder).await
+    }
+
+    /// Like [`watch`](Self::watch), checking the file for changes every
+    /// `interval`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the initial build.
+    ///
+    /// # Panics
+    ///
+    /// Panics outside a Tokio runtime.
+    pub async fn watch_every<F>(
+        path: impl AsRef<Path>,
+        interval: Duration,
+        builder: F,
+    ) -> Result<Self>
+    where
+        F: Fn() -> SandboxTemplateBuilder + Send + Sync + 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let version = file_version(&path).await;
+        let template = builder().build(&path).await?;
+        let manager = Self {
+            inner: Arc::new(Inner {
+                path,
+                builder: Box::new(builder),
+                current: RwLock::new(Arc::new(template)),
+                subscribers: Mutex::new(Vec::new()),
+                built: tokio::sync::Mutex::new(version),
+            }),
+        };
+        tokio::spawn(poll(Arc::downgrade(&manager.inner), interval));
+        Ok(manager)
+    }
+
+    /// Return the most recently built template.
+    #[must_use]
+    pub fn template(&self) -> Arc<SandboxTemplate> {
+        Arc::clone(&self.inner.current.read())
+    }
+
+    /// Call `callback` after every rebuild with the new template or the
+    /// error that kept the previous one in place.
+    ///
+    /// Callbacks run on the polling task, or on the task calling
+    /// [`reload`](Self::reload). They must not block or subscribe further
+    /// callbacks.
+    pub fn subscribe(
+        &self,
+        callback: impl Fn(&Result<Arc<SandboxTemplate>>) + Send + Sync + 'static,
+    ) {
+        self.inner.subscribers.lock().push(Box::new(callback));
+    }
+
+    /// Rebuild the template now, whether or not the file changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the rebuild, in which case the previous template
+    /// stays in use.
+    pub async fn reload(&self) -> Result<Arc<SandboxTemplate>> {
+        self.inner
+            .rebuild(true)
+            .await
+            .unwrap_or_else(|| Ok(self.template()))
+    }
+}
+
+impl Inner {
+    /// Rebuild the template if the file changed since the last build, or
+    /// regardless with `force`. Returns `None` when nothing was rebuilt.
+    async fn rebuild(&self, force: bool) -> Option<Result<Arc<SandboxTemplate>>> {
+        let mut built = self.built.lock().await;
+        let version = file_version(&self.path).await;
+        if !force && version == *built {
+            return None;
+        }
+        *built = version;
+        let result = (self.builder)().build(&self.path).await.map(Arc::new);
+        if let Ok(template) = &result {
+            *self.current.write() = Arc::clone(template);
+        }
+        drop(built);
+        for subscriber in self.subscribers.lock().iter() {
+            subscriber(&result);
+        }
+        Some(result)
+    }
+}
+
+/// Rebuild the template of `inner` whenever its file changes, until the
+/// manager is dropped.
+async fn poll(inner: Weak<Inner>, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        if let Some(Err(error)) = inner.rebuild(false).await {
+            tracing::warn!(path = %inner.path.display(), %error, "template rebuild failed");
+        }
+    }
+}
+
+async fn file_version(path: &Path) -> FileVersion {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
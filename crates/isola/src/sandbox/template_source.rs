@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+#[cfg(feature = "remote-template")]
+use std::{fmt::Write as _, io, path::Path};
+
+#[cfg(feature = "remote-template")]
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "remote-template")]
+use crate::{
+    internal::module::cache::write_cache_file_atomic,
+    sandbox::{Error, Result, TrustPolicy},
+};
+
+/// Where [`SandboxTemplateBuilder::build_from_source`] reads the runtime
+/// component from.
+///
+/// [`SandboxTemplateBuilder::build_from_source`]: crate::sandbox::SandboxTemplateBuilder::build_from_source
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateSource {
+    /// Component file on the local filesystem, as for
+    /// [`build`](crate::sandbox::SandboxTemplateBuilder::build).
+    Path(PathBuf),
+    /// Component bytes already in memory, as for
+    /// [`build_from_bytes`](crate::sandbox::SandboxTemplateBuilder::build_from_bytes).
+    Bytes(Vec<u8>),
+    /// Component downloaded with `GET url` and pinned by its SHA-256 digest.
+    ///
+    /// `sha256` is the lowercase or uppercase hex digest of the component
+    /// bytes. Downloads whose digest differs are rejected. With a
+    /// [`cache`](crate::sandbox::SandboxTemplateBuilder::cache) directory,
+    /// verified components are kept under `downloads/` and reused without
+    /// another request. A configured
+    /// [`trust_policy`](crate::sandbox::SandboxTemplateBuilder::trust_policy)
+    /// verifies the detached signature fetched from `url` with `.sig`
+    /// appended.
+    ///
+    /// Available with the `remote-template` feature.
+    #[cfg(feature = "remote-template")]
+    Url {
+        /// HTTP or HTTPS URL of the component.
+        url: String,
+        /// Expected hex SHA-256 digest of the component bytes.
+        sha256: String,
+    },
+}
+
+/// Download the component at `url`, check it against `sha256`, and keep it
+/// in `cache_dir` for later builds.
+#[cfg(feature = "remote-template")]
+pub async fn fetch(
+    url: &str,
+    sha256: &str,
+    cache_dir: Option<&Path>,
+    trust_policy: Option<&dyn TrustPolicy>,
+) -> Result<Vec<u8>> {
+    let expected = sha256.to_ascii_lowercase();
+    if expected.len() != 64 || !expected.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{sha256}' is not a hex SHA-256 digest"),
+        )));
+    }
+
+    let cache_path = cache_dir.map(|dir| dir.join("downloads").join(format!("{expected}.wasm")));
+    let cached = match &cache_path {
+        Some(path) => tokio::fs::read(path)
+            .await
+            .ok()
+            .filter(|bytes| hex_digest(bytes) == expected),
+        None => None,
+    };
+    let bytes = match cached {
+        Some(bytes) => bytes,
+        None => download(url, &expected, cache_path.as_deref()).await?,
+    };
+
+    if let Some(policy) = trust_policy {
+        let denied = |reason: String| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("untrusted runtime artifact '{url}': {reason}"),
+            ))
+        };
+        let signature = get(&format!("{url}.sig"))
+            .await
+            .map_err(|e| denied(format!("cannot fetch signature: {e}")))?;
+        policy
+            .verify(&bytes, &signature)
+            .map_err(|e| denied(e.to_string()))?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(feature = "remote-template")]
+async fn download(url: &str, expected: &str, cache_path: Option<&Path>) -> Result<Vec<u8>> {
+    let bytes = get(url).await?;
+    let actual = hex_digest(&bytes);
+    if actual != expected {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("component at '{url}' has SHA-256 {actual}, expected {expected}"),
+        )));
+    }
+    if let Some(path) = cache_path {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(Error::from)?;
+        }
+        write_cache_file_atomic(path, &bytes).await?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(feature = "remote-template")]
+async fn get(url: &str) -> Result<Vec<u8>> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| Error::Io(io::Error::other(e)))?;
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| Error::Io(io::Error::other(e)))?;
+    Ok(body.to_vec())
+}
+
+#[cfg(feature = "remote-template")]
+fn hex_digest(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let mut out = String::with_capacity(digest.len() * 2);
+    for b in digest {
+        let _ = write!(&mut out, "{b:02x}");
+    }
+    out
+}
+
+#[cfg(all(test, feature = "remote-template"))]
+mod tests {
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{method, path},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn downloads_are_verified_and_cached() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/python3.wasm"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"component".to_vec()))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let url = format!("{}/python3.wasm", server.uri());
+        let digest = hex_digest(b"component");
+        let dir = tempfile::tempdir().expect("tempdir");
+
+        let err = fetch(&url, "abc", None, None)
+            .await
+            .expect_err("malformed digest");
+        assert!(err.to_string().contains("not a hex SHA-256 digest"));
+
+        let err = fetch(&url, &"0".repeat(64), Some(dir.path()), None)
+            .await
+            .expect_err("digest mismatch");
+        assert!(err.to_string().contains(&digest));
+
+        let bytes = fetch(&url, &digest.to_uppercase(), Some(dir.path()), None)
+            .await
+            .expect("download");
+        assert_eq!(bytes, b"component");
+        // Served from the cache; the mock expects no third request.
+        let bytes = fetch(&url, &digest, Some(dir.path()), None)
+            .await
+            .expect("cached");
+        assert_eq!(bytes, b"component");
+        assert!(
+            dir.path()
+                .join("downloads")
+                .join(format!("{digest}.wasm"))
+                .is_file()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::internal::sandbox::exports::GuestException;
+
+const HEADER: &str = "Traceback (most recent call last):";
+
+/// Python traceback recovered from a guest exception.
+///
+/// Reported field by field by the Python runtime. For runtimes that only
+/// report tracebacks as text, it is parsed host-side from the error message
+/// or, failing that, from the stderr the guest wrote during the failed call.
+/// Available through
+/// [`Error::traceback`](crate::sandbox::Error::traceback).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Traceback {
+    /// Exception type name as printed by Python, such as `ValueError` or
+    /// `mod.CustomError`.
+    pub exception_type: String,
+    /// Exception message; empty when the exception carried none.
+    pub message: String,
+    /// Stack frames, outermost call first.
+    pub frames: Vec<TracebackFrame>,
+}
+
+/// One stack frame of a [`Traceback`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TracebackFrame {
+    /// Source file name, such as `<string>` for evaluated scripts.
+    pub file: String,
+    /// 1-based line number, when reported.
+    pub line: Option<u32>,
+    /// Function name, such as `<module>` for top-level code.
+    pub function: Option<String>,
+}
+
+impl Traceback {
+    /// Convert the exception a guest runtime reported alongside its error.
+    pub(crate) fn from_guest(exception: GuestException) -> Self {
+        Self {
+            exception_type: exception.exception_type,
+            message: exception.message,
+            frames: exception
+                .frames
+                .into_iter()
+                .map(|frame| TracebackFrame {
+                    file: frame.file,
+                    line: frame.line,
+                    function: frame.function,
+                })
+                .collect(),
+        }
+    }
+
+    /// Parse the last Python traceback in `text`.
+    ///
+    /// When the traceback is not followed by an exception line, the first
+    /// line of `text` is used instead, which matches messages of the form
+    /// `"{exception}\n\n{traceback}"`.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let start = text.rfind(HEADER)?;
+        let mut frames = Vec::new();
+        let mut exception = None;
+        for line in text[start + HEADER.len()..].lines().skip(1) {
+            if line.is_empty() {
+                continue;
+            }
+            if !line.starts_with(char::is_whitespace) {
+                exception = Some(line);
+                break;
+            }
+            if let Some(frame) = TracebackFrame::parse(line) {
+                frames.push(frame);
+            }
+        }
+        let exception = exception.or_else(|| text[..start].lines().next())?;
+        let (exception_type, message) = exception
+            .split_once(": ")
+            .unwrap_or_else(|| (exception.trim_end_matches(':'), ""));
+        if exception_type.is_empty() || exception_type.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Self {
+            exception_type: exception_type.to_string(),
+            message: message.to_string(),
+            frames,
+        })
+    }
+}
+
+impl TracebackFrame {
+    /// Parse a `  File "name", line N, in function` line.
+    fn parse(line: &str) -> Option<Self> {
+        let rest = line.trim_start().strip_prefix("File \"")?;
+        let (file, rest) = rest.split_once('"')?;
+        let mut frame = Self {
+            file: file.to_string(),
+            line: None,
+            function: None,
+        };
+        for part in rest.split(", ").skip(1) {
+            if let Some(line) = part.strip_prefix("line ") {
+                frame.line = line.parse().ok();
+            } else if let Some(function) = part.strip_prefix("in ") {
+                frame.function = Some(function.to_string());
+            }
+        }
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(file: &str, line: u32, function: &str) -> TracebackFrame {
+        TracebackFrame {
+            file: file.to_string(),
+            line: Some(line),
+            function: Some(function.to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_the_last_stderr_traceback() {
+        let stderr = "\
+warming up
+Traceback (most recent call last):
+  File \"<string>\", line 2, in <module>
+KeyError: 'a'
+
+During handling of the above exception, another exception occurred:
+
+Traceback (most recent call last):
+  File \"<string>\", line 4, in <module>
+  File \"/lib/app.py\", line 10, in handler
+    raise app.Invalid(\"bad: input\")
+    ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+app.Invalid: bad: input
+";
+        let traceback = Traceback::parse(stderr).expect("traceback");
+        assert_eq!(traceback.exception_type, "app.Invalid");
+        assert_eq!(traceback.message, "bad: input");
+        assert_eq!(
+            traceback.frames,
+            [
+                frame("<string>", 4, "<module>"),
+                frame("/lib/app.py", 10, "handler"),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_leading_exception_line() {
+        let message = "\
+ZeroDivisionError: division by zero
+
+Traceback (most recent call last):
+  File \"<string>\", line 1, in <module>
+";
+        let traceback = Traceback::parse(message).expect("traceback");
+        assert_eq!(traceback.exception_type, "ZeroDivisionError");
+        assert_eq!(traceback.message, "division by zero");
+        assert_eq!(traceback.frames, [frame("<string>", 1, "<module>")]);
+
+        let bare = Traceback::parse("Traceback (most recent call last):\nStopIteration\n")
+            .expect("traceback");
+        assert_eq!(bare.exception_type, "StopIteration");
+        assert!(bare.message.is_empty() && bare.frames.is_empty());
+
+        assert!(Traceback::parse("Error: no python here").is_none());
+        assert!(Traceback::parse("Traceback (most recent call last):\n").is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    host::BoxError,
+    sandbox::{Error, Result},
+};
+
+/// Builder-supplied policy that decides whether a runtime artifact is trusted.
+///
+/// When a policy is configured with
+/// [`SandboxTemplateBuilder::trust_policy`](crate::sandbox::SandboxTemplateBuilder::trust_policy),
+/// the builder reads a detached signature from the artifact path with `.sig`
+/// appended (for example `python3.wasm.sig`) and passes it to
+/// [`verify`](Self::verify) together with the exact bytes that will be
+/// compiled. Implement this trait to plug in minisign, sigstore, or a key
+/// management service.
+///
+/// Templates built from in-memory bytes have no signature file, so
+/// [`build_from_bytes`](crate::sandbox::SandboxTemplateBuilder::build_from_bytes)
+/// fails whenever a policy is configured.
+pub trait TrustPolicy: Send + Sync + 'static {
+    /// Accept or reject `artifact` based on its detached `signature`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing why the artifact is not trusted.
+    fn verify(&self, artifact: &[u8], signature: &[u8]) -> core::result::Result<(), BoxError>;
+}
+
+/// [`TrustPolicy`] accepting raw Ed25519 signatures from a fixed set of keys.
+///
+/// The signature file must contain the 64-byte signature over the artifact
+/// bytes. The artifact is trusted if any configured key verifies it.
+///
+/// Available with the `signature` feature.
+#[cfg(feature = "signature")]
+#[derive(Clone, Debug)]
+pub struct Ed25519TrustPolicy {
+    keys: Vec<[u8; 32]>,
+}
+
+#[cfg(feature = "signature")]
+impl Ed25519TrustPolicy {
+    /// Create a policy that trusts signatures from any of `keys`.
+    #[must_use]
+    pub fn new(keys: impl IntoIterator<Item = [u8; 32]>) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(feature = "signature")]
+impl TrustPolicy for Ed25519TrustPolicy {
+    fn verify(&self, artifact: &[u8], signature: &[u8]) -> core::result::Result<(), BoxError> {
+        use ring::signature::{ED25519, UnparsedPublicKey};
+
+        if signature.len() != 64 {
+            return Err(format!(
+                "expected a 64-byte Ed25519 signature, found {} bytes",
+                signature.len()
+            )
+            .into());
+        }
+        if self.keys.iter().any(|key| {
+            UnparsedPublicKey::new(&ED25519, key)
+                .verify(artifact, signature)
+                .is_ok()
+        }) {
+            Ok(())
+        } else {
+            Err("signature does not match any trusted key".into())
+        }
+    }
+}
+
+fn signature_path(artifact_path: &Path) -> PathBuf {
+    let mut path = artifact_path.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+/// Check `artifact` against `policy` using the detached signature stored next
+/// to `artifact_path`.
+pub async fn verify_artifact(
+    policy: &dyn TrustPolicy,
+    artifact_path: &Path,
+    artifact: &[u8],
+) -> Result<()> {
+    let sig_path = signature_path(artifact_path);
+    let denied = |reason: String| {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!(
+                "untrusted runtime artifact '{}': {reason}",
+                artifact_path.display()
+            ),
+        ))
+    };
+    let signature = tokio::fs::read(&sig_path)
+        .await
+        .map_err(|e| denied(format!("cannot read '{}': {e}", sig_path.display())))?;
+    policy
+        .verify(artifact, &signature)
+        .map_err(|e| denied(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::ErrorKind;
+
+    struct ExactSignature(&'static [u8]);
+
+    impl TrustPolicy for ExactSignature {
+        fn verify(&self, _artifact: &[u8], signature: &[u8]) -> core::result::Result<(), BoxError> {
+            if signature == self.0 {
+                Ok(())
+            } else {
+                Err("bad signature".into())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn artifacts_require_an_accepted_detached_signature() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let wasm = dir.path().join("runtime.wasm");
+        let policy = ExactSignature(b"ok");
+
+        let missing = verify_artifact(&policy, &wasm, b"wasm")
+            .await
+            .expect_err("missing signature rejected");
+        assert_eq!(missing.kind(), ErrorKind::PolicyDenied);
+
+        std::fs::write(dir.path().join("runtime.wasm.sig"), b"nope").expect("write sig");
+        let rejected = verify_artifact(&policy, &wasm, b"wasm")
+            .await
+            .expect_err("bad signature rejected");
+        assert!(rejected.to_string().contains("bad signature"));
+
+        std::fs::write(dir.path().join("runtime.wasm.sig"), b"ok").expect("write sig");
+        verify_artifact(&policy, &wasm, b"wasm")
+            .await
+            .expect("signature accepted");
+    }
+
+    #[cfg(feature = "signature")]
+    #[test]
+    fn ed25519_policy_verifies_against_trusted_keys() {
+        use ring::{
+            rand::SystemRandom,
+            signature::{Ed25519KeyPair, KeyPair as _},
+        };
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).expect("generate key");
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("parse key");
+        let public: [u8; 32] = pair.public_key().as_ref().try_into().expect("key length");
+        let signature = pair.sign(b"artifact");
+
+        let policy = Ed25519TrustPolicy::new([public]);
+        policy
+            .verify(b"artifact", signature.as_ref())
+            .expect("trusted signature");
+        assert!(policy.verify(b"tampered", signature.as_ref()).is_err());
+        assert!(
+            Ed25519TrustPolicy::new([[0; 32]])
+                .verify(b"artifact", signature.as_ref())
+                .is_err()
+        );
+    }
+}
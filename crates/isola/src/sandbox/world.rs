@@ -0,0 +1,249 @@
+use wasmtime::{
+    Engine,
+    component::{Component, Linker, types::ComponentItem},
+};
+
+use super::{Error, Result};
+use crate::internal::sandbox::{SandboxPre, interface_name};
+
+/// Interface the runtime calls back into the host through.
+const HOST_IMPORT: &str = "isola:script/host";
+
+/// Items of [`HOST_IMPORT`] the host provides.
+const HOST_ITEMS: &[&str] = &[
+    "value",
+    "value-iterator",
+    "[method]value-iterator.read",
+    "emit-type",
+    "blocking-emit",
+    "hostcall",
+];
+
+/// WASI packages the host provides and the version prefixes it provides them
+/// at.
+const WASI_PACKAGES: &[(&str, &[&str])] = &[
+    ("wasi:cli", &["0.2.", "0.3."]),
+    ("wasi:clocks", &["0.2.", "0.3."]),
+    ("wasi:filesystem", &["0.2.", "0.3."]),
+    ("wasi:io", &["0.2."]),
+    ("wasi:random", &["0.2.", "0.3."]),
+    ("wasi:sockets", &["0.2.", "0.3."]),
+    ("wasi:http", &["0.3."]),
+    ("wasi:logging", &["0.1.0-draft"]),
+];
+
+/// Optional parts of the `isola:script` world a runtime component uses.
+///
+/// Components only import the host interfaces they need, so a bundle built
+/// for an older world that lacks, say, hostcalls still loads; the sandbox
+/// simply never receives those calls. Likewise only the `runtime` export is
+/// required: operations that need an optional export the component lacks
+/// fail with [`Error::IncompatibleWorld`] naming it. Returned by
+/// [`SandboxTemplate::world`](super::SandboxTemplate::world).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "each flag reports an independent optional part of the world"
+)]
+pub struct ScriptWorld {
+    /// The runtime can make hostcalls to [`Host::hostcall`](crate::host::Host::hostcall).
+    pub hostcall: bool,
+    /// The runtime can send HTTP requests through
+    /// [`Host::http_request`](crate::host::Host::http_request).
+    pub http: bool,
+    /// The runtime reports log records through `wasi:logging`.
+    pub logging: bool,
+    /// The runtime reports guest exceptions field by field; otherwise
+    /// [`Traceback`](super::Traceback)s are parsed from error messages and
+    /// stderr.
+    pub exceptions: bool,
+    /// The runtime takes several
+    /// [`preludes`](super::SandboxTemplateBuilder::add_prelude); otherwise at
+    /// most one.
+    pub preludes: bool,
+    /// The runtime can re-read its directories after
+    /// [`mount`](super::Sandbox::mount) and
+    /// [`unmount`](super::Sandbox::unmount).
+    pub preopens: bool,
+    /// The runtime implements [`eval_expr`](super::Sandbox::eval_expr).
+    pub expressions: bool,
+    /// The runtime implements [`load_module`](super::Sandbox::load_module).
+    pub modules: bool,
+    /// The runtime implements [`eval_package`](super::Sandbox::eval_package).
+    pub packages: bool,
+    /// The runtime records [`coverage`](super::CallOptions::coverage).
+    pub coverage: bool,
+    /// The runtime runs a [`call_many`](super::Sandbox::call_many) batch in a
+    /// single guest invocation; otherwise each call is made separately.
+    pub batch: bool,
+}
+
+/// Check that the host provides every interface `component` imports and that
+/// it exports every function of the runtime interfaces the host calls.
+///
+/// Export types are checked when a sandbox is instantiated.
+///
+/// With `extended`, imports the crate does not know are left to the linker
+/// extensions to define.
+///
+/// # Errors
+///
+/// Returns [`Error::IncompatibleWorld`] listing every mismatch.
+pub fn check_world(engine: &Engine, component: &Component, extended: bool) -> Result<ScriptWorld> {
+    let ty = component.component_type();
+    let mut world = ScriptWorld::default();
+    let mut missing = Vec::new();
+
+    for (name, import) in ty.imports(engine) {
+        if name == HOST_IMPORT {
+            let ComponentItem::ComponentInstance(instance) = import.ty else {
+                missing.push(format!("import {name}"));
+                continue;
+            };
+            for (item, _) in instance.exports(engine) {
+                if HOST_ITEMS.contains(&item) {
+                    world.hostcall |= item == "hostcall";
+                } else if !extended {
+                    missing.push(format!("import {name}#{item}"));
+                }
+            }
+        } else if provides_wasi(name) {
+            world.http |= name.starts_with("wasi:http/");
+            world.logging |= name.starts_with("wasi:logging/");
+        } else if !extended {
+            missing.push(format!("import {name}"));
+        }
+    }
+
+    match SandboxPre::new(stub_instance_pre(component)?) {
+        Ok(pre) => {
+            world.exceptions = pre.exceptions.is_some();
+            world.preludes = pre.preludes.is_some();
+            world.preopens = pre.preopens.is_some();
+            world.expressions = pre.expressions.is_some();
+            world.modules = pre.modules.is_some();
+            world.packages = pre.packages.is_some();
+            world.coverage = pre.coverage.is_some();
+            world.batch = pre.batch.is_some();
+        }
+        Err(exports) => missing.extend(exports),
+    }
+    if missing.is_empty() {
+        Ok(world)
+    } else {
+        Err(Error::IncompatibleWorld { missing })
+    }
+}
+
+/// Pre-instantiate `component` with every import a trapping stub, so its
+/// exports can be looked up without instantiating it.
+fn stub_instance_pre(component: &Component) -> Result<wasmtime::component::InstancePre<()>> {
+    let mut linker = Linker::<()>::new(component.engine());
+    linker
+        .define_unknown_imports_as_traps(component)
+        .map_err(Error::Wasm)?;
+    linker.instantiate_pre(component).map_err(Error::Wasm)
+}
+
+/// Error for an operation that needs the optional export `interface`, such
+/// as `expressions`, which the runtime component lacks.
+pub fn missing_export(interface: &str) -> Error {
+    Error::IncompatibleWorld {
+        missing: vec![format!("export {}", interface_name(interface))],
+    }
+}
+
+/// Return whether the host provides the WASI interface `name`, such as
+/// `wasi:cli/environment@0.3.0`.
+fn provides_wasi(name: &str) -> bool {
+    let Some((package, rest)) = name.split_once('/') else {
+        return false;
+    };
+    let Some((_, version)) = rest.split_once('@') else {
+        return false;
+    };
+    WASI_PACKAGES.iter().any(|(provided, versions)| {
+        *provided == package && versions.iter().any(|prefix| version.starts_with(prefix))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::{
+        ComponentImportSection, ComponentTypeRef, ComponentTypeSection, ComponentValType,
+        InstanceType,
+    };
+
+    use super::*;
+
+    /// Component importing instances `imports`, each exporting a `func()`
+    /// named after every given item, and exporting nothing.
+    fn component_importing(imports: &[(&str, &[&str])]) -> Vec<u8> {
+        let mut component = wasm_encoder::Component::new();
+        let mut types = ComponentTypeSection::new();
+        for (_, items) in imports {
+            let mut instance = InstanceType::new();
+            instance
+                .ty()
+                .function()
+                .params::<_, ComponentValType>([])
+                .result(None);
+            for item in *items {
+                instance.export(*item, ComponentTypeRef::Func(0));
+            }
+            types.instance(&instance);
+        }
+        component.section(&types);
+        let mut section = ComponentImportSection::new();
+        for (index, (name, _)) in (0..).zip(imports) {
+            section.import(*name, ComponentTypeRef::Instance(index));
+        }
+        component.section(&section);
+        component.finish()
+    }
+
+    #[test]
+    fn check_world_lists_every_mismatch() {
+        let engine = Engine::default();
+        let wasm = component_importing(&[
+            ("wasi:cli/environment@0.3.0", &["get-environment"]),
+            ("wasi:logging/logging@0.1.0-draft", &["log"]),
+            ("wasi:http/types@0.2.0", &["new-fields"]),
+            ("isola:script/host", &["hostcall", "open-websocket"]),
+            ("isola:script/websocket", &["send"]),
+        ]);
+        let component = Component::new(&engine, &wasm).unwrap();
+
+        let Err(Error::IncompatibleWorld { missing }) = check_world(&engine, &component, false)
+        else {
+            panic!("component must not be compatible");
+        };
+        assert_eq!(
+            missing,
+            [
+                "import wasi:http/types@0.2.0",
+                "import isola:script/host#open-websocket",
+                "import isola:script/websocket",
+                "export isola:script/runtime",
+            ]
+        );
+
+        let Err(Error::IncompatibleWorld { missing }) = check_world(&engine, &component, true)
+        else {
+            panic!("extensions do not provide runtime exports");
+        };
+        assert_eq!(missing, ["export isola:script/runtime"]);
+    }
+
+    #[test]
+    fn provides_wasi_matches_package_and_version() {
+        assert!(provides_wasi("wasi:io/streams@0.2.6"));
+        assert!(provides_wasi(
+            "wasi:clocks/monotonic-clock@0.3.0-rc-2025-09-16"
+        ));
+        assert!(!provides_wasi("wasi:io/streams@0.3.0"));
+        assert!(!provides_wasi("wasi:keyvalue/store@0.2.0"));
+        assert!(!provides_wasi("wasi:cli/environment"));
+    }
+}
@@ -119,6 +119,27 @@ impl Value {
         Ok(T::deserialize(&mut deserializer)?)
     }
 
+    /// Split an encoded map with text keys into its entries, in order.
+    pub(crate) fn map_entries(&self) -> Result<Vec<(String, Self)>, Error> {
+        let input = self.as_cbor();
+        let mut decoder = minicbor::Decoder::new(input);
+        let len = decoder.map().map_err(|_| Error::NotAMap)?;
+        let mut entries = Vec::with_capacity(len.map_or(0, |len| len.min(64) as usize));
+        let mut remaining = len;
+        while remaining.map_or_else(
+            || !matches!(decoder.datatype(), Ok(minicbor::data::Type::Break)),
+            |n| n > 0,
+        ) {
+            let key = decoder.str().map_err(|_| Error::NotAMap)?.to_string();
+            let start = decoder.position();
+            decoder.skip().map_err(|_| Error::NotAMap)?;
+            let value = Self(self.0.slice(start..decoder.position()));
+            entries.push((key, value));
+            remaining = remaining.map(|n| n - 1);
+        }
+        Ok(entries)
+    }
+
     fn to_json_bytes(&self) -> Result<Vec<u8>, Error> {
         let mut o = vec![];
         TaggedCbor::new(self.as_ref())
@@ -432,6 +453,10 @@ pub enum Error {
     /// Writing the converted representation failed.
     #[error("I/O error")]
     Io(#[from] io::Error),
+    /// A value expected to hold keyword arguments was not a map with text
+    /// keys.
+    #[error("expected a map with string keys")]
+    NotAMap,
 }
 
 #[cfg(feature = "serde")]
@@ -196,7 +196,7 @@ async fn integration_js_guest_exception_surface() -> Result<()> {
     let err = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(5))
         .await
         .expect_err("expected exception from guest function");
-    let IsolaError::UserCode { message } = err else {
+    let IsolaError::UserCode { message, .. } = err else {
         panic!("expected guest error, got {err:?}");
     };
     assert!(
@@ -2,6 +2,7 @@ use std::{
     env,
     path::{Path, PathBuf},
     sync::{Arc, Once, OnceLock},
+    time::Duration,
 };
 
 use anyhow::{Context, Result};
@@ -9,7 +10,10 @@ use futures::TryStreamExt;
 use http::header::HOST;
 use isola::{
     host::{BoxError, Host, HttpBodyStream, HttpRequest, HttpResponse},
-    sandbox::{DirPerms, FilePerms, SandboxTemplate},
+    sandbox::{
+        Arg, CallOutput, DirPerms, Error as IsolaError, FilePerms, Sandbox, SandboxOptions,
+        SandboxPool, SandboxPoolConfig, SandboxTemplate, SandboxTemplateBuilder,
+    },
     value::Value,
 };
 use reqwest::Client;
@@ -143,7 +147,9 @@ fn build_module_lock() -> &'static tokio::sync::Mutex<()> {
     BUILD_MODULE_LOCK.get_or_init(|| tokio::sync::Mutex::new(()))
 }
 
-async fn build_module_with_policy(max_memory: Option<usize>) -> Result<Option<SandboxTemplate>> {
+pub async fn build_module_with(
+    configure: impl FnOnce(SandboxTemplateBuilder) -> SandboxTemplateBuilder,
+) -> Result<Option<SandboxTemplate>> {
     // Serialize compilation because tests can run in parallel and share cache
     // paths.
     let _build_guard = build_module_lock().lock().await;
@@ -155,15 +161,9 @@ async fn build_module_with_policy(max_memory: Option<usize>) -> Result<Option<Sa
         .ok_or_else(|| anyhow::anyhow!("integration wasm bundle has no parent directory"))?
         .join("cache");
 
-    let mut builder = SandboxTemplate::builder()
-        .prelude(Some("import sandbox.asyncio".to_string()))
-        .cache(Some(cache_dir))
-        .mount(&lib_dir, "/lib", DirPerms::READ, FilePerms::READ);
-    if let Some(max_memory) = max_memory {
-        builder = builder.max_memory(max_memory);
-    }
+    let builder = base_builder(&lib_dir).cache(Some(cache_dir));
 
-    let module = builder
+    let module = configure(builder)
         .build(&wasm)
         .await
         .context("failed to build module from integration wasm bundle")?;
@@ -171,10 +171,91 @@ async fn build_module_with_policy(max_memory: Option<usize>) -> Result<Option<Sa
     Ok(Some(module))
 }
 
+fn base_builder(lib_dir: &Path) -> SandboxTemplateBuilder {
+    SandboxTemplate::builder()
+        .prelude(Some("import sandbox.asyncio".to_string()))
+        .mount(lib_dir, "/lib", DirPerms::READ, FilePerms::READ)
+}
+
+/// Load a template exported with `SandboxTemplate::serialize` using the same
+/// builder settings as [`build_module_with`].
+pub async fn load_precompiled_module_with(
+    artifact: &Path,
+    configure: impl FnOnce(SandboxTemplateBuilder) -> SandboxTemplateBuilder,
+) -> Result<Option<SandboxTemplate>> {
+    let Some((_, lib_dir)) = resolve_prereqs()? else {
+        return Ok(None);
+    };
+    // SAFETY: the tests only load artifacts they serialized themselves.
+    let module = unsafe { configure(base_builder(&lib_dir)).build_precompiled(artifact) }
+        .await
+        .context("failed to load precompiled module")?;
+    Ok(Some(module))
+}
+
+/// Return the integration wasm bundle and a factory for builders configured
+/// like [`build_module_with`], caching compiled artifacts in `cache_dir`.
+pub fn module_source(
+    cache_dir: &Path,
+) -> Result<
+    Option<(
+        PathBuf,
+        impl Fn() -> SandboxTemplateBuilder + Send + Sync + 'static,
+    )>,
+> {
+    let Some((wasm, lib_dir)) = resolve_prereqs()? else {
+        return Ok(None);
+    };
+    let cache_dir = cache_dir.to_path_buf();
+    Ok(Some((wasm, move || {
+        base_builder(&lib_dir).cache(Some(cache_dir.clone()))
+    })))
+}
+
 pub async fn build_module() -> Result<Option<SandboxTemplate>> {
-    build_module_with_policy(None).await
+    build_module_with(|builder| builder).await
 }
 
 pub async fn build_module_with_max_memory(max_memory: usize) -> Result<Option<SandboxTemplate>> {
-    build_module_with_policy(Some(max_memory)).await
+    build_module_with(|builder| builder.max_memory(max_memory)).await
+}
+
+pub async fn build_module_with_native_async() -> Result<Option<SandboxTemplate>> {
+    build_module_with(|builder| builder.native_async(true)).await
+}
+
+pub async fn call_with_timeout<I>(
+    sandbox: &mut Sandbox<TestHost>,
+    function: &str,
+    args: I,
+    timeout: Duration,
+) -> std::result::Result<CallOutput, IsolaError>
+where
+    I: IntoIterator<Item = Arg>,
+{
+    tokio::time::timeout(timeout, sandbox.call(function, args))
+        .await
+        .unwrap_or_else(|_| {
+            Err(IsolaError::Other(
+                anyhow::anyhow!("sandbox call timed out after {}ms", timeout.as_millis()).into(),
+            ))
+        })
+}
+
+/// Pool holding a single sandbox, so every checkout after the first gets the
+/// previous one back once it has been recycled.
+pub async fn single_sandbox_pool(
+    module: &Arc<SandboxTemplate>,
+    options: SandboxOptions,
+) -> Result<SandboxPool<TestHost>> {
+    SandboxPool::new(
+        Arc::clone(module),
+        SandboxPoolConfig::default()
+            .min_idle(1)
+            .max_size(1)
+            .options(options),
+        TestHost::default,
+    )
+    .await
+    .context("failed to create pool")
 }
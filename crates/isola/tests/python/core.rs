@@ -1,16 +1,28 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
+use futures::StreamExt as _;
 use isola::{
-    host::{OutputEvent, OutputTarget},
+    host::{Host, ManualClock, OutputEvent, OutputTarget, SeededEntropy},
     sandbox::{
-        Arg, CallOutput, DirPerms, Error as IsolaError, FilePerms, Sandbox, SandboxOptions, args,
+        CacheBackend, CacheStatus, CallOptions, CallOutput, DirPerms, Error as IsolaError,
+        ErrorKind, FilePerms, OutputLimit, RUNTIME_ABI_VERSION, SandboxOptions, SandboxPool,
+        SandboxPoolConfig, SharedSandbox, TemplateManager, WasiInterface, args,
     },
 };
 use parking_lot::Mutex;
 use tempfile::tempdir;
 
-use super::common::{TestHost, build_module, build_module_with_max_memory};
+use super::common::{
+    TestHost, build_module, build_module_with, build_module_with_max_memory, call_with_timeout,
+    load_precompiled_module_with, module_source, single_sandbox_pool,
+};
 
 const CAP_NEIGHBORHOOD_BYTES: usize = 1024 * 1024;
 const MEMORY_CAP_BYTES: usize = 64 * 1024 * 1024;
@@ -35,24 +47,6 @@ impl CollectLogsSink {
     }
 }
 
-async fn call_with_timeout<I>(
-    sandbox: &mut Sandbox<TestHost>,
-    function: &str,
-    args: I,
-    timeout: Duration,
-) -> std::result::Result<CallOutput, IsolaError>
-where
-    I: IntoIterator<Item = Arg>,
-{
-    tokio::time::timeout(timeout, sandbox.call(function, args))
-        .await
-        .unwrap_or_else(|_| {
-            Err(IsolaError::Other(
-                anyhow::anyhow!("sandbox call timed out after {}ms", timeout.as_millis()).into(),
-            ))
-        })
-}
-
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
 async fn integration_python_eval_and_call_roundtrip() -> Result<()> {
@@ -272,7 +266,7 @@ async fn integration_python_call_with_bounded_output_channel() -> Result<()> {
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_streaming_output() -> Result<()> {
+async fn integration_python_eval_expr_returns_value() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
@@ -282,35 +276,32 @@ async fn integration_python_streaming_output() -> Result<()> {
         .context("failed to instantiate sandbox")?;
 
     sandbox
-        .eval_script(
-            "def main():\n\tfor i in range(3):\n\t\tyield i",
-            OutputTarget::discard(),
-        )
+        .eval_script("x = [1, 2, 3]", OutputTarget::discard())
         .await
-        .context("failed to evaluate streaming script")?;
-
-    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .context("failed to evaluate script")?;
+    let value = sandbox
+        .eval_expr("1 + len(x)")
         .await
-        .context("failed to call streaming function")?;
+        .context("failed to evaluate expression")?;
+    assert_eq!(value.to_serde::<i64>()?, 4);
 
-    assert_eq!(output.items.len(), 3, "expected three partial outputs");
-    let mut values = Vec::with_capacity(output.items.len());
-    for item in &output.items {
-        values.push(
-            item.to_serde::<i64>()
-                .context("failed to decode partial output")?,
-        );
-    }
-    assert_eq!(values, vec![0, 1, 2]);
-
-    assert!(output.result.is_none(), "expected null end output");
+    let err = sandbox
+        .eval_expr("undefined_name")
+        .await
+        .expect_err("unknown names raise");
+    assert!(err.to_string().contains("undefined_name"), "{err}");
+    let err = sandbox
+        .eval_expr("iter(x)")
+        .await
+        .expect_err("iterators are not single values");
+    assert_eq!(err.kind(), ErrorKind::Io);
 
     Ok(())
 }
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_failed_emit_does_not_corrupt_next_output() -> Result<()> {
+async fn integration_python_named_modules_import_each_other() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
@@ -319,43 +310,67 @@ async fn integration_python_failed_emit_does_not_corrupt_next_output() -> Result
         .await
         .context("failed to instantiate sandbox")?;
 
+    sandbox
+        .load_module(
+            "helpers",
+            "def double(x):\n    return 2 * x\n",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to load helpers")?;
+    sandbox
+        .load_module(
+            "app",
+            "from helpers import double\n\ndef main(x):\n    return double(x) + 1\n",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to load app")?;
     sandbox
         .eval_script(
-            "import _isola_sys\n\
-             def main():\n\
-             \ttry:\n\
-             \t\t_isola_sys.emit([\"x\" * 2048, object()])\n\
-             \texcept ValueError:\n\
-             \t\tpass\n\
-             \t_isola_sys.emit(\"valid\")\n\
-             \treturn \"done\"",
+            "import app\nhelper_leaked = 'double' in globals()",
             OutputTarget::discard(),
         )
         .await
-        .context("failed to evaluate failed emit script")?;
+        .context("failed to import app")?;
+    assert_eq!(
+        sandbox.eval_expr("app.main(20)").await?.to_serde::<i64>()?,
+        41
+    );
+    assert!(
+        !sandbox
+            .eval_expr("helper_leaked")
+            .await?
+            .to_serde::<bool>()?
+    );
 
-    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(5))
+    let err = sandbox
+        .load_module(
+            "helpers",
+            "raise ValueError('broken')",
+            OutputTarget::discard(),
+        )
         .await
-        .context("failed to call failed emit function")?;
-    assert_eq!(output.items.len(), 1, "failed emit should not reach sink");
-    let item: String = output.items[0]
-        .to_serde()
-        .context("failed to decode valid partial output")?;
-    assert_eq!(item, "valid");
-    let result: String = output
-        .result
-        .as_ref()
-        .context("expected end output")?
-        .to_serde()
-        .context("failed to decode final output")?;
-    assert_eq!(result, "done");
+        .expect_err("failing module");
+    assert!(err.to_string().contains("broken"), "{err}");
+    assert_eq!(
+        sandbox
+            .eval_expr("helpers.double(3)")
+            .await?
+            .to_serde::<i64>()?,
+        6
+    );
+    sandbox
+        .load_module("a.b", "", OutputTarget::discard())
+        .await
+        .expect_err("dotted names are rejected");
 
     Ok(())
 }
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_eval_script_logs_to_sink() -> Result<()> {
+async fn integration_python_streaming_output() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
@@ -364,46 +379,36 @@ async fn integration_python_eval_script_logs_to_sink() -> Result<()> {
         .await
         .context("failed to instantiate sandbox")?;
 
-    let logs = Arc::new(Mutex::new(Vec::new()));
-    let sink = CollectLogsSink::new(logs.clone());
-    match tokio::time::timeout(
-        Duration::from_secs(2),
-        sandbox.eval_script(
-            "print('eval-stdout')\nimport sandbox.logging\nsandbox.logging.info('eval-log')",
-            sink.into_target(),
-        ),
-    )
-    .await
-    {
-        Ok(result) => result.context("failed to evaluate script")?,
-        Err(_) => {
-            return Err(anyhow::anyhow!("sandbox eval timed out after {}ms", 2_000));
-        }
-    }
-    {
-        let logs = logs.lock();
+    sandbox
+        .eval_script(
+            "def main():\n\tfor i in range(3):\n\t\tyield i",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate streaming script")?;
 
-        assert!(
-            logs.iter()
-                .any(|(context, message)| context == "stdout" && message.contains("eval-stdout")),
-            "expected eval stdout log in sink, logs: {:?}",
-            *logs
-        );
-        assert!(
-            logs.iter()
-                .any(|(context, message)| context == "info" && message.contains("eval-log")),
-            "expected eval logging event in sink, logs: {:?}",
-            *logs
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed to call streaming function")?;
+
+    assert_eq!(output.items.len(), 3, "expected three partial outputs");
+    let mut values = Vec::with_capacity(output.items.len());
+    for item in &output.items {
+        values.push(
+            item.to_serde::<i64>()
+                .context("failed to decode partial output")?,
         );
-        drop(logs);
     }
+    assert_eq!(values, vec![0, 1, 2]);
+
+    assert!(output.result.is_none(), "expected null end output");
 
     Ok(())
 }
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_large_stdout_output_is_not_truncated() -> Result<()> {
+async fn integration_python_output_limits_fail_the_call() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
@@ -411,38 +416,43 @@ async fn integration_python_large_stdout_output_is_not_truncated() -> Result<()>
         .instantiate(TestHost::default(), SandboxOptions::default())
         .await
         .context("failed to instantiate sandbox")?;
-
     sandbox
         .eval_script(
-            format!(
-                "def main():\n\
-                 \tpayload = 'x' * {LARGE_STDOUT_BYTES}\n\
-                 \tprint(payload, end='')\n\
-                 \treturn len(payload)"
-            ),
+            "def many():\n\
+             \twhile True:\n\
+             \t\tyield 1\n\
+             def big():\n\
+             \treturn 'x' * 100000",
             OutputTarget::discard(),
         )
         .await
-        .context("failed to evaluate large stdout script")?;
+        .context("failed to evaluate script")?;
 
-    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(10))
+    let err = sandbox
+        .call_collect("many", [], CallOptions::default().max_output_items(3))
         .await
-        .context("failed to call large stdout function")?;
+        .expect_err("unbounded generator must hit the item limit");
+    assert!(matches!(
+        err,
+        IsolaError::OutputLimitExceeded(OutputLimit::Items(3))
+    ));
+    assert_eq!(err.kind(), ErrorKind::PolicyDenied);
 
-    let emitted_len: usize = output
-        .result
-        .as_ref()
-        .context("expected exactly one end output")?
-        .to_serde()
-        .context("failed to decode end output")?;
-    assert_eq!(emitted_len, LARGE_STDOUT_BYTES);
+    let err = sandbox
+        .call_collect("big", [], CallOptions::default().max_output_bytes(1024))
+        .await
+        .expect_err("large result must hit the byte limit");
+    assert!(matches!(
+        err,
+        IsolaError::OutputLimitExceeded(OutputLimit::Bytes(1024))
+    ));
 
     Ok(())
 }
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_argument_cbor_path() -> Result<()> {
+async fn integration_python_call_many_reports_each_call() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
@@ -450,119 +460,223 @@ async fn integration_python_argument_cbor_path() -> Result<()> {
         .instantiate(TestHost::default(), SandboxOptions::default())
         .await
         .context("failed to instantiate sandbox")?;
-
     sandbox
         .eval_script(
-            "def main(i, s):\n\treturn (i + 1, s.upper())",
+            "import sandbox.logging\n\
+             def add(a, b):\n\
+             \tsandbox.logging.info(f'add {a}')\n\
+             \tyield a\n\
+             \treturn a + b\n\
+             def fail():\n\
+             \tyield 0\n\
+             \traise ValueError('boom')",
             OutputTarget::discard(),
         )
         .await
-        .context("failed to evaluate argument script")?;
+        .context("failed to evaluate script")?;
 
-    let args = args![41_i64, s = "hello"]?;
-    let output = call_with_timeout(&mut sandbox, "main", args, Duration::from_secs(2))
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let mut results = sandbox
+        .call_many_with_logs(
+            [
+                ("add", Vec::from(args![1_i64, 2_i64]?)),
+                ("fail", Vec::new()),
+                ("add", Vec::from(args![10_i64, 20_i64]?)),
+            ],
+            CollectLogsSink::new(Arc::clone(&logs)).into_target(),
+        )
         .await
-        .context("failed to call argument function")?;
+        .context("batch must run")?
+        .into_iter();
+
+    let decode = |output: CallOutput| -> Result<(Vec<i64>, i64)> {
+        let items = output
+            .items
+            .iter()
+            .map(isola::value::Value::to_serde)
+            .collect::<Result<_, _>>()?;
+        let result = output.result.context("missing result")?.to_serde()?;
+        Ok((items, result))
+    };
+    let first = results.next().context("missing first entry")??;
+    assert_eq!(decode(first)?, (vec![1], 3));
+    let err = results
+        .next()
+        .context("missing second entry")?
+        .expect_err("failing call must report its error");
+    assert_eq!(err.kind(), ErrorKind::GuestException);
+    let third = results.next().context("missing third entry")??;
+    assert_eq!(
+        decode(third)?,
+        (vec![10], 30),
+        "calls after a failure still run"
+    );
+    assert!(results.next().is_none());
+    let messages: Vec<_> = logs
+        .lock()
+        .iter()
+        .map(|(_, message)| message.clone())
+        .collect();
+    assert!(
+        messages.iter().any(|message| message.contains("add 1"))
+            && messages.iter().any(|message| message.contains("add 10")),
+        "logs of every call must reach the sink: {messages:?}"
+    );
 
-    assert!(output.items.is_empty(), "expected no partial outputs");
-    let value: (i64, String) = output
-        .result
-        .as_ref()
-        .context("expected exactly one end output")?
-        .to_serde()
-        .context("failed to decode argument result")?;
-    assert_eq!(value, (42, "HELLO".to_string()));
     Ok(())
 }
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_numpy_typed_array_cbor_path() -> Result<()> {
+async fn integration_python_shared_sandbox_queues_concurrent_calls() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
     let mut sandbox = module
         .instantiate(TestHost::default(), SandboxOptions::default())
-        .await?;
+        .await
+        .context("failed to instantiate sandbox")?;
     sandbox
-        .eval_script(
-            "def main():\n\timport numpy as np\n\treturn np.array([1.5, -2.25], dtype='<f4')",
-            OutputTarget::discard(),
-        )
-        .await?;
-    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(5)).await?;
-    let value = output.result.context("expected typed-array result")?;
-    let mut decoder = minicbor::Decoder::new(value.as_cbor());
-    assert_eq!(decoder.tag()?.as_u64(), 84);
-    assert_eq!(decoder.bytes()?, &[0, 0, 192, 63, 0, 0, 16, 192]);
-    assert_eq!(value.to_json_str()?, r#""AADAPwAAEMA=""#);
+        .eval_script("def double(x):\n\treturn x * 2", OutputTarget::discard())
+        .await
+        .context("failed to evaluate script")?;
+    let shared = SharedSandbox::with_max_queue_depth(sandbox, Some(1));
+
+    let held = shared.lock().await?;
+    let queued = {
+        let shared = shared.clone();
+        tokio::spawn(async move {
+            let mut sandbox = shared.lock().await?;
+            let output = sandbox
+                .call_collect("double", args![21_i64]?, CallOptions::default())
+                .await?;
+            drop(sandbox);
+            anyhow::Ok(output.result.context("missing result")?.to_serde::<i64>()?)
+        })
+    };
+    while shared.queue_depth() == 0 {
+        tokio::task::yield_now().await;
+    }
+    let err = shared
+        .lock()
+        .await
+        .err()
+        .context("a full queue must reject new callers")?;
+    assert!(matches!(err, IsolaError::QueueFull(1)), "got {err:?}");
+    assert_eq!(err.kind(), ErrorKind::PolicyDenied);
+
+    drop(held);
+    assert_eq!(queued.await??, 42, "the queued caller runs once released");
+    assert_eq!(shared.queue_depth(), 0);
+
     Ok(())
 }
 
+#[derive(Clone, Default)]
+struct IdleHost {
+    disposed: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl Host for IdleHost {
+    fn on_idle_dispose(&self, idle: Duration) {
+        self.disposed.lock().push(idle);
+    }
+}
+
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_reinstantiate_smoke() -> Result<()> {
+async fn integration_python_shared_sandbox_disposes_idle_sandbox() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
+    let host = IdleHost::default();
+    let timeout = Duration::from_millis(200);
+    let sandbox = module
+        .instantiate(
+            host.clone(),
+            SandboxOptions::default().idle_timeout(timeout),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+    assert_eq!(sandbox.idle_timeout(), Some(timeout));
+    let shared = SharedSandbox::new(sandbox);
 
-    for expected in [7_i64, 11_i64] {
-        let mut sandbox = module
-            .instantiate(TestHost::default(), SandboxOptions::default())
-            .await
-            .context("failed to instantiate sandbox")?;
-
-        sandbox
-            .eval_script(
-                format!("def main():\n\treturn {expected}"),
-                OutputTarget::discard(),
-            )
-            .await
-            .context("failed to evaluate script")?;
-
-        let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
-            .await
-            .context("failed to call function")?;
-        let value: i64 = output
-            .result
-            .as_ref()
-            .context("expected exactly one end output")?
-            .to_serde()
-            .context("failed to decode roundtrip output")?;
-        assert_eq!(value, expected);
-    }
+    let mut held = shared.lock().await?;
+    held.eval_script("x = 1", OutputTarget::discard())
+        .await
+        .context("failed to evaluate script")?;
+    tokio::time::sleep(timeout * 2).await;
+    assert!(host.disposed.lock().is_empty(), "a held sandbox is in use");
+    drop(held);
+
+    tokio::time::sleep(timeout / 2).await;
+    assert!(!shared.is_disposed(), "released only moments ago");
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while host.disposed.lock().is_empty() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .context("idle sandbox was never disposed")?;
+    assert_eq!(*host.disposed.lock(), [timeout]);
+    assert!(shared.is_disposed());
+    let err = shared
+        .lock()
+        .await
+        .err()
+        .context("a disposed sandbox cannot be locked")?;
+    assert!(
+        matches!(err, IsolaError::IdleDisposed(t) if t == timeout),
+        "got {err:?}"
+    );
 
     Ok(())
 }
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_guest_exception_surface() -> Result<()> {
-    let Some(module) = build_module().await? else {
+async fn integration_python_template_manager_reloads_changed_component() -> Result<()> {
+    let dir = tempdir()?;
+    let Some((wasm, builder)) = module_source(&dir.path().join("cache"))? else {
         return Ok(());
     };
-    let mut sandbox = module
+    let watched = dir.path().join("python.wasm");
+    let component = std::fs::read(&wasm)?;
+    std::fs::write(&watched, &component)?;
+    let manager = TemplateManager::watch_every(&watched, Duration::from_millis(50), builder)
+        .await
+        .context("failed to build watched template")?;
+    let (sender, mut reloads) = tokio::sync::mpsc::unbounded_channel();
+    manager.subscribe(move |result| {
+        let _ = sender.send(result.as_ref().map(Arc::clone).map_err(ToString::to_string));
+    });
+    let first = manager.template();
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    std::fs::write(&watched, &component)?;
+    let reloaded = tokio::time::timeout(Duration::from_secs(120), reloads.recv())
+        .await?
+        .context("subscriber dropped")?
+        .map_err(anyhow::Error::msg)?;
+    assert!(!Arc::ptr_eq(&first, &reloaded));
+    assert!(Arc::ptr_eq(&manager.template(), &reloaded));
+    let mut sandbox = reloaded
         .instantiate(TestHost::default(), SandboxOptions::default())
         .await
-        .context("failed to instantiate sandbox")?;
-
+        .context("failed to instantiate reloaded template")?;
     sandbox
-        .eval_script(
-            "def main():\n\traise RuntimeError(\"boom\")",
-            OutputTarget::discard(),
-        )
+        .eval_script("x = 1", OutputTarget::discard())
         .await
-        .context("failed to evaluate exception script")?;
+        .context("reloaded template must run code")?;
 
-    let err = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
-        .await
-        .expect_err("expected exception from guest function");
-    let IsolaError::UserCode { message } = err else {
-        panic!("expected guest error, got {err:?}");
-    };
+    std::fs::write(&watched, b"not a component")?;
+    let failed = tokio::time::timeout(Duration::from_secs(120), reloads.recv())
+        .await?
+        .context("subscriber dropped")?;
+    assert!(failed.is_err(), "a broken component must not load");
     assert!(
-        message.contains("boom"),
-        "unexpected error message: {message}",
+        Arc::ptr_eq(&manager.template(), &reloaded),
+        "a failed rebuild keeps the previous template"
     );
 
     Ok(())
@@ -570,18 +684,661 @@ async fn integration_python_guest_exception_surface() -> Result<()> {
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_state_persists_within_sandbox() -> Result<()> {
+async fn integration_python_template_reports_runtime_metadata() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
-    let mut sandbox = module
+    let metadata = module
+        .metadata()
+        .context("python bundle must carry runtime metadata")?;
+    assert_eq!(metadata.language, "python");
+    assert_eq!(metadata.abi_version, RUNTIME_ABI_VERSION);
+    assert!(metadata.is_compatible());
+    assert!(
+        metadata
+            .build_info
+            .as_deref()
+            .is_some_and(|info| info.starts_with("cpython ")),
+        "unexpected build info: {:?}",
+        metadata.build_info
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_template_detects_world_interfaces() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let world = module.world();
+    assert!(world.hostcall, "python runtime must import hostcalls");
+    assert!(world.http, "python runtime must import wasi:http");
+    assert!(world.logging, "python runtime must import wasi:logging");
+    assert!(world.batch, "python runtime must export call-batch");
+    assert!(
+        world.exceptions
+            && world.preludes
+            && world.preopens
+            && world.expressions
+            && world.modules
+            && world.packages
+            && world.coverage,
+        "python runtime must export every optional interface: {world:?}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_linker_extensions_apply_per_host_type() -> Result<()> {
+    let extended = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&extended);
+    let Some(module) = build_module_with(move |builder| {
+        builder
+            .with_linker::<TestHost>(move |linker| {
+                counted.fetch_add(1, Ordering::Relaxed);
+                linker
+                    .root()
+                    .func_wrap("isola-test-extension", |_, (): ()| Ok(()))
+            })
+            .with_linker::<Arc<TestHost>>(|_| wasmtime::bail!("not for this host"))
+    })
+    .await?
+    else {
+        return Ok(());
+    };
+
+    for _ in 0..2 {
+        let mut sandbox = module
+            .instantiate(TestHost::default(), SandboxOptions::default())
+            .await
+            .context("failed to instantiate extended sandbox")?;
+        sandbox
+            .eval_script("x = 1", OutputTarget::discard())
+            .await
+            .context("extended sandbox must run code")?;
+    }
+    assert_eq!(
+        extended.load(Ordering::Relaxed),
+        1,
+        "the extended linker must be built once per host type"
+    );
+
+    let Err(err) = module
+        .instantiate(Arc::new(TestHost::default()), SandboxOptions::default())
+        .await
+    else {
+        panic!("a failing extension must fail instantiation");
+    };
+    assert!(
+        format!("{err:#}").contains("not for this host"),
+        "unexpected error: {err:#}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_preludes_run_in_order() -> Result<()> {
+    let dir = tempdir()?;
+    let shims = dir.path().join("shims.py");
+    std::fs::write(&shims, "def shout(s):\n    return s.upper() + SUFFIX\n")?;
+    let Some(module) =
+        build_module_with(|builder| builder.add_prelude("SUFFIX = '!'").prelude_file(&shims))
+            .await?
+    else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    let output = sandbox
+        .call_collect("shout", args!["hi"]?, CallOptions::default())
+        .await
+        .context("prelude functions must be callable")?;
+    assert_eq!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<String>()?,
+        "HI!"
+    );
+
+    let broken = dir.path().join("broken.py");
+    std::fs::write(&broken, "raise ValueError('bad shim')\n")?;
+    let err = build_module_with(|builder| builder.prelude_file(&broken))
+        .await
+        .err()
+        .context("a raising prelude must fail the build")?;
+    let err = err
+        .downcast_ref::<IsolaError>()
+        .context("build error must come from isola")?;
+    let IsolaError::Prelude { name, message } = err else {
+        panic!("expected a prelude error, got {err:?}");
+    };
+    assert_eq!(name, &broken.display().to_string());
+    assert!(message.contains("bad shim"), "got {message}");
+    assert_eq!(err.kind(), ErrorKind::GuestException);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_warmup_imports_are_preloaded() -> Result<()> {
+    let Some(module) =
+        build_module_with(|builder| builder.warmup_imports(["fractions", "email.mime.text"]))
+            .await?
+    else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    sandbox
+        .eval_script(
+            "import sys\n\
+             def loaded():\n\
+             \treturn ['fractions' in sys.modules, 'email.mime.text' in sys.modules]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+    let output = sandbox
+        .call_collect("loaded", [], CallOptions::default())
+        .await?;
+    assert_eq!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<Vec<bool>>()?,
+        [true, true]
+    );
+
+    // Warmup runs after the preludes, so it can import what they provide.
+    let Some(module) = build_module_with(|builder| {
+        builder
+            .add_prelude(
+                "import sys, types\n\
+                 shim = types.ModuleType('prelude_shim')\n\
+                 sys.modules['prelude_shim'] = shim",
+            )
+            .warmup_imports(["prelude_shim"])
+    })
+    .await?
+    else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    sandbox
+        .eval_script(
+            "import sys\n\
+             def loaded():\n\
+             \treturn 'prelude_shim' in sys.modules",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+    let output = sandbox
+        .call_collect("loaded", [], CallOptions::default())
+        .await?;
+    assert!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<bool>()?
+    );
+
+    let err = build_module_with(|builder| builder.warmup_imports(["os; print()"]))
+        .await
+        .err()
+        .context("invalid module names must be rejected")?;
+    assert!(
+        format!("{err:#}").contains("invalid warmup import"),
+        "got {err:#}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_call_stream_yields_items_then_result() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "def main(n):\n\tfor i in range(n):\n\t\tyield i\n\treturn n\n\
+             def fail():\n\tyield 1\n\traise ValueError('boom')",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate streaming script")?;
+
+    // More items than the stream buffers, so the guest must wait for the
+    // consumer.
+    let mut stream = sandbox.call_stream("main", args![100_i64]?);
+    let mut values = Vec::new();
+    while let Some(item) = stream.next().await {
+        values.push(item.to_serde::<i64>()?);
+    }
+    assert_eq!(values, (0..100).collect::<Vec<_>>());
+    let result = stream.result().await?.context("expected a final value")?;
+    assert_eq!(result.to_serde::<i64>()?, 100);
+
+    let stream = sandbox.call_stream("fail", []);
+    let err = stream.result().await.expect_err("guest raised");
+    assert!(err.to_string().contains("boom"), "unexpected error: {err}");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_failed_emit_does_not_corrupt_next_output() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import _isola_sys\n\
+             def main():\n\
+             \ttry:\n\
+             \t\t_isola_sys.emit([\"x\" * 2048, object()])\n\
+             \texcept ValueError:\n\
+             \t\tpass\n\
+             \t_isola_sys.emit(\"valid\")\n\
+             \treturn \"done\"",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate failed emit script")?;
+
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(5))
+        .await
+        .context("failed to call failed emit function")?;
+    assert_eq!(output.items.len(), 1, "failed emit should not reach sink");
+    let item: String = output.items[0]
+        .to_serde()
+        .context("failed to decode valid partial output")?;
+    assert_eq!(item, "valid");
+    let result: String = output
+        .result
+        .as_ref()
+        .context("expected end output")?
+        .to_serde()
+        .context("failed to decode final output")?;
+    assert_eq!(result, "done");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_eval_script_logs_to_sink() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let sink = CollectLogsSink::new(logs.clone());
+    match tokio::time::timeout(
+        Duration::from_secs(2),
+        sandbox.eval_script(
+            "print('eval-stdout')\nimport sandbox.logging\nsandbox.logging.info('eval-log')",
+            sink.into_target(),
+        ),
+    )
+    .await
+    {
+        Ok(result) => result.context("failed to evaluate script")?,
+        Err(_) => {
+            return Err(anyhow::anyhow!("sandbox eval timed out after {}ms", 2_000));
+        }
+    }
+    {
+        let logs = logs.lock();
+
+        assert!(
+            logs.iter()
+                .any(|(context, message)| context == "stdout" && message.contains("eval-stdout")),
+            "expected eval stdout log in sink, logs: {:?}",
+            *logs
+        );
+        assert!(
+            logs.iter()
+                .any(|(context, message)| context == "info" && message.contains("eval-log")),
+            "expected eval logging event in sink, logs: {:?}",
+            *logs
+        );
+        drop(logs);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_large_stdout_output_is_not_truncated() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            format!(
+                "def main():\n\
+                 \tpayload = 'x' * {LARGE_STDOUT_BYTES}\n\
+                 \tprint(payload, end='')\n\
+                 \treturn len(payload)"
+            ),
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate large stdout script")?;
+
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(10))
+        .await
+        .context("failed to call large stdout function")?;
+
+    let emitted_len: usize = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode end output")?;
+    assert_eq!(emitted_len, LARGE_STDOUT_BYTES);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_argument_cbor_path() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "def main(i, s):\n\treturn (i + 1, s.upper())",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate argument script")?;
+
+    let args = args![41_i64, s = "hello"]?;
+    let output = call_with_timeout(&mut sandbox, "main", args, Duration::from_secs(2))
+        .await
+        .context("failed to call argument function")?;
+
+    assert!(output.items.is_empty(), "expected no partial outputs");
+    let value: (i64, String) = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode argument result")?;
+    assert_eq!(value, (42, "HELLO".to_string()));
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_numpy_typed_array_cbor_path() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await?;
+    sandbox
+        .eval_script(
+            "def main():\n\timport numpy as np\n\treturn np.array([1.5, -2.25], dtype='<f4')",
+            OutputTarget::discard(),
+        )
+        .await?;
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(5)).await?;
+    let value = output.result.context("expected typed-array result")?;
+    let mut decoder = minicbor::Decoder::new(value.as_cbor());
+    assert_eq!(decoder.tag()?.as_u64(), 84);
+    assert_eq!(decoder.bytes()?, &[0, 0, 192, 63, 0, 0, 16, 192]);
+    assert_eq!(value.to_json_str()?, r#""AADAPwAAEMA=""#);
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_reinstantiate_smoke() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+
+    for expected in [7_i64, 11_i64] {
+        let mut sandbox = module
+            .instantiate(TestHost::default(), SandboxOptions::default())
+            .await
+            .context("failed to instantiate sandbox")?;
+
+        sandbox
+            .eval_script(
+                format!("def main():\n\treturn {expected}"),
+                OutputTarget::discard(),
+            )
+            .await
+            .context("failed to evaluate script")?;
+
+        let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+            .await
+            .context("failed to call function")?;
+        let value: i64 = output
+            .result
+            .as_ref()
+            .context("expected exactly one end output")?
+            .to_serde()
+            .context("failed to decode roundtrip output")?;
+        assert_eq!(value, expected);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_guest_exception_surface() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "def main():\n\traise RuntimeError(\"boom\")",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate exception script")?;
+
+    let err = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .expect_err("expected exception from guest function");
+    assert_eq!(err.kind(), ErrorKind::GuestException);
+    assert!(
+        err.guest_message()
+            .is_some_and(|message| message.contains("boom"))
+    );
+    let traceback = err.traceback().context("expected a traceback")?;
+    assert_eq!(traceback.exception_type, "RuntimeError");
+    assert_eq!(traceback.message, "boom");
+    let frame = traceback.frames.last().context("expected a frame")?;
+    assert_eq!(frame.function.as_deref(), Some("main"));
+    assert_eq!(frame.line, Some(2));
+    assert_eq!(err.exception_type(), Some("RuntimeError"));
+    let IsolaError::UserCode { message, .. } = err else {
+        panic!("expected guest error, got {err:?}");
+    };
+    assert!(
+        message.contains("boom"),
+        "unexpected error message: {message}",
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_state_persists_within_sandbox() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "counter = 0\n\
+             def main():\n\
+             \tglobal counter\n\
+             \tcounter += 1\n\
+             \treturn counter",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate stateful script")?;
+
+    let first = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed first stateful call")?;
+    let second = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed second stateful call")?;
+
+    let first_v: i64 = first
+        .result
+        .as_ref()
+        .context("expected exactly one first end output")?
+        .to_serde()
+        .context("failed to decode first value")?;
+    let second_v: i64 = second
+        .result
+        .as_ref()
+        .context("expected exactly one second end output")?
+        .to_serde()
+        .context("failed to decode second value")?;
+    assert_eq!(first_v, 1);
+    assert_eq!(second_v, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_reset_discards_guest_state() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
         .instantiate(TestHost::default(), SandboxOptions::default())
         .await
-        .context("failed to instantiate sandbox")?;
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import builtins\n\
+             builtins.leaked = 1\n\
+             def main():\n\
+             \treturn leaked",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate leaking script")?;
+    call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed call before reset")?;
+
+    sandbox.reset().await.context("failed to reset sandbox")?;
+    assert!(
+        call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+            .await
+            .is_err(),
+        "functions defined before the reset must be gone"
+    );
 
     sandbox
         .eval_script(
-            "counter = 0\n\
+            "import builtins\n\
+             def main():\n\
+             \treturn hasattr(builtins, 'leaked')",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script after reset")?;
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed call after reset")?;
+    let leaked: bool = output
+        .result
+        .as_ref()
+        .context("expected a result")?
+        .to_serde()
+        .context("failed to decode result")?;
+    assert!(!leaked, "module state must not survive a reset");
+    assert_eq!(module.stats().live_sandboxes, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_snapshot_restores_prepared_state() -> Result<()> {
+    let Some(module) = build_module_with(|builder| builder.snapshots(true)).await? else {
+        return Ok(());
+    };
+    let mut prepared = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    prepared
+        .eval_script(
+            "import json\n\
+             counter = 40\n\
              def main():\n\
              \tglobal counter\n\
              \tcounter += 1\n\
@@ -589,29 +1346,477 @@ async fn integration_python_state_persists_within_sandbox() -> Result<()> {
             OutputTarget::discard(),
         )
         .await
-        .context("failed to evaluate stateful script")?;
+        .context("failed to prepare sandbox")?;
+    let snapshot = prepared
+        .snapshot()
+        .await
+        .context("failed to snapshot sandbox")?;
 
-    let first = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+    for _ in 0..2 {
+        let mut restored = module
+            .restore(&snapshot, TestHost::default(), SandboxOptions::default())
+            .await
+            .context("failed to restore snapshot")?;
+        let output = call_with_timeout(&mut restored, "main", [], Duration::from_secs(2))
+            .await
+            .context("failed call on restored sandbox")?;
+        let value: i64 = output
+            .result
+            .as_ref()
+            .context("expected a result")?
+            .to_serde()
+            .context("failed to decode result")?;
+        assert_eq!(value, 41, "each restore starts from the snapshot");
+        assert!(
+            restored.snapshot().await.is_err(),
+            "restored sandboxes are not instrumented for snapshots"
+        );
+    }
+
+    let output = call_with_timeout(&mut prepared, "main", [], Duration::from_secs(2))
         .await
-        .context("failed first stateful call")?;
-    let second = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .context("source sandbox unusable after snapshot")?;
+    assert!(output.result.is_some());
+
+    let Some(plain) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = plain
+        .instantiate(TestHost::default(), SandboxOptions::default())
         .await
-        .context("failed second stateful call")?;
+        .context("failed to instantiate sandbox")?;
+    assert!(sandbox.snapshot().await.is_err());
+    assert!(
+        plain
+            .restore(&snapshot, TestHost::default(), SandboxOptions::default())
+            .await
+            .is_err(),
+        "snapshots are tied to their template"
+    );
 
-    let first_v: i64 = first
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_pool_recycles_sandboxes() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let pool = SandboxPool::new(
+        Arc::new(module),
+        SandboxPoolConfig::default().min_idle(1).max_size(2),
+        TestHost::default,
+    )
+    .await
+    .context("failed to create pool")?;
+    assert_eq!(pool.stats().idle, 1);
+
+    let mut sandbox = pool.acquire().await.context("failed to acquire")?;
+    sandbox
+        .eval_script("leftover = 1", OutputTarget::discard())
+        .await
+        .context("failed to evaluate script")?;
+    drop(sandbox);
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while pool.stats().in_use > 0 {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "returned sandbox was not recycled"
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let mut sandbox = pool.acquire().await.context("failed to acquire")?;
+    sandbox
+        .eval_script(
+            "def main():\n\treturn 'leftover' in globals()",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed call on pooled sandbox")?;
+    drop(sandbox);
+    let leaked: bool = output
         .result
         .as_ref()
-        .context("expected exactly one first end output")?
+        .context("expected a result")?
         .to_serde()
-        .context("failed to decode first value")?;
-    let second_v: i64 = second
+        .context("failed to decode result")?;
+    assert!(!leaked, "recycled sandboxes must not keep guest state");
+
+    pool.acquire().await.context("failed to acquire")?.discard();
+
+    let held = [
+        pool.acquire().await.context("failed to acquire")?,
+        pool.acquire().await.context("failed to acquire")?,
+    ];
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let stats = pool.stats();
+    drop(pool);
+    assert_eq!(
+        (stats.idle, stats.in_use),
+        (0, 2),
+        "max_size must bound idle sandboxes"
+    );
+    drop(held);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_fuel_budgets_bound_calls() -> Result<()> {
+    let Some(module) = build_module_with(|builder| builder.fuel_metering(true)).await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    sandbox
+        .eval_script(
+            "def spin(n):\n\
+             \ttotal = 0\n\
+             \tfor i in range(n):\n\
+             \t\ttotal += i\n\
+             \treturn total",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+
+    let small = sandbox
+        .call("spin", args![10_i64]?)
+        .await
+        .context("failed unbudgeted call")?
+        .fuel_consumed
+        .context("metered calls report consumed fuel")?;
+    let large = sandbox
+        .call("spin", args![10_000_i64]?)
+        .await
+        .context("failed larger call")?
+        .fuel_consumed
+        .context("metered calls report consumed fuel")?;
+    assert!(large > small, "expected {large} > {small}");
+
+    let err = sandbox
+        .call_collect(
+            "spin",
+            args![10_000_i64]?,
+            CallOptions::default().max_fuel(small),
+        )
+        .await
+        .expect_err("call exceeding its fuel budget must fail");
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert!(sandbox.last_fuel_consumed() >= Some(small));
+
+    let output = sandbox
+        .call("spin", args![10_i64]?)
+        .await
+        .context("budget must not outlive its call")?;
+    assert!(output.fuel_consumed.is_some());
+
+    let unmetered = build_module()
+        .await?
+        .context("runtime was available a moment ago")?;
+    assert!(
+        unmetered
+            .instantiate(TestHost::default(), SandboxOptions::default().max_fuel(1))
+            .await
+            .is_err(),
+        "max_fuel requires fuel metering"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_calls_report_exec_stats() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    assert_eq!(sandbox.last_exec_stats(), None);
+    sandbox
+        .eval_script(
+            "def spin(n):\n\
+             \ttotal = 0\n\
+             \tfor i in range(n):\n\
+             \t\ttotal += i\n\
+             \treturn total",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+
+    let output = sandbox
+        .call("spin", args![100_000_i64]?)
+        .await
+        .context("failed to call spin")?;
+    let stats = output.exec_stats;
+    assert!(stats.guest_time > Duration::ZERO, "{stats:?}");
+    assert!(stats.guest_time <= stats.wall_time, "{stats:?}");
+    assert!(stats.peak_memory > 0, "{stats:?}");
+    assert!(stats.output_bytes > 0, "{stats:?}");
+    assert_eq!((stats.hostcalls, stats.http_requests), (0, 0), "{stats:?}");
+    assert_eq!(sandbox.last_exec_stats(), Some(stats));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_deadline_interrupts_guest() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let pool = single_sandbox_pool(&Arc::new(module), SandboxOptions::default()).await?;
+    let mut sandbox = pool.acquire().await.context("failed to acquire")?;
+    sandbox
+        .eval_script(
+            "def spin():\n\
+             \twhile True:\n\
+             \t\tpass\n\
+             def main():\n\
+             \treturn 'ok'",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+
+    let started = std::time::Instant::now();
+    let err = tokio::time::timeout(
+        Duration::from_secs(5),
+        sandbox.call_collect(
+            "spin",
+            [],
+            CallOptions::default().deadline(started + Duration::from_millis(200)),
+        ),
+    )
+    .await
+    .context("deadline was not enforced by the runtime")?
+    .expect_err("spinning call must hit its deadline");
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    let err = sandbox
+        .eval_script_with_options(
+            "while True:\n\tpass",
+            OutputTarget::discard(),
+            CallOptions::default().deadline(std::time::Instant::now() + Duration::from_millis(100)),
+        )
+        .await
+        .expect_err("spinning eval must hit its deadline");
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+
+    drop(sandbox);
+    let mut sandbox = pool.acquire().await.context("failed to acquire")?;
+    sandbox
+        .eval_script("def main():\n\treturn 'ok'", OutputTarget::discard())
+        .await
+        .context("failed to evaluate script after recycling")?;
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed call after recycling")?;
+    drop(sandbox);
+    let result: String = output
         .result
         .as_ref()
-        .context("expected exactly one second end output")?
+        .context("expected a result")?
         .to_serde()
-        .context("failed to decode second value")?;
-    assert_eq!(first_v, 1);
-    assert_eq!(second_v, 2);
+        .context("failed to decode result")?;
+    assert_eq!(result, "ok");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_custom_epoch_tick_enforces_deadline() -> Result<()> {
+    let Some(module) =
+        build_module_with(|builder| builder.epoch_tick(Duration::from_millis(2))).await?
+    else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().epoch_yield_ticks(5),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    let started = std::time::Instant::now();
+    let err = tokio::time::timeout(
+        Duration::from_secs(5),
+        sandbox.eval_script_with_options(
+            "while True:\n\tpass",
+            OutputTarget::discard(),
+            CallOptions::default().deadline(started + Duration::from_millis(100)),
+        ),
+    )
+    .await
+    .context("deadline was not enforced by the runtime")?
+    .expect_err("spinning eval must hit its deadline");
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_interrupt_handle_cancels_call() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let pool = single_sandbox_pool(&Arc::new(module), SandboxOptions::default()).await?;
+    let mut sandbox = pool.acquire().await.context("failed to acquire")?;
+    sandbox
+        .eval_script(
+            "def spin():\n\twhile True:\n\t\tpass",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+
+    let handle = sandbox.interrupt_handle();
+    let interrupter = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        handle.interrupt();
+    });
+    let err = tokio::time::timeout(Duration::from_secs(5), sandbox.call("spin", []))
+        .await
+        .context("interrupt did not stop the call")?
+        .expect_err("interrupted call must fail");
+    interrupter.join().expect("interrupter thread panicked");
+    assert!(matches!(err, IsolaError::Cancelled), "got {err:?}");
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+
+    drop(sandbox);
+    let mut sandbox = pool.acquire().await.context("failed to acquire")?;
+    sandbox.interrupt_handle().interrupt();
+    sandbox
+        .eval_script("def main():\n\treturn 1", OutputTarget::discard())
+        .await
+        .context("an idle interrupt must not affect later operations")?;
+    drop(sandbox);
+
+    Ok(())
+}
+
+/// Backend that hands out a truncated copy of what was uploaded.
+#[derive(Default)]
+struct TruncatingBackend {
+    artifacts: Mutex<std::collections::HashMap<String, Vec<u8>>>,
+    truncate: std::sync::atomic::AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for TruncatingBackend {
+    async fn load(&self, key: &str) -> std::io::Result<Option<Vec<u8>>> {
+        let mut artifact = self.artifacts.lock().get(key).cloned();
+        if self.truncate.load(Ordering::Relaxed)
+            && let Some(artifact) = &mut artifact
+        {
+            artifact.truncate(artifact.len() / 2);
+        }
+        Ok(artifact)
+    }
+
+    async fn store(&self, key: &str, artifact: &[u8]) -> std::io::Result<()> {
+        self.artifacts
+            .lock()
+            .insert(key.to_string(), artifact.to_vec());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_corrupt_backend_artifacts_are_recompiled() -> Result<()> {
+    let backend = Arc::new(TruncatingBackend::default());
+    let build = || {
+        let backend: Arc<dyn CacheBackend> = backend.clone();
+        // SAFETY: the backend only holds artifacts this test compiled.
+        build_module_with(move |builder| unsafe {
+            builder.cache(None).cache_backend(Some(backend))
+        })
+    };
+    let Some(module) = build().await? else {
+        return Ok(());
+    };
+    assert_eq!(module.stats().cache, CacheStatus::Miss);
+    assert_eq!(
+        build().await?.context("built")?.stats().cache,
+        CacheStatus::Hit
+    );
+
+    backend.truncate.store(true, Ordering::Relaxed);
+    let module = build()
+        .await?
+        .context("corrupt artifact must not fail the build")?;
+    assert_eq!(module.stats().cache, CacheStatus::Miss);
+    module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate recompiled template")?;
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_precompiled_template_roundtrip() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let dir = tempdir().context("failed to create temp dir")?;
+    let artifact = dir.path().join("python.isola");
+    module
+        .serialize(&artifact)
+        .await
+        .context("failed to serialize template")?;
+
+    let loaded = load_precompiled_module_with(&artifact, |builder| builder)
+        .await?
+        .context("runtime was available a moment ago")?;
+    assert_eq!(loaded.stats().cache, CacheStatus::Precompiled);
+    let mut sandbox = loaded
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate precompiled template")?;
+    sandbox
+        .eval_script("def main():\n\treturn 42", OutputTarget::discard())
+        .await
+        .context("failed to evaluate script")?;
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed call on precompiled template")?;
+    let result: i64 = output
+        .result
+        .as_ref()
+        .context("expected a result")?
+        .to_serde()
+        .context("failed to decode result")?;
+    assert_eq!(result, 42);
+
+    let err = load_precompiled_module_with(&artifact, |builder| builder.fuel_metering(true))
+        .await
+        .err()
+        .expect("artifact must not load into an incompatible engine");
+    assert!(
+        format!("{err:#}").contains("engine settings"),
+        "got {err:#}"
+    );
 
     Ok(())
 }
@@ -865,10 +2070,11 @@ async fn integration_python_memory_limiter_is_enforced() -> Result<()> {
     let memory_after = sandbox.memory_usage();
 
     let message = match err {
-        IsolaError::UserCode { message } => message.to_ascii_lowercase(),
+        IsolaError::UserCode { message, .. } => message.to_ascii_lowercase(),
         IsolaError::Wasm(cause) => cause.to_string().to_ascii_lowercase(),
         IsolaError::Io(cause) => cause.to_string().to_ascii_lowercase(),
         IsolaError::Other(cause) => cause.to_string().to_ascii_lowercase(),
+        other => other.to_string().to_ascii_lowercase(),
     };
     assert!(
         message.contains("memory")
@@ -894,62 +2100,530 @@ async fn integration_python_memory_limiter_is_enforced() -> Result<()> {
     Ok(())
 }
 
+struct GrowthCapHost {
+    cap: usize,
+    requests: Arc<Mutex<Vec<(usize, usize)>>>,
+}
+
+impl Host for GrowthCapHost {
+    fn on_memory_growth(&self, current: usize, requested: usize, _limit: usize) -> bool {
+        self.requests.lock().push((current, requested));
+        requested <= self.cap
+    }
+}
+
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
-async fn integration_python_writable_directory_mapping_filesystem_roundtrip() -> Result<()> {
-    let temp = tempdir().context("failed to create temp directory")?;
-    let mapped_dir = temp.path().to_path_buf();
-
+async fn integration_python_host_can_veto_memory_growth() -> Result<()> {
     let Some(module) = build_module().await? else {
         return Ok(());
     };
-    let mut options = SandboxOptions::default();
-    options = options.mount(
-        &mapped_dir,
-        "/fs",
-        DirPerms::READ | DirPerms::MUTATE,
-        FilePerms::READ | FilePerms::WRITE,
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let mut sandbox = module
+        .instantiate(
+            GrowthCapHost {
+                cap: MEMORY_CAP_BYTES,
+                requests: Arc::clone(&requests),
+            },
+            SandboxOptions::default(),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+    assert!(
+        !requests.lock().is_empty(),
+        "expected the initial allocation to be reported"
+    );
+
+    sandbox
+        .eval_script(
+            "def main():\n\
+             \tchunks = []\n\
+             \tfor _ in range(1024):\n\
+             \t\tchunks.append(bytes(1024 * 1024))\n\
+             \treturn len(chunks)",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate memory pressure script")?;
+    let err = sandbox
+        .call("main", [])
+        .await
+        .expect_err("expected the host to veto memory growth");
+    assert_eq!(
+        err.kind(),
+        ErrorKind::MemoryLimit,
+        "unexpected error: {err}"
+    );
+    assert!(sandbox.memory_usage() <= MEMORY_CAP_BYTES);
+
+    // A guest that recovers from the failed allocation fails on its own terms.
+    sandbox
+        .eval_script(
+            "def recovers():\n\
+             \ttry:\n\
+             \t\tbytes(512 * 1024 * 1024)\n\
+             \texcept MemoryError:\n\
+             \t\tpass\n\
+             \traise ValueError('after recovering')",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate recovering script")?;
+    let err = sandbox
+        .call("recovers", [])
+        .await
+        .expect_err("expected the guest exception");
+    assert_eq!(
+        err.kind(),
+        ErrorKind::GuestException,
+        "unexpected error: {err}"
     );
+    let stats = sandbox.memory_stats();
+    assert_eq!(stats.current, sandbox.memory_usage());
+    assert!(stats.peak >= stats.current);
+    assert!(stats.table_elements > 0);
+    assert!(stats.grow_failures >= 1, "{stats:?}");
+    assert!(
+        requests
+            .lock()
+            .iter()
+            .any(|&(_, requested)| requested > MEMORY_CAP_BYTES),
+        "expected a vetoed growth request to be reported"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_seeded_entropy_is_reproducible() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut draws = Vec::new();
+    for seed in [42, 42, 43] {
+        let mut sandbox = module
+            .instantiate(
+                TestHost::default(),
+                SandboxOptions::default().entropy(SeededEntropy::new(seed)),
+            )
+            .await
+            .context("failed to instantiate sandbox")?;
+        sandbox
+            .eval_script(
+                "import os\ndef main():\n\treturn os.urandom(16).hex()",
+                OutputTarget::discard(),
+            )
+            .await
+            .context("failed to evaluate entropy script")?;
+        let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+            .await
+            .context("failed to call main")?;
+        draws.push(
+            output
+                .result
+                .context("missing result")?
+                .to_serde::<String>()?,
+        );
+    }
+
+    assert_eq!(draws[0], draws[1]);
+    assert_ne!(draws[0], draws[2]);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_manual_clock_fast_forwards_sleeps() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let clock = Arc::new(ManualClock::new(Duration::from_secs(1_000_000)));
     let mut sandbox = module
-        .instantiate(TestHost::default(), options)
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().clock(Arc::clone(&clock)),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+    sandbox
+        .eval_script(
+            "import asyncio, time\n\
+             def main():\n\
+             \tstart = time.time()\n\
+             \ttime.sleep(3600)\n\
+             \tasyncio.run(asyncio.sleep(60))\n\
+             \treturn [start, time.time() - start]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate clock script")?;
+
+    let output = call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("sleeps should not take real time")?;
+    let [start, slept] = output
+        .result
+        .context("missing result")?
+        .to_serde::<[f64; 2]>()?;
+    assert!((start - 1_000_000.0).abs() < 1.0, "{start}");
+    assert!((3660.0..3661.0).contains(&slept), "{slept}");
+    assert!(clock.elapsed() >= Duration::from_secs(3660));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_reads_configured_stdin() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let script = "import sys\n\
+                  def main():\n\
+                  \ttry:\n\
+                  \t\tfirst = input()\n\
+                  \texcept EOFError:\n\
+                  \t\treturn ['eof']\n\
+                  \treturn [first, sys.stdin.read()]";
+
+    let mut fed = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().stdin(&b"alice\nbob\n"[..]),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+    let mut closed = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
         .await
         .context("failed to instantiate sandbox")?;
+    for (sandbox, expected) in [
+        (&mut fed, vec!["alice", "bob\n"]),
+        (&mut closed, vec!["eof"]),
+    ] {
+        sandbox
+            .eval_script(script, OutputTarget::discard())
+            .await
+            .context("failed to evaluate stdin script")?;
+        let output = call_with_timeout(sandbox, "main", [], Duration::from_secs(2))
+            .await
+            .context("failed to call main")?;
+        assert_eq!(
+            output
+                .result
+                .context("missing result")?
+                .to_serde::<Vec<String>>()?,
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_stdout_writer_receives_raw_bytes() -> Result<()> {
+    let dir = tempdir().context("failed to create temp directory")?;
+    let path = dir.path().join("stdout.bin");
+    let file = tokio::fs::File::create(&path)
+        .await
+        .context("failed to create stdout file")?;
 
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().stdout_writer(file),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
     sandbox
         .eval_script(
-            "def main(text):\n\
-             \tpath = '/fs/output.txt'\n\
-             \twith open(path, 'w', encoding='utf-8') as fh:\n\
-             \t\tfh.write(text)\n\
-             \twith open(path, 'r', encoding='utf-8') as fh:\n\
-             \t\treturn fh.read()",
+            "import sys\n\
+             def main():\n\
+             \tsys.stdout.buffer.write(bytes(range(256)))\n\
+             \tsys.stdout.flush()",
             OutputTarget::discard(),
         )
         .await
-        .context("failed to evaluate filesystem script")?;
+        .context("failed to evaluate script")?;
+    call_with_timeout(&mut sandbox, "main", [], Duration::from_secs(2))
+        .await
+        .context("failed to call main")?;
 
-    let args = args!["hello-fs"]?;
-    let output = call_with_timeout(&mut sandbox, "main", args, Duration::from_secs(2))
+    assert_eq!(std::fs::read(&path)?, (0..=255).collect::<Vec<u8>>());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_eval_package_and_files() -> Result<()> {
+    let dir = tempdir().context("failed to create temp directory")?;
+    std::fs::create_dir_all(dir.path().join("app/steps"))?;
+    std::fs::write(
+        dir.path().join("app/__init__.py"),
+        "from .util import double\n__all__ = ['main']\ndef main(x):\n    return double(x)\n",
+    )?;
+    std::fs::write(
+        dir.path().join("app/util.py"),
+        "def double(x):\n    return x * 2\n",
+    )?;
+    std::fs::write(dir.path().join("app/steps/a.py"), "order = ['a']\n")?;
+    std::fs::write(dir.path().join("app/steps/b.py"), "order.append('b')\n")?;
+    std::fs::write(dir.path().join("app/steps/notes.txt"), "not python")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options =
+        SandboxOptions::default().mount(dir.path(), "/src", DirPerms::READ, FilePerms::READ);
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
         .await
-        .context("failed to call filesystem function")?;
+        .context("failed to instantiate sandbox")?;
 
-    assert!(output.items.is_empty(), "expected no partial outputs");
-    let result: String = output
+    sandbox
+        .eval_package("/src/app", OutputTarget::discard())
+        .await
+        .context("failed to evaluate package")?;
+    let output = call_with_timeout(&mut sandbox, "main", args![21_i64]?, Duration::from_secs(2))
+        .await
+        .context("failed to call package function")?;
+    let value: i64 = output
         .result
         .as_ref()
         .context("expected exactly one end output")?
         .to_serde()
-        .context("failed to decode filesystem result")?;
-    assert_eq!(result, "hello-fs");
+        .context("failed to decode package result")?;
+    assert_eq!(value, 42);
+
+    sandbox
+        .eval_files("/src/app/steps", "*.py", OutputTarget::discard())
+        .await
+        .context("failed to evaluate files")?;
+    sandbox
+        .eval_script("def order_of():\n    return order", OutputTarget::discard())
+        .await?;
+    let output = call_with_timeout(&mut sandbox, "order_of", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call order function")?;
+    let order: Vec<String> = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode order")?;
+    assert_eq!(order, ["a", "b"]);
+
+    let err = sandbox
+        .eval_package("/src/app/steps", OutputTarget::discard())
+        .await
+        .expect_err("directory without __init__.py is not a package");
+    assert_eq!(err.kind(), ErrorKind::GuestException);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_call_coverage() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "def main(x):\n    if x > 0:\n        return 'positive'\n    return 'other'",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate coverage script")?;
+
+    let output = sandbox
+        .call_collect("main", args![1_i64]?, CallOptions::default().coverage(true))
+        .await
+        .context("failed to call with coverage")?;
+    let coverage = output.coverage.context("expected a coverage report")?;
+    assert_eq!(coverage.lines("<string>"), Some(&[2, 3][..]));
+
+    let output = sandbox
+        .call_collect(
+            "main",
+            args![-1_i64]?,
+            CallOptions::default().coverage(true),
+        )
+        .await
+        .context("failed to call with coverage")?;
+    let coverage = output.coverage.context("expected a coverage report")?;
+    assert_eq!(coverage.lines("<string>"), Some(&[2, 4][..]));
+
+    let output = call_with_timeout(&mut sandbox, "main", args![1_i64]?, Duration::from_secs(2))
+        .await
+        .context("failed to call without coverage")?;
+    assert!(output.coverage.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_fresh_globals_discard_call_mutations() -> Result<()> {
+    let Some(module) = build_module_with(|builder| builder.snapshots(true)).await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    sandbox
+        .eval_script(
+            "import json, sys
+             counter = 0
+             def bump():
+             	global counter, patched
+             	counter += 1
+             	patched = True
+             	json.dumps = lambda *args, **kwargs: 'patched'
+             	sys.modules['injected'] = json
+             	return counter
+             def state():
+             	return [
+             		counter,
+             		'patched' in globals(),
+             		json.dumps(1) == '1' and 'injected' not in sys.modules,
+             	]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate script")?;
+
+    let isolated = CallOptions::default().fresh_globals(true);
+    for _ in 0..2 {
+        let output = sandbox
+            .call_collect("bump", [], isolated.clone())
+            .await
+            .context("failed to call bump")?;
+        assert_eq!(
+            output.result.context("missing result")?.to_serde::<i64>()?,
+            1,
+            "each isolated call must start from the same globals"
+        );
+    }
+    let output = sandbox
+        .call_collect("state", [], CallOptions::default())
+        .await
+        .context("failed to call state")?;
+    assert_eq!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<(i64, bool, bool)>()?,
+        (0, false, true)
+    );
+
+    sandbox
+        .call_collect("bump", [], CallOptions::default())
+        .await
+        .context("failed to call bump")?;
+    let output = sandbox
+        .call_collect("state", [], CallOptions::default())
+        .await
+        .context("failed to call state")?;
+    assert_eq!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<(i64, bool, bool)>()?,
+        (1, true, false),
+        "calls without the option keep their changes"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_template_stats() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let stats = module.stats();
+    assert_eq!(stats.instantiations, 0);
+    assert_eq!(stats.average_instantiate_time, None);
 
-    let host_file = mapped_dir.join("output.txt");
-    let host_contents = std::fs::read_to_string(&host_file).with_context(|| {
-        format!(
-            "failed to read mapped host file after guest write: {}",
-            host_file.display()
+    let first = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    let second = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    let stats = module.stats();
+    assert_eq!(stats.instantiations, 2);
+    assert_eq!(stats.live_sandboxes, 2);
+    assert!(stats.average_instantiate_time.is_some());
+
+    drop(first);
+    assert_eq!(module.stats().live_sandboxes, 1);
+    drop(second);
+    assert_eq!(module.stats().live_sandboxes, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_disabled_wasi_interfaces() -> Result<()> {
+    let Some(module) = build_module_with(|builder| {
+        builder
+            .disable_wasi(WasiInterface::Clocks)
+            .disable_wasi(WasiInterface::Stdio)
+    })
+    .await?
+    else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import sys, time\n\
+             def main():\n\
+             \ttry:\n\
+             \t\tprint('hello', flush=True)\n\
+             \t\tprinted = True\n\
+             \texcept OSError:\n\
+             \t\tprinted = False\n\
+             \treturn [time.time(), printed]",
+            OutputTarget::discard(),
         )
-    })?;
-    assert_eq!(host_contents, "hello-fs");
+        .await
+        .context("failed to evaluate disabled-interface script")?;
+
+    let output = call_with_timeout(&mut sandbox, "main", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call disabled-interface function")?;
+    let (now, printed): (f64, bool) = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode disabled-interface result")?;
+    assert!(now.abs() < f64::EPSILON, "expected frozen clock, got {now}");
+    assert!(!printed, "expected stdout writes to fail");
 
     Ok(())
 }
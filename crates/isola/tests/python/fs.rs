@@ -0,0 +1,1110 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use isola::{
+    host::{FileAccess, FileOperation, FilePolicy, OutputTarget},
+    sandbox::{
+        DirPerms, Error as IsolaError, ErrorKind, FilePerms, FsQuota, GuestFileKind, OverlayMount,
+        SandboxOptions, args,
+    },
+};
+use parking_lot::Mutex;
+use tempfile::tempdir;
+
+use super::common::{TestHost, build_module, call_with_timeout, single_sandbox_pool};
+
+#[derive(Default)]
+struct AuditFilePolicy {
+    accesses: Mutex<Vec<(FileOperation, String)>>,
+}
+
+impl FilePolicy for AuditFilePolicy {
+    fn allows(&self, access: &FileAccess<'_>) -> bool {
+        self.accesses.lock().push((
+            access.operation,
+            format!("{}/{}", access.mount, access.path),
+        ));
+        !access.path.starts_with("secret")
+    }
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_invalid_mount_fails_instantiation() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let dir = tempdir()?;
+    let missing = dir.path().join("missing");
+    let Err(err) = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().mount(&missing, "/data", DirPerms::READ, FilePerms::READ),
+        )
+        .await
+    else {
+        panic!("mounting a missing directory must fail");
+    };
+    let IsolaError::InvalidMount { host, guest, cause } = &err else {
+        panic!("expected an invalid mount, got {err:?}");
+    };
+    assert_eq!(host, &missing);
+    assert_eq!(guest, "/data");
+    assert_eq!(cause.kind(), std::io::ErrorKind::NotFound);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_writable_directory_mapping_filesystem_roundtrip() -> Result<()> {
+    let temp = tempdir().context("failed to create temp directory")?;
+    let mapped_dir = temp.path().to_path_buf();
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut options = SandboxOptions::default();
+    options = options.mount(
+        &mapped_dir,
+        "/fs",
+        DirPerms::READ | DirPerms::MUTATE,
+        FilePerms::READ | FilePerms::WRITE,
+    );
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "def main(text):\n\
+             \tpath = '/fs/output.txt'\n\
+             \twith open(path, 'w', encoding='utf-8') as fh:\n\
+             \t\tfh.write(text)\n\
+             \twith open(path, 'r', encoding='utf-8') as fh:\n\
+             \t\treturn fh.read()",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate filesystem script")?;
+
+    let args = args!["hello-fs"]?;
+    let output = call_with_timeout(&mut sandbox, "main", args, Duration::from_secs(2))
+        .await
+        .context("failed to call filesystem function")?;
+
+    assert!(output.items.is_empty(), "expected no partial outputs");
+    let result: String = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode filesystem result")?;
+    assert_eq!(result, "hello-fs");
+
+    let host_file = mapped_dir.join("output.txt");
+    let host_contents = std::fs::read_to_string(&host_file).with_context(|| {
+        format!(
+            "failed to read mapped host file after guest write: {}",
+            host_file.display()
+        )
+    })?;
+    assert_eq!(host_contents, "hello-fs");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_host_reads_guest_written_files() -> Result<()> {
+    let temp = tempdir().context("failed to create temp directory")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default().mount(
+        temp.path(),
+        "/tmp",
+        DirPerms::READ | DirPerms::MUTATE,
+        FilePerms::READ | FilePerms::WRITE,
+    );
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import os\n\
+             os.makedirs('/tmp/plots', exist_ok=True)\n\
+             with open('/tmp/result.csv', 'w') as fh:\n\
+             \tfh.write('a,b\\n1,2\\n')\n\
+             with open('/tmp/plots/chart.svg', 'w') as fh:\n\
+             \tfh.write('<svg/>')",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate file-writing script")?;
+
+    assert_eq!(
+        sandbox.list_guest_files("/tmp").await?,
+        ["/tmp/plots/chart.svg", "/tmp/result.csv"]
+    );
+    assert_eq!(
+        sandbox.read_guest_file("/tmp/result.csv").await?,
+        b"a,b\n1,2\n"
+    );
+    assert!(sandbox.read_guest_file("/tmp/../etc/passwd").await.is_err());
+    assert!(sandbox.list_guest_files("/missing").await.is_err());
+
+    let entries = sandbox.list_guest_dir("/tmp").await?;
+    let listed: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry.kind))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("/tmp/plots", GuestFileKind::Directory),
+            ("/tmp/result.csv", GuestFileKind::File),
+        ]
+    );
+    let stat = sandbox.stat_guest_path("/tmp/plots/chart.svg").await?;
+    assert_eq!((stat.kind, stat.size), (GuestFileKind::File, 6));
+    assert!(stat.modified.is_some());
+    assert!(sandbox.stat_guest_path("/tmp/missing.csv").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_host_writes_files_for_the_guest() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default().scratch_dir("/work", 1024 * 1024);
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .write_guest_file("/work/in/data.txt", "hello")
+        .await
+        .context("failed to push input file")?;
+    sandbox
+        .eval_script(
+            "with open('/work/in/data.txt') as src, open('/work/out.txt', 'w') as dst:\n\
+             \tdst.write(src.read().upper())",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate file-copying script")?;
+    assert_eq!(sandbox.read_guest_file("/work/out.txt").await?, b"HELLO");
+
+    assert!(
+        sandbox
+            .write_guest_file("/work/../etc/x", "x")
+            .await
+            .is_err()
+    );
+    assert!(sandbox.write_guest_file("/missing/x", "x").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_read_only_sandbox_rejects_writes() -> Result<()> {
+    let temp = tempdir().context("failed to create temp directory")?;
+    let mapped_dir = temp.path().to_path_buf();
+    std::fs::write(mapped_dir.join("input.txt"), "hello")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default()
+        .mount(
+            &mapped_dir,
+            "/fs",
+            DirPerms::READ | DirPerms::MUTATE,
+            FilePerms::READ | FilePerms::WRITE,
+        )
+        .read_only();
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import os\n\
+             def main():\n\
+             \twith open('/fs/input.txt', encoding='utf-8') as fh:\n\
+             \t\tcontents = fh.read()\n\
+             \tdenied = 0\n\
+             \tfor op in (lambda: open('/fs/input.txt', 'w'), lambda: open('/fs/new.txt', 'x'),\n\
+             \t\t\tlambda: os.mkdir('/fs/dir'), lambda: os.remove('/fs/input.txt')):\n\
+             \t\ttry:\n\
+             \t\t\top()\n\
+             \t\texcept OSError:\n\
+             \t\t\tdenied += 1\n\
+             \treturn [contents, denied]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate read-only script")?;
+
+    let output = call_with_timeout(&mut sandbox, "main", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call read-only function")?;
+    let result: (String, u32) = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode read-only result")?;
+    assert_eq!(result, ("hello".to_string(), 4));
+    assert_eq!(
+        std::fs::read_to_string(mapped_dir.join("input.txt"))?,
+        "hello"
+    );
+    assert!(!mapped_dir.join("new.txt").exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_mount_quota_rejects_writes_past_limit() -> Result<()> {
+    let temp = tempdir().context("failed to create temp directory")?;
+    let mapped_dir = temp.path().to_path_buf();
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default().mount_with_quota(
+        &mapped_dir,
+        "/fs",
+        DirPerms::READ | DirPerms::MUTATE,
+        FilePerms::READ | FilePerms::WRITE,
+        FsQuota::default().max_bytes(Some(4096)).max_inodes(Some(2)),
+    );
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import errno\n\
+             def write(name, size):\n\
+             \ttry:\n\
+             \t\twith open('/fs/' + name, 'wb') as fh:\n\
+             \t\t\tfh.write(b'x' * size)\n\
+             \texcept OSError as e:\n\
+             \t\treturn errno.errorcode.get(e.errno, str(e.errno))\n\
+             \treturn 'ok'",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate quota script")?;
+
+    let cases = [
+        ("a.bin", 1024, "ok"),
+        ("a.bin", 8192, "ENOSPC"),
+        ("b.bin", 1024, "ok"),
+        ("c.bin", 1, "ENOSPC"),
+    ];
+    for (name, size, expected) in cases {
+        let output = call_with_timeout(
+            &mut sandbox,
+            "write",
+            args![name, size]?,
+            Duration::from_secs(2),
+        )
+        .await
+        .with_context(|| format!("failed to write {name}"))?;
+        let result: String = output
+            .result
+            .as_ref()
+            .context("expected exactly one end output")?
+            .to_serde()
+            .context("failed to decode write result")?;
+        assert_eq!(result, expected, "writing {size} bytes to {name}");
+    }
+
+    let total: u64 = std::fs::read_dir(&mapped_dir)?
+        .map(|entry| Ok(entry?.metadata()?.len()))
+        .sum::<std::io::Result<u64>>()?;
+    assert!(total <= 4096, "quota exceeded on host: {total} bytes");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_write_budget_counts_overwrites() -> Result<()> {
+    let temp = tempdir().context("failed to create temp directory")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default()
+        .mount(
+            temp.path(),
+            "/fs",
+            DirPerms::READ | DirPerms::MUTATE,
+            FilePerms::READ | FilePerms::WRITE,
+        )
+        .max_write_bytes(4096);
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import errno\n\
+             def write(size):\n\
+             \ttry:\n\
+             \t\twith open('/fs/a.bin', 'wb') as fh:\n\
+             \t\t\tfh.write(b'x' * size)\n\
+             \texcept OSError as e:\n\
+             \t\treturn errno.errorcode.get(e.errno, str(e.errno))\n\
+             \treturn 'ok'",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate write script")?;
+
+    // Rewriting the same file still draws from the budget.
+    for (size, expected) in [(2048, "ok"), (2048, "ok"), (1, "ENOSPC")] {
+        let output = call_with_timeout(&mut sandbox, "write", args![size]?, Duration::from_secs(2))
+            .await
+            .context("failed to call write")?;
+        let result: String = output
+            .result
+            .as_ref()
+            .context("expected exactly one end output")?
+            .to_serde()
+            .context("failed to decode write result")?;
+        assert_eq!(result, expected, "writing {size} bytes");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_file_policy_audits_and_denies_access() -> Result<()> {
+    let temp = tempdir().context("failed to create temp directory")?;
+    std::fs::write(temp.path().join("public.txt"), "hello").context("failed to write file")?;
+    std::fs::write(temp.path().join("secret.txt"), "hidden").context("failed to write file")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let policy = Arc::new(AuditFilePolicy::default());
+    let options = SandboxOptions::default()
+        .mount(
+            temp.path(),
+            "/fs",
+            DirPerms::READ | DirPerms::MUTATE,
+            FilePerms::READ | FilePerms::WRITE,
+        )
+        .file_policy(Arc::clone(&policy));
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import errno, os\n\
+             def attempt(f):\n\
+             \ttry:\n\
+             \t\treturn f()\n\
+             \texcept OSError as e:\n\
+             \t\treturn errno.errorcode.get(e.errno, str(e.errno))\n\
+             def probe():\n\
+             \treturn [\n\
+             \t\tattempt(lambda: open('/fs/public.txt').read()),\n\
+             \t\tattempt(lambda: open('/fs/secret.txt').read()),\n\
+             \t\tattempt(lambda: open('/fs/secret-new.txt', 'w').close()),\n\
+             \t\tattempt(lambda: sorted(os.listdir('/fs'))),\n\
+             \t]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate policy script")?;
+
+    policy.accesses.lock().clear();
+    let output = call_with_timeout(&mut sandbox, "probe", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call probe")?;
+    let result: (String, String, String, Vec<String>) = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode probe result")?;
+    assert_eq!(
+        result,
+        (
+            "hello".to_string(),
+            "EACCES".to_string(),
+            "EACCES".to_string(),
+            vec!["public.txt".to_string(), "secret.txt".to_string()],
+        )
+    );
+    assert!(!temp.path().join("secret-new.txt").exists());
+
+    let accesses = policy.accesses.lock().clone();
+    for expected in [
+        (FileOperation::Open, "/fs/public.txt"),
+        (FileOperation::Open, "/fs/secret.txt"),
+        (FileOperation::Create, "/fs/secret-new.txt"),
+        (FileOperation::ReadDirectory, "/fs/"),
+    ] {
+        assert!(
+            accesses
+                .iter()
+                .any(|(operation, path)| (*operation, path.as_str()) == expected),
+            "missing {expected:?} in {accesses:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_file_policy_covers_renames_and_links() -> Result<()> {
+    let temp = tempdir().context("failed to create temp directory")?;
+    std::fs::write(temp.path().join("secret.txt"), "hidden").context("failed to write file")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default()
+        .mount(
+            temp.path(),
+            "/fs",
+            DirPerms::READ | DirPerms::MUTATE,
+            FilePerms::READ | FilePerms::WRITE,
+        )
+        .file_policy(AuditFilePolicy::default());
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import errno, os\n\
+             def attempt(f):\n\
+             \ttry:\n\
+             \t\tf()\n\
+             \t\treturn 'ok'\n\
+             \texcept OSError as e:\n\
+             \t\treturn errno.errorcode.get(e.errno, str(e.errno))\n\
+             def probe():\n\
+             \treturn [\n\
+             \t\tattempt(lambda: os.rename('/fs/secret.txt', '/fs/moved.txt')),\n\
+             \t\tattempt(lambda: os.link('/fs/secret.txt', '/fs/linked.txt')),\n\
+             \t\tattempt(lambda: os.symlink('secret.txt', '/fs/alias.txt')),\n\
+             \t\tattempt(lambda: os.symlink('/fs/secret.txt', '/fs/absolute.txt')),\n\
+             \t]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate policy script")?;
+
+    let output = call_with_timeout(&mut sandbox, "probe", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call probe")?;
+    let result: Vec<String> = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode probe result")?;
+    assert_eq!(result, ["EACCES"; 4]);
+    for name in ["moved.txt", "linked.txt", "alias.txt", "absolute.txt"] {
+        assert!(!temp.path().join(name).exists(), "{name} was created");
+    }
+    assert!(temp.path().join("secret.txt").exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_scratch_dir_is_private_and_limited() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let module = Arc::new(module);
+    let options = || SandboxOptions::default().scratch_dir("/scratch", 4096);
+    let pool = single_sandbox_pool(&module, options()).await?;
+    let mut first = pool.acquire().await.context("failed to acquire")?;
+    let second = module
+        .instantiate(TestHost::default(), options())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    first
+        .eval_script(
+            "import errno\n\
+             def write(name, size):\n\
+             \ttry:\n\
+             \t\twith open('/scratch/' + name, 'wb') as fh:\n\
+             \t\t\tfh.write(b'x' * size)\n\
+             \texcept OSError as e:\n\
+             \t\treturn errno.errorcode.get(e.errno, str(e.errno))\n\
+             \treturn 'ok'",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate scratch script")?;
+    for (name, size, expected) in [("a.bin", 1024, "ok"), ("b.bin", 8192, "ENOSPC")] {
+        let output = call_with_timeout(
+            &mut first,
+            "write",
+            args![name, size]?,
+            Duration::from_secs(2),
+        )
+        .await
+        .context("failed to call write")?;
+        assert_eq!(
+            output
+                .result
+                .context("missing result")?
+                .to_serde::<String>()?,
+            expected
+        );
+    }
+
+    let files = first.list_guest_files("/scratch").await?;
+    assert!(files.contains(&"/scratch/a.bin".to_string()), "{files:?}");
+    assert!(second.list_guest_files("/scratch").await?.is_empty());
+
+    drop(first);
+    let recycled = pool.acquire().await.context("failed to acquire")?;
+    assert!(recycled.list_guest_files("/scratch").await?.is_empty());
+    drop(recycled);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_filtered_mount_hides_unmatched_files() -> Result<()> {
+    let dir = tempdir().context("failed to create temp directory")?;
+    std::fs::write(dir.path().join("a.csv"), "x,y")?;
+    std::fs::write(dir.path().join("b.txt"), "secret")?;
+    std::fs::create_dir(dir.path().join("sub"))?;
+    std::fs::write(dir.path().join("sub/c.csv"), "nested")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().mount_filtered(
+                dir.path(),
+                "/data",
+                DirPerms::all(),
+                FilePerms::all(),
+                ["*.csv"],
+            ),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+    sandbox
+        .eval_script(
+            "import errno, os\n\
+             def attempt(f):\n\
+             \ttry:\n\
+             \t\tf()\n\
+             \texcept OSError as e:\n\
+             \t\treturn errno.errorcode.get(e.errno, str(e.errno))\n\
+             \treturn 'ok'\n\
+             def probe():\n\
+             \treturn [\n\
+             \t\t','.join(sorted(os.listdir('/data'))),\n\
+             \t\t','.join(sorted(os.listdir('/data/sub'))),\n\
+             \t\topen('/data/a.csv').read(),\n\
+             \t\tattempt(lambda: open('/data/b.txt')),\n\
+             \t\tattempt(lambda: os.stat('/data/b.txt')),\n\
+             \t\tattempt(lambda: open('/data/new.txt', 'w')),\n\
+             \t\tattempt(lambda: open('/data/new.csv', 'w').close()),\n\
+             \t]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate probe script")?;
+
+    let output = call_with_timeout(&mut sandbox, "probe", [], Duration::from_secs(2))
+        .await
+        .context("failed to call probe")?;
+    assert_eq!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<Vec<String>>()?,
+        ["a.csv,sub", "", "x,y", "ENOENT", "ENOENT", "EACCES", "ok"]
+    );
+    assert!(!dir.path().join("new.txt").exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_overlay_copy_up_counts_against_write_budget() -> Result<()> {
+    let base = tempdir().context("failed to create temp directory")?;
+    std::fs::write(base.path().join("large.bin"), vec![b'x'; 4096])?;
+    std::fs::write(base.path().join("small.txt"), "base")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default()
+                .mount_overlay(OverlayMount::new(base.path()).capture_writes(), "/data")
+                .max_write_bytes(1024),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import errno\n\
+             def append(name):\n\
+             \ttry:\n\
+             \t\twith open('/data/' + name, 'a') as fh:\n\
+             \t\t\tfh.write('+')\n\
+             \texcept OSError as e:\n\
+             \t\treturn errno.errorcode.get(e.errno, str(e.errno))\n\
+             \treturn 'ok'",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate append script")?;
+    for (name, expected) in [("large.bin", "ENOSPC"), ("small.txt", "ok")] {
+        let output =
+            call_with_timeout(&mut sandbox, "append", args![name]?, Duration::from_secs(2))
+                .await
+                .context("failed to call append")?;
+        let result: String = output
+            .result
+            .as_ref()
+            .context("expected exactly one end output")?
+            .to_serde()
+            .context("failed to decode append result")?;
+        assert_eq!(result, expected, "appending to {name}");
+    }
+    assert_eq!(
+        sandbox.captured_writes("/data").await?,
+        [("/data/small.txt".to_string(), b"base+".to_vec())]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_overlay_mount_captures_writes() -> Result<()> {
+    let base = tempdir().context("failed to create temp directory")?;
+    std::fs::write(base.path().join("shared.txt"), "base")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = || {
+        SandboxOptions::default()
+            .mount_overlay(OverlayMount::new(base.path()).capture_writes(), "/data")
+    };
+    let module = Arc::new(module);
+    let pool = single_sandbox_pool(&module, options()).await?;
+    let mut first = pool.acquire().await.context("failed to acquire")?;
+    let second = module
+        .instantiate(TestHost::default(), options())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    first
+        .eval_script(
+            "import os\n\
+             os.makedirs('/data/out', exist_ok=True)\n\
+             with open('/data/shared.txt', 'a', encoding='utf-8') as fh:\n\
+             \tfh.write('+guest')\n\
+             with open('/data/out/new.txt', 'w', encoding='utf-8') as fh:\n\
+             \tfh.write('new')",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to write through overlay")?;
+
+    assert_eq!(
+        first.captured_writes("/data").await?,
+        [
+            ("/data/out/new.txt".to_string(), b"new".to_vec()),
+            ("/data/shared.txt".to_string(), b"base+guest".to_vec()),
+        ]
+    );
+    assert_eq!(
+        std::fs::read_to_string(base.path().join("shared.txt"))?,
+        "base"
+    );
+    assert!(second.captured_writes("/data").await?.is_empty());
+    assert_eq!(second.read_guest_file("/data/shared.txt").await?, b"base");
+    second
+        .captured_writes("/elsewhere")
+        .await
+        .expect_err("no capturing mount");
+
+    drop(first);
+    let recycled = pool.acquire().await.context("failed to acquire")?;
+    assert!(recycled.captured_writes("/data").await?.is_empty());
+    assert_eq!(recycled.read_guest_file("/data/shared.txt").await?, b"base");
+    drop(recycled);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_overlay_mount_layers_directories() -> Result<()> {
+    let base = tempdir().context("failed to create temp directory")?;
+    let tenant = tempdir().context("failed to create temp directory")?;
+    let scratch = tempdir().context("failed to create temp directory")?;
+    std::fs::write(base.path().join("shared.txt"), "base")?;
+    std::fs::write(base.path().join("config.txt"), "base")?;
+    std::fs::write(tenant.path().join("config.txt"), "tenant")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let overlay = OverlayMount::new(base.path())
+        .layer(tenant.path())
+        .writable(scratch.path());
+    let options = SandboxOptions::default().mount_overlay(overlay, "/data");
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import os\n\
+             def main():\n\
+             \tread = lambda name: open('/data/' + name, encoding='utf-8').read()\n\
+             \tbefore = [read('shared.txt'), read('config.txt')]\n\
+             \twith open('/data/shared.txt', 'a', encoding='utf-8') as fh:\n\
+             \t\tfh.write('+guest')\n\
+             \twith open('/data/new.txt', 'w', encoding='utf-8') as fh:\n\
+             \t\tfh.write('new')\n\
+             \ttry:\n\
+             \t\tos.remove('/data/config.txt')\n\
+             \t\tremoved = True\n\
+             \texcept OSError:\n\
+             \t\tremoved = False\n\
+             \treturn [before, read('shared.txt'), sorted(os.listdir('/data')), removed]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate overlay script")?;
+
+    let output = call_with_timeout(&mut sandbox, "main", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call overlay function")?;
+    let result: (Vec<String>, String, Vec<String>, bool) = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode overlay result")?;
+    assert_eq!(
+        result,
+        (
+            vec!["base".to_string(), "tenant".to_string()],
+            "base+guest".to_string(),
+            vec![
+                "config.txt".to_string(),
+                "new.txt".to_string(),
+                "shared.txt".to_string()
+            ],
+            false,
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(base.path().join("shared.txt"))?,
+        "base"
+    );
+    assert_eq!(
+        std::fs::read_to_string(scratch.path().join("shared.txt"))?,
+        "base+guest"
+    );
+    assert_eq!(
+        sandbox.list_guest_files("/data").await?,
+        ["/data/config.txt", "/data/new.txt", "/data/shared.txt"]
+    );
+    assert_eq!(
+        sandbox.read_guest_file("/data/config.txt").await?,
+        b"tenant"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_live_mount_and_unmount() -> Result<()> {
+    let first = tempdir().context("failed to create temp directory")?;
+    let second = tempdir().context("failed to create temp directory")?;
+    std::fs::write(first.path().join("input.txt"), "first")?;
+    std::fs::write(second.path().join("input.txt"), "second")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+    sandbox
+        .eval_script(
+            "def read():\n    with open('/request/input.txt') as f:\n        return f.read()\n",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to define reader")?;
+
+    sandbox
+        .mount(first.path(), "/request", DirPerms::READ, FilePerms::READ)
+        .await
+        .context("failed to mount first directory")?;
+    let output = sandbox.call("read", []).await?;
+    assert_eq!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<String>()?,
+        "first"
+    );
+
+    sandbox
+        .mount(second.path(), "/request", DirPerms::READ, FilePerms::READ)
+        .await
+        .context("failed to replace mount")?;
+    let output = sandbox.call("read", []).await?;
+    assert_eq!(
+        output
+            .result
+            .context("missing result")?
+            .to_serde::<String>()?,
+        "second"
+    );
+    assert_eq!(
+        sandbox.list_guest_files("/request").await?,
+        ["/request/input.txt"]
+    );
+
+    sandbox.unmount("/request").await?;
+    sandbox
+        .call("read", [])
+        .await
+        .expect_err("unmounted directories are gone");
+    let err = sandbox
+        .unmount("/request")
+        .await
+        .expect_err("nothing left to unmount");
+    assert_eq!(err.kind(), ErrorKind::Io);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_workdir_sets_guest_cwd() -> Result<()> {
+    let dir = tempdir().context("failed to create temp directory")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default()
+        .mount(dir.path(), "/work", DirPerms::all(), FilePerms::all())
+        .workdir("/work/job");
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import os\n\
+             def main():\n\
+             \twith open('out.txt', 'w', encoding='utf-8') as fh:\n\
+             \t\tfh.write('ok')\n\
+             \treturn os.getcwd()",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate workdir script")?;
+
+    let output = call_with_timeout(&mut sandbox, "main", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call workdir function")?;
+    let cwd: String = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode workdir result")?;
+    assert_eq!(cwd, "/work/job");
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("job/out.txt"))?,
+        "ok"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_open_handle_limit() -> Result<()> {
+    let dir = tempdir().context("failed to create temp directory")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default()
+        .mount(dir.path(), "/data", DirPerms::all(), FilePerms::all())
+        .max_open_handles(64);
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "def closed():\n\
+             \tfor i in range(256):\n\
+             \t\twith open('/data/f', 'w') as fh:\n\
+             \t\t\tfh.write(str(i))\n\
+             \treturn i\n\
+             def leaked():\n\
+             \treturn [open('/data/f') for _ in range(256)]",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate handle script")?;
+
+    let output = call_with_timeout(&mut sandbox, "closed", vec![], Duration::from_secs(2))
+        .await
+        .context("failed to call closed")?;
+    let last: i64 = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode handle result")?;
+    assert_eq!(last, 255);
+
+    let err = sandbox
+        .call("leaked", [])
+        .await
+        .expect_err("expected the handle limit to stop the leak");
+    assert_eq!(
+        err.kind(),
+        ErrorKind::PolicyDenied,
+        "unexpected error: {err}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_open_file_limit() -> Result<()> {
+    let dir = tempdir().context("failed to create temp directory")?;
+    std::fs::write(dir.path().join("f"), "data").context("failed to write file")?;
+
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let options = SandboxOptions::default()
+        .mount(dir.path(), "/data", DirPerms::all(), FilePerms::all())
+        .max_open_files(8);
+    let mut sandbox = module
+        .instantiate(TestHost::default(), options)
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "held = []\n\
+             def hold(n):\n\
+             \ttry:\n\
+             \t\theld.extend(open('/data/f') for _ in range(n))\n\
+             \texcept OSError:\n\
+             \t\treturn -len(held)\n\
+             \treturn len(held)\n\
+             def release():\n\
+             \theld.pop().close()\n\
+             \treturn len(held)",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate file script")?;
+
+    let output = sandbox
+        .call("hold", args![8_i64]?)
+        .await
+        .context("failed to hold files up to the limit")?;
+    let held: i64 = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode file count")?;
+    assert_eq!(held, 8);
+
+    let output = sandbox
+        .call("hold", args![1_i64]?)
+        .await
+        .context("the file limit must fail the open, not the call")?;
+    let held: i64 = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode file count")?;
+    assert_eq!(held, -8, "the guest must see the open fail with OSError");
+
+    sandbox
+        .call("release", [])
+        .await
+        .context("failed to close a file")?;
+    let output = sandbox
+        .call("hold", args![1_i64]?)
+        .await
+        .context("failed to reopen a file after closing one")?;
+    let held: i64 = output
+        .result
+        .as_ref()
+        .context("expected exactly one end output")?
+        .to_serde()
+        .context("failed to decode file count")?;
+    assert_eq!(held, 8);
+
+    Ok(())
+}
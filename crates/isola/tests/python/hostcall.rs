@@ -1,9 +1,17 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
-use isola::{host::OutputTarget, sandbox::SandboxOptions};
+use isola::{
+    host::{BoxError, Host, OutputEvent, OutputTarget, current_call_id},
+    sandbox::{CallOptions, Capability, CapabilitySet, SandboxOptions, TraceKind, TraceOutcome},
+    value::Value,
+};
+use parking_lot::Mutex;
 
-use super::common::{TestHost, build_module};
+use super::common::{TestHost, build_module, build_module_with_native_async};
 
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
@@ -46,6 +54,183 @@ async fn integration_python_async_hostcall_echo() -> Result<()> {
     Ok(())
 }
 
+#[derive(Clone, Default)]
+struct CallIdHost(Arc<Mutex<Vec<Option<u64>>>>);
+
+impl Host for CallIdHost {
+    async fn hostcall(&self, _call_type: &str, payload: Value) -> Result<Value, BoxError> {
+        self.0.lock().push(current_call_id());
+        Ok(payload)
+    }
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_hostcalls_and_sinks_see_the_call_id() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let host = CallIdHost::default();
+    let mut sandbox = module
+        .instantiate(host.clone(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "from sandbox.asyncio import hostcall\n\
+             async def main():\n\
+             \treturn await hostcall(\"echo\", 1)",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate hostcall script")?;
+    let eval_id = sandbox.last_call_id().context("expected an eval call id")?;
+
+    let sink_ids = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&sink_ids);
+    let target = OutputTarget::synchronous(move |_event| {
+        recorded.lock().push(current_call_id());
+        Ok(())
+    });
+    tokio::time::timeout(
+        Duration::from_secs(2),
+        sandbox.call_with_sink("main", [], target),
+    )
+    .await
+    .context("call timed out")?
+    .context("failed to call hostcall function")?;
+
+    let call_id = sandbox.last_call_id().context("expected a call id")?;
+    assert_ne!(call_id, eval_id);
+    assert_eq!(*host.0.lock(), [Some(call_id)]);
+    assert_eq!(*sink_ids.lock(), [Some(call_id)]);
+    assert_eq!(current_call_id(), None);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_call_capabilities_gate_hostcalls() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "from sandbox.asyncio import hostcall\n\
+             async def main():\n\
+             \ttry:\n\
+             \t\treturn await hostcall(\"echo\", 1)\n\
+             \texcept Exception as e:\n\
+             \t\treturn 'denied: ' + str(e)",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate capability script")?;
+
+    for (capabilities, allowed) in [
+        (CapabilitySet::new(), false),
+        (
+            CapabilitySet::new().allow(Capability::Hostcall("ec*".to_string())),
+            true,
+        ),
+    ] {
+        let result = Arc::new(Mutex::new(None));
+        let sink_result = Arc::clone(&result);
+        let target = OutputTarget::synchronous(move |event| {
+            if let OutputEvent::Complete(value) = event {
+                *sink_result.lock() = value;
+            }
+            Ok(())
+        });
+        tokio::time::timeout(
+            Duration::from_secs(2),
+            sandbox.call_with_options(
+                "main",
+                [],
+                target,
+                CallOptions::default().capabilities(capabilities),
+            ),
+        )
+        .await
+        .context("capability call timed out")?
+        .context("failed to call capability function")?;
+        let value = result.lock().take().context("expected end output")?;
+        if allowed {
+            assert_eq!(value.to_serde::<i64>()?, 1);
+        } else {
+            let message: String = value.to_serde()?;
+            assert!(message.contains("not permitted"), "unexpected: {message}");
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_hostcall_limits_reject_excess_calls() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().hostcall_limits(3, 1, Duration::from_millis(20)),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "from sandbox.asyncio import hostcall\n\
+             async def main():\n\
+             \tvalues, errors = [], []\n\
+             \tfor i in range(5):\n\
+             \t\ttry:\n\
+             \t\t\tvalues.append(await hostcall(\"echo\", i))\n\
+             \t\texcept Exception as e:\n\
+             \t\t\terrors.append(str(e))\n\
+             \treturn values, errors",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate hostcall limit script")?;
+
+    for _ in 0..2 {
+        let started = Instant::now();
+        let output = tokio::time::timeout(Duration::from_secs(2), sandbox.call("main", []))
+            .await
+            .context("hostcall limit call timed out")?
+            .context("failed to call hostcall limit function")?;
+        assert!(
+            started.elapsed() >= Duration::from_millis(40),
+            "admitted calls are spaced by the minimum interval"
+        );
+        let (values, errors): (Vec<i64>, Vec<String>) = output
+            .result
+            .context("expected end output")?
+            .to_serde()
+            .context("failed to decode results")?;
+        assert_eq!(values, [0, 1, 2], "counts restart with each call");
+        assert_eq!(errors.len(), 2);
+        for message in &errors {
+            assert!(
+                message.contains("hostcall limit exceeded"),
+                "unexpected: {message}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 #[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
 async fn integration_python_unobserved_raw_hostcall_does_not_block() -> Result<()> {
@@ -344,3 +529,87 @@ async fn integration_python_cancelled_waiter_releases_hostcall() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_native_async_overlaps_hostcalls() -> Result<()> {
+    let Some(module) = build_module_with_native_async().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "import asyncio\n\
+             from sandbox.asyncio import hostcall\n\
+             async def main():\n\
+             \treturn await asyncio.gather(hostcall('delay', 200), hostcall('delay', 200))",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate native async hostcall script")?;
+
+    let started = Instant::now();
+    let output = tokio::time::timeout(Duration::from_secs(5), sandbox.call("main", []))
+        .await
+        .context("native async hostcall test timed out")?
+        .context("failed to call native async hostcall function")?;
+    assert!(started.elapsed() < Duration::from_millis(390));
+    let value: Vec<i64> = output
+        .result
+        .as_ref()
+        .context("expected end output")?
+        .to_serde()
+        .context("failed to decode native async hostcall result")?;
+    assert_eq!(value, vec![200, 200]);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_hostcall_trace_dump() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(
+            TestHost::default(),
+            SandboxOptions::default().trace_hostcalls(2),
+        )
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "from sandbox.asyncio import hostcall\n\
+             async def main():\n\
+             \tawait hostcall(\"echo\", 1)\n\
+             \tawait hostcall(\"echo\", 'payload')\n\
+             \tawait hostcall(\"missing\", None)",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate trace script")?;
+
+    let result = tokio::time::timeout(Duration::from_secs(2), sandbox.call("main", []))
+        .await
+        .context("call timed out")?;
+    assert!(result.is_err(), "unknown hostcall should fail the call");
+
+    let dump = sandbox.debug_dump();
+    let call_id = sandbox.last_call_id();
+    assert_eq!(dump.last_call_id, call_id);
+    let targets: Vec<_> = dump.entries.iter().map(|e| e.target.as_str()).collect();
+    assert_eq!(targets, ["echo", "missing"]);
+    assert!(dump.entries.iter().all(|e| e.kind == TraceKind::Hostcall));
+    assert!(dump.entries.iter().all(|e| e.call_id == call_id));
+    assert_eq!(dump.entries[0].outcome, TraceOutcome::Ok);
+    assert!(matches!(dump.entries[1].outcome, TraceOutcome::Failed(_)));
+    assert!(dump.to_string().contains("hostcall missing"));
+
+    Ok(())
+}
@@ -513,3 +513,53 @@ def main(url):
 
     Ok(())
 }
+
+#[tokio::test]
+#[cfg_attr(debug_assertions, ignore = "integration tests run in release mode")]
+async fn integration_python_guest_http_handler_streams_response() -> Result<()> {
+    let Some(module) = build_module().await? else {
+        return Ok(());
+    };
+    let mut sandbox = module
+        .instantiate(TestHost::default(), SandboxOptions::default())
+        .await
+        .context("failed to instantiate sandbox")?;
+
+    sandbox
+        .eval_script(
+            "def handle(request):\n\
+             \tyield {'status': 201, 'headers': {'content-type': 'text/plain'}}\n\
+             \tyield request['method'] + ' ' + request['uri']\n\
+             \tyield b' ' + request['body']",
+            OutputTarget::discard(),
+        )
+        .await
+        .context("failed to evaluate handler script")?;
+
+    let request = http::Request::builder()
+        .method("POST")
+        .uri("http://svc.example/hook")
+        .body(Some(bytes::Bytes::from_static(b"payload")))?;
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tokio::time::timeout(
+        Duration::from_secs(2),
+        sandbox.handle_http("handle", request, move |response| {
+            let _ = tx.send(response);
+        }),
+    )
+    .await
+    .context("handler timed out")?
+    .context("handler failed")?;
+
+    let response = rx.await.context("handler did not respond")?;
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    let body =
+        http_body_util::BodyExt::collect(http_body_util::StreamBody::new(response.into_body()))
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?
+            .to_bytes();
+    assert_eq!(&body[..], b"POST http://svc.example/hook payload");
+
+    Ok(())
+}
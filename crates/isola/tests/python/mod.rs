@@ -1,5 +1,6 @@
 mod common;
 mod core;
+mod fs;
 mod hostcall;
 mod http;
 mod third_party;
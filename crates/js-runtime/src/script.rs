@@ -1,14 +1,15 @@
 use std::{
     borrow::Cow,
     cell::RefCell,
-    collections::{HashMap, hash_map::DefaultHasher},
+    collections::{HashMap, HashSet, hash_map::DefaultHasher},
     hash::{Hash, Hasher},
-    path::Path,
+    path::{Path, PathBuf},
     rc::Rc,
 };
 
 use rquickjs::{
-    Array, Context, Ctx, Function, Object, Runtime, Value, function::Args, promise::PromiseState,
+    Array, Context, Ctx, Function, Module, Object, Runtime, Value, function::Args,
+    promise::PromiseState,
 };
 
 use crate::{
@@ -26,6 +27,7 @@ pub struct Scope {
     runtime: Runtime,
     context: Context,
     rejections: Rc<RefCell<HashMap<u64, Rejection>>>,
+    modules: RefCell<HashSet<String>>,
 }
 
 #[derive(Clone)]
@@ -132,6 +134,7 @@ impl Scope {
             runtime,
             context,
             rejections,
+            modules: RefCell::default(),
         }
     }
 
@@ -163,6 +166,57 @@ impl Scope {
         self.finish_boundary(result)
     }
 
+    /// Evaluate every `.js` and `.ts` file below the directory at `path` in
+    /// path order, finishing with its top-level `index.js` or `index.ts`.
+    pub fn load_package(&self, path: &str) -> Result<()> {
+        let dir = Path::new(path);
+        let mut files = Vec::new();
+        collect_sources(dir, &mut files)
+            .map_err(|_| Error::Unexpected("failed to read package directory"))?;
+        files.sort();
+        let is_entry = |file: &PathBuf| {
+            file.parent() == Some(dir) && file.file_stem().is_some_and(|stem| stem == "index")
+        };
+        if !files.iter().any(is_entry) {
+            return Err(Error::Unexpected("package has no index.js or index.ts"));
+        }
+        files.sort_by_key(is_entry);
+        for file in files {
+            let file = file
+                .to_str()
+                .ok_or(Error::Unexpected("package path is not valid UTF-8"))?;
+            self.load_file(file)?;
+        }
+        Ok(())
+    }
+
+    /// Evaluate `code` as an ES module named `name`, which later modules can
+    /// import by that name, and bind its namespace to the global `name`.
+    pub fn load_module(&self, name: &str, code: &str) -> Result<()> {
+        if self.modules.borrow().contains(name) {
+            return Err(Error::Js {
+                cause: format!("module '{name}' is already loaded"),
+                stack: None,
+            });
+        }
+        self.begin_boundary();
+        let code = Self::transpile(code, None)?;
+        let result = self.context.with(|ctx| {
+            let module =
+                Module::declare(ctx.clone(), name, code).map_err(|_| Error::from_js_catch(&ctx))?;
+            // QuickJS keeps a declared module even if evaluating it fails.
+            self.modules.borrow_mut().insert(name.to_string());
+            let (module, promise) = module.eval().map_err(|_| Error::from_js_catch(&ctx))?;
+            self.drive_promise(&ctx, &promise, false)?;
+            self.checkpoint(&ctx)?;
+            let namespace = module.namespace().map_err(|_| Error::from_js_catch(&ctx))?;
+            ctx.globals()
+                .set(name, namespace)
+                .map_err(|_| Error::from_js_catch(&ctx))
+        });
+        self.finish_boundary(result)
+    }
+
     pub fn run<'a>(
         &self,
         name: &str,
@@ -209,6 +263,26 @@ impl Scope {
         self.finish_boundary(result)
     }
 
+    /// Evaluate the expression `expr` in the global scope and emit its value
+    /// as [`run`](Self::run) emits a call's result.
+    pub fn eval_expr(&self, expr: &str, mut callback: impl FnMut(EmitType, &[u8])) -> Result<()> {
+        self.begin_boundary();
+        let result = self.context.with(|ctx| {
+            // Parenthesize so object literals are not parsed as blocks.
+            let val: Value<'_> = ctx
+                .eval(format!("({expr}\n)"))
+                .map_err(|_| Error::from_js_catch(&ctx))?;
+            self.checkpoint(&ctx)?;
+            let obj = if let Some(promise) = val.as_promise() {
+                self.drive_promise(&ctx, promise, false)?
+            } else {
+                val
+            };
+            self.emit_result(&ctx, obj, &mut callback)
+        });
+        self.finish_boundary(result)
+    }
+
     fn begin_boundary(&self) {
         self.rejections.borrow_mut().clear();
     }
@@ -542,3 +616,21 @@ impl Scope {
             || val.is_object()
     }
 }
+
+fn collect_sources(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_sources(&path, files)?;
+        } else if file_type.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == "js" || ext == "ts")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
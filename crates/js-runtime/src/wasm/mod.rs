@@ -3,11 +3,16 @@ pub mod http;
 mod logging;
 mod serde;
 
-use std::cell::RefCell;
+use std::{borrow::Cow, cell::RefCell};
 
 pub use isola_runtime::{exports, isola, wasi};
 
-use self::{exports::isola::script::runtime, isola::script::host};
+use self::{
+    exports::isola::script::{
+        batch, coverage, exceptions, expressions, modules, packages, preludes, preopens, runtime,
+    },
+    isola::script::host,
+};
 use crate::{
     error::Error,
     script::{InputValue, Scope},
@@ -19,45 +24,58 @@ isola_runtime::export!(Global with_types_in isola_runtime);
 
 pub struct Global;
 
-impl runtime::Guest for Global {
-    fn initialize(preinit: bool, prelude: Option<String>) {
-        GLOBAL_SCOPE.with(|scope| {
-            let mut scope = scope.borrow_mut();
-            if scope.is_none() {
-                const ASYNC_JS: &str = include_str!("../../js/sandbox/async.js");
-                const WINTERTC_ABORT_JS: &str = include_str!("../../js/sandbox/wintertc_abort.js");
-                const WINTERTC_HTTP_JS: &str = include_str!("../../js/sandbox/wintertc_http.js");
-
-                let s = Scope::new();
-
-                // Register native bridge modules as globals
-                s.context().with(|ctx| {
-                    self::serde::register(&ctx);
-                    self::logging::register(&ctx);
-                    self::http::register(&ctx);
-                    register_sys_module(&ctx);
-                    // future::register_js must come after register_sys_module
-                    // because it reads _isola_sys from globals
-                    self::future::register_js(&ctx);
-                });
-
-                // Load JS-side async infrastructure and HTTP platform wrappers.
-                // async.js must come before wintertc_http.js (uses _isola_async._wait).
-                // async.js must come after register_sys_module because it
-                // reads _isola_sys and exposes top-level async helpers.
-                s.load_script(ASYNC_JS).unwrap();
-                s.load_script(WINTERTC_ABORT_JS).unwrap();
-                s.load_script(WINTERTC_HTTP_JS).unwrap();
-
-                if let Some(prelude) = prelude {
-                    s.load_script(&prelude).unwrap();
-                }
-                scope.replace(s);
+/// Set up the interpreter and run `preludes` in order, unless an earlier
+/// call already did.
+fn initialize(preinit: bool, preludes: &[String]) -> Result<(), preludes::PreludeError> {
+    GLOBAL_SCOPE.with(|scope| {
+        let mut scope = scope.borrow_mut();
+        if scope.is_none() {
+            const ASYNC_JS: &str = include_str!("../../js/sandbox/async.js");
+            const WINTERTC_ABORT_JS: &str = include_str!("../../js/sandbox/wintertc_abort.js");
+            const WINTERTC_HTTP_JS: &str = include_str!("../../js/sandbox/wintertc_http.js");
+
+            let s = Scope::new();
+
+            // Register native bridge modules as globals
+            s.context().with(|ctx| {
+                self::serde::register(&ctx);
+                self::logging::register(&ctx);
+                self::http::register(&ctx);
+                register_sys_module(&ctx);
+                // future::register_js must come after register_sys_module
+                // because it reads _isola_sys from globals
+                self::future::register_js(&ctx);
+            });
+
+            // Load JS-side async infrastructure and HTTP platform wrappers.
+            // async.js must come before wintertc_http.js (uses _isola_async._wait).
+            // async.js must come after register_sys_module because it
+            // reads _isola_sys and exposes top-level async helpers.
+            s.load_script(ASYNC_JS).unwrap();
+            s.load_script(WINTERTC_ABORT_JS).unwrap();
+            s.load_script(WINTERTC_HTTP_JS).unwrap();
+
+            for (index, prelude) in (0..).zip(preludes) {
+                s.load_script(prelude).map_err(|e| preludes::PreludeError {
+                    index,
+                    error: e.into(),
+                })?;
             }
-        });
+            scope.replace(s);
+        }
+        Ok::<_, preludes::PreludeError>(())
+    })?;
+
+    if preinit {
+        isola_runtime::lifecycle::reset_preinitialized_state();
+    }
+    Ok(())
+}
 
-        if preinit {
-            isola_runtime::lifecycle::reset_preinitialized_state();
+impl runtime::Guest for Global {
+    fn initialize(preinit: bool, prelude: Option<String>) {
+        if let Err(e) = initialize(preinit, prelude.as_slice()) {
+            panic!("prelude failed: {}", e.error.message);
         }
     }
 
@@ -66,6 +84,7 @@ impl runtime::Guest for Global {
         reason = "WIT async export requires an async trait method"
     )]
     async fn eval_script(script: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
         GLOBAL_SCOPE.with_borrow(|sandbox| {
             sandbox.as_ref().map_or_else(
                 || Err(Error::Unexpected("Sandbox not initialized").into()),
@@ -83,6 +102,7 @@ impl runtime::Guest for Global {
         reason = "WIT async export requires an async trait method"
     )]
     async fn eval_file(path: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
         GLOBAL_SCOPE.with_borrow(|sandbox| {
             sandbox.as_ref().map_or_else(
                 || Err(Error::Unexpected("Sandbox not initialized").into()),
@@ -100,26 +120,12 @@ impl runtime::Guest for Global {
         reason = "WIT async export requires an async trait method"
     )]
     async fn call_func(func: String, args: Vec<runtime::Argument>) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
         GLOBAL_SCOPE.with_borrow(|sandbox| {
             sandbox.as_ref().map_or_else(
                 || Err(Error::Unexpected("Sandbox not initialized").into()),
                 |sandbox| {
-                    let mut positional = vec![];
-                    let mut named = vec![];
-                    for arg in args {
-                        let runtime::Argument { name, value } = arg;
-                        let value = match value {
-                            isola::script::host::Value::Cbor(s) => InputValue::Cbor(s.into()),
-                            isola::script::host::Value::CborIterator(e) => {
-                                InputValue::Iter(collect_stream_arg(&e))
-                            }
-                        };
-                        if let Some(name) = name {
-                            named.push((name.into(), value));
-                        } else {
-                            positional.push(value);
-                        }
-                    }
+                    let (positional, named) = split_args(args);
                     sandbox
                         .run(&func, positional, named, |emit_type, data| {
                             isola::script::host::blocking_emit(emit_type, data);
@@ -131,6 +137,158 @@ impl runtime::Guest for Global {
     }
 }
 
+impl exceptions::Guest for Global {
+    /// Exceptions are only reported as text, in the error message.
+    fn last_exception() -> Option<runtime::GuestException> {
+        None
+    }
+}
+
+impl preludes::Guest for Global {
+    fn initialize(preinit: bool, preludes: Vec<String>) -> Result<(), preludes::PreludeError> {
+        initialize(preinit, &preludes)
+    }
+}
+
+impl preopens::Guest for Global {
+    fn reload_preopens() {
+        isola_runtime::lifecycle::reload_preopens();
+    }
+}
+
+impl expressions::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn eval_expr(expr: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
+        GLOBAL_SCOPE.with_borrow(|sandbox| {
+            sandbox.as_ref().map_or_else(
+                || Err(Error::Unexpected("Sandbox not initialized").into()),
+                |sandbox| {
+                    sandbox
+                        .eval_expr(&expr, |emit_type, data| {
+                            isola::script::host::blocking_emit(emit_type, data);
+                        })
+                        .map_err(Into::<runtime::Error>::into)
+                },
+            )
+        })
+    }
+}
+
+impl modules::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn load_module(name: String, code: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
+        GLOBAL_SCOPE.with_borrow(|sandbox| {
+            sandbox.as_ref().map_or_else(
+                || Err(Error::Unexpected("Sandbox not initialized").into()),
+                |sandbox| {
+                    sandbox
+                        .load_module(&name, &code)
+                        .map_err(Into::<runtime::Error>::into)
+                },
+            )
+        })
+    }
+}
+
+impl packages::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn eval_package(path: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
+        GLOBAL_SCOPE.with_borrow(|sandbox| {
+            sandbox.as_ref().map_or_else(
+                || Err(Error::Unexpected("Sandbox not initialized").into()),
+                |sandbox| {
+                    sandbox
+                        .load_package(&path)
+                        .map_err(Into::<runtime::Error>::into)
+                },
+            )
+        })
+    }
+}
+
+impl coverage::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn call_func(
+        _func: String,
+        _args: Vec<runtime::Argument>,
+    ) -> Result<Vec<coverage::FileCoverage>, runtime::Error> {
+        Err(Error::Unexpected("line coverage is not supported by the JS runtime").into())
+    }
+}
+
+impl batch::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn call_batch(calls: Vec<batch::BatchCall>) -> Vec<Result<(), batch::CallError>> {
+        isola_runtime::lifecycle::enter_initial_cwd();
+        GLOBAL_SCOPE.with_borrow(|sandbox| {
+            calls
+                .into_iter()
+                .map(|call| {
+                    let result = sandbox.as_ref().map_or_else(
+                        || Err(Error::Unexpected("Sandbox not initialized").into()),
+                        |sandbox| {
+                            let (positional, named) = split_args(call.args);
+                            sandbox
+                                .run(&call.func, positional, named, |emit_type, data| {
+                                    isola::script::host::blocking_emit(emit_type, data);
+                                })
+                                .map_err(Into::<runtime::Error>::into)
+                        },
+                    );
+                    // Successful calls end with their return value; close
+                    // failed ones too so the host can tell their output apart.
+                    if result.is_err() {
+                        host::blocking_emit(host::EmitType::End, &[]);
+                    }
+                    result.map_err(|error| batch::CallError {
+                        error,
+                        exception: None,
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+type NamedArgs<'a> = Vec<(Cow<'a, str>, InputValue<'a>)>;
+
+/// Split call arguments into positional and named values.
+fn split_args<'a>(args: Vec<runtime::Argument>) -> (Vec<InputValue<'a>>, NamedArgs<'a>) {
+    let mut positional = vec![];
+    let mut named = vec![];
+    for arg in args {
+        let runtime::Argument { name, value } = arg;
+        let value = match value {
+            host::Value::Cbor(s) => InputValue::Cbor(s.into()),
+            host::Value::CborIterator(e) => InputValue::Iter(collect_stream_arg(&e)),
+        };
+        if let Some(name) = name {
+            named.push((name.into(), value));
+        } else {
+            positional.push(value);
+        }
+    }
+    (positional, named)
+}
+
 fn collect_stream_arg(iter: &host::ValueIterator) -> Vec<Vec<u8>> {
     let mut items = Vec::new();
     while let Some(cbor) = isola_runtime::block_on(iter.read()) {
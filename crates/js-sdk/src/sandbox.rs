@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use isola::{
     host::{BoxError, LogLevel, OutputEvent, OutputTarget},
-    sandbox::{Arg, Sandbox},
+    sandbox::{Arg, SharedSandbox, SharedSandboxGuard},
     value::Value,
 };
 use napi::{
@@ -185,49 +185,11 @@ enum SandboxInner {
         hostcall_handler: Option<Arc<JsHostcallHandler>>,
     },
     Running {
-        sandbox: Option<Sandbox<Env>>,
+        sandbox: SharedSandbox<Env>,
         callback: Option<CallbackTsfn>,
     },
 }
 
-// ---------------------------------------------------------------------------
-// RunningSandboxLease
-// ---------------------------------------------------------------------------
-
-struct RunningSandboxLease {
-    inner: Arc<Mutex<SandboxInner>>,
-    sandbox: Option<Sandbox<Env>>,
-}
-
-impl RunningSandboxLease {
-    const fn new(inner: Arc<Mutex<SandboxInner>>, sandbox: Sandbox<Env>) -> Self {
-        Self {
-            inner,
-            sandbox: Some(sandbox),
-        }
-    }
-
-    const fn sandbox_mut(&mut self) -> &mut Sandbox<Env> {
-        self.sandbox
-            .as_mut()
-            .expect("running sandbox lease must contain sandbox")
-    }
-}
-
-impl Drop for RunningSandboxLease {
-    fn drop(&mut self) {
-        let Some(sandbox) = self.sandbox.take() else {
-            return;
-        };
-        let mut guard = self.inner.lock();
-        if let SandboxInner::Running { sandbox: slot, .. } = &mut *guard
-            && slot.is_none()
-        {
-            *slot = Some(sandbox);
-        }
-    }
-}
-
 // ---------------------------------------------------------------------------
 // Argument parsing
 // ---------------------------------------------------------------------------
@@ -330,27 +292,26 @@ fn take_stream_receivers(
 }
 
 // ---------------------------------------------------------------------------
-// Helper: take lease from running sandbox
+// Helper: wait for the running sandbox
 // ---------------------------------------------------------------------------
 
-fn take_running_lease(
-    inner: &Arc<Mutex<SandboxInner>>,
-) -> napi::Result<(RunningSandboxLease, Option<CallbackTsfn>)> {
-    let mut guard = inner.lock();
-    match &mut *guard {
-        SandboxInner::Running { sandbox, callback } => {
-            let sandbox = sandbox
-                .take()
-                .ok_or_else(|| napi::Error::from(invalid_argument("sandbox is busy")))?;
-            Ok((
-                RunningSandboxLease::new(Arc::clone(inner), sandbox),
-                callback.clone(),
-            ))
+async fn lock_running(
+    inner: &Mutex<SandboxInner>,
+) -> napi::Result<(SharedSandboxGuard<Env>, Option<CallbackTsfn>)> {
+    let (shared, callback) = match &*inner.lock() {
+        SandboxInner::Running { sandbox, callback } => (sandbox.clone(), callback.clone()),
+        _ => {
+            return Err(napi::Error::from(invalid_argument(
+                "sandbox is not running",
+            )));
         }
-        _ => Err(napi::Error::from(invalid_argument(
-            "sandbox is not running",
-        ))),
-    }
+    };
+    // Concurrent calls wait their turn instead of failing.
+    let sandbox = shared
+        .lock()
+        .await
+        .map_err(|err| napi::Error::from(Error::Internal(err.to_string())))?;
+    Ok((sandbox, callback))
 }
 
 // ---------------------------------------------------------------------------
@@ -519,7 +480,7 @@ impl SandboxCore {
             Ok(sandbox) => {
                 let mut guard = inner.lock();
                 *guard = SandboxInner::Running {
-                    sandbox: Some(sandbox),
+                    sandbox: SharedSandbox::new(sandbox),
                     callback,
                 };
                 drop(guard);
@@ -541,12 +502,11 @@ impl SandboxCore {
 
     #[napi]
     pub async fn load_script(&self, code: String) -> napi::Result<()> {
-        let inner = Arc::clone(&self.inner);
-        let (mut lease, callback) = take_running_lease(&inner)?;
+        let (mut sandbox, callback) = lock_running(&self.inner).await?;
 
         let collector = OutputCollector::new(callback);
         let sink = collector.target();
-        let outcome = lease.sandbox_mut().eval_script(&code, sink).await;
+        let outcome = sandbox.eval_script(&code, sink).await;
         if let Err(err) = outcome {
             let message = format!("Script loading failed: {err}");
             collector.emit_error_message(&message);
@@ -557,8 +517,7 @@ impl SandboxCore {
 
     #[napi]
     pub async fn run(&self, func: String, args: Vec<WireArgument>) -> napi::Result<RunResult> {
-        let inner = Arc::clone(&self.inner);
-        let (mut lease, callback) = take_running_lease(&inner)?;
+        let (mut sandbox, callback) = lock_running(&self.inner).await?;
 
         let parsed_args = parse_run_args(args).map_err(napi::Error::from)?;
 
@@ -575,10 +534,7 @@ impl SandboxCore {
             .collect::<crate::error::Result<Vec<_>>>()
             .map_err(napi::Error::from)?;
 
-        let outcome = lease
-            .sandbox_mut()
-            .call_with_sink(&func, isola_args, sink)
-            .await;
+        let outcome = sandbox.call_with_sink(&func, isola_args, sink).await;
         if let Err(err) = outcome {
             let message = format!("Sandbox execution failed: {err}");
             collector.emit_error_message(&message);
@@ -596,8 +552,7 @@ impl SandboxCore {
         args: Vec<WireArgument>,
         stream_args: Vec<&StreamHandle>,
     ) -> napi::Result<RunResult> {
-        let inner = Arc::clone(&self.inner);
-        let (mut lease, callback) = take_running_lease(&inner)?;
+        let (mut sandbox, callback) = lock_running(&self.inner).await?;
 
         let parsed = parse_stream_run_args(args, stream_args.len()).map_err(napi::Error::from)?;
         let mut receivers = take_stream_receivers(&stream_args).map_err(napi::Error::from)?;
@@ -625,10 +580,7 @@ impl SandboxCore {
         let collector = OutputCollector::new(callback);
         let sink = collector.target();
 
-        let outcome = lease
-            .sandbox_mut()
-            .call_with_sink(&func, isola_args, sink)
-            .await;
+        let outcome = sandbox.call_with_sink(&func, isola_args, sink).await;
         if let Err(err) = outcome {
             let message = format!("Sandbox execution failed: {err}");
             collector.emit_error_message(&message);
@@ -0,0 +1,43 @@
+from __future__ import annotations
+
+import os
+import sys
+from typing import TYPE_CHECKING
+
+if TYPE_CHECKING:
+    import types
+
+__all__: list[str] = []
+
+_TOOL_ID = sys.monitoring.COVERAGE_ID
+_EXCLUDED = (
+    os.path.dirname(os.__file__) + os.sep,
+    os.path.dirname(os.path.dirname(__file__)) + os.sep,
+    "<frozen ",
+)
+_lines: dict[str, set[int]] = {}
+
+
+def _on_line(code: types.CodeType, line: int) -> object:
+    filename = code.co_filename
+    if not filename.startswith(_EXCLUDED):
+        _lines.setdefault(filename, set()).add(line)
+    # Each line only needs to be seen once per call.
+    return sys.monitoring.DISABLE
+
+
+def _start() -> None:  # pyright: ignore[reportUnusedFunction]
+    _lines.clear()
+    sys.monitoring.use_tool_id(_TOOL_ID, "isola")
+    _ = sys.monitoring.register_callback(_TOOL_ID, sys.monitoring.events.LINE, _on_line)
+    sys.monitoring.set_events(_TOOL_ID, sys.monitoring.events.LINE)
+    sys.monitoring.restart_events()
+
+
+def _stop() -> list[tuple[str, list[int]]]:  # pyright: ignore[reportUnusedFunction]
+    sys.monitoring.set_events(_TOOL_ID, sys.monitoring.events.NO_EVENTS)
+    _ = sys.monitoring.register_callback(_TOOL_ID, sys.monitoring.events.LINE, None)
+    sys.monitoring.free_tool_id(_TOOL_ID)
+    report = sorted((path, sorted(lines)) for path, lines in _lines.items())
+    _lines.clear()
+    return report
@@ -4,6 +4,7 @@ import importlib.abc
 import importlib.resources.abc
 import importlib.util
 import io
+import os
 import re
 import sys
 import zipfile
@@ -222,3 +223,82 @@ def _initialize_pep723(meta: str) -> None:  # pyright: ignore[reportUnusedFuncti
         importers.append(HttpImporter(dep_info["url"]))
 
     sys.meta_path.extend(importers)
+
+
+def _load_package(path: str, scope: dict[str, object]) -> None:  # pyright: ignore[reportUnusedFunction]
+    parent, name = os.path.split(os.path.normpath(path))
+    if not name.isidentifier():
+        msg = f"{name!r} is not a valid package name"
+        raise ImportError(msg)
+    if not os.path.isfile(os.path.join(path, "__init__.py")):
+        msg = f"{path!r} is not a package: missing __init__.py"
+        raise ImportError(msg)
+
+    if parent not in sys.path:
+        sys.path.insert(0, parent)
+    importlib.invalidate_caches()
+    module = importlib.import_module(name)
+
+    names = cast("list[str] | None", getattr(module, "__all__", None))
+    if names is None:
+        names = [n for n in vars(module) if not n.startswith("_")]
+    scope[name] = module
+    for n in names:
+        scope[n] = getattr(module, n)
+
+
+class _SourceImporter(importlib.abc.MetaPathFinder, importlib.abc.InspectLoader):
+    def __init__(self) -> None:
+        self.sources: dict[str, str] = {}
+
+    @override
+    def find_spec(
+        self,
+        fullname: str,
+        path: Sequence[str] | None = None,
+        target: types.ModuleType | None = None,
+    ) -> ModuleSpec | None:
+        if fullname in self.sources:
+            return importlib.util.spec_from_loader(
+                fullname, self, origin=f"<{fullname}>"
+            )
+        return None
+
+    @override
+    def get_source(self, fullname: str) -> str:
+        if fullname not in self.sources:
+            msg = f"Module '{fullname}' was not loaded"
+            raise ImportError(msg)
+        return self.sources[fullname]
+
+    @override
+    def exec_module(self, module: types.ModuleType) -> None:
+        module.__file__ = f"<{module.__name__}>"
+        code = compile(self.get_source(module.__name__), module.__file__, "exec")
+        exec(code, module.__dict__)  # ruff:ignore[exec-builtin]
+
+
+_source_importer = _SourceImporter()
+
+
+def _load_module(name: str, code: str, scope: dict[str, object]) -> None:  # pyright: ignore[reportUnusedFunction]
+    if not name.isidentifier():
+        msg = f"{name!r} is not a valid module name"
+        raise ImportError(msg)
+    if _source_importer not in sys.meta_path:
+        sys.meta_path.insert(0, _source_importer)
+
+    previous = (_source_importer.sources.get(name), sys.modules.pop(name, None))
+    _source_importer.sources[name] = code
+    try:
+        module = importlib.import_module(name)
+    except BaseException:
+        source, module = previous
+        if source is None:
+            del _source_importer.sources[name]
+        else:
+            _source_importer.sources[name] = source
+        if module is not None:
+            sys.modules[name] = module
+        raise
+    scope[name] = module
@@ -1,7 +1,15 @@
-use pyo3::{PyErr, Python, prelude::PyTracebackMethods};
+use std::cell::RefCell;
+
+use pyo3::{
+    Bound, PyAny, PyErr, PyResult, Python,
+    exceptions::PyMemoryError,
+    prelude::{PyAnyMethods, PyTracebackMethods, PyTypeMethods},
+};
 use thiserror::Error;
 
-use crate::wasm::exports::{self, isola::script::runtime::ErrorCode};
+use crate::wasm::exports::isola::script::runtime::{
+    self, ErrorCode, GuestException, TracebackFrame,
+};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -9,6 +17,7 @@ pub enum Error {
     PythonError {
         cause: String,
         traceback: Option<String>,
+        exception: Option<GuestException>,
     },
 
     #[error("Unexpected error: {0}")]
@@ -20,34 +29,134 @@ pub type Result<T> = core::result::Result<T, Error>;
 impl Error {
     pub fn from_pyerr(py: Python<'_>, e: impl Into<PyErr>) -> Self {
         let e = e.into();
+        let mut cause = e.to_string();
+        if e.is_instance_of::<PyMemoryError>(py)
+            && let Some(sites) = top_allocation_sites(py)
+        {
+            cause.push_str("\n\n");
+            cause.push_str(&sites);
+        }
         Self::PythonError {
-            cause: e.to_string(),
+            cause,
             traceback: e.traceback(py).and_then(|e| e.format().ok()),
+            exception: describe_exception(py, &e).ok(),
         }
     }
 }
 
-impl From<Error> for exports::isola::script::runtime::Error {
-    fn from(value: Error) -> Self {
-        match value {
-            Error::PythonError {
+/// Report the exception type, message and stack frames of `e` as the host
+/// sees them, so embedders need not parse the formatted traceback.
+fn describe_exception(py: Python<'_>, e: &PyErr) -> PyResult<GuestException> {
+    let ty = e.get_type(py);
+    let name = ty.qualname()?.to_string();
+    let module = ty.module()?.to_string();
+    // Named like Python's own traceback printer does.
+    let exception_type = if matches!(module.as_str(), "builtins" | "__main__") {
+        name
+    } else {
+        format!("{module}.{name}")
+    };
+    let mut frames = Vec::new();
+    let mut next = e.traceback(py).map(Bound::into_any);
+    while let Some(tb) = next {
+        frames.push(describe_frame(&tb)?);
+        next = Some(tb.getattr("tb_next")?).filter(|tb| !tb.is_none());
+    }
+    Ok(GuestException {
+        exception_type,
+        message: e.value(py).str()?.to_string(),
+        frames,
+    })
+}
+
+fn describe_frame(tb: &Bound<'_, PyAny>) -> PyResult<TracebackFrame> {
+    let code = tb.getattr("tb_frame")?.getattr("f_code")?;
+    Ok(TracebackFrame {
+        file: code.getattr("co_filename")?.extract()?,
+        line: tb.getattr("tb_lineno")?.extract().ok(),
+        function: code.getattr("co_name")?.extract().ok(),
+    })
+}
+
+/// Number of allocation sites listed when a `MemoryError` is reported.
+const TOP_ALLOCATION_SITES: usize = 10;
+
+/// Summarize the largest allocation sites when `tracemalloc` is tracing.
+///
+/// Best effort: taking a snapshot allocates, so this returns `None` whenever
+/// the interpreter is too close to its memory limit to produce the report.
+fn top_allocation_sites(py: Python<'_>) -> Option<String> {
+    let tracemalloc = py.import("tracemalloc").ok()?;
+    if !tracemalloc
+        .call_method0("is_tracing")
+        .and_then(|tracing| tracing.is_truthy())
+        .ok()?
+    {
+        return None;
+    }
+    let stats = tracemalloc
+        .call_method0("take_snapshot")
+        .and_then(|snapshot| snapshot.call_method1("statistics", ("lineno",)))
+        .ok()?;
+    let mut report = String::from("Top allocation sites (tracemalloc):");
+    for stat in stats.try_iter().ok()?.take(TOP_ALLOCATION_SITES) {
+        report.push_str("\n  ");
+        report.push_str(&stat.and_then(|stat| stat.str()).ok()?.to_string());
+    }
+    Some(report)
+}
+
+impl Error {
+    /// Split into the error exports return and the exception behind it.
+    pub fn into_parts(self) -> (runtime::Error, Option<GuestException>) {
+        match self {
+            Self::PythonError {
                 cause,
                 traceback: Some(traceback),
-            } => Self {
-                code: ErrorCode::Aborted,
-                message: format!("{cause}\n\n{traceback}"),
-            },
-            Error::PythonError {
+                exception,
+            } => (
+                runtime::Error {
+                    code: ErrorCode::Aborted,
+                    message: format!("{cause}\n\n{traceback}"),
+                },
+                exception,
+            ),
+            Self::PythonError {
                 cause,
                 traceback: None,
-            } => Self {
-                code: ErrorCode::Aborted,
-                message: cause,
-            },
-            Error::UnexpectedError(e) => Self {
-                code: ErrorCode::Internal,
-                message: e.to_string(),
-            },
+                exception,
+            } => (
+                runtime::Error {
+                    code: ErrorCode::Aborted,
+                    message: cause,
+                },
+                exception,
+            ),
+            Self::UnexpectedError(e) => (
+                runtime::Error {
+                    code: ErrorCode::Internal,
+                    message: e.to_string(),
+                },
+                None,
+            ),
         }
     }
 }
+
+thread_local! {
+    /// Exception behind the error the last failed export returned.
+    static LAST_EXCEPTION: RefCell<Option<GuestException>> = const { RefCell::new(None) };
+}
+
+/// Take the exception behind the error the last failed export returned.
+pub fn take_last_exception() -> Option<GuestException> {
+    LAST_EXCEPTION.take()
+}
+
+impl From<Error> for runtime::Error {
+    fn from(value: Error) -> Self {
+        let (error, exception) = value.into_parts();
+        LAST_EXCEPTION.set(exception);
+        error
+    }
+}
@@ -110,6 +110,61 @@ impl Scope {
         })
     }
 
+    /// Import the package directory at `path` and bind its public names, and
+    /// the package itself, in the global scope.
+    pub fn load_package(&self, path: &str) -> crate::error::Result<()> {
+        static LOAD: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+
+        Python::attach(|py| {
+            LOAD.import(py, "sandbox.importlib", "_load_package")
+                .expect("failed to import sandbox.importlib")
+                .call1((path, self.locals.bind(py)))
+                .map_err(|e| Error::from_pyerr(py, e))?;
+            Ok(())
+        })
+    }
+
+    /// Load `code` as the module `name`, importable from later code, and bind
+    /// the module in the global scope.
+    pub fn load_module(&self, name: &str, code: &str) -> crate::error::Result<()> {
+        static LOAD: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+
+        Python::attach(|py| {
+            LOAD.import(py, "sandbox.importlib", "_load_module")
+                .expect("failed to import sandbox.importlib")
+                .call1((name, code, self.locals.bind(py)))
+                .map_err(|e| Error::from_pyerr(py, e))?;
+            Ok(())
+        })
+    }
+
+    /// Start recording the lines of user code executed by later calls.
+    pub fn start_coverage() -> crate::error::Result<()> {
+        static START: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+
+        Python::attach(|py| {
+            START
+                .import(py, "sandbox.coverage", "_start")
+                .expect("failed to import sandbox.coverage")
+                .call0()
+                .map_err(|e| Error::from_pyerr(py, e))?;
+            Ok(())
+        })
+    }
+
+    /// Stop recording and return the executed lines, sorted, of each file.
+    pub fn stop_coverage() -> crate::error::Result<Vec<(String, Vec<u32>)>> {
+        static STOP: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+
+        Python::attach(|py| {
+            STOP.import(py, "sandbox.coverage", "_stop")
+                .expect("failed to import sandbox.coverage")
+                .call0()
+                .and_then(|report| report.extract())
+                .map_err(|e| Error::from_pyerr(py, e))
+        })
+    }
+
     fn is_serializable(pyobject: &Bound<'_, PyAny>) -> bool {
         pyobject.is_none()
             || PyDict::is_exact_type_of(pyobject)
@@ -134,7 +189,7 @@ impl Scope {
         name: &str,
         positional: impl IntoIterator<Item = InputValue<'a>, IntoIter = U>,
         named: impl IntoIterator<Item = (Cow<'a, str>, InputValue<'a>)>,
-        mut callback: impl FnMut(crate::wasm::isola::script::host::EmitType, &[u8]),
+        callback: impl FnMut(crate::wasm::isola::script::host::EmitType, &[u8]),
     ) -> Result<()>
     where
         U: ExactSizeIterator<Item = InputValue<'a>>,
@@ -188,42 +243,81 @@ impl Scope {
                 f
             };
 
-            let obj = if obj.hasattr("__await__").unwrap_or_default()
-                || obj.hasattr("__aiter__").unwrap_or_default()
-            {
-                static ASYNC_RUN: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
-                ASYNC_RUN
-                    .import(py, "sandbox.asyncio", "run")
-                    .expect("failed to import sandbox.asyncio")
-                    .call1((obj,))
-                    .map_err(|e| Error::from_pyerr(py, e))?
-            } else {
-                obj
-            };
+            Self::emit_output(py, obj, callback)
+        })
+    }
 
-            if Self::is_serializable(&obj) {
-                return python_to_cbor_emit(obj, EmitType::End, callback)
-                    .map_err(|e| Error::from_pyerr(py, e));
-            }
+    /// Evaluate the expression `expr` in the global scope and emit its value
+    /// as [`run`](Self::run) emits a call's result.
+    pub fn eval_expr(
+        &self,
+        expr: &str,
+        callback: impl FnMut(crate::wasm::isola::script::host::EmitType, &[u8]),
+    ) -> Result<()> {
+        Python::attach(|py| {
+            let expr = std::ffi::CString::new(expr).map_err(|e| {
+                Error::from_pyerr(
+                    py,
+                    PyValueError::new_err(format!("expression contains NUL byte: {e}")),
+                )
+            })?;
+            let obj = py
+                .eval(
+                    &expr,
+                    Some(
+                        self.locals
+                            .cast_bound(py)
+                            .map_err(|e| Error::from_pyerr(py, e))?,
+                    ),
+                    None,
+                )
+                .map_err(|e| Error::from_pyerr(py, e))?;
+            Self::emit_output(py, obj, callback)
+        })
+    }
 
-            if let Ok(iter) = obj.try_iter() {
-                for el in iter {
-                    python_to_cbor_emit(
-                        el.map_err(|e| Error::from_pyerr(py, e))?,
-                        EmitType::PartialResult,
-                        &mut callback,
-                    )
-                    .map_err(|e| Error::from_pyerr(py, e))?;
-                }
+    /// Emit `obj` as the final value, awaiting it first if it is awaitable
+    /// and streaming its items if it is an iterator.
+    fn emit_output(
+        py: Python<'_>,
+        obj: Bound<'_, PyAny>,
+        mut callback: impl FnMut(crate::wasm::isola::script::host::EmitType, &[u8]),
+    ) -> Result<()> {
+        let obj = if obj.hasattr("__await__").unwrap_or_default()
+            || obj.hasattr("__aiter__").unwrap_or_default()
+        {
+            static ASYNC_RUN: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+            ASYNC_RUN
+                .import(py, "sandbox.asyncio", "run")
+                .expect("failed to import sandbox.asyncio")
+                .call1((obj,))
+                .map_err(|e| Error::from_pyerr(py, e))?
+        } else {
+            obj
+        };
+
+        if Self::is_serializable(&obj) {
+            return python_to_cbor_emit(obj, EmitType::End, callback)
+                .map_err(|e| Error::from_pyerr(py, e));
+        }
 
-                callback(EmitType::End, &[]);
-                return Ok(());
+        if let Ok(iter) = obj.try_iter() {
+            for el in iter {
+                python_to_cbor_emit(
+                    el.map_err(|e| Error::from_pyerr(py, e))?,
+                    EmitType::PartialResult,
+                    &mut callback,
+                )
+                .map_err(|e| Error::from_pyerr(py, e))?;
             }
 
-            Err(Error::UnexpectedError(
-                "Return type is not serializable or iterable",
-            ))
-        })
+            callback(EmitType::End, &[]);
+            return Ok(());
+        }
+
+        Err(Error::UnexpectedError(
+            "Return type is not serializable or iterable",
+        ))
     }
 }
 
@@ -353,5 +447,13 @@ def gen():
         for (i, vv) in v.iter().enumerate() {
             assert_eq!(*vv, minicbor_serde::to_vec(i).unwrap());
         }
+
+        let mut x = vec![];
+        s.eval_expr("hello(i) + '!'", |_emit_type, data| {
+            x.push(data.to_owned());
+        })
+        .unwrap();
+        assert_eq!(x[0], minicbor_serde::to_vec("hello 44!").unwrap());
+        assert!(s.eval_expr("i = 2", |_, _| {}).is_err());
     }
 }
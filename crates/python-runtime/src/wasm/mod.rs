@@ -4,14 +4,19 @@ mod http;
 mod logging;
 mod serde;
 
-use std::cell::RefCell;
+use std::{borrow::Cow, cell::RefCell};
 
 pub use isola_runtime::{exports, isola, wasi};
 use pyo3::{append_to_inittab, prelude::*, sync::PyOnceLock};
 
-use self::{exports::isola::script::runtime, isola::script::host};
+use self::{
+    exports::isola::script::{
+        batch, coverage, exceptions, expressions, modules, packages, preludes, preopens, runtime,
+    },
+    isola::script::host,
+};
 use crate::{
-    error::Error,
+    error::{Error, take_last_exception},
     script::{InputValue, Scope},
     serde::cbor_to_python,
     wasm::future::PyPollable,
@@ -112,33 +117,47 @@ isola_runtime::export!(Global with_types_in isola_runtime);
 
 pub struct Global;
 
-impl runtime::Guest for Global {
-    fn initialize(preinit: bool, prelude: Option<String>) {
-        GLOBAL_SCOPE.with(|scope| {
-            let mut scope = scope.borrow_mut();
-            if scope.is_none() {
-                use http::http_module;
-                use logging::logging_module;
-                use serde::serde_module;
-
-                append_to_inittab!(http_module);
-                append_to_inittab!(logging_module);
-                append_to_inittab!(sys_module);
-                append_to_inittab!(serde_module);
-
-                let v = Scope::new();
-                if let Some(prelude) = prelude {
-                    v.load_script(&prelude).unwrap();
-                    v.flush();
-                }
-                isola_runtime::pending::clear();
-                scope.replace(v);
+/// Set up the interpreter and run `preludes` in order, unless an earlier
+/// call already did.
+fn initialize(preinit: bool, preludes: &[String]) -> Result<(), preludes::PreludeError> {
+    GLOBAL_SCOPE.with(|scope| {
+        let mut scope = scope.borrow_mut();
+        if scope.is_none() {
+            use http::http_module;
+            use logging::logging_module;
+            use serde::serde_module;
+
+            append_to_inittab!(http_module);
+            append_to_inittab!(logging_module);
+            append_to_inittab!(sys_module);
+            append_to_inittab!(serde_module);
+
+            let v = Scope::new();
+            for (index, prelude) in (0..).zip(preludes) {
+                let result = v.load_script(prelude);
+                v.flush();
+                result.map_err(|e| preludes::PreludeError {
+                    index,
+                    error: e.into(),
+                })?;
             }
-        });
+            isola_runtime::pending::clear();
+            scope.replace(v);
+        }
+        Ok::<_, preludes::PreludeError>(())
+    })?;
+
+    // https://github.com/bytecodealliance/componentize-py/blob/72348e0ebd74ef1027c52528409a289765ed5c4c/runtime/src/lib.rs#L377
+    if preinit {
+        isola_runtime::lifecycle::reset_preinitialized_state();
+    }
+    Ok(())
+}
 
-        // https://github.com/bytecodealliance/componentize-py/blob/72348e0ebd74ef1027c52528409a289765ed5c4c/runtime/src/lib.rs#L377
-        if preinit {
-            isola_runtime::lifecycle::reset_preinitialized_state();
+impl runtime::Guest for Global {
+    fn initialize(preinit: bool, prelude: Option<String>) {
+        if let Err(e) = initialize(preinit, prelude.as_slice()) {
+            panic!("prelude failed: {}", e.error.message);
         }
     }
 
@@ -147,6 +166,7 @@ impl runtime::Guest for Global {
         reason = "WIT async export requires an async trait method"
     )]
     async fn eval_script(script: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
         GLOBAL_SCOPE.with_borrow(|sandbox| {
             sandbox.as_ref().map_or_else(
                 || Err(Error::UnexpectedError("Sandbox not initialized").into()),
@@ -167,6 +187,7 @@ impl runtime::Guest for Global {
         reason = "WIT async export requires an async trait method"
     )]
     async fn eval_file(path: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
         GLOBAL_SCOPE.with_borrow(|sandbox| {
             if let Some(sandbox) = sandbox.as_ref() {
                 let script = std::fs::read_to_string(std::path::Path::new(&path))
@@ -188,38 +209,209 @@ impl runtime::Guest for Global {
         reason = "WIT async export requires an async trait method"
     )]
     async fn call_func(func: String, args: Vec<runtime::Argument>) -> Result<(), runtime::Error> {
+        call(&func, args, false).map(drop)
+    }
+}
+
+impl exceptions::Guest for Global {
+    fn last_exception() -> Option<runtime::GuestException> {
+        take_last_exception()
+    }
+}
+
+impl preludes::Guest for Global {
+    fn initialize(preinit: bool, preludes: Vec<String>) -> Result<(), preludes::PreludeError> {
+        initialize(preinit, &preludes)
+    }
+}
+
+impl preopens::Guest for Global {
+    fn reload_preopens() {
+        isola_runtime::lifecycle::reload_preopens();
+    }
+}
+
+impl expressions::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn eval_expr(expr: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
         GLOBAL_SCOPE.with_borrow(|sandbox| {
             sandbox.as_ref().map_or_else(
                 || Err(Error::UnexpectedError("Sandbox not initialized").into()),
                 |sandbox| {
-                    let mut positional = vec![];
-                    let mut named = vec![];
-                    for arg in args {
-                        let runtime::Argument { name, value } = arg;
-                        let value = match value {
-                            host::Value::Cbor(s) => InputValue::Cbor(s.into()),
-                            host::Value::CborIterator(e) => InputValue::Iter(ArgIter { iter: e }),
-                        };
-                        if let Some(name) = name {
-                            named.push((name.into(), value));
-                        } else {
-                            positional.push(value);
-                        }
-                    }
-                    let ret = sandbox
-                        .run(&func, positional, named, |emit_type, data| {
+                    let result = sandbox
+                        .eval_expr(&expr, |emit_type, data| {
                             host::blocking_emit(emit_type, data);
                         })
                         .map_err(Into::<runtime::Error>::into);
                     sandbox.flush();
                     isola_runtime::pending::clear();
-                    ret
+                    result
                 },
             )
         })
     }
 }
 
+impl modules::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn load_module(name: String, code: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
+        GLOBAL_SCOPE.with_borrow(|sandbox| {
+            sandbox.as_ref().map_or_else(
+                || Err(Error::UnexpectedError("Sandbox not initialized").into()),
+                |sandbox| {
+                    let result = sandbox
+                        .load_module(&name, &code)
+                        .map_err(Into::<runtime::Error>::into);
+                    sandbox.flush();
+                    isola_runtime::pending::clear();
+                    result
+                },
+            )
+        })
+    }
+}
+
+impl packages::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn eval_package(path: String) -> Result<(), runtime::Error> {
+        isola_runtime::lifecycle::enter_initial_cwd();
+        GLOBAL_SCOPE.with_borrow(|sandbox| {
+            sandbox.as_ref().map_or_else(
+                || Err(Error::UnexpectedError("Sandbox not initialized").into()),
+                |sandbox| {
+                    let result = sandbox
+                        .load_package(&path)
+                        .map_err(Into::<runtime::Error>::into);
+                    sandbox.flush();
+                    isola_runtime::pending::clear();
+                    result
+                },
+            )
+        })
+    }
+}
+
+impl coverage::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn call_func(
+        func: String,
+        args: Vec<runtime::Argument>,
+    ) -> Result<Vec<coverage::FileCoverage>, runtime::Error> {
+        call(&func, args, true).map(Option::unwrap_or_default)
+    }
+}
+
+impl batch::Guest for Global {
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "WIT async export requires an async trait method"
+    )]
+    async fn call_batch(calls: Vec<batch::BatchCall>) -> Vec<Result<(), batch::CallError>> {
+        isola_runtime::lifecycle::enter_initial_cwd();
+        GLOBAL_SCOPE.with_borrow(|sandbox| {
+            calls
+                .into_iter()
+                .map(|call| {
+                    let result = sandbox.as_ref().map_or_else(
+                        || Err(Error::UnexpectedError("Sandbox not initialized")),
+                        |sandbox| {
+                            let (positional, named) = split_args(call.args);
+                            let result =
+                                sandbox.run(&call.func, positional, named, |emit_type, data| {
+                                    host::blocking_emit(emit_type, data);
+                                });
+                            sandbox.flush();
+                            isola_runtime::pending::clear();
+                            result
+                        },
+                    );
+                    // Successful calls end with their return value; close
+                    // failed ones too so the host can tell their output apart.
+                    if result.is_err() {
+                        host::blocking_emit(host::EmitType::End, &[]);
+                    }
+                    result.map_err(|e| {
+                        let (error, exception) = e.into_parts();
+                        batch::CallError { error, exception }
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+/// Call `func`, reporting the lines it ran when `record_coverage` is set.
+fn call(
+    func: &str,
+    args: Vec<runtime::Argument>,
+    record_coverage: bool,
+) -> Result<Option<Vec<coverage::FileCoverage>>, runtime::Error> {
+    isola_runtime::lifecycle::enter_initial_cwd();
+    GLOBAL_SCOPE.with_borrow(|sandbox| {
+        sandbox.as_ref().map_or_else(
+            || Err(Error::UnexpectedError("Sandbox not initialized").into()),
+            |sandbox| {
+                let (positional, named) = split_args(args);
+                if record_coverage {
+                    Scope::start_coverage()?;
+                }
+                let ret = sandbox.run(func, positional, named, |emit_type, data| {
+                    host::blocking_emit(emit_type, data);
+                });
+                let report = if record_coverage {
+                    Scope::stop_coverage().map(Some)
+                } else {
+                    Ok(None)
+                };
+                sandbox.flush();
+                isola_runtime::pending::clear();
+                ret?;
+                Ok(report?.map(|files| {
+                    files
+                        .into_iter()
+                        .map(|(path, lines)| coverage::FileCoverage { path, lines })
+                        .collect()
+                }))
+            },
+        )
+    })
+}
+
+type NamedArgs<'a> = Vec<(Cow<'a, str>, InputValue<'a>)>;
+
+/// Split call arguments into positional and named values.
+fn split_args<'a>(args: Vec<runtime::Argument>) -> (Vec<InputValue<'a>>, NamedArgs<'a>) {
+    let mut positional = vec![];
+    let mut named = vec![];
+    for arg in args {
+        let runtime::Argument { name, value } = arg;
+        let value = match value {
+            host::Value::Cbor(s) => InputValue::Cbor(s.into()),
+            host::Value::CborIterator(e) => InputValue::Iter(ArgIter { iter: e }),
+        };
+        if let Some(name) = name {
+            named.push((name.into(), value));
+        } else {
+            positional.push(value);
+        }
+    }
+    (positional, named)
+}
+
 #[pyclass]
 pub struct ArgIter {
     iter: host::ValueIterator,
@@ -15,7 +15,7 @@ use isola::{
         BoxError, Host, HttpBodyStream, HttpRequest, HttpResponse, LogLevel, OutputEvent,
         OutputTarget,
     },
-    sandbox::{Arg, DirPerms, FilePerms, Sandbox, SandboxOptions, SandboxTemplate},
+    sandbox::{Arg, DirPerms, FilePerms, Sandbox, SandboxOptions, SandboxTemplate, SharedSandbox},
     value::Value,
 };
 use parking_lot::Mutex;
@@ -470,13 +470,6 @@ struct PyCallback {
     callback: Py<PyAny>,
 }
 
-// SAFETY: callback invocation always reacquires the GIL before touching Python
-// objects.
-unsafe impl Send for PyCallback {}
-// SAFETY: callback invocation always reacquires the GIL before touching Python
-// objects.
-unsafe impl Sync for PyCallback {}
-
 impl PyCallback {
     fn emit(&self, event: CallbackEvent, data: Option<&str>) {
         Python::attach(|py| {
@@ -512,21 +505,11 @@ struct PyHttpHandler {
     event_loop: Py<PyAny>,
 }
 
-// SAFETY: Python objects are only touched while holding the GIL.
-unsafe impl Send for PyHttpHandler {}
-// SAFETY: Python objects are only touched while holding the GIL.
-unsafe impl Sync for PyHttpHandler {}
-
 struct PyHostcallHandler {
     callback: Py<PyAny>,
     event_loop: Py<PyAny>,
 }
 
-// SAFETY: Python objects are only touched while holding the GIL.
-unsafe impl Send for PyHostcallHandler {}
-// SAFETY: Python objects are only touched while holding the GIL.
-unsafe impl Sync for PyHostcallHandler {}
-
 enum HttpResponseBody {
     Empty,
     Buffered(Bytes),
@@ -906,55 +889,21 @@ enum SandboxInner {
         hostcall_handler: Option<Arc<PyHostcallHandler>>,
     },
     Running {
-        sandbox: Option<Sandbox<Env>>,
+        sandbox: SharedSandbox<Env>,
         callback: Option<Arc<PyCallback>>,
     },
 }
 
-struct RunningSandboxLease {
-    inner: Arc<Mutex<SandboxInner>>,
-    sandbox: Option<Sandbox<Env>>,
-}
-
-impl RunningSandboxLease {
-    const fn new(inner: Arc<Mutex<SandboxInner>>, sandbox: Sandbox<Env>) -> Self {
-        Self {
-            inner,
-            sandbox: Some(sandbox),
-        }
-    }
-
-    const fn sandbox_mut(&mut self) -> &mut Sandbox<Env> {
-        self.sandbox
-            .as_mut()
-            .expect("running sandbox lease must contain sandbox")
-    }
-}
-
-impl Drop for RunningSandboxLease {
-    fn drop(&mut self) {
-        let Some(sandbox) = self.sandbox.take() else {
-            return;
-        };
-
-        let mut guard = self.inner.lock();
-        if let SandboxInner::Running { sandbox: slot, .. } = &mut *guard
-            && slot.is_none()
-        {
-            *slot = Some(sandbox);
-        }
-    }
-}
-
 #[pyclass(name = "_ContextCore")]
 struct PyContext {
-    inner: Option<Arc<ContextInner>>,
+    inner: Mutex<Option<Arc<ContextInner>>>,
 }
 
 impl PyContext {
-    fn inner_ref(&self) -> Result<&Arc<ContextInner>> {
+    fn inner_ref(&self) -> Result<Arc<ContextInner>> {
         self.inner
-            .as_ref()
+            .lock()
+            .clone()
             .ok_or_else(|| invalid_argument("context is closed"))
     }
 }
@@ -965,13 +914,13 @@ impl PyContext {
     fn new() -> Self {
         let inner = ContextInner::new();
         Self {
-            inner: Some(Arc::new(inner)),
+            inner: Mutex::new(Some(Arc::new(inner))),
         }
     }
 
     fn configure(&self, config: &Bound<'_, PyAny>) -> PyResult<()> {
         let patch: ContextConfigPatch = crate::serde::py_to_serde(config).map_err(to_py_err)?;
-        let inner = Arc::clone(self.inner_ref().map_err(to_py_err)?);
+        let inner = self.inner_ref().map_err(to_py_err)?;
         let mut state = inner.state.lock();
         if state.template.is_some() {
             return Err(to_py_err(invalid_argument(
@@ -988,7 +937,7 @@ impl PyContext {
         runtime_path: &str,
         runtime_name: &str,
     ) -> PyResult<Bound<'py, PyAny>> {
-        let inner = Arc::clone(self.inner_ref().map_err(to_py_err)?);
+        let inner = self.inner_ref().map_err(to_py_err)?;
         let runtime_path = PathBuf::from(runtime_path);
         let runtime = RuntimeFlavor::parse(runtime_name).map_err(to_py_err)?;
         pyo3_async_runtimes::tokio::future_into_py(py, async move {
@@ -1001,7 +950,7 @@ impl PyContext {
     }
 
     fn instantiate<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
-        let inner = Arc::clone(self.inner_ref().map_err(to_py_err)?);
+        let inner = self.inner_ref().map_err(to_py_err)?;
         pyo3_async_runtimes::tokio::future_into_py(py, async move {
             let has_template = inner.has_template();
             if !has_template {
@@ -1027,8 +976,8 @@ impl PyContext {
         })
     }
 
-    fn close(&mut self) {
-        self.inner = None;
+    fn close(&self) {
+        self.inner.lock().take();
     }
 }
 
@@ -1206,7 +1155,7 @@ impl PySandbox {
                 Ok(sandbox) => {
                     let mut guard = inner.lock();
                     *guard = SandboxInner::Running {
-                        sandbox: Some(sandbox),
+                        sandbox: SharedSandbox::new(sandbox),
                         callback,
                     };
                     drop(guard);
@@ -1233,25 +1182,20 @@ impl PySandbox {
         let inner = Arc::clone(&self.inner);
 
         pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            let (mut lease, callback) = {
-                let mut guard = inner.lock();
-                match &mut *guard {
-                    SandboxInner::Running { sandbox, callback } => {
-                        let sandbox = sandbox
-                            .take()
-                            .ok_or_else(|| to_py_err(invalid_argument("sandbox is busy")))?;
-                        (
-                            RunningSandboxLease::new(Arc::clone(&inner), sandbox),
-                            callback.clone(),
-                        )
-                    }
-                    _ => return Err(to_py_err(invalid_argument("sandbox is not running"))),
-                }
+            let (shared, callback) = match &*inner.lock() {
+                SandboxInner::Running { sandbox, callback } => (sandbox.clone(), callback.clone()),
+                _ => return Err(to_py_err(invalid_argument("sandbox is not running"))),
             };
+            // Concurrent calls wait their turn instead of failing.
+            let mut sandbox = shared
+                .lock()
+                .await
+                .map_err(|err| to_py_err(Error::Internal(err.to_string())))?;
 
             let collector = OutputCollector::new(callback);
             let sink = collector.target();
-            let outcome = lease.sandbox_mut().eval_script(&script, sink).await;
+            let outcome = sandbox.eval_script(&script, sink).await;
+            drop(sandbox);
             if let Err(err) = outcome {
                 let message = format!("Script loading failed: {err}");
                 collector.emit_error_message(&message);
@@ -1272,21 +1216,14 @@ impl PySandbox {
         let inner = Arc::clone(&self.inner);
 
         pyo3_async_runtimes::tokio::future_into_py(py, async move {
-            let (mut lease, callback) = {
-                let mut guard = inner.lock();
-                match &mut *guard {
-                    SandboxInner::Running { sandbox, callback } => {
-                        let sandbox = sandbox
-                            .take()
-                            .ok_or_else(|| to_py_err(invalid_argument("sandbox is busy")))?;
-                        (
-                            RunningSandboxLease::new(Arc::clone(&inner), sandbox),
-                            callback.clone(),
-                        )
-                    }
-                    _ => return Err(to_py_err(invalid_argument("sandbox is not running"))),
-                }
+            let (shared, callback) = match &*inner.lock() {
+                SandboxInner::Running { sandbox, callback } => (sandbox.clone(), callback.clone()),
+                _ => return Err(to_py_err(invalid_argument("sandbox is not running"))),
             };
+            let mut sandbox = shared
+                .lock()
+                .await
+                .map_err(|err| to_py_err(Error::Internal(err.to_string())))?;
 
             let parsed_args = match Python::attach(|py| parse_run_args(py, args)) {
                 Ok(parsed_args) => parsed_args,
@@ -1314,10 +1251,8 @@ impl PySandbox {
                 .collect::<Result<Vec<_>>>()
                 .map_err(to_py_err)?;
 
-            let outcome = lease
-                .sandbox_mut()
-                .call_with_sink(&func, isola_args, sink)
-                .await;
+            let outcome = sandbox.call_with_sink(&func, isola_args, sink).await;
+            drop(sandbox);
             if let Err(err) = outcome {
                 let message = format!("Sandbox execution failed: {err}");
                 collector.emit_error_message(&message);
@@ -1509,7 +1444,7 @@ impl Host for Env {
     }
 }
 
-#[pymodule]
+#[pymodule(gil_used = false)]
 fn _isola(py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
     module.add("IsolaError", py.get_type::<IsolaError>())?;
     module.add(
@@ -2,8 +2,8 @@ use isola::value::Value;
 use pyo3::{
     Bound, IntoPyObject, PyAny, PyResult, PyTypeInfo, Python,
     types::{
-        PyAnyMethods, PyBytes, PyDict, PyFloat, PyInt, PyList, PyNone, PySet, PyStringMethods,
-        PyTuple, PyTypeMethods,
+        PyAnyMethods, PyByteArray, PyByteArrayMethods, PyBytes, PyDict, PyFloat, PyInt, PyList,
+        PyNone, PySet, PyStringMethods, PyTuple, PyTypeMethods,
     },
 };
 use serde::{
@@ -123,6 +123,8 @@ impl Serialize for PyValue<'_> {
                     map.end()
                 } else if let Ok(s) = o.extract::<&[u8]>() {
                     serializer.serialize_bytes(s)
+                } else if let Ok(b) = o.cast_exact::<PyByteArray>() {
+                    serializer.serialize_bytes(&b.to_vec())
                 } else if let Ok(list) = o.cast_exact::<PyList>() {
                     let len = list.len().ok();
                     let mut seq = serializer.serialize_seq(len)?;
@@ -165,15 +167,18 @@ impl Serialize for PyValue<'_> {
                         serializer.serialize_i32(i)
                     } else if let Ok(i) = o.extract::<i64>() {
                         serializer.serialize_i64(i)
+                    } else if let Ok(i) = o.extract::<i128>() {
+                        // Encoded as a CBOR bignum when outside the 64-bit range.
+                        serializer.serialize_i128(i)
                     } else {
-                        o.extract::<u64>().map_or_else(
+                        o.extract::<u128>().map_or_else(
                             |_| {
                                 Err(serde::ser::Error::custom(format!(
-                                    "object of type '{}' does not fit into an integer",
+                                    "object of type '{}' does not fit into a 128-bit integer",
                                     o.get_type()
                                 )))
                             },
-                            |u| serializer.serialize_u64(u),
+                            |u| serializer.serialize_u128(u),
                         )
                     }
                 } else {
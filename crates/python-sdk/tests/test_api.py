@@ -3,6 +3,8 @@ from __future__ import annotations
 import asyncio
 import io
 import os
+import sys
+import sysconfig
 import tarfile
 from pathlib import Path
 from types import SimpleNamespace
@@ -35,6 +37,13 @@ def test_json_stream_from_iterable_roundtrip() -> None:
     assert stream_arg.producer_task is None
 
 
+def test_import_keeps_gil_disabled_on_free_threaded_python() -> None:
+    is_gil_enabled = getattr(sys, "_is_gil_enabled", None)
+    if is_gil_enabled is None or sysconfig.get_config_var("Py_GIL_DISABLED") != 1:
+        pytest.skip("requires a free-threaded interpreter")
+    assert not is_gil_enabled()
+
+
 def test_strip_first_path_component_flattens_bundle_root() -> None:
     strip_first_path_component = runtime_module._strip_first_path_component  # ruff:ignore[private-member-access]
 
@@ -585,6 +594,38 @@ async def test_two_sandboxes_can_run_concurrently() -> None:
         assert result_b == "sandbox-b"
 
 
+@pytest.mark.asyncio
+async def test_stream_items_keep_bytes_and_large_ints() -> None:
+    runtime_dir, lib_dir = _resolve_runtime_paths()
+    template = await isola.build_template(
+        "python",
+        runtime_path=runtime_dir,
+        runtime_lib_dir=lib_dir,
+    )
+
+    async with template.create() as sandbox:
+        await sandbox.load_script(
+            "def describe(values):\n"
+            "\treturn [[type(v).__name__, str(v)] for v in values]\n"
+        )
+        stream = isola.StreamArg.from_iterable(
+            [b"\x00\xff", bytearray(b"ab"), 2**100, -(2**70)]
+        )
+        result = await sandbox.run("describe", stream)
+        assert result == [
+            ["bytes", "b'\\x00\\xff'"],
+            ["bytes", "b'ab'"],
+            ["int", str(2**100)],
+            ["int", str(-(2**70))],
+        ]
+
+
+def test_stream_push_rejects_ints_beyond_128_bits() -> None:
+    core = isola._isola._StreamCore(1)  # ruff:ignore[private-member-access]
+    with pytest.raises(isola.InvalidArgumentError):
+        core.push(2**200)
+
+
 @pytest.mark.asyncio
 async def test_template_create_configures_http_handler() -> None:
     class _FakeCore:
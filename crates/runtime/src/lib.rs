@@ -1,5 +1,6 @@
 #![expect(
     clippy::same_length_and_capacity,
+    clippy::collection_is_never_read,
     reason = "generated by wit_bindgen::generate! macro"
 )]
 
@@ -14,7 +15,7 @@ pub use time::{Deadline, DeadlineOverflow, monotonic};
 pub use wit_bindgen::block_on;
 
 wit_bindgen::generate!({
-    world: "sandbox",
+    world: "sandbox-runtime",
     path: "../isola/wit",
     generate_all,
     pub_export_macro: true,
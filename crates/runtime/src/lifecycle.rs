@@ -1,5 +1,22 @@
+use std::cell::Cell;
+
+thread_local! {
+    static INITIAL_CWD_ENTERED: Cell<bool> = const { Cell::new(false) };
+}
+
 /// Reset process state that must not be retained in a preinitialized runtime.
 pub fn reset_preinitialized_state() {
+    reload_preopens();
+    crate::pending::clear();
+    crate::time::reset_monotonic();
+    INITIAL_CWD_ENTERED.set(false);
+}
+
+/// Forget the WASI descriptors cached by the adapter and libc so the
+/// preopened directories are fetched from the host again on next use.
+///
+/// Descriptors opened before the reload are no longer valid afterwards.
+pub fn reload_preopens() {
     #[link(wasm_import_module = "wasi_snapshot_preview1")]
     unsafe extern "C" {
         #[cfg_attr(target_arch = "wasm32", link_name = "reset_adapter_state")]
@@ -16,6 +33,18 @@ pub fn reset_preinitialized_state() {
         reset_adapter_state();
         wasilibc_reset_preopens();
     }
-    crate::pending::clear();
-    crate::time::reset_monotonic();
+}
+
+/// Change into the working directory configured by the host.
+///
+/// The directory is only known once the runtime is instantiated, so this runs
+/// on the first script or function call rather than during preinitialization.
+pub fn enter_initial_cwd() {
+    if INITIAL_CWD_ENTERED.replace(true) {
+        return;
+    }
+    if let Some(cwd) = crate::wasi::cli::environment::get_initial_cwd() {
+        // The host has already checked that the directory exists.
+        let _ = std::env::set_current_dir(cwd);
+    }
 }
@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     collections::hash_map::DefaultHasher,
     env,
     hash::Hasher,
@@ -7,6 +8,7 @@ use std::{
 };
 
 use anyhow::{Context, Result};
+use wasm_encoder::{ComponentSection, CustomSection};
 use xshell::{Shell, cmd};
 
 use crate::async_shim::link_library;
@@ -14,6 +16,10 @@ use crate::async_shim::link_library;
 mod async_shim;
 
 const TARGET: &str = "wasm32-wasip1";
+const PYTHON_VERSION: &str = "3.14";
+/// Host interface version the bundles implement. Keep in sync with
+/// `isola::sandbox::RUNTIME_ABI_VERSION`.
+const RUNTIME_ABI_VERSION: u32 = 2;
 const COMPONENT_FINGERPRINT_VERSION: &[u8] = b"isola-component-v1";
 const COMPONENT_BUILD_INPUTS: &[(&str, &[u8])] = &[
     ("crates/xtask/src/main.rs", include_bytes!("main.rs")),
@@ -150,13 +156,18 @@ fn build_python(sh: &Shell) -> Result<()> {
         sh,
         "cargo build --locked -Z build-std=std,panic_abort --release --target {TARGET} -p isola-python-runtime"
     )
-    .env("PYO3_CROSS_PYTHON_VERSION", "3.14")
+    .env("PYO3_CROSS_PYTHON_VERSION", PYTHON_VERSION)
     .env("RUSTFLAGS", &rustflags)
     .run()?;
 
     let runtime = PathBuf::from(format!("target/{TARGET}/release/isola_python_runtime.wasm"));
     let libraries = python_libraries(Path::new(&wasi_deps_dir), &runtime)?;
-    write_component_if_changed(libraries, Path::new("target/python.wasm"), 8_388_608)?;
+    write_component_if_changed(
+        libraries,
+        Path::new("target/python.wasm"),
+        stack_size("ISOLA_PYTHON_STACK_SIZE", 8_388_608)?,
+        &runtime_metadata("python", &format!("cpython {PYTHON_VERSION}")),
+    )?;
 
     Ok(())
 }
@@ -174,11 +185,41 @@ fn build_js(sh: &Shell) -> Result<()> {
 
     let runtime = PathBuf::from(format!("target/{TARGET}/release/isola_js_runtime.wasm"));
     let libraries = js_libraries(Path::new(&wasi_deps_dir), &runtime);
-    write_component_if_changed(libraries, Path::new("target/js.wasm"), 2_097_152)?;
+    write_component_if_changed(
+        libraries,
+        Path::new("target/js.wasm"),
+        stack_size("ISOLA_JS_STACK_SIZE", 2_097_152)?,
+        &runtime_metadata("js", "quickjs"),
+    )?;
 
     Ok(())
 }
 
+/// Custom section describing a runtime bundle, read by
+/// `SandboxTemplate::metadata`.
+fn runtime_metadata(language: &str, build_info: &str) -> Vec<u8> {
+    let data = format!(
+        "language={language}\nversion={}\nabi_version={RUNTIME_ABI_VERSION}\nbuild_info={build_info}\n",
+        env!("CARGO_PKG_VERSION"),
+    );
+    let mut section = Vec::new();
+    CustomSection {
+        name: Cow::Borrowed("isola:runtime-metadata"),
+        data: Cow::Owned(data.into_bytes()),
+    }
+    .append_to_component(&mut section);
+    section
+}
+
+/// Guest shadow stack size in bytes, overridable through `var`.
+fn stack_size(var: &str, default: u32) -> Result<u32> {
+    env::var(var).map_or(Ok(default), |value| {
+        value
+            .parse()
+            .with_context(|| format!("{var} must be a byte count, got '{value}'"))
+    })
+}
+
 fn python_libraries(wasi_deps_dir: &Path, runtime: &Path) -> Result<Vec<ComponentLibrary>> {
     let lib_dir = wasi_deps_dir.join("lib");
     let mut libraries = vec![
@@ -380,6 +421,7 @@ fn write_component_if_changed(
     libraries: Vec<ComponentLibrary>,
     output: &Path,
     stack_size: u32,
+    metadata: &[u8],
 ) -> Result<()> {
     let metadata_fingerprint = component_metadata_fingerprint(&libraries, stack_size)?;
     let fingerprint_path = output.with_extension("wasm.fingerprint");
@@ -419,12 +461,13 @@ fn write_component_if_changed(
             library.async_shim_name,
         )?;
     }
-    let component = linker
+    let mut component = linker
         .adapter(
             wasi_preview1_component_adapter_provider::WASI_SNAPSHOT_PREVIEW1_ADAPTER_NAME,
             wasi_preview1_component_adapter_provider::WASI_SNAPSHOT_PREVIEW1_REACTOR_ADAPTER,
         )?
         .encode()?;
+    component.extend_from_slice(metadata);
 
     std::fs::write(output, component)
         .with_context(|| format!("failed to write component {}", output.display()))?;